@@ -5,6 +5,18 @@ KERNEL_BIN := $(KERNEL_ELF).bin
 KERNEL_QEMU := ../bin/kernel-laqemu
 
 BOARD := laqemu
+
+# GDBSTUB=1 时启用内核 GDB 调试桩，并在命令行中加入 gdbstub=wait，启动后在串口上等待 GDB 连接
+GDBSTUB ?=
+FEATURES := board_$(BOARD)
+ifeq ($(GDBSTUB), 1)
+    FEATURES += gdbstub
+endif
+# 内核命令行，LoongArch 上没有设备树，在编译时写入内核
+BOOTARGS ?=
+ifeq ($(GDBSTUB), 1)
+    override BOOTARGS += gdbstub=wait
+endif
 SBI ?=
 BOOTLOADER := ../bootloader/u-boot-with-spl.bin
 
@@ -25,7 +37,7 @@ $(KERNEL_BIN): kernel
 kernel: pre
 	@echo Platform: $(BOARD), SBI: $(SBI)
 	@cp src/hal/arch/loongarch/linker-$(BOARD).ld src/hal/arch/loongarch/linker.ld
-	@LOG=${LOG} cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD)"
+	@LOG=${LOG} BOOTARGS="${BOOTARGS}" cargo build --${MODE} --target $(TARGET) --features "$(FEATURES)"
 
 pre:
 	@rm .cargo/config.toml || true
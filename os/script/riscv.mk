@@ -4,9 +4,40 @@ KERNEL_ELF := target/riscv64gc-unknown-none-elf/$(MODE)/os
 KERNEL_BIN := $(KERNEL_ELF).bin
 KERNEL_QEMU := ../bin/kernel-rvqemu
 FS_IMG := ../fs-img/fs.img
+SWAP_IMG := ../fs-img/swap.img
 
 BOARD := rvqemu
 
+# GDBSTUB=1 时启用内核 GDB 调试桩，并在命令行中加入 gdbstub=wait，启动后在串口上等待 GDB 连接
+GDBSTUB ?=
+FEATURES := board_$(BOARD)
+ifeq ($(GDBSTUB), 1)
+    FEATURES += gdbstub
+endif
+# SWAP=1 时启用匿名页换出，交换区为额外挂载的 virtio 块设备 $(SWAP_IMG)（由 swap-img 创建）
+SWAP ?=
+SWAP_SIZE ?= 64M
+ifeq ($(SWAP), 1)
+    FEATURES += swap
+    SWAP_DRIVE := -drive file=$(SWAP_IMG),if=none,format=raw,id=x1 \
+	-device virtio-blk-device,drive=x1,bus=virtio-mmio-bus.1
+endif
+# NET=1 时挂载 virtio-net 网卡，后端为 QEMU 用户态网络
+NET ?=
+ifeq ($(NET), 1)
+    NET_DEVICE := -device virtio-net-device,netdev=net \
+	-netdev user,id=net
+endif
+
+# 内核命令行，如 BOOTARGS="init=/busybox loglevel=info selftest=heap,frame"
+BOOTARGS ?=
+ifeq ($(GDBSTUB), 1)
+    override BOOTARGS += gdbstub=wait
+endif
+ifneq ($(BOOTARGS),)
+    APPEND := -append "$(BOOTARGS)"
+endif
+
 # KERNEL ENTRY
 KERNEL_ENTRY_PA := 0x80200000
 
@@ -26,7 +57,7 @@ $(KERNEL_BIN): kernel
 kernel: pre fs-img
 	@echo Platform: $(BOARD), SBI: $(SBI)
 	@cp src/hal/arch/riscv/linker-$(BOARD).ld src/hal/arch/riscv/linker.ld
-	@LOG=${LOG} cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD)"
+	@LOG=${LOG} cargo build --${MODE} --target $(TARGET) --features "$(FEATURES)"
 
 pre:
 	@rm .cargo/config.toml || true
@@ -35,6 +66,11 @@ pre:
 fs-img: user
 	@./buildfs.sh
 
+swap-img:
+	@mkdir -p $(dir $(SWAP_IMG))
+	@truncate -s $(SWAP_SIZE) $(SWAP_IMG)
+	@mkswap $(SWAP_IMG)
+
 user:
 	@cd ../user && make build
 
@@ -42,12 +78,13 @@ run:
 	qemu-system-riscv64 \
 	-machine virt \
 	-kernel $(KERNEL_QEMU) \
+	$(APPEND) \
 	-m 128M \
 	-nographic \
 	-smp 2	\
 	-drive file=$(FS_IMG),if=none,format=raw,id=x0 \
-	-device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0
+	-device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0 \
+	$(SWAP_DRIVE) \
+	$(NET_DEVICE)
 
-#	-device virtio-net-device,netdev=net \
-#	-netdev user,id=net \
 #	-initrd initrd.img
\ No newline at end of file
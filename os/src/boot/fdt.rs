@@ -0,0 +1,220 @@
+//! # 扁平设备树（FDT）读取
+//!
+//! ## Overview
+//! 按 Devicetree Specification 的二进制格式读取引导程序传入的设备树：
+//! - `Fdt::from_addr` 校验头部，返回只读视图
+//! - `tokens` 按顺序遍历结构块中的节点开始 / 属性 / 节点结束
+//! - `property` 按路径查找单个属性，如 `/chosen` 的 `bootargs`
+//! - `reg_entries` / `cell` / `string_list_contains` 解码常见的属性值
+//!
+//! ## Assumptions
+//! - 设备树位于物理内存中，通过内核直接映射区读取（启动页表已映射）
+//! - 设备树所在的内存在读取完成前不会被分配出去，需要保留的内容由调用者复制
+//!
+//! ## Invariants
+//! - 所有读取都在 `totalsize` 范围内，损坏的设备树只会使遍历提前结束
+
+/// 头部魔数（大端）
+const FDT_MAGIC: u32 = 0xd00d_feed;
+/// 结构块的标记
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+
+/// 设备树的只读视图
+pub struct Fdt {
+    data: &'static [u8],
+    /// 结构块的起始偏移与长度
+    struct_off: usize,
+    struct_size: usize,
+    /// 字符串块的起始偏移
+    strings_off: usize,
+}
+
+/// 结构块中的一个标记
+pub enum Token {
+    /// 节点开始，带节点名（含单元地址，如 `memory@80000000`），根节点名为空
+    BeginNode(&'static str),
+    /// 当前节点的一个属性：属性名与原始值
+    Property(&'static str, &'static [u8]),
+    /// 节点结束
+    EndNode,
+}
+
+impl Fdt {
+    /// 从物理地址 `pa` 读取设备树，头部无效时返回 `None`
+    ///
+    /// # Safety
+    /// `pa` 为 0 或指向一个有效的设备树，且在返回值使用期间可经直接映射区读取
+    pub unsafe fn from_addr(pa: usize) -> Option<Self> {
+        if pa == 0 || pa % 4 != 0 {
+            return None;
+        }
+        let addr = crate::mm::phys_to_virt(pa);
+        let header = core::slice::from_raw_parts(addr as *const u8, 40);
+        if be32(header, 0)? != FDT_MAGIC {
+            return None;
+        }
+        let total_size = be32(header, 4)? as usize;
+        let data = core::slice::from_raw_parts(addr as *const u8, total_size);
+        let struct_off = be32(data, 8)? as usize;
+        let strings_off = be32(data, 12)? as usize;
+        let struct_size = be32(data, 36)? as usize;
+        if struct_off.checked_add(struct_size)? > total_size || strings_off > total_size {
+            return None;
+        }
+        Some(Self {
+            data,
+            struct_off,
+            struct_size,
+            strings_off,
+        })
+    }
+
+    /// 设备树占用的字节数（头部中的 `totalsize`）
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// 按顺序遍历结构块
+    pub fn tokens(&self) -> Tokens<'_> {
+        Tokens {
+            fdt: self,
+            offset: self.struct_off,
+        }
+    }
+
+    /// 查找路径为 `path` 的节点的属性 `name`
+    ///
+    /// 路径中的节点名可以省略单元地址，如 `/memory` 匹配 `memory@80000000`
+    pub fn property(&self, path: &str, name: &str) -> Option<&'static [u8]> {
+        let component = |i: usize| path.split('/').filter(|c| !c.is_empty()).nth(i);
+        let target_depth = path.split('/').filter(|c| !c.is_empty()).count() + 1;
+        // depth：当前所在节点的深度，根节点为 1；matched：沿路径已匹配的深度
+        let mut depth = 0;
+        let mut matched = 0;
+        for token in self.tokens() {
+            match token {
+                Token::BeginNode(node) => {
+                    depth += 1;
+                    let on_path =
+                        depth == 1 || component(depth - 2).is_some_and(|c| node_matches(node, c));
+                    if matched == depth - 1 && on_path {
+                        matched = depth;
+                    }
+                }
+                Token::EndNode => {
+                    if matched == depth {
+                        matched = matched.saturating_sub(1);
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                Token::Property(prop, value) => {
+                    if depth == target_depth && matched == depth && prop == name {
+                        return Some(value);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// 字符串块中偏移 `offset` 处以 NUL 结尾的字符串
+    fn string_at(&self, offset: usize) -> Option<&'static str> {
+        cstr(self.data.get(self.strings_off.checked_add(offset)?..)?)
+    }
+}
+
+/// `Fdt::tokens` 返回的迭代器
+pub struct Tokens<'a> {
+    fdt: &'a Fdt,
+    offset: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let data = self.fdt.data;
+        let end = self.fdt.struct_off + self.fdt.struct_size;
+        while self.offset + 4 <= end {
+            let tag = be32(data, self.offset)?;
+            self.offset += 4;
+            match tag {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(data.get(self.offset..end)?)?;
+                    self.offset = align4(self.offset + name.len() + 1);
+                    return Some(Token::BeginNode(name));
+                }
+                FDT_END_NODE => return Some(Token::EndNode),
+                FDT_PROP => {
+                    let len = be32(data, self.offset)? as usize;
+                    let name_off = be32(data, self.offset + 4)? as usize;
+                    let value_start = self.offset + 8;
+                    let value = data.get(value_start..value_start.checked_add(len)?)?;
+                    self.offset = align4(value_start + len);
+                    return Some(Token::Property(self.fdt.string_at(name_off)?, value));
+                }
+                FDT_NOP => continue,
+                // FDT_END 或无法识别的标记，结束遍历
+                _ => break,
+            }
+        }
+        self.offset = end;
+        None
+    }
+}
+
+/// 按父节点的 `#address-cells` / `#size-cells` 解码 `reg` 属性，得到 `(地址, 大小)` 列表
+pub fn reg_entries(
+    reg: &'static [u8],
+    address_cells: usize,
+    size_cells: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let entry_size = (address_cells + size_cells) * 4;
+    reg.chunks_exact(entry_size.max(4))
+        .filter_map(move |entry| {
+            let (addr, size) = entry.split_at(address_cells * 4);
+            Some((cell(addr)?, cell(size)?))
+        })
+}
+
+/// 字符串列表属性（如 `compatible`）是否包含 `s`
+pub fn string_list_contains(value: &[u8], s: &str) -> bool {
+    value.split(|&b| b == 0).any(|item| item == s.as_bytes())
+}
+
+/// 由至多两个大端 32 位单元组成的整数值，如 `#address-cells` 或 `timebase-frequency`，
+/// 超出 64 位时返回 `None`
+pub fn cell(value: &[u8]) -> Option<usize> {
+    if value.len() > 8 {
+        return None;
+    }
+    Some(
+        value
+            .chunks_exact(4)
+            .fold(0, |acc, cell| (acc << 32) | be32(cell, 0).unwrap() as usize),
+    )
+}
+
+/// 节点名 `node` 是否匹配路径分量 `component`：完全相同，或去掉单元地址后相同
+fn node_matches(node: &str, component: &str) -> bool {
+    node == component || node.split('@').next() == Some(component)
+}
+
+/// 读取偏移 `offset` 处的大端 32 位整数
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// 以 NUL 结尾的字符串，不含结尾的 NUL
+fn cstr(bytes: &'static [u8]) -> Option<&'static str> {
+    let len = bytes.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&bytes[..len]).ok()
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
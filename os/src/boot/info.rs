@@ -0,0 +1,350 @@
+//! # 平台信息
+//!
+//! ## Overview
+//! 启动时从设备树中读取平台信息，代替 `hal::platform` 中按板卡写死的常量：
+//! - `/memory` 节点：物理内存区域，决定页帧分配器与内核直接映射的范围
+//! - `/reserved-memory` 的子节点：固件等占用的内存，不交给页帧分配器
+//! - `/cpus` 节点：hart 数量与 `timebase-frequency`（定时器频率）
+//! - `compatible = "virtio,mmio"` 的节点：VirtIO 设备的寄存器区域与中断号
+//! - `compatible = "google,goldfish-rtc"` 的节点：实时时钟的寄存器区域
+//!
+//! 没有设备树时（LoongArch，或引导程序没有传入）使用静态表：
+//! `hal::MEMORY_END`、`hal::CLOCK_FREQ` / CPUCFG、`hal::MMIO`、`hal::VIRTIO_MMIO_SLOTS` 与 `hal::RTC_BASE`
+//!
+//! ## Design
+//! - 解析在初始化堆之前进行，结果保存在定长数组中，超出容量的条目被忽略
+//! - 每个节点的属性在节点结束时统一处理，`reg` 按父节点的 `#address-cells` / `#size-cells` 解码
+//! - `usable_memory` 从各内存区域中扣除保留区域，得到页帧分配器与直接映射使用的范围。保留区域包括：
+//!   内核镜像、设备树本身（`reserve`）、`/reserved-memory` 中的区域，以及 LoongArch 上从 `DISK_IMAGE_BASE`
+//!   到内存末尾的磁盘镜像；内存区域之间或保留区域之间重叠说明平台信息有误，直接 panic
+//!
+//! ## Assumptions
+//! - 总线节点的 `ranges` 为空或恒等映射，子节点的 `reg` 即为物理地址
+//! - 包含内核镜像的内存区域中，内核之前的部分属于固件（如 OpenSBI），即使没有列在 `/reserved-memory` 中也不使用
+//! - 直接映射区之外（`hal::DIRECT_MAP_SIZE`）的内存无法访问，被忽略
+//! - 设备树中的 VirtIO 设备包含根磁盘所在的 0 号槽位，由驱动自行跳过
+//!
+//! ## Invariants
+//! - `mmio_regions` 返回的区域互不重叠，可以逐个加入内核地址空间
+
+use super::fdt::{self, Fdt, Token};
+use crate::hal::{DIRECT_MAP_SIZE, MEMORY_END, MMIO, RTC_BASE, VIRTIO_MMIO_SLOTS};
+use crate::sync::SpinMutex;
+use alloc::vec::Vec;
+
+/// 记录的内存区域、保留区域与 VirtIO 设备的上限
+const MAX_MEMORY_REGIONS: usize = 8;
+const MAX_RESERVED_REGIONS: usize = 8;
+const MAX_VIRTIO_DEVICES: usize = 16;
+/// 节点嵌套深度的上限
+const MAX_DEPTH: usize = 8;
+
+/// 一个 VirtIO MMIO 设备
+#[derive(Clone, Copy)]
+struct VirtioDevice {
+    base: usize,
+    size: usize,
+    irq: usize,
+}
+
+/// 从设备树中读取的平台信息
+struct BootInfo {
+    /// 是否读取到了设备树
+    from_fdt: bool,
+    /// 物理内存区域 `(起始地址, 大小)`
+    memory: [(usize, usize); MAX_MEMORY_REGIONS],
+    memory_count: usize,
+    /// 保留区域 `(起始地址, 大小, 名称)`
+    reserved: [(usize, usize, &'static str); MAX_RESERVED_REGIONS],
+    reserved_count: usize,
+    /// hart 数量
+    harts: usize,
+    /// 定时器频率（Hz）
+    timebase_freq: Option<usize>,
+    virtio: [VirtioDevice; MAX_VIRTIO_DEVICES],
+    virtio_count: usize,
+    /// goldfish RTC 的寄存器区域 `(基址, 大小)`
+    rtc: Option<(usize, usize)>,
+}
+
+static BOOT_INFO: SpinMutex<BootInfo> = SpinMutex::new(BootInfo {
+    from_fdt: false,
+    memory: [(0, 0); MAX_MEMORY_REGIONS],
+    memory_count: 0,
+    reserved: [(0, 0, ""); MAX_RESERVED_REGIONS],
+    reserved_count: 0,
+    harts: 0,
+    timebase_freq: None,
+    virtio: [VirtioDevice {
+        base: 0,
+        size: 0,
+        irq: 0,
+    }; MAX_VIRTIO_DEVICES],
+    virtio_count: 0,
+    rtc: None,
+});
+
+/// 遍历过程中一个节点已读到的属性
+#[derive(Clone, Copy)]
+struct Node {
+    name: &'static str,
+    /// 本节点声明的、供子节点使用的单元数
+    address_cells: usize,
+    size_cells: usize,
+    device_type: &'static [u8],
+    compatible: &'static [u8],
+    reg: &'static [u8],
+    interrupts: Option<usize>,
+    timebase_freq: Option<usize>,
+    disabled: bool,
+}
+
+impl Node {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            // Devicetree Specification 规定的默认值
+            address_cells: 2,
+            size_cells: 1,
+            device_type: &[],
+            compatible: &[],
+            reg: &[],
+            interrupts: None,
+            timebase_freq: None,
+            disabled: false,
+        }
+    }
+}
+
+/// 从设备树中读取平台信息
+pub(super) fn parse(fdt: &Fdt) {
+    let mut info = BOOT_INFO.lock();
+    info.from_fdt = true;
+    let mut stack = [Node::new(""); MAX_DEPTH];
+    let mut depth = 0;
+    for token in fdt.tokens() {
+        match token {
+            Token::BeginNode(name) => {
+                if depth < MAX_DEPTH {
+                    stack[depth] = Node::new(name);
+                }
+                depth += 1;
+            }
+            Token::Property(name, value) => {
+                let Some(node) = depth.checked_sub(1).and_then(|i| stack.get_mut(i)) else {
+                    continue;
+                };
+                match name {
+                    "#address-cells" => node.address_cells = fdt::cell(value).unwrap_or(2),
+                    "#size-cells" => node.size_cells = fdt::cell(value).unwrap_or(1),
+                    "device_type" => node.device_type = value,
+                    "compatible" => node.compatible = value,
+                    "reg" => node.reg = value,
+                    "interrupts" => node.interrupts = value.get(..4).and_then(fdt::cell),
+                    "timebase-frequency" => node.timebase_freq = fdt::cell(value),
+                    "status" => node.disabled = value.starts_with(b"disabled"),
+                    _ => {}
+                }
+            }
+            Token::EndNode => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 || depth >= MAX_DEPTH {
+                    continue;
+                }
+                let parent = stack[depth - 1];
+                info.add_node(&stack[depth], &parent);
+            }
+        }
+    }
+    println!(
+        "[kernel] fdt: {} hart(s), timebase {:?} Hz, {} memory region(s), {} virtio slot(s)",
+        info.harts, info.timebase_freq, info.memory_count, info.virtio_count
+    );
+}
+
+impl BootInfo {
+    fn reserve(&mut self, base: usize, size: usize, name: &'static str) {
+        if self.reserved_count < MAX_RESERVED_REGIONS && size > 0 {
+            self.reserved[self.reserved_count] = (base, size, name);
+            self.reserved_count += 1;
+        }
+    }
+
+    /// 处理一个结束的节点，`parent` 为其父节点
+    fn add_node(&mut self, node: &Node, parent: &Node) {
+        if node.disabled {
+            return;
+        }
+        let reg = || fdt::reg_entries(node.reg, parent.address_cells, parent.size_cells);
+        if node.device_type.starts_with(b"memory\0") {
+            for region in reg() {
+                if self.memory_count < MAX_MEMORY_REGIONS {
+                    self.memory[self.memory_count] = region;
+                    self.memory_count += 1;
+                }
+            }
+        } else if node.device_type.starts_with(b"cpu\0") {
+            self.harts += 1;
+        } else if parent.name == "reserved-memory" {
+            for (base, size) in reg() {
+                self.reserve(base, size, node.name);
+            }
+        }
+        // timebase-frequency 可以写在 /cpus 或每个 cpu 节点上
+        if node.name == "cpus" || node.device_type.starts_with(b"cpu\0") {
+            self.timebase_freq = self.timebase_freq.or(node.timebase_freq);
+        }
+        if fdt::string_list_contains(node.compatible, "virtio,mmio") {
+            if let (Some((base, size)), Some(irq)) = (reg().next(), node.interrupts) {
+                if self.virtio_count < MAX_VIRTIO_DEVICES {
+                    self.virtio[self.virtio_count] = VirtioDevice { base, size, irq };
+                    self.virtio_count += 1;
+                }
+            }
+        }
+        if fdt::string_list_contains(node.compatible, "google,goldfish-rtc") {
+            self.rtc = self.rtc.or(reg().next());
+        }
+    }
+}
+
+/// 把 `[base, base + size)` 记为保留区域，不交给页帧分配器
+pub(super) fn reserve(base: usize, size: usize, name: &'static str) {
+    BOOT_INFO.lock().reserve(base, size, name);
+}
+
+/// 按起始地址排序后检查相邻的区域，有重叠时 panic
+fn assert_disjoint(kind: &str, regions: &mut [(usize, usize, &str)]) {
+    regions.sort_unstable_by_key(|&(base, _, _)| base);
+    for pair in regions.windows(2) {
+        let (a_base, a_size, a_name) = pair[0];
+        let (b_base, b_size, b_name) = pair[1];
+        if b_base < a_base + a_size {
+            panic!(
+                "[kernel] {} regions overlap: {} [{:#x}, {:#x}) and {} [{:#x}, {:#x})",
+                kind,
+                a_name,
+                a_base,
+                a_base + a_size,
+                b_name,
+                b_base,
+                b_base + b_size
+            );
+        }
+    }
+}
+
+/// 可供页帧分配器使用、并需要加入内核直接映射的物理内存范围 `[start, end)`，按地址升序、页对齐
+///
+/// 设备树中没有内存区域时，以内核镜像起点到 `hal::MEMORY_END` 为唯一的内存区域
+pub fn usable_memory() -> Vec<(usize, usize)> {
+    extern "C" {
+        fn skernel();
+        fn ekernel();
+    }
+    let skernel = crate::mm::virt_to_phys(skernel as *const () as usize).unwrap();
+    let ekernel = crate::mm::virt_to_phys(ekernel as *const () as usize).unwrap();
+    let info = BOOT_INFO.lock();
+    let mut memory: Vec<(usize, usize, &str)> = info.memory[..info.memory_count]
+        .iter()
+        .map(|&(base, size)| (base, size, "memory"))
+        .collect();
+    if memory.is_empty() {
+        memory.push((skernel, MEMORY_END - skernel, "memory"));
+    }
+    let mut reserved: Vec<(usize, usize, &str)> = info.reserved[..info.reserved_count].to_vec();
+    drop(info);
+    reserved.push((skernel, ekernel - skernel, "kernel image"));
+    #[cfg(feature = "loongarch")]
+    {
+        use crate::hal::platform::{DISK_IMAGE_BASE, MEM_SIZE, MEM_START};
+        reserved.push((
+            DISK_IMAGE_BASE,
+            MEM_START + MEM_SIZE - DISK_IMAGE_BASE,
+            "disk image",
+        ));
+    }
+    assert_disjoint("memory", &mut memory);
+    assert_disjoint("reserved", &mut reserved);
+
+    let page_size = crate::hal::PAGE_SIZE;
+    let mut usable = Vec::new();
+    for (base, size, _) in memory {
+        let end = (base + size).min(DIRECT_MAP_SIZE);
+        // 内核所在区域中内核之前的部分留给固件
+        let mut start = if (base..end).contains(&skernel) {
+            skernel
+        } else {
+            base
+        };
+        for &(r_base, r_size, _) in reserved.iter() {
+            let r_end = r_base + r_size;
+            if r_end <= start || r_base >= end {
+                continue;
+            }
+            if r_base > start {
+                usable.push((start, r_base));
+            }
+            start = start.max(r_end);
+        }
+        if start < end {
+            usable.push((start, end));
+        }
+    }
+    usable
+        .into_iter()
+        .map(|(start, end)| {
+            (
+                start.next_multiple_of(page_size),
+                end / page_size * page_size,
+            )
+        })
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// 定时器频率，设备树中没有时返回 `None`
+pub fn timebase_freq() -> Option<usize> {
+    BOOT_INFO.lock().timebase_freq
+}
+
+/// 可供探测的 VirtIO MMIO 槽位 `(base, irq)`
+pub fn virtio_mmio_slots() -> Vec<(usize, usize)> {
+    let info = BOOT_INFO.lock();
+    if !info.from_fdt {
+        return VIRTIO_MMIO_SLOTS.to_vec();
+    }
+    info.virtio[..info.virtio_count]
+        .iter()
+        .map(|dev| (dev.base, dev.irq))
+        .collect()
+}
+
+/// 实时时钟的寄存器基址，设备树中没有 RTC 节点时使用 `hal::RTC_BASE`
+pub fn rtc_base() -> Option<usize> {
+    let info = BOOT_INFO.lock();
+    if !info.from_fdt {
+        return RTC_BASE;
+    }
+    info.rtc.map(|(base, _)| base)
+}
+
+/// 需要映射到内核地址空间的 MMIO 区域 `(base, size)`：
+/// 静态表 `hal::MMIO`，加上设备树中与已有区域不重叠的 VirtIO 设备与 RTC
+pub fn mmio_regions() -> Vec<(usize, usize)> {
+    let mut regions = MMIO.to_vec();
+    let info = BOOT_INFO.lock();
+    let devices = info.virtio[..info.virtio_count]
+        .iter()
+        .map(|dev| (dev.base, dev.size))
+        .chain(info.rtc);
+    for (dev_base, dev_size) in devices {
+        let overlaps = regions
+            .iter()
+            .any(|&(base, size)| dev_base < base + size && base < dev_base + dev_size);
+        if !overlaps {
+            regions.push((dev_base, dev_size));
+        }
+    }
+    regions
+}
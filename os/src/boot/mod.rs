@@ -0,0 +1,46 @@
+//! # 启动信息
+//!
+//! ## Overview
+//! 收集引导程序传入的信息，供 `rust_main` 决定启动行为：
+//! - `fdt`：读取扁平设备树
+//! - `info`：设备树中的内存区域与保留区域、hart 数量、定时器频率、VirtIO 设备与 RTC，没有设备树时使用静态表
+//! - `params`：内核命令行与 `init=` / `autorun=` / `root=` / `loglevel=` / `codepage=` / `selftest=` 等选项
+//!
+//! ## Assumptions
+//! - `init` 在清理 BSS 之后、启用内核页表之前调用，此时通过启动页表的直接映射区读取设备树
+//! - RISC-V 上设备树地址由 SBI 在 `a1` 中传入；LoongArch 上没有设备树，只使用编译期的 `BOOTARGS`
+
+mod fdt;
+mod info;
+mod params;
+
+use fdt::Fdt;
+pub use info::{mmio_regions, rtc_base, timebase_freq, usable_memory, virtio_mmio_slots};
+#[cfg(feature = "fault_inject")]
+pub use params::fault_attr;
+#[cfg(feature = "gdbstub")]
+pub use params::gdbstub_wait;
+#[cfg(feature = "selftest")]
+pub use params::selftests;
+pub use params::{autorun_path, cmdline, codepage, console_rank, init_path, loglevel};
+pub use params::{root_partition, tick_hz, timeslice_ms};
+
+/// 从物理地址为 `dtb` 的设备树（为 0 表示没有）中读取平台信息与命令行
+pub fn init(dtb: usize) {
+    let fdt = unsafe { Fdt::from_addr(dtb) };
+    if let Some(fdt) = fdt.as_ref() {
+        info::parse(fdt);
+        // 保留区域的名称指向设备树内部，设备树所在的内存不交给页帧分配器
+        info::reserve(dtb, fdt.total_size(), "dtb");
+    }
+    // bootargs 以 NUL 结尾
+    let bootargs = fdt
+        .as_ref()
+        .and_then(|fdt| fdt.property("/chosen", "bootargs"))
+        .and_then(|bootargs| bootargs.split(|&b| b == 0).next())
+        .filter(|bootargs| !bootargs.is_empty());
+    match bootargs {
+        Some(bootargs) => params::set_cmdline(bootargs),
+        None => params::set_cmdline(option_env!("BOOTARGS").unwrap_or("").as_bytes()),
+    }
+}
@@ -0,0 +1,177 @@
+//! # 内核启动参数
+//!
+//! ## Overview
+//! 保存引导程序传入的内核命令行，并解析其中的选项，代替启动流程中写死的行为：
+//! - `init=<path>`：初始进程的可执行文件，默认 `/initproc`
+//! - `autorun=<path>`：自动运行列表，其中每行一条命令，作为参数交给初始进程依次运行，
+//!   默认 `/autorun.txt`
+//! - `root=<device>`：根文件系统所在的分区，如 `/dev/vda2` 或 `2`（从 1 开始编号），
+//!   默认为第一个 FAT 分区
+//! - `loglevel=<level>`：日志级别，取 `off`/`error`/`warn`/`info`/`debug`/`trace`
+//!   或 Linux 的数字级别 0..=7，默认使用编译期环境变量 `LOG`
+//! - `hz=<n>`：每秒的时钟 tick 数（1..=1000），默认使用各架构的 `TICKS_PER_SEC`
+//! - `timeslice=<ms>`：调度时间片的长度（毫秒），与 tick 无关，默认为一个默认 tick 周期，见 `timer`
+//! - `codepage=<437|ascii>`：FAT32 短文件名的代码页，默认 437，见 `fs::fat_name`
+//! - `console=<name>`：输出内核信息的控制台，如 `sbi`、`ttyS0`、`hvc0`，可以出现多次，
+//!   列出的控制台都输出内核信息，最后一个是用户程序使用的主控制台，见 `console`
+//! - `selftest=<name>[,<name>...]`：启用 `selftest` feature 时要运行的自检组，缺省时运行全部，见 `selftest`
+//! - `gdbstub=wait`：启用 `gdbstub` feature 时在 `machine_init` 之后陷入调试桩等待 GDB 连接，
+//!   缺省时不等待，见 `gdbstub`
+//! - `fail_page_alloc=` / `failslab=` / `fail_make_request=`：启用 `fault_inject` feature 时
+//!   各故障注入点的设置，见 `fault`
+//!
+//! 选项以空白分隔，除 `console=` 外同名选项以最后一次出现的为准，无法识别的选项被忽略
+//!
+//! ## Design
+//! - 命令行在启用分页与初始化堆之前复制到固定大小的缓冲区中，
+//!   此后设备树所在的内存可以被页帧分配器回收
+//! - 没有设备树或 `/chosen` 中没有 `bootargs` 时，使用编译期环境变量 `BOOTARGS`
+//!
+//! ## Invariants
+//! - 缓冲区中的命令行总是合法的 UTF-8，超出 `CMDLINE_MAX` 的部分被截断
+
+use crate::sync::SpinMutex;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use log::LevelFilter;
+
+/// 命令行的最大长度
+const CMDLINE_MAX: usize = 512;
+/// 默认的初始进程
+const DEFAULT_INIT: &str = "/initproc";
+/// 默认的自动运行列表
+const DEFAULT_AUTORUN: &str = "/autorun.txt";
+
+struct Cmdline {
+    buf: [u8; CMDLINE_MAX],
+    len: usize,
+}
+
+static CMDLINE: SpinMutex<Cmdline> = SpinMutex::new(Cmdline {
+    buf: [0; CMDLINE_MAX],
+    len: 0,
+});
+
+/// 保存命令行，非 UTF-8 的命令行被忽略
+pub(super) fn set_cmdline(cmdline: &[u8]) {
+    let Ok(cmdline) = core::str::from_utf8(cmdline) else {
+        return;
+    };
+    let mut len = cmdline.len().min(CMDLINE_MAX);
+    // 截断不能落在多字节字符的中间
+    while !cmdline.is_char_boundary(len) {
+        len -= 1;
+    }
+    let mut saved = CMDLINE.lock();
+    saved.buf[..len].copy_from_slice(&cmdline.as_bytes()[..len]);
+    saved.len = len;
+}
+
+/// 在保存的命令行上执行 `f`
+fn with_cmdline<R>(f: impl FnOnce(&str) -> R) -> R {
+    let saved = CMDLINE.lock();
+    f(core::str::from_utf8(&saved.buf[..saved.len]).unwrap_or(""))
+}
+
+/// 选项 `key` 的值，多次出现时取最后一次
+fn param<'a>(cmdline: &'a str, key: &str) -> Option<&'a str> {
+    cmdline
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix(key)?.strip_prefix('='))
+        .last()
+}
+
+/// 完整的命令行
+pub fn cmdline() -> String {
+    with_cmdline(|cmdline| cmdline.to_string())
+}
+
+/// 初始进程的路径
+pub fn init_path() -> String {
+    with_cmdline(|cmdline| param(cmdline, "init").unwrap_or(DEFAULT_INIT).to_string())
+}
+
+/// 自动运行列表的路径
+pub fn autorun_path() -> String {
+    with_cmdline(|cmdline| {
+        param(cmdline, "autorun")
+            .unwrap_or(DEFAULT_AUTORUN)
+            .to_string()
+    })
+}
+
+/// `root=` 指定的分区编号（从 1 开始），未指定或指定整盘时返回 `None`
+pub fn root_partition() -> Option<usize> {
+    with_cmdline(|cmdline| {
+        let root = param(cmdline, "root")?;
+        // 取末尾的数字，`/dev/vda2` 与 `2` 都表示第 2 个分区
+        let device = root.trim_end_matches(|c: char| c.is_ascii_digit());
+        root[device.len()..].parse().ok()
+    })
+}
+
+/// `loglevel=` 指定的日志级别，无法识别时返回 `None`
+pub fn loglevel() -> Option<LevelFilter> {
+    with_cmdline(|cmdline| match param(cmdline, "loglevel")? {
+        "off" => Some(LevelFilter::Off),
+        "error" | "0" | "1" | "2" | "3" => Some(LevelFilter::Error),
+        "warn" | "4" => Some(LevelFilter::Warn),
+        "info" | "5" | "6" => Some(LevelFilter::Info),
+        "debug" | "7" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    })
+}
+
+/// `hz=` 指定的每秒 tick 数，无法解析时返回 `None`
+pub fn tick_hz() -> Option<usize> {
+    with_cmdline(|cmdline| param(cmdline, "hz")?.parse().ok())
+}
+
+/// `timeslice=` 指定的调度时间片（毫秒），无法解析时返回 `None`
+pub fn timeslice_ms() -> Option<usize> {
+    with_cmdline(|cmdline| param(cmdline, "timeslice")?.parse().ok())
+}
+
+/// `codepage=` 指定的 FAT32 短文件名代码页
+pub fn codepage() -> Option<String> {
+    with_cmdline(|cmdline| param(cmdline, "codepage").map(String::from))
+}
+
+/// 名为 `name` 的控制台在 `console=` 选项中最后一次出现的位置，没有列出时返回 `None`
+pub fn console_rank(name: &str) -> Option<usize> {
+    with_cmdline(|cmdline| {
+        cmdline
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix("console="))
+            .enumerate()
+            .filter(|(_, console)| *console == name)
+            .map(|(rank, _)| rank)
+            .last()
+    })
+}
+
+/// `selftest=` 列出的自检名称
+#[cfg(feature = "selftest")]
+pub fn selftests() -> Vec<String> {
+    with_cmdline(|cmdline| {
+        param(cmdline, "selftest")
+            .into_iter()
+            .flat_map(|list| list.split(','))
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect()
+    })
+}
+
+/// 是否指定了 `gdbstub=wait`
+#[cfg(feature = "gdbstub")]
+pub fn gdbstub_wait() -> bool {
+    with_cmdline(|cmdline| param(cmdline, "gdbstub") == Some("wait"))
+}
+
+/// 故障注入点 `name` 的设置
+#[cfg(feature = "fault_inject")]
+pub fn fault_attr(name: &str) -> Option<String> {
+    with_cmdline(|cmdline| param(cmdline, name).map(String::from))
+}
@@ -4,67 +4,376 @@
 //! 向上提供：
 //! - `print!` / `println!` 宏，用于格式化输出
 //! - 基于 `log` crate 的日志系统实现
+//! - 控制台后端的注册表：`register` 登记后端，`select` / `set_mirror` 在运行时切换
 //!
 //! # Overview
-//! - 字符输出最终通过 HAL 的 `console_putchar` 完成
-//! - 输出缓冲按字符数定期调用 `console_flush`
+//! - 字符输出最终交给控制台后端（`ConsoleBackend`）成批写出，后端包括 HAL 的 `sbi` 接口、
+//!   MMIO 串口（`ttyS0`）与 virtio-console（`hvc0`），后两者由 `drivers::serial` 探测并登记
+//! - 内核输出经过行缓冲：遇到换行或缓冲区满时写出并刷新后端，
+//!   不以换行结尾的输出需要调用 `flush` 显式写出
+//! - 内核输出写给所有打开了镜像的后端；用户程序的输入输出（`write_slices` / `getchar`）
+//!   只经过主控制台，与 Linux 的 `/dev/console` 相同
+//! - 用户程序写出的数据（`write_slices`）是任意字节，不做 UTF-8 校验，
+//!   也不经过行缓冲，多个分段整体写出后只刷新一次，跨页的多字节字符不会被拆开处理
 //! - 日志输出支持不同级别，并使用 ANSI 颜色区分
 //!
+//! # Design
+//! - 注册表在编译期就含有 `sbi` 后端，作为启动控制台，第一条输出之前无需初始化
+//! - 选择方式与 Linux 相同：命令行中 `console=` 列出的后端都镜像内核输出，最后一个是主控制台；
+//!   没有列出时只使用 `sbi`。列出的后端登记之前内核输出仍经过 `sbi`，登记之后 `sbi`（若未列出）不再输出，
+//!   避免在同一个串口上重复
+//! - `/proc/consoles` 列出已登记的后端及其状态，见 `report`
+//!
 //! # Concurrency Model
-//! - 本模块假定运行在内核态
-//! - 控制台输出本身不保证并发安全
-//! - 调用方需保证在关中断或串行化环境中使用
+//! - 缓冲区由 `SpinMutex` 保护，每次 `print` 调用在持锁期间完成格式化与写入，
+//!   一次 `println!` 输出的整行不会与其他任务或中断处理程序的输出交错
+//! - 注册表由另一个 `SpinMutex` 保护，总是在缓冲区的锁之后获取
+//! - panic 处理开始后进入紧急模式：不再等待控制台锁，直接逐字符输出，
+//!   避免在持锁期间 panic 时死锁
 //!
 //! # Safety
 //! - 本模块不直接使用 `unsafe`
-//! - 但依赖外部保证：
-//!   - HAL 层输出接口的正确性
-//!   - 日志输出期间不会发生破坏性并发
+//! - 但依赖外部保证：HAL 层输出接口的正确性
 //!
 //! # Invariants
 //! - 控制台输出必须保持字符顺序
 //! - 日志输出不得引起递归打印或死锁
+//! - 主控制台总是镜像内核输出，后端一经登记便不会注销
 
-use crate::hal::{console_flush, console_putchar};
+use crate::hal::{console_flush, console_getchar, console_write};
+use crate::sync::SpinMutex;
 use crate::task::current_task;
+use alloc::string::String;
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
 use log::{Level, LevelFilter, Log, Metadata, Record};
 
-/// 标准输出结构体。
+/// 输出缓冲区大小
+const BUFFER_SIZE: usize = 256;
+/// 控制台后端的最大数量
+const MAX_CONSOLES: usize = 4;
+
+/// 控制台后端
+pub trait ConsoleBackend: Send + Sync {
+    /// 后端的名称，命令行的 `console=` 以此选择
+    fn name(&self) -> &str;
+    /// 写出一段字节
+    fn write(&self, bytes: &[u8]);
+    /// 等待写出的字节发送完毕
+    fn flush(&self) {}
+    /// 读取一个输入字符，没有输入时返回 `None`
+    fn getchar(&self) -> Option<u8> {
+        None
+    }
+}
+
+/// HAL 的字符输入输出接口：RISC-V 上为 SBI 的控制台调用，LoongArch 上直接读写 UART
+struct SbiConsole;
+
+impl ConsoleBackend for SbiConsole {
+    fn name(&self) -> &str {
+        "sbi"
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        console_write(bytes);
+    }
+
+    fn flush(&self) {
+        console_flush();
+    }
+
+    fn getchar(&self) -> Option<u8> {
+        // 根据 sbi 接口规定，若无输入则返回 usize::MAX
+        match console_getchar() {
+            usize::MAX => None,
+            c => Some(c as u8),
+        }
+    }
+}
+
+struct ConsoleEntry {
+    backend: &'static dyn ConsoleBackend,
+    /// 是否镜像内核输出
+    mirror: bool,
+    /// 在命令行 `console=` 中的位置，没有列出时为 `None`
+    rank: Option<usize>,
+}
+
+/// 已登记的控制台后端，0 号为启动控制台 `sbi`
+struct Registry {
+    entries: [Option<ConsoleEntry>; MAX_CONSOLES],
+    /// 主控制台的下标
+    primary: usize,
+}
+
+impl Registry {
+    fn iter(&self) -> impl Iterator<Item = (usize, &ConsoleEntry)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((i, entry.as_ref()?)))
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.iter()
+            .find(|(_, entry)| entry.backend.name() == name)
+            .map(|(i, _)| i)
+    }
+
+    fn primary(&self) -> &'static dyn ConsoleBackend {
+        self.entries[self.primary].as_ref().unwrap().backend
+    }
+
+    /// 写给所有镜像内核输出的后端
+    fn write(&self, bytes: &[u8]) {
+        for (_, entry) in self.iter().filter(|(_, entry)| entry.mirror) {
+            entry.backend.write(bytes);
+        }
+    }
+
+    fn flush(&self) {
+        for (_, entry) in self.iter().filter(|(_, entry)| entry.mirror) {
+            entry.backend.flush();
+        }
+    }
+
+    /// 按 `console=` 选出主控制台：列出的后端中位置最后的一个，都没有列出时保持不变
+    ///
+    /// 列出的后端登记之后，未列出的启动控制台不再镜像内核输出
+    fn elect(&mut self) {
+        let listed = self
+            .iter()
+            .filter_map(|(i, entry)| Some((entry.rank?, i)))
+            .max();
+        let Some((_, primary)) = listed else {
+            return;
+        };
+        self.primary = primary;
+        if let Some(boot) = self.entries[0].as_mut() {
+            if boot.rank.is_none() {
+                boot.mirror = false;
+            }
+        }
+    }
+}
+
+static REGISTRY: SpinMutex<Registry> = SpinMutex::new(Registry {
+    entries: [
+        Some(ConsoleEntry {
+            backend: &SbiConsole,
+            mirror: true,
+            rank: None,
+        }),
+        None,
+        None,
+        None,
+    ],
+    primary: 0,
+});
+
+/// 行缓冲的控制台输出。
 ///
 /// 该结构体实现 `core::fmt::Write`，
 /// 作为格式化输出的最终落地点。
-struct Stdout;
+struct Console {
+    buf: [u8; BUFFER_SIZE],
+    len: usize,
+}
 
-impl Write for Stdout {
-    /// 将字符串写入控制台。
-    ///
-    /// 字符逐个通过 `console_putchar` 输出，
-    /// 并每输出若干字符后调用 `console_flush`，
-    /// 以减少底层 I/O 调用开销。
+impl Console {
+    /// 追加一个字节，遇到换行或缓冲区满时写出
+    fn push(&mut self, byte: u8) {
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if byte == b'\n' || self.len == BUFFER_SIZE {
+            self.flush();
+        }
+    }
+
+    /// 写出缓冲区中的全部字符
+    fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        write_mirrors(&self.buf[..self.len]);
+        self.len = 0;
+    }
+}
+
+impl Write for Console {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let mut i = 0;
-        for c in s.chars() {
-            console_putchar(c as usize);
-            i += 1;
-            if i >= 4 {
-                console_flush();
-                i = 0;
-            }
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+        Ok(())
+    }
+}
+
+/// 紧急模式下不经缓冲、不加锁的输出
+struct Direct;
+
+impl Write for Direct {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_mirrors(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// 把内核输出写给所有镜像内核输出的后端并刷新
+///
+/// 紧急模式下不等待注册表的锁，锁被占用时直接使用 HAL 的接口
+fn write_mirrors(bytes: &[u8]) {
+    let registry = if EMERGENCY.load(Ordering::Relaxed) {
+        REGISTRY.try_lock()
+    } else {
+        Some(REGISTRY.lock())
+    };
+    match registry {
+        Some(registry) => {
+            registry.write(bytes);
+            registry.flush();
         }
-        if i != 0 {
+        None => {
+            console_write(bytes);
             console_flush();
         }
-        Ok(())
     }
 }
 
+static CONSOLE: SpinMutex<Console> = SpinMutex::new(Console {
+    buf: [0; BUFFER_SIZE],
+    len: 0,
+});
+
+/// 是否处于紧急模式（panic 处理中）
+static EMERGENCY: AtomicBool = AtomicBool::new(false);
+
 /// 内部打印函数。
 ///
 /// 该函数是 `print!` / `println!` 宏的实际实现，
-/// 接收格式化后的参数并输出到控制台。
+/// 接收格式化后的参数并输出到控制台，持有控制台锁直到本次输出写入缓冲区。
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    if EMERGENCY.load(Ordering::Relaxed) {
+        Direct.write_fmt(args).unwrap();
+    } else {
+        CONSOLE.lock().write_fmt(args).unwrap();
+    }
+}
+
+/// 依次向主控制台写出若干段任意字节（如用户程序写标准输出的数据），全部写完后刷新一次
+///
+/// 先写出缓冲区中的内核输出以保持顺序，各段不经缓冲直接交给主控制台
+pub fn write_slices<'a>(slices: impl IntoIterator<Item = &'a [u8]>) {
+    let console = if EMERGENCY.load(Ordering::Relaxed) {
+        None
+    } else {
+        let mut console = CONSOLE.lock();
+        console.flush();
+        Some(console)
+    };
+    let primary = REGISTRY.lock().primary();
+    for bytes in slices {
+        primary.write(bytes);
+    }
+    primary.flush();
+    drop(console);
+}
+
+/// 从主控制台读取一个输入字符，没有输入时返回 `None`
+pub fn getchar() -> Option<u8> {
+    let primary = REGISTRY.lock().primary();
+    primary.getchar()
+}
+
+/// 登记一个控制台后端，注册表已满或同名后端已登记时返回 `false`
+///
+/// 命令行 `console=` 中列出的后端登记后立即镜像内核输出，位置最后的一个成为主控制台
+pub fn register(backend: &'static dyn ConsoleBackend) -> bool {
+    let rank = crate::boot::console_rank(backend.name());
+    let _console = CONSOLE.lock();
+    let mut registry = REGISTRY.lock();
+    if registry.find(backend.name()).is_some() {
+        return false;
+    }
+    let Some(slot) = registry.entries.iter_mut().find(|entry| entry.is_none()) else {
+        return false;
+    };
+    *slot = Some(ConsoleEntry {
+        backend,
+        mirror: rank.is_some(),
+        rank,
+    });
+    registry.elect();
+    true
+}
+
+/// 把名为 `name` 的后端设为主控制台，后端不存在时返回 `false`
+///
+/// 主控制台总是镜像内核输出
+pub fn select(name: &str) -> bool {
+    let mut console = CONSOLE.lock();
+    console.flush();
+    let mut registry = REGISTRY.lock();
+    let Some(i) = registry.find(name) else {
+        return false;
+    };
+    registry.primary = i;
+    registry.entries[i].as_mut().unwrap().mirror = true;
+    true
+}
+
+/// 打开或关闭名为 `name` 的后端上的内核输出镜像，后端不存在或试图关闭主控制台时返回 `false`
+pub fn set_mirror(name: &str, mirror: bool) -> bool {
+    let mut console = CONSOLE.lock();
+    console.flush();
+    let mut registry = REGISTRY.lock();
+    match registry.find(name) {
+        Some(i) if mirror || i != registry.primary => {
+            registry.entries[i].as_mut().unwrap().mirror = mirror;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 按 `/proc/consoles` 的格式列出已登记的后端：名称、读写能力与状态
+///
+/// 状态中的 `E` 表示镜像内核输出，`C` 表示主控制台
+pub fn report() -> String {
+    let registry = REGISTRY.lock();
+    let mut out = String::new();
+    for (i, entry) in registry.iter() {
+        let _ = writeln!(
+            out,
+            "{:<21} -W- ({}{})",
+            entry.backend.name(),
+            if entry.mirror { 'E' } else { ' ' },
+            if i == registry.primary { 'C' } else { ' ' },
+        );
+    }
+    out
+}
+
+/// 写出缓冲区中尚未输出的内容
+///
+/// 紧急模式下锁被占用时放弃刷新，而不是等待
+pub fn flush() {
+    if EMERGENCY.load(Ordering::Relaxed) {
+        if let Some(mut console) = CONSOLE.try_lock() {
+            console.flush();
+        }
+    } else {
+        CONSOLE.lock().flush();
+    }
+}
+
+/// 进入紧急模式：先尽量写出缓冲区，此后的输出不再经过缓冲与锁
+pub fn enter_emergency() {
+    if let Some(mut console) = CONSOLE.try_lock() {
+        console.flush();
+    }
+    EMERGENCY.store(true, Ordering::Relaxed);
 }
 
 /// 打印宏（不自动换行）。
@@ -95,7 +404,7 @@ macro_rules! println {
 /// 初始化日志系统。
 ///
 /// 使用 `log` crate 的全局日志接口，
-/// 并通过编译期环境变量 `LOG` 设置日志级别。
+/// 日志级别由启动参数 `loglevel=` 指定，未指定时使用编译期环境变量 `LOG`。
 ///
 /// 支持的日志级别：
 /// - error
@@ -105,17 +414,25 @@ macro_rules! println {
 /// - trace
 ///
 /// 默认关闭日志输出。
+///
+/// 同时按命令行的 `console=` 设置启动控制台 `sbi`。
 pub fn init() {
+    {
+        let mut registry = REGISTRY.lock();
+        let boot = registry.entries[0].as_mut().unwrap();
+        boot.rank = crate::boot::console_rank(boot.backend.name());
+        registry.elect();
+    }
     static LOGGER: Logger = Logger;
     log::set_logger(&LOGGER).unwrap();
-    log::set_max_level(match option_env!("LOG") {
+    log::set_max_level(crate::boot::loglevel().unwrap_or(match option_env!("LOG") {
         Some("error") => LevelFilter::Error,
         Some("warn") => LevelFilter::Warn,
         Some("info") => LevelFilter::Info,
         Some("debug") => LevelFilter::Debug,
         Some("trace") => LevelFilter::Trace,
         _ => LevelFilter::Off,
-    });
+    }));
 }
 
 /// 内核日志记录器。
@@ -144,8 +461,8 @@ impl Log for Logger {
             return;
         }
 
-        // 设置日志颜色
-        print!("\x1b[{}m", level_to_color_code(record.level()));
+        // 颜色、内容与重置颜色在同一次输出中完成，不会与其他输出交错
+        let color = level_to_color_code(record.level());
         match current_task() {
             Some(task) => {
                 let tid = task
@@ -153,16 +470,15 @@ impl Log for Logger {
                     .res
                     .as_ref()
                     .map_or(usize::MAX, |res| res.tid);
-                println!("pid {}: {}", tid, record.args())
+                println!("\x1b[{}mpid {}: {}\x1b[0m", color, tid, record.args())
             }
-            None => println!("kernel: {}", record.args()),
+            None => println!("\x1b[{}mkernel: {}\x1b[0m", color, record.args()),
         }
-
-        // 重置颜色
-        print!("\x1b[0m")
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        flush();
+    }
 }
 
 /// 将日志级别映射为 ANSI 颜色码。
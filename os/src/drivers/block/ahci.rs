@@ -0,0 +1,422 @@
+//! # AHCI SATA 块设备驱动
+//!
+//! ## Overview
+//! 龙芯 2K1000 开发板的存储接口是片上 AHCI SATA 控制器，本模块为其提供块设备驱动，
+//! 使开发板可以直接从 SATA 盘（或 SATA 接口的 SD 读卡器）启动，而不必依赖常驻内存的磁盘镜像：
+//! - 只使用第一个连接了 ATA 设备的端口，且只使用 0 号命令槽
+//! - 通过 `READ DMA EXT` / `WRITE DMA EXT` 以 48 位 LBA 读写，数据经由 DMA 中转页
+//! - `read_blocks` / `write_blocks` 以一条命令读写至多 `BOUNCE_PAGES` 页的连续块，
+//!   PRDT 中每个表项指向一个中转页
+//!
+//! ## Design
+//! - 轮询模式：发出命令后轮询 `PxCI`，直到命令槽被控制器清除
+//! - 中断模式：平台提供 `AHCI_IRQ` 时启用，发出命令的任务阻塞在条件变量上，
+//!   由 `handle_irq` 在命令完成中断到来时唤醒；没有当前任务（如启动阶段挂载根文件系统）时仍然轮询
+//!
+//! ## Assumptions
+//! - 控制器寄存器（ABAR）位于 `AHCI_BASE`，已通过平台 `MMIO` 映射
+//! - 控制器的 DMA 与 CPU 缓存一致，不需要手动刷新缓存
+//! - 扇区大小为 512 字节，`BLOCK_SZ` 是其整数倍
+//!
+//! ## Safety
+//! - 所有寄存器访问均为 volatile MMIO 读写
+//! - 命令列表、接收 FIS 区、命令表与数据中转页都来自页帧分配器，生命周期与驱动相同
+//!
+//! ## Invariants
+//! - 任意时刻至多有一条命令在执行：一次块读写从发出命令到拷出中转页的全过程都持有 `lock`
+//! - 端口运行期间 `PxCLB` / `PxFB` 指向的内存不会被释放
+
+use super::block_dev::BlockDevice;
+use crate::hal::{AHCI_BASE, AHCI_IRQ, BLOCK_SZ, PAGE_SIZE, PALEN};
+use crate::mm::{frame_alloc, FrameTracker, PhysAddr};
+use crate::sync::{Condvar, Mutex, MutexBlocking, UPIntrFreeCell};
+use crate::task::{current_task, schedule};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+
+const SECTOR_SIZE: usize = 512;
+/// DMA 中转页的数目，即一条命令最多传输的页数
+const BOUNCE_PAGES: usize = 16;
+/// 等待设备就绪时的最大轮询次数
+const SPIN_LIMIT: usize = 10_000_000;
+
+// HBA 全局寄存器
+const HBA_GHC: usize = 0x04;
+const HBA_IS: usize = 0x08;
+const HBA_PI: usize = 0x0C;
+const GHC_AE: u32 = 1 << 31;
+const GHC_IE: u32 = 1 << 1;
+
+// 端口寄存器（相对端口基址）
+const PORT_BASE: usize = 0x100;
+const PORT_STRIDE: usize = 0x80;
+const PX_CLB: usize = 0x00;
+const PX_CLBU: usize = 0x04;
+const PX_FB: usize = 0x08;
+const PX_FBU: usize = 0x0C;
+const PX_IS: usize = 0x10;
+const PX_IE: usize = 0x14;
+const PX_CMD: usize = 0x18;
+const PX_TFD: usize = 0x20;
+const PX_SIG: usize = 0x24;
+const PX_SSTS: usize = 0x28;
+const PX_SERR: usize = 0x30;
+const PX_CI: usize = 0x38;
+
+const CMD_ST: u32 = 1 << 0;
+const CMD_FRE: u32 = 1 << 4;
+const CMD_FR: u32 = 1 << 14;
+const CMD_CR: u32 = 1 << 15;
+const TFD_ERR: u32 = 1 << 0;
+const TFD_DRQ: u32 = 1 << 3;
+const TFD_BSY: u32 = 1 << 7;
+/// D2H Register FIS 中断 | 任务文件错误
+const IS_DHRS: u32 = 1 << 0;
+const IS_TFES: u32 = 1 << 30;
+/// SATA 状态：检测到设备且 PHY 通信已建立
+const SSTS_DET_PRESENT: u32 = 3;
+/// 端口签名：ATA 设备
+const SIG_ATA: u32 = 0x0000_0101;
+
+// ATA 命令
+const FIS_TYPE_REG_H2D: u8 = 0x27;
+const ATA_READ_DMA_EXT: u8 = 0x25;
+const ATA_WRITE_DMA_EXT: u8 = 0x35;
+const ATA_IDENTIFY: u8 = 0xEC;
+
+// 控制页内的布局：命令列表（1 KiB 对齐）、接收 FIS（256 字节对齐）、命令表（128 字节对齐）
+const CMD_LIST_OFFSET: usize = 0x000;
+const RX_FIS_OFFSET: usize = 0x400;
+const CMD_TABLE_OFFSET: usize = 0x800;
+/// 命令表中 PRDT 的偏移
+const PRDT_OFFSET: usize = 0x80;
+
+/// 设备可见的物理地址
+fn dma_addr(frame: &FrameTracker) -> u64 {
+    let pa: PhysAddr = frame.ppn.into();
+    (pa.0 & ((1 << PALEN) - 1)) as u64
+}
+
+fn reg(offset: usize) -> *mut u32 {
+    (AHCI_BASE + offset) as *mut u32
+}
+
+fn port_reg(port: usize, offset: usize) -> *mut u32 {
+    reg(PORT_BASE + port * PORT_STRIDE + offset)
+}
+
+/// 轮询直到 `cond` 成立，超时返回 `false`
+fn spin_until(mut cond: impl FnMut() -> bool) -> bool {
+    for _ in 0..SPIN_LIMIT {
+        if cond() {
+            return true;
+        }
+        core::hint::spin_loop();
+    }
+    false
+}
+
+struct Ahci {
+    port: usize,
+    /// 命令列表、接收 FIS 区与命令表所在的页
+    ctrl: FrameTracker,
+    /// DMA 数据中转页，依次对应 PRDT 中的表项
+    bounce: Vec<FrameTracker>,
+    /// 设备的扇区总数
+    sectors: u64,
+}
+
+impl Ahci {
+    fn read_port(&self, offset: usize) -> u32 {
+        unsafe { read_volatile(port_reg(self.port, offset)) }
+    }
+
+    fn write_port(&self, offset: usize, value: u32) {
+        unsafe { write_volatile(port_reg(self.port, offset), value) }
+    }
+
+    /// 查找第一个连接了 ATA 设备的端口并完成初始化
+    fn probe() -> Option<Self> {
+        unsafe {
+            write_volatile(reg(HBA_GHC), read_volatile(reg(HBA_GHC)) | GHC_AE);
+        }
+        let implemented = unsafe { read_volatile(reg(HBA_PI)) };
+        let port = (0..32).find(|&port| {
+            implemented & (1 << port) != 0
+                && unsafe { read_volatile(port_reg(port, PX_SSTS)) } & 0xF == SSTS_DET_PRESENT
+                && unsafe { read_volatile(port_reg(port, PX_SIG)) } == SIG_ATA
+        })?;
+        let mut ahci = Self {
+            port,
+            ctrl: frame_alloc()?,
+            bounce: (0..BOUNCE_PAGES)
+                .map(|_| frame_alloc())
+                .collect::<Option<Vec<_>>>()?,
+            sectors: 0,
+        };
+        ahci.ctrl.ppn.get_bytes_array().fill(0);
+        if !ahci.start_port() {
+            return None;
+        }
+        ahci.identify()?;
+        Some(ahci)
+    }
+
+    /// 停止端口、设置命令列表与接收 FIS 区后重新启动
+    fn start_port(&self) -> bool {
+        let cmd = self.read_port(PX_CMD);
+        self.write_port(PX_CMD, cmd & !CMD_ST);
+        if !spin_until(|| self.read_port(PX_CMD) & CMD_CR == 0) {
+            return false;
+        }
+        self.write_port(PX_CMD, self.read_port(PX_CMD) & !CMD_FRE);
+        if !spin_until(|| self.read_port(PX_CMD) & CMD_FR == 0) {
+            return false;
+        }
+
+        let base = dma_addr(&self.ctrl);
+        let clb = base + CMD_LIST_OFFSET as u64;
+        let fb = base + RX_FIS_OFFSET as u64;
+        self.write_port(PX_CLB, clb as u32);
+        self.write_port(PX_CLBU, (clb >> 32) as u32);
+        self.write_port(PX_FB, fb as u32);
+        self.write_port(PX_FBU, (fb >> 32) as u32);
+        self.write_port(PX_SERR, u32::MAX);
+        self.write_port(PX_IS, u32::MAX);
+
+        if AHCI_IRQ.is_some() {
+            self.write_port(PX_IE, IS_DHRS | IS_TFES);
+            unsafe {
+                write_volatile(reg(HBA_GHC), read_volatile(reg(HBA_GHC)) | GHC_IE);
+            }
+        } else {
+            self.write_port(PX_IE, 0);
+        }
+
+        self.write_port(PX_CMD, self.read_port(PX_CMD) | CMD_FRE);
+        if !spin_until(|| self.read_port(PX_TFD) & (TFD_BSY | TFD_DRQ) == 0) {
+            return false;
+        }
+        self.write_port(PX_CMD, self.read_port(PX_CMD) | CMD_ST);
+        true
+    }
+
+    /// 读取 IDENTIFY DEVICE 数据，得到设备容量
+    fn identify(&mut self) -> Option<()> {
+        self.prepare(ATA_IDENTIFY, 0, 0, SECTOR_SIZE, false);
+        self.issue();
+        if !self.poll_complete() {
+            return None;
+        }
+        let data = self.bounce[0].ppn.get_bytes_array();
+        // 字 100..=103：48 位 LBA 可寻址的扇区总数
+        self.sectors = u64::from_le_bytes(data[200..208].try_into().unwrap());
+        Some(())
+    }
+
+    /// 在 0 号命令槽中构造一条数据长度为 `bytes` 的命令，数据经由中转页
+    fn prepare(&self, command: u8, lba: u64, count: u16, bytes: usize, write: bool) {
+        assert!(bytes <= BOUNCE_PAGES * PAGE_SIZE);
+        let ctrl = self.ctrl.ppn.get_bytes_array();
+        let table = dma_addr(&self.ctrl) + CMD_TABLE_OFFSET as u64;
+        let prdtl = bytes.div_ceil(PAGE_SIZE);
+
+        // 命令头：CFL = 5 个双字，W 位，PRDTL 为用到的中转页数
+        let header = &mut ctrl[CMD_LIST_OFFSET..CMD_LIST_OFFSET + 32];
+        header.fill(0);
+        let dw0 = 5 | ((write as u32) << 6) | ((prdtl as u32) << 16);
+        header[0..4].copy_from_slice(&dw0.to_le_bytes());
+        header[8..16].copy_from_slice(&table.to_le_bytes());
+
+        // 命令 FIS：Register H2D
+        let fis = &mut ctrl[CMD_TABLE_OFFSET..CMD_TABLE_OFFSET + PRDT_OFFSET];
+        fis.fill(0);
+        fis[0] = FIS_TYPE_REG_H2D;
+        fis[1] = 1 << 7; // C：这是一条命令
+        fis[2] = command;
+        fis[4] = lba as u8;
+        fis[5] = (lba >> 8) as u8;
+        fis[6] = (lba >> 16) as u8;
+        fis[7] = 1 << 6; // LBA 模式
+        fis[8] = (lba >> 24) as u8;
+        fis[9] = (lba >> 32) as u8;
+        fis[10] = (lba >> 40) as u8;
+        fis[12] = count as u8;
+        fis[13] = (count >> 8) as u8;
+
+        // PRDT：每个表项指向一个中转页，中断模式下最后一项完成时请求中断
+        for i in 0..prdtl {
+            let offset = CMD_TABLE_OFFSET + PRDT_OFFSET + i * 16;
+            let prd = &mut ctrl[offset..offset + 16];
+            prd[0..8].copy_from_slice(&dma_addr(&self.bounce[i]).to_le_bytes());
+            prd[8..12].fill(0);
+            let len = (bytes - i * PAGE_SIZE).min(PAGE_SIZE);
+            let irq_on_done = if AHCI_IRQ.is_some() && i == prdtl - 1 {
+                1 << 31
+            } else {
+                0
+            };
+            let dbc = (len as u32 - 1) | irq_on_done;
+            prd[12..16].copy_from_slice(&dbc.to_le_bytes());
+        }
+    }
+
+    fn issue(&self) {
+        spin_until(|| self.read_port(PX_TFD) & (TFD_BSY | TFD_DRQ) == 0);
+        self.write_port(PX_IS, u32::MAX);
+        self.write_port(PX_CI, 1);
+    }
+
+    /// 轮询等待 0 号命令槽完成，返回命令是否成功
+    fn poll_complete(&self) -> bool {
+        let done =
+            spin_until(|| self.read_port(PX_CI) & 1 == 0 || self.read_port(PX_IS) & IS_TFES != 0);
+        done && self.succeeded()
+    }
+
+    fn succeeded(&self) -> bool {
+        self.read_port(PX_IS) & IS_TFES == 0 && self.read_port(PX_TFD) & TFD_ERR == 0
+    }
+}
+
+pub struct AhciBlock {
+    ahci: UPIntrFreeCell<Ahci>,
+    port: usize,
+    /// 串行化块读写；中断模式下等待命令完成时会让出 CPU
+    lock: MutexBlocking,
+    /// 中断模式下等待命令完成的任务
+    done: Condvar,
+}
+
+/// 中断模式下最近一次命令是否报告了任务文件错误
+static IRQ_ERROR: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref AHCI_BLOCK: Option<Arc<AhciBlock>> = Ahci::probe().map(|ahci| {
+        println!(
+            "[kernel] ahci: port {}, {} MiB",
+            ahci.port,
+            (ahci.sectors * SECTOR_SIZE as u64) >> 20
+        );
+        if let Some(irq) = AHCI_IRQ {
+            crate::hal::enable_irq(irq);
+        }
+        Arc::new(AhciBlock {
+            port: ahci.port,
+            ahci: unsafe { UPIntrFreeCell::new(ahci) },
+            lock: MutexBlocking::new(),
+            done: Condvar::new(),
+        })
+    });
+}
+
+impl AhciBlock {
+    /// 探测 AHCI 控制器，没有可用的磁盘时返回 `None`
+    pub fn get() -> Option<Arc<Self>> {
+        AHCI_BLOCK.clone()
+    }
+
+    /// 在中转页上执行一次读写命令，`block_id` 以 `BLOCK_SZ` 为单位，`len` 为传输的字节数
+    fn transfer(&self, block_id: usize, len: usize, write: bool) -> bool {
+        let sectors_per_block = BLOCK_SZ / SECTOR_SIZE;
+        let lba = (block_id * sectors_per_block) as u64;
+        let count = (len / SECTOR_SIZE) as u16;
+        let command = if write {
+            ATA_WRITE_DMA_EXT
+        } else {
+            ATA_READ_DMA_EXT
+        };
+        if AHCI_IRQ.is_some() && current_task().is_some() {
+            // 发出命令与进入等待队列之间关中断，避免完成中断先于等待到来
+            let task_cx_ptr = self.ahci.exclusive_session(|ahci| {
+                ahci.prepare(command, lba, count, len, write);
+                IRQ_ERROR.store(false, Ordering::Relaxed);
+                ahci.issue();
+                self.done.wait_no_sched()
+            });
+            schedule(task_cx_ptr);
+            !IRQ_ERROR.load(Ordering::Relaxed) && self.ahci.exclusive_access().succeeded()
+        } else {
+            let ahci = self.ahci.exclusive_access();
+            ahci.prepare(command, lba, count, len, write);
+            ahci.issue();
+            ahci.poll_complete()
+        }
+    }
+
+    /// 把中转页中的数据依次拷贝到 `buf`
+    fn copy_from_bounce(&self, buf: &mut [u8]) {
+        let ahci = self.ahci.exclusive_access();
+        for (chunk, page) in buf.chunks_mut(PAGE_SIZE).zip(ahci.bounce.iter()) {
+            chunk.copy_from_slice(&page.ppn.get_bytes_array()[..chunk.len()]);
+        }
+    }
+
+    /// 把 `buf` 依次拷贝到中转页
+    fn copy_to_bounce(&self, buf: &[u8]) {
+        let ahci = self.ahci.exclusive_access();
+        for (chunk, page) in buf.chunks(PAGE_SIZE).zip(ahci.bounce.iter()) {
+            page.ppn.get_bytes_array()[..chunk.len()].copy_from_slice(chunk);
+        }
+    }
+}
+
+impl BlockDevice for AhciBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.read_blocks(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.write_blocks(block_id, buf);
+    }
+
+    /// 每条命令读取至多 `BOUNCE_PAGES` 页
+    fn read_blocks(&self, block_id: usize, buf: &mut [u8]) {
+        let blocks_per_cmd = BOUNCE_PAGES * PAGE_SIZE / BLOCK_SZ;
+        for (i, chunk) in buf.chunks_mut(blocks_per_cmd * BLOCK_SZ).enumerate() {
+            self.lock.lock();
+            let ok = self.transfer(block_id + i * blocks_per_cmd, chunk.len(), false);
+            self.copy_from_bounce(chunk);
+            self.lock.unlock();
+            assert!(ok, "Error when reading AHCI disk");
+        }
+    }
+
+    /// 每条命令写入至多 `BOUNCE_PAGES` 页
+    fn write_blocks(&self, block_id: usize, buf: &[u8]) {
+        let blocks_per_cmd = BOUNCE_PAGES * PAGE_SIZE / BLOCK_SZ;
+        for (i, chunk) in buf.chunks(blocks_per_cmd * BLOCK_SZ).enumerate() {
+            self.lock.lock();
+            self.copy_to_bounce(chunk);
+            let ok = self.transfer(block_id + i * blocks_per_cmd, chunk.len(), true);
+            self.lock.unlock();
+            assert!(ok, "Error when writing AHCI disk");
+        }
+    }
+}
+
+/// 处理 AHCI 中断，`irq` 不属于 AHCI 时返回 `false`
+pub fn handle_irq(irq: usize) -> bool {
+    if AHCI_IRQ != Some(irq) {
+        return false;
+    }
+    if let Some(block) = AHCI_BLOCK.as_ref() {
+        let port = block.port;
+        // 清除中断状态前记下是否出错，留给发起命令的任务检查
+        unsafe {
+            let status = read_volatile(port_reg(port, PX_IS));
+            if status & IS_TFES != 0 {
+                IRQ_ERROR.store(true, Ordering::Relaxed);
+            }
+            write_volatile(port_reg(port, PX_IS), status);
+            write_volatile(reg(HBA_IS), 1 << port);
+        }
+        // 唤醒等待的任务推迟到中断处理之外
+        let block = block.clone();
+        crate::workqueue::schedule_work(move || block.done.signal());
+    }
+    true
+}
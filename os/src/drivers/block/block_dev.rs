@@ -1,6 +1,26 @@
+use crate::hal::BLOCK_SZ;
 use core::any::Any;
 
 pub trait BlockDevice: Send + Sync + Any {
     fn read_block(&self, block_id: usize, buf: &mut [u8]);
     fn write_block(&self, block_id: usize, buf: &[u8]);
+
+    /// 从 `block_id` 开始读取连续的多个块，`buf` 的长度是 `BLOCK_SZ` 的整数倍
+    ///
+    /// 默认逐块读取，驱动可以覆盖它以一条命令完成
+    fn read_blocks(&self, block_id: usize, buf: &mut [u8]) {
+        for (i, chunk) in buf.chunks_mut(BLOCK_SZ).enumerate() {
+            self.read_block(block_id + i, chunk);
+        }
+    }
+
+    /// 从 `block_id` 开始写入连续的多个块，`buf` 的长度是 `BLOCK_SZ` 的整数倍
+    fn write_blocks(&self, block_id: usize, buf: &[u8]) {
+        for (i, chunk) in buf.chunks(BLOCK_SZ).enumerate() {
+            self.write_block(block_id + i, chunk);
+        }
+    }
+
+    /// 把缓冲在设备一侧的写请求写入设备，默认没有缓冲
+    fn flush(&self) {}
 }
@@ -1,11 +1,81 @@
+#[cfg(feature = "board_2k1000")]
+pub(crate) mod ahci;
 pub mod block_dev;
-mod virtio_blk_mmio;
+pub mod partition;
+pub mod request_queue;
+pub(crate) mod virtio_blk_mmio;
 
 use alloc::sync::Arc;
 use block_dev::BlockDevice;
 use lazy_static::lazy_static;
-use virtio_blk_mmio::VirtIOBlock;
+use request_queue::RequestQueue;
 
 lazy_static! {
-    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(VirtIOBlock::new());
+    /// 整盘设备，分区设备共用同一个底层磁盘
+    static ref DISK: Arc<dyn BlockDevice> = disk();
+    /// 根文件系统所在的块设备（整盘镜像上的第一个 FAT 分区，或没有分区表时的整盘），
+    /// 经由请求队列访问，见 `request_queue`
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> =
+        Arc::new(RequestQueue::new(partition::root_device(DISK.clone())));
+}
+
+#[cfg(feature = "swap")]
+lazy_static! {
+    /// 交换设备：磁盘上的第一个 Linux 交换分区，没有时为额外的 VirtIO 块设备
+    pub static ref SWAP_DEVICE: Option<Arc<dyn BlockDevice>> = swap_device();
+}
+
+#[cfg(feature = "swap")]
+fn swap_device() -> Option<Arc<dyn BlockDevice>> {
+    if let Some(part) = partition::swap_partition(&DISK) {
+        return Some(part);
+    }
+    extra_virtio_disk()
+}
+
+/// 平台 virtio-mmio 槽位上的第一个块设备（根磁盘之外的 VirtIO 块设备）
+#[cfg(all(feature = "swap", not(feature = "board_2k1000")))]
+fn extra_virtio_disk() -> Option<Arc<dyn BlockDevice>> {
+    use virtio_drivers::{DeviceType, VirtIOHeader};
+    crate::boot::virtio_mmio_slots()
+        .into_iter()
+        .find_map(|(base, _)| {
+            if base == virtio_blk_mmio::VIRTIO0 {
+                return None;
+            }
+            let header = unsafe { &*(crate::mm::phys_to_virt(base) as *const VirtIOHeader) };
+            if !header.verify() || !matches!(header.device_type(), DeviceType::Block) {
+                return None;
+            }
+            let dev = virtio_blk_mmio::VirtIOBlock::with_base(base)?;
+            Some(Arc::new(dev) as Arc<dyn BlockDevice>)
+        })
+}
+
+#[cfg(all(feature = "swap", feature = "board_2k1000"))]
+fn extra_virtio_disk() -> Option<Arc<dyn BlockDevice>> {
+    None
+}
+
+/// 整盘设备：2K1000 开发板上为 AHCI 磁盘，其余平台为 VirtIO 块设备
+#[cfg(feature = "board_2k1000")]
+fn disk() -> Arc<dyn BlockDevice> {
+    ahci::AhciBlock::get().expect("no AHCI disk found")
+}
+
+#[cfg(not(feature = "board_2k1000"))]
+fn disk() -> Arc<dyn BlockDevice> {
+    Arc::new(virtio_blk_mmio::VirtIOBlock::new())
+}
+
+/// 处理块设备中断，`irq` 不属于任何块设备时返回 `false`
+#[cfg(feature = "board_2k1000")]
+pub fn handle_irq(irq: usize) -> bool {
+    ahci::handle_irq(irq)
+}
+
+/// VirtIO 块设备以轮询方式访问，不处理中断
+#[cfg(not(feature = "board_2k1000"))]
+pub fn handle_irq(_irq: usize) -> bool {
+    false
 }
@@ -0,0 +1,283 @@
+//! # 分区表解析
+//!
+//! ## Overview
+//! 解析整盘镜像上的 MBR / GPT 分区表，把每个分区包装成一个独立的 `BlockDevice`：
+//! - `Partition` 只做块号偏移与越界检查，读写直接转发给底层磁盘
+//! - `root_device` 选出根文件系统所在的设备：启动参数 `root=` 指定的分区，默认为第一个 FAT 分区
+//! - `swap_partition` 找出第一个 Linux 交换分区，供换页子系统使用
+//!
+//! 若磁盘的 0 号扇区本身就是 FAT 引导扇区（没有分区表的裸文件系统镜像），
+//! 则整盘作为根设备，行为与引入分区表之前一致。
+//!
+//! ## Assumptions
+//! - 分区表中的 LBA 以 512 字节扇区为单位
+//! - 分区起始扇区按 `BLOCK_SZ` 对齐，未对齐的分区会被忽略
+//! - 分区表只在启动时读取一次，且直接读盘、不经过块缓存
+//!
+//! ## Invariants
+//! - 分区设备上的块号 `block_id` 映射到磁盘上的 `start + block_id`，且 `block_id < blocks`
+
+use super::block_dev::BlockDevice;
+use crate::hal::BLOCK_SZ;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 分区表使用的扇区大小
+const SECTOR_SIZE: usize = 512;
+/// 每个块包含的扇区数
+const SECTORS_PER_BLOCK: usize = BLOCK_SZ / SECTOR_SIZE;
+
+/// MBR 分区表项的起始偏移与个数
+const MBR_TABLE_OFFSET: usize = 446;
+const MBR_ENTRIES: usize = 4;
+/// GPT 保护性 MBR 的分区类型
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xEE;
+/// 可能承载 FAT 文件系统的 MBR 分区类型
+const MBR_FAT_TYPES: &[u8] = &[0x01, 0x04, 0x06, 0x0B, 0x0C, 0x0E, 0xEF];
+/// Linux 交换分区的 MBR 分区类型
+const MBR_TYPE_LINUX_SWAP: u8 = 0x82;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// GPT 表项最多解析的数量，避免损坏的表头导致过量读盘
+const GPT_MAX_ENTRIES: usize = 128;
+/// Microsoft Basic Data（EBD0A0A2-B9E5-4433-87C0-68B6B72699C7），按磁盘上的字节序
+const GPT_TYPE_BASIC_DATA: [u8; 16] = [
+    0xA2, 0xA0, 0xD0, 0xEB, 0xE5, 0xB9, 0x33, 0x44, 0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7,
+];
+/// EFI System Partition（C12A7328-F81F-11D2-BA4B-00A0C93EC93B），按磁盘上的字节序
+const GPT_TYPE_EFI_SYSTEM: [u8; 16] = [
+    0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B,
+];
+/// Linux Swap（0657FD6D-A4AB-43C4-84E5-0933C84B4F4F），按磁盘上的字节序
+const GPT_TYPE_LINUX_SWAP: [u8; 16] = [
+    0x6D, 0xFD, 0x57, 0x06, 0xAB, 0xA4, 0xC4, 0x43, 0x84, 0xE5, 0x09, 0x33, 0xC8, 0x4B, 0x4F, 0x4F,
+];
+
+/// 分区的用途，由分区类型判断
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PartitionKind {
+    /// 可能承载 FAT 文件系统
+    Fat,
+    /// Linux 交换分区
+    Swap,
+    Other,
+}
+
+/// 磁盘上的一个分区
+pub struct Partition {
+    disk: Arc<dyn BlockDevice>,
+    /// 起始块号（以 `BLOCK_SZ` 为单位）
+    start: usize,
+    /// 分区包含的块数
+    blocks: usize,
+    /// 分区用途
+    kind: PartitionKind,
+}
+
+impl Partition {
+    pub fn start_block(&self) -> usize {
+        self.start
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks
+    }
+
+    pub fn is_fat(&self) -> bool {
+        self.kind == PartitionKind::Fat
+    }
+}
+
+impl BlockDevice for Partition {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert!(block_id < self.blocks, "read beyond partition end");
+        self.disk.read_block(self.start + block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert!(block_id < self.blocks, "write beyond partition end");
+        self.disk.write_block(self.start + block_id, buf);
+    }
+
+    fn read_blocks(&self, block_id: usize, buf: &mut [u8]) {
+        assert!(
+            block_id + buf.len() / BLOCK_SZ <= self.blocks,
+            "read beyond partition end"
+        );
+        self.disk.read_blocks(self.start + block_id, buf);
+    }
+
+    fn write_blocks(&self, block_id: usize, buf: &[u8]) {
+        assert!(
+            block_id + buf.len() / BLOCK_SZ <= self.blocks,
+            "write beyond partition end"
+        );
+        self.disk.write_blocks(self.start + block_id, buf);
+    }
+
+    fn flush(&self) {
+        self.disk.flush();
+    }
+}
+
+/// 读取 `lba` 号扇区
+fn read_sector(disk: &Arc<dyn BlockDevice>, lba: u64) -> [u8; SECTOR_SIZE] {
+    let lba = lba as usize;
+    let mut block = vec![0u8; BLOCK_SZ];
+    disk.read_block(lba / SECTORS_PER_BLOCK, &mut block);
+    let offset = (lba % SECTORS_PER_BLOCK) * SECTOR_SIZE;
+    let mut sector = [0u8; SECTOR_SIZE];
+    sector.copy_from_slice(&block[offset..offset + SECTOR_SIZE]);
+    sector
+}
+
+fn le_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn le_u64(buf: &[u8], offset: usize) -> u64 {
+    le_u32(buf, offset) as u64 | ((le_u32(buf, offset + 4) as u64) << 32)
+}
+
+/// 0 号扇区是否为 FAT 引导扇区（无分区表的裸文件系统）
+fn is_fat_boot_sector(sector: &[u8; SECTOR_SIZE]) -> bool {
+    let jump = sector[0] == 0xEB || sector[0] == 0xE9;
+    jump && (&sector[82..87] == b"FAT32" || &sector[54..57] == b"FAT")
+}
+
+/// 按扇区范围构造分区，未按块对齐或长度为 0 时返回 `None`
+fn make_partition(
+    disk: &Arc<dyn BlockDevice>,
+    first_lba: u64,
+    sectors: u64,
+    kind: PartitionKind,
+) -> Option<Arc<Partition>> {
+    let (first_lba, sectors) = (first_lba as usize, sectors as usize);
+    if sectors == 0 {
+        return None;
+    }
+    if first_lba % SECTORS_PER_BLOCK != 0 {
+        println!(
+            "[kernel] partition at LBA {} is not aligned to {} bytes, ignored",
+            first_lba, BLOCK_SZ
+        );
+        return None;
+    }
+    Some(Arc::new(Partition {
+        disk: disk.clone(),
+        start: first_lba / SECTORS_PER_BLOCK,
+        blocks: sectors / SECTORS_PER_BLOCK,
+        kind,
+    }))
+}
+
+fn parse_gpt(disk: &Arc<dyn BlockDevice>) -> Vec<Arc<Partition>> {
+    let header = read_sector(disk, 1);
+    if &header[..8] != GPT_SIGNATURE {
+        return Vec::new();
+    }
+    let entries_lba = le_u64(&header, 72);
+    let entries = (le_u32(&header, 80) as usize).min(GPT_MAX_ENTRIES);
+    let entry_size = le_u32(&header, 84) as usize;
+    if entry_size < 128 || entry_size > SECTOR_SIZE || SECTOR_SIZE % entry_size != 0 {
+        return Vec::new();
+    }
+    let per_sector = SECTOR_SIZE / entry_size;
+    let mut partitions = Vec::new();
+    for i in 0..entries {
+        let sector = read_sector(disk, entries_lba + (i / per_sector) as u64);
+        let entry = &sector[(i % per_sector) * entry_size..][..entry_size];
+        let type_guid = &entry[..16];
+        if type_guid.iter().all(|&b| b == 0) {
+            continue;
+        }
+        let first = le_u64(entry, 32);
+        let last = le_u64(entry, 40);
+        if last < first {
+            continue;
+        }
+        let kind = if type_guid == GPT_TYPE_BASIC_DATA || type_guid == GPT_TYPE_EFI_SYSTEM {
+            PartitionKind::Fat
+        } else if type_guid == GPT_TYPE_LINUX_SWAP {
+            PartitionKind::Swap
+        } else {
+            PartitionKind::Other
+        };
+        if let Some(part) = make_partition(disk, first, last - first + 1, kind) {
+            partitions.push(part);
+        }
+    }
+    partitions
+}
+
+/// 解析磁盘上的分区表，返回全部分区
+///
+/// 没有可识别的分区表时返回空表
+pub fn scan_partitions(disk: &Arc<dyn BlockDevice>) -> Vec<Arc<Partition>> {
+    let mbr = read_sector(disk, 0);
+    if mbr[510] != 0x55 || mbr[511] != 0xAA || is_fat_boot_sector(&mbr) {
+        return Vec::new();
+    }
+    let mut partitions = Vec::new();
+    for i in 0..MBR_ENTRIES {
+        let entry = &mbr[MBR_TABLE_OFFSET + i * 16..][..16];
+        let kind = entry[4];
+        if kind == MBR_TYPE_GPT_PROTECTIVE {
+            return parse_gpt(disk);
+        }
+        if kind == 0 {
+            continue;
+        }
+        let first = le_u32(entry, 8) as u64;
+        let sectors = le_u32(entry, 12) as u64;
+        let kind = if MBR_FAT_TYPES.contains(&kind) {
+            PartitionKind::Fat
+        } else if kind == MBR_TYPE_LINUX_SWAP {
+            PartitionKind::Swap
+        } else {
+            PartitionKind::Other
+        };
+        if let Some(part) = make_partition(disk, first, sectors, kind) {
+            partitions.push(part);
+        }
+    }
+    partitions
+}
+
+/// 选出根文件系统所在的块设备：`root=` 指定的分区，否则为第一个 FAT 分区，没有分区表时为整盘
+pub fn root_device(disk: Arc<dyn BlockDevice>) -> Arc<dyn BlockDevice> {
+    let partitions = scan_partitions(&disk);
+    for (i, part) in partitions.iter().enumerate() {
+        println!(
+            "[kernel] partition {}: start block {}, {} blocks{}",
+            i,
+            part.start_block(),
+            part.block_count(),
+            match part.kind {
+                PartitionKind::Fat => ", FAT",
+                PartitionKind::Swap => ", swap",
+                PartitionKind::Other => "",
+            }
+        );
+    }
+    // 启动参数 root= 指定了分区时优先使用
+    if let Some(number) = crate::boot::root_partition() {
+        match number.checked_sub(1).and_then(|i| partitions.get(i)) {
+            Some(part) => return part.clone(),
+            None => println!("[kernel] root partition {} not found", number),
+        }
+    }
+    match partitions.into_iter().find(|part| part.is_fat()) {
+        Some(part) => part,
+        None => disk,
+    }
+}
+
+/// 磁盘上的第一个 Linux 交换分区
+#[cfg(feature = "swap")]
+pub fn swap_partition(disk: &Arc<dyn BlockDevice>) -> Option<Arc<Partition>> {
+    scan_partitions(disk)
+        .into_iter()
+        .find(|part| part.kind == PartitionKind::Swap)
+}
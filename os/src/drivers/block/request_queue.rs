@@ -0,0 +1,197 @@
+//! # 块 I/O 请求队列
+//!
+//! ## Overview
+//! 位于文件系统与块设备驱动之间，把提交的块读写请求排序、合并之后再交给驱动：
+//! - `submit`：异步提交一个请求，请求完成时调用它的完成回调
+//! - `unplug`：把积攒的请求按电梯顺序派发给驱动
+//! - `RequestQueue` 本身也实现了 `BlockDevice`：写请求只入队，在之后的派发中才写入设备；
+//!   读请求先派发积攒的请求，再直接读设备
+//!
+//! ## Design
+//! - 电梯算法为 C-LOOK：从上一次派发结束的位置开始按块号递增派发，
+//!   到达最大的块号后回到最小的块号，磁头只沿一个方向移动
+//! - 排序是稳定的，同一块上的多个请求保持提交顺序，读总能看到之前提交的写
+//! - 块号连续、方向相同的请求合并为一次 `read_blocks` / `write_blocks`，一次至多 `MAX_MERGE` 块
+//! - 积攒的请求达到 `MAX_PENDING` 个、读请求到来或 `flush` 时派发
+//! - 派发期间持有队列的 `SleepLock`，派发是串行的，完成回调也在持锁时调用
+//!
+//! ## Assumptions
+//! - 驱动以同步方式完成请求，“异步”指提交与完成解耦：写请求在之后的某次派发中才真正写入，
+//!   完成回调在派发它的任务中调用
+//! - 驱动覆盖 `read_blocks` / `write_blocks`，以一条命令（AHCI）或一批同时提交的请求（VirtIO）
+//!   完成合并后的请求
+//! - 完成回调不能再向同一个队列提交请求
+//! - 交换设备不经过请求队列
+//!
+//! ## Invariants
+//! - 每个请求的完成回调恰好被调用一次
+//! - 派发顺序中，同一块上的请求保持提交顺序
+
+use super::block_dev::BlockDevice;
+use crate::hal::BLOCK_SZ;
+use crate::sync::SleepLock;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 积攒的请求达到该数目时派发
+const MAX_PENDING: usize = 32;
+/// 一次合并的最大块数
+pub(crate) const MAX_MERGE: usize = 16;
+
+/// 请求的方向
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BioOp {
+    Read,
+    Write,
+}
+
+/// 完成回调，参数为请求的缓冲区，读请求中为读到的数据
+pub type BioCallback = Box<dyn FnOnce(Vec<u8>) + Send>;
+
+/// 一个块读写请求
+pub struct Bio {
+    op: BioOp,
+    block_id: usize,
+    /// 长度为 `BLOCK_SZ` 的缓冲区
+    buf: Vec<u8>,
+    done: Option<BioCallback>,
+}
+
+impl Bio {
+    /// 读 `block_id` 号块，完成时以读到的数据调用 `done`
+    pub fn read(block_id: usize, done: BioCallback) -> Self {
+        Self {
+            op: BioOp::Read,
+            block_id,
+            buf: vec![0u8; BLOCK_SZ],
+            done: Some(done),
+        }
+    }
+
+    /// 把 `data` 写入 `block_id` 号块
+    pub fn write(block_id: usize, data: &[u8]) -> Self {
+        Self {
+            op: BioOp::Write,
+            block_id,
+            buf: data.to_vec(),
+            done: None,
+        }
+    }
+}
+
+struct QueueInner {
+    /// 尚未派发的请求，按提交顺序排列
+    pending: Vec<Bio>,
+    /// 上一次派发结束的位置（块号），C-LOOK 从这里继续
+    head: usize,
+}
+
+/// 块设备上的请求队列
+pub struct RequestQueue {
+    device: Arc<dyn BlockDevice>,
+    inner: SleepLock<QueueInner>,
+}
+
+impl RequestQueue {
+    pub fn new(device: Arc<dyn BlockDevice>) -> Self {
+        Self {
+            device,
+            inner: SleepLock::new(QueueInner {
+                pending: Vec::new(),
+                head: 0,
+            }),
+        }
+    }
+
+    /// 提交一个请求，积攒的请求达到 `MAX_PENDING` 个时立即派发
+    pub fn submit(&self, bio: Bio) {
+        assert_eq!(bio.buf.len(), BLOCK_SZ);
+        let mut inner = self.inner.lock();
+        inner.pending.push(bio);
+        if inner.pending.len() >= MAX_PENDING {
+            inner.dispatch(&self.device);
+        }
+    }
+
+    /// 派发所有积攒的请求
+    pub fn unplug(&self) {
+        self.inner.lock().dispatch(&self.device);
+    }
+}
+
+impl QueueInner {
+    /// 按 C-LOOK 顺序派发积攒的请求并调用完成回调
+    fn dispatch(&mut self, device: &Arc<dyn BlockDevice>) {
+        let mut bios = core::mem::take(&mut self.pending);
+        let head = self.head;
+        // 先派发不小于磁头位置的请求，再回到最小的块号
+        bios.sort_by_key(|bio| (bio.block_id < head, bio.block_id));
+        let mut start = 0;
+        while start < bios.len() {
+            let mut end = start + 1;
+            while end < bios.len()
+                && end - start < MAX_MERGE
+                && bios[end].op == bios[start].op
+                && bios[end].block_id == bios[end - 1].block_id + 1
+            {
+                end += 1;
+            }
+            dispatch_run(device, &mut bios[start..end]);
+            self.head = bios[end - 1].block_id + 1;
+            start = end;
+        }
+        for bio in bios {
+            if let Some(done) = bio.done {
+                done(bio.buf);
+            }
+        }
+    }
+}
+
+/// 以一次设备访问完成块号连续、方向相同的一组请求
+fn dispatch_run(device: &Arc<dyn BlockDevice>, run: &mut [Bio]) {
+    let first = run[0].block_id;
+    if let [bio] = run {
+        match bio.op {
+            BioOp::Read => device.read_block(first, &mut bio.buf),
+            BioOp::Write => device.write_block(first, &bio.buf),
+        }
+        return;
+    }
+    let mut buf = vec![0u8; run.len() * BLOCK_SZ];
+    match run[0].op {
+        BioOp::Read => {
+            device.read_blocks(first, &mut buf);
+            for (bio, chunk) in run.iter_mut().zip(buf.chunks(BLOCK_SZ)) {
+                bio.buf.copy_from_slice(chunk);
+            }
+        }
+        BioOp::Write => {
+            for (bio, chunk) in run.iter().zip(buf.chunks_mut(BLOCK_SZ)) {
+                chunk.copy_from_slice(&bio.buf);
+            }
+            device.write_blocks(first, &buf);
+        }
+    }
+}
+
+impl BlockDevice for RequestQueue {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        // 先派发积攒的写请求，读总能看到之前提交的写
+        let mut inner = self.inner.lock();
+        inner.dispatch(&self.device);
+        self.device.read_block(block_id, buf);
+        inner.head = block_id + 1;
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.submit(Bio::write(block_id, buf));
+    }
+
+    fn flush(&self) {
+        self.unplug();
+        self.device.flush();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::drivers::block::block_dev::BlockDevice;
-use crate::hal::PageTableImpl;
+use crate::hal::{PageTableImpl, BLOCK_SZ};
 use crate::mm;
 use crate::mm::{
     frame_alloc_more, frame_dealloc, kernel_token, FrameTracker, PageTable, StepByOne,
@@ -7,9 +7,10 @@ use crate::mm::{
 use crate::sync::UPIntrFreeCell;
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
-use virtio_drivers::VirtIOBlk;
+use virtio_drivers::{BlkResp, RespStatus, VirtIOBlk};
 
-const VIRTIO0: usize = 0x10001000;
+/// 根磁盘所在的 VirtIO 槽位
+pub const VIRTIO0: usize = 0x10001000;
 
 pub struct VirtIOBlock(UPIntrFreeCell<VirtIOBlk<'static, VirtIOHal>>);
 
@@ -27,16 +28,77 @@ impl BlockDevice for VirtIOBlock {
             .write_block(block_id, buf)
             .expect("Error when writing VirtIOBlk");
     }
+
+    /// 把连续的块作为一批请求同时放入虚拟队列，设备一次处理整批请求
+    fn read_blocks(&self, block_id: usize, buf: &mut [u8]) {
+        let mut blk = self.0.exclusive_access();
+        let batch = batch_size(&blk);
+        for (i, group) in buf.chunks_mut(batch * BLOCK_SZ).enumerate() {
+            let first = block_id + i * batch;
+            let mut resps = new_resps(group.len() / BLOCK_SZ);
+            for (j, (chunk, resp)) in group.chunks_mut(BLOCK_SZ).zip(resps.iter_mut()).enumerate() {
+                unsafe { blk.read_block_nb(first + j, chunk, resp) }
+                    .expect("Error when reading VirtIOBlk");
+            }
+            wait_batch(&mut blk, &resps);
+        }
+    }
+
+    fn write_blocks(&self, block_id: usize, buf: &[u8]) {
+        let mut blk = self.0.exclusive_access();
+        let batch = batch_size(&blk);
+        for (i, group) in buf.chunks(batch * BLOCK_SZ).enumerate() {
+            let first = block_id + i * batch;
+            let mut resps = new_resps(group.len() / BLOCK_SZ);
+            for (j, (chunk, resp)) in group.chunks(BLOCK_SZ).zip(resps.iter_mut()).enumerate() {
+                unsafe { blk.write_block_nb(first + j, chunk, resp) }
+                    .expect("Error when writing VirtIOBlk");
+            }
+            wait_batch(&mut blk, &resps);
+        }
+    }
+}
+
+/// 一批最多同时放入虚拟队列的请求数：每个请求占用 3 个描述符
+fn batch_size(blk: &VirtIOBlk<'static, VirtIOHal>) -> usize {
+    (blk.virt_queue_size() as usize / 3).max(1)
+}
+
+fn new_resps(n: usize) -> Vec<BlkResp> {
+    (0..n).map(|_| BlkResp::default()).collect()
+}
+
+/// 轮询等待一批请求全部完成，请求的缓冲区与 `resps` 在此之前不能释放
+fn wait_batch(blk: &mut VirtIOBlk<'static, VirtIOHal>, resps: &[BlkResp]) {
+    let mut done = 0;
+    while done < resps.len() {
+        if blk.pop_used().is_ok() {
+            done += 1;
+        } else {
+            core::hint::spin_loop();
+        }
+    }
+    for resp in resps {
+        assert_eq!(
+            resp.status(),
+            RespStatus::Ok,
+            "Error when accessing VirtIOBlk"
+        );
+    }
 }
 
 impl VirtIOBlock {
     #[allow(unused)]
     pub fn new() -> Self {
+        Self::with_base(VIRTIO0).unwrap()
+    }
+
+    /// 在 MMIO 物理地址 `base` 上初始化 VirtIO 块设备，该处不是可用的块设备时返回 `None`
+    pub fn with_base(base: usize) -> Option<Self> {
+        let header = mm::phys_to_virt(base) as *mut virtio_drivers::VirtIOHeader;
         unsafe {
-            Self(UPIntrFreeCell::new(
-                VirtIOBlk::<VirtIOHal>::new(&mut *(VIRTIO0 as *mut virtio_drivers::VirtIOHeader))
-                    .unwrap(),
-            ))
+            let blk = VirtIOBlk::<VirtIOHal>::new(&mut *header).ok()?;
+            Some(Self(UPIntrFreeCell::new(blk)))
         }
     }
 }
@@ -46,7 +108,7 @@ lazy_static! {
         unsafe { UPIntrFreeCell::new(Vec::new()) };
 }
 
-pub struct VirtIOHal;
+pub(crate) struct VirtIOHal;
 
 impl virtio_drivers::Hal for VirtIOHal {
     fn dma_alloc(pages: usize) -> virtio_drivers::PhysAddr {
@@ -69,7 +131,7 @@ impl virtio_drivers::Hal for VirtIOHal {
     }
 
     fn phys_to_virt(paddr: virtio_drivers::PhysAddr) -> virtio_drivers::VirtAddr {
-        paddr.into()
+        mm::phys_to_virt(paddr)
     }
 
     fn virt_to_phys(vaddr: virtio_drivers::VirtAddr) -> virtio_drivers::PhysAddr {
@@ -0,0 +1,90 @@
+//! # LS2K1000 传统 I/O 中断控制器（liointc）
+//!
+//! ## Overview
+//! 把片上外设的中断路由到 0 号核的 INT0 引脚（处理器的 HWI0 中断线）：
+//! - `enable`：设置中断源为高电平触发，路由到 0 号核的 INT0 并使能
+//! - `disable`：关闭中断源
+//! - `claim`：返回一个已使能且待处理的中断号
+//!
+//! ## Design
+//! - 控制器分为两组，每组 32 个中断源，第二组的寄存器紧随第一组之后（`+ 0x40`），
+//!   中断号 `irq` 位于第 `irq / 32` 组的第 `irq % 32` 位
+//! - 每个中断源有一个字节的路由寄存器：低 4 位选择处理器核，高 4 位选择核上的 INT0 ~ INT3 引脚
+//! - 只使用电平触发，中断源在设备清除中断条件后自动撤销，无需向控制器确认完成
+//!
+//! ## Assumptions
+//! - 内核只在 0 号核上处理外部中断
+//! - 寄存器基址为平台的 `LIOINTC_BASE`，位于窗口映射的设备地址空间中
+//!
+//! ## Invariants
+//! - 已使能的中断源都路由到 0 号核的 INT0，HWI0 中断线上的中断都来自 liointc
+
+use crate::hal::platform::LIOINTC_BASE;
+use core::ptr::{read_volatile, write_volatile};
+
+/// 每组的中断源数
+const IRQS_PER_BANK: usize = 32;
+/// 组数
+const BANKS: usize = 2;
+/// 相邻两组寄存器的间距
+const BANK_STRIDE: usize = 0x40;
+
+/// 中断状态（已使能且待处理）
+const INTISR: usize = 0x20;
+/// 使能状态
+const INTEN: usize = 0x24;
+/// 写 1 使能
+const INTENSET: usize = 0x28;
+/// 写 1 关闭
+const INTENCLR: usize = 0x2c;
+/// 极性，0 为高电平或上升沿
+const INTPOL: usize = 0x30;
+/// 触发方式，0 为电平触发
+const INTEDGE: usize = 0x34;
+
+/// 路由目标：0 号核
+const ROUTE_CORE0: u8 = 1 << 0;
+/// 路由目标：核上的 INT0 引脚，对应处理器的 HWI0
+const ROUTE_INT0: u8 = 1 << 4;
+
+fn bank_base(irq: usize) -> usize {
+    LIOINTC_BASE + irq / IRQS_PER_BANK * BANK_STRIDE
+}
+
+fn read_reg(irq: usize, offset: usize) -> u32 {
+    unsafe { read_volatile((bank_base(irq) + offset) as *const u32) }
+}
+
+fn write_reg(irq: usize, offset: usize, value: u32) {
+    unsafe { write_volatile((bank_base(irq) + offset) as *mut u32, value) }
+}
+
+/// 在 `offset` 处的寄存器中清除 `irq` 对应的位
+fn clear_bit(irq: usize, offset: usize) {
+    let value = read_reg(irq, offset) & !(1 << (irq % IRQS_PER_BANK));
+    write_reg(irq, offset, value);
+}
+
+/// 把中断源 `irq` 设置为高电平触发，路由到 0 号核的 INT0 并使能
+pub fn enable(irq: usize) {
+    assert!(irq < IRQS_PER_BANK * BANKS, "liointc: bad irq {}", irq);
+    let route = (bank_base(irq) + irq % IRQS_PER_BANK) as *mut u8;
+    unsafe { write_volatile(route, ROUTE_CORE0 | ROUTE_INT0) };
+    clear_bit(irq, INTEDGE);
+    clear_bit(irq, INTPOL);
+    write_reg(irq, INTENSET, 1 << (irq % IRQS_PER_BANK));
+}
+
+/// 关闭中断源 `irq`
+pub fn disable(irq: usize) {
+    write_reg(irq, INTENCLR, 1 << (irq % IRQS_PER_BANK));
+}
+
+/// 返回一个已使能且待处理的中断号，没有时返回 `None`
+pub fn claim() -> Option<usize> {
+    (0..BANKS).find_map(|bank| {
+        let first = bank * IRQS_PER_BANK;
+        let pending = read_reg(first, INTISR) & read_reg(first, INTEN);
+        (pending != 0).then(|| first + pending.trailing_zeros() as usize)
+    })
+}
@@ -0,0 +1,10 @@
+//! # 中断控制器驱动（drivers::irqchip）
+//!
+//! ## Overview
+//! 开发板上的外部中断控制器，RISC-V 的 PLIC 属于体系结构的一部分，位于 `hal` 中：
+//! - `liointc`：LS2K1000 的传统 I/O 中断控制器，片上外设（UART、AHCI 等）的中断都经由它送往处理器核
+//!
+//! ## Assumptions
+//! - LS2K1000 没有扩展 I/O 中断控制器（eiointc），片上外设的中断全部经由 liointc 路由
+
+pub mod liointc;
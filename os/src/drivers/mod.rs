@@ -1,6 +1,19 @@
 mod block;
+#[cfg(feature = "board_2k1000")]
+pub mod irqchip;
+pub mod net;
+pub mod rtc;
 pub mod serial;
 
 pub use block::block_dev::BlockDevice;
 pub use block::BLOCK_DEVICE;
+#[cfg(feature = "swap")]
+pub use block::SWAP_DEVICE;
 pub use serial::ns16550a::Ns16550a;
+
+/// 外部中断分发入口，由体系结构相关的陷阱处理代码在领取中断号后调用
+pub fn irq_handler(irq: usize) {
+    if !serial::handle_irq(irq) && !block::handle_irq(irq) && !net::handle_irq(irq) {
+        println!("[kernel] unexpected external interrupt {}", irq);
+    }
+}
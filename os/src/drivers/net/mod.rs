@@ -0,0 +1,100 @@
+//! # 网卡驱动（drivers::net）
+//!
+//! ## Overview
+//! 定义网卡设备的统一接口 `NetDevice`，并维护全局网卡注册表：
+//! - `init` 在启动时探测平台上的 virtio-mmio 槽位，把发现的 virtio-net 设备登记进来
+//! - 协议栈可以通过 `net_devices` 取得已登记的网卡并收发以太网帧；
+//!   目前的套接字只走环回，尚未接入网卡，`transmit` / `receive` 还没有调用者
+//! - 外部中断经 `handle_irq` 分发到中断号匹配的网卡：中断处理程序只应答设备，
+//!   收包（`poll_rx`）放入工作队列稍后执行
+//!
+//! ## Assumptions
+//! - 只支持 virtio-mmio 传输；`boot::virtio_mmio_slots` 为空时不会登记任何网卡
+//! - 目前只有 board_rvqemu 上能使用网卡：QEMU LoongArch virt 机器的 VirtIO 设备只能挂在 PCI 上，
+//!   而依赖的 virtio-drivers 版本没有 PCI 传输，board_laqemu 与 2K1000 开发板不在支持范围内
+//! - 接收到的帧先缓存在驱动内部，协议栈以非阻塞方式轮询取走
+//!
+//! ## Invariants
+//! - 网卡一经登记便不会注销，其编号即在注册表中的下标
+
+mod virtio_net;
+
+use crate::hal::enable_irq;
+use crate::sync::UPIntrFreeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use virtio_drivers::{DeviceType, VirtIOHeader};
+
+pub use virtio_net::VirtIONetDevice;
+
+/// 网卡设备接口，收发的都是完整的以太网帧
+pub trait NetDevice: Send + Sync {
+    /// 网卡的 MAC 地址
+    fn mac(&self) -> [u8; 6];
+    /// 发送一帧，发送队列已满时失败
+    #[allow(unused)]
+    fn transmit(&self, frame: &[u8]) -> Result<(), isize>;
+    /// 取出一帧到 `buf`，返回帧长度；没有待收取的帧时返回 `None`
+    #[allow(unused)]
+    fn receive(&self, buf: &mut [u8]) -> Option<usize>;
+    /// 网卡使用的外部中断号
+    fn irq(&self) -> usize;
+    /// 中断处理：应答设备
+    fn handle_irq(&self);
+    /// 把设备已收到的帧搬到驱动的接收队列，由中断处理推迟到工作队列中执行
+    fn poll_rx(&self);
+}
+
+lazy_static! {
+    /// 已登记的网卡
+    static ref NET_DEVICES: UPIntrFreeCell<Vec<Arc<dyn NetDevice>>> =
+        unsafe { UPIntrFreeCell::new(Vec::new()) };
+}
+
+/// 登记一块网卡并使能其中断，返回网卡编号
+pub fn register_net_device(dev: Arc<dyn NetDevice>) -> usize {
+    enable_irq(dev.irq());
+    let mut devices = NET_DEVICES.exclusive_access();
+    devices.push(dev);
+    devices.len() - 1
+}
+
+/// 所有已登记的网卡
+pub fn net_devices() -> Vec<Arc<dyn NetDevice>> {
+    NET_DEVICES.exclusive_access().clone()
+}
+
+/// 将中断号为 `irq` 的外部中断分发给对应网卡，返回是否有网卡处理了该中断
+pub fn handle_irq(irq: usize) -> bool {
+    let mut handled = false;
+    for dev in net_devices() {
+        if dev.irq() == irq {
+            dev.handle_irq();
+            crate::workqueue::schedule_work(move || dev.poll_rx());
+            handled = true;
+        }
+    }
+    handled
+}
+
+/// 探测并登记平台上的 virtio-net 设备
+pub fn init() {
+    for (base, irq) in crate::boot::virtio_mmio_slots() {
+        let header = unsafe { &*(crate::mm::phys_to_virt(base) as *const VirtIOHeader) };
+        if !header.verify() || !matches!(header.device_type(), DeviceType::Network) {
+            continue;
+        }
+        match VirtIONetDevice::new(base, irq) {
+            Some(dev) => {
+                let mac = dev.mac();
+                let id = register_net_device(Arc::new(dev));
+                println!(
+                    "[kernel] net{}: virtio-net at {:#x}, irq {}, mac {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                    id, base, irq, mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+                );
+            }
+            None => println!("[kernel] failed to initialize virtio-net at {:#x}", base),
+        }
+    }
+}
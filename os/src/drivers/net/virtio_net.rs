@@ -0,0 +1,78 @@
+use super::NetDevice;
+use crate::drivers::block::virtio_blk_mmio::VirtIOHal;
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use virtio_drivers::{VirtIOHeader, VirtIONet};
+
+/// 以太网帧的最大长度（含 virtio-net 头部预留）
+const NET_BUF_LEN: usize = 2048;
+/// 中断中收取、尚未被协议栈取走的帧的最大数量
+const RX_QUEUE_LIMIT: usize = 64;
+
+pub struct VirtIONetDevice {
+    net: UPIntrFreeCell<VirtIONet<'static, VirtIOHal>>,
+    /// 中断处理中从 RX 虚拟队列取出的帧
+    rx_frames: UPIntrFreeCell<VecDeque<Vec<u8>>>,
+    irq: usize,
+}
+
+impl VirtIONetDevice {
+    /// 在物理地址 `base` 处的 virtio-mmio 槽位上初始化网卡，`irq` 为其中断号
+    pub fn new(base: usize, irq: usize) -> Option<Self> {
+        let header = crate::mm::phys_to_virt(base) as *mut VirtIOHeader;
+        let net = VirtIONet::<VirtIOHal>::new(unsafe { &mut *header }).ok()?;
+        Some(Self {
+            net: unsafe { UPIntrFreeCell::new(net) },
+            rx_frames: unsafe { UPIntrFreeCell::new(VecDeque::new()) },
+            irq,
+        })
+    }
+}
+
+impl NetDevice for VirtIONetDevice {
+    fn mac(&self) -> [u8; 6] {
+        self.net.exclusive_access().mac()
+    }
+
+    fn transmit(&self, frame: &[u8]) -> Result<(), isize> {
+        let mut net = self.net.exclusive_access();
+        if !net.can_send() {
+            return Err(-1); // EAGAIN
+        }
+        net.send(frame).map_err(|_| -1)
+    }
+
+    fn receive(&self, buf: &mut [u8]) -> Option<usize> {
+        let frame = self.rx_frames.exclusive_access().pop_front()?;
+        let len = frame.len().min(buf.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+        Some(len)
+    }
+
+    fn irq(&self) -> usize {
+        self.irq
+    }
+
+    fn handle_irq(&self) {
+        self.net.exclusive_access().ack_interrupt();
+    }
+
+    fn poll_rx(&self) {
+        let mut net = self.net.exclusive_access();
+        while net.can_recv() {
+            let mut buf = vec![0u8; NET_BUF_LEN];
+            let len = match net.recv(&mut buf) {
+                Ok(len) => len,
+                Err(_) => break,
+            };
+            buf.truncate(len);
+            let mut rx_frames = self.rx_frames.exclusive_access();
+            // 协议栈来不及取走时丢弃最新的帧，与真实网卡的行为一致
+            if rx_frames.len() < RX_QUEUE_LIMIT {
+                rx_frames.push_back(buf);
+            }
+        }
+    }
+}
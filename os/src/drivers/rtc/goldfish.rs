@@ -0,0 +1,38 @@
+//! # goldfish-rtc 驱动
+//!
+//! QEMU RISC-V virt 机器上的实时时钟，`TIME_LOW` / `TIME_HIGH` 给出自 1970 年起的纳秒数。
+//! 读 `TIME_LOW` 时设备锁存高 32 位，随后读到的 `TIME_HIGH` 与之属于同一时刻，因此必须先读低位。
+
+use super::RtcDevice;
+use crate::mm::phys_to_virt;
+use crate::timer::NSEC_PER_SEC;
+use core::ptr::read_volatile;
+
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+
+pub struct GoldfishRtc {
+    /// 寄存器的内核虚拟地址
+    base: usize,
+}
+
+impl GoldfishRtc {
+    /// `base` 为寄存器的物理地址，需已映射到内核地址空间
+    pub fn new(base: usize) -> Self {
+        Self {
+            base: phys_to_virt(base),
+        }
+    }
+
+    fn read(&self, reg: usize) -> u32 {
+        unsafe { read_volatile((self.base + reg) as *const u32) }
+    }
+}
+
+impl RtcDevice for GoldfishRtc {
+    fn read_time(&self) -> u64 {
+        let low = self.read(TIME_LOW) as u64;
+        let high = self.read(TIME_HIGH) as u64;
+        ((high << 32) | low) / NSEC_PER_SEC as u64
+    }
+}
@@ -0,0 +1,62 @@
+//! # LS7A RTC 驱动
+//!
+//! 龙芯 LS7A 桥片（以及 2K1000 片上）的实时时钟。TOY（time of year）计数器以日历形式保存时间：
+//! - `TOY_READ0`：月、日、时、分、秒与 0.1 秒
+//! - `TOY_READ1`：自 1900 年起的年数
+//!
+//! 计数器需要在 `RTC_CTRL` 中同时打开晶振与 TOY 才会走时，创建驱动时打开。
+
+use super::{RtcDevice, RtcTime};
+use core::ptr::{read_volatile, write_volatile};
+
+const TOY_READ0: usize = 0x2c;
+const TOY_READ1: usize = 0x30;
+const RTC_CTRL: usize = 0x40;
+
+/// `RTC_CTRL` 中的晶振使能与 TOY 使能位
+const CTRL_OSC_EN: u32 = 1 << 8;
+const CTRL_TOY_EN: u32 = 1 << 11;
+
+pub struct Ls7aRtc {
+    /// 寄存器的（非缓存窗口）虚拟地址
+    base: usize,
+}
+
+impl Ls7aRtc {
+    pub fn new(base: usize) -> Self {
+        let rtc = Self { base };
+        let ctrl = rtc.read(RTC_CTRL);
+        rtc.write(RTC_CTRL, ctrl | CTRL_OSC_EN | CTRL_TOY_EN);
+        rtc
+    }
+
+    fn read(&self, reg: usize) -> u32 {
+        unsafe { read_volatile((self.base + reg) as *const u32) }
+    }
+
+    fn write(&self, reg: usize, value: u32) {
+        unsafe { write_volatile((self.base + reg) as *mut u32, value) }
+    }
+}
+
+/// 取出 `value` 中 `[low, low + width)` 位
+fn field(value: u32, low: u32, width: u32) -> i32 {
+    ((value >> low) & ((1 << width) - 1)) as i32
+}
+
+impl RtcDevice for Ls7aRtc {
+    fn read_time(&self) -> u64 {
+        let toy = self.read(TOY_READ0);
+        let year = self.read(TOY_READ1);
+        let time = RtcTime {
+            tm_sec: field(toy, 4, 6),
+            tm_min: field(toy, 10, 6),
+            tm_hour: field(toy, 16, 5),
+            tm_mday: field(toy, 21, 5),
+            tm_mon: field(toy, 26, 6) - 1,
+            tm_year: year as i32,
+            ..RtcTime::default()
+        };
+        time.to_unix()
+    }
+}
@@ -0,0 +1,159 @@
+//! # 实时时钟驱动（drivers::rtc）
+//!
+//! ## Overview
+//! 定义实时时钟的统一接口 `RtcDevice`，启动时探测平台上的 RTC：
+//! - `init` 找到 RTC 后读取当前日期时间，设置墙上时间的启动时刻
+//! - `rtc` 返回探测到的 RTC，供 `/dev/rtc` 的 `RTC_RD_TIME` 读取
+//! - `RtcTime` 是用户态的 `struct rtc_time`，与 Unix 时间互相换算
+//!
+//! ## Design
+//! - RISC-V QEMU virt 上为 goldfish-rtc，寄存器直接给出 Unix 纳秒数
+//! - LoongArch 上为 LS7A（2K1000 片上 RTC 布局相同），TOY 寄存器给出日历时间，按 UTC 换算
+//! - 日历与 Unix 时间的换算使用公历的纪元（400 年）算法，不依赖查表
+//!
+//! ## Assumptions
+//! - RTC 中保存的是 UTC 时间
+//! - 没有 RTC 时墙上时间从 1970-01-01 开始，可由 settimeofday 设置
+//!
+//! ## Invariants
+//! - RTC 只在 `init` 中探测一次，之后不会改变
+
+#[cfg(feature = "riscv")]
+mod goldfish;
+#[cfg(feature = "loongarch")]
+mod ls7a;
+
+use crate::timer::{set_boot_epoch, TimeSpec, NSEC_PER_SEC};
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+/// 实时时钟接口
+pub trait RtcDevice: Send + Sync {
+    /// 当前的 Unix 时间（秒）
+    fn read_time(&self) -> u64;
+}
+
+/// 用户态的 `struct rtc_time`，字段含义与 `struct tm` 相同
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RtcTime {
+    pub tm_sec: i32,
+    pub tm_min: i32,
+    pub tm_hour: i32,
+    pub tm_mday: i32,
+    /// 月份，0 ~ 11
+    pub tm_mon: i32,
+    /// 自 1900 年起的年数
+    pub tm_year: i32,
+    /// 星期，0 为星期日
+    pub tm_wday: i32,
+    /// 一年中的第几天，0 ~ 365
+    pub tm_yday: i32,
+    pub tm_isdst: i32,
+}
+
+const SECS_PER_DAY: i64 = 86400;
+
+impl RtcTime {
+    /// 由 Unix 时间（秒）换算为 UTC 日历时间
+    pub fn from_unix(secs: u64) -> Self {
+        let days = secs as i64 / SECS_PER_DAY;
+        let rem = secs as i64 % SECS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            tm_sec: (rem % 60) as i32,
+            tm_min: (rem / 60 % 60) as i32,
+            tm_hour: (rem / 3600) as i32,
+            tm_mday: day as i32,
+            tm_mon: month as i32 - 1,
+            tm_year: year as i32 - 1900,
+            // 1970-01-01 是星期四
+            tm_wday: (days + 4).rem_euclid(7) as i32,
+            tm_yday: (days - days_from_civil(year, 1, 1)) as i32,
+            tm_isdst: 0,
+        }
+    }
+
+    /// 换算为 Unix 时间（秒），早于 1970 年的时间按 0 处理
+    pub fn to_unix(&self) -> u64 {
+        let days = days_from_civil(
+            self.tm_year as i64 + 1900,
+            self.tm_mon as i64 + 1,
+            self.tm_mday as i64,
+        );
+        let secs = days * SECS_PER_DAY
+            + self.tm_hour as i64 * 3600
+            + self.tm_min as i64 * 60
+            + self.tm_sec as i64;
+        secs.max(0) as u64
+    }
+}
+
+/// 公历日期（月份 1 ~ 12）距 1970-01-01 的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // 以 3 月为一年的开始，闰日落在年末
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// 距 1970-01-01 的天数对应的公历日期 `(年, 月, 日)`，月份 1 ~ 12
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+lazy_static! {
+    /// 启动时探测到的 RTC
+    static ref RTC: Option<Arc<dyn RtcDevice>> = probe();
+}
+
+/// 探测到的 RTC，没有时返回 `None`
+pub fn rtc() -> Option<Arc<dyn RtcDevice>> {
+    RTC.clone()
+}
+
+/// 探测 RTC 并以其时间设置墙上时间的启动时刻，需在内核页表启用之后调用
+pub fn init() {
+    let Some(rtc) = rtc() else {
+        println!("[kernel] no RTC found, wall clock starts at the epoch");
+        return;
+    };
+    let now = rtc.read_time();
+    let boot_ns = (now as usize * NSEC_PER_SEC).saturating_sub(TimeSpec::now().to_ns());
+    set_boot_epoch(TimeSpec::from_ns(boot_ns));
+    let time = RtcTime::from_unix(now);
+    println!(
+        "[kernel] RTC: {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        time.tm_year + 1900,
+        time.tm_mon + 1,
+        time.tm_mday,
+        time.tm_hour,
+        time.tm_min,
+        time.tm_sec
+    );
+}
+
+#[cfg(feature = "riscv")]
+fn probe() -> Option<Arc<dyn RtcDevice>> {
+    let base = crate::boot::rtc_base()?;
+    Some(Arc::new(goldfish::GoldfishRtc::new(base)))
+}
+
+#[cfg(feature = "loongarch")]
+fn probe() -> Option<Arc<dyn RtcDevice>> {
+    let base = crate::hal::RTC_BASE?;
+    Some(Arc::new(ls7a::Ls7aRtc::new(base)))
+}
@@ -0,0 +1,66 @@
+//! # LS2K1000 串口的中断接收
+//!
+//! ## Overview
+//! 开发板的 UART0 由轮询改为中断驱动接收：
+//! - `init`：打开 UART 的接收中断，并经由 `hal::enable_irq` 在 liointc 上使能 `UART_IRQ`
+//! - `handle_irq`：在中断中读空 UART 的接收缓冲区，字符存入内核的接收队列
+//! - `getchar`：`console_getchar` 先从接收队列中取字符
+//!
+//! ## Design
+//! - 开发板上的 UART 接收 FIFO 只有 16 字节，粘贴或快速输入时若只靠行规程按需轮询会丢字符；
+//!   中断中及时取走字符，由接收队列缓冲到行规程读取为止
+//! - 发送仍然轮询 THRE，不使用发送中断
+//! - 接收队列满时丢弃新到的字符，与硬件 FIFO 溢出的行为一致
+//!
+//! ## Assumptions
+//! - 固件已设置好波特率与线路控制（LCR 中 DLAB 为 0），这里只修改 IER
+//!
+//! ## Invariants
+//! - 接收队列中的字符数不超过 `RX_CAPACITY`
+
+use super::ns16550a::Ns16550a;
+use crate::hal::platform::{UART_BASE, UART_IRQ};
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::VecDeque;
+use embedded_hal::serial::nb::Read;
+use lazy_static::lazy_static;
+
+/// 接收队列的容量
+const RX_CAPACITY: usize = 4096;
+
+lazy_static! {
+    /// 中断中收到、尚未被读取的字符
+    static ref RX_QUEUE: UPIntrFreeCell<VecDeque<u8>> =
+        unsafe { UPIntrFreeCell::new(VecDeque::with_capacity(RX_CAPACITY)) };
+}
+
+/// 打开 UART0 的接收中断
+pub fn init() {
+    let mut uart = Ns16550a::new(UART_BASE);
+    uart.enable_rx_interrupt();
+    crate::hal::enable_irq(UART_IRQ);
+    println!(
+        "[kernel] uart: interrupt-driven receive on irq {}",
+        UART_IRQ
+    );
+}
+
+/// 处理 UART0 的中断，`irq` 不是 `UART_IRQ` 时返回 `false`
+pub fn handle_irq(irq: usize) -> bool {
+    if irq != UART_IRQ {
+        return false;
+    }
+    let mut uart = Ns16550a::new(UART_BASE);
+    let mut queue = RX_QUEUE.exclusive_access();
+    while let Ok(c) = uart.read() {
+        if queue.len() < RX_CAPACITY {
+            queue.push_back(c);
+        }
+    }
+    true
+}
+
+/// 取出接收队列中的一个字符
+pub fn getchar() -> Option<u8> {
+    RX_QUEUE.exclusive_access().pop_front()
+}
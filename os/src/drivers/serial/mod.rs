@@ -1 +1,27 @@
+#[cfg(feature = "board_2k1000")]
+pub mod ls2k1000;
 pub mod ns16550a;
+#[cfg(feature = "riscv")]
+mod uart_console;
+mod virtio_console;
+
+/// 初始化串口：登记 MMIO 串口与 virtio-console 控制台后端，2K1000 开发板上改为中断驱动接收
+pub fn init() {
+    #[cfg(feature = "board_2k1000")]
+    ls2k1000::init();
+    #[cfg(feature = "riscv")]
+    uart_console::init();
+    virtio_console::init();
+}
+
+/// 处理串口中断，`irq` 不属于串口时返回 `false`
+#[cfg(feature = "board_2k1000")]
+pub fn handle_irq(irq: usize) -> bool {
+    ls2k1000::handle_irq(irq)
+}
+
+/// 其余平台的串口以轮询方式读取
+#[cfg(not(feature = "board_2k1000"))]
+pub fn handle_irq(_irq: usize) -> bool {
+    false
+}
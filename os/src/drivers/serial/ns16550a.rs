@@ -10,6 +10,11 @@ impl Ns16550a {
     pub fn new(base: usize) -> Self {
         Self { base }
     }
+
+    /// 使能接收中断：接收缓冲区中有数据时发出中断，读空后中断自动撤销
+    pub fn enable_rx_interrupt(&mut self) {
+        unsafe { write_volatile((self.base + offsets::IER) as *mut u8, masks::ERBFI) };
+    }
 }
 
 impl embedded_hal::serial::ErrorType for Ns16550a {
@@ -68,6 +73,8 @@ mod offsets {
 }
 
 mod masks {
+    /// IER：接收数据可用中断
+    pub const ERBFI: u8 = 1;
     pub const THRE: u8 = 1 << 5;
     pub const DR: u8 = 1;
 }
@@ -0,0 +1,53 @@
+//! # MMIO 串口控制台
+//!
+//! ## Overview
+//! 把 QEMU virt 上的 NS16550A 串口登记为控制台后端 `ttyS0`，不经过 SBI 直接读写寄存器
+//!
+//! ## Design
+//! - 逐字节写出，每个字节写入之前等待发送保持寄存器为空（THRE），不会覆盖尚未发出的字节
+//! - 输入轮询线路状态寄存器的 DR 位，不使用中断
+//!
+//! ## Assumptions
+//! - OpenSBI 已设置好串口的波特率；SBI 的控制台调用使用同一个串口，
+//!   因此一般只选择其中之一（`console=ttyS0`），否则内核输出会在串口上出现两次
+
+use super::ns16550a::Ns16550a;
+use crate::console::{register, ConsoleBackend};
+use crate::hal::platform::UART_BASE;
+use alloc::boxed::Box;
+use embedded_hal::serial::nb::{Read, Write};
+
+/// 直接读写寄存器的串口控制台
+pub struct UartConsole {
+    /// 寄存器基址（直接映射区中的虚拟地址）
+    base: usize,
+}
+
+impl ConsoleBackend for UartConsole {
+    fn name(&self) -> &str {
+        "ttyS0"
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut uart = Ns16550a::new(self.base);
+        for &byte in bytes {
+            while uart.flush().is_err() {}
+            let _ = uart.write(byte);
+        }
+    }
+
+    fn flush(&self) {
+        let mut uart = Ns16550a::new(self.base);
+        while uart.flush().is_err() {}
+    }
+
+    fn getchar(&self) -> Option<u8> {
+        Ns16550a::new(self.base).read().ok()
+    }
+}
+
+/// 登记平台的 MMIO 串口
+pub fn init() {
+    let base = crate::mm::phys_to_virt(UART_BASE);
+    register(Box::leak(Box::new(UartConsole { base })));
+}
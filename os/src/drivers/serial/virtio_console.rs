@@ -0,0 +1,64 @@
+//! # virtio-console 控制台
+//!
+//! ## Overview
+//! 探测 virtio-mmio 槽位上的 virtio-console 设备，把第一个登记为控制台后端 `hvc0`
+//!
+//! ## Design
+//! - 发送逐字节提交到发送队列并等待设备取走；接收轮询接收队列，不使用中断
+//! - 与其他 virtio 设备相同，使用块设备驱动中的 `VirtIOHal` 分配 DMA 内存
+//!
+//! ## Assumptions
+//! - 只使用设备的 0 号端口，不支持多端口（`VIRTIO_CONSOLE_F_MULTIPORT`）
+//! - QEMU 以 `-device virtio-serial-device -device virtconsole,chardev=...` 提供设备
+
+use crate::console::{register, ConsoleBackend};
+use crate::drivers::block::virtio_blk_mmio::VirtIOHal;
+use crate::sync::UPIntrFreeCell;
+use alloc::boxed::Box;
+use virtio_drivers::{DeviceType, VirtIOConsole, VirtIOHeader};
+
+/// virtio-console 控制台
+pub struct VirtIOConsoleDevice {
+    console: UPIntrFreeCell<VirtIOConsole<'static, VirtIOHal>>,
+}
+
+impl ConsoleBackend for VirtIOConsoleDevice {
+    fn name(&self) -> &str {
+        "hvc0"
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut console = self.console.exclusive_access();
+        for &byte in bytes {
+            let _ = console.send(byte);
+        }
+    }
+
+    fn getchar(&self) -> Option<u8> {
+        self.console.exclusive_access().recv(true).ok().flatten()
+    }
+}
+
+/// 探测并登记第一个 virtio-console 设备
+pub fn init() {
+    for (base, _) in crate::boot::virtio_mmio_slots() {
+        let header = crate::mm::phys_to_virt(base) as *mut VirtIOHeader;
+        let header = unsafe { &mut *header };
+        if !header.verify() || !matches!(header.device_type(), DeviceType::Console) {
+            continue;
+        }
+        let Ok(console) = VirtIOConsole::<VirtIOHal>::new(header) else {
+            println!(
+                "[kernel] failed to initialize virtio-console at {:#x}",
+                base
+            );
+            continue;
+        };
+        let dev = VirtIOConsoleDevice {
+            console: unsafe { UPIntrFreeCell::new(console) },
+        };
+        register(Box::leak(Box::new(dev)));
+        println!("[kernel] hvc0: virtio-console at {:#x}", base);
+        return;
+    }
+}
@@ -0,0 +1,177 @@
+//! # 故障注入
+//!
+//! ## Overview
+//! 启用 `fault_inject` feature 时，让下列操作按设定的概率或调用次数失败，用于检验错误处理路径：
+//! - `fail_page_alloc`：`frame_alloc` / `frame_alloc_more` 返回 `None`，调用者应返回 ENOMEM
+//! - `failslab`：内核堆分配返回空指针；不可失败的分配因此进入 `handle_alloc_error`
+//! - `fail_make_request`：FAT32 经块设备的读取返回 IO 错误，系统调用应返回 EIO
+//!
+//! 每个注入点由同名的启动参数设置，格式与 Linux 相同：
+//! `<name>=<interval>,<probability>,<space>,<times>`，例如 `fail_page_alloc=1,10,0,-1`。
+//! 当前设置与调用、失败次数可从 `/proc/fault_inject` 读取
+//!
+//! ## Design
+//! - 与 Linux 的 `should_fail` 相同，依次检查：
+//!   1. `times` 为 0 时不再失败，为 -1 时不限次数
+//!   2. `space` 大于本次的大小时从中扣除并成功，即先放过 `space` 字节
+//!   3. `interval` 大于 1 时只有每第 `interval` 次调用可能失败
+//!   4. 以 `probability`% 的概率失败
+//! - 判断只使用原子变量与无锁的伪随机数，可以在堆分配器与中断上下文中调用
+//!
+//! ## Assumptions
+//! - 注入在创建初始进程之后才开始（`init`），内核初始化不受影响
+//! - 各计数器之间不加锁，并发调用时次数与概率只是近似值
+
+use crate::timer::get_time_us;
+use alloc::string::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+/// 一个故障注入点的设置与统计
+pub struct FaultAttr {
+    name: &'static str,
+    interval: AtomicUsize,
+    /// 失败的概率（百分比），为 0 表示不注入
+    probability: AtomicUsize,
+    space: AtomicUsize,
+    /// 剩余的失败次数，-1 表示不限
+    times: AtomicIsize,
+    /// 调用与注入失败的次数
+    calls: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl FaultAttr {
+    const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            interval: AtomicUsize::new(1),
+            probability: AtomicUsize::new(0),
+            space: AtomicUsize::new(0),
+            times: AtomicIsize::new(-1),
+            calls: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// 解析 `<interval>,<probability>,<space>,<times>`，格式错误时返回 `false` 且不修改设置
+    fn configure(&self, value: &str) -> bool {
+        let mut fields = value.split(',');
+        let mut next = || fields.next().map(str::trim);
+        let (Some(interval), Some(probability), Some(space), Some(times)) =
+            (next(), next(), next(), next())
+        else {
+            return false;
+        };
+        let (Ok(interval), Ok(probability), Ok(space), Ok(times)) = (
+            interval.parse::<usize>(),
+            probability.parse::<usize>(),
+            space.parse::<usize>(),
+            times.parse::<isize>(),
+        ) else {
+            return false;
+        };
+        if probability > 100 || times < -1 {
+            return false;
+        }
+        self.interval.store(interval, Ordering::Relaxed);
+        self.probability.store(probability, Ordering::Relaxed);
+        self.space.store(space, Ordering::Relaxed);
+        self.times.store(times, Ordering::Relaxed);
+        true
+    }
+
+    /// 本次大小为 `size` 的操作是否应当失败
+    pub fn should_fail(&self, size: usize) -> bool {
+        if !ARMED.load(Ordering::Relaxed) {
+            return false;
+        }
+        let probability = self.probability.load(Ordering::Relaxed);
+        if probability == 0 || self.times.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+        let space = self.space.load(Ordering::Relaxed);
+        if space > size {
+            self.space.store(space - size, Ordering::Relaxed);
+            return false;
+        }
+        let calls = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+        let interval = self.interval.load(Ordering::Relaxed);
+        if interval > 1 && calls % interval != 0 {
+            return false;
+        }
+        if next_random() % 100 >= probability {
+            return false;
+        }
+        let taken = self
+            .times
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |times| match times {
+                0 => None,
+                -1 => Some(-1),
+                times => Some(times - 1),
+            });
+        if taken.is_err() {
+            return false;
+        }
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+/// 页帧分配
+pub static FAIL_PAGE_ALLOC: FaultAttr = FaultAttr::new("fail_page_alloc");
+/// 内核堆分配
+pub static FAILSLAB: FaultAttr = FaultAttr::new("failslab");
+/// 块设备读取
+pub static FAIL_MAKE_REQUEST: FaultAttr = FaultAttr::new("fail_make_request");
+
+static FAULT_ATTRS: [&FaultAttr; 3] = [&FAIL_PAGE_ALLOC, &FAILSLAB, &FAIL_MAKE_REQUEST];
+
+/// 是否已开始注入
+static ARMED: AtomicBool = AtomicBool::new(false);
+/// xorshift 伪随机数的状态
+static RANDOM_STATE: AtomicUsize = AtomicUsize::new(0);
+
+fn next_random() -> usize {
+    let mut x = RANDOM_STATE.load(Ordering::Relaxed);
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    RANDOM_STATE.store(x, Ordering::Relaxed);
+    x
+}
+
+/// 按启动参数设置各注入点并开始注入
+pub fn init() {
+    RANDOM_STATE.store(get_time_us() | 1, Ordering::Relaxed);
+    for attr in FAULT_ATTRS {
+        let Some(value) = crate::boot::fault_attr(attr.name) else {
+            continue;
+        };
+        if attr.configure(&value) {
+            println!("[kernel] fault injection: {}={}", attr.name, value);
+        } else {
+            println!("[kernel] fault injection: bad {}={}", attr.name, value);
+        }
+    }
+    ARMED.store(true, Ordering::Relaxed);
+}
+
+/// `/proc/fault_inject` 的内容：每个注入点一行
+pub fn report() -> String {
+    let mut out = String::new();
+    for attr in FAULT_ATTRS {
+        let _ = writeln!(
+            out,
+            "{} interval={} probability={} space={} times={} calls={} failures={}",
+            attr.name,
+            attr.interval.load(Ordering::Relaxed),
+            attr.probability.load(Ordering::Relaxed),
+            attr.space.load(Ordering::Relaxed),
+            attr.times.load(Ordering::Relaxed),
+            attr.calls.load(Ordering::Relaxed),
+            attr.failures.load(Ordering::Relaxed),
+        );
+    }
+    out
+}
@@ -35,9 +35,11 @@
 //! - 缓存采用固定容量（`BLOCK_CACHE_SIZE`）
 //! - 当缓存满时，优先回收 `Arc` 强引用计数为 1 的缓存块
 //! - 若无可回收缓存块，则直接 panic
+//! - 管理器与每个缓存块都由 `SleepLock` 保护：持锁期间可能读写块设备，争用时阻塞而不是忙等
 
-use crate::drivers::BlockDevice;
+use crate::drivers::{BlockDevice, BLOCK_DEVICE};
 use crate::hal::BLOCK_SZ;
+use crate::sync::SleepLock;
 use alloc::boxed::Box;
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
@@ -46,7 +48,6 @@ use core::mem::ManuallyDrop;
 use core::ptr::{addr_of, addr_of_mut};
 use core::slice;
 use lazy_static::*;
-use spin::Mutex;
 
 /// 使用 `ManuallyDrop` 确保数据以 `BLOCK_SZ` 对齐方式分配和释放
 ///
@@ -233,7 +234,7 @@ const BLOCK_CACHE_SIZE: usize = 16;
 /// - 查找命中直接返回
 /// - 未命中则可能触发缓存替换
 pub struct BlockCacheManager {
-    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+    queue: VecDeque<(usize, Arc<SleepLock<BlockCache>>)>,
 }
 
 impl BlockCacheManager {
@@ -254,7 +255,7 @@ impl BlockCacheManager {
         &mut self,
         block_id: usize,
         block_device: Arc<dyn BlockDevice>,
-    ) -> Arc<Mutex<BlockCache>> {
+    ) -> Arc<SleepLock<BlockCache>> {
         if let Some(pair) = self.queue.iter().find(|pair| pair.0 == block_id) {
             Arc::clone(&pair.1)
         } else {
@@ -273,7 +274,7 @@ impl BlockCacheManager {
                 }
             }
             // load block into mem and push back
-            let block_cache = Arc::new(Mutex::new(BlockCache::new(
+            let block_cache = Arc::new(SleepLock::new(BlockCache::new(
                 block_id,
                 Arc::clone(&block_device),
             )));
@@ -285,15 +286,15 @@ impl BlockCacheManager {
 
 lazy_static! {
     /// 全局块缓存管理器实例
-    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
-        Mutex::new(BlockCacheManager::new());
+    pub static ref BLOCK_CACHE_MANAGER: SleepLock<BlockCacheManager> =
+        SleepLock::new(BlockCacheManager::new());
 }
 
 /// 获取指定块的缓存（全局接口）
 pub fn get_block_cache(
     block_id: usize,
     block_device: Arc<dyn BlockDevice>,
-) -> Arc<Mutex<BlockCache>> {
+) -> Arc<SleepLock<BlockCache>> {
     BLOCK_CACHE_MANAGER
         .lock()
         .get_block_cache(block_id, block_device)
@@ -304,9 +305,13 @@ pub fn get_block_cache(
 /// ## Behavior
 /// - 遍历当前缓存队列
 /// - 对每个缓存执行 `sync`
+/// - 派发块设备请求队列中积攒的写请求
 pub fn block_cache_sync_all() {
     let manager = BLOCK_CACHE_MANAGER.lock();
     for (_, cache) in manager.queue.iter() {
         cache.lock().sync();
     }
+    drop(manager);
+    // 写回的块先进入请求队列，在这里一次派发
+    BLOCK_DEVICE.flush();
 }
@@ -0,0 +1,283 @@
+//! # 设备文件系统（devfs）
+//!
+//! ## Overview
+//! 挂载在 `/dev` 下的内存文件系统，提供以下字符设备：
+//! - `null`：读到 EOF，写入被丢弃
+//! - `zero`：读到全 0，写入被丢弃
+//! - `full`：读到全 0，写入失败（ENOSPC）
+//! - `urandom` / `random`：读到内核 CSPRNG 产生的随机字节，写入被丢弃
+//! - `tty`：控制台的别名，与标准输入共用行规程，支持 termios 与窗口大小的 ioctl
+//! - `rtc`：实时时钟，只支持 `RTC_RD_TIME` ioctl，平台上没有 RTC 时不存在
+//!
+//! ## Assumptions
+//! - FAT32 无法保存设备节点，因此 `/dev` 下的路径在打开时由 `open_device` 拦截，
+//!   不会落到磁盘文件系统上
+//!
+//! ## Invariants
+//! - 每个设备都是无状态的单例，多次打开得到的是同一个对象
+
+use super::ino::{ino_of, makedev, DEVFS_DEV};
+use super::tty::{tty_ioctl, tty_poll, tty_read};
+use super::{File, PollEvents, UserStat};
+use crate::drivers::rtc::{rtc, RtcTime};
+use crate::fs::file::BLK_SIZE;
+use crate::mm::{copy_to_user, UserBuffer};
+use crate::random::fill_random;
+use crate::task::current_user_token;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+use lazy_static::lazy_static;
+
+/// devfs 的挂载点
+pub const DEV_ROOT: &str = "/dev";
+
+const S_IFCHR: u32 = 0o020000;
+const S_IFDIR: u32 = 0o040000;
+
+/// 读取 RTC 时间：`_IOR('p', 0x09, struct rtc_time)`
+const RTC_RD_TIME: usize = 0x8024_7009;
+
+/// 字符设备种类
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DevKind {
+    Null,
+    Zero,
+    Full,
+    Urandom,
+    Tty,
+    Rtc,
+}
+
+impl DevKind {
+    fn name(&self) -> &'static str {
+        match self {
+            DevKind::Null => "null",
+            DevKind::Zero => "zero",
+            DevKind::Full => "full",
+            DevKind::Urandom => "urandom",
+            DevKind::Tty => "tty",
+            DevKind::Rtc => "rtc",
+        }
+    }
+
+    fn rdev(&self) -> u64 {
+        match self {
+            DevKind::Null => makedev(1, 3),
+            DevKind::Zero => makedev(1, 5),
+            DevKind::Full => makedev(1, 7),
+            DevKind::Urandom => makedev(1, 9),
+            DevKind::Tty => makedev(5, 0),
+            DevKind::Rtc => makedev(253, 0),
+        }
+    }
+}
+
+lazy_static! {
+    static ref DEVICES: [Arc<CharDev>; 6] = [
+        Arc::new(CharDev(DevKind::Null)),
+        Arc::new(CharDev(DevKind::Zero)),
+        Arc::new(CharDev(DevKind::Full)),
+        Arc::new(CharDev(DevKind::Urandom)),
+        Arc::new(CharDev(DevKind::Tty)),
+        Arc::new(CharDev(DevKind::Rtc)),
+    ];
+    static ref DEV_DIR: Arc<DevDir> = Arc::new(DevDir);
+}
+
+/// 内存文件系统中文件的 stat，`dev` 为所在文件系统的设备号
+pub(super) fn dev_stat(dev: u64, path: &str, mode: u32, rdev: u64) -> UserStat {
+    UserStat {
+        st_dev: dev,
+        st_ino: ino_of(path),
+        st_mode: mode,
+        st_nlink: 1,
+        st_uid: 0,
+        st_gid: 0,
+        st_rdev: rdev,
+        __pad: 0,
+        st_size: 0,
+        st_blksize: BLK_SIZE,
+        __pad2: 0,
+        st_blocks: 0,
+        st_atime_sec: 0,
+        st_atime_nsec: 0,
+        st_mtime_sec: 0,
+        st_mtime_nsec: 0,
+        st_ctime_sec: 0,
+        st_ctime_nsec: 0,
+        __unused: [0; 2],
+    }
+}
+
+/// 打开 `/dev` 下的设备，`path` 必须是已解析的绝对路径
+///
+/// 路径不在 devfs 中时返回 `None`，调用者应继续交给磁盘文件系统处理
+pub fn open_device(path: &str) -> Option<Arc<dyn File + Send + Sync>> {
+    if path == DEV_ROOT {
+        return Some(DEV_DIR.clone());
+    }
+    let name = path.strip_prefix(DEV_ROOT)?.strip_prefix('/')?;
+    // `random` 与 `urandom` 共用同一个发生器
+    let name = if name == "random" { "urandom" } else { name };
+    if name == "rtc" && rtc().is_none() {
+        return None;
+    }
+    DEVICES
+        .iter()
+        .find(|dev| dev.0.name() == name)
+        .map(|dev| dev.clone() as Arc<dyn File + Send + Sync>)
+}
+
+/// `/dev` 下的字符设备
+pub struct CharDev(DevKind);
+
+impl File for CharDev {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        self.0 != DevKind::Rtc
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        match self.0 {
+            // 没有 RTC 中断，读不到任何事件
+            DevKind::Null | DevKind::Rtc => 0,
+            DevKind::Zero | DevKind::Full => {
+                for b in buf.buffers.iter_mut() {
+                    b.fill(0);
+                }
+                buf.len()
+            }
+            DevKind::Urandom => {
+                for b in buf.buffers.iter_mut() {
+                    fill_random(b);
+                }
+                buf.len()
+            }
+            // 与 Stdin 共用行规程
+            DevKind::Tty => tty_read(buf),
+        }
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        match self.0 {
+            DevKind::Full => 0, // ENOSPC
+            DevKind::Tty => {
+                crate::console::write_slices(buf.buffers.iter().map(|b| &**b));
+                buf.len()
+            }
+            DevKind::Rtc => 0,
+            _ => buf.len(),
+        }
+    }
+
+    fn get_stat(&self) -> UserStat {
+        let perm = if self.0 == DevKind::Rtc { 0o644 } else { 0o666 };
+        dev_stat(DEVFS_DEV, &self.get_path(), S_IFCHR | perm, self.0.rdev())
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        alloc::format!("{}/{}", DEV_ROOT, self.0.name())
+    }
+
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
+        match self.0 {
+            DevKind::Null | DevKind::Rtc => Ok(0),
+            DevKind::Zero | DevKind::Full => {
+                buf.fill(0);
+                Ok(buf.len())
+            }
+            DevKind::Urandom => {
+                fill_random(buf);
+                Ok(buf.len())
+            }
+            DevKind::Tty => Err(-1), // ESPIPE
+        }
+    }
+
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, isize> {
+        match self.0 {
+            DevKind::Full => Err(-1), // ENOSPC
+            DevKind::Tty => Err(-1),  // ESPIPE
+            DevKind::Rtc => Err(-1),  // EBADF
+            _ => Ok(buf.len()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        match (self.0, cmd) {
+            (DevKind::Rtc, RTC_RD_TIME) => {
+                let Some(rtc) = rtc() else {
+                    return -1; // ENODEV
+                };
+                let time = RtcTime::from_unix(rtc.read_time());
+                if copy_to_user(current_user_token(), &time, arg as *mut RtcTime).is_err() {
+                    return -1; // EFAULT
+                }
+                0
+            }
+            (DevKind::Tty, _) => tty_ioctl(cmd, arg),
+            _ => -1, // ENOTTY
+        }
+    }
+
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        events.set(PollEvents::POLLOUT, self.writable());
+        if self.0 == DevKind::Tty {
+            events | tty_poll()
+        } else {
+            events | PollEvents::POLLIN
+        }
+    }
+}
+
+/// devfs 的根目录 `/dev`，只用于作为 `openat` 的目录 fd
+pub struct DevDir;
+
+impl File for DevDir {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn get_stat(&self) -> UserStat {
+        dev_stat(DEVFS_DEV, DEV_ROOT, S_IFDIR | 0o755, 0)
+    }
+
+    fn is_dir(&self) -> bool {
+        true
+    }
+
+    fn get_path(&self) -> String {
+        String::from(DEV_ROOT)
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1) // EISDIR
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,228 @@
+//! # epoll
+//!
+//! ## Overview
+//! `epoll_create1` 创建的事件轮询实例，本身也是一个文件描述符：
+//! - 关注列表：`ctl` 以 `EPOLL_CTL_ADD` / `EPOLL_CTL_MOD` / `EPOLL_CTL_DEL` 增删关注的文件及其事件
+//! - 就绪列表：`collect` 检查关注列表，取出就绪的事件，供 `epoll_pwait` 返回
+//! - 水平触发（默认）：只要文件就绪就每次报告；边沿触发（`EPOLLET`）：只在就绪状态新出现时报告一次；
+//!   `EPOLLONESHOT`：报告一次后停用，直到 `EPOLL_CTL_MOD` 重新启用
+//!
+//! ## Design
+//! - 与 ppoll 相同，就绪状态由各文件的 `File::poll` 给出，适用于管道、套接字、eventfd、timerfd 与标准输入；
+//!   内核没有就绪时的回调，`epoll_pwait` 在 ppoll 的等待循环中反复调用 `collect`
+//! - 关注项以（文件描述符，打开的文件）为键，只持有文件的 `Weak` 引用：
+//!   文件的所有描述符都关闭后关注项在下一次检查时自动移除，与 Linux 一致
+//! - 边沿触发记录上一次检查时的就绪状态，出现上次没有的事件位才报告
+//! - epoll 实例自身的 `poll` 在有关注项就绪时可读，因此可以嵌套
+//!
+//! ## Assumptions
+//! - 边沿由相邻两次检查之间就绪状态的变化判断：已经可读的文件又收到数据不会再次触发，
+//!   与 Linux 在每次数据到达时触发不同；按照边沿触发的用法读到 EAGAIN 后再等待的程序不受影响
+//! - 不支持 `EPOLLEXCLUSIVE` 与 `EPOLLWAKEUP`，这两个标志被忽略
+//!
+//! ## Invariants
+//! - 关注列表中不含 epoll 实例自身
+
+use super::eventfd::anon_stat;
+use super::ino::alloc_ino;
+use super::{File, PollEvents, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+
+pub const EPOLL_CLOEXEC: u32 = 0o2000000;
+/// epoll_ctl 的操作
+pub const EPOLL_CTL_ADD: usize = 1;
+pub const EPOLL_CTL_DEL: usize = 2;
+pub const EPOLL_CTL_MOD: usize = 3;
+/// 报告一次后停用
+pub const EPOLLONESHOT: u32 = 1 << 30;
+/// 边沿触发
+pub const EPOLLET: u32 = 1 << 31;
+
+/// 用户态 `struct epoll_event`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EpollEvent {
+    pub events: u32,
+    pub data: u64,
+}
+
+/// 关注列表中的一项
+struct EpollItem {
+    file: Weak<dyn File + Send + Sync>,
+    /// 关注的事件与 `EPOLLET` / `EPOLLONESHOT` 等标志
+    events: u32,
+    /// 原样返回给用户的数据
+    data: u64,
+    /// 上一次检查时的就绪状态，用于边沿触发
+    last: PollEvents,
+    /// `EPOLLONESHOT` 报告之后为 `false`
+    armed: bool,
+}
+
+impl EpollItem {
+    /// 文件当前就绪的、关注的事件（错误与挂断总是关注）
+    fn current(&self, file: &Arc<dyn File + Send + Sync>) -> PollEvents {
+        let requested = PollEvents::from_bits_truncate(self.events as i16)
+            | PollEvents::POLLERR
+            | PollEvents::POLLHUP;
+        file.poll() & requested
+    }
+}
+
+/// epoll 实例
+pub struct Epoll {
+    /// （文件描述符，打开的文件的地址）→ 关注项
+    interest: UPIntrFreeCell<BTreeMap<(usize, usize), EpollItem>>,
+    ino: u64,
+}
+
+/// 以打开的文件的地址区分同一描述符上先后打开的不同文件
+fn file_key(file: &Arc<dyn File + Send + Sync>) -> usize {
+    Arc::as_ptr(file) as *const u8 as usize
+}
+
+impl Epoll {
+    pub fn new() -> Self {
+        Self {
+            interest: unsafe { UPIntrFreeCell::new(BTreeMap::new()) },
+            ino: alloc_ino(),
+        }
+    }
+
+    /// 对描述符 `fd` 上的文件 `file` 执行 `op`，`event` 在 `EPOLL_CTL_DEL` 时被忽略
+    ///
+    /// 已经关注时 `EPOLL_CTL_ADD` 返回 EEXIST，尚未关注时 `EPOLL_CTL_MOD` / `EPOLL_CTL_DEL` 返回 ENOENT
+    pub fn ctl(
+        &self,
+        op: usize,
+        fd: usize,
+        file: &Arc<dyn File + Send + Sync>,
+        event: EpollEvent,
+    ) -> Result<(), isize> {
+        let key = (fd, file_key(file));
+        if key.1 == self as *const Epoll as usize {
+            return Err(-1); // EINVAL
+        }
+        let mut interest = self.interest.exclusive_access();
+        match op {
+            EPOLL_CTL_ADD => {
+                if interest.contains_key(&key) {
+                    return Err(-1); // EEXIST
+                }
+                interest.insert(
+                    key,
+                    EpollItem {
+                        file: Arc::downgrade(file),
+                        events: event.events,
+                        data: event.data,
+                        last: PollEvents::empty(),
+                        armed: true,
+                    },
+                );
+            }
+            EPOLL_CTL_MOD => {
+                let item = interest.get_mut(&key).ok_or(-1isize)?; // ENOENT
+                item.events = event.events;
+                item.data = event.data;
+                // 修改之后重新判断，已经就绪的文件再报告一次
+                item.last = PollEvents::empty();
+                item.armed = true;
+            }
+            EPOLL_CTL_DEL => {
+                interest.remove(&key).ok_or(-1isize)?; // ENOENT
+            }
+            _ => return Err(-1), // EINVAL
+        }
+        Ok(())
+    }
+
+    /// 检查关注列表，取出最多 `max` 个就绪的事件
+    pub fn collect(&self, max: usize) -> Vec<EpollEvent> {
+        let mut interest = self.interest.exclusive_access();
+        interest.retain(|_, item| item.file.strong_count() > 0);
+        let mut ready = Vec::new();
+        for item in interest.values_mut() {
+            if ready.len() >= max {
+                break;
+            }
+            let Some(file) = item.file.upgrade() else {
+                continue;
+            };
+            if !item.armed {
+                continue;
+            }
+            let current = item.current(&file);
+            let last = core::mem::replace(&mut item.last, current);
+            if current.is_empty() || (item.events & EPOLLET != 0 && (current - last).is_empty()) {
+                continue;
+            }
+            if item.events & EPOLLONESHOT != 0 {
+                item.armed = false;
+            }
+            ready.push(EpollEvent {
+                events: current.bits() as u16 as u32,
+                data: item.data,
+            });
+        }
+        ready
+    }
+}
+
+impl File for Epoll {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn get_stat(&self) -> UserStat {
+        anon_stat(self.ino)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        String::from("anon_inode:[eventpoll]")
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1) // EINVAL
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1) // EINVAL
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// 有关注项就绪时可读，不改变边沿触发记录的状态
+    fn poll(&self) -> PollEvents {
+        let ready = self.interest.exclusive_access().values().any(|item| {
+            item.armed
+                && item
+                    .file
+                    .upgrade()
+                    .is_some_and(|file| !item.current(&file).is_empty())
+        });
+        let mut events = PollEvents::empty();
+        events.set(PollEvents::POLLIN, ready);
+        events
+    }
+}
@@ -0,0 +1,191 @@
+//! # eventfd
+//!
+//! ## Overview
+//! `eventfd2` 创建的事件通知文件，内容是一个 64 位计数器，供事件循环在线程或进程之间传递通知：
+//! - `write` 把用户写入的 8 字节值加到计数器上
+//! - `read` 读出计数器的值并清零；信号量模式（`EFD_SEMAPHORE`）下每次只读出 1 并减 1
+//! - `poll`：计数器非零时可读，还能再加 1 时可写
+//!
+//! ## Design
+//! - 计数器最大为 `u64::MAX - 1`：读时计数器为零、或写入后会超过最大值时阻塞，
+//!   与管道一样以让出处理器的方式轮询，非阻塞模式下立即返回
+//!
+//! ## Assumptions
+//! - `File::read` / `File::write` 只能返回字节数：缓冲区不足 8 字节、写入 `u64::MAX`、
+//!   非阻塞模式下无法完成时都返回 0，而不是 EINVAL / EAGAIN；被信号打断时由 sys_read 返回 EINTR
+//!
+//! ## Invariants
+//! - 计数器不超过 `EVENTFD_MAX`
+
+use super::file::BLK_SIZE;
+use super::ino::{alloc_ino, ANON_INODE_DEV};
+use super::{File, PollEvents, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use alloc::string::String;
+use core::any::Any;
+
+/// 信号量模式：每次读出 1
+pub const EFD_SEMAPHORE: u32 = 1;
+/// 与 `O_NONBLOCK` / `O_CLOEXEC` 的取值相同
+pub const EFD_NONBLOCK: u32 = 0o4000;
+pub const EFD_CLOEXEC: u32 = 0o2000000;
+/// 计数器的最大值
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+struct EventFdInner {
+    count: u64,
+    semaphore: bool,
+    nonblocking: bool,
+}
+
+/// eventfd 文件
+pub struct EventFd {
+    inner: UPIntrFreeCell<EventFdInner>,
+    ino: u64,
+}
+
+impl EventFd {
+    /// 创建计数器初值为 `count` 的 eventfd
+    pub fn new(count: u64, semaphore: bool, nonblocking: bool) -> Self {
+        Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(EventFdInner {
+                    count,
+                    semaphore,
+                    nonblocking,
+                })
+            },
+            ino: alloc_ino(),
+        }
+    }
+}
+
+/// 匿名 inode 的 stat：没有文件类型位，只有属主可读写，与 Linux 的 anon_inode 相同
+pub(super) fn anon_stat(ino: u64) -> UserStat {
+    UserStat {
+        st_dev: ANON_INODE_DEV,
+        st_ino: ino,
+        st_mode: 0o600,
+        st_nlink: 1,
+        st_uid: 0,
+        st_gid: 0,
+        st_rdev: 0,
+        __pad: 0,
+        st_size: 0,
+        st_blksize: BLK_SIZE,
+        __pad2: 0,
+        st_blocks: 0,
+        st_atime_sec: 0,
+        st_atime_nsec: 0,
+        st_mtime_sec: 0,
+        st_mtime_nsec: 0,
+        st_ctime_sec: 0,
+        st_ctime_nsec: 0,
+        __unused: [0; 2],
+    }
+}
+
+/// 把 8 字节的值写到用户缓冲区开头，返回写入的字节数
+pub(super) fn put_u64(buf: &mut UserBuffer, value: u64) -> usize {
+    let bytes = value.to_ne_bytes();
+    let mut written = 0;
+    for chunk in buf.buffers.iter_mut() {
+        let n = chunk.len().min(bytes.len() - written);
+        chunk[..n].copy_from_slice(&bytes[written..written + n]);
+        written += n;
+        if written == bytes.len() {
+            break;
+        }
+    }
+    written
+}
+
+impl File for EventFd {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() < 8 {
+            return 0; // EINVAL
+        }
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if inner.count > 0 {
+                let value = if inner.semaphore { 1 } else { inner.count };
+                inner.count -= value;
+                return put_u64(&mut buf, value);
+            }
+            if inner.nonblocking {
+                return 0; // EAGAIN
+            }
+            drop(inner);
+            if signal_pending_of_current() {
+                return 0;
+            }
+            suspend_current_and_run_next();
+        }
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        let mut bytes = [0u8; 8];
+        if buf.len() < 8 {
+            return 0; // EINVAL
+        }
+        buf.read(None, &mut bytes);
+        let value = u64::from_ne_bytes(bytes);
+        if value == u64::MAX {
+            return 0; // EINVAL
+        }
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if EVENTFD_MAX - inner.count >= value {
+                inner.count += value;
+                return 8;
+            }
+            if inner.nonblocking {
+                return 0; // EAGAIN
+            }
+            drop(inner);
+            if signal_pending_of_current() {
+                return 0;
+            }
+            suspend_current_and_run_next();
+        }
+    }
+
+    fn get_stat(&self) -> UserStat {
+        anon_stat(self.ino)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        String::from("anon_inode:[eventfd]")
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1) // ESPIPE
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1) // ESPIPE
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn poll(&self) -> PollEvents {
+        let inner = self.inner.exclusive_access();
+        let mut events = PollEvents::empty();
+        events.set(PollEvents::POLLIN, inner.count > 0);
+        events.set(PollEvents::POLLOUT, inner.count < EVENTFD_MAX);
+        events
+    }
+}
@@ -1,17 +1,21 @@
+use super::fat_name::FatCodePage;
 use crate::drivers::{BlockDevice, BLOCK_DEVICE};
 use crate::fs::{block_cache_sync_all, get_block_cache};
 use crate::hal::BLOCK_SZ;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
-use fatfs::{IoBase, IoError, Read, Seek, SeekFrom, Write};
+use fatfs::{DefaultTimeProvider, IoBase, IoError, Read, Seek, SeekFrom, Write};
 use lazy_static::lazy_static;
 use spin::Mutex;
 
+/// 挂载的 FAT32 文件系统，短文件名按 `FatCodePage` 转换
+pub type FatFs = fatfs::FileSystem<FatFsBlockDevice, DefaultTimeProvider, FatCodePage>;
+
 lazy_static! {
-    pub static ref FAT_FS: Mutex<fatfs::FileSystem<FatFsBlockDevice>> = Mutex::new({
+    pub static ref FAT_FS: Mutex<FatFs> = Mutex::new({
         let fat_device = FatFsBlockDevice::new(BLOCK_DEVICE.clone());
-        let fs = fatfs::FileSystem::new(fat_device, fatfs::FsOptions::new())
-            .expect("Failed to mount FAT filesystem");
+        let options = fatfs::FsOptions::new().oem_cp_converter(FatCodePage::from_cmdline());
+        let fs = FatFs::new(fat_device, options).expect("Failed to mount FAT filesystem");
         fs
     });
 }
@@ -60,6 +64,10 @@ impl Read for FatFsBlockDevice {
         if buf.is_empty() {
             return Ok(0);
         }
+        #[cfg(feature = "fault_inject")]
+        if crate::fault::FAIL_MAKE_REQUEST.should_fail(buf.len()) {
+            return Err(FatFsError::IoError);
+        }
 
         let mut read_size = 0;
         let mut current_offset = self.offset;
@@ -0,0 +1,143 @@
+//! # FAT32 文件名
+//!
+//! ## Overview
+//! FAT32 的文件名规则与 Linux 不同，本模块集中处理两者之间的转换：
+//! - `FatCodePage`：短文件名（8.3）的 OEM 代码页，由启动参数 `codepage=` 选择，
+//!   取 `437`（默认，与 DOS / Windows 创建的磁盘一致）或 `ascii`（非 ASCII 字符一律替换）
+//! - `check_name`：新建文件或目录时检查名字，拒绝 FAT32 无法保存的名字
+//! - `name_eq`：FAT32 按不区分大小写的方式比较名字，与 fatfs 查找目录项的规则一致
+//!
+//! ## Design
+//! - 长文件名以 UTF-16 保存，任意 Unicode 名字经由长文件名原样往返，代码页只影响没有长文件名的
+//!   短文件名目录项；不在代码页中的字符在短文件名中被替换，此时 fatfs 总是同时写入长文件名
+//! - 大小写：同一目录中只差大小写的名字指向同一个目录项，目录项保留创建时的大小写。
+//!   打开文件时把路径中已存在的分量换成目录项中的名字（`canonical_path`），
+//!   使以路径为键的页缓存、inode 号与文件锁不因大小写不同而分裂
+//!
+//! ## Assumptions
+//! - 不区分大小写的比较使用 Unicode 的大写映射，与 fatfs 的长文件名比较相同；
+//!   短文件名只有 ASCII 字母区分大小写
+//! - 文件名末尾的空格与点号按原样传给 fatfs，不像 Windows 那样去掉
+
+use alloc::string::String;
+use fatfs::OemCpConverter;
+
+/// 长文件名的最大长度（UTF-16 代码单元）
+const FAT_NAME_MAX: usize = 255;
+
+/// 代码页 437 中 0x80..=0xFF 对应的字符
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// 短文件名使用的 OEM 代码页
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatCodePage {
+    /// 只有 ASCII，其余字节显示为替换字符
+    Ascii,
+    /// IBM PC 代码页 437
+    Cp437,
+}
+
+impl FatCodePage {
+    /// 按启动参数 `codepage=` 选择代码页，缺省或无法识别时使用 437
+    pub fn from_cmdline() -> Self {
+        match crate::boot::codepage().as_deref() {
+            Some("ascii") => FatCodePage::Ascii,
+            Some("437") | None => FatCodePage::Cp437,
+            Some(other) => {
+                log::warn!("unknown codepage={}, using 437", other);
+                FatCodePage::Cp437
+            }
+        }
+    }
+}
+
+impl OemCpConverter for FatCodePage {
+    fn decode(&self, oem_char: u8) -> char {
+        match (self, oem_char) {
+            (_, 0..=0x7f) => oem_char as char,
+            (FatCodePage::Ascii, _) => '\u{fffd}',
+            (FatCodePage::Cp437, _) => CP437_HIGH[oem_char as usize - 0x80],
+        }
+    }
+
+    fn encode(&self, uni_char: char) -> Option<u8> {
+        if uni_char.is_ascii() {
+            return Some(uni_char as u8);
+        }
+        match self {
+            FatCodePage::Ascii => None,
+            FatCodePage::Cp437 => CP437_HIGH
+                .iter()
+                .position(|&c| c == uni_char)
+                .map(|i| 0x80 + i as u8),
+        }
+    }
+}
+
+/// 检查新建的文件或目录的名字 `name`（路径的最后一个分量）
+///
+/// 含 FAT32 不允许的字符或控制字符时返回 EINVAL，超出长文件名的长度时返回 ENAMETOOLONG
+pub fn check_name(name: &str) -> Result<(), isize> {
+    if name.is_empty() {
+        return Err(-1); // ENOENT
+    }
+    if name
+        .chars()
+        .any(|c| (c as u32) < 0x20 || matches!(c, '"' | '*' | ':' | '<' | '>' | '?' | '\\' | '|'))
+    {
+        return Err(-1); // EINVAL
+    }
+    if name.encode_utf16().count() > FAT_NAME_MAX {
+        return Err(-1); // ENAMETOOLONG
+    }
+    Ok(())
+}
+
+/// 按 FAT32 的规则（不区分大小写）比较两个名字
+pub fn name_eq(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
+
+/// 把绝对路径 `full_path` 中已存在的分量换成目录项中保存的名字，
+/// 从第一个不存在的分量开始保持原样
+pub fn canonical_path(full_path: &str) -> String {
+    let mut dir = super::inode::ROOT_DIR.exclusive_access().clone();
+    let mut result = String::new();
+    let mut components = full_path.split('/').filter(|c| !c.is_empty());
+    for component in components.by_ref() {
+        let entry = dir.iter().flatten().find(|entry| {
+            let name = entry.file_name();
+            name != "." && name != ".." && name_eq(&name, component)
+        });
+        let Some(entry) = entry else {
+            result.push('/');
+            result.push_str(component);
+            break;
+        };
+        result.push('/');
+        result.push_str(&entry.file_name());
+        if !entry.is_dir() {
+            break;
+        }
+        dir = entry.to_dir();
+    }
+    for component in components {
+        result.push('/');
+        result.push_str(component);
+    }
+    if result.is_empty() {
+        result.push('/');
+    }
+    result
+}
@@ -0,0 +1,104 @@
+//! # 命名管道（FIFO）
+//!
+//! ## Overview
+//! `mknodat(S_IFIFO)` 在文件系统中创建的命名管道：
+//! - `make_fifo` 登记一个 FIFO 节点，磁盘上只留下一个空的普通文件占位，使目录遍历与删除照常工作
+//! - `open_fifo` 打开节点时返回连接到该节点管道缓冲区的读端或写端（`Pipe`）
+//! - `drop_fifo` 在节点被删除时注销
+//!
+//! ## Design
+//! - 每个节点对应一个 `PipeRingBuffer`，同一节点的所有读端与写端共享，读写行为与匿名管道相同
+//! - 打开时遵循阻塞语义：只读打开等待至少一个写者，只写打开等待至少一个读者；读写打开从不等待
+//! - `O_NONBLOCK` 只读打开立即返回；没有读者时 `O_NONBLOCK` 只写打开失败（ENXIO）
+//! - 所有端都关闭后缓冲区中残留的数据被丢弃，下一次打开时重新开始
+//!
+//! ## Assumptions
+//! - FAT32 不能保存特殊文件，节点的类型只记录在内存中，重启后占位文件退化为普通文件
+//! - 与页缓存一样以绝对路径标识节点
+//! - 等待对端时以让出处理器的方式轮询，与匿名管道的读写等待一致
+
+use super::pipe::{Pipe, PipeRingBuffer};
+use super::OpenFlags;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+type FifoBuffer = Arc<UPIntrFreeCell<PipeRingBuffer>>;
+
+lazy_static! {
+    /// 已登记的 FIFO 节点及其管道缓冲区
+    static ref FIFOS: UPIntrFreeCell<BTreeMap<String, FifoBuffer>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+}
+
+fn new_buffer(path: &str) -> FifoBuffer {
+    Arc::new(unsafe { UPIntrFreeCell::new(PipeRingBuffer::for_fifo(path)) })
+}
+
+/// 把 `path` 登记为 FIFO 节点
+pub fn make_fifo(path: &str) {
+    FIFOS
+        .exclusive_access()
+        .insert(String::from(path), new_buffer(path));
+}
+
+/// `path` 是否为 FIFO 节点
+pub fn is_fifo(path: &str) -> bool {
+    FIFOS.exclusive_access().contains_key(path)
+}
+
+/// 注销 `path` 上的 FIFO 节点（节点被删除时调用），已打开的端不受影响
+pub fn drop_fifo(path: &str) {
+    FIFOS.exclusive_access().remove(path);
+}
+
+/// 打开 FIFO 节点 `path`，按 `flags` 返回读端、写端或读写端
+///
+/// 阻塞等待对端时被信号打断返回 EINTR
+pub fn open_fifo(path: &str, flags: OpenFlags) -> Result<Arc<Pipe>, isize> {
+    let buffer = {
+        let mut fifos = FIFOS.exclusive_access();
+        let Some(buffer) = fifos.get_mut(path) else {
+            return Err(-1); // ENOENT
+        };
+        // 所有端都已关闭：丢弃残留的数据
+        let idle = {
+            let ring_buffer = buffer.exclusive_access();
+            ring_buffer.readers() == 0 && ring_buffer.writers() == 0
+        };
+        if idle {
+            *buffer = new_buffer(path);
+        }
+        buffer.clone()
+    };
+    let (readable, writable) = flags.read_write();
+    let nonblock = flags.contains(OpenFlags::NONBLOCK);
+    if writable && !readable && nonblock && buffer.exclusive_access().readers() == 0 {
+        return Err(-1); // ENXIO
+    }
+    let pipe = Arc::new(Pipe::with_buffer(buffer.clone(), readable, writable));
+    pipe.set_nonblocking(nonblock);
+    if nonblock || (readable && writable) {
+        return Ok(pipe);
+    }
+    loop {
+        let peer_opened = {
+            let ring_buffer = buffer.exclusive_access();
+            if readable {
+                ring_buffer.writers() > 0
+            } else {
+                ring_buffer.readers() > 0
+            }
+        };
+        if peer_opened {
+            return Ok(pipe);
+        }
+        if signal_pending_of_current() {
+            return Err(-1); // EINTR
+        }
+        suspend_current_and_run_next();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::fs::inode::{FatType, OSInode};
 use crate::mm::UserBuffer;
 use alloc::string::String;
+use bitflags::bitflags;
 use core::any::Any;
 use core::cell::UnsafeCell;
 use fatfs::SeekFrom;
@@ -18,6 +19,45 @@ pub trait File: Send + Sync {
     fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, isize>;
     ///可以获得OsInode结构体
     fn as_any(&self) -> &dyn Any;
+    /// 设备相关的控制操作，`arg` 通常是用户态指针；不支持的文件返回 ENOTTY
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> isize {
+        -1 // ENOTTY
+    }
+    /// 把文件的修改写到磁盘上（fsync），`datasync` 为 `true` 时可以跳过只涉及元数据的更新（fdatasync）；
+    /// 管道、套接字与设备等没有可以同步的内容，返回 EINVAL
+    fn sync(&self, _datasync: bool) -> Result<(), isize> {
+        Err(-1) // EINVAL
+    }
+    /// 当前的就绪状态（poll / select），默认可读的文件总是可读、可写的文件总是可写
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        events.set(PollEvents::POLLIN, self.readable());
+        events.set(PollEvents::POLLOUT, self.writable());
+        events
+    }
+    /// 读出目录中的下一项并前进（getdents64），读完时返回 `None`；不是目录时返回 ENOTDIR
+    fn read_dir(&self) -> Result<Option<DirEntry>, isize> {
+        Err(-1) // ENOTDIR
+    }
+}
+
+bitflags! {
+    /// `struct pollfd` 中的事件
+    #[derive(Clone, Copy)]
+    pub struct PollEvents: i16 {
+        // 有数据可读
+        const POLLIN = 0x001;
+        // 有紧急数据可读
+        const POLLPRI = 0x002;
+        // 可以写入而不阻塞
+        const POLLOUT = 0x004;
+        // 出错（总是报告）
+        const POLLERR = 0x008;
+        // 对端已关闭（总是报告）
+        const POLLHUP = 0x010;
+        // 描述符无效（总是报告）
+        const POLLNVAL = 0x020;
+    }
 }
 
 pub const S_IFREG: u32 = 0o100000; //普通文件
@@ -86,7 +126,7 @@ pub struct LinuxDirent64 {
     pub d_name: [u8; 256], 
 }
 
-///仅仅作为dir_list()的返回值使用，字段还是比较少的
+///仅仅作为 list_dir() / read_dir() 的返回值使用，字段还是比较少的
 pub struct DirEntry {
     pub d_name: String,
     pub is_dir: bool,
@@ -0,0 +1,89 @@
+//! # inode 号与设备号
+//!
+//! ## Overview
+//! FAT32 的目录项没有 inode 号，内存文件系统、管道与套接字也没有；本模块为它们提供 `stat` 与
+//! `getdents64` 中的 `st_dev` / `st_ino`：
+//! - `ino_of`：按绝对路径分配 inode 号，FAT32、devfs 与 procfs 中的文件使用
+//! - `alloc_ino`：不对应任何路径的对象（匿名管道、套接字）各自分配一个
+//! - 每个文件系统一个固定的设备号，磁盘文件系统使用块设备的设备号，
+//!   其余与 Linux 的匿名设备一样使用主设备号 0
+//!
+//! ## Design
+//! - 所有 inode 号来自同一个递增计数器，从 2 开始，1 留给 FAT32 的根目录
+//! - 文件被删除时 `forget_ino` 移除记录，之后新建的同名文件得到新的 inode 号；
+//!   被改名时 `rename_ino` 把 inode 号转给新路径
+//!
+//! ## Assumptions
+//! - 与页缓存一样，以绝对路径作为键；硬链接的别名一律转到主路径（见 `link`），
+//!   同一路径在任意时刻只对应一个文件
+//! - inode 号只保存在内存中，重启后重新分配
+//!
+//! ## Invariants
+//! - 同一路径在被删除之前总是得到同一个 inode 号
+//! - inode 号不会被重复使用，因此 `(st_dev, st_ino)` 在所有文件之间唯一
+
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+
+/// Linux 的设备号编码（仅适用于 major / minor 都小于 256 的情况）
+pub(super) const fn makedev(major: u64, minor: u64) -> u64 {
+    (major << 8) | minor
+}
+
+/// FAT32 所在块设备（virtio-blk 的第一个磁盘）的设备号
+pub const FAT_DEV: u64 = makedev(254, 0);
+/// procfs 的设备号
+pub const PROCFS_DEV: u64 = makedev(0, 3);
+/// devfs 的设备号
+pub const DEVFS_DEV: u64 = makedev(0, 5);
+/// 匿名管道所在的伪文件系统的设备号
+pub const PIPEFS_DEV: u64 = makedev(0, 12);
+/// 套接字所在的伪文件系统的设备号
+pub const SOCKFS_DEV: u64 = makedev(0, 8);
+/// eventfd、timerfd 等匿名 inode 所在的伪文件系统的设备号
+pub const ANON_INODE_DEV: u64 = makedev(0, 13);
+
+/// FAT32 根目录的 inode 号
+const ROOT_INO: u64 = 1;
+
+/// 下一个可分配的 inode 号
+static NEXT_INO: AtomicU64 = AtomicU64::new(ROOT_INO + 1);
+
+lazy_static! {
+    /// 路径 → inode 号
+    static ref INODES: UPIntrFreeCell<BTreeMap<String, u64>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+}
+
+/// 分配一个不对应任何路径的 inode 号
+pub fn alloc_ino() -> u64 {
+    NEXT_INO.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 绝对路径 `path` 的 inode 号，第一次查询时分配
+pub fn ino_of(path: &str) -> u64 {
+    if path == "/" {
+        return ROOT_INO;
+    }
+    *INODES
+        .exclusive_access()
+        .entry(String::from(path))
+        .or_insert_with(alloc_ino)
+}
+
+/// 移除 `path` 的 inode 号（文件被删除时调用）
+pub fn forget_ino(path: &str) {
+    INODES.exclusive_access().remove(path);
+}
+
+/// 把 `old` 的 inode 号转给 `new`（文件被改名时调用），`new` 原有的 inode 号被丢弃
+pub fn rename_ino(old: &str, new: &str) {
+    let mut inodes = INODES.exclusive_access();
+    inodes.remove(new);
+    if let Some(ino) = inodes.remove(old) {
+        inodes.insert(String::from(new), ino);
+    }
+}
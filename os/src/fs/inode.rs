@@ -1,21 +1,38 @@
 use crate::fs::fat32::FAT_FS;
+use crate::fs::fat_name::{canonical_path, check_name, FatCodePage};
+use crate::fs::fifo::is_fifo;
 use crate::fs::file::{Stat, UserStat, BLK_SIZE};
-use crate::fs::{DirEntry, FatFsBlockDevice};
-use crate::mm::UserBuffer;
+use crate::fs::ino::{ino_of, FAT_DEV};
+use crate::fs::link::{self, nlink_of, read_link};
+use crate::fs::lock::release_flock;
+use crate::fs::metadata::{file_meta_or_default, MODE_MASK};
+use crate::fs::page_cache::{page_cache_of, PageCache};
+use crate::fs::readahead::{
+    self, Advice, ReadAhead, POSIX_FADV_DONTNEED, POSIX_FADV_NOREUSE, POSIX_FADV_NORMAL,
+    POSIX_FADV_RANDOM, POSIX_FADV_SEQUENTIAL, POSIX_FADV_WILLNEED,
+};
+use crate::fs::{block_cache_sync_all, DirEntry, FatFsBlockDevice};
+use crate::hal::PAGE_SIZE;
+use crate::mm::{FrameTracker, UserBuffer};
 use crate::sync::UPIntrFreeCell;
 use crate::syscall::StatMode;
-use crate::task::current_process;
+use crate::task::{cond_resched, current_process};
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use core::any::Any;
 use core::cell::UnsafeCell;
-use fatfs::{
-    DefaultTimeProvider, Dir, File, FileSystem, LossyOemCpConverter, Read, Seek, SeekFrom, Write,
-};
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use fatfs::{DefaultTimeProvider, Dir, File, FileSystem, Read, Seek, SeekFrom, Write};
 use lazy_static::lazy_static;
 
+/// `read_all` 每次持锁读取的字节数
+const READ_ALL_CHUNK: usize = 16 * PAGE_SIZE;
+/// FAT32 中文件长度的上限，目录项中的长度字段为 32 位
+const FAT32_MAX_FILE_SIZE: usize = 0xFFFF_FFFF;
+
 pub struct OSInode {
     readable: bool,
     writable: bool,
@@ -24,14 +41,32 @@ pub struct OSInode {
     file: UPIntrFreeCell<FatType>,
     pub is_directory: bool, // 是否是目录
     path: String,           // 文件的完整路径
+    // 普通文件的页缓存，同一路径的所有打开实例共享
+    cache: Option<Arc<PageCache>>,
+    // O_APPEND：每次写之前先移动到文件末尾
+    append: bool,
+    // 顺序读取的预读状态
+    ra: UPIntrFreeCell<ReadAhead>,
+    // 上次 fsync / fdatasync 时的文件长度，尚未同步过时为 usize::MAX
+    synced_size: AtomicUsize,
+    // getdents64 已经读过的目录项数
+    dir_pos: AtomicUsize,
+    // lseek 移到的文件末尾之后的偏移，fatfs 的偏移不能超过文件末尾，由这里记录；
+    // 为 usize::MAX 时偏移在文件之内，以 fatfs 的偏移为准
+    past_eof: AtomicUsize,
 }
 
+/// FAT32 上的普通文件
+pub type FatFile = File<'static, FatFsBlockDevice, DefaultTimeProvider, FatCodePage>;
+/// FAT32 上的目录
+pub type FatDir = Dir<'static, FatFsBlockDevice, DefaultTimeProvider, FatCodePage>;
+
 pub enum FatType {
     //底层通过 FatFsBlockDevice 访问磁盘
     // 使用 DefaultTimeProvider 提供时间
-    // 使用 LossyOemCpConverter 处理文件名
-    File(File<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>),
-    Dir(Dir<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>),
+    // 使用 FatCodePage 转换短文件名
+    File(FatFile),
+    Dir(FatDir),
 }
 
 // 理由：在单核环境下，UPIntrFreeCell 通过屏蔽中断保证了原子性。
@@ -54,12 +89,16 @@ impl OSInode {
         let st_size = 0;
         let st_blocks = ((st_size + 511) / 512) as u64;
         let is_directory = is_dir;
+        let cache = match file {
+            FatType::File(_) => Some(page_cache_of(&path)),
+            FatType::Dir(_) => None,
+        };
         Self {
             readable,
             writable,
             stat: Stat {
-                st_dev: 0,
-                st_ino: 0, // 或者生成伪 inode
+                st_dev: FAT_DEV,
+                st_ino: ino_of(&path),
                 st_mode,
                 st_nlink: 1,
                 st_uid: 0,
@@ -81,31 +120,260 @@ impl OSInode {
             file: unsafe { UPIntrFreeCell::new(file) },
             is_directory,
             path,
+            cache,
+            append: false,
+            ra: unsafe { UPIntrFreeCell::new(ReadAhead::default()) },
+            synced_size: AtomicUsize::new(usize::MAX),
+            dir_pos: AtomicUsize::new(0),
+            past_eof: AtomicUsize::new(usize::MAX),
         }
     }
 
-    /// 当前 read_all 时从 offset 到 EOF 而不是从文件开始到 EOF
-    /// 把注释部分取消则从文件开始到 EOF
-    pub fn read_all(&self) -> Vec<u8> {
+    /// 页缓存（目录没有页缓存）
+    pub fn page_cache(&self) -> Option<Arc<PageCache>> {
+        self.cache.clone()
+    }
+
+    /// 对底层 FAT 文件执行 `f`，目录返回 `None`
+    pub fn with_fat_file<R>(&self, f: impl FnOnce(&mut FatFile) -> R) -> Option<R> {
+        match &mut *self.file.exclusive_access() {
+            FatType::File(file) => Some(f(file)),
+            FatType::Dir(_) => None,
+        }
+    }
+
+    /// 把文件第 `pages` 页中已缓存的内容写回磁盘（msync）
+    pub fn sync_pages(&self, pages: Range<usize>) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        if let FatType::File(file) = &mut *self.file.exclusive_access() {
+            let size = get_size(file) as usize;
+            cache.writeback_range(file, size, pages);
+            let _ = file.flush();
+        }
+    }
+
+    /// 丢弃文件第 `pages` 页中干净且未被映射的缓存页（msync `MS_INVALIDATE`）
+    pub fn invalidate_pages(&self, pages: Range<usize>) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(pages);
+        }
+    }
+
+    /// fadvise：对 `[offset, offset + len)` 给出访问模式建议，`len` 为 0 表示直到文件末尾
+    ///
+    /// - `NORMAL` / `SEQUENTIAL` / `RANDOM` 作用于整个打开的文件，改变预读与读后丢弃的策略
+    /// - `WILLNEED` 在后台把范围内的页读入页缓存
+    /// - `DONTNEED` 丢弃范围内完整的、既不脏也没有被映射的缓存页，不写回脏页
+    /// - `NOREUSE` 被忽略
+    pub fn fadvise(&self, offset: usize, len: usize, advice: usize) -> Result<(), isize> {
+        let end = match len {
+            0 => usize::MAX,
+            len => offset.checked_add(len).ok_or(-1isize)?, // EINVAL
+        };
+        match advice {
+            POSIX_FADV_NORMAL => self.ra.exclusive_access().set_advice(Advice::Normal),
+            POSIX_FADV_SEQUENTIAL => self.ra.exclusive_access().set_advice(Advice::Sequential),
+            POSIX_FADV_RANDOM => self.ra.exclusive_access().set_advice(Advice::Random),
+            POSIX_FADV_WILLNEED => {
+                let Some(cache) = &self.cache else {
+                    return Ok(());
+                };
+                let size = self
+                    .with_fat_file(|file| get_size(file) as usize)
+                    .unwrap_or(0);
+                let pages = offset / PAGE_SIZE..end.min(size).div_ceil(PAGE_SIZE);
+                if !pages.is_empty() {
+                    readahead::submit(&self.path, cache.clone(), pages);
+                }
+            }
+            POSIX_FADV_DONTNEED => {
+                if let Some(cache) = &self.cache {
+                    // 只丢弃完整的页；`len` 为 0 时 `end` 为 `usize::MAX`，直到文件末尾
+                    cache.invalidate(offset.div_ceil(PAGE_SIZE)..end / PAGE_SIZE);
+                }
+            }
+            POSIX_FADV_NOREUSE => {}
+            _ => return Err(-1), // EINVAL
+        }
+        Ok(())
+    }
+
+    /// 当前的文件偏移，目录返回 0
+    pub fn offset(&self) -> usize {
+        self.with_fat_file(|file| self.current_pos(file))
+            .unwrap_or(0)
+    }
+
+    /// 把文件偏移设为 `pos`，可以在文件末尾之后
+    pub fn set_offset(&self, pos: usize) {
+        let _ = self.with_fat_file(|file| self.seek_to(file, pos));
+    }
+
+    /// lseek：移动文件偏移并返回新的偏移
+    ///
+    /// 偏移可以移到文件末尾之后，之后在该处写入时，中间的部分填零（见 `extend_to`），
+    /// 但不能超过 FAT32 的文件长度上限；目录的偏移是 getdents64 已经读过的目录项数，只支持 `SEEK_SET` 与 `SEEK_CUR`
+    pub fn lseek(&self, pos: SeekFrom) -> Result<usize, isize> {
         let mut inner = self.file.exclusive_access();
-        let mut buffer = [0u8; 512];
-        let mut v: Vec<u8> = Vec::new();
+        let (base, delta) = match (&mut *inner, pos) {
+            (_, SeekFrom::Start(offset)) => (0, offset as i64),
+            (FatType::File(file), SeekFrom::Current(delta)) => (self.current_pos(file), delta),
+            (FatType::File(file), SeekFrom::End(delta)) => (get_size(file) as usize, delta),
+            (FatType::Dir(_), SeekFrom::Current(delta)) => {
+                (self.dir_pos.load(Ordering::Relaxed), delta)
+            }
+            (FatType::Dir(_), SeekFrom::End(_)) => return Err(-1), // EINVAL
+        };
+        let new_pos = base.checked_add_signed(delta as isize).ok_or(-1isize)?; // EINVAL
         match &mut *inner {
-            FatType::File(file) => {
-                // file.seek(SeekFrom::Start(0)).unwrap();
-                loop {
-                    let len = file.read(&mut buffer);
-                    let size = len.unwrap();
-                    if size == 0 {
-                        break;
-                    }
-                    v.extend_from_slice(&buffer[..size]);
-                }
+            FatType::File(_) if new_pos > FAT32_MAX_FILE_SIZE => return Err(-1), // EINVAL
+            FatType::File(file) => self.seek_to(file, new_pos),
+            FatType::Dir(_) => self.dir_pos.store(new_pos, Ordering::Relaxed),
+        }
+        Ok(new_pos)
+    }
+
+    /// 本次读写开始的偏移
+    ///
+    /// 偏移在文件末尾之后、而文件此后已被其他写入扩展到该偏移时，把 fatfs 的偏移移过去
+    fn current_pos(&self, file: &mut FatFile) -> usize {
+        let past_eof = self.past_eof.load(Ordering::Relaxed);
+        if past_eof == usize::MAX {
+            return file.seek(SeekFrom::Current(0)).unwrap() as usize;
+        }
+        if past_eof <= get_size(file) as usize {
+            self.seek_to(file, past_eof);
+        }
+        past_eof
+    }
+
+    /// 把偏移设为 `pos`，超出文件末尾时 fatfs 的偏移停在末尾，由 `past_eof` 记录
+    fn seek_to(&self, file: &mut FatFile, pos: usize) {
+        let size = get_size(file) as usize;
+        if pos > size {
+            file.seek(SeekFrom::End(0)).unwrap();
+            self.past_eof.store(pos, Ordering::Relaxed);
+        } else {
+            file.seek(SeekFrom::Start(pos as u64)).unwrap();
+            self.past_eof.store(usize::MAX, Ordering::Relaxed);
+        }
+    }
+
+    /// 在文件末尾之后写零，把文件扩展到 `len` 字节，之后 fatfs 的偏移位于 `len`
+    ///
+    /// FAT32 不支持空洞，空洞的部分需要实际写入零并分配簇；
+    /// 页缓存中文件末尾之后的部分本来就是零，仍然同步一次以防该页的缓存不完整；
+    /// `len` 超过 FAT32 的文件长度上限时不写入任何内容
+    fn extend_to(&self, file: &mut FatFile, len: usize) -> Result<(), isize> {
+        if len > FAT32_MAX_FILE_SIZE {
+            return Err(-1); // EFBIG
+        }
+        let cache = self.cache.as_ref().unwrap();
+        let size = get_size(file) as usize;
+        file.seek(SeekFrom::End(0)).map_err(|_| -1isize)?;
+        let zeros = [0u8; 512];
+        let mut pos = size;
+        while pos < len {
+            let n = zeros.len().min(len - pos);
+            file.write_all(&zeros[..n]).map_err(|_| -1isize)?; // ENOSPC
+            cache.update(pos, &zeros[..n]);
+            pos += n;
+        }
+        self.past_eof.store(usize::MAX, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 经由页缓存取得 `pos` 所在的页，以及该页中从 `pos` 开始、不超过文件末尾的字节数
+    ///
+    /// `pos` 在文件末尾之后或是目录时返回 `None`
+    pub fn page_at(&self, pos: usize) -> Option<(Arc<FrameTracker>, usize)> {
+        let cache = self.cache.as_ref()?;
+        self.with_fat_file(|file| {
+            let size = get_size(file) as usize;
+            if pos >= size {
+                return None;
             }
+            let page = cache.get_page(pos / PAGE_SIZE, file)?;
+            Some((page, (PAGE_SIZE - pos % PAGE_SIZE).min(size - pos)))
+        })?
+    }
+
+    /// 截断时丢弃页缓存中超出新长度的内容
+    pub fn truncate_cache(&self, size: usize) {
+        if let Some(cache) = &self.cache {
+            cache.truncate(size);
+        }
+    }
+
+    /// 把文件截断或扩展到 `len` 字节，扩展的部分填零，文件偏移不变
+    pub fn truncate(&self, len: usize) -> Result<(), isize> {
+        let mut inner = self.file.exclusive_access();
+        let FatType::File(file) = &mut *inner else {
+            return Err(-1); // EISDIR
+        };
+        let cache = self.cache.as_ref().unwrap();
+        let cur = self.current_pos(file);
+        let size = get_size(file) as usize;
+        if len < size {
+            file.seek(SeekFrom::Start(len as u64))
+                .map_err(|_| -1isize)?;
+            file.truncate().map_err(|_| -1isize)?;
+            cache.truncate(len);
+        } else if len > size {
+            self.extend_to(file, len)?;
+        }
+        // 截短之后原来的偏移可能在文件末尾之后
+        self.seek_to(file, cur);
+        unsafe {
+            *self.stat.st_size.get() = len as i64;
+            *self.stat.st_blocks.get() = len.div_ceil(512) as u64;
+        }
+        Ok(())
+    }
+
+    /// 从当前偏移读到 EOF，而不是从文件开始
+    ///
+    /// 每读完 `READ_ALL_CHUNK` 字节释放一次文件的锁并调用 `cond_resched`，
+    /// 读取大文件（例如 exec 时的 ELF）不会长时间占用处理器；
+    /// 与 read 一样，不保证读取期间被其他任务修改的文件内容的一致性
+    pub fn read_all(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        let (start, size) = match &mut *self.file.exclusive_access() {
+            FatType::File(file) => (
+                file.seek(SeekFrom::Current(0)).unwrap() as usize,
+                get_size(file) as usize,
+            ),
             FatType::Dir(_) => {
                 log::debug!("Get a Dir to read, which is not supported");
+                return v;
+            }
+        };
+        v.resize(size.saturating_sub(start), 0);
+        let mut done = 0;
+        while done < v.len() {
+            let mut inner = self.file.exclusive_access();
+            let FatType::File(file) = &mut *inner else {
+                unreachable!()
+            };
+            // 经由页缓存读取，反复执行同一程序时直接命中缓存
+            let chunk = READ_ALL_CHUNK.min(v.len() - done);
+            let n = self.cache.as_ref().unwrap().read(
+                file,
+                start + done,
+                &mut v[done..done + chunk],
+                size,
+            );
+            done += n;
+            file.seek(SeekFrom::Start((start + done) as u64)).unwrap();
+            drop(inner);
+            if n < chunk {
+                break;
             }
+            cond_resched();
         }
+        v.truncate(done);
         v
     }
     pub fn is_dir(&self) -> bool {
@@ -115,15 +383,25 @@ impl OSInode {
             FatType::File(_) => false,
         }
     }
+
+    /// 目录的句柄，普通文件返回 `None`
+    ///
+    /// 句柄指向目录所在的簇，目录被重命名或移动后仍然有效
+    pub fn dir(&self) -> Option<FatDir> {
+        match &*self.file.exclusive_access() {
+            FatType::Dir(dir) => Some(dir.clone()),
+            FatType::File(_) => None,
+        }
+    }
 }
 
 lazy_static! {
-    pub static ref ROOT_DIR: UPIntrFreeCell<Dir<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>> = {
+    pub static ref ROOT_DIR: UPIntrFreeCell<FatDir> = {
         // 获取文件系统的锁
         let fs_guard = FAT_FS.lock();
         // 关键点：fatfs 的 root_dir() 会借用 FileSystem。
         // 在 static 初始化块中，需要确保引用的合法性。
-        let fs_static: &'static FileSystem<FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter> =
+        let fs_static: &'static FileSystem<FatFsBlockDevice, DefaultTimeProvider, FatCodePage> =
             unsafe { &*(fs_guard.deref() as *const _) };
 
         let root_dir = fs_static.root_dir();
@@ -158,13 +436,17 @@ bitflags! {
         // 创建
         const CREATE = 1 << 6;
         // 截断（若存在则以可写方式打开，但是长度清空为0）
-        const TRUNC = 1 << 10;
+        const TRUNC = 1 << 9;
+        // 追加，每次写之前移动到文件末尾
+        const APPEND = 1 << 10;
         // 非阻塞模式
         const NONBLOCK = 1 << 12;
         // 执行时关闭
         const CLOEXEC = 1 << 20;
         //
         const DIRECTORY = 1 << 21;
+        // 最后一个分量是符号链接时失败（ELOOP）
+        const NOFOLLOW = 1 << 17;
         // 尽量减少缓存影响（如O_DIRECT）
         const DIRECT = 1 << 24;
     }
@@ -180,6 +462,22 @@ impl OpenFlags {
             (true, false)
         }
     }
+
+    /// 检查标志的组合是否合法
+    pub fn validate(&self) -> Result<(), isize> {
+        if self.contains(Self::WRONLY | Self::RDWR) {
+            return Err(-1); // EINVAL
+        }
+        if self.contains(Self::DIRECTORY) {
+            if self.contains(Self::CREATE) {
+                return Err(-1); // EINVAL
+            }
+            if self.intersects(Self::WRONLY | Self::RDWR | Self::TRUNC | Self::APPEND) {
+                return Err(-1); // EISDIR
+            }
+        }
+        Ok(())
+    }
 }
 
 impl super::File for OSInode {
@@ -194,19 +492,31 @@ impl super::File for OSInode {
     fn read(&self, mut buf: UserBuffer) -> usize {
         let mut inner = self.file.exclusive_access();
         let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            match &mut *inner {
-                FatType::File(file) => {
-                    let read_size = file.read(slice).unwrap();
+        match &mut *inner {
+            FatType::File(file) => {
+                let cache = self.cache.as_ref().unwrap();
+                let size = get_size(file) as usize;
+                let mut pos = self.current_pos(file);
+                // 先提交后续页的预读，本次读取等待磁盘时预读线程即可开始
+                if let Some(pages) = self.ra.exclusive_access().on_read(pos, buf.len(), size) {
+                    readahead::submit(&self.path, cache.clone(), pages);
+                }
+                for slice in buf.buffers.iter_mut() {
+                    let read_size = cache.read(file, pos, slice, size);
                     total_read_size += read_size;
+                    pos += read_size;
                     if read_size < slice.len() {
                         break;
                     }
                 }
-                FatType::Dir(_) => {
-                    log::debug!("Get a Dir to read, which is not supported");
+                self.seek_to(file, pos);
+                if let Some(pages) = self.ra.exclusive_access().drop_behind(pos) {
+                    cache.invalidate(pages);
                 }
             }
+            FatType::Dir(_) => {
+                log::debug!("Get a Dir to read, which is not supported");
+            }
         }
         total_read_size
     }
@@ -214,10 +524,28 @@ impl super::File for OSInode {
     fn write(&self, buf: UserBuffer) -> usize {
         let mut inner = self.file.exclusive_access();
         let mut total_write_size = 0usize;
+        if let FatType::File(file) = &mut *inner {
+            // 移动到末尾与写入在同一次独占访问中完成，并发的追加不会互相覆盖
+            if self.append {
+                let size = get_size(file) as usize;
+                self.seek_to(file, size);
+            }
+            // 偏移在文件末尾之后：中间的部分先填零
+            let pos = self.current_pos(file);
+            if pos > get_size(file) as usize && self.extend_to(file, pos).is_err() {
+                return 0;
+            }
+        }
         for slice in buf.buffers.iter() {
             match &mut *inner {
                 FatType::File(file) => {
+                    let pos = file.seek(SeekFrom::Current(0)).unwrap() as usize;
                     let write_size = file.write(slice).unwrap();
+                    // 写穿到磁盘的同时更新已缓存的页
+                    self.cache
+                        .as_ref()
+                        .unwrap()
+                        .update(pos, &slice[..write_size]);
                     total_write_size += write_size;
                     if write_size < slice.len() {
                         break;
@@ -232,20 +560,37 @@ impl super::File for OSInode {
         total_write_size
     }
     fn get_stat(&self) -> UserStat {
+        // 权限位与属主来自 chmod / chown 记录的元数据（没有记录时为默认值），与打开方式无关
+        let meta = file_meta_or_default(&self.path);
+        let st_mode = (self.stat.st_mode & !MODE_MASK) | meta.mode;
+        // 大小以磁盘上的文件为准，经由其他打开实例的写入同样可见
+        let st_size = match self.with_fat_file(|file| get_size(file)) {
+            Some(size) => size,
+            None => unsafe { *self.stat.st_size.get() },
+        };
+        // FIFO 节点与符号链接在磁盘上是空的普通文件占位
+        let symlink = read_link(&self.path);
+        let (st_mode, st_size) = if is_fifo(&self.path) {
+            (StatMode::S_IFIFO.bits() | (st_mode & MODE_MASK), st_size)
+        } else if let Some(target) = &symlink {
+            (StatMode::S_IFLNK.bits() | 0o777, target.len() as i64)
+        } else {
+            (st_mode, st_size)
+        };
         unsafe {
             UserStat {
                 st_dev: self.stat.st_dev,
                 st_ino: self.stat.st_ino,
-                st_mode: self.stat.st_mode,
-                st_nlink: self.stat.st_nlink,
-                st_uid: self.stat.st_uid,
-                st_gid: self.stat.st_gid,
+                st_mode,
+                st_nlink: nlink_of(&self.path),
+                st_uid: meta.uid,
+                st_gid: meta.gid,
                 st_rdev: self.stat.st_rdev,
                 __pad: self.stat.__pad,
-                st_size: *self.stat.st_size.get(),
+                st_size,
                 st_blksize: self.stat.st_blksize,
                 __pad2: self.stat.__pad2,
-                st_blocks: *self.stat.st_blocks.get(),
+                st_blocks: (st_size as u64).div_ceil(512),
                 st_ctime_sec: self.stat.st_atime_sec,
                 st_ctime_nsec: self.stat.st_atime_nsec,
                 st_atime_sec: self.stat.st_atime_sec,
@@ -271,16 +616,9 @@ impl super::File for OSInode {
         let mut inner = self.file.exclusive_access();
         match &mut *inner {
             FatType::File(file) => {
-                // fatfs::File 需要 &mut 来读写
-                let mut file_ref = file; // File 类型本身可能在 UPIntrFreeCell 内部
-
-                // seek 到 offset
-                file_ref
-                    .seek(SeekFrom::Start(offset as u64))
-                    .map_err(|_| -1isize)?;
-                // 读取数据
-                let n = file_ref.read(buf).map_err(|_| -1isize)?;
-                Ok(n)
+                // 经由页缓存读取，与文件映射看到同一份数据
+                let size = get_size(file) as usize;
+                Ok(self.cache.as_ref().unwrap().read(file, offset, buf, size))
             }
             FatType::Dir(_) => Err(-1),
         }
@@ -290,6 +628,10 @@ impl super::File for OSInode {
         let mut inner = self.file.exclusive_access();
         match &mut *inner {
             FatType::File(file) => {
+                // 在文件末尾之后写入：中间的部分先填零
+                if offset > get_size(file) as usize {
+                    self.extend_to(file, offset)?;
+                }
                 let mut file_ref = file;
                 // seek 到 offset
                 file_ref
@@ -297,6 +639,7 @@ impl super::File for OSInode {
                     .map_err(|_| -1isize)?;
                 // 写入数据
                 let n = file_ref.write(buf).map_err(|_| -1isize)?;
+                self.cache.as_ref().unwrap().update(offset, &buf[..n]);
 
                 // 更新 stat
                 let file_size = file_ref.seek(SeekFrom::End(0)).map_err(|_| -1isize)? as i64;
@@ -306,17 +649,59 @@ impl super::File for OSInode {
                 unsafe {
                     *self.stat.st_blocks.get() = ((file_size as usize + 511) / 512) as u64;
                 }
-                drop(self.file.exclusive_access());
                 Ok(n)
             }
             FatType::Dir(_) => Err(-1),
         }
     }
+    /// 写回页缓存中的脏页，再写回目录项与块缓存
+    ///
+    /// 文件长度自上次同步以来没有变化时，fdatasync 不写目录项（其中只有修改时间变了），只写回块缓存
+    fn sync(&self, datasync: bool) -> Result<(), isize> {
+        if let FatType::File(file) = &mut *self.file.exclusive_access() {
+            let size = get_size(file) as usize;
+            if let Some(cache) = &self.cache {
+                cache.sync(file, size);
+            }
+            let size_changed = self.synced_size.swap(size, Ordering::Relaxed) != size;
+            if !datasync || size_changed {
+                // 写目录项之后写回全部块缓存
+                return file.flush().map_err(|_| -1); // EIO
+            }
+        }
+        block_cache_sync_all();
+        Ok(())
+    }
+    /// 每次重新遍历目录，跳过已经读过的项
+    fn read_dir(&self) -> Result<Option<DirEntry>, isize> {
+        let pos = self.dir_pos.load(Ordering::Relaxed);
+        let entry = self.list_dir()?.into_iter().nth(pos);
+        if entry.is_some() {
+            self.dir_pos.store(pos + 1, Ordering::Relaxed);
+        }
+        Ok(entry)
+    }
     ///可以直接获得OsInode结构体
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+impl Drop for OSInode {
+    /// 关闭文件时写回经由共享映射修改过的页，并释放这个打开的文件持有的 flock
+    ///
+    /// 页缓存由同一路径的所有打开实例共享，其他映射仍在的页保留脏标记，之后的修改不会丢失
+    fn drop(&mut self) {
+        release_flock(&self.path, self as *const Self as usize);
+        if let Some(cache) = &self.cache {
+            if let FatType::File(file) = &mut *self.file.exclusive_access() {
+                let size = get_size(file) as usize;
+                cache.sync(file, size);
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
 impl OSInode {
     pub fn list_dir(&self) -> Result<Vec<DirEntry>, isize> {
         if !self.is_directory {
@@ -393,100 +778,200 @@ pub fn resolve_path(relative: &str, base: &str) -> String {
     result
 }
 
-pub fn open_initproc(flags: OpenFlags) -> Option<Arc<OSInode>> {
+/// 查询绝对路径 `full_path` 是否存在，存在时返回其是否为目录
+pub fn lookup_path(full_path: &str) -> Option<bool> {
+    if full_path == "/" {
+        return Some(true);
+    }
+    let path_in_fs = full_path.strip_prefix("/").unwrap_or(full_path);
+    let root_dir = ROOT_DIR.exclusive_access();
+    if root_dir.open_dir(path_in_fs).is_ok() {
+        Some(true)
+    } else if root_dir.open_file(path_in_fs).is_ok() {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 打开初始进程的可执行文件，`path` 为从根目录开始的路径（由启动参数 `init=` 指定）
+pub fn open_initproc(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     let (readable, writable) = flags.read_write();
     let root_dir = ROOT_DIR.exclusive_access();
-    root_dir.open_file("initproc").ok().map(|inode| {
+    let path_in_fs = path.trim_start_matches('/');
+    root_dir.open_file(path_in_fs).ok().map(|inode| {
         Arc::new(OSInode::new(
             readable,
             writable,
             FatType::File(inode),
             false,
-            String::from("/initproc"),
+            alloc::format!("/{}", path_in_fs),
         ))
     })
 }
 
-// 实现不完整，还未支持文件的所有权描述
-pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
-    let (readable, writable) = flags.read_write();
+/// 当前进程的工作目录：绝对路径与目录句柄
+///
+/// 工作目录不是 FAT32 上的目录（如 `/dev`）时，按路径从根目录重新查找
+fn cwd() -> (String, Option<FatDir>) {
+    let proc = current_process();
+    let inner = proc.inner_exclusive_access();
+    let dir = inner
+        .cwd_inode
+        .as_any()
+        .downcast_ref::<OSInode>()
+        .and_then(OSInode::dir);
+    (inner.cwd.clone(), dir)
+}
 
-    let full_path = {
-        let proc = current_process();
-        let inner = proc.inner_exclusive_access();
-        let cwd = &inner.cwd;
-        resolve_path(path, &cwd)
+/// 从目录 `base`（为 `None` 时按 `base_path` 从根目录查找）出发，解析路径 `path`
+///
+/// 返回 `(完整路径, 最后一个分量所在的目录, 最后一个分量)`；
+/// 最后一个分量为空表示 `path` 指向该目录本身（如 `.`、`..` 或 `/`）。
+/// 完整路径中已存在的分量使用目录项中保存的大小写；最后一个分量是硬链接时转到主路径，
+/// 是符号链接且 `follow` 为真时转到其目标
+fn walk(
+    base_path: &str,
+    base: Option<FatDir>,
+    path: &str,
+    follow: bool,
+) -> Option<(String, FatDir, String)> {
+    let full_path = canonical_path(&resolve_path(path, base_path));
+    let resolved = link::follow(&full_path, follow).ok()?;
+    if resolved != full_path {
+        return walk("/", None, &resolved, follow);
+    }
+    let root = || ROOT_DIR.exclusive_access().clone();
+    let mut dir = if path.starts_with('/') {
+        root()
+    } else {
+        match base {
+            Some(dir) => dir,
+            None => walk_from(root(), base_path)?,
+        }
+    };
+    let (parent, name) = match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, name)) => (parent, name),
+        None => ("", path.trim_end_matches('/')),
     };
+    dir = walk_from(dir, parent)?;
+    if name == "." || name == ".." {
+        return Some((full_path, walk_from(dir, name)?, String::new()));
+    }
+    Some((full_path, dir, name.to_string()))
+}
 
-    let path_in_fs = full_path.strip_prefix("/").unwrap_or(&full_path);
+/// 从目录 `dir` 出发逐个分量进入 `path` 中的目录
+fn walk_from(mut dir: FatDir, path: &str) -> Option<FatDir> {
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            // 根目录中没有 `..` 项，在根目录上停留
+            ".." => {
+                if let Ok(parent) = dir.open_dir("..") {
+                    dir = parent;
+                }
+            }
+            _ => dir = dir.open_dir(component).ok()?,
+        }
+    }
+    Some(dir)
+}
 
-    let root_dir = ROOT_DIR.exclusive_access();
+// 实现不完整，还未支持文件的所有权描述
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    let (readable, writable) = flags.read_write();
+
+    let (cwd, cwd_dir) = cwd();
+    let (full_path, dir, name) = walk(&cwd, cwd_dir, path, true)?;
+    if name.is_empty() {
+        return None; // EISDIR
+    }
 
     let maybe_inode = if flags.contains(OpenFlags::CREATE) {
-        root_dir
-            .open_file(path_in_fs)
-            .or_else(|_| root_dir.create_file(path_in_fs))
+        dir.open_file(&name)
+            .or_else(|_| {
+                check_name(&name)?;
+                dir.create_file(&name).map_err(|_| -1isize)
+            })
             .ok()
     } else {
-        root_dir.open_file(path_in_fs).ok()
+        dir.open_file(&name).ok()
     };
 
     maybe_inode.map(|mut inode| {
         if flags.contains(OpenFlags::TRUNC) {
             inode.truncate().expect("Truncation failed");
+            page_cache_of(&full_path).truncate(0);
         }
-        Arc::new(OSInode::new(
+        let mut inode = OSInode::new(
             readable,
             writable,
             FatType::File(inode),
             false,
             full_path, // 传入完整路径
-        ))
+        );
+        inode.append = flags.contains(OpenFlags::APPEND);
+        Arc::new(inode)
     })
 }
 
-/// 在指定目录下打开文件
+/// 在目录 `base` 下打开文件，`base` 不是 FAT32 上的目录时按其路径从根目录查找
 pub fn open_file_at(
-    base_dir: &str,
+    base: &dyn super::File,
     path: &str,
     flags: OpenFlags,
     mode: StatMode,
 ) -> Option<Arc<OSInode>> {
-    let full_path = resolve_path(path, base_dir);
+    let base_dir = base
+        .as_any()
+        .downcast_ref::<OSInode>()
+        .and_then(OSInode::dir);
+    let follow = !flags.contains(OpenFlags::NOFOLLOW);
+    let (full_path, dir, name) = walk(&base.get_path(), base_dir, path, follow)?;
     if full_path == "/" {
         return Some(current_root_inode());
     }
-    let root_dir = ROOT_DIR.exclusive_access();
 
     // 尝试打开目录
-    if let Ok(dir) = root_dir.open_dir(&full_path) {
-        return Some(Arc::new(OSInode::new(
-            true,  // 可读
-            false, // 不可写
-            FatType::Dir(dir),
-            true, // 是目录
-            full_path,
-        )));
-    }
+    let dir = if name.is_empty() {
+        Ok(dir)
+    } else {
+        dir.open_dir(&name).map_err(|_| dir)
+    };
+    let dir = match dir {
+        Ok(dir) => {
+            return Some(Arc::new(OSInode::new(
+                true,  // 可读
+                false, // 不可写
+                FatType::Dir(dir),
+                true, // 是目录
+                full_path,
+            )));
+        }
+        Err(dir) => dir,
+    };
 
     // 尝试打开或创建文件
     let file_result = if flags.contains(OpenFlags::CREATE) {
-        root_dir
-            .create_file(&full_path)
-            .or_else(|_| root_dir.open_file(&full_path))
+        check_name(&name).ok()?;
+        dir.create_file(&name).or_else(|_| dir.open_file(&name))
     } else {
-        root_dir.open_file(&full_path)
+        dir.open_file(&name)
     };
 
-    file_result.ok().map(|file| {
-        Arc::new(OSInode::new(
-            flags.contains(OpenFlags::RDONLY) || flags.contains(OpenFlags::RDWR),
-            flags.contains(OpenFlags::WRONLY) || flags.contains(OpenFlags::RDWR),
-            FatType::File(file),
-            false, // 不是目录
-            full_path,
-        ))
-    })
+    let mut inode = OSInode::new(
+        flags.contains(OpenFlags::RDONLY) || flags.contains(OpenFlags::RDWR),
+        flags.contains(OpenFlags::WRONLY) || flags.contains(OpenFlags::RDWR),
+        FatType::File(file_result.ok()?),
+        false, // 不是目录
+        full_path,
+    );
+    inode.append = flags.contains(OpenFlags::APPEND);
+    if flags.contains(OpenFlags::TRUNC) {
+        inode.truncate(0).ok()?;
+    }
+    Some(Arc::new(inode))
 }
 
 ///创建目录，如果存在就返回err(-1)
@@ -510,6 +995,7 @@ pub fn create_dir(path: &str) -> Result<Arc<OSInode>, isize> {
     if dir_name.is_empty() {
         return Err(-1);
     }
+    check_name(dir_name)?;
 
     let root_dir = ROOT_DIR.exclusive_access();
 
@@ -538,31 +1024,36 @@ pub fn create_dir(path: &str) -> Result<Arc<OSInode>, isize> {
     )))
 }
 
+/// 把 FAT32 上的文件 `old` 改名为 `new`，两者都是绝对路径
+pub fn rename_path(old: &str, new: &str) -> Result<(), isize> {
+    let root_dir = ROOT_DIR.exclusive_access();
+    root_dir
+        .rename(
+            old.trim_start_matches('/'),
+            &root_dir,
+            new.trim_start_matches('/'),
+        )
+        .map_err(|_| -1isize) // EIO
+}
+
 /// 打开目录，返回 OSInode
 /// path 可以是绝对路径或相对路径
 /// 返回 Err(-1) 表示打开失败
 pub fn open_dir(path: &str) -> Result<Arc<OSInode>, isize> {
-    let full_path = {
-        let proc = current_process();
-        let inner = proc.inner_exclusive_access();
-        resolve_path(path, &inner.cwd)
+    let (cwd, cwd_dir) = cwd();
+    let (full_path, dir, name) = walk(&cwd, cwd_dir, path, true).ok_or(-1isize)?;
+    let dir = if name.is_empty() {
+        dir
+    } else {
+        dir.open_dir(&name).map_err(|_| -1isize)?
     };
-
-    let path_in_fs = full_path.strip_prefix("/").unwrap_or(&full_path);
-    let root_dir = ROOT_DIR.exclusive_access();
-
-    root_dir
-        .open_dir(path_in_fs)
-        .map(|dir| {
-            Arc::new(OSInode::new(
-                true,
-                false,
-                FatType::Dir(dir),
-                true,
-                full_path,
-            ))
-        })
-        .map_err(|_| -1)
+    Ok(Arc::new(OSInode::new(
+        true,
+        false,
+        FatType::Dir(dir),
+        true,
+        full_path,
+    )))
 }
 
 pub fn get_size<IO: fatfs::ReadWriteSeek, TP: fatfs::TimeProvider, OCC: fatfs::OemCpConverter>(
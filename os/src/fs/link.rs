@@ -0,0 +1,162 @@
+//! # 硬链接与符号链接的模拟
+//!
+//! ## Overview
+//! FAT32 不能保存硬链接与符号链接，本模块在内存中记录它们；与 FIFO 一样，
+//! 磁盘上只为每个链接留下一个空的普通文件占位，使目录遍历、查找与删除照常工作：
+//! - 硬链接：`add_hard_link` 把新路径登记为已有文件的别名。数据只保存在主路径的文件中，
+//!   打开或查询别名时转到主路径，因此所有链接共享页缓存、inode 号与文件锁；`nlink_of` 给出链接数
+//! - 符号链接：`make_symlink` 记录目标，`read_link` 读出目标
+//! - `follow`：把路径最后一个分量上的符号链接与硬链接别名解析为实际的文件路径
+//! - `unlink`：删除一个链接时更新记录，并告诉调用者还需要对磁盘做什么
+//!
+//! ## Design
+//! - 别名表记录“别名 → 主路径”，另一张表记录每个主路径的全部别名
+//! - 删除别名只需删除占位；删除主路径而仍有别名时，第一个别名成为新的主路径，
+//!   调用者把磁盘上的文件改名过去（`Unlink::Promote`），其余别名随之指向新的主路径
+//! - 符号链接的目标按原样保存，相对目标相对于链接所在的目录解析；
+//!   连续跟随超过 `MAX_SYMLINK_FOLLOW` 层时返回 ELOOP
+//!
+//! ## Assumptions
+//! - 链接只保存在内存中，重启后占位退化为空的普通文件
+//! - 只跟随路径最后一个分量上的符号链接，路径中间的分量不会是链接
+//! - 硬链接只能指向普通文件；与页缓存一样以绝对路径标识文件
+//! - 主路径被改名时，已经打开的实例继续使用原来的 FAT 文件句柄直到关闭
+//!
+//! ## Invariants
+//! - 别名表中的主路径一定在主路径表中，且其别名列表包含该别名
+//! - 主路径表中没有别名列表为空的项
+
+use super::inode::resolve_path;
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 连续跟随符号链接的最大层数，与 Linux 相同
+const MAX_SYMLINK_FOLLOW: usize = 40;
+
+struct LinkTable {
+    /// 符号链接 → 目标
+    symlinks: BTreeMap<String, String>,
+    /// 硬链接别名 → 主路径
+    aliases: BTreeMap<String, String>,
+    /// 主路径 → 别名
+    links: BTreeMap<String, Vec<String>>,
+}
+
+lazy_static! {
+    static ref LINKS: UPIntrFreeCell<LinkTable> = unsafe {
+        UPIntrFreeCell::new(LinkTable {
+            symlinks: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            links: BTreeMap::new(),
+        })
+    };
+}
+
+/// 删除一个链接之后，调用者还需要对磁盘做的处理
+#[derive(Debug, PartialEq, Eq)]
+pub enum Unlink {
+    /// 不是链接，也没有别名：按普通文件删除
+    Plain,
+    /// 删除的是符号链接或硬链接别名：只删除占位
+    Placeholder,
+    /// 删除的是仍有别名的主路径：删除新主路径上的占位，再把文件改名过去
+    Promote(String),
+}
+
+/// 把 `path` 登记为指向 `target` 的符号链接
+pub fn make_symlink(path: &str, target: &str) {
+    LINKS
+        .exclusive_access()
+        .symlinks
+        .insert(String::from(path), String::from(target));
+}
+
+/// `path` 是否为符号链接
+pub fn is_symlink(path: &str) -> bool {
+    LINKS.exclusive_access().symlinks.contains_key(path)
+}
+
+/// 符号链接 `path` 的目标，不是符号链接时返回 `None`
+pub fn read_link(path: &str) -> Option<String> {
+    LINKS.exclusive_access().symlinks.get(path).cloned()
+}
+
+/// 把 `new` 登记为 `existing` 所在文件的硬链接
+pub fn add_hard_link(existing: &str, new: &str) {
+    let mut table = LINKS.exclusive_access();
+    let primary = table
+        .aliases
+        .get(existing)
+        .cloned()
+        .unwrap_or_else(|| String::from(existing));
+    table
+        .links
+        .entry(primary.clone())
+        .or_default()
+        .push(String::from(new));
+    table.aliases.insert(String::from(new), primary);
+}
+
+/// 主路径 `path` 上的文件的链接数
+pub fn nlink_of(path: &str) -> u32 {
+    LINKS
+        .exclusive_access()
+        .links
+        .get(path)
+        .map_or(1, |aliases| 1 + aliases.len() as u32)
+}
+
+/// 解析 `path` 最后一个分量上的链接，返回实际的文件路径
+///
+/// `symlinks` 为 `false` 时不跟随符号链接，只把硬链接别名换成主路径；
+/// 符号链接成环或层数过多时返回 ELOOP
+pub fn follow(path: &str, symlinks: bool) -> Result<String, isize> {
+    let table = LINKS.exclusive_access();
+    let mut path = String::from(path);
+    if symlinks {
+        let mut depth = 0;
+        while let Some(target) = table.symlinks.get(&path) {
+            depth += 1;
+            if depth > MAX_SYMLINK_FOLLOW {
+                return Err(-1); // ELOOP
+            }
+            let dir = match path.rsplit_once('/') {
+                Some(("", _)) | None => "/",
+                Some((dir, _)) => dir,
+            };
+            path = resolve_path(target, dir);
+        }
+    }
+    Ok(table.aliases.get(&path).cloned().unwrap_or(path))
+}
+
+/// 删除链接 `path` 的记录
+pub fn unlink(path: &str) -> Unlink {
+    let mut table = LINKS.exclusive_access();
+    if table.symlinks.remove(path).is_some() {
+        return Unlink::Placeholder;
+    }
+    if let Some(primary) = table.aliases.remove(path) {
+        let aliases = table.links.get_mut(&primary).unwrap();
+        aliases.retain(|alias| alias != path);
+        if aliases.is_empty() {
+            table.links.remove(&primary);
+        }
+        return Unlink::Placeholder;
+    }
+    let Some(mut aliases) = table.links.remove(path) else {
+        return Unlink::Plain;
+    };
+    let new_primary = aliases.remove(0);
+    table.aliases.remove(&new_primary);
+    for alias in aliases.iter() {
+        table.aliases.insert(alias.clone(), new_primary.clone());
+    }
+    if !aliases.is_empty() {
+        table.links.insert(new_primary.clone(), aliases);
+    }
+    Unlink::Promote(new_primary)
+}
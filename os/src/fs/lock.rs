@@ -0,0 +1,325 @@
+//! # 文件锁
+//!
+//! ## Overview
+//! 劝告式文件锁，两类锁互不影响，与 Linux 一致：
+//! - `flock`：整个文件的共享 / 独占锁，属于打开的文件（dup 与 fork 得到的描述符共享同一把锁），
+//!   该打开的文件的最后一个引用消失时释放
+//! - POSIX 记录锁（fcntl `F_SETLK` / `F_SETLKW` / `F_GETLK`）：字节范围的读 / 写锁，属于进程，
+//!   进程关闭该文件的任意一个描述符或退出时全部释放
+//!
+//! ## Design
+//! - 每个文件一个 `FileLocks`，保存两类锁与等待队列，存放在全局表中
+//! - 无法立即加锁时把当前任务加入文件的等待队列，可被信号打断地阻塞；
+//!   任何锁被释放或降级时唤醒该文件的全部等待者，由它们重新检查冲突
+//! - POSIX 锁阻塞前检查等待图：沿“进程 → 阻塞它的进程”的链能回到自己时返回 EDEADLK，而不是永远等待
+//! - 设置 POSIX 锁时先从自己已有的锁中挖掉该范围（必要时拆成两段），再加入新锁；解锁只挖掉范围
+//!
+//! ## Assumptions
+//! - 锁只用于 FAT32 上的普通文件；FAT32 没有 inode 号，与页缓存一样以绝对路径作为文件的标识
+//! - flock 不做死锁检测，与 Linux 一致
+//! - 不会在中断上下文中加锁或解锁，全局表由 `UPIntrFreeCell` 保护
+//!
+//! ## Invariants
+//! - 同一进程在同一文件上的 POSIX 锁互不重叠
+//! - 同一打开的文件在同一文件上最多持有一把 flock
+//! - 没有锁也没有等待者的 `FileLocks` 会从全局表中移除
+
+use crate::sync::UPIntrFreeCell;
+use crate::task::{block_current_interruptible, current_task, wake_blocked, TaskControlBlock};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// POSIX 锁的范围一直延伸到文件末尾之后
+pub const LOCK_TO_EOF: u64 = u64::MAX;
+
+/// 锁的种类
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LockKind {
+    /// 共享锁（读锁）
+    Shared,
+    /// 独占锁（写锁）
+    Exclusive,
+}
+
+impl LockKind {
+    fn conflicts_with(self, other: LockKind) -> bool {
+        self == LockKind::Exclusive || other == LockKind::Exclusive
+    }
+}
+
+/// 一把 POSIX 记录锁，覆盖 `[start, end)`
+#[derive(Clone, Copy, Debug)]
+pub struct PosixLock {
+    pub pid: usize,
+    pub kind: LockKind,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl PosixLock {
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// 一把 flock，`owner` 标识持有它的打开的文件
+struct Flock {
+    owner: usize,
+    kind: LockKind,
+}
+
+/// 一个文件上的锁与等待者
+#[derive(Default)]
+struct FileLocks {
+    flocks: Vec<Flock>,
+    posix: Vec<PosixLock>,
+    waiters: Vec<Arc<TaskControlBlock>>,
+}
+
+impl FileLocks {
+    fn flock_conflict(&self, owner: usize, kind: LockKind) -> bool {
+        self.flocks
+            .iter()
+            .any(|lock| lock.owner != owner && lock.kind.conflicts_with(kind))
+    }
+
+    /// 与请求的锁 `req` 冲突的第一把其他进程的锁
+    fn posix_conflict(&self, req: &PosixLock) -> Option<PosixLock> {
+        self.posix
+            .iter()
+            .find(|lock| {
+                lock.pid != req.pid
+                    && lock.overlaps(req.start, req.end)
+                    && lock.kind.conflicts_with(req.kind)
+            })
+            .copied()
+    }
+
+    /// 从 `pid` 的锁中挖掉 `[start, end)`，返回是否有锁被修改
+    fn posix_unlock(&mut self, pid: usize, start: u64, end: u64) -> bool {
+        let mut changed = false;
+        let mut kept = Vec::with_capacity(self.posix.len());
+        for lock in self.posix.drain(..) {
+            if lock.pid != pid || !lock.overlaps(start, end) {
+                kept.push(lock);
+                continue;
+            }
+            changed = true;
+            if lock.start < start {
+                kept.push(PosixLock { end: start, ..lock });
+            }
+            if end < lock.end {
+                kept.push(PosixLock { start: end, ..lock });
+            }
+        }
+        self.posix = kept;
+        changed
+    }
+
+    fn wake_all(&mut self) {
+        for task in self.waiters.drain(..) {
+            wake_blocked(task);
+        }
+    }
+
+    fn remove_waiter(&mut self, task: &Arc<TaskControlBlock>) {
+        self.waiters.retain(|waiter| !Arc::ptr_eq(waiter, task));
+    }
+
+    fn is_unused(&self) -> bool {
+        self.flocks.is_empty() && self.posix.is_empty() && self.waiters.is_empty()
+    }
+}
+
+/// 全局锁表
+struct LockTable {
+    files: BTreeMap<String, FileLocks>,
+    /// 阻塞在 POSIX 锁上的进程 → 阻塞它的进程
+    blocked_on: BTreeMap<usize, usize>,
+}
+
+impl LockTable {
+    fn file(&mut self, path: &str) -> &mut FileLocks {
+        self.files.entry(String::from(path)).or_default()
+    }
+
+    fn remove_if_unused(&mut self, path: &str) {
+        if self.files.get(path).is_some_and(FileLocks::is_unused) {
+            self.files.remove(path);
+        }
+    }
+
+    /// `pid` 等待 `blocker` 是否会形成环
+    fn would_deadlock(&self, pid: usize, blocker: usize) -> bool {
+        let mut next = Some(blocker);
+        // 链的长度不超过等待中的进程数
+        for _ in 0..=self.blocked_on.len() {
+            match next {
+                Some(p) if p == pid => return true,
+                Some(p) => next = self.blocked_on.get(&p).copied(),
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+lazy_static! {
+    static ref LOCKS: UPIntrFreeCell<LockTable> = unsafe {
+        UPIntrFreeCell::new(LockTable {
+            files: BTreeMap::new(),
+            blocked_on: BTreeMap::new(),
+        })
+    };
+}
+
+/// 可被信号打断地阻塞，直到 `path` 上有锁被释放；被打断时移出等待队列并返回 `false`
+///
+/// 调用者已在释放锁表之前把当前任务加入了等待队列
+fn wait_for_unlock(path: &str) -> bool {
+    if !block_current_interruptible(|| false) {
+        return true;
+    }
+    let task = current_task().unwrap();
+    let mut table = LOCKS.exclusive_access();
+    table.file(path).remove_waiter(&task);
+    table.remove_if_unused(path);
+    false
+}
+
+/// 设置或解除 flock，`owner` 标识打开的文件，`kind` 为 `None` 表示解锁
+///
+/// 已持有的锁转换种类时先释放旧锁再加新锁，与 Linux 一样不保证原子性
+pub fn flock(path: &str, owner: usize, kind: Option<LockKind>, nonblock: bool) -> isize {
+    loop {
+        let mut table = LOCKS.exclusive_access();
+        let file = table.file(path);
+        if let Some(held) = file.flocks.iter().position(|lock| lock.owner == owner) {
+            if Some(file.flocks[held].kind) == kind {
+                return 0;
+            }
+            file.flocks.swap_remove(held);
+            file.wake_all();
+        }
+        let Some(kind) = kind else {
+            table.remove_if_unused(path);
+            return 0;
+        };
+        if !file.flock_conflict(owner, kind) {
+            file.flocks.push(Flock { owner, kind });
+            return 0;
+        }
+        if nonblock {
+            table.remove_if_unused(path);
+            return -1; // EWOULDBLOCK
+        }
+        file.waiters.push(current_task().unwrap());
+        drop(table);
+        if !wait_for_unlock(path) {
+            return -1; // EINTR
+        }
+    }
+}
+
+/// 释放打开的文件 `owner` 在 `path` 上的 flock，在其最后一个引用消失时调用
+pub fn release_flock(path: &str, owner: usize) {
+    let mut table = LOCKS.exclusive_access();
+    let Some(file) = table.files.get_mut(path) else {
+        return;
+    };
+    let before = file.flocks.len();
+    file.flocks.retain(|lock| lock.owner != owner);
+    if file.flocks.len() != before {
+        file.wake_all();
+        table.remove_if_unused(path);
+    }
+}
+
+/// 为进程 `pid` 在 `path` 的 `[start, end)` 上设置或解除 POSIX 锁，`kind` 为 `None` 表示解锁
+///
+/// `wait` 为 `false` 时遇到冲突返回 EAGAIN；为 `true` 时阻塞等待，会形成死锁时返回 EDEADLK
+pub fn posix_lock(
+    path: &str,
+    pid: usize,
+    kind: Option<LockKind>,
+    start: u64,
+    end: u64,
+    wait: bool,
+) -> isize {
+    loop {
+        let mut guard = LOCKS.exclusive_access();
+        let table = &mut *guard;
+        let file = table.file(path);
+        let Some(kind) = kind else {
+            if file.posix_unlock(pid, start, end) {
+                file.wake_all();
+            }
+            table.remove_if_unused(path);
+            return 0;
+        };
+        let req = PosixLock {
+            pid,
+            kind,
+            start,
+            end,
+        };
+        let Some(blocker) = file.posix_conflict(&req) else {
+            // 替换自己在该范围内的旧锁，降级或缩小时其他进程可能因此可以加锁
+            if file.posix_unlock(pid, start, end) {
+                file.wake_all();
+            }
+            file.posix.push(req);
+            table.blocked_on.remove(&pid);
+            return 0;
+        };
+        if !wait {
+            table.remove_if_unused(path);
+            return -1; // EAGAIN
+        }
+        if table.would_deadlock(pid, blocker.pid) {
+            table.blocked_on.remove(&pid);
+            table.remove_if_unused(path);
+            return -1; // EDEADLK
+        }
+        table.blocked_on.insert(pid, blocker.pid);
+        table.file(path).waiters.push(current_task().unwrap());
+        drop(guard);
+        if !wait_for_unlock(path) {
+            LOCKS.exclusive_access().blocked_on.remove(&pid);
+            return -1; // EINTR
+        }
+    }
+}
+
+/// `path` 上与请求的锁 `req` 冲突的锁（F_GETLK）
+pub fn posix_test(path: &str, req: &PosixLock) -> Option<PosixLock> {
+    let table = LOCKS.exclusive_access();
+    table.files.get(path)?.posix_conflict(req)
+}
+
+/// 释放进程 `pid` 在 `path` 上的全部 POSIX 锁，进程关闭该文件的任意描述符时调用
+pub fn release_posix_locks(path: &str, pid: usize) {
+    let mut table = LOCKS.exclusive_access();
+    let Some(file) = table.files.get_mut(path) else {
+        return;
+    };
+    if file.posix_unlock(pid, 0, LOCK_TO_EOF) {
+        file.wake_all();
+        table.remove_if_unused(path);
+    }
+}
+
+/// 释放进程 `pid` 在所有文件上的 POSIX 锁，进程退出时调用
+pub fn release_process_locks(pid: usize) {
+    let mut table = LOCKS.exclusive_access();
+    table.blocked_on.remove(&pid);
+    for file in table.files.values_mut() {
+        if file.posix_unlock(pid, 0, LOCK_TO_EOF) {
+            file.wake_all();
+        }
+    }
+    table.files.retain(|_, file| !file.is_unused());
+}
@@ -0,0 +1,129 @@
+//! # 文件权限元数据
+//!
+//! ## Overview
+//! FAT32 的目录项没有权限位与属主，本模块在内存中为文件补充这部分元数据：
+//! - `chmod` / `chown` 系列系统调用修改的权限位与 uid / gid 保存在这里
+//! - `stat` 与 `access` 优先使用这里记录的值，没有记录时退回默认值
+//!
+//! ## Assumptions
+//! - 与页缓存一样，以绝对路径作为键；硬链接的别名一律转到主路径（见 `link`），
+//!   同一路径在任意时刻只对应一个文件
+//! - 元数据只保存在内存中，重启后恢复为默认值；FAT32 能持久化的只读属性不在此维护
+//!
+//! ## Invariants
+//! - 记录的 `mode` 只包含权限位与 set-id / sticky 位（`0o7777`），不包含文件类型
+//! - 文件被删除时对应的记录随之删除，新建的同名文件不会继承旧的权限
+
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use lazy_static::lazy_static;
+
+/// 没有记录时文件与目录的默认权限
+pub const DEFAULT_MODE: u32 = 0o755;
+/// `mode` 中可由 `chmod` 修改的部分
+pub const MODE_MASK: u32 = 0o7777;
+/// 初始进程的文件创建掩码
+pub const DEFAULT_UMASK: u32 = 0o022;
+
+/// `access` 的检查项
+pub const R_OK: u32 = 4;
+pub const W_OK: u32 = 2;
+pub const X_OK: u32 = 1;
+
+#[derive(Clone, Copy)]
+pub struct FileMeta {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Default for FileMeta {
+    fn default() -> Self {
+        Self {
+            mode: DEFAULT_MODE,
+            uid: 0,
+            gid: 0,
+        }
+    }
+}
+
+impl FileMeta {
+    /// 以 `uid` / `gid` 的身份访问时，`want`（`R_OK | W_OK | X_OK` 的组合）是否全部允许
+    pub fn permits(&self, uid: u32, gid: u32, want: u32, is_dir: bool) -> bool {
+        if uid == 0 {
+            // root 不受读写权限限制，但执行普通文件至少需要一个 x 位
+            return want & X_OK == 0 || is_dir || self.mode & 0o111 != 0;
+        }
+        let granted = if uid == self.uid {
+            (self.mode >> 6) & 0o7
+        } else if gid == self.gid {
+            (self.mode >> 3) & 0o7
+        } else {
+            self.mode & 0o7
+        };
+        want & !granted == 0
+    }
+}
+
+lazy_static! {
+    static ref FILE_META: UPIntrFreeCell<BTreeMap<String, FileMeta>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+}
+
+/// 取得 `path` 记录的元数据，没有记录时返回 `None`
+pub fn file_meta(path: &str) -> Option<FileMeta> {
+    FILE_META.exclusive_access().get(path).copied()
+}
+
+/// 取得 `path` 的元数据，没有记录时返回默认值
+pub fn file_meta_or_default(path: &str) -> FileMeta {
+    file_meta(path).unwrap_or_default()
+}
+
+/// 修改 `path` 的权限位
+pub fn set_file_mode(path: &str, mode: u32) {
+    FILE_META
+        .exclusive_access()
+        .entry(String::from(path))
+        .or_default()
+        .mode = mode & MODE_MASK;
+}
+
+/// 修改 `path` 的属主，`None` 表示保持不变
+pub fn set_file_owner(path: &str, uid: Option<u32>, gid: Option<u32>) {
+    let mut table = FILE_META.exclusive_access();
+    let meta = table.entry(String::from(path)).or_default();
+    if let Some(uid) = uid {
+        meta.uid = uid;
+    }
+    if let Some(gid) = gid {
+        meta.gid = gid;
+    }
+}
+
+/// 记录新建的 `path` 的元数据：权限为 `mode & !umask`，属主为创建者
+pub fn init_file_meta(path: &str, mode: u32, umask: u32, uid: u32, gid: u32) {
+    FILE_META.exclusive_access().insert(
+        String::from(path),
+        FileMeta {
+            mode: mode & !umask & MODE_MASK,
+            uid,
+            gid,
+        },
+    );
+}
+
+/// 删除 `path` 的元数据（文件被删除时调用）
+pub fn drop_file_meta(path: &str) {
+    FILE_META.exclusive_access().remove(path);
+}
+
+/// 把 `old` 的元数据转给 `new`（文件被改名时调用）
+pub fn rename_file_meta(old: &str, new: &str) {
+    let mut table = FILE_META.exclusive_access();
+    table.remove(new);
+    if let Some(meta) = table.remove(old) {
+        table.insert(String::from(new), meta);
+    }
+}
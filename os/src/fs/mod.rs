@@ -1,16 +1,58 @@
 mod block_cache;
+mod devfs;
+pub(crate) mod epoll;
+mod eventfd;
 mod fat32;
+pub(crate) mod fat_name;
+mod fifo;
 mod file;
+mod ino;
 pub(crate) mod inode;
+mod link;
+mod lock;
+mod metadata;
+mod mount;
+mod page_cache;
 mod pipe;
+mod procfs;
+mod readahead;
+mod socket;
+mod splice;
 mod stdio;
+mod timerfd;
+mod tty;
 
 pub use block_cache::{block_cache_sync_all, get_block_cache};
+pub use devfs::{open_device, DEV_ROOT};
+pub use epoll::{Epoll, EpollEvent, EPOLL_CLOEXEC, EPOLL_CTL_DEL};
+pub use eventfd::{EventFd, EFD_CLOEXEC, EFD_NONBLOCK, EFD_SEMAPHORE};
 pub use fat32::FatFsBlockDevice;
-pub use file::{DirEntry, File, LinuxDirent64, UserStat};
+pub use fifo::{drop_fifo, is_fifo, make_fifo, open_fifo};
+pub use file::{DirEntry, File, LinuxDirent64, PollEvents, UserStat, BLK_SIZE};
+pub use ino::{alloc_ino, forget_ino, ino_of, rename_ino, SOCKFS_DEV};
 pub use inode::{
-    current_root_inode, list_apps, open_dir, open_file, open_file_at, open_initproc, resolve_path,
-    OpenFlags,
+    current_root_inode, list_apps, lookup_path, open_dir, open_file, open_file_at, open_initproc,
+    rename_path, resolve_path, OpenFlags,
 };
-pub use pipe::make_pipe;
+pub use link::{add_hard_link, follow, is_symlink, make_symlink, read_link, unlink, Unlink};
+pub use lock::{
+    flock, posix_lock, posix_test, release_posix_locks, release_process_locks, LockKind, PosixLock,
+    LOCK_TO_EOF,
+};
+pub use metadata::{
+    drop_file_meta, file_meta_or_default, init_file_meta, rename_file_meta, set_file_mode,
+    set_file_owner, DEFAULT_UMASK, R_OK, W_OK, X_OK,
+};
+pub use mount::{mount, umount};
+pub use page_cache::{
+    drop_page_cache, path_under, rename_page_cache, shrink_page_caches, sync_page_caches, PageCache,
+};
+pub use pipe::{make_pipe, Pipe};
+pub use procfs::{open_proc, PROC_ROOT};
+pub use readahead::POSIX_FADV_NOREUSE;
+pub use socket::{
+    make_socket_pair, SockAddrUn, Socket, AF_UNIX, SOCK_CLOEXEC, SOCK_NONBLOCK, SOCK_STREAM,
+};
+pub use splice::{splice, tee, SPLICE_F_GIFT, SPLICE_F_MORE, SPLICE_F_MOVE, SPLICE_F_NONBLOCK};
 pub use stdio::{Stdin, Stdout};
+pub use timerfd::{TimerFd, TFD_CLOEXEC, TFD_NONBLOCK, TFD_TIMER_ABSTIME};
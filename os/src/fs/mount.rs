@@ -0,0 +1,115 @@
+//! # 挂载表
+//!
+//! ## Overview
+//! 记录 `mount` 建立的挂载点，供 `umount2` 卸载：
+//! - `mount`：把来源、目标、文件系统类型与选项登记到挂载表
+//! - `umount`：检查挂载点是否繁忙，写回挂载点之下的缓存，然后从挂载表中移除
+//!
+//! ## Design
+//! - 内核只有根上的一个 FAT32 文件系统，挂载不改变路径解析，挂载点之下仍是原来的目录；
+//!   挂载表只记录挂载关系，使 mount / umount2 的成对调用与错误码符合 Linux
+//! - 繁忙：任一进程的当前目录或打开的文件在挂载点之下时卸载返回 EBUSY；
+//!   `MNT_DETACH` 不检查是否繁忙，立即从挂载表中移除，已打开的文件继续可用
+//! - 移除之前写回挂载点之下所有文件的页缓存中的脏页，再写回全部块缓存，
+//!   卸载返回时挂载点之下的修改都已落盘
+//!
+//! ## Assumptions
+//! - 块缓存不区分文件系统，卸载时整体写回
+//! - 文件映射不计入繁忙，映射的页在卸载时同样被写回
+//!
+//! ## Invariants
+//! - 挂载表中的目标互不相同，都是规范化的绝对路径
+
+use super::block_cache_sync_all;
+use super::page_cache::{path_under, sync_page_caches};
+use crate::sync::UPIntrFreeCell;
+use crate::task::{max_pid, pid2process};
+use alloc::string::String;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 挂载表中的一项
+struct Mount {
+    source: String,
+    target: String,
+    fstype: String,
+    flags: usize,
+}
+
+lazy_static! {
+    static ref MOUNTS: UPIntrFreeCell<Vec<Mount>> = unsafe { UPIntrFreeCell::new(Vec::new()) };
+}
+
+/// 把 `source` 以类型 `fstype`、选项 `flags` 挂载到目录 `target`
+///
+/// `remount` 为 `true` 时只修改已有挂载点的选项；`target` 已经是挂载点时返回 EBUSY
+pub fn mount(
+    source: &str,
+    target: &str,
+    fstype: &str,
+    flags: usize,
+    remount: bool,
+) -> Result<(), isize> {
+    let mut mounts = MOUNTS.exclusive_access();
+    let existing = mounts.iter().position(|mount| mount.target == target);
+    match (existing, remount) {
+        (Some(pos), true) => {
+            mounts[pos].flags = flags;
+            Ok(())
+        }
+        (None, true) => Err(-1),     // EINVAL
+        (Some(_), false) => Err(-1), // EBUSY
+        (None, false) => {
+            mounts.push(Mount {
+                source: String::from(source),
+                target: String::from(target),
+                fstype: String::from(fstype),
+                flags,
+            });
+            Ok(())
+        }
+    }
+}
+
+/// 卸载挂载点 `target`，`detach` 为 `true` 时不检查是否繁忙（`MNT_DETACH`）
+///
+/// `target` 不是挂载点时返回 EINVAL，仍在使用时返回 EBUSY
+pub fn umount(target: &str, detach: bool) -> Result<(), isize> {
+    if !MOUNTS
+        .exclusive_access()
+        .iter()
+        .any(|mount| mount.target == target)
+    {
+        return Err(-1); // EINVAL
+    }
+    if !detach && is_busy(target) {
+        return Err(-1); // EBUSY
+    }
+    sync_page_caches(target);
+    block_cache_sync_all();
+    let mut mounts = MOUNTS.exclusive_access();
+    if let Some(pos) = mounts.iter().position(|mount| mount.target == target) {
+        let mount = mounts.remove(pos);
+        log::info!(
+            "umount {} ({} on {}, flags {:#x})",
+            mount.target,
+            mount.fstype,
+            mount.source,
+            mount.flags
+        );
+    }
+    Ok(())
+}
+
+/// 是否有进程的当前目录或打开的文件在 `target` 之下
+fn is_busy(target: &str) -> bool {
+    (0..=max_pid()).filter_map(pid2process).any(|process| {
+        let inner = process.inner_exclusive_access();
+        path_under(&inner.cwd, target)
+            || inner
+                .fd_table
+                .iter()
+                .flatten()
+                .any(|file| path_under(&file.get_path(), target))
+    })
+}
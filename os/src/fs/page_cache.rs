@@ -0,0 +1,327 @@
+//! # 文件页缓存
+//!
+//! ## Overview
+//! 以文件页号为键缓存文件内容，每个文件（按绝对路径区分）对应一个 `PageCache`：
+//! - `read` / `read_at` 经由页缓存读取，缺页时从 FAT32 读入整页
+//! - `write` / `write_at` 写穿到 FAT32，同时更新已缓存的页
+//! - `MAP_SHARED` 文件映射直接把缓存页映射进用户地址空间，
+//!   因此映射与读写系统调用看到的是同一份数据
+//! - `prefetch` 供预读线程在后台读入页（见 `readahead`）
+//! - `sync_page_caches` 把某个目录之下所有文件的脏页写回磁盘，供卸载与 sync 使用
+//!
+//! ## Assumptions
+//! - FAT32 没有 inode 号，同一路径在任意时刻只对应一个文件，因此以绝对路径作为缓存的键
+//! - 通过可写共享映射修改的页无法逐次追踪，映射时即标记为脏页，在文件关闭时统一写回；
+//!   `msync` 可以提前把映射范围内的页写回
+//! - 从文件读入一页期间可能切换到其他任务，读入的页只在该页仍未被缓存时加入，
+//!   已缓存的页（可能已被映射）不会被替换
+//!
+//! ## Invariants
+//! - 缓存页的内容与磁盘上的文件内容一致，或者该页在 `dirty` 中
+//! - 文件末尾之后的部分在缓存页中恒为 0
+//! - 全局缓存表中页数超过 `PAGE_CACHE_LIMIT` 时，只回收没有被打开、也没有被映射的文件的缓存
+//! - 页帧不足时 `shrink_page_caches` 回收所有文件中既不脏、也没有被映射的缓存页
+
+use super::inode::{get_size, FatFile, ROOT_DIR};
+use crate::hal::PAGE_SIZE;
+use crate::mm::{frame_alloc, FrameTracker};
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+use fatfs::{Read, Seek, SeekFrom, Write};
+use lazy_static::lazy_static;
+
+/// 全局缓存页数的软上限
+const PAGE_CACHE_LIMIT: usize = 4096;
+
+struct PageCacheInner {
+    pages: BTreeMap<usize, Arc<FrameTracker>>,
+    dirty: BTreeSet<usize>,
+    /// 每次写入或截断时递增，预读据此判断读到的页是否已经过时
+    generation: usize,
+}
+
+pub struct PageCache {
+    inner: UPIntrFreeCell<PageCacheInner>,
+}
+
+impl PageCache {
+    fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(PageCacheInner {
+                    pages: BTreeMap::new(),
+                    dirty: BTreeSet::new(),
+                    generation: 0,
+                })
+            },
+        }
+    }
+
+    /// 取得第 `idx` 页，不在缓存中时从文件读入
+    pub fn get_page(&self, idx: usize, file: &mut FatFile) -> Option<Arc<FrameTracker>> {
+        if let Some(page) = self.inner.exclusive_access().pages.get(&idx) {
+            return Some(page.clone());
+        }
+        let frame = read_page(file, idx)?;
+        // 读入期间预读线程可能已缓存了这一页
+        Some(
+            self.inner
+                .exclusive_access()
+                .pages
+                .entry(idx)
+                .or_insert(frame)
+                .clone(),
+        )
+    }
+
+    /// 在后台读入第 `idx` 页，已缓存时不做处理；页帧不足或读取失败时返回 `false`
+    ///
+    /// 读入期间文件被写入或截断时丢弃读到的内容
+    pub fn prefetch(&self, idx: usize, file: &mut FatFile) -> bool {
+        let generation = {
+            let inner = self.inner.exclusive_access();
+            if inner.pages.contains_key(&idx) {
+                return true;
+            }
+            inner.generation
+        };
+        let Some(frame) = read_page(file, idx) else {
+            return false;
+        };
+        let mut inner = self.inner.exclusive_access();
+        if inner.generation == generation {
+            inner.pages.entry(idx).or_insert(frame);
+        }
+        true
+    }
+
+    /// 经由缓存从 `pos` 开始读取，`size` 为当前文件长度
+    pub fn read(&self, file: &mut FatFile, pos: usize, buf: &mut [u8], size: usize) -> usize {
+        let end = size.min(pos.saturating_add(buf.len()));
+        let mut cur = pos;
+        while cur < end {
+            let page = match self.get_page(cur / PAGE_SIZE, file) {
+                Some(page) => page,
+                None => break,
+            };
+            let in_page = cur % PAGE_SIZE;
+            let n = (PAGE_SIZE - in_page).min(end - cur);
+            buf[cur - pos..cur - pos + n]
+                .copy_from_slice(&page.ppn.get_bytes_array()[in_page..in_page + n]);
+            cur += n;
+        }
+        cur - pos
+    }
+
+    /// 把已写入文件 `pos` 处的数据同步到已缓存的页
+    pub fn update(&self, pos: usize, data: &[u8]) {
+        let mut inner = self.inner.exclusive_access();
+        inner.generation += 1;
+        let mut cur = pos;
+        let end = pos + data.len();
+        while cur < end {
+            let in_page = cur % PAGE_SIZE;
+            let n = (PAGE_SIZE - in_page).min(end - cur);
+            if let Some(page) = inner.pages.get(&(cur / PAGE_SIZE)) {
+                page.ppn.get_bytes_array()[in_page..in_page + n]
+                    .copy_from_slice(&data[cur - pos..cur - pos + n]);
+            }
+            cur += n;
+        }
+    }
+
+    /// 标记第 `idx` 页可能被映射修改过
+    pub fn mark_dirty(&self, idx: usize) {
+        self.inner.exclusive_access().dirty.insert(idx);
+    }
+
+    /// 将脏页写回文件，不会把文件扩展到 `size` 之外；
+    /// 仍被映射的页保留脏标记，之后还可能经由映射被修改
+    pub fn sync(&self, file: &mut FatFile, size: usize) {
+        let mut guard = self.inner.exclusive_access();
+        let inner = &mut *guard;
+        for &idx in inner.dirty.iter() {
+            if let Some(page) = inner.pages.get(&idx) {
+                write_page(file, idx, page, size);
+            }
+        }
+        let pages = &inner.pages;
+        inner.dirty.retain(|idx| {
+            pages
+                .get(idx)
+                .is_some_and(|page| Arc::strong_count(page) > 1)
+        });
+    }
+
+    /// 是否有脏页
+    fn has_dirty(&self) -> bool {
+        !self.inner.exclusive_access().dirty.is_empty()
+    }
+
+    /// 将 `pages` 中已缓存的页写回文件（msync），不会把文件扩展到 `size` 之外
+    ///
+    /// 可写共享映射之后仍可能修改这些页，因此保留脏标记
+    pub fn writeback_range(&self, file: &mut FatFile, size: usize, pages: Range<usize>) {
+        let inner = self.inner.exclusive_access();
+        for (&idx, page) in inner.pages.range(pages) {
+            write_page(file, idx, page, size);
+        }
+    }
+
+    /// 丢弃 `pages` 中既不脏、也没有被映射的缓存页，之后的读取重新从文件读入
+    pub fn invalidate(&self, pages: Range<usize>) {
+        let mut guard = self.inner.exclusive_access();
+        let inner = &mut *guard;
+        inner.pages.retain(|idx, page| {
+            !pages.contains(idx) || inner.dirty.contains(idx) || Arc::strong_count(page) > 1
+        });
+    }
+
+    /// 丢弃 `size` 之后的缓存内容（截断文件时调用）
+    pub fn truncate(&self, size: usize) {
+        let mut inner = self.inner.exclusive_access();
+        inner.generation += 1;
+        let first_gone = size.div_ceil(PAGE_SIZE);
+        inner.pages.retain(|&idx, _| idx < first_gone);
+        inner.dirty.retain(|&idx| idx < first_gone);
+        if size % PAGE_SIZE != 0 {
+            if let Some(page) = inner.pages.get(&(size / PAGE_SIZE)) {
+                page.ppn.get_bytes_array()[size % PAGE_SIZE..].fill(0);
+            }
+        }
+    }
+
+    fn page_count(&self) -> usize {
+        self.inner.exclusive_access().pages.len()
+    }
+
+    /// 丢弃既不脏、也没有被映射的缓存页，返回丢弃的页数；缓存正被使用时不做处理
+    fn shrink(&self) -> usize {
+        let mut guard = match self.inner.try_exclusive_access() {
+            Some(inner) => inner,
+            None => return 0,
+        };
+        let inner = &mut *guard;
+        let before = inner.pages.len();
+        inner
+            .pages
+            .retain(|idx, page| inner.dirty.contains(idx) || Arc::strong_count(page) > 1);
+        before - inner.pages.len()
+    }
+
+    /// 是否有缓存页仍被映射在某个地址空间中
+    fn is_mapped(&self) -> bool {
+        self.inner
+            .exclusive_access()
+            .pages
+            .values()
+            .any(|page| Arc::strong_count(page) > 1)
+    }
+}
+
+/// 从文件读入第 `idx` 页，文件末尾之后的部分为 0
+///
+/// 读入后恢复文件偏移，调用者（如 mmap）不应观察到偏移变化
+fn read_page(file: &mut FatFile, idx: usize) -> Option<Arc<FrameTracker>> {
+    let frame = Arc::new(frame_alloc()?);
+    let bytes = frame.ppn.get_bytes_array();
+    let saved = file.seek(SeekFrom::Current(0)).ok()?;
+    file.seek(SeekFrom::Start((idx * PAGE_SIZE) as u64)).ok()?;
+    let mut filled = 0;
+    while filled < PAGE_SIZE {
+        match file.read(&mut bytes[filled..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => filled += n,
+        }
+    }
+    file.seek(SeekFrom::Start(saved)).ok()?;
+    Some(frame)
+}
+
+/// 把第 `idx` 页写回文件，不会把文件扩展到 `size` 之外
+fn write_page(file: &mut FatFile, idx: usize, page: &FrameTracker, size: usize) {
+    let start = idx * PAGE_SIZE;
+    if start >= size {
+        return;
+    }
+    let len = PAGE_SIZE.min(size - start);
+    if file.seek(SeekFrom::Start(start as u64)).is_ok() {
+        let _ = file.write_all(&page.ppn.get_bytes_array()[..len]);
+    }
+}
+
+lazy_static! {
+    static ref PAGE_CACHES: UPIntrFreeCell<BTreeMap<String, Arc<PageCache>>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+}
+
+/// 取得 `path` 对应文件的页缓存，不存在时创建
+pub fn page_cache_of(path: &str) -> Arc<PageCache> {
+    let mut caches = PAGE_CACHES.exclusive_access();
+    if let Some(cache) = caches.get(path) {
+        return cache.clone();
+    }
+    let total: usize = caches.values().map(|cache| cache.page_count()).sum();
+    if total > PAGE_CACHE_LIMIT {
+        // 只回收没有打开的文件、也没有映射引用的缓存
+        caches.retain(|_, cache| Arc::strong_count(cache) > 1 || cache.is_mapped());
+    }
+    let cache = Arc::new(PageCache::new());
+    caches.insert(String::from(path), cache.clone());
+    cache
+}
+
+/// 内存回收函数：回收所有文件中干净且未被映射的缓存页
+pub fn shrink_page_caches() -> usize {
+    let caches: Vec<Arc<PageCache>> = match PAGE_CACHES.try_exclusive_access() {
+        Some(caches) => caches.values().cloned().collect(),
+        None => return 0,
+    };
+    caches.iter().map(|cache| cache.shrink()).sum()
+}
+
+/// 把 `old` 的页缓存转给 `new`（文件被改名时调用）
+pub fn rename_page_cache(old: &str, new: &str) {
+    let mut caches = PAGE_CACHES.exclusive_access();
+    if let Some(cache) = caches.remove(old) {
+        caches.insert(String::from(new), cache);
+    }
+}
+
+/// 把路径在目录 `dir` 之下的文件的脏页写回磁盘
+pub fn sync_page_caches(dir: &str) {
+    let caches: Vec<(String, Arc<PageCache>)> = PAGE_CACHES
+        .exclusive_access()
+        .iter()
+        .filter(|(path, cache)| path_under(path, dir) && cache.has_dirty())
+        .map(|(path, cache)| (path.clone(), cache.clone()))
+        .collect();
+    for (path, cache) in caches {
+        // 与预读线程一样另开一个文件句柄，不影响已打开实例的文件偏移
+        let Ok(mut file) = ROOT_DIR
+            .exclusive_access()
+            .open_file(path.trim_start_matches('/'))
+        else {
+            continue;
+        };
+        let size = get_size(&mut file) as usize;
+        cache.sync(&mut file, size);
+        let _ = file.flush();
+    }
+}
+
+/// 绝对路径 `path` 是否为目录 `dir` 本身或在其之下
+pub fn path_under(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// 丢弃 `path` 的页缓存（文件被删除时调用）
+pub fn drop_page_cache(path: &str) {
+    PAGE_CACHES.exclusive_access().remove(path);
+}
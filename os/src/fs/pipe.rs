@@ -1,11 +1,40 @@
-use super::UserStat;
+//! # 管道
+//!
+//! ## Overview
+//! 匿名管道、命名管道与本地套接字共用的字节流缓冲区：
+//! - `PipeRingBuffer`：堆上分配的环形缓冲区，默认容量 `DEFAULT_PIPE_SIZE`
+//! - `Pipe`：缓冲区的一个读端或写端，实现 `File`
+//!
+//! ## Design
+//! - 读写以切片为单位整段复制，用户缓冲区按页切分，因此每次加锁最多复制一页
+//! - 读到任意数据后立即返回，不等待凑满请求的长度；缓冲区为空时阻塞，所有写端关闭后返回 0
+//! - 不超过 `PIPE_BUF` 的写入是原子的：剩余空间不足以一次写完时等待，不与其他写者交错
+//! - 容量可由 fcntl `F_SETPIPE_SZ` 调整为 2 的幂个页，上限 `PIPE_MAX_SIZE`；
+//!   新容量放不下已缓冲的数据时返回 EBUSY
+//! - splice / tee 经由 `buffer` 直接访问缓冲区，以 `data` 取得尚未读出的数据、`consume` 丢弃已搬运的部分
+//!
+//! ## Assumptions
+//! - 等待时以让出处理器的方式轮询，被信号打断时返回已经传输的字节数
+
+use super::ino::{alloc_ino, ino_of, FAT_DEV, PIPEFS_DEV};
+use super::{PollEvents, UserStat};
+use crate::fs::file::BLK_SIZE;
+use crate::hal::PAGE_SIZE;
 use crate::mm::UserBuffer;
 use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
 use alloc::string::String;
-use alloc::sync::{Arc, Weak};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::any::Any;
-use crate::fs::file::BLK_SIZE;
-use crate::task::suspend_current_and_run_next;
+
+/// 管道缓冲区的默认容量
+pub const DEFAULT_PIPE_SIZE: usize = 16 * PAGE_SIZE;
+/// `F_SETPIPE_SZ` 允许的最大容量，与 Linux 的 `/proc/sys/fs/pipe-max-size` 默认值相同
+pub const PIPE_MAX_SIZE: usize = 1024 * 1024;
+/// 不超过该长度的写入是原子的
+pub const PIPE_BUF: usize = PAGE_SIZE;
 
 pub struct Pipe {
     readable: bool,
@@ -15,93 +44,166 @@ pub struct Pipe {
 }
 
 impl Pipe {
-    pub fn read_end_with_buffer(buffer: Arc<UPIntrFreeCell<PipeRingBuffer>>) -> Self {
+    /// 在 `buffer` 上打开一端，读写两端都打开时同时计入读者与写者
+    pub fn with_buffer(
+        buffer: Arc<UPIntrFreeCell<PipeRingBuffer>>,
+        readable: bool,
+        writable: bool,
+    ) -> Self {
+        let mut ring_buffer = buffer.exclusive_access();
+        ring_buffer.readers += readable as usize;
+        ring_buffer.writers += writable as usize;
+        drop(ring_buffer);
         Self {
-            readable: true,
-            writable: false,
+            readable,
+            writable,
             buffer,
             nonblocking: unsafe { UPIntrFreeCell::new(false) },
         }
     }
+    pub fn read_end_with_buffer(buffer: Arc<UPIntrFreeCell<PipeRingBuffer>>) -> Self {
+        Self::with_buffer(buffer, true, false)
+    }
     pub fn write_end_with_buffer(buffer: Arc<UPIntrFreeCell<PipeRingBuffer>>) -> Self {
-        Self {
-            readable: false,
-            writable: true,
-            buffer,
-            nonblocking: unsafe { UPIntrFreeCell::new(false) },
-        }
+        Self::with_buffer(buffer, false, true)
     }
 
     pub fn set_nonblocking(&self, nb: bool) {
         *self.nonblocking.exclusive_access() = nb;
     }
-}
 
-const RING_BUFFER_SIZE: usize = 32;
+    pub fn is_nonblocking(&self) -> bool {
+        *self.nonblocking.exclusive_access()
+    }
+
+    /// 两端共享的缓冲区，供 splice / tee 在内核中直接搬运数据
+    pub fn buffer(&self) -> &Arc<UPIntrFreeCell<PipeRingBuffer>> {
+        &self.buffer
+    }
+
+    /// 缓冲区容量（F_GETPIPE_SZ）
+    pub fn capacity(&self) -> usize {
+        self.buffer.exclusive_access().capacity()
+    }
+
+    /// 把缓冲区容量调整为不小于 `size` 的 2 的幂个页，返回实际容量（F_SETPIPE_SZ）
+    pub fn set_capacity(&self, size: usize) -> isize {
+        if size > PIPE_MAX_SIZE {
+            return -1; // EPERM
+        }
+        let size = size.max(PAGE_SIZE).next_power_of_two();
+        match self.buffer.exclusive_access().resize(size) {
+            Ok(()) => size as isize,
+            Err(err) => err,
+        }
+    }
+}
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let mut ring_buffer = self.buffer.exclusive_access();
+        ring_buffer.readers -= self.readable as usize;
+        ring_buffer.writers -= self.writable as usize;
+    }
 }
 
+/// 环形缓冲区，`[head, head + len)`（模容量）中是尚未读出的数据
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Vec<u8>,
     head: usize,
-    tail: usize,
-    status: RingBufferStatus,
-    write_end: Option<Weak<Pipe>>,
+    len: usize,
+    /// 打开的读端与写端个数
+    readers: usize,
+    writers: usize,
+    /// 管道所在文件系统的设备号与 inode 号，两端共享
+    dev: u64,
+    ino: u64,
 }
 
 impl PipeRingBuffer {
+    /// 匿名管道的缓冲区
     pub fn new() -> Self {
+        Self::with_ino(PIPEFS_DEV, alloc_ino())
+    }
+    /// 命名管道 `path` 的缓冲区，与磁盘上的节点报告相同的设备号与 inode 号
+    pub fn for_fifo(path: &str) -> Self {
+        Self::with_ino(FAT_DEV, ino_of(path))
+    }
+    fn with_ino(dev: u64, ino: u64) -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            arr: vec![0; DEFAULT_PIPE_SIZE],
             head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
-            write_end: None,
+            len: 0,
+            readers: 0,
+            writers: 0,
+            dev,
+            ino,
         }
     }
-    pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
-        self.write_end = Some(Arc::downgrade(write_end));
+    /// 读出至多 `dst.len()` 个字节，返回读出的字节数
+    pub fn read(&mut self, dst: &mut [u8]) -> usize {
+        let n = dst.len().min(self.len);
+        let first = n.min(self.arr.len() - self.head);
+        dst[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        dst[first..n].copy_from_slice(&self.arr[..n - first]);
+        self.head = (self.head + n) % self.arr.len();
+        self.len -= n;
+        n
     }
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
+    /// 写入 `src` 中能放下的部分，返回写入的字节数
+    pub fn write(&mut self, src: &[u8]) -> usize {
+        let n = src.len().min(self.available_write());
+        let tail = (self.head + self.len) % self.arr.len();
+        let first = n.min(self.arr.len() - tail);
+        self.arr[tail..tail + first].copy_from_slice(&src[..first]);
+        self.arr[..n - first].copy_from_slice(&src[first..n]);
+        self.len += n;
+        n
     }
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
-        }
-        c
+    /// 尚未读出的数据，绕回缓冲区末尾时分为两段
+    pub fn data(&self) -> (&[u8], &[u8]) {
+        let first = self.len.min(self.arr.len() - self.head);
+        (
+            &self.arr[self.head..self.head + first],
+            &self.arr[..self.len - first],
+        )
+    }
+    /// 丢弃开头的至多 `n` 个字节
+    pub fn consume(&mut self, n: usize) {
+        let n = n.min(self.len);
+        self.head = (self.head + n) % self.arr.len();
+        self.len -= n;
     }
     pub fn available_read(&self) -> usize {
-        if self.status == RingBufferStatus::Empty {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
-        }
+        self.len
     }
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::Full {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
+        self.arr.len() - self.len
+    }
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
+    }
+    /// 把容量改为 `size` 字节，已缓冲的数据保持不变；放不下已缓冲的数据时返回 EBUSY
+    pub fn resize(&mut self, size: usize) -> Result<(), isize> {
+        if size < self.len {
+            return Err(-1); // EBUSY
         }
+        let len = self.len;
+        let mut arr = vec![0; size];
+        self.read(&mut arr[..len]);
+        self.arr = arr;
+        self.head = 0;
+        self.len = len;
+        Ok(())
     }
     pub fn all_write_ends_closed(&self) -> bool {
-        self.write_end.as_ref().unwrap().upgrade().is_none()
+        self.writers == 0
+    }
+    pub fn readers(&self) -> usize {
+        self.readers
+    }
+    pub fn writers(&self) -> usize {
+        self.writers
     }
 }
 
@@ -109,8 +211,7 @@ impl PipeRingBuffer {
 pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
     let buffer = Arc::new(unsafe { UPIntrFreeCell::new(PipeRingBuffer::new()) });
     let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
-    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
-    buffer.exclusive_access().set_write_end(&write_end);
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer));
     (read_end, write_end)
 }
 
@@ -121,78 +222,77 @@ impl super::File for Pipe {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, buf: UserBuffer) -> usize {
+    fn read(&self, mut buf: UserBuffer) -> usize {
         assert!(self.readable());
-        let want_to_read = buf.len();
-        let mut buf_iter = buf.into_iter();
-        let mut already_read = 0usize;
         loop {
             let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_read = ring_buffer.available_read();
-            if loop_read == 0 {
-                // nonblocking: return immediately
-                if *self.nonblocking.exclusive_access() {
-                    return already_read;
-                }
-                if ring_buffer.all_write_ends_closed() {
-                    return already_read;
-                }
-                drop(ring_buffer);
-                suspend_current_and_run_next();
-                continue;
-            }
-            for _ in 0..loop_read {
-                if let Some(byte_ref) = buf_iter.next() {
-                    unsafe {
-                        *byte_ref = ring_buffer.read_byte();
+            if ring_buffer.available_read() > 0 {
+                // 读出当前缓冲的数据即返回，不等待凑满
+                let mut already_read = 0usize;
+                for chunk in buf.buffers.iter_mut() {
+                    let n = ring_buffer.read(chunk);
+                    already_read += n;
+                    if n < chunk.len() {
+                        break;
                     }
-                    already_read += 1;
-                    if already_read == want_to_read {
-                        return want_to_read;
-                    }
-                } else {
-                    return already_read;
                 }
+                return already_read;
             }
+            // nonblocking: return immediately
+            if *self.nonblocking.exclusive_access() || ring_buffer.all_write_ends_closed() {
+                return 0;
+            }
+            drop(ring_buffer);
+            // 被信号打断时由 sys_read 返回 EINTR
+            if signal_pending_of_current() {
+                return 0;
+            }
+            suspend_current_and_run_next();
         }
     }
     fn write(&self, buf: UserBuffer) -> usize {
         assert!(self.writable());
         let want_to_write = buf.len();
-        let mut buf_iter = buf.into_iter();
         let mut already_write = 0usize;
-        loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
-                // nonblocking: return immediately
-                if *self.nonblocking.exclusive_access() {
-                    return already_write;
-                }
-                drop(ring_buffer);
-                suspend_current_and_run_next();
-                continue;
-            }
-            // write at most loop_write bytes
-            for _ in 0..loop_write {
-                if let Some(byte_ref) = buf_iter.next() {
-                    ring_buffer.write_byte(unsafe { *byte_ref });
-                    already_write += 1;
-                    if already_write == want_to_write {
-                        return want_to_write;
-                    }
+        for chunk in buf.buffers.iter() {
+            let mut chunk: &[u8] = chunk;
+            while !chunk.is_empty() {
+                let mut ring_buffer = self.buffer.exclusive_access();
+                // 不超过 PIPE_BUF 的写入等到能一次写完
+                let need = if want_to_write <= PIPE_BUF {
+                    want_to_write - already_write
                 } else {
-                    return already_write;
+                    1
+                };
+                if ring_buffer.available_write() < need {
+                    // nonblocking: return immediately
+                    if *self.nonblocking.exclusive_access() {
+                        return already_write;
+                    }
+                    drop(ring_buffer);
+                    if signal_pending_of_current() {
+                        return already_write;
+                    }
+                    suspend_current_and_run_next();
+                    continue;
                 }
+                let n = ring_buffer.write(chunk);
+                already_write += n;
+                chunk = &chunk[n..];
             }
         }
+        already_write
     }
 
     fn get_stat(&self) -> UserStat {
         // Return a minimal but valid stat for FIFO/pipe
+        let (st_dev, st_ino) = {
+            let ring_buffer = self.buffer.exclusive_access();
+            (ring_buffer.dev, ring_buffer.ino)
+        };
         UserStat {
-            st_dev: 0,
-            st_ino: 0,
+            st_dev,
+            st_ino,
             // FIFO type
             st_mode: 0o010000,
             st_nlink: 1,
@@ -224,31 +324,30 @@ impl super::File for Pipe {
 
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
         if offset != 0 { /* pipes do not support offset */ }
-        let mut read_cnt = 0usize;
-        let mut ring_buffer = self.buffer.exclusive_access();
-        let avail = ring_buffer.available_read();
-        let to_read = core::cmp::min(avail, buf.len());
-        for i in 0..to_read {
-            buf[i] = ring_buffer.read_byte();
-            read_cnt += 1;
-        }
-        Ok(read_cnt)
+        Ok(self.buffer.exclusive_access().read(buf))
     }
 
     fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, isize> {
         if offset != 0 { /* pipes do not support offset */ }
-        let mut write_cnt = 0usize;
-        let mut ring_buffer = self.buffer.exclusive_access();
-        let avail = ring_buffer.available_write();
-        let to_write = core::cmp::min(avail, buf.len());
-        for i in 0..to_write {
-            ring_buffer.write_byte(buf[i]);
-            write_cnt += 1;
-        }
-        Ok(write_cnt)
+        Ok(self.buffer.exclusive_access().write(buf))
     }
 
     fn as_any(&self) -> &dyn Any {
-        todo!()
+        self
+    }
+
+    fn poll(&self) -> PollEvents {
+        let ring_buffer = self.buffer.exclusive_access();
+        let mut events = PollEvents::empty();
+        if self.readable {
+            // 所有写端关闭后读到 EOF，同样视为可读
+            events.set(PollEvents::POLLIN, ring_buffer.available_read() > 0);
+            events.set(PollEvents::POLLHUP, ring_buffer.all_write_ends_closed());
+        }
+        if self.writable {
+            events.set(PollEvents::POLLOUT, ring_buffer.available_write() > 0);
+            events.set(PollEvents::POLLERR, ring_buffer.readers() == 0);
+        }
+        events
     }
 }
@@ -0,0 +1,240 @@
+//! # 进程信息文件系统（procfs）
+//!
+//! ## Overview
+//! 挂载在 `/proc` 下的只读内存文件系统，文件内容在打开时由内核生成：
+//! - `meminfo`：内存与交换区统计，见 `stats::meminfo`
+//! - `loadavg`：负载平均值，见 `stats::loadavg`
+//! - `consoles`：已登记的控制台后端，见 `console::report`
+//! - `fault_inject`：各故障注入点的设置与统计（启用 `fault_inject` feature 时），见 `fault::report`
+//! - `<pid>/status`：进程的任务名、运行状态、父进程、进程组、内存大小、打开的文件数与线程数，
+//!   见 `stats::pid_status`
+//! - `<pid>/maps`：进程地址空间中的区域：起止地址、权限、文件偏移与映射的文件，见 `stats::pid_maps`
+//! - `<pid>/task/<tid>/status`：线程的状态，格式与 `<pid>/status` 相同
+//!
+//! ## Design
+//! - `/proc`、`/proc/<pid>`、`/proc/<pid>/task` 与 `/proc/<pid>/task/<tid>` 是目录，
+//!   可以用 getdents64 列出：根目录列出上面的文件与现有进程的 PID，`task` 列出进程现有线程的线程 ID
+//! - 线程 ID 与 `gettid` 的返回值相同，是线程在所属进程内的编号
+//!
+//! ## Assumptions
+//! - 与 devfs 相同，`/proc` 下的路径在打开时由 `open_proc` 拦截，不会落到磁盘文件系统上
+//!
+//! ## Invariants
+//! - 每次打开得到一份独立的快照，之后的读取与列目录都作用于这份快照
+
+use super::devfs::dev_stat;
+use super::ino::PROCFS_DEV;
+use super::{DirEntry, File, UserStat};
+use crate::mm::UserBuffer;
+use crate::stats::{pid_maps, pid_status, thread_ids};
+use crate::sync::UPIntrFreeCell;
+use crate::task::{max_pid, pid2process};
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// procfs 的挂载点
+pub const PROC_ROOT: &str = "/proc";
+
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+
+/// `/proc` 下的文件及其内容的生成函数
+const PROC_FILES: &[(&str, fn() -> String)] = &[
+    ("meminfo", crate::stats::meminfo),
+    ("loadavg", crate::stats::loadavg),
+    ("consoles", crate::console::report),
+    #[cfg(feature = "fault_inject")]
+    ("fault_inject", crate::fault::report),
+];
+
+/// 打开 `/proc` 下的文件或目录，`path` 必须是已解析的绝对路径
+///
+/// 路径不在 procfs 中时返回 `None`，调用者应继续交给磁盘文件系统处理
+pub fn open_proc(path: &str) -> Option<Arc<dyn File + Send + Sync>> {
+    let name = match path.strip_prefix(PROC_ROOT)? {
+        "" => "",
+        rest => rest.strip_prefix('/')?,
+    };
+    if let Some(entries) = dir_entries(name) {
+        return Some(Arc::new(ProcDir {
+            name: String::from(name),
+            entries: unsafe { UPIntrFreeCell::new(entries) },
+        }));
+    }
+    Some(Arc::new(ProcFile {
+        name: String::from(name),
+        content: file_content(name)?.into_bytes(),
+        offset: unsafe { UPIntrFreeCell::new(0) },
+    }))
+}
+
+/// procfs 中的文件 `name`（相对于 `/proc`）的内容，不存在时返回 `None`
+fn file_content(name: &str) -> Option<String> {
+    if let Some((_, generate)) = PROC_FILES.iter().find(|(file, _)| *file == name) {
+        return Some(generate());
+    }
+    match name.split('/').collect::<Vec<_>>().as_slice() {
+        [pid, "status"] => pid_status(pid.parse().ok()?, None),
+        [pid, "maps"] => pid_maps(pid.parse().ok()?),
+        [pid, "task", tid, "status"] => pid_status(pid.parse().ok()?, Some(tid.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// procfs 中的目录 `name`（相对于 `/proc`，根目录为空串）的各项，不是目录时返回 `None`
+fn dir_entries(name: &str) -> Option<VecDeque<DirEntry>> {
+    let entry = |d_name: String, is_dir: bool| DirEntry { d_name, is_dir };
+    let components: Vec<&str> = name.split('/').filter(|c| !c.is_empty()).collect();
+    let entries = match components.as_slice() {
+        [] => {
+            let files = PROC_FILES
+                .iter()
+                .map(|(file, _)| entry(String::from(*file), false));
+            let pids = (0..=max_pid())
+                .filter(|&pid| pid2process(pid).is_some())
+                .map(|pid| entry(pid.to_string(), true));
+            files.chain(pids).collect()
+        }
+        [pid] => {
+            pid2process(pid.parse().ok()?)?;
+            VecDeque::from([
+                entry(String::from("status"), false),
+                entry(String::from("maps"), false),
+                entry(String::from("task"), true),
+            ])
+        }
+        [pid, "task"] => thread_ids(pid.parse().ok()?)?
+            .into_iter()
+            .map(|tid| entry(tid.to_string(), true))
+            .collect(),
+        [pid, "task", tid] => {
+            let tid = tid.parse().ok()?;
+            if !thread_ids(pid.parse().ok()?)?.contains(&tid) {
+                return None;
+            }
+            VecDeque::from([entry(String::from("status"), false)])
+        }
+        _ => return None,
+    };
+    Some(entries)
+}
+
+/// `/proc` 下的只读文件，内容为打开时生成的快照
+pub struct ProcFile {
+    /// 相对于 `/proc` 的路径
+    name: String,
+    content: Vec<u8>,
+    /// `read` 使用的文件偏移
+    offset: UPIntrFreeCell<usize>,
+}
+
+impl File for ProcFile {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        let mut offset = self.offset.exclusive_access();
+        let mut total_read_size = 0;
+        for slice in buf.buffers {
+            let read_size = self.read_at(*offset, slice).unwrap();
+            *offset += read_size;
+            total_read_size += read_size;
+            if read_size < slice.len() {
+                break;
+            }
+        }
+        total_read_size
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn get_stat(&self) -> UserStat {
+        // 与 Linux 相同，内容在读取时生成，大小报告为 0
+        dev_stat(PROCFS_DEV, &self.get_path(), S_IFREG | 0o444, 0)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        alloc::format!("{}/{}", PROC_ROOT, self.name)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
+        let content = self.content.get(offset..).unwrap_or(&[]);
+        let len = content.len().min(buf.len());
+        buf[..len].copy_from_slice(&content[..len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1) // EACCES
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// procfs 中的目录，目录项为打开时生成的快照
+pub struct ProcDir {
+    /// 相对于 `/proc` 的路径，根目录为空串
+    name: String,
+    /// 尚未被 getdents64 读出的目录项
+    entries: UPIntrFreeCell<VecDeque<DirEntry>>,
+}
+
+impl File for ProcDir {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn get_stat(&self) -> UserStat {
+        dev_stat(PROCFS_DEV, &self.get_path(), S_IFDIR | 0o555, 0)
+    }
+
+    fn is_dir(&self) -> bool {
+        true
+    }
+
+    fn get_path(&self) -> String {
+        if self.name.is_empty() {
+            String::from(PROC_ROOT)
+        } else {
+            alloc::format!("{}/{}", PROC_ROOT, self.name)
+        }
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1) // EISDIR
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+
+    fn read_dir(&self) -> Result<Option<DirEntry>, isize> {
+        Ok(self.entries.exclusive_access().pop_front())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,218 @@
+//! # 顺序读取的预读
+//!
+//! ## Overview
+//! 顺序读取大文件时，在用户读到之前把后续的页读入页缓存，使之后的 `read` 直接命中缓存：
+//! - `ReadAhead`：每个打开的文件一份，识别顺序读取并决定下一次预读的范围
+//! - `submit`：把预读请求交给后台的内核线程 `kreadahead`，调用 `read` 的任务不等待
+//! - 读后丢弃（drop-behind）：长的顺序读取丢弃读过的页，复制大文件不会把其他文件挤出页缓存
+//! - `fadvise` 的建议（`Advice`）改变单个打开的文件的预读与丢弃策略
+//!
+//! ## Design
+//! - 本次读取从上次读取结束的位置开始即视为顺序读取，否则清空窗口，不预读
+//! - 顺序读取第一次预读紧随其后的 `RA_MIN_PAGES` 页；此后每当已预读的页被读到一半，
+//!   就预读下一窗口，窗口逐次翻倍，最大 `RA_MAX_PAGES` 页，不超出文件末尾
+//! - 请求放入全局队列，第一次提交时才创建内核线程；队列为空时线程阻塞，提交请求时唤醒。
+//!   线程每读入一页让出一次处理器，不会长时间占用
+//! - 线程以自己打开的 FAT 文件句柄读取，不影响用户的文件偏移；已缓存的页直接跳过
+//! - 同一次顺序读取超过 `DROP_BEHIND_MIN` 页之后，或建议为 `POSIX_FADV_SEQUENTIAL` 时，
+//!   每次读取后丢弃读取位置之前 `DROP_BEHIND_LAG` 页以外、本次顺序读取读过的页；
+//!   只丢弃既不脏、也没有被映射的页（`PageCache::invalidate`）
+//! - `POSIX_FADV_SEQUENTIAL` 第一次即预读最大窗口，`POSIX_FADV_RANDOM` 不预读
+//!
+//! ## Assumptions
+//! - 只有 `read` 参与顺序判断；`pread` 与文件映射仍按需读入
+//! - 文件已经没有打开的实例时放弃其请求；队列已满时丢弃新的请求，预读只是优化
+//! - 读入期间文件被写入或截断时，页缓存丢弃读到的页（见 `PageCache::prefetch`）
+//! - 页缓存按文件共享，读后丢弃也会丢弃其他打开实例刚读过的页，它们之后重新从磁盘读入
+
+use super::inode::ROOT_DIR;
+use super::page_cache::PageCache;
+use crate::hal::PAGE_SIZE;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{
+    block_current_and_run_next, kthread_spawn, suspend_current_and_run_next, wake_blocked,
+    TaskControlBlock, TaskName,
+};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::ops::Range;
+use lazy_static::lazy_static;
+
+/// 第一次预读的页数
+const RA_MIN_PAGES: usize = 4;
+/// 预读窗口的最大页数
+const RA_MAX_PAGES: usize = 32;
+/// 队列中最多等待的请求数
+const MAX_PENDING: usize = 16;
+/// 没有建议时，顺序读取超过该页数后开始读后丢弃
+const DROP_BEHIND_MIN: usize = 1024;
+/// 读后丢弃时保留在读取位置之前的页数
+const DROP_BEHIND_LAG: usize = RA_MAX_PAGES;
+
+pub const POSIX_FADV_NORMAL: usize = 0;
+pub const POSIX_FADV_RANDOM: usize = 1;
+pub const POSIX_FADV_SEQUENTIAL: usize = 2;
+pub const POSIX_FADV_WILLNEED: usize = 3;
+pub const POSIX_FADV_DONTNEED: usize = 4;
+pub const POSIX_FADV_NOREUSE: usize = 5;
+
+/// 打开的文件的访问模式建议
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Advice {
+    #[default]
+    Normal,
+    Sequential,
+    Random,
+}
+
+/// 一个打开的文件的预读状态
+#[derive(Default)]
+pub struct ReadAhead {
+    /// 上次读取结束的位置
+    prev_end: usize,
+    /// 上一次预读的页数，为 0 表示尚未开始预读
+    window: usize,
+    /// 已提交预读的页的结束页号
+    ra_end: usize,
+    /// 本次顺序读取中尚未被读后丢弃的第一页
+    drop_from: usize,
+    /// 本次顺序读取开始的页号
+    run_start: usize,
+    advice: Advice,
+}
+
+impl ReadAhead {
+    /// 记录一次从 `pos` 开始、长 `len` 的读取，返回应当预读的页；`size` 为文件长度
+    pub fn on_read(&mut self, pos: usize, len: usize, size: usize) -> Option<Range<usize>> {
+        let sequential = pos == self.prev_end;
+        self.prev_end = pos.saturating_add(len);
+        if !sequential {
+            self.window = 0;
+            self.ra_end = 0;
+            self.run_start = pos / PAGE_SIZE;
+            self.drop_from = self.run_start;
+            return None;
+        }
+        if self.advice == Advice::Random {
+            return None;
+        }
+        let read_end = self.prev_end.min(size).div_ceil(PAGE_SIZE);
+        let (start, window) = if self.window == 0 {
+            let first = match self.advice {
+                Advice::Sequential => RA_MAX_PAGES,
+                _ => RA_MIN_PAGES,
+            };
+            (read_end, first)
+        } else if read_end + self.window / 2 < self.ra_end {
+            // 上一窗口还剩一半以上没有被读到
+            return None;
+        } else {
+            (
+                self.ra_end.max(read_end),
+                (self.window * 2).min(RA_MAX_PAGES),
+            )
+        };
+        let end = (start + window).min(size.div_ceil(PAGE_SIZE));
+        if start >= end {
+            return None;
+        }
+        self.window = window;
+        self.ra_end = end;
+        Some(start..end)
+    }
+
+    /// 一次读取结束于 `pos` 之后，返回应当丢弃的已读过的页
+    pub fn drop_behind(&mut self, pos: usize) -> Option<Range<usize>> {
+        let read_page = pos / PAGE_SIZE;
+        let streaming = match self.advice {
+            Advice::Sequential => true,
+            Advice::Normal => read_page >= self.run_start + DROP_BEHIND_MIN,
+            Advice::Random => false,
+        };
+        let end = read_page.saturating_sub(DROP_BEHIND_LAG);
+        if !streaming || end <= self.drop_from {
+            return None;
+        }
+        let pages = self.drop_from..end;
+        self.drop_from = end;
+        Some(pages)
+    }
+
+    /// 按 `POSIX_FADV_NORMAL` / `SEQUENTIAL` / `RANDOM` 设置访问模式
+    pub fn set_advice(&mut self, advice: Advice) {
+        self.advice = advice;
+    }
+}
+
+/// 一个预读请求：把 `path` 的 `pages` 读入 `cache`
+struct Request {
+    path: String,
+    cache: Arc<PageCache>,
+    pages: Range<usize>,
+}
+
+struct ReadAheadQueue {
+    requests: VecDeque<Request>,
+    /// 预读线程，第一次提交请求时创建
+    worker: Option<Arc<TaskControlBlock>>,
+}
+
+lazy_static! {
+    static ref QUEUE: UPIntrFreeCell<ReadAheadQueue> = unsafe {
+        UPIntrFreeCell::new(ReadAheadQueue {
+            requests: VecDeque::new(),
+            worker: None,
+        })
+    };
+}
+
+/// 请求在后台把文件 `path` 的 `pages` 读入页缓存 `cache`
+pub fn submit(path: &str, cache: Arc<PageCache>, pages: Range<usize>) {
+    let mut queue = QUEUE.exclusive_access();
+    if queue.requests.len() >= MAX_PENDING {
+        return;
+    }
+    queue.requests.push_back(Request {
+        path: String::from(path),
+        cache,
+        pages,
+    });
+    match &queue.worker {
+        Some(worker) => wake_blocked(worker.clone()),
+        None => {
+            let worker = kthread_spawn(readahead_worker, 0);
+            worker.inner_exclusive_access().name = TaskName::new(b"kreadahead");
+            queue.worker = Some(worker);
+        }
+    }
+}
+
+/// 预读线程：依次处理队列中的请求，队列为空时阻塞
+fn readahead_worker(_: usize) {
+    loop {
+        // 请求只在任务上下文中提交，取队列与阻塞之间不会有新请求到来
+        let request = QUEUE.exclusive_access().requests.pop_front();
+        match request {
+            Some(request) => run(request),
+            None => block_current_and_run_next(),
+        }
+    }
+}
+
+fn run(request: Request) {
+    // 只剩全局缓存表与本请求持有：文件已经关闭
+    if Arc::strong_count(&request.cache) <= 2 {
+        return;
+    }
+    let path_in_fs = request.path.trim_start_matches('/');
+    let Ok(mut file) = ROOT_DIR.exclusive_access().open_file(path_in_fs) else {
+        return;
+    };
+    for idx in request.pages {
+        if !request.cache.prefetch(idx, &mut file) {
+            return;
+        }
+        suspend_current_and_run_next();
+    }
+}
@@ -0,0 +1,400 @@
+//! # AF_UNIX 流式套接字
+//!
+//! ## Overview
+//! 提供最小化的本地套接字实现，用于进程间通信：
+//! - `socketpair(AF_UNIX, SOCK_STREAM)` 直接得到一对已连接的套接字
+//! - `socket / bind / listen / connect / accept` 支持以路径命名的监听套接字
+//!
+//! 已连接的套接字由两条方向相反的管道组成，
+//! 读写语义（阻塞、非阻塞、对端关闭后读到 EOF）与 `Pipe` 完全一致。
+//!
+//! 已连接的套接字还可以经由 `sendmsg` / `recvmsg` 的 `SCM_RIGHTS` 控制消息把打开的文件传给对端：
+//! 每个方向上除了管道还有一个文件队列，`send_files` 把一条消息中的文件作为一批放入队列，
+//! 并记下该消息数据在字节流中的起始位置；对端读到这个位置之后，`take_files` 才取出这一批，
+//! 由 `recvmsg` 安装到接收进程的文件描述符表中。
+//!
+//! ## Assumptions
+//! - FAT32 无法保存套接字类型的目录项，
+//!   因此绑定的路径登记在内核的 `UNIX_SOCKETS` 命名表中，以绝对路径为键
+//! - 只支持 `SOCK_STREAM`
+//! - 一批文件随读到其消息数据的 `recvmsg` 交付，每次最多交付一批；只用 `read` 读取数据时
+//!   文件留在队列中，由之后第一次读到数据的 `recvmsg` 交付；连接关闭时未被取走的文件随队列一起释放
+//! - 文件在数据写入成功后才进入队列：数据超过管道容量而分多次写入时，对端可能先读到其中一部分，
+//!   这一批文件随对端下一次读到数据的 `recvmsg` 交付
+//! - 没有 Linux 回收 in-flight 文件环的垃圾回收：把套接字经由自身传递会使其无法释放
+//!
+//! ## Invariants
+//! - 命名表中只保存 `Weak` 引用，套接字关闭后其路径自动失效
+
+use super::ino::{alloc_ino, SOCKFS_DEV};
+use super::pipe::{make_pipe, Pipe};
+use super::{File, PollEvents, UserStat};
+use crate::fs::file::BLK_SIZE;
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::lazy_static;
+
+pub const AF_UNIX: usize = 1;
+pub const SOCK_STREAM: usize = 1;
+/// socket type 中附带的标志位
+pub const SOCK_NONBLOCK: usize = 0o4000;
+pub const SOCK_CLOEXEC: usize = 0o2000000;
+
+/// 用户态 `struct sockaddr_un`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SockAddrUn {
+    pub sun_family: u16,
+    pub sun_path: [u8; 108],
+}
+
+/// 一个方向上经由 `SCM_RIGHTS` 传递、尚未被取走的文件
+#[derive(Default)]
+struct FileQueue {
+    /// 写入该方向的字节总数
+    sent: usize,
+    /// 从该方向读出的字节总数
+    received: usize,
+    /// 每条消息一批文件，附带该消息数据在字节流中的起始位置
+    batches: VecDeque<(usize, Vec<Arc<dyn File + Send + Sync>>)>,
+}
+
+lazy_static! {
+    /// 已绑定路径 -> 套接字状态
+    static ref UNIX_SOCKETS: UPIntrFreeCell<BTreeMap<String, Weak<UPIntrFreeCell<SocketState>>>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+}
+
+/// 套接字所处的状态
+enum SocketState {
+    /// 刚创建
+    Unbound,
+    /// 已绑定到路径
+    Bound(String),
+    /// 正在监听，`pending` 为等待 accept 的服务端连接
+    Listening {
+        path: String,
+        backlog: usize,
+        pending: VecDeque<Arc<Socket>>,
+    },
+    /// 已连接：`rx` 读取对端数据，`tx` 写往对端；`rx_files` / `tx_files` 是同方向上传递的文件
+    Connected {
+        rx: Arc<Pipe>,
+        tx: Arc<Pipe>,
+        rx_files: Arc<UPIntrFreeCell<FileQueue>>,
+        tx_files: Arc<UPIntrFreeCell<FileQueue>>,
+    },
+}
+
+pub struct Socket {
+    /// 状态以 `Arc` 持有，命名表通过 `Weak` 引用它找到监听者
+    state: Arc<UPIntrFreeCell<SocketState>>,
+    nonblocking: UPIntrFreeCell<bool>,
+    ino: u64,
+}
+
+impl Socket {
+    pub fn new() -> Self {
+        Self::with_state(SocketState::Unbound)
+    }
+
+    fn with_state(state: SocketState) -> Self {
+        Self {
+            state: Arc::new(unsafe { UPIntrFreeCell::new(state) }),
+            nonblocking: unsafe { UPIntrFreeCell::new(false) },
+            ino: alloc_ino(),
+        }
+    }
+
+    /// 创建两个已连接端点的状态（两条方向相反的管道及各自的文件队列）
+    fn connected_states() -> (SocketState, SocketState) {
+        let (a_rx, b_tx) = make_pipe();
+        let (b_rx, a_tx) = make_pipe();
+        let a_files = Arc::new(unsafe { UPIntrFreeCell::new(FileQueue::default()) });
+        let b_files = Arc::new(unsafe { UPIntrFreeCell::new(FileQueue::default()) });
+        (
+            SocketState::Connected {
+                rx: a_rx,
+                tx: a_tx,
+                rx_files: a_files.clone(),
+                tx_files: b_files.clone(),
+            },
+            SocketState::Connected {
+                rx: b_rx,
+                tx: b_tx,
+                rx_files: b_files,
+                tx_files: a_files,
+            },
+        )
+    }
+
+    pub fn set_nonblocking(&self, nb: bool) {
+        *self.nonblocking.exclusive_access() = nb;
+        if let SocketState::Connected { rx, tx, .. } = &*self.state.exclusive_access() {
+            rx.set_nonblocking(nb);
+            tx.set_nonblocking(nb);
+        }
+    }
+
+    /// 将套接字绑定到 `path`，路径已被占用时失败
+    pub fn bind(&self, path: String) -> Result<(), isize> {
+        let mut state = self.state.exclusive_access();
+        if !matches!(*state, SocketState::Unbound) {
+            return Err(-1); // EINVAL
+        }
+        let mut table = UNIX_SOCKETS.exclusive_access();
+        if table.get(&path).and_then(|s| s.upgrade()).is_some() {
+            return Err(-1); // EADDRINUSE
+        }
+        table.insert(path.clone(), Arc::downgrade(&self.state));
+        *state = SocketState::Bound(path);
+        Ok(())
+    }
+
+    pub fn listen(&self, backlog: usize) -> Result<(), isize> {
+        let mut state = self.state.exclusive_access();
+        let path = match &*state {
+            SocketState::Bound(path) => path.clone(),
+            SocketState::Listening { .. } => return Ok(()),
+            _ => return Err(-1), // EINVAL
+        };
+        *state = SocketState::Listening {
+            path,
+            backlog: backlog.max(1),
+            pending: VecDeque::new(),
+        };
+        Ok(())
+    }
+
+    /// 连接到 `path` 上正在监听的套接字
+    pub fn connect(&self, path: &str) -> Result<(), isize> {
+        if matches!(
+            *self.state.exclusive_access(),
+            SocketState::Connected { .. }
+        ) {
+            return Err(-1); // EISCONN
+        }
+        let listener = UNIX_SOCKETS
+            .exclusive_access()
+            .get(path)
+            .and_then(|s| s.upgrade())
+            .ok_or(-1isize)?; // ECONNREFUSED
+        let (client, server) = Self::connected_states();
+        match &mut *listener.exclusive_access() {
+            SocketState::Listening {
+                backlog, pending, ..
+            } => {
+                if pending.len() >= *backlog {
+                    return Err(-1); // EAGAIN
+                }
+                pending.push_back(Arc::new(Self::with_state(server)));
+            }
+            _ => return Err(-1), // ECONNREFUSED
+        }
+        let nonblocking = *self.nonblocking.exclusive_access();
+        *self.state.exclusive_access() = client;
+        self.set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    /// 取出一个等待中的连接，没有连接时阻塞（非阻塞模式下直接失败）
+    pub fn accept(&self) -> Result<Arc<Socket>, isize> {
+        loop {
+            let mut state = self.state.exclusive_access();
+            match &mut *state {
+                SocketState::Listening { pending, .. } => {
+                    if let Some(conn) = pending.pop_front() {
+                        return Ok(conn);
+                    }
+                }
+                _ => return Err(-1), // EINVAL
+            }
+            drop(state);
+            if *self.nonblocking.exclusive_access() {
+                return Err(-1); // EAGAIN
+            }
+            if signal_pending_of_current() {
+                return Err(-1); // EINTR
+            }
+            suspend_current_and_run_next();
+        }
+    }
+
+    /// 把一批文件放入发往对端的队列（`SCM_RIGHTS`），它们随刚写入的 `len` 字节数据一起传递；
+    /// 未连接时返回 ENOTCONN
+    pub fn send_files(
+        &self,
+        files: Vec<Arc<dyn File + Send + Sync>>,
+        len: usize,
+    ) -> Result<(), isize> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { tx_files, .. } => {
+                let mut queue = tx_files.exclusive_access();
+                let start = queue.sent - len;
+                queue.batches.push_back((start, files));
+                Ok(())
+            }
+            _ => Err(-1), // ENOTCONN
+        }
+    }
+
+    /// 取出对端最早传来、其数据已被读到的一批文件
+    pub fn take_files(&self) -> Option<Vec<Arc<dyn File + Send + Sync>>> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { rx_files, .. } => {
+                let mut queue = rx_files.exclusive_access();
+                let received = queue.received;
+                match queue.batches.front() {
+                    Some((start, _)) if *start < received => {
+                        queue.batches.pop_front().map(|(_, files)| files)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn rx_files(&self) -> Option<Arc<UPIntrFreeCell<FileQueue>>> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { rx_files, .. } => Some(rx_files.clone()),
+            _ => None,
+        }
+    }
+
+    fn tx_files(&self) -> Option<Arc<UPIntrFreeCell<FileQueue>>> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { tx_files, .. } => Some(tx_files.clone()),
+            _ => None,
+        }
+    }
+
+    fn rx(&self) -> Option<Arc<Pipe>> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { rx, .. } => Some(rx.clone()),
+            _ => None,
+        }
+    }
+
+    fn tx(&self) -> Option<Arc<Pipe>> {
+        match &*self.state.exclusive_access() {
+            SocketState::Connected { tx, .. } => Some(tx.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let path = match &*self.state.exclusive_access() {
+            SocketState::Bound(path) | SocketState::Listening { path, .. } => path.clone(),
+            _ => return,
+        };
+        let mut table = UNIX_SOCKETS.exclusive_access();
+        // 只移除仍指向自己的登记项
+        if table
+            .get(&path)
+            .map_or(false, |s| Weak::ptr_eq(s, &Arc::downgrade(&self.state)))
+        {
+            table.remove(&path);
+        }
+    }
+}
+
+/// 创建一对已连接的 AF_UNIX 流套接字
+pub fn make_socket_pair() -> (Arc<Socket>, Arc<Socket>) {
+    let (a, b) = Socket::connected_states();
+    (
+        Arc::new(Socket::with_state(a)),
+        Arc::new(Socket::with_state(b)),
+    )
+}
+
+impl File for Socket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        let (Some(rx), Some(rx_files)) = (self.rx(), self.rx_files()) else {
+            return 0;
+        };
+        let n = rx.read(buf);
+        rx_files.exclusive_access().received += n;
+        n
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        let (Some(tx), Some(tx_files)) = (self.tx(), self.tx_files()) else {
+            return 0;
+        };
+        let n = tx.write(buf);
+        tx_files.exclusive_access().sent += n;
+        n
+    }
+
+    fn get_stat(&self) -> UserStat {
+        UserStat {
+            st_dev: SOCKFS_DEV,
+            st_ino: self.ino,
+            // socket type
+            st_mode: 0o140000,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: BLK_SIZE,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime_sec: 0,
+            st_atime_nsec: 0,
+            st_mtime_sec: 0,
+            st_mtime_nsec: 0,
+            st_ctime_sec: 0,
+            st_ctime_nsec: 0,
+            __unused: [0; 2],
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        String::from("socket")
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        // sockets do not support offset (ESPIPE)
+        Err(-1)
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// 已连接时由两条管道决定；监听时有等待中的连接即可读
+    fn poll(&self) -> PollEvents {
+        if let SocketState::Listening { pending, .. } = &*self.state.exclusive_access() {
+            let mut events = PollEvents::empty();
+            events.set(PollEvents::POLLIN, !pending.is_empty());
+            return events;
+        }
+        let rx = self.rx().map_or(PollEvents::empty(), |rx| rx.poll());
+        let tx = self.tx().map_or(PollEvents::empty(), |tx| tx.poll());
+        rx | tx
+    }
+}
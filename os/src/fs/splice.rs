@@ -0,0 +1,244 @@
+//! # splice 与 tee
+//!
+//! ## Overview
+//! 在内核中搬运管道中的数据，数据不经过用户空间：
+//! - `splice`：在管道与普通文件之间，或两个管道之间移动数据
+//! - `tee`：把一个管道中的数据复制到另一个管道，不从源管道中取走
+//!
+//! ## Design
+//! - 文件到管道：经由 `OSInode::page_at` 取得页缓存中的页，直接从缓存页复制到管道缓冲区，
+//!   缺页时与 read 一样从磁盘读入整页，之后的 splice 与 read 共享这一页
+//! - 管道到文件：以管道缓冲区中的数据直接调用 `write_at`，写穿到磁盘并更新页缓存
+//! - 管道之间：同时持有两个缓冲区，一次复制；`tee` 复制之后不调用 `consume`
+//! - 给出偏移时从该偏移读写，文件偏移不变；否则从文件偏移开始，并把文件偏移前移
+//! - 与管道的读写相同：没有数据可读或没有空间可写时阻塞，
+//!   `SPLICE_F_NONBLOCK` 或管道处于非阻塞模式时返回 EAGAIN，只要搬运了数据就立即返回
+//!
+//! ## Assumptions
+//! - 管道缓冲区是连续的字节数组而不是页的数组，无法引用缓存页，因此每次搬运仍有一次内核中的复制
+//! - 非管道的一端只支持 FAT32 上的普通文件，其他文件返回 EINVAL
+//! - `SPLICE_F_MOVE`、`SPLICE_F_MORE`、`SPLICE_F_GIFT` 只是提示，被忽略
+//!
+//! ## Invariants
+//! - 从源管道中取走的字节数等于写入目标的字节数
+
+use super::inode::OSInode;
+use super::pipe::Pipe;
+use super::File;
+use crate::hal::PAGE_SIZE;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use alloc::sync::Arc;
+
+pub const SPLICE_F_MOVE: u32 = 1;
+/// 管道上的操作不阻塞
+pub const SPLICE_F_NONBLOCK: u32 = 2;
+pub const SPLICE_F_MORE: u32 = 4;
+pub const SPLICE_F_GIFT: u32 = 8;
+
+fn pipe_of(file: &Arc<dyn File + Send + Sync>) -> Option<&Pipe> {
+    file.as_any().downcast_ref::<Pipe>()
+}
+
+/// 等待管道中有数据可读，所有写端都已关闭且没有数据时返回 `Ok(false)`
+fn wait_readable(pipe: &Pipe, nonblocking: bool) -> Result<bool, isize> {
+    loop {
+        let ring_buffer = pipe.buffer().exclusive_access();
+        if ring_buffer.available_read() > 0 {
+            return Ok(true);
+        }
+        if ring_buffer.all_write_ends_closed() {
+            return Ok(false);
+        }
+        drop(ring_buffer);
+        if nonblocking || pipe.is_nonblocking() {
+            return Err(-1); // EAGAIN
+        }
+        if signal_pending_of_current() {
+            return Err(-1); // EINTR
+        }
+        suspend_current_and_run_next();
+    }
+}
+
+/// 等待管道中有空间可写，所有读端都已关闭时返回 EPIPE
+fn wait_writable(pipe: &Pipe, nonblocking: bool) -> Result<(), isize> {
+    loop {
+        let ring_buffer = pipe.buffer().exclusive_access();
+        if ring_buffer.readers() == 0 {
+            return Err(-1); // EPIPE
+        }
+        if ring_buffer.available_write() > 0 {
+            return Ok(());
+        }
+        drop(ring_buffer);
+        if nonblocking || pipe.is_nonblocking() {
+            return Err(-1); // EAGAIN
+        }
+        if signal_pending_of_current() {
+            return Err(-1); // EINTR
+        }
+        suspend_current_and_run_next();
+    }
+}
+
+/// 从 `file_in` 向 `file_out` 移动至多 `len` 个字节，至少一端是管道，返回移动的字节数
+///
+/// `off_in` / `off_out` 为非管道一端的偏移，`None` 表示使用并前移文件偏移；管道一端给出偏移时返回 ESPIPE
+pub fn splice(
+    file_in: &Arc<dyn File + Send + Sync>,
+    off_in: Option<usize>,
+    file_out: &Arc<dyn File + Send + Sync>,
+    off_out: Option<usize>,
+    len: usize,
+    nonblocking: bool,
+) -> Result<usize, isize> {
+    if !file_in.readable() || !file_out.writable() {
+        return Err(-1); // EBADF
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    match (pipe_of(file_in), pipe_of(file_out)) {
+        (Some(_), _) if off_in.is_some() => Err(-1),  // ESPIPE
+        (_, Some(_)) if off_out.is_some() => Err(-1), // ESPIPE
+        (Some(src), Some(dst)) => pipe_to_pipe(src, dst, len, nonblocking, true),
+        (Some(src), None) => pipe_to_file(src, file_out, off_out, len, nonblocking),
+        (None, Some(dst)) => file_to_pipe(file_in, off_in, dst, len, nonblocking),
+        (None, None) => Err(-1), // EINVAL
+    }
+}
+
+/// 把管道 `file_in` 中至多 `len` 个字节复制到管道 `file_out`，不从 `file_in` 中取走，返回复制的字节数
+pub fn tee(
+    file_in: &Arc<dyn File + Send + Sync>,
+    file_out: &Arc<dyn File + Send + Sync>,
+    len: usize,
+    nonblocking: bool,
+) -> Result<usize, isize> {
+    if !file_in.readable() || !file_out.writable() {
+        return Err(-1); // EBADF
+    }
+    match (pipe_of(file_in), pipe_of(file_out)) {
+        (Some(_), Some(_)) if len == 0 => Ok(0),
+        (Some(src), Some(dst)) => pipe_to_pipe(src, dst, len, nonblocking, false),
+        _ => Err(-1), // EINVAL
+    }
+}
+
+/// 管道之间复制数据，`consume` 为 `true` 时从源管道中取走复制的部分
+fn pipe_to_pipe(
+    src: &Pipe,
+    dst: &Pipe,
+    len: usize,
+    nonblocking: bool,
+    consume: bool,
+) -> Result<usize, isize> {
+    if Arc::ptr_eq(src.buffer(), dst.buffer()) {
+        return Err(-1); // EINVAL
+    }
+    loop {
+        if !wait_readable(src, nonblocking)? {
+            return Ok(0);
+        }
+        wait_writable(dst, nonblocking)?;
+        let mut from = src.buffer().exclusive_access();
+        let mut to = dst.buffer().exclusive_access();
+        let mut copied = 0;
+        let (first, second) = from.data();
+        for segment in [first, second] {
+            let segment = &segment[..segment.len().min(len - copied)];
+            let n = to.write(segment);
+            copied += n;
+            if n < segment.len() {
+                break;
+            }
+        }
+        if consume {
+            from.consume(copied);
+        }
+        // 等待可写期间源管道可能已被读空，此时重新等待
+        if copied > 0 {
+            return Ok(copied);
+        }
+    }
+}
+
+/// 把管道中的数据写入普通文件
+fn pipe_to_file(
+    src: &Pipe,
+    file: &Arc<dyn File + Send + Sync>,
+    off: Option<usize>,
+    len: usize,
+    nonblocking: bool,
+) -> Result<usize, isize> {
+    let Some(inode) = file.as_any().downcast_ref::<OSInode>() else {
+        return Err(-1); // EINVAL
+    };
+    if !wait_readable(src, nonblocking)? {
+        return Ok(0);
+    }
+    let saved = inode.offset();
+    let pos = off.unwrap_or(saved);
+    let mut ring_buffer = src.buffer().exclusive_access();
+    let mut written = 0;
+    let (first, second) = ring_buffer.data();
+    for segment in [first, second] {
+        let segment = &segment[..segment.len().min(len - written)];
+        if segment.is_empty() {
+            break;
+        }
+        let n = match inode.write_at(pos + written, segment) {
+            Ok(n) => n,
+            Err(err) if written == 0 => return Err(err),
+            Err(_) => break,
+        };
+        written += n;
+        if n < segment.len() {
+            break;
+        }
+    }
+    ring_buffer.consume(written);
+    drop(ring_buffer);
+    // write_at 会移动文件偏移
+    inode.set_offset(if off.is_some() { saved } else { pos + written });
+    Ok(written)
+}
+
+/// 把普通文件的内容经由页缓存写入管道
+fn file_to_pipe(
+    file: &Arc<dyn File + Send + Sync>,
+    off: Option<usize>,
+    dst: &Pipe,
+    len: usize,
+    nonblocking: bool,
+) -> Result<usize, isize> {
+    let Some(inode) = file.as_any().downcast_ref::<OSInode>() else {
+        return Err(-1); // EINVAL
+    };
+    if inode.is_dir() {
+        return Err(-1); // EINVAL
+    }
+    wait_writable(dst, nonblocking)?;
+    let mut pos = off.unwrap_or_else(|| inode.offset());
+    let mut moved = 0;
+    while moved < len {
+        let Some((page, available)) = inode.page_at(pos) else {
+            break;
+        };
+        let start = pos % PAGE_SIZE;
+        let want = available.min(len - moved);
+        let n = dst
+            .buffer()
+            .exclusive_access()
+            .write(&page.ppn.get_bytes_array()[start..start + want]);
+        moved += n;
+        pos += n;
+        if n < want {
+            break;
+        }
+    }
+    if off.is_none() {
+        inode.set_offset(pos);
+    }
+    Ok(moved)
+}
@@ -1,6 +1,6 @@
-use super::File;
+use super::tty::{tty_ioctl, tty_poll, tty_read};
+use super::{File, PollEvents};
 use crate::fs::file::{Stat, UserStat};
-use crate::hal::console_getchar;
 use crate::mm::UserBuffer;
 use alloc::string::String;
 use core::any::Any;
@@ -15,20 +15,9 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-
-        // 根据 sbi 接口规定，若无输入则返回 usize::MAX
-        let ch = loop {
-            let c = console_getchar();
-            if c != usize::MAX {
-                break c;
-            }
-        };
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch as u8);
-        }
-        1
+    /// 经过行规程读入，默认按行缓冲并回显
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        tty_read(user_buf)
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
@@ -56,6 +45,12 @@ impl File for Stdin {
     fn as_any(&self) -> &dyn Any {
         self
     }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        tty_ioctl(cmd, arg)
+    }
+    fn poll(&self) -> PollEvents {
+        tty_poll()
+    }
 }
 
 impl File for Stdout {
@@ -69,9 +64,7 @@ impl File for Stdout {
         panic!("Cannot read from stdout!");
     }
     fn write(&self, user_buf: UserBuffer) -> usize {
-        for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
-        }
+        crate::console::write_slices(user_buf.buffers.iter().map(|buffer| &**buffer));
         user_buf.len()
     }
 
@@ -97,4 +90,8 @@ impl File for Stdout {
     fn as_any(&self) -> &dyn Any {
         self
     }
+    /// 标准输出同样是控制台，isatty 与窗口大小的查询作用于同一个终端
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        tty_ioctl(cmd, arg)
+    }
 }
@@ -0,0 +1,216 @@
+//! # timerfd
+//!
+//! ## Overview
+//! `timerfd_create` 创建的定时器文件，到期次数可以经由文件描述符读出，供事件循环与 poll 一起使用：
+//! - `set_time`（timerfd_settime）：设置或停止定时器，支持相对时间与本定时器时钟上的绝对时间
+//! - `get_time`（timerfd_gettime）：距离下次到期的时间与重复间隔
+//! - `read` 读出自上次读取以来的到期次数并清零，尚未到期时阻塞；`poll` 有未读的到期时可读
+//!
+//! ## Design
+//! - 到期由内核定时器（时间轮）驱动：回调只增加到期次数，有重复间隔时以上次的到期时间为基准再次设置，
+//!   处理延迟而错过的周期一并计入到期次数
+//! - 回调只持有状态的 `Weak` 引用，文件关闭后已设置的定时器不会使其无法释放；关闭时撤销定时器
+//! - 绝对时间按设置时刻的时钟换算为单调时间，之后调整墙上时间不影响已设置的定时器
+//!
+//! ## Assumptions
+//! - 与 eventfd 相同，缓冲区不足 8 字节或非阻塞模式下尚未到期时 `read` 返回 0，而不是 EINVAL / EAGAIN
+//! - 不支持 `TFD_TIMER_CANCEL_ON_SET`
+//!
+//! ## Invariants
+//! - 定时器停止时 `next_us` 与 `timer` 都为 `None`
+
+use super::eventfd::{anon_stat, put_u64};
+use super::ino::alloc_ino;
+use super::{File, PollEvents, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use crate::timer::{clock_now, get_time_us, ITimerSpec, TimeSpec, Timer, NSEC_PER_USEC};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use core::any::Any;
+
+/// timerfd_settime 的标志：`it_value` 是绝对时间
+pub const TFD_TIMER_ABSTIME: u32 = 1;
+/// timerfd_create 的标志，与 `O_NONBLOCK` / `O_CLOEXEC` 的取值相同
+pub const TFD_NONBLOCK: u32 = 0o4000;
+pub const TFD_CLOEXEC: u32 = 0o2000000;
+
+struct TimerFdInner {
+    /// 尚未被读取的到期次数
+    expirations: u64,
+    /// 下一次到期的单调时间（微秒）
+    next_us: Option<usize>,
+    /// 重复间隔（微秒），为零时只到期一次
+    interval_us: usize,
+    timer: Option<Timer>,
+    nonblocking: bool,
+}
+
+/// timerfd 文件
+pub struct TimerFd {
+    /// 创建时指定的时钟，解释绝对时间时使用
+    clock: usize,
+    inner: Arc<UPIntrFreeCell<TimerFdInner>>,
+    ino: u64,
+}
+
+/// 向上取整到微秒
+fn timespec_to_us(time: TimeSpec) -> usize {
+    time.to_ns().div_ceil(NSEC_PER_USEC)
+}
+
+impl TimerFd {
+    /// 创建以 `clock` 为时钟、尚未设置的 timerfd，调用者保证 `clock` 受 `clock_now` 支持
+    pub fn new(clock: usize, nonblocking: bool) -> Self {
+        Self {
+            clock,
+            inner: Arc::new(unsafe {
+                UPIntrFreeCell::new(TimerFdInner {
+                    expirations: 0,
+                    next_us: None,
+                    interval_us: 0,
+                    timer: None,
+                    nonblocking,
+                })
+            }),
+            ino: alloc_ino(),
+        }
+    }
+
+    /// 当前设置，`it_value` 为距离下次到期的剩余时间，停止时为零
+    pub fn get_time(&self) -> ITimerSpec {
+        let inner = self.inner.exclusive_access();
+        let remaining_us = inner
+            .next_us
+            .map_or(0, |next| next.saturating_sub(get_time_us()).max(1));
+        ITimerSpec {
+            it_interval: TimeSpec::from_ns(inner.interval_us * NSEC_PER_USEC),
+            it_value: TimeSpec::from_ns(remaining_us * NSEC_PER_USEC),
+        }
+    }
+
+    /// 设置定时器并返回原先的设置，`new.it_value` 为零时停止定时器，同时清零未读的到期次数
+    ///
+    /// `abstime` 为 `true` 时 `new.it_value` 是本定时器时钟上的绝对时间，已经过去时立即到期
+    pub fn set_time(&self, new: ITimerSpec, abstime: bool) -> ITimerSpec {
+        let old = self.get_time();
+        let mut inner = self.inner.exclusive_access();
+        if let Some(timer) = inner.timer.take() {
+            timer.cancel();
+        }
+        inner.expirations = 0;
+        inner.next_us = None;
+        inner.interval_us = timespec_to_us(new.it_interval);
+        if new.it_value != TimeSpec::new() {
+            let delay = if abstime {
+                new.it_value - clock_now(self.clock).unwrap()
+            } else {
+                new.it_value
+            };
+            let expire_us = get_time_us() + timespec_to_us(delay);
+            inner.next_us = Some(expire_us);
+            inner.timer = Some(arm(Arc::downgrade(&self.inner), expire_us));
+        }
+        old
+    }
+}
+
+/// 在 `expire_us` 记录一次到期，有重复间隔时再次设置
+fn arm(state: Weak<UPIntrFreeCell<TimerFdInner>>, expire_us: usize) -> Timer {
+    Timer::call_at_us(expire_us, move || {
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let mut inner = state.exclusive_access();
+        inner.expirations += 1;
+        let interval = inner.interval_us;
+        if interval == 0 {
+            inner.next_us = None;
+            inner.timer = None;
+            return;
+        }
+        let mut next = expire_us + interval;
+        let now = get_time_us();
+        if next <= now {
+            let missed = (now - next) / interval + 1;
+            inner.expirations += missed as u64;
+            next += missed * interval;
+        }
+        inner.next_us = Some(next);
+        inner.timer = Some(arm(Arc::downgrade(&state), next));
+    })
+}
+
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        if let Some(timer) = self.inner.exclusive_access().timer.take() {
+            timer.cancel();
+        }
+    }
+}
+
+impl File for TimerFd {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() < 8 {
+            return 0; // EINVAL
+        }
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if inner.expirations > 0 {
+                let expirations = core::mem::take(&mut inner.expirations);
+                return put_u64(&mut buf, expirations);
+            }
+            if inner.nonblocking {
+                return 0; // EAGAIN
+            }
+            drop(inner);
+            if signal_pending_of_current() {
+                return 0;
+            }
+            suspend_current_and_run_next();
+        }
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn get_stat(&self) -> UserStat {
+        anon_stat(self.ino)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        String::from("anon_inode:[timerfd]")
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1) // ESPIPE
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1) // ESPIPE
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        events.set(
+            PollEvents::POLLIN,
+            self.inner.exclusive_access().expirations > 0,
+        );
+        events
+    }
+}
@@ -0,0 +1,345 @@
+//! # 控制台的行规程（line discipline）
+//!
+//! ## Overview
+//! 标准输入与 `/dev/tty` 共用的终端输入处理，行为由 termios 设置决定：
+//! - 规范模式（`ICANON`，默认）：输入按行缓冲，`read` 每次最多返回一行；
+//!   行内可以用 `VERASE` 删除一个字符、`VWERASE` 删除一个词、`VKILL` 删除整行，
+//!   行首的 `VEOF` 使 `read` 返回 0
+//! - 非规范模式：收到的字节立即可读，`read` 至少等到 `VMIN` 个字节（`VMIN` 为 0 时不等待）
+//! - 回显（`ECHO`）：收到的字符写回控制台，删除时以 `"\b \b"` 擦除
+//! - ioctl：`TCGETS` / `TCSETS` / `TCSETSW` / `TCSETSF` 读写 termios，`TIOCGWINSZ` / `TIOCSWINSZ` 读写窗口大小
+//!
+//! ## Design
+//! - 控制台只有一个，行规程是全局唯一的状态，所有打开的标准输入共享同一个输入队列
+//! - 输入来自主控制台（见 `console::getchar`），`read` 与 `poll` 先把其上已有的字符全部取入行规程（`pump`），
+//!   没有可读的输入时让出处理器，下次被调度时再取
+//! - 完成的行放入 `lines`，规范模式下 `read` 取出一行，读不完的部分留到下一次；
+//!   行首的 `VEOF` 以空行表示
+//!
+//! ## Assumptions
+//! - 不支持 `ISIG`：`VINTR` / `VQUIT` / `VSUSP` 作为普通字符处理，不产生信号；也没有前台进程组
+//! - 只处理输入：`c_oflag` 只被保存，输出不做 `ONLCR` 等转换；`c_cflag` 的波特率等设置被忽略
+//! - 很多终端的退格键发送 `^H` 而不是 `VERASE`（`DEL`），规范模式下两者都删除一个字符
+//! - `VTIME` 被忽略，非规范模式下不会超时返回
+//!
+//! ## Invariants
+//! - `line` 的长度不超过 `LINE_MAX - 1`，总为行结束符留出位置
+
+use crate::mm::{copy_to_user, get_from_user, UserBuffer};
+use crate::sync::UPIntrFreeCell;
+use crate::task::{current_user_token, signal_pending_of_current, suspend_current_and_run_next};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+use super::PollEvents;
+
+/// 读取 termios
+pub const TCGETS: usize = 0x5401;
+/// 立即设置 termios
+pub const TCSETS: usize = 0x5402;
+/// 等待输出完成后设置 termios，控制台的输出是同步的，与 `TCSETS` 相同
+pub const TCSETSW: usize = 0x5403;
+/// 丢弃尚未读取的输入后设置 termios
+pub const TCSETSF: usize = 0x5404;
+pub const TIOCGWINSZ: usize = 0x5413;
+pub const TIOCSWINSZ: usize = 0x5414;
+
+/// `c_cc` 的长度
+pub const NCCS: usize = 19;
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSUSP: usize = 10;
+pub const VWERASE: usize = 14;
+
+/// `c_iflag`：把收到的 `\n` 转换为 `\r`
+pub const INLCR: u32 = 0o100;
+/// `c_iflag`：丢弃收到的 `\r`
+pub const IGNCR: u32 = 0o200;
+/// `c_iflag`：把收到的 `\r` 转换为 `\n`
+pub const ICRNL: u32 = 0o400;
+pub const OPOST: u32 = 0o1;
+pub const ONLCR: u32 = 0o4;
+pub const B38400: u32 = 0o17;
+pub const CS8: u32 = 0o60;
+pub const CREAD: u32 = 0o200;
+pub const ISIG: u32 = 0o1;
+/// `c_lflag`：规范模式
+pub const ICANON: u32 = 0o2;
+/// `c_lflag`：回显收到的字符
+pub const ECHO: u32 = 0o10;
+/// `c_lflag`：`VERASE` / `VWERASE` 回显为擦除屏幕上的字符
+pub const ECHOE: u32 = 0o20;
+/// `c_lflag`：`VKILL` 回显为擦除整行
+pub const ECHOK: u32 = 0o40;
+/// `c_lflag`：即使没有 `ECHO` 也回显换行
+pub const ECHONL: u32 = 0o100;
+/// `c_lflag`：启用 `VWERASE` 等扩展的编辑字符
+pub const IEXTEN: u32 = 0o100000;
+
+/// 规范模式下一行的最大长度（含行结束符），与 Linux 的 `N_TTY_BUF_SIZE` 相同
+const LINE_MAX: usize = 4096;
+/// 退格
+const BS: u8 = 0x08;
+
+/// 内核的 `struct termios`（asm-generic，不含波特率字段）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// 与 Linux 新打开的终端相同：规范模式、回显、`\r` 转换为 `\n`
+    fn new() -> Self {
+        let mut c_cc = [0u8; NCCS];
+        c_cc[VINTR] = 0x03;
+        c_cc[VQUIT] = 0x1c;
+        c_cc[VERASE] = 0x7f;
+        c_cc[VKILL] = 0x15;
+        c_cc[VEOF] = 0x04;
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        c_cc[VSUSP] = 0x1a;
+        c_cc[VWERASE] = 0x17;
+        Self {
+            c_iflag: ICRNL,
+            c_oflag: OPOST | ONLCR,
+            c_cflag: B38400 | CS8 | CREAD,
+            c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK | IEXTEN,
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+/// `struct winsize`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+struct LineDiscipline {
+    termios: Termios,
+    winsize: WinSize,
+    /// 规范模式下正在编辑的行
+    line: Vec<u8>,
+    /// 可以被读取的输入：规范模式下每项是完成的一行（空行表示 EOF），非规范模式下是收到的字节
+    lines: VecDeque<Vec<u8>>,
+}
+
+lazy_static! {
+    static ref TTY: UPIntrFreeCell<LineDiscipline> = unsafe {
+        UPIntrFreeCell::new(LineDiscipline {
+            termios: Termios::new(),
+            // 串口无法得知窗口大小，按最常见的终端大小报告
+            winsize: WinSize {
+                ws_row: 24,
+                ws_col: 80,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            },
+            line: Vec::new(),
+            lines: VecDeque::new(),
+        })
+    };
+}
+
+fn echo(bytes: &[u8]) {
+    crate::console::write_slices(core::iter::once(bytes));
+}
+
+impl LineDiscipline {
+    fn lflag(&self, flag: u32) -> bool {
+        self.termios.c_lflag & flag != 0
+    }
+
+    /// 取入主控制台上已有的全部字符
+    fn pump(&mut self) {
+        while let Some(c) = crate::console::getchar() {
+            self.receive(c);
+        }
+    }
+
+    /// 擦除正在编辑的行的最后一个字符
+    fn erase(&mut self) -> bool {
+        if self.line.pop().is_none() {
+            return false;
+        }
+        if self.lflag(ECHO) && self.lflag(ECHOE) {
+            echo(b"\x08 \x08");
+        }
+        true
+    }
+
+    /// 处理收到的一个字符
+    fn receive(&mut self, mut c: u8) {
+        let iflag = self.termios.c_iflag;
+        match c {
+            b'\r' if iflag & IGNCR != 0 => return,
+            b'\r' if iflag & ICRNL != 0 => c = b'\n',
+            b'\n' if iflag & INLCR != 0 => c = b'\r',
+            _ => {}
+        }
+        if !self.lflag(ICANON) {
+            match self.lines.back_mut() {
+                Some(bytes) => bytes.push(c),
+                None => self.lines.push_back(vec![c]),
+            }
+            if self.lflag(ECHO) {
+                echo(&[c]);
+            }
+            return;
+        }
+        let cc = self.termios.c_cc;
+        if c == cc[VERASE] || c == BS {
+            self.erase();
+        } else if c == cc[VKILL] {
+            if self.lflag(ECHOK) {
+                while self.erase() {}
+            } else {
+                self.line.clear();
+            }
+        } else if c == cc[VWERASE] && self.lflag(IEXTEN) {
+            while self.line.last() == Some(&b' ') {
+                self.erase();
+            }
+            while self.line.last().is_some_and(|c| *c != b' ') {
+                self.erase();
+            }
+        } else if c == cc[VEOF] {
+            // 行首的 EOF 留下空行，使 read 返回 0
+            self.lines.push_back(core::mem::take(&mut self.line));
+        } else if c == b'\n' {
+            self.line.push(c);
+            self.lines.push_back(core::mem::take(&mut self.line));
+            if self.lflag(ECHO) || self.lflag(ECHONL) {
+                echo(b"\n");
+            }
+        } else if self.line.len() < LINE_MAX - 1 {
+            self.line.push(c);
+            if self.lflag(ECHO) {
+                echo(&[c]);
+            }
+        }
+    }
+
+    /// 有可以交给 `read` 的输入
+    fn readable(&self) -> bool {
+        if self.lflag(ICANON) {
+            !self.lines.is_empty()
+        } else {
+            let min = self.termios.c_cc[VMIN] as usize;
+            self.lines.iter().map(Vec::len).sum::<usize>() >= min.max(1)
+        }
+    }
+
+    /// 取出至多 `len` 字节的输入，规范模式下最多一行
+    fn take(&mut self, len: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        while data.len() < len {
+            let Some(mut bytes) = self.lines.pop_front() else {
+                break;
+            };
+            let n = bytes.len().min(len - data.len());
+            data.extend(bytes.drain(..n));
+            if !bytes.is_empty() {
+                self.lines.push_front(bytes);
+            }
+            if self.lflag(ICANON) {
+                break;
+            }
+        }
+        data
+    }
+}
+
+/// 从控制台读入，语义见模块文档；等待期间收到信号时返回已读到的部分（可能为 0）
+pub fn tty_read(mut buf: UserBuffer) -> usize {
+    if buf.len() == 0 {
+        return 0;
+    }
+    loop {
+        let mut tty = TTY.exclusive_access();
+        tty.pump();
+        let nonblocking = !tty.lflag(ICANON) && tty.termios.c_cc[VMIN] == 0;
+        if tty.readable() || nonblocking {
+            let data = tty.take(buf.len());
+            drop(tty);
+            return buf.write_buffer(None, &data);
+        }
+        drop(tty);
+        if signal_pending_of_current() {
+            return 0;
+        }
+        suspend_current_and_run_next();
+    }
+}
+
+/// 控制台输入的就绪状态
+pub fn tty_poll() -> PollEvents {
+    let mut tty = TTY.exclusive_access();
+    tty.pump();
+    let mut events = PollEvents::empty();
+    events.set(PollEvents::POLLIN, tty.readable());
+    events
+}
+
+/// 终端相关的 ioctl，`arg` 为用户态指针
+pub fn tty_ioctl(cmd: usize, arg: usize) -> isize {
+    let token = current_user_token();
+    match cmd {
+        TCGETS => {
+            let termios = TTY.exclusive_access().termios;
+            if copy_to_user(token, &termios, arg as *mut Termios).is_err() {
+                return -1; // EFAULT
+            }
+        }
+        TCSETS | TCSETSW | TCSETSF => {
+            let Ok(termios) = get_from_user(token, arg as *const Termios) else {
+                return -1; // EFAULT
+            };
+            let mut tty = TTY.exclusive_access();
+            if cmd == TCSETSF {
+                tty.line.clear();
+                tty.lines.clear();
+            } else if termios.c_lflag & ICANON == 0 && tty.lflag(ICANON) {
+                // 离开规范模式时正在编辑的行立即可读
+                let line = core::mem::take(&mut tty.line);
+                if !line.is_empty() {
+                    tty.lines.push_back(line);
+                }
+            }
+            tty.termios = termios;
+        }
+        TIOCGWINSZ => {
+            let winsize = TTY.exclusive_access().winsize;
+            if copy_to_user(token, &winsize, arg as *mut WinSize).is_err() {
+                return -1; // EFAULT
+            }
+        }
+        TIOCSWINSZ => {
+            let Ok(winsize) = get_from_user(token, arg as *const WinSize) else {
+                return -1; // EFAULT
+            };
+            TTY.exclusive_access().winsize = winsize;
+        }
+        _ => return -1, // ENOTTY
+    }
+    0
+}
@@ -0,0 +1,371 @@
+//! # 内核 GDB 调试桩
+//!
+//! ## Overview
+//! 在串口控制台上实现 GDB 远程串行协议（RSP）的一个子集，使 GDB 可以直接调试运行中的内核：
+//! - 读写寄存器（`g` / `G` / `p` / `P`）与内存（`m` / `M`）
+//! - 软件断点（`Z0` / `z0`）、继续执行（`c`）与单步（`s`）
+//! - 断开（`D`）与结束（`k`，关机）
+//!
+//! 以 `gdbstub` 特性编译（`make run GDBSTUB=1`）且命令行含 `gdbstub=wait` 时，
+//! 内核在 `machine_init` 之后主动陷入调试桩，等待 GDB 通过 `target remote` 连接到串口。
+//!
+//! ## Design
+//! - 断点指令陷入内核后由 `trap_from_kernel` 转交 `handle_breakpoint`，
+//!   调试桩在陷入上下文中与 GDB 交互，直到收到继续或单步命令才返回
+//! - 停下时撤销所有写入的断点指令，恢复执行前再重新写入，
+//!   因此 GDB 读到的内存始终是原始指令
+//! - 单步与“从断点处继续”都通过在当前指令的所有后继地址放置临时断点实现，
+//!   后继地址由 `hal::gdb::step_targets` 解码得到
+//! - 编译进内核的断点指令（如 `init` 中的那一条）在停下时直接跳过，避免继续执行时再次陷入
+//!
+//! ## Assumptions
+//! - 调试期间串口只由调试桩使用，内核的其它输出会混入协议流而被 GDB 忽略或视为噪声
+//! - 陷入内核时中断已关闭，与 GDB 交互的整个过程不会被打断
+//!
+//! ## Invariants
+//! - 调试桩返回（恢复执行）时，`patched` 中记录的地址都写着断点指令；停下时 `patched` 为空
+
+use crate::hal::gdb::{self, KernelTrapFrame, REG_COUNT};
+use crate::hal::{console_getchar, console_putchar, shutdown};
+use crate::mm::kernel_token;
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 通告给 GDB 的最大包长
+const PACKET_SIZE: usize = 0x1000;
+
+/// 停止原因：SIGTRAP
+const STOP_REPLY: &[u8] = b"S05";
+
+struct GdbStub {
+    kernel_token: usize,
+    /// GDB 设置的断点地址
+    breakpoints: BTreeSet<usize>,
+    /// 当前写入了断点指令的地址及其原始内容
+    patched: Vec<(usize, Vec<u8>)>,
+    /// 正在跨过断点继续执行，临时断点命中后不通知 GDB
+    stepping_over: bool,
+    /// GDB 已连接，停下时需要主动发送停止原因
+    connected: bool,
+}
+
+lazy_static! {
+    static ref GDB_STUB: UPIntrFreeCell<GdbStub> = unsafe {
+        UPIntrFreeCell::new(GdbStub {
+            kernel_token: 0,
+            breakpoints: BTreeSet::new(),
+            patched: Vec::new(),
+            stepping_over: false,
+            connected: false,
+        })
+    };
+}
+
+/// 启用调试桩并等待 GDB 连接
+pub fn init() {
+    GDB_STUB.exclusive_access().kernel_token = kernel_token();
+    println!("[gdbstub] waiting for gdb on the serial console...");
+    gdb::trigger_breakpoint();
+}
+
+/// 内核执行到断点指令时由陷入处理调用
+pub fn handle_breakpoint(frame: &mut KernelTrapFrame) {
+    let mut stub = GDB_STUB.exclusive_access();
+    let pc = gdb::pc(frame);
+    let ours = stub.remove_all(pc);
+    if ours && stub.stepping_over && !stub.breakpoints.contains(&pc) {
+        // 已经跨过断点，重新写入断点后继续
+        stub.stepping_over = false;
+        stub.resume(frame, false);
+        return;
+    }
+    stub.stepping_over = false;
+    if !ours {
+        if let Some(len) = gdb::hardcoded_breakpoint(pc) {
+            gdb::set_pc(frame, pc + len);
+        }
+    }
+    stub.session(frame);
+}
+
+impl GdbStub {
+    /// 恢复所有被断点覆盖的指令，返回 `pc` 处是否是调试桩写入的断点
+    fn remove_all(&mut self, pc: usize) -> bool {
+        let mut ours = false;
+        while let Some((addr, original)) = self.patched.pop() {
+            unsafe {
+                core::slice::from_raw_parts_mut(addr as *mut u8, original.len())
+                    .copy_from_slice(&original);
+            }
+            ours |= addr == pc;
+        }
+        gdb::flush_icache();
+        ours
+    }
+
+    /// 在 `addr` 处写入断点指令
+    fn insert(&mut self, addr: usize) {
+        if self.patched.iter().any(|(patched, _)| *patched == addr) {
+            return;
+        }
+        if !gdb::accessible(self.kernel_token, addr, 4) {
+            return;
+        }
+        let insn = gdb::breakpoint_insn(addr);
+        let code = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, insn.len()) };
+        self.patched.push((addr, code.to_vec()));
+        code.copy_from_slice(insn);
+    }
+
+    /// 写入断点后恢复执行；`step` 为真时只执行一条指令
+    fn resume(&mut self, frame: &KernelTrapFrame, step: bool) {
+        let pc = gdb::pc(frame);
+        if step || self.breakpoints.contains(&pc) {
+            for target in gdb::step_targets(frame).into_iter().flatten() {
+                self.insert(target);
+            }
+            self.stepping_over = !step;
+        }
+        if !step {
+            let breakpoints: Vec<usize> = self.breakpoints.iter().copied().collect();
+            for addr in breakpoints.into_iter().filter(|&addr| addr != pc) {
+                self.insert(addr);
+            }
+        }
+        gdb::flush_icache();
+    }
+
+    /// 与 GDB 交互，直到收到继续执行的命令
+    fn session(&mut self, frame: &mut KernelTrapFrame) {
+        if self.connected {
+            send_packet(STOP_REPLY);
+        }
+        loop {
+            let packet = recv_packet();
+            self.connected = true;
+            let (&command, args) = match packet.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            let mut reply = Vec::new();
+            match command {
+                b'?' => reply.extend_from_slice(STOP_REPLY),
+                b'g' => {
+                    let mut regs = [0; REG_COUNT];
+                    gdb::read_regs(frame, &mut regs);
+                    for reg in regs {
+                        push_hex(&mut reply, &reg.to_le_bytes());
+                    }
+                }
+                b'G' => {
+                    let mut regs = [0; REG_COUNT];
+                    gdb::read_regs(frame, &mut regs);
+                    let bytes = decode_hex(args).unwrap_or_default();
+                    for (reg, chunk) in regs.iter_mut().zip(bytes.chunks_exact(8)) {
+                        *reg = usize::from_le_bytes(chunk.try_into().unwrap());
+                    }
+                    gdb::write_regs(frame, &regs);
+                    reply.extend_from_slice(b"OK");
+                }
+                b'p' => match parse_hex(args).filter(|&n| n < REG_COUNT) {
+                    Some(n) => {
+                        let mut regs = [0; REG_COUNT];
+                        gdb::read_regs(frame, &mut regs);
+                        push_hex(&mut reply, &regs[n].to_le_bytes());
+                    }
+                    None => reply.extend_from_slice(b"E01"),
+                },
+                b'P' => reply.extend_from_slice(self.write_reg(frame, args)),
+                b'm' => self.read_memory(args, &mut reply),
+                b'M' => reply.extend_from_slice(self.write_memory(args)),
+                b'c' | b's' => {
+                    if let Some(addr) = parse_hex(args) {
+                        gdb::set_pc(frame, addr);
+                    }
+                    self.resume(frame, command == b's');
+                    return;
+                }
+                b'Z' | b'z' => reply.extend_from_slice(self.set_breakpoint(command == b'Z', args)),
+                b'q' => {
+                    if args.starts_with(b"Supported") {
+                        reply.extend_from_slice(format!("PacketSize={:x}", PACKET_SIZE).as_bytes());
+                    } else if args.starts_with(b"Attached") {
+                        reply.push(b'1');
+                    }
+                }
+                b'H' => reply.extend_from_slice(b"OK"),
+                b'D' => {
+                    send_packet(b"OK");
+                    self.breakpoints.clear();
+                    self.connected = false;
+                    self.resume(frame, false);
+                    return;
+                }
+                b'k' => shutdown(),
+                _ => {}
+            }
+            send_packet(&reply);
+        }
+    }
+
+    /// `Pn=r`
+    fn write_reg(&self, frame: &mut KernelTrapFrame, args: &[u8]) -> &'static [u8] {
+        let mut parts = args.splitn(2, |&c| c == b'=');
+        let n = parts.next().and_then(parse_hex).filter(|&n| n < REG_COUNT);
+        let value = parts.next().and_then(decode_hex).filter(|v| v.len() == 8);
+        match (n, value) {
+            (Some(n), Some(value)) => {
+                let mut regs = [0; REG_COUNT];
+                gdb::read_regs(frame, &mut regs);
+                regs[n] = usize::from_le_bytes(value.as_slice().try_into().unwrap());
+                gdb::write_regs(frame, &regs);
+                b"OK"
+            }
+            _ => b"E01",
+        }
+    }
+
+    /// `maddr,length`
+    fn read_memory(&self, args: &[u8], reply: &mut Vec<u8>) {
+        match parse_addr_len(args) {
+            Some((addr, len)) if gdb::accessible(self.kernel_token, addr, len) => {
+                let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, len) };
+                push_hex(reply, bytes);
+            }
+            _ => reply.extend_from_slice(b"E14"), // EFAULT
+        }
+    }
+
+    /// `Maddr,length:XX...`
+    fn write_memory(&self, args: &[u8]) -> &'static [u8] {
+        let mut parts = args.splitn(2, |&c| c == b':');
+        let range = parts.next().and_then(parse_addr_len);
+        let data = parts.next().and_then(decode_hex);
+        match (range, data) {
+            (Some((addr, len)), Some(data))
+                if data.len() == len && gdb::accessible(self.kernel_token, addr, len) =>
+            {
+                unsafe {
+                    core::slice::from_raw_parts_mut(addr as *mut u8, len).copy_from_slice(&data);
+                }
+                gdb::flush_icache();
+                b"OK"
+            }
+            _ => b"E14", // EFAULT
+        }
+    }
+
+    /// `Z0,addr,kind` / `z0,addr,kind`，只支持软件断点
+    fn set_breakpoint(&mut self, insert: bool, args: &[u8]) -> &'static [u8] {
+        let mut parts = args.split(|&c| c == b',');
+        if parts.next() != Some(b"0".as_slice()) {
+            return b"";
+        }
+        match parts.next().and_then(parse_hex) {
+            Some(addr) if gdb::accessible(self.kernel_token, addr, 4) => {
+                if insert {
+                    self.breakpoints.insert(addr);
+                } else {
+                    self.breakpoints.remove(&addr);
+                }
+                b"OK"
+            }
+            _ => b"E14", // EFAULT
+        }
+    }
+}
+
+fn getchar() -> u8 {
+    loop {
+        let c = console_getchar();
+        if c <= u8::MAX as usize {
+            return c as u8;
+        }
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn parse_hex(s: &[u8]) -> Option<usize> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    s.iter()
+        .try_fold(0, |acc, &c| Some((acc << 4) | hex_digit(c)? as usize))
+}
+
+fn parse_addr_len(s: &[u8]) -> Option<(usize, usize)> {
+    let mut parts = s.splitn(2, |&c| c == b',');
+    let addr = parse_hex(parts.next()?)?;
+    let len = parse_hex(parts.next()?)?;
+    Some((addr, len.min(PACKET_SIZE / 2)))
+}
+
+fn decode_hex(s: &[u8]) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    s.chunks_exact(2)
+        .map(|pair| Some((hex_digit(pair[0])? << 4) | hex_digit(pair[1])?))
+        .collect()
+}
+
+fn push_hex(out: &mut Vec<u8>, bytes: &[u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for &b in bytes {
+        out.push(DIGITS[(b >> 4) as usize]);
+        out.push(DIGITS[(b & 0xf) as usize]);
+    }
+}
+
+/// 接收一个 `$data#xx` 包并应答，校验失败时请求重传
+fn recv_packet() -> Vec<u8> {
+    loop {
+        while getchar() != b'$' {}
+        let mut data = Vec::new();
+        let mut sum: u8 = 0;
+        loop {
+            match getchar() {
+                b'#' => break,
+                c => {
+                    sum = sum.wrapping_add(c);
+                    data.push(c);
+                }
+            }
+        }
+        let checksum = hex_digit(getchar())
+            .zip(hex_digit(getchar()))
+            .map(|(hi, lo)| (hi << 4) | lo);
+        if checksum == Some(sum) {
+            console_putchar(b'+' as usize);
+            return data;
+        }
+        console_putchar(b'-' as usize);
+    }
+}
+
+/// 发送一个包，直到 GDB 确认收到
+fn send_packet(data: &[u8]) {
+    let sum = data.iter().fold(0u8, |sum, &c| sum.wrapping_add(c));
+    let mut checksum = Vec::new();
+    push_hex(&mut checksum, &[sum]);
+    loop {
+        console_putchar(b'$' as usize);
+        for &c in data.iter().chain(b"#").chain(checksum.iter()) {
+            console_putchar(c as usize);
+        }
+        // 跳过应答之前的噪声（如 GDB 发来的中断字符）
+        loop {
+            match getchar() {
+                b'+' => return,
+                b'-' => break,
+                _ => {}
+            }
+        }
+    }
+}
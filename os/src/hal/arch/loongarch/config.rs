@@ -15,6 +15,21 @@ pub const PAGE_SIZE: usize = 0x1000; // 4 * 1024 = 4096 bytes
 /// 例如，页对齐地址可以用 addr >> PAGE_SIZE_BITS
 pub const PAGE_SIZE_BITS: usize = 0xc; // 12，即 2^12 = 4096 bytes
 
+/// 页表项的大小（字节）
+pub const PTE_WIDTH: usize = 8;
+
+/// 页表项大小对应的位数
+pub const PTE_WIDTH_BITS: usize = PTE_WIDTH.trailing_zeros() as usize; // 3
+
+/// 每级页表的索引位数：一个页表页恰好容纳 2^DIR_WIDTH 个页表项
+pub const DIR_WIDTH: usize = PAGE_SIZE_BITS - PTE_WIDTH_BITS; // 9
+
+/// 每个页表页中的页表项数
+pub const PTES_PER_TABLE: usize = 1 << DIR_WIDTH; // 512
+
+/// 页表级数：PWCL/PWCH 只启用目录 3、目录 1 与末级页表（见 `bootstrap_init`），共三级
+pub const PT_LEVELS: usize = 3;
+
 //todo 看一下这个原先UER_STACK_SIZE原先设为8MB是不是预期的
 //todo 我需要这个字段去给每一个线程分配用户栈，8MB是不是太大了？
 //todo 我加了一个USER_STACK_Totol_SIZE字段，表示用户栈的总大小。是否是预期总大小为8MB?
@@ -32,14 +47,32 @@ pub const USER_HEAP_SIZE: usize = 512 * 0x1000 * 0x1000; // 512 MB
 /// 内核堆大小，64 MB
 pub const KERNEL_HEAP_SIZE: usize = 64 * 0x1000 * 0x1000; // 64 MB
 
+/// 内核直接映射区的起始虚拟地址
+/// 内核通过 DMW 窗口访问物理内存，虚拟地址与物理地址相同
+pub const KERNEL_OFFSET: usize = 0;
+
+/// 内核直接映射区覆盖的物理地址范围大小，DMW 窗口覆盖整个物理地址空间
+pub const DIRECT_MAP_SIZE: usize = usize::MAX;
+
+/// 物理地址位数，与 LA64 的 PALEN 一致
+pub const PALEN: usize = 48;
+
 /// 39 位虚拟地址
 pub const VA_BITS: usize = 39; // 39 bits for virtual address
 
 /// 虚拟地址掩码，低 39 位为 1
 pub const VA_MASK: usize = (1 << VA_BITS) - 1; // Mask for 39-bit virtual address
 
+/// 虚拟地址转为 `usize` 时是否做符号扩展：用户地址空间占据整个 39 位（mmap 区域越过第 38 位），
+/// 内核经由 DMW 窗口访问物理内存而不经过页表，因此只做零扩展
+pub const VA_SIGN_EXTEND: bool = false;
+
 /// 虚拟地址空间大小，512 GB
 pub const VA_SPACE_SIZE: usize = 1 << VA_BITS; // 512 GB virtual address space
+/// 用户地址空间中 mmap 区域的起始地址，256GB，堆（brk）不能越过此地址
+pub const MMAP_BASE: usize = 0x40_0000_0000;
+/// 用户地址空间中 mmap 区域的结束地址，384GB，位于用户栈之下
+pub const MMAP_TOP: usize = 0x60_0000_0000;
 /// Trampoline 位于虚拟地址空间的顶端 - 4KB
 pub const TRAMPOLINE: usize = VA_SPACE_SIZE - PAGE_SIZE + 1;
 /// Trap Context 的基地址
@@ -0,0 +1,97 @@
+//! GDB 调试桩的 LoongArch 相关部分
+//! # Overview
+//! 为 `crate::gdbstub` 提供体系结构相关的操作：
+//! - 内核陷入帧与 GDB 寄存器编号（r0..r31、orig_a0、pc、badv）之间的转换
+//! - 断点指令（`break 0`）的识别
+//! - 软件单步：由 `step` 解码当前指令求出所有可能的后继地址，在这些地址上放置临时断点
+//!
+//! # Assumptions
+//! - 内核陷入帧由 `__kern_trap` 在内核栈上构造，布局与 `GeneralRegs` 一致，
+//!   0 号位置保存的是 era（陷入时的 pc），恢复时从该位置写回 era
+//! - 内核运行在直接映射窗口中，代码段可以直接写入
+//!
+//! # Safety
+//! - 直接映射窗口不经过页表，只能检查地址是否落在内核镜像起点到 `MEMORY_END` 之间
+
+use super::step;
+use super::trap::context::GeneralRegs;
+use crate::hal::MEMORY_END;
+use loongArch64::register::badv;
+
+/// 内核陷入时使用的寄存器帧
+pub type KernelTrapFrame = GeneralRegs;
+
+/// GDB 的 LoongArch 寄存器个数：r0..r31、orig_a0、pc 与 badv
+pub const REG_COUNT: usize = 35;
+
+fn gprs(frame: &KernelTrapFrame) -> &[usize; 32] {
+    unsafe { &*(frame as *const _ as *const [usize; 32]) }
+}
+
+/// 读取第 `reg` 号通用寄存器在陷入前的值
+fn gpr(frame: &KernelTrapFrame, reg: usize) -> usize {
+    frame[reg]
+}
+
+/// 按 GDB 编号读出全部寄存器
+pub fn read_regs(frame: &KernelTrapFrame, regs: &mut [usize; REG_COUNT]) {
+    regs[0] = 0;
+    regs[1..32].copy_from_slice(&gprs(frame)[1..32]);
+    regs[32] = 0;
+    regs[33] = frame.pc;
+    regs[34] = badv::read().vaddr();
+}
+
+/// 按 GDB 编号写回寄存器；sp 由陷入入口恢复，对它的修改被忽略
+pub fn write_regs(frame: &mut KernelTrapFrame, regs: &[usize; REG_COUNT]) {
+    for reg in (1..32).filter(|&reg| reg != 3) {
+        frame[reg] = regs[reg];
+    }
+    frame.pc = regs[33];
+}
+
+pub fn pc(frame: &KernelTrapFrame) -> usize {
+    frame.pc
+}
+
+pub fn set_pc(frame: &mut KernelTrapFrame, pc: usize) {
+    frame.pc = pc;
+}
+
+fn read_u16(addr: usize) -> u16 {
+    unsafe { (addr as *const u16).read() }
+}
+
+/// 用于替换 `addr` 处指令的断点指令（LoongArch 指令定长 4 字节）
+pub fn breakpoint_insn(_addr: usize) -> &'static [u8] {
+    step::breakpoint_insn(4)
+}
+
+/// `addr` 处是否为编译进内核的断点指令，是则返回其长度
+pub fn hardcoded_breakpoint(addr: usize) -> Option<usize> {
+    let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, 4) };
+    (bytes == step::breakpoint_insn(4)).then_some(4)
+}
+
+/// 当前指令执行后所有可能的下一条指令地址
+pub fn step_targets(frame: &KernelTrapFrame) -> [Option<usize>; 2] {
+    step::next_pcs(frame.pc, |addr| Some(read_u16(addr)), |reg| gpr(frame, reg))
+}
+
+/// `[addr, addr + len)` 是否都位于内核可直接访问的物理内存中
+pub fn accessible(_kernel_token: usize, addr: usize, len: usize) -> bool {
+    extern "C" {
+        fn skernel();
+    }
+    addr >= skernel as usize && addr.checked_add(len).is_some_and(|end| end <= MEMORY_END)
+}
+
+/// 修改代码后使指令缓存失效
+pub fn flush_icache() {
+    unsafe { core::arch::asm!("ibar 0") };
+}
+
+/// 主动陷入调试桩
+pub fn trigger_breakpoint() {
+    unsafe { core::arch::asm!("break 0") };
+}
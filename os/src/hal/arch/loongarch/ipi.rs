@@ -0,0 +1,44 @@
+//! # LoongArch 核间中断
+//!
+//! ## Overview
+//! 通过 IOCSR 中每个核的 IPI 寄存器收发核间中断：
+//! - `send_ipi`：向位图中的每个核写 `IPI_SEND`
+//! - `clear_ipi`：读出 `IPI_STATUS` 并写回 `IPI_CLEAR`
+//! - `enable_ipi_interrupt`：使能全部 IPI 向量与 IPI 中断线
+//!
+//! ## Assumptions
+//! - 只使用 0 号向量，请求内容放在内核的每核队列中，不使用信箱寄存器传递数据
+//! - trap 处理尚未实现中断分发，IPI 中断需在其中调用 `crate::smp::handle_ipi`
+
+use core::arch::asm;
+use loongArch64::register::ecfg::{self, LineBasedInterrupt};
+
+const IOCSR_IPI_STATUS: usize = 0x1000;
+const IOCSR_IPI_EN: usize = 0x1004;
+const IOCSR_IPI_CLEAR: usize = 0x100c;
+const IOCSR_IPI_SEND: usize = 0x1040;
+/// `IPI_SEND` 中目标核号的位置
+const IPI_SEND_CPU_SHIFT: usize = 16;
+
+/// 向 `hart_mask` 中的核（位 i 对应 i 号核）发送 0 号向量的 IPI
+pub fn send_ipi(hart_mask: usize) {
+    for cpu in (0..usize::BITS as usize).filter(|cpu| hart_mask & (1 << cpu) != 0) {
+        let value = cpu << IPI_SEND_CPU_SHIFT;
+        unsafe { asm!("iocsrwr.w {}, {}", in(reg) value, in(reg) IOCSR_IPI_SEND) };
+    }
+}
+
+/// 清除本核所有待处理的 IPI 向量
+pub fn clear_ipi() {
+    let status: usize;
+    unsafe {
+        asm!("iocsrrd.w {}, {}", out(reg) status, in(reg) IOCSR_IPI_STATUS);
+        asm!("iocsrwr.w {}, {}", in(reg) status, in(reg) IOCSR_IPI_CLEAR);
+    }
+}
+
+/// 使能本核的 IPI
+pub fn enable_ipi_interrupt() {
+    unsafe { asm!("iocsrwr.w {}, {}", in(reg) u32::MAX as usize, in(reg) IOCSR_IPI_EN) };
+    ecfg::set_lie(ecfg::read().lie() | LineBasedInterrupt::IPI);
+}
@@ -1,10 +1,13 @@
-use crate::hal::arch::loongarch::tlb::tlb_global_invalidate;
+use crate::hal::arch::loongarch::tlb::{
+    local_flush_tlb, tlb_global_invalidate, tlb_invalidate_asid,
+};
 use crate::hal::{
-    PageTableEntryImpl, MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN, PAGE_SIZE_BITS, PALEN, VPN_SEG_MASK,
+    PageTableEntryImpl, MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN, PAGE_SIZE_BITS, PALEN, PT_LEVELS,
+    VPN_SEG_MASK,
 };
 use crate::mm::{
-    frame_alloc, FrameTracker, MapPermission, PageTable, PhysAddr, PhysPageNum, VirtAddr,
-    VirtPageNum,
+    asid_alloc, frame_alloc, Asid, FrameTracker, MapPermission, PageSize, PageTable, PhysAddr,
+    PhysPageNum, VirtAddr, VirtPageNum, HUGE_PAGE_PAGES,
 };
 use alloc::vec;
 use alloc::vec::Vec;
@@ -22,6 +25,7 @@ bitflags! {
         const MAT_CC = 1 << 4;  // 内存访问类型：一致性缓存（Coherent Cached）
         const MAT_SUC = 0 << 4; // 内存访问类型：强顺序非缓存（Strongly-ordered UnCached）
         const G = 1 << 6;   // 全局位
+        const H = 1 << 6;   // 目录项中的大页位（与 G 同位，大页的全局位移到第 12 位）
 
         // ------ 自定义位(软件) ------
         const P = 1 << 7;   // 物理位，表示物理页是否存在
@@ -41,6 +45,11 @@ pub struct PageTableEntry {
 }
 
 const PPN_MASK: usize = ((1usize << PALEN) - 1) & !((1usize << 12) - 1);
+/// 换出页 PTE 的标记位（软件位），此时 V / P 均为 0，交换槽号存放在 PPN 字段
+const SWAPPED: usize = 1 << 9;
+/// 叶子项与大页叶子项所在的页表级别（0 为根页表）
+const LEAF_LEVEL: usize = PT_LEVELS - 1;
+const HUGE_LEVEL: usize = PT_LEVELS - 2;
 
 impl PageTableEntry {
     pub fn new(ppn: PhysPageNum, flags: PTEFlags) -> Self {
@@ -77,6 +86,10 @@ impl PageTableEntry {
         !self.flags().contains(PTEFlags::NX)
     }
 
+    pub fn is_user(&self) -> bool {
+        self.flags().contains(PTEFlags::PLV3)
+    }
+
     pub fn set_dirty(&mut self) {
         self.bits |= PTEFlags::D.bits();
     }
@@ -89,6 +102,26 @@ impl PageTableEntry {
         self.bits &= !PTEFlags::D.bits();
     }
 
+    /// 创建换出页的 PTE
+    pub fn swapped(slot: usize) -> Self {
+        Self {
+            bits: ((slot << 12) & PPN_MASK) | SWAPPED,
+        }
+    }
+
+    /// 换出页的交换槽号，不是换出页时返回 `None`
+    pub fn swap_slot(&self) -> Option<usize> {
+        if self.bits & (PTEFlags::V.bits() | SWAPPED) != SWAPPED {
+            return None;
+        }
+        Some((self.bits & PPN_MASK) >> 12)
+    }
+
+    /// LoongArch 页表项没有硬件维护的访问位，总是返回 `false`，换出时退化为按顺序轮转
+    pub fn take_accessed(&mut self) -> bool {
+        false
+    }
+
     pub fn set_permission(&mut self, flags: MapPermission) {
         if flags.contains(MapPermission::R) {
             self.bits &= !PTEFlags::NR.bits();
@@ -110,9 +143,14 @@ impl PageTableEntry {
     }
 }
 
+/// token 中 ASID 所在的位置：高于根页表地址，`__restore` 左移 12 位写入 PGDL 时被移出
+const ASID_SHIFT: usize = 54;
+
 pub struct LaflexPageTable {
     root_ppn: PhysPageNum,
     frames: Vec<FrameTracker>,
+    /// 用户页表的 ASID，内核页表、临时页表与未分配到 ASID 的页表为 `None`
+    asid: Option<Asid>,
 }
 
 impl LaflexPageTable {
@@ -131,15 +169,63 @@ impl LaflexPageTable {
             self.root_ppn
         }
     }
+
+    /// 查找或创建大页叶子项所在级别的页表项
+    fn find_huge_pte_create(&mut self, vpn: VirtPageNum) -> &mut PageTableEntry {
+        let idxs = vpn.indexes();
+        let mut ppn = self.get_root_ppn();
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
+            if i == HUGE_LEVEL {
+                result = Some(pte);
+                break;
+            }
+            if pte.bits & PTEFlags::V.bits() == 0 {
+                let frame = frame_alloc().unwrap();
+                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+                self.frames.push(frame);
+            }
+            ppn = PhysAddr::from((pte.ppn().0 << 12) | MEMORY_HIGH_BASE).floor();
+        }
+        result.unwrap()
+    }
+}
+
+/// 目录项是否为大页叶子项
+fn is_huge(pte: &PageTableEntry) -> bool {
+    pte.bits & (PTEFlags::V.bits() | PTEFlags::H.bits()) == PTEFlags::V.bits() | PTEFlags::H.bits()
+}
+
+/// 把大页叶子项拆成一张映射相同的 4KB 页表，返回新页表所在的页帧
+fn split_huge(pte: &mut PageTableEntry) -> FrameTracker {
+    let frame = frame_alloc().unwrap();
+    let base = pte.ppn().0;
+    let flags = pte.bits & !PPN_MASK & !PTEFlags::H.bits();
+    let table = PhysAddr::from((frame.ppn.0 << 12) | MEMORY_HIGH_BASE).floor();
+    for (i, entry) in table
+        .get_pte_array::<PageTableEntry>()
+        .iter_mut()
+        .enumerate()
+    {
+        entry.bits = (((base + i) << 12) & PPN_MASK) | flags;
+    }
+    *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+    frame
 }
 
 impl PageTable for LaflexPageTable {
-    /// 仅能用于创建用户页表
+    /// 仅能用于创建用户页表，并清除分配到的 ASID 上一个持有者留下的 TLB 项
     fn new() -> Self {
         let frame = frame_alloc().unwrap();
+        let asid = asid_alloc();
+        if let Some(asid) = &asid {
+            tlb_invalidate_asid(asid.id());
+        }
         Self {
             root_ppn: frame.ppn,
             frames: vec![frame],
+            asid,
         }
     }
 
@@ -149,23 +235,25 @@ impl PageTable for LaflexPageTable {
         Self {
             root_ppn: PhysPageNum(frame.ppn.0 << 32),
             frames: vec![frame],
+            asid: None,
         }
     }
 
     fn from_token(token: usize) -> Self {
         Self {
-            root_ppn: PhysPageNum::from(token),
+            root_ppn: PhysPageNum::from(token & ((1 << ASID_SHIFT) - 1)),
             frames: Vec::new(),
+            asid: None,
         }
     }
 
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntryImpl> {
-        let idex = vpn.indexes::<4>();
+        let idex = vpn.indexes();
         let mut ppn = self.get_root_ppn();
         let mut result: Option<&mut PageTableEntry> = None;
         for (i, idex) in idex.iter().enumerate() {
             let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idex];
-            if i == 3 {
+            if i == LEAF_LEVEL {
                 result = Some(pte);
                 break;
             }
@@ -173,6 +261,9 @@ impl PageTable for LaflexPageTable {
                 let frame = frame_alloc().unwrap();
                 *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
                 self.frames.push(frame);
+            } else if i == HUGE_LEVEL && is_huge(pte) {
+                let frame = split_huge(pte);
+                self.frames.push(frame);
             }
             ppn = PhysAddr::from((pte.ppn().0 << 12) | MEMORY_HIGH_BASE).floor();
         }
@@ -180,12 +271,12 @@ impl PageTable for LaflexPageTable {
     }
 
     fn find_pte(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntryImpl> {
-        let idxs = vpn.indexes::<4>();
+        let idxs = vpn.indexes();
         let mut ppn = self.get_root_ppn();
         let mut result: Option<&mut PageTableEntry> = None;
         for (i, idx) in idxs.iter().enumerate() {
             let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
-            if i == 3 {
+            if i == LEAF_LEVEL || (i == HUGE_LEVEL && is_huge(pte)) {
                 result = Some(pte);
                 break;
             }
@@ -196,9 +287,20 @@ impl PageTable for LaflexPageTable {
         }
     }
 
-    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission) {
-        let pte = self.find_pte_create(vpn).unwrap();
+    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission, size: PageSize) {
         let mut flag = PTEFlags::V | PTEFlags::MAT_CC;
+        let pte = match size {
+            PageSize::Small => self.find_pte_create(vpn).unwrap(),
+            PageSize::Huge => {
+                assert!(
+                    vpn.0 % HUGE_PAGE_PAGES == 0 && ppn.0 % HUGE_PAGE_PAGES == 0,
+                    "huge page {:?} is not aligned",
+                    vpn
+                );
+                flag |= PTEFlags::H;
+                self.find_huge_pte_create(vpn)
+            }
+        };
         if !flags.contains(MapPermission::R) {
             flag |= PTEFlags::NR;
         }
@@ -213,20 +315,43 @@ impl PageTable for LaflexPageTable {
         }
         let pte_new = PageTableEntry::new(ppn, flag);
         *pte = pte_new;
+        self.flush_tlb(vpn);
     }
 
+    /// vpn 位于大页中时先拆分大页
     fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte(vpn).unwrap();
+        let pte = self.find_pte_create(vpn).unwrap();
         assert!(pte.is_valid(), "vpn {:?} is unmapped before unmapping", vpn);
         *pte = PageTableEntry::empty();
+        self.flush_tlb(vpn);
     }
 
+    fn unmap_huge(&mut self, vpn: VirtPageNum) -> bool {
+        match self.find_pte(vpn) {
+            Some(pte) if is_huge(pte) => {
+                *pte = PageTableEntry::empty();
+                self.flush_tlb(vpn);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 大页中的 vpn 得到该 4KB 页对应的页表项
     fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntryImpl> {
-        self.find_pte(vpn).map(|pte: PageTableEntry| *pte)
+        self.find_pte(vpn).map(|pte| {
+            if !is_huge(pte) {
+                return *pte;
+            }
+            let offset = (vpn.0 % HUGE_PAGE_PAGES) << 12;
+            PageTableEntry {
+                bits: (pte.bits + offset) & !PTEFlags::H.bits(),
+            }
+        })
     }
 
     fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
-        self.find_pte(va.clone().floor())
+        self.translate(va.clone().floor())
             .map(|pte: PageTableEntry| {
                 let aligned_pa: PhysAddr = pte.ppn().into();
                 let offset = va.page_offset();
@@ -235,7 +360,18 @@ impl PageTable for LaflexPageTable {
             })
     }
 
+    /// 有 ASID 时只针对该 ASID，否则清除所有非全局 TLB 项
+    fn flush_tlb(&self, vpn: VirtPageNum) {
+        let asid = self.asid.as_ref().map(Asid::id);
+        let va = VirtAddr::from(vpn).0;
+        local_flush_tlb(asid, va);
+        // 页表可能在其他核上活跃
+        crate::smp::tlb_shootdown(asid, va);
+    }
+
+    /// 写页表基址前用 `dbar` 保证之前对页表的写入对 TLB 重填可见
     fn activate(&self) {
+        unsafe { core::arch::asm!("dbar 0") };
         tlb_global_invalidate();
         if self.is_kernel_pt() {
             pgdh::set_base(self.get_root_ppn().0 << PAGE_SIZE_BITS);
@@ -245,6 +381,6 @@ impl PageTable for LaflexPageTable {
     }
 
     fn token(&self) -> usize {
-        self.root_ppn.0
+        self.asid.as_ref().map_or(0, Asid::id) << ASID_SHIFT | self.root_ppn.0
     }
 }
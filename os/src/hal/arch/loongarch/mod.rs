@@ -1,9 +1,13 @@
 mod boot;
 pub mod config;
+#[cfg(feature = "gdbstub")]
+pub mod gdb;
+mod ipi;
 pub mod kernel_stack;
 mod laflex;
 mod merrera;
 pub mod sbi;
+pub mod step;
 pub mod sync;
 pub mod timer;
 pub mod trap;
@@ -11,7 +15,7 @@ pub mod trap;
 mod tlb;
 
 use crate::hal::platform::UART_BASE;
-use config::{DIR_WIDTH, MMAP_BASE, PAGE_SIZE_BITS, PTE_WIDTH, PTE_WIDTH_BITS, SUC_DMW_VSEG};
+use config::{DIR_WIDTH, MMAP_BASE, PAGE_SIZE_BITS, SUC_DMW_VSEG};
 use loongArch64::register::ecfg::LineBasedInterrupt;
 use loongArch64::register::{
     cpuid, crmd, dmw0, dmw2, ecfg, euen, misc, prcfg1, pwch, pwcl, rvacfg, stlbps, tcfg, ticlr,
@@ -60,17 +64,16 @@ pub fn bootstrap_init() {
 
     // INFO: dmw3 npucore中实现了，但是新版LoongArch64库接口缺失
 
-    stlbps::set_ps(PTE_WIDTH_BITS); // 这是普通页大小的设置
-    tlbrehi::set_ps(PTE_WIDTH_BITS); // 这是大页大小的设置
+    stlbps::set_ps(PAGE_SIZE_BITS); // 这是普通页大小的设置
+    tlbrehi::set_ps(PAGE_SIZE_BITS); // 这是 TLB 重填页大小的设置
 
-    // 设置页表项格式
-    pwcl::set_ptbase(PTE_WIDTH_BITS); // 设置页表项基址偏移
+    // 设置页表格式，各级的索引位置与宽度与 `config` 中的页表几何（`DIR_WIDTH`、`PT_LEVELS`）一致
+    pwcl::set_ptbase(PAGE_SIZE_BITS); // 末级页表索引的起始位
     pwcl::set_ptwidth(DIR_WIDTH); // 索引宽度
     pwcl::set_dir1_base(PAGE_SIZE_BITS + DIR_WIDTH); // 目录1基址偏移
     pwcl::set_dir1_width(DIR_WIDTH); // 目录1索引宽度
     pwcl::set_dir2_base(0); // 目录2基址偏移
     pwcl::set_dir2_width(0); // 目录2索引宽度
-    pwcl::set_ptwidth(PTE_WIDTH); // 每级页表项大小8字节
 
     pwch::set_dir3_base(PAGE_SIZE_BITS + DIR_WIDTH * 2); // 目录3基址偏移
     pwch::set_dir3_width(DIR_WIDTH); // 目录3索引宽度
@@ -83,6 +86,7 @@ pub fn bootstrap_init() {
 
 pub fn machine_init() {
     trap::init();
+    ipi::enable_ipi_interrupt();
     get_timer_freq_first_time();
     /* println!(
      *     "[machine_init] VALEN: {}, PALEN: {}",
@@ -106,5 +110,35 @@ pub fn machine_init() {
     trap::enable_timer_interrupt();
 }
 
+/// 使能外部中断源 `irq`
+///
+/// 2K1000 开发板上在 liointc 中把 `irq` 路由到 0 号核的 INT0，并打开对应的 HWI0 中断线
+#[cfg(feature = "board_2k1000")]
+pub fn enable_irq(irq: usize) {
+    crate::drivers::irqchip::liointc::enable(irq);
+    ecfg::set_lie(ecfg::read().lie() | LineBasedInterrupt::HWI0);
+}
+
+/// QEMU 上尚无 MMIO 外设需要中断，外部中断暂不路由，故为空函数
+///
+/// board_laqemu 上不探测 VirtIO 设备（见 `VIRTIO_MMIO_SLOTS`），因此不会有网卡调用到这里
+#[cfg(not(feature = "board_2k1000"))]
+pub fn enable_irq(_irq: usize) {}
+
+/// 读取当前帧指针（$fp，即 $r22），内核以 `-Cforce-frame-pointers=yes` 编译
+///
+/// 栈帧布局与 RISC-V 相同：`fp - 8` 处保存返回地址，`fp - 16` 处保存上一帧的帧指针
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        core::arch::asm!("move {}, $fp", out(reg) fp);
+    }
+    fp
+}
+
 pub type PageTableEntryImpl = laflex::LAFlexPageTableEntry;
 pub type PageTableImpl = laflex::LAFlexPageTable;
+
+pub use ipi::{clear_ipi, send_ipi};
+pub use tlb::{asid_bits, local_flush_tlb};
@@ -11,7 +11,20 @@ pub fn console_putchar(c: usize) {
     }
 }
 
+pub fn console_write(bytes: &[u8]) {
+    for &byte in bytes {
+        console_putchar(byte as usize);
+    }
+}
+
+/// 读取一个字符，没有输入时返回 `usize::MAX`
+///
+/// 2K1000 开发板上先取串口中断收到的字符，中断打开之前仍直接轮询 UART
 pub fn console_getchar() -> usize {
+    #[cfg(feature = "board_2k1000")]
+    if let Some(c) = crate::drivers::serial::ls2k1000::getchar() {
+        return c as usize;
+    }
     unsafe {
         if let Ok(i) = UART.read() {
             i as usize
@@ -26,6 +39,7 @@ pub fn console_flush() {
 }
 
 pub fn shutdown() -> ! {
+    crate::console::flush();
     {
         unsafe {
             (0x100E_001C as *mut u8).write_volatile(0x34);
@@ -0,0 +1,68 @@
+//! 软件单步（LoongArch）
+//! # Overview
+//! 与 RISC-V 一致，内核调试桩与 `ptrace` 单步都通过“在当前指令所有可能的后继地址放置断点指令”
+//! 来实现，不依赖取指监视点等调试寄存器。本模块负责：
+//! - 给出断点指令（`break 0`，LoongArch 指令定长 4 字节）
+//! - 解码跳转与分支指令，求出后继地址
+//!
+//! # Assumptions
+//! - 取指由调用者提供：内核地址可直接读取，用户地址需要经过页表翻译
+
+const BREAK: [u8; 4] = 0x002a_0000u32.to_le_bytes();
+
+/// 指令长度，LoongArch 指令定长
+pub fn insn_len(_low: u16) -> usize {
+    4
+}
+
+/// 断点指令
+pub fn breakpoint_insn(_len: usize) -> &'static [u8] {
+    &BREAK
+}
+
+fn sign_extend(value: usize, bits: u32) -> usize {
+    let shift = usize::BITS - bits;
+    (((value << shift) as isize) >> shift) as usize
+}
+
+/// `pc` 处的指令执行后所有可能的下一条指令地址
+///
+/// - `fetch(addr)` 读取 `addr` 处的 16 位指令片，无法读取时返回 `None`
+/// - `reg(n)` 读取第 `n` 号通用寄存器在该指令执行前的值
+pub fn next_pcs(
+    pc: usize,
+    fetch: impl Fn(usize) -> Option<u16>,
+    reg: impl Fn(usize) -> usize,
+) -> [Option<usize>; 2] {
+    let insn = match fetch(pc).zip(fetch(pc + 2)) {
+        Some((low, high)) => ((high as usize) << 16) | low as usize,
+        None => return [None, None],
+    };
+    let offs16 = (insn >> 10) & 0xffff;
+    match insn >> 26 {
+        // beqz / bnez / bceqz / bcnez
+        0x10..=0x12 => {
+            let offs = offs16 | ((insn & 0x1f) << 16);
+            [
+                Some(pc + 4),
+                Some(pc.wrapping_add(sign_extend(offs, 21) << 2)),
+            ]
+        }
+        // jirl
+        0x13 => {
+            let base = reg((insn >> 5) & 0x1f);
+            [Some(base.wrapping_add(sign_extend(offs16, 16) << 2)), None]
+        }
+        // b / bl
+        0x14 | 0x15 => {
+            let offs = offs16 | ((insn & 0x3ff) << 16);
+            [Some(pc.wrapping_add(sign_extend(offs, 26) << 2)), None]
+        }
+        // beq / bne / blt / bge / bltu / bgeu
+        0x16..=0x1b => [
+            Some(pc + 4),
+            Some(pc.wrapping_add(sign_extend(offs16, 16) << 2)),
+        ],
+        _ => [Some(pc + 4), None],
+    }
+}
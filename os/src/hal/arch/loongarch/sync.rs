@@ -39,4 +39,14 @@ impl IntrMaskingInfo {
             crmd::set_ie(true);
         }
     }
+
+    pub fn nested_level(&self) -> usize {
+        self.nested_level
+    }
+}
+
+/// 全屏障：之前的访存完成后才执行之后的访存（`dbar 0`）
+#[inline(always)]
+pub fn memory_barrier() {
+    unsafe { core::arch::asm!("dbar 0") };
 }
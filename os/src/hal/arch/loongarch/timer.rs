@@ -1,6 +1,8 @@
 use super::config;
 use core::arch::asm;
+use loongArch64::register::tcfg;
 
+/// 默认的每秒 tick 数，可由启动参数 `hz=` 修改
 pub const TICKS_PER_SEC: usize = 100;
 
 pub fn get_time() -> usize {
@@ -15,6 +17,21 @@ pub fn get_time() -> usize {
     counter
 }
 
+/// 下一个周期性 tick 设在一个 tick 周期之后，并据此设置下一次定时器中断
+pub fn set_next_trigger() {
+    crate::timer::set_next_tick(get_time() + crate::timer::tick_period());
+}
+
+/// 把下一次定时器中断设置在时刻 `deadline`（时钟周期数）
+///
+/// 定时器按剩余的周期数倒计时，已经过去的时刻按最小的倒计时处理，尽快触发
+pub fn set_timer_event(deadline: usize) {
+    tcfg::set_init_val(deadline.saturating_sub(get_time()).max(MIN_TIMER_DELTA));
+}
+
+/// 最小的倒计时周期数
+const MIN_TIMER_DELTA: usize = 4;
+
 #[inline]
 pub fn get_clock_freq() -> usize {
     unsafe { config::CLOCK_FREQ }
@@ -13,6 +13,11 @@ pub fn set_asid(asid: usize) {
     asid::set_asid(asid & (1 << id.asid_width() - 1));
 }
 
+/// 硬件支持的 ASID 位数
+pub fn asid_bits() -> usize {
+    asid::read().asid_width()
+}
+
 pub fn tlb_addr_allow_write(vpn: VirtPageNum, ppn: PhysPageNum) -> Result<(), ()> {
     tlbehi::set_vppn(0, usize::from(vpn) >> 1);
     tlbsrch();
@@ -38,6 +43,32 @@ pub fn tlb_invalidate() {
     }
 }
 
+/// 清除 ASID 为 `asid` 的全部非全局 TLB 项
+#[inline(always)]
+pub fn tlb_invalidate_asid(asid: usize) {
+    unsafe {
+        asm!("invtlb 0x4, {}, $zero", in(reg) asid);
+    }
+}
+
+/// 清除 ASID 为 `asid`、包含虚拟地址 `va` 的非全局 TLB 项
+#[inline(always)]
+pub fn tlb_invalidate_page(asid: usize, va: usize) {
+    unsafe {
+        asm!("invtlb 0x5, {}, {}", in(reg) asid, in(reg) va);
+    }
+}
+
+/// 在本核上使 `va` 的 TLB 项失效，`asid` 为 `None` 时清除所有非全局 TLB 项
+///
+/// 也是跨核 TLB shootdown 的接收方在对方核上执行的操作
+pub fn local_flush_tlb(asid: Option<usize>, va: usize) {
+    match asid {
+        Some(asid) => tlb_invalidate_page(asid, va),
+        None => tlb_invalidate(),
+    }
+}
+
 #[inline(always)]
 pub fn tlb_global_invalidate() {
     unsafe {
@@ -102,12 +102,17 @@ impl Debug for GeneralRegs {
 }
 
 /// FP registers
+///
+/// `used` 为 0 时任务从未使用过浮点扩展：陷入时不保存、返回时不恢复浮点寄存器，
+/// 并在用户态关闭 `euen.fpe`，首次执行浮点指令时触发浮点不可用异常（FPD）
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
 pub struct FloatRegs {
     pub f: [usize; 32],
     pub fcsr: u32,
     pub fcc: u8,
+    /// 任务是否使用过浮点扩展，偏移需与 `trap.S` 中的 `FP_USED` 一致
+    pub used: u8,
 }
 
 #[repr(C)]
@@ -144,10 +149,89 @@ pub struct TrapContext {
 //     }
 // }
 
+/// `GeneralRegs` 各字段的寄存器名，顺序与结构体布局一致（0 号位置保存的是 pc）
+const REG_NAMES: [&str; 32] = [
+    "pc", "ra", "tp", "sp", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "t0", "t1", "t2", "t3",
+    "t4", "t5", "t6", "t7", "t8", "r21", "fp", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8",
+];
+
+/// ELF 文件头中的机器类型（EM_LOONGARCH），用于生成 core 文件
+pub const ELF_MACHINE: u16 = 258;
+/// core 文件 `NT_PRSTATUS` 中通用寄存器组的项数，与 Linux 的 `ELF_NGREG` 相同
+pub const ELF_NGREG: usize = 45;
+
 impl TrapContext {
+    /// 按 Linux `user_pt_regs` 的顺序排列的寄存器：r0 ~ r31、orig_a0、era、badv 与保留项
+    ///
+    /// 陷入时的 badv 没有保存，记为 0
+    pub fn elf_gregset(&self) -> [usize; ELF_NGREG] {
+        let mut regs = [0; ELF_NGREG];
+        for (n, reg) in regs.iter_mut().enumerate().take(32).skip(1) {
+            *reg = self.reg(n);
+        }
+        regs[32] = self.origin_a0;
+        regs[33] = self.pc();
+        regs
+    }
+
+    /// 打印全部寄存器，供 panic 时诊断使用
+    pub fn dump(&self) {
+        let regs = unsafe { &*(&self.gp as *const GeneralRegs as *const [usize; 32]) };
+        for (i, (name, value)) in REG_NAMES.iter().zip(regs.iter()).enumerate() {
+            print!("{:>4}={:#018x}", name, value);
+            print!("{}", if i % 4 == 3 { "\n" } else { " " });
+        }
+        println!(
+            "prmd.pplv={:?} origin_a0={:#x} kernel_sp={:#018x}",
+            self.sstatus.get_pplv(),
+            self.origin_a0,
+            self.kernel_sp
+        );
+    }
+
     pub fn set_sp(&mut self, sp: usize) {
         self.gp.sp = sp;
     }
+
+    /// 用户态栈指针
+    pub fn sp(&self) -> usize {
+        self.gp.sp
+    }
+
+    /// 设置线程指针（$tp，即 $r2）
+    pub fn set_tp(&mut self, tp: usize) {
+        self.gp.tp = tp;
+    }
+
+    /// 陷入时的 pc（era）
+    pub fn pc(&self) -> usize {
+        self.gp.pc
+    }
+
+    /// 帧指针（r22），用户程序保留帧指针时可据此回溯用户栈
+    pub fn fp(&self) -> usize {
+        self.reg(22)
+    }
+
+    /// 第 `n` 号通用寄存器（r0 恒为 0）
+    pub fn reg(&self, n: usize) -> usize {
+        self.gp[n]
+    }
+
+    /// 处理首次使用浮点的异常：为任务启用浮点扩展，浮点寄存器从全零开始
+    ///
+    /// 返回 `false` 表示任务已经启用过浮点扩展，这次异常不是首次使用导致的
+    pub fn enable_fp(&mut self) -> bool {
+        if self.fp.used != 0 {
+            return false;
+        }
+        self.fp = FloatRegs {
+            used: 1,
+            ..FloatRegs::default()
+        };
+        true
+    }
     pub fn app_init_context(
         entry: usize,
         sp: usize,
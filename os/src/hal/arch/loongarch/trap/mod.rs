@@ -2,13 +2,12 @@ pub mod context;
 mod mem_access;
 
 use super::merrera;
-use crate::hal::arch::loongarch::timer::TICKS_PER_SEC;
-use crate::hal::get_clock_freq;
+use crate::hal::arch::loongarch::timer::set_next_trigger;
 use context::GeneralRegs;
 use core::arch::{asm, global_asm};
 use loongArch64::register::ecfg::LineBasedInterrupt;
-use loongArch64::register::estat::{Exception, Trap};
-use loongArch64::register::{badi, badv, ecfg, eentry, era, estat, pgdh, tcfg};
+use loongArch64::register::estat::{Exception, Interrupt, Trap};
+use loongArch64::register::{badi, badv, ecfg, eentry, era, estat, pgdh, tcfg, ticlr};
 use mem_access::Instruction;
 
 global_asm!(include_str!("trap.S"));
@@ -103,12 +102,43 @@ pub fn set_machine_error_trap_entry() {
     todo!()
 }
 
+/// 以单次模式启用定时器，倒计时的长度由 `set_timer_event` 逐次设置
 pub fn enable_timer_interrupt() {
-    let timer_freq = get_clock_freq();
     tcfg::set_en(true);
     tcfg::set_periodic(false);
-    tcfg::set_init_val(timer_freq / TICKS_PER_SEC);
-    ecfg::set_lie(LineBasedInterrupt::TIMER);
+    set_next_trigger();
+    // 保留已打开的 IPI 与外部中断线
+    ecfg::set_lie(ecfg::read().lie() | LineBasedInterrupt::TIMER);
+}
+
+/// 处理外部中断：从 liointc 逐个领取待处理的中断号交给驱动分发
+///
+/// liointc 只使用电平触发，驱动清除设备的中断条件后中断源即撤销，无需通知完成
+#[cfg(feature = "board_2k1000")]
+fn handle_external_interrupt() {
+    while let Some(irq) = crate::drivers::irqchip::liointc::claim() {
+        crate::drivers::irq_handler(irq);
+    }
+}
+
+/// QEMU 上没有路由外部中断
+#[cfg(not(feature = "board_2k1000"))]
+fn handle_external_interrupt() {}
+
+/// 空闲时等待下一个中断
+///
+/// 内核态的陷阱处理尚不处理中断，因此在关中断的状态下执行 `idle`（中断待处理时同样会唤醒），
+/// 醒来后在这里处理外部中断并清除时钟中断；到达 tick 时开始下一个 tick 周期，`check_timer` 随后设置下一次中断
+pub fn wait_for_interrupt() {
+    unsafe {
+        asm!("idle 0");
+    }
+    handle_external_interrupt();
+    ticlr::clear_timer_interrupt();
+    if crate::timer::tick_due() {
+        set_next_trigger();
+    }
+    crate::timer::check_timer();
 }
 
 pub type TrapImpl = Trap;
@@ -200,6 +230,15 @@ pub extern "C" fn trap_from_kernel(gr: &mut GeneralRegs) {
             //debug!("{:?}", gr);
             return;
         }
+        Trap::Interrupt(Interrupt::HWI0) => {
+            handle_external_interrupt();
+            return;
+        }
+        #[cfg(feature = "gdbstub")]
+        Trap::Exception(Exception::Breakpoint) => {
+            crate::gdbstub::handle_breakpoint(gr);
+            return;
+        }
         _ => {}
     }
     panic!(
@@ -228,6 +267,14 @@ pub extern "C" fn trap_from_kernel(gr: &mut GeneralRegs) {
 
 #[no_mangle]
 pub fn trap_handler() -> ! {
+    match get_exception_cause() {
+        // 首次使用浮点：为任务启用浮点扩展后重新执行该指令
+        Trap::Exception(Exception::FloatingPointUnavailable) => {
+            crate::task::current_trap_cx().enable_fp();
+        }
+        Trap::Interrupt(Interrupt::HWI0) => handle_external_interrupt(),
+        _ => {}
+    }
     trap_return();
     unreachable!()
 }
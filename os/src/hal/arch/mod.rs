@@ -12,7 +12,7 @@
 //!     - `kernel_stack`：内核栈分配和管理接口
 //!     - `sbi`：控制台、关机等系统调用接口
 //!     - `switch`：任务上下文切换函数
-//!     - `sync`：中断屏蔽信息
+//!     - `sync`：中断屏蔽信息与内存屏障
 //!     - `timer`：时钟和定时器接口
 //!     - `trap`：TrapContext 和中断处理
 //!     - 页表类型别名：`PageTableImpl` / `PageTableEntryImpl`
@@ -39,22 +39,34 @@ pub use riscv::{
     bootstrap_init,
     // 配置常量
     config::{
-        UserStackBase, BLOCK_SZ, KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END, PAGE_SIZE,
-        PAGE_SIZE_BITS, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE,
+        UserStackBase, BLOCK_SZ, DIRECT_MAP_SIZE, KERNEL_HEAP_SIZE, KERNEL_OFFSET,
+        KERNEL_STACK_SIZE, MEMORY_END, MMAP_BASE, MMAP_TOP, PAGE_SIZE, PAGE_SIZE_BITS, PALEN,
+        PTES_PER_TABLE, PT_LEVELS, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE, VA_BITS,
+        VA_MASK, VA_SIGN_EXTEND,
     },
+    // 帧指针（panic 回溯）
+    frame_pointer,
     // 内核栈管理
     kernel_stack::{kstack_alloc, trap_cx_bottom_from_tid, ustack_bottom_from_tid, KernelStack},
     machine_init,
+    // 外部中断控制器
+    plic::enable_irq,
     // SBI 系统调用
-    sbi::{console_flush, console_getchar, console_putchar, shutdown},
+    sbi::{console_flush, console_getchar, console_putchar, console_write, send_ipi, shutdown},
+    // ASID 位数探测与本 hart 的 TLB 失效
+    sv39::{asid_bits, local_flush_tlb},
     // 任务上下文切换
     switch::__switch,
-    // 中断屏蔽管理
-    sync::INTR_MASKING_INFO,
+    // 中断屏蔽管理与内存屏障
+    sync::{memory_barrier, INTR_MASKING_INFO},
     // 时钟与定时器
-    timer::{get_clock_freq, get_time},
+    timer::{get_clock_freq, get_time, set_timer_event, TICKS_PER_SEC},
     // Trap 相关
-    trap::{context::TrapContext, trap_handler, trap_return},
+    trap::{
+        clear_ipi,
+        context::{TrapContext, ELF_MACHINE, ELF_NGREG},
+        trap_handler, trap_return, wait_for_interrupt,
+    },
     // 页表类型别名
     PageTableEntryImpl,
     PageTableImpl,
@@ -65,26 +77,55 @@ pub mod loongarch;
 
 #[cfg(feature = "loongarch")]
 pub use loongarch::{
+    // ASID 位数探测
+    asid_bits,
     // 启动与初始化
     bootstrap_init,
+    // 核间中断
+    clear_ipi,
     // 配置常量
     config::{
-        UserStackBase, HIGH_BASE_EIGHT, KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END,
-        MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN, MEMORY_SIZE, PAGE_SIZE, PAGE_SIZE_BITS, PALEN,
-        TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE, VA_MASK, VPN_SEG_MASK,
+        UserStackBase, DIRECT_MAP_SIZE, HIGH_BASE_EIGHT, KERNEL_HEAP_SIZE, KERNEL_OFFSET,
+        KERNEL_STACK_SIZE, MEMORY_END, MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN, MEMORY_SIZE,
+        MMAP_BASE, MMAP_TOP, PAGE_SIZE, PAGE_SIZE_BITS, PALEN, PTES_PER_TABLE, PT_LEVELS,
+        TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE, VA_BITS, VA_MASK, VA_SIGN_EXTEND,
+        VPN_SEG_MASK,
     },
+    enable_irq,
+    // 帧指针（panic 回溯）
+    frame_pointer,
     // 内核栈管理
     kernel_stack::{kstack_alloc, KernelStack},
+    // 本核的 TLB 失效
+    local_flush_tlb,
     machine_init,
     // SBI 系统调用
-    sbi::{console_flush, console_getchar, console_putchar, shutdown},
-    // 中断屏蔽管理
-    sync::INTR_MASKING_INFO,
+    sbi::{console_flush, console_getchar, console_putchar, console_write, shutdown},
+    send_ipi,
+    // 中断屏蔽管理与内存屏障
+    sync::{memory_barrier, INTR_MASKING_INFO},
     // 时钟与定时器
-    timer::{get_clock_freq, get_time},
+    timer::{get_clock_freq, get_time, set_timer_event, TICKS_PER_SEC},
     // Trap 相关
-    trap::{context::TrapContext, trap_handler, trap_return},
+    trap::{
+        context::{TrapContext, ELF_MACHINE, ELF_NGREG},
+        trap_handler, trap_return, wait_for_interrupt,
+    },
     // 页表类型别名
     PageTableEntryImpl,
     PageTableImpl,
 };
+
+// 软件单步（ptrace 与 GDB 调试桩共用）
+#[cfg(feature = "riscv")]
+pub use riscv::step;
+
+#[cfg(feature = "loongarch")]
+pub use loongarch::step;
+
+// GDB 调试桩的体系结构相关部分
+#[cfg(all(feature = "riscv", feature = "gdbstub"))]
+pub use riscv::gdb;
+
+#[cfg(all(feature = "loongarch", feature = "gdbstub"))]
+pub use loongarch::gdb;
@@ -1,15 +1,25 @@
 //! 低级启动汇编代码（Boot Assembly Code）
 //!
-//! 这段汇编用于程序的最初启动阶段，设置栈指针，并跳转到 Rust 的主入口函数 `rust_main`。
-//! 同时定义了一个用于内核或裸机程序的静态栈空间。
+//! 这段汇编用于程序的最初启动阶段，启用启动页表、设置栈指针，并跳转到 Rust 的主入口函数 `rust_main`。
+//! 同时定义了一个用于内核或裸机程序的静态栈空间与启动页表。
 //!
 //! 主要功能包括：
-//! 1. 设置栈指针 `sp`。
-//! 2. 调用 Rust 层的主函数 `rust_main`。
-//! 3. 定义 `.bss` 段的栈空间。
+//! 1. 以 `boot_page_table` 启用 SV39 分页：内核链接在高半部分（`KERNEL_OFFSET` 之上），
+//!    而 SBI 以物理地址跳转到 `_start`，启动页表同时映射两者。
+//! 2. 设置栈指针 `sp` 为栈顶的高半部分虚拟地址。
+//! 3. 跳转到高半部分的 `rust_main`，SBI 传入的 `a0`（hart id）与 `a1`（设备树地址）原样作为参数。
+//! 4. 定义 `.bss` 段的栈空间。
+//!
+//! 启动页表由 1GB 大页组成：
+//! - 0 ~ 3 项：物理地址 0 ~ 4GB 的恒等映射，只用于启用分页后到跳转之前的几条指令
+//! - 256 ~ 259 项：`KERNEL_OFFSET` 起的 4GB 映射到物理地址 0 ~ 4GB，即内核直接映射区
+//!
+//! `mm::init` 建立内核页表后不再使用启动页表，内核页表中没有低半部分的映射。
 //!
 //! 注意：这是裸机或操作系统内核开发中的启动代码，不依赖标准库。
+//! `_start` 运行时尚未启用分页，其中的 `la` 均为 PC 相对寻址，得到的是物理地址。
 
+use super::config::KERNEL_OFFSET;
 use core::arch::global_asm;
 
 global_asm!(
@@ -17,8 +27,20 @@ global_asm!(
     .section .text.entry
     .globl _start
 _start:
+    # satp = (8 << 60) | ppn(boot_page_table)
+    la t0, boot_page_table
+    srli t0, t0, 12
+    li t1, 8 << 60
+    or t0, t0, t1
+    csrw satp, t0
+    sfence.vma
+    # 栈与入口换算为高半部分的虚拟地址
+    li t1, {kernel_offset}
     la sp, boot_stack_top
-    call rust_main
+    add sp, sp, t1
+    la t0, rust_main
+    add t0, t0, t1
+    jr t0
 
     .section .bss.stack
     .globl boot_stack
@@ -26,5 +48,22 @@ boot_stack:
     .space 4096 * 64
     .globl boot_stack_top
 boot_stack_top:
-"#
+
+    .section .data
+    .align 12
+boot_page_table:
+    # 0x0000_0000 ~ 0xffff_ffff -> 0x0000_0000 ~ 0xffff_ffff，VRWXAD
+    .quad (0x00000 << 10) | 0xcf
+    .quad (0x40000 << 10) | 0xcf
+    .quad (0x80000 << 10) | 0xcf
+    .quad (0xc0000 << 10) | 0xcf
+    .zero 8 * 252
+    # KERNEL_OFFSET ~ KERNEL_OFFSET + 0xffff_ffff -> 0x0000_0000 ~ 0xffff_ffff
+    .quad (0x00000 << 10) | 0xcf
+    .quad (0x40000 << 10) | 0xcf
+    .quad (0x80000 << 10) | 0xcf
+    .quad (0xc0000 << 10) | 0xcf
+    .zero 8 * 252
+"#,
+    kernel_offset = const KERNEL_OFFSET,
 );
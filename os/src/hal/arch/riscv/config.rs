@@ -12,6 +12,34 @@ pub const PAGE_SIZE: usize = 0x1000; // 4 * 1024 = 4096 bytes
 /// 例如，页对齐地址可以用 addr >> PAGE_SIZE_BITS
 pub const PAGE_SIZE_BITS: usize = 0xc; // 12，即 2^12 = 4096 bytes
 
+/// 页表项的大小（字节）
+pub const PTE_WIDTH: usize = 8;
+
+/// 页表项大小对应的位数
+pub const PTE_WIDTH_BITS: usize = PTE_WIDTH.trailing_zeros() as usize; // 3
+
+/// 每级页表的索引位数：一个页表页恰好容纳 2^DIR_WIDTH 个页表项
+pub const DIR_WIDTH: usize = PAGE_SIZE_BITS - PTE_WIDTH_BITS; // 9
+
+/// 每个页表页中的页表项数
+pub const PTES_PER_TABLE: usize = 1 << DIR_WIDTH; // 512
+
+/// 页表级数，SV39 为三级
+pub const PT_LEVELS: usize = 3;
+
+/// 物理地址位数，SV39 的页表项可以表示 56 位物理地址
+pub const PALEN: usize = 56;
+
+/// 虚拟地址的有效位数，由页内偏移与各级页表索引组成
+pub const VA_BITS: usize = PAGE_SIZE_BITS + PT_LEVELS * DIR_WIDTH; // 39
+
+/// 虚拟地址掩码，低 39 位为 1
+pub const VA_MASK: usize = (1 << VA_BITS) - 1;
+
+/// 虚拟地址转为 `usize` 时是否做符号扩展：SV39 要求第 63～39 位与第 38 位一致，
+/// 高半部分的内核地址因此以全 1 开头
+pub const VA_SIGN_EXTEND: bool = true;
+
 /// 用户态栈大小，2 页，总共 8KB
 pub const USER_STACK_SIZE: usize = PAGE_SIZE * 2; // 8 KB
 
@@ -31,8 +59,24 @@ pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1; // 通常是虚拟地
 /// 紧邻 trampoline 之下，占一页
 pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE; // 位于 trampoline 之前
 
+/// 内核直接映射区的起始虚拟地址
+/// 物理地址 `pa` 在内核地址空间中的虚拟地址为 `pa + KERNEL_OFFSET`，
+/// 位于 SV39 高半部分的起点，低半部分的虚拟地址全部留给用户程序
+pub const KERNEL_OFFSET: usize = 0xffff_ffc0_0000_0000;
+
+/// 内核直接映射区覆盖的物理地址范围大小，4GB
+/// 与启动页表中的 4 个 1GB 大页一致，内核栈等不在此范围内的虚拟地址不能直接换算为物理地址
+pub const DIRECT_MAP_SIZE: usize = 4 << 30;
+
+/// 用户地址空间中 mmap 区域的起始地址，128GB
+/// 未指定地址的 mmap 与共享内存挂接在 `[MMAP_BASE, MMAP_TOP)` 中分配，堆（brk）不能越过此地址
+pub const MMAP_BASE: usize = 0x20_0000_0000;
+
+/// 用户地址空间中 mmap 区域的结束地址，即 SV39 低半部分的上界
+pub const MMAP_TOP: usize = 0x40_0000_0000;
+
 /// 内存结束地址
-/// 用于标记物理或虚拟内存的可用上限
+/// 用于标记物理或虚拟内存的可用上限，设备树中有内存节点时以设备树为准（`boot::memory_end`）
 pub const MEMORY_END: usize = 0x8800_0000; // 约 2.2 GB
 
 /// 内存块大小，512 字节
@@ -0,0 +1,129 @@
+//! GDB 调试桩的 RISC-V 相关部分
+//! # Overview
+//! 为 `crate::gdbstub` 提供体系结构相关的操作：
+//! - 内核陷入帧与 GDB 寄存器编号（x0..x31、pc）之间的转换
+//! - 断点指令（`ebreak` / `c.ebreak`）的识别
+//! - 软件单步：RISC-V 在 S 态没有硬件单步，由 `step` 解码当前指令求出所有可能的后继地址，
+//!   在这些地址上放置临时断点
+//!
+//! # Assumptions
+//! - 内核陷入帧由 `__alltraps_k` 在内核栈上构造，布局与 `TrapContext` 的前 34 个字一致，
+//!   其中不保存 sp 与 tp；陷入前的 sp 等于帧地址加上帧大小
+//! - 调试桩启用时内核代码段以可写方式映射（见 `MemorySet::new_kernel`）
+//!
+//! # Safety
+//! - 读写内存前通过内核页表检查地址是否已映射
+
+use super::step;
+use super::trap::context::TrapContext;
+use crate::hal::{PageTableImpl, PAGE_SIZE};
+use crate::mm::{PageTable, VirtAddr};
+
+/// 内核陷入时使用的寄存器帧
+pub type KernelTrapFrame = TrapContext;
+
+/// GDB 的 RISC-V 寄存器个数：x0..x31 与 pc
+pub const REG_COUNT: usize = 33;
+
+/// `__alltraps_k` 在内核栈上分配的帧大小
+const KERNEL_FRAME_SIZE: usize = 34 * 8;
+
+fn gprs(frame: &KernelTrapFrame) -> &[usize; 32] {
+    unsafe { &*(&frame.general_regs as *const _ as *const [usize; 32]) }
+}
+
+fn gprs_mut(frame: &mut KernelTrapFrame) -> &mut [usize; 32] {
+    unsafe { &mut *(&mut frame.general_regs as *mut _ as *mut [usize; 32]) }
+}
+
+/// 读取第 `reg` 号通用寄存器在陷入前的值
+fn gpr(frame: &KernelTrapFrame, reg: usize) -> usize {
+    match reg {
+        0 => 0,
+        2 => frame as *const _ as usize + KERNEL_FRAME_SIZE,
+        4 => {
+            let tp: usize;
+            unsafe { core::arch::asm!("mv {}, tp", out(reg) tp) };
+            tp
+        }
+        _ => gprs(frame)[reg],
+    }
+}
+
+/// 按 GDB 编号读出全部寄存器
+pub fn read_regs(frame: &KernelTrapFrame, regs: &mut [usize; REG_COUNT]) {
+    for (reg, value) in regs.iter_mut().enumerate().take(32) {
+        *value = gpr(frame, reg);
+    }
+    regs[32] = frame.sepc;
+}
+
+/// 按 GDB 编号写回寄存器；sp 与 tp 不在陷入帧中，对它们的修改被忽略
+pub fn write_regs(frame: &mut KernelTrapFrame, regs: &[usize; REG_COUNT]) {
+    let gprs = gprs_mut(frame);
+    for reg in (1..32).filter(|&reg| reg != 2 && reg != 4) {
+        gprs[reg] = regs[reg];
+    }
+    frame.sepc = regs[32];
+}
+
+pub fn pc(frame: &KernelTrapFrame) -> usize {
+    frame.sepc
+}
+
+pub fn set_pc(frame: &mut KernelTrapFrame, pc: usize) {
+    frame.sepc = pc;
+}
+
+fn read_u16(addr: usize) -> u16 {
+    unsafe { (addr as *const u16).read_unaligned() }
+}
+
+/// 用于替换 `addr` 处指令的断点指令，长度与原指令相同
+pub fn breakpoint_insn(addr: usize) -> &'static [u8] {
+    step::breakpoint_insn(step::insn_len(read_u16(addr)))
+}
+
+/// `addr` 处是否为编译进内核的断点指令，是则返回其长度
+pub fn hardcoded_breakpoint(addr: usize) -> Option<usize> {
+    let insn = breakpoint_insn(addr);
+    let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, insn.len()) };
+    (bytes == insn).then_some(insn.len())
+}
+
+/// 当前指令执行后所有可能的下一条指令地址
+pub fn step_targets(frame: &KernelTrapFrame) -> [Option<usize>; 2] {
+    step::next_pcs(
+        frame.sepc,
+        |addr| Some(read_u16(addr)),
+        |reg| gpr(frame, reg),
+    )
+}
+
+/// `[addr, addr + len)` 是否都已映射在内核地址空间中
+pub fn accessible(kernel_token: usize, addr: usize, len: usize) -> bool {
+    let page_table = PageTableImpl::from_token(kernel_token);
+    let end = match addr.checked_add(len) {
+        Some(end) => end,
+        None => return false,
+    };
+    let mut page = addr & !(PAGE_SIZE - 1);
+    while page < end {
+        let pte = page_table.translate(VirtAddr::from(page).floor());
+        if !pte.is_some_and(|pte| pte.is_valid()) {
+            return false;
+        }
+        page += PAGE_SIZE;
+    }
+    true
+}
+
+/// 修改代码后使指令缓存失效
+pub fn flush_icache() {
+    unsafe { core::arch::asm!("fence.i") };
+}
+
+/// 主动陷入调试桩
+pub fn trigger_breakpoint() {
+    unsafe { core::arch::asm!("ebreak") };
+}
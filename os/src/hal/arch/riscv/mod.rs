@@ -8,6 +8,7 @@
 //! - `machine_init()`：初始化机器相关部分，设置中断处理函数和定时器中断。
 //! - 通过 `trap::init()` 初始化中断向量。
 //! - 通过 `trap::enable_timer_interrupt()` 启用时钟中断。
+//! - 通过 `trap::enable_external_interrupt()` 启用外部中断，具体中断源由驱动通过 `plic::enable_irq()` 使能。
 //! - 通过 `set_next_trigger()` 设置下一次定时器触发。
 //! - 提供类型别名 `PageTableImpl` 和 `PageTableEntryImpl`，统一上层内核页表接口。
 //!
@@ -28,8 +29,12 @@ use crate::hal::arch::riscv::timer::set_next_trigger;
 
 pub mod boot;
 pub mod config;
+#[cfg(feature = "gdbstub")]
+pub mod gdb;
 pub mod kernel_stack;
+pub mod plic;
 pub mod sbi;
+pub mod step;
 pub mod sv39;
 pub mod switch;
 pub mod sync;
@@ -45,15 +50,33 @@ pub fn bootstrap_init() {}
 /// 初始化机器相关部分
 ///
 /// # Overview
+/// - 使用设备树中的定时器频率（若有）
 /// - 初始化中断处理函数
-/// - 启用时钟中断
+/// - 启用时钟中断与外部中断
 /// - 设置下一次定时器触发
 pub fn machine_init() {
+    if let Some(freq) = crate::boot::timebase_freq() {
+        timer::set_clock_freq(freq);
+    }
     trap::init();
     trap::enable_timer_interrupt();
+    trap::enable_external_interrupt();
+    trap::enable_ipi_interrupt();
     set_next_trigger();
 }
 
+/// 读取当前帧指针（s0），内核以 `-Cforce-frame-pointers=yes` 编译
+///
+/// 栈帧布局：`fp - 8` 处保存返回地址，`fp - 16` 处保存上一帧的帧指针
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        core::arch::asm!("mv {}, s0", out(reg) fp);
+    }
+    fp
+}
+
 /// 页表实现类型别名
 ///
 /// # Overview
@@ -0,0 +1,64 @@
+//! RISC-V 平台级中断控制器（PLIC）
+//! # Overview
+//! 本模块提供 QEMU virt 机器上 PLIC 的最小驱动，只服务于 0 号 hart 的 S 态上下文：
+//! - `enable_irq`：设置中断源优先级并在 S 态上下文中使能该中断源
+//! - `claim` / `complete`：外部中断到来时领取中断号，处理完毕后通知 PLIC
+//!
+//! # Assumptions
+//! - PLIC 物理基地址为 `0x0C00_0000`，且该区域已映射到内核直接映射区（见平台 `MMIO`）
+//! - 内核只在 0 号 hart 上处理外部中断，其 S 态上下文编号为 1
+//!
+//! # Safety
+//! - 所有寄存器访问均为 volatile MMIO 读写，调用者需保证 PLIC 已映射
+//!
+//! # Invariants
+//! - S 态上下文的优先级阈值恒为 0，任何优先级大于 0 的已使能中断源都会被投递
+
+use super::config::KERNEL_OFFSET;
+use core::ptr::{read_volatile, write_volatile};
+
+/// PLIC 寄存器基地址（直接映射区中的虚拟地址）
+const PLIC_BASE: usize = KERNEL_OFFSET + 0x0C00_0000;
+/// 0 号 hart S 态对应的上下文编号
+const S_CONTEXT: usize = 1;
+
+fn priority_reg(irq: usize) -> *mut u32 {
+    (PLIC_BASE + irq * 4) as *mut u32
+}
+
+fn enable_reg(irq: usize) -> *mut u32 {
+    (PLIC_BASE + 0x2000 + S_CONTEXT * 0x80 + (irq / 32) * 4) as *mut u32
+}
+
+fn threshold_reg() -> *mut u32 {
+    (PLIC_BASE + 0x20_0000 + S_CONTEXT * 0x1000) as *mut u32
+}
+
+fn claim_reg() -> *mut u32 {
+    (PLIC_BASE + 0x20_0004 + S_CONTEXT * 0x1000) as *mut u32
+}
+
+/// 在 S 态上下文中使能中断源 `irq`
+pub fn enable_irq(irq: usize) {
+    unsafe {
+        write_volatile(priority_reg(irq), 1);
+        let enable = read_volatile(enable_reg(irq));
+        write_volatile(enable_reg(irq), enable | 1 << (irq % 32));
+        write_volatile(threshold_reg(), 0);
+    }
+}
+
+/// 领取当前待处理的外部中断号，没有待处理中断时返回 `None`
+pub fn claim() -> Option<usize> {
+    match unsafe { read_volatile(claim_reg()) } {
+        0 => None,
+        irq => Some(irq as usize),
+    }
+}
+
+/// 通知 PLIC 中断 `irq` 已处理完毕
+pub fn complete(irq: usize) {
+    unsafe {
+        write_volatile(claim_reg(), irq as u32);
+    }
+}
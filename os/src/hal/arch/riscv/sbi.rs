@@ -1,16 +1,20 @@
 //! SBI 调用模块
 //! # Overview
 //! 本模块提供对 RISC-V SBI（Supervisor Binary Interface）的封装，用于内核和平台交互。
-//! 包含定时器设置、控制台输入输出、IPI（Inter-Processor Interrupt）、页表同步和系统关机等功能。
+//! 包含定时器设置、控制台输入输出、IPI（Inter-Processor Interrupt）和系统关机等功能。
 //!
 //! # Design
 //! - 所有 SBI 调用通过 `ecall` 指令触发陷入 S 模式执行。
 //! - `sbi_call` 函数是通用封装，将函数号和参数传递给 SBI。
 //! - 上层函数（如 `set_timer`、`console_putchar`）直接调用 `sbi_call`，简化内核接口。
+//! - `console_write` 优先使用 SBI 2.0 的调试控制台扩展（DBCN）一次写出多个字节，
+//!   固件不支持时退回逐字符的 `console_putchar`。
 //!
 //! # Assumptions
 //! - 内核运行在 S 模式下，并且底层固件或 SBI 实现可响应这些调用。
 //! - 调用参数和返回值遵循 RISC-V SBI ABI 规范。
+//! - DBCN 需要缓冲区的物理地址，只有位于内核直接映射区中的缓冲区才能整段交给固件，
+//!   其余（如内核栈上的缓冲区）逐字符输出。
 //!
 //! # Safety
 //! - `sbi_call` 使用裸 `asm!` 调用 ecall，需要确保传入参数正确且安全。
@@ -24,6 +28,7 @@
 #![allow(unused)]
 
 use core::arch::asm;
+use core::sync::atomic::{AtomicU8, Ordering};
 
 /// SBI (Supervisor Binary Interface) 系统调用常量
 const SBI_SET_TIMER: usize = 0;
@@ -36,6 +41,17 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
+/// SBI 0.2 之后按扩展号（EID）与功能号（FID）调用的扩展
+const SBI_EXT_BASE: usize = 0x10;
+const SBI_BASE_PROBE_EXTENSION: usize = 3;
+const SBI_EXT_DBCN: usize = 0x4442_434E;
+const SBI_EXT_IPI: usize = 0x0073_5049;
+const SBI_IPI_SEND: usize = 0;
+const SBI_DBCN_WRITE: usize = 0;
+
+/// DBCN 扩展是否可用：0 尚未探测，1 可用，2 不可用
+static DBCN_STATE: AtomicU8 = AtomicU8::new(0);
+
 /// 通用 SBI 调用封装函数
 ///
 /// # Fields
@@ -64,6 +80,44 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// 按 SBI 0.2 调用约定调用扩展 `eid` 的功能 `fid`，返回 `(error, value)`
+fn sbi_ecall(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> (isize, usize) {
+    let (error, value);
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") arg0 => error,
+        inlateout("x11") arg1 => value,
+        in("x12") arg2,
+        in("x16") fid,
+        in("x17") eid,
+        );
+    }
+    (error, value)
+}
+
+/// 固件是否支持 DBCN 扩展，第一次调用时探测
+fn has_dbcn() -> bool {
+    match DBCN_STATE.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            let (error, value) =
+                sbi_ecall(SBI_EXT_BASE, SBI_BASE_PROBE_EXTENSION, SBI_EXT_DBCN, 0, 0);
+            let supported = error == 0 && value != 0;
+            DBCN_STATE.store(if supported { 1 } else { 2 }, Ordering::Relaxed);
+            supported
+        }
+    }
+}
+
+/// 向 `hart_mask` 中的 hart（位 i 对应 i 号 hart）发送 S 态软件中断
+///
+/// 使用 SBI 0.2 的 IPI 扩展，位图按值传递，不需要像旧接口那样传入位图的地址
+pub fn send_ipi(hart_mask: usize) {
+    sbi_ecall(SBI_EXT_IPI, SBI_IPI_SEND, hart_mask, 0, 0);
+}
+
 /// 设置定时器
 ///
 /// # Arguments
@@ -84,6 +138,29 @@ pub fn console_putchar(c: usize) {
     sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
 }
 
+/// 控制台输出一段字节
+///
+/// 支持 DBCN 时整段交给固件写出（固件可能只写出一部分，需要循环），
+/// 否则或出错时剩余部分逐字符输出。
+pub fn console_write(bytes: &[u8]) {
+    let mut rest = bytes;
+    if has_dbcn() {
+        while !rest.is_empty() {
+            let Some(pa) = crate::mm::virt_to_phys(rest.as_ptr() as usize) else {
+                break;
+            };
+            let (error, written) = sbi_ecall(SBI_EXT_DBCN, SBI_DBCN_WRITE, rest.len(), pa, 0);
+            if error != 0 || written == 0 {
+                break;
+            }
+            rest = &rest[written.min(rest.len())..];
+        }
+    }
+    for &byte in rest {
+        console_putchar(byte as usize);
+    }
+}
+
 /// 控制台读取一个字符
 ///
 /// # Returns
@@ -107,6 +184,7 @@ pub fn console_flush() {}
 /// - 如果关机失败，会触发 panic。
 pub fn shutdown() -> ! {
     println!("run shutdown");
+    crate::console::flush();
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
 }
@@ -0,0 +1,116 @@
+//! 软件单步（RISC-V）
+//! # Overview
+//! RISC-V 在 S 态与 U 态都没有可用的硬件单步，内核调试桩与 `ptrace` 单步都通过
+//! “在当前指令所有可能的后继地址放置断点指令”来实现。本模块负责：
+//! - 判断指令长度（压缩指令为 2 字节）
+//! - 给出与原指令等长的断点指令（`ebreak` / `c.ebreak`）
+//! - 解码跳转与分支指令，求出后继地址
+//!
+//! # Assumptions
+//! - 取指由调用者提供：内核地址可直接读取，用户地址需要经过页表翻译
+
+const EBREAK: [u8; 4] = 0x0010_0073u32.to_le_bytes();
+const C_EBREAK: [u8; 2] = 0x9002u16.to_le_bytes();
+
+/// 以指令的低 16 位判断指令长度
+pub fn insn_len(low: u16) -> usize {
+    if low & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// 与 `len` 字节长的指令等长的断点指令
+pub fn breakpoint_insn(len: usize) -> &'static [u8] {
+    if len == 4 {
+        &EBREAK
+    } else {
+        &C_EBREAK
+    }
+}
+
+fn sign_extend(value: usize, bits: u32) -> usize {
+    let shift = usize::BITS - bits;
+    (((value << shift) as isize) >> shift) as usize
+}
+
+/// `pc` 处的指令执行后所有可能的下一条指令地址
+///
+/// - `fetch(addr)` 读取 `addr` 处的 16 位指令片，无法读取时返回 `None`
+/// - `reg(n)` 读取第 `n` 号通用寄存器在该指令执行前的值
+pub fn next_pcs(
+    pc: usize,
+    fetch: impl Fn(usize) -> Option<u16>,
+    reg: impl Fn(usize) -> usize,
+) -> [Option<usize>; 2] {
+    let low = match fetch(pc) {
+        Some(low) => low,
+        None => return [None, None],
+    };
+    if insn_len(low) == 2 {
+        let insn = low as usize;
+        let bit = |n: usize| (insn >> n) & 1;
+        let (op, funct3) = (insn & 0b11, (insn >> 13) & 0b111);
+        match (op, funct3) {
+            // c.j
+            (1, 5) => {
+                let imm = (bit(12) << 11)
+                    | (bit(11) << 4)
+                    | (((insn >> 9) & 0b11) << 8)
+                    | (bit(8) << 10)
+                    | (bit(7) << 6)
+                    | (bit(6) << 7)
+                    | (((insn >> 3) & 0b111) << 1)
+                    | (bit(2) << 5);
+                [Some(pc.wrapping_add(sign_extend(imm, 12))), None]
+            }
+            // c.beqz / c.bnez
+            (1, 6) | (1, 7) => {
+                let imm = (bit(12) << 8)
+                    | (((insn >> 10) & 0b11) << 3)
+                    | (((insn >> 5) & 0b11) << 6)
+                    | (((insn >> 3) & 0b11) << 1)
+                    | (bit(2) << 5);
+                [Some(pc + 2), Some(pc.wrapping_add(sign_extend(imm, 9)))]
+            }
+            // c.jr / c.jalr
+            (2, 4) if (insn >> 2) & 0x1f == 0 && (insn >> 7) & 0x1f != 0 => {
+                [Some(reg((insn >> 7) & 0x1f) & !1), None]
+            }
+            _ => [Some(pc + 2), None],
+        }
+    } else {
+        let high = match fetch(pc + 2) {
+            Some(high) => high,
+            None => return [None, None],
+        };
+        let insn = ((high as usize) << 16) | low as usize;
+        let bit = |n: usize| (insn >> n) & 1;
+        match insn & 0x7f {
+            // jal
+            0x6f => {
+                let imm = (bit(31) << 20)
+                    | (((insn >> 21) & 0x3ff) << 1)
+                    | (bit(20) << 11)
+                    | (((insn >> 12) & 0xff) << 12);
+                [Some(pc.wrapping_add(sign_extend(imm, 21))), None]
+            }
+            // jalr
+            0x67 => {
+                let base = reg((insn >> 15) & 0x1f);
+                let imm = sign_extend(insn >> 20, 12);
+                [Some(base.wrapping_add(imm) & !1), None]
+            }
+            // beq / bne / blt / bge / bltu / bgeu
+            0x63 => {
+                let imm = (bit(31) << 12)
+                    | (((insn >> 25) & 0x3f) << 5)
+                    | (((insn >> 8) & 0xf) << 1)
+                    | (bit(7) << 11);
+                [Some(pc + 4), Some(pc.wrapping_add(sign_extend(imm, 13)))]
+            }
+            _ => [Some(pc + 4), None],
+        }
+    }
+}
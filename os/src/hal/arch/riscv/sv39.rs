@@ -10,6 +10,11 @@
 //! - 每个页表条目（PTE）包含物理页号（PPN）和标记位（PTEFlags）。
 //! - 页表使用 `frames` 记录当前分配的物理页，用于生命周期管理。
 //! - 映射操作保证不会覆盖已存在的有效映射。
+//! - 第 1 级（2MB）页表项可以直接作为叶子映射大页；按 4KB 修改大页中的某一页时，
+//!   先把大页拆成一张映射相同的 4KB 页表。
+//! - 每个用户页表持有一个 ASID 并写入 SATP，TLB 项按 ASID 区分地址空间；
+//!   映射变化时只使对应页的 TLB 项失效，切换地址空间时无需清空 TLB。
+//!   ASID 耗尽时页表使用与内核相同的 ASID 0，由陷阱入口与返回时清空 TLB。
 //!
 //! # Assumptions
 //! - 物理页分配（frame_alloc）不会失败，不考虑 OOM。
@@ -27,9 +32,10 @@
 //! - 页表条目有效性（V 位）与权限位保持一致。
 //! - 激活页表后，SATP 寄存器反映根页表地址，并完成 TLB 同步。
 
+use crate::hal::PT_LEVELS;
 use crate::mm::{
-    frame_alloc, FrameTracker, MapPermission, PageTable, PhysAddr, PhysPageNum, VirtAddr,
-    VirtPageNum,
+    asid_alloc, frame_alloc, Asid, FrameTracker, MapPermission, PageSize, PageTable, PhysAddr,
+    PhysPageNum, VirtAddr, VirtPageNum, HUGE_PAGE_PAGES,
 };
 use alloc::vec;
 use alloc::vec::Vec;
@@ -121,6 +127,65 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+
+    /// 判断页是否允许用户态访问
+    pub fn is_user(&self) -> bool {
+        (self.flags() & PTEFlags::U) != PTEFlags::empty()
+    }
+
+    /// 判断有效的 PTE 是否为叶子项（R / W / X 不全为 0），否则指向下一级页表
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid()
+            && (self.flags() & (PTEFlags::R | PTEFlags::W | PTEFlags::X)) != PTEFlags::empty()
+    }
+
+    /// 创建换出页的 PTE：V 位为 0，RSW 中的 `SWAPPED` 位为 1，PPN 字段存放交换槽号
+    pub fn swapped(slot: usize) -> Self {
+        PageTableEntry {
+            bits: slot << 10 | SWAPPED,
+        }
+    }
+
+    /// 换出页的交换槽号，不是换出页时返回 `None`
+    pub fn swap_slot(&self) -> Option<usize> {
+        if self.is_valid() || self.bits & SWAPPED == 0 {
+            return None;
+        }
+        Some(self.bits >> 10)
+    }
+
+    /// 读取并清除 A 位，返回该页自上次清除以来是否被访问过
+    ///
+    /// 清除后旧的 TLB 项可能仍然缓存着 A 位，调用者需通过 `flush_tlb` 使其失效
+    pub fn take_accessed(&mut self) -> bool {
+        let accessed = self.bits & PTEFlags::A.bits() as usize != 0;
+        self.bits &= !(PTEFlags::A.bits() as usize);
+        accessed
+    }
+}
+
+/// 换出页 PTE 的标记位（RSW 的低位，硬件忽略）
+const SWAPPED: usize = 1 << 8;
+
+/// 叶子项所在的页表级别（0 为根页表）
+const LEAF_LEVEL: usize = PT_LEVELS - 1;
+/// 大页叶子项所在的页表级别
+const HUGE_LEVEL: usize = PT_LEVELS - 2;
+
+/// 把大页叶子项拆成一张映射相同的 4KB 页表，返回新页表所在的页帧
+fn split_huge(pte: &mut PageTableEntry) -> FrameTracker {
+    let frame = frame_alloc().unwrap();
+    let (base, flags) = (pte.ppn().0, pte.flags());
+    for (i, entry) in frame
+        .ppn
+        .get_pte_array::<PageTableEntry>()
+        .iter_mut()
+        .enumerate()
+    {
+        *entry = PageTableEntry::new(PhysPageNum(base + i), flags);
+    }
+    *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+    frame
 }
 
 /// SV39 页表实现
@@ -136,6 +201,7 @@ impl PageTableEntry {
 /// # Fields
 /// - `root_ppn`：根页表物理页号
 /// - `frames`：当前页表使用的物理页集合
+/// - `asid`：用户页表的 ASID，内核页表、临时页表与未分配到 ASID 的页表为 `None`
 ///
 /// # Assumptions
 /// - frame_alloc() 分配成功，不考虑 OOM
@@ -149,15 +215,66 @@ impl PageTableEntry {
 pub struct SV39PageTable {
     root_ppn: PhysPageNum,
     frames: Vec<FrameTracker>,
+    asid: Option<Asid>,
+}
+
+impl SV39PageTable {
+    /// 查找或创建 vpn 在第 `level` 级（0 为根页表）的页表项，途经的大页会被拆分
+    fn find_entry_create(&mut self, vpn: VirtPageNum, level: usize) -> &mut PageTableEntry {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
+            if i == level {
+                result = Some(pte);
+                break;
+            }
+            if !pte.is_valid() {
+                let frame = frame_alloc().unwrap();
+                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+                self.frames.push(frame);
+            } else if pte.is_leaf() {
+                let frame = split_huge(pte);
+                self.frames.push(frame);
+            }
+            ppn = pte.ppn();
+        }
+        result.unwrap()
+    }
+
+    /// 查找 vpn 所在的叶子项及其级别，途经无效的页表项时返回 None
+    fn find_leaf(&self, vpn: VirtPageNum) -> Option<(&mut PageTableEntry, usize)> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<(&mut PageTableEntry, usize)> = None;
+        for (i, idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
+            if i == LEAF_LEVEL || pte.is_leaf() {
+                result = Some((pte, i));
+                break;
+            }
+            if !pte.is_valid() {
+                return None;
+            }
+            ppn = pte.ppn();
+        }
+        result
+    }
 }
 
 impl PageTable for SV39PageTable {
-    /// 创建新的空页表
+    /// 创建新的空页表，并清除分配到的 ASID 上一个持有者留下的 TLB 项
     fn new() -> Self {
         let frame = frame_alloc().unwrap();
+        let asid = asid_alloc();
+        if let Some(asid) = &asid {
+            unsafe { asm!("sfence.vma zero, {}", in(reg) asid.id()) };
+        }
         Self {
             root_ppn: frame.ppn,
             frames: vec![frame],
+            asid,
         }
     }
 
@@ -167,6 +284,7 @@ impl PageTable for SV39PageTable {
         Self {
             root_ppn: frame.ppn,
             frames: vec![frame],
+            asid: None,
         }
     }
 
@@ -175,86 +293,102 @@ impl PageTable for SV39PageTable {
         Self {
             root_ppn: PhysPageNum::from(satp & ((1usize << 44) - 1)),
             frames: Vec::new(),
+            asid: None,
         }
     }
 
     /// 查找或创建页表条目
     ///
     /// # Design
-    /// 尝试查找 vpn 对应的物理 pte，若 pte 还没有创建就先创建
+    /// 尝试查找 vpn 对应的物理 pte，若 pte 还没有创建就先创建；vpn 位于大页中时先拆分大页
     ///
     /// # Reture
     /// vpn 对应的 pte
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
-        let idxs = vpn.indexes::<3>();
-        let mut ppn = self.root_ppn;
-        let mut result: Option<&mut PageTableEntry> = None;
-        for (i, idx) in idxs.iter().enumerate() {
-            let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
-            if i == 2 {
-                result = Some(pte);
-                break;
-            }
-            if !pte.is_valid() {
-                let frame = frame_alloc().unwrap();
-                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
-                self.frames.push(frame);
-            }
-            ppn = pte.ppn();
-        }
-        result
+        Some(self.find_entry_create(vpn, LEAF_LEVEL))
     }
 
     /// 查找页表条目
     ///
     /// # Design
-    /// 尝试查找 vpn 对应的 pte， 若 pte 不存在则返回 None
+    /// 尝试查找 vpn 对应的 pte， 若 pte 不存在则返回 None；vpn 位于大页中时返回大页的叶子项
     ///
     /// # Return
     /// vpn 对应的 pte 或 None
     fn find_pte(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
-        let idxs = vpn.indexes::<3>();
-        let mut ppn = self.root_ppn;
-        let mut result: Option<&mut PageTableEntry> = None;
-        for (i, idx) in idxs.iter().enumerate() {
-            let pte = &mut ppn.get_pte_array::<PageTableEntry>()[*idx];
-            if i == 2 {
-                result = Some(pte);
-                break;
-            }
-            if !pte.is_valid() {
-                return None;
-            }
-            ppn = pte.ppn();
-        }
-        result
+        self.find_leaf(vpn).map(|(pte, _)| pte)
     }
 
-    /// 映射虚拟页到物理页
-    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission) {
-        let pte = self.find_pte_create(vpn).unwrap();
+    /// 映射虚拟页到物理页，`size` 为大页时 vpn 与 ppn 必须按 2MB 对齐
+    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission, size: PageSize) {
+        let pte = match size {
+            PageSize::Small => self.find_entry_create(vpn, LEAF_LEVEL),
+            PageSize::Huge => {
+                assert!(
+                    vpn.0 % HUGE_PAGE_PAGES == 0 && ppn.0 % HUGE_PAGE_PAGES == 0,
+                    "huge page {:?} is not aligned",
+                    vpn
+                );
+                let pte = self.find_entry_create(vpn, HUGE_LEVEL);
+                // 之前的映射全部解除后留下的空页表可以直接被大页取代
+                if pte.is_valid()
+                    && !pte.is_leaf()
+                    && pte
+                        .ppn()
+                        .get_pte_array::<PageTableEntry>()
+                        .iter()
+                        .all(|entry| entry.bits == 0)
+                {
+                    *pte = PageTableEntry::empty();
+                }
+                pte
+            }
+        };
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         *pte = PageTableEntry::new(
             ppn,
             PTEFlags::from_bits(flags.bits()).unwrap() | PTEFlags::V,
         );
+        self.flush_tlb(vpn);
     }
 
     /// 解除虚拟页映射
     fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte(vpn).unwrap();
+        let pte = self.find_entry_create(vpn, LEAF_LEVEL);
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
+        self.flush_tlb(vpn);
+    }
+
+    /// 整体解除 vpn 所在大页的映射
+    fn unmap_huge(&mut self, vpn: VirtPageNum) -> bool {
+        match self.find_leaf(vpn) {
+            Some((pte, HUGE_LEVEL)) => {
+                *pte = PageTableEntry::empty();
+                self.flush_tlb(vpn);
+                true
+            }
+            _ => false,
+        }
     }
 
-    /// 虚拟页号到页表条目转换
+    /// 虚拟页号到页表条目转换，大页中的 vpn 得到该 4KB 页对应的页表项
     fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        self.find_pte(vpn).map(|pte| *pte)
+        self.find_leaf(vpn).map(|(pte, level)| {
+            if level == HUGE_LEVEL {
+                PageTableEntry::new(
+                    PhysPageNum(pte.ppn().0 + vpn.0 % HUGE_PAGE_PAGES),
+                    pte.flags(),
+                )
+            } else {
+                *pte
+            }
+        })
     }
 
     /// 虚拟地址到物理地址转换
     fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
-        self.find_pte(va.clone().floor()).map(|pte| {
+        self.translate(va.clone().floor()).map(|pte| {
             let aligned_pa: PhysAddr = pte.ppn().into();
             let offset = va.page_offset();
             let aligned_pa_usize: usize = aligned_pa.into();
@@ -262,17 +396,63 @@ impl PageTable for SV39PageTable {
         })
     }
 
+    /// 使 vpn 的 TLB 项失效：有 ASID 时只针对该 ASID，否则针对所有 ASID
+    fn flush_tlb(&self, vpn: VirtPageNum) {
+        // SV39 要求虚拟地址的高 25 位与第 38 位一致
+        let va = ((VirtAddr::from(vpn).0 << 25) as isize >> 25) as usize;
+        let asid = self.asid.as_ref().map(Asid::id);
+        local_flush_tlb(asid, va);
+        // 页表可能在其他 hart 上活跃
+        crate::smp::tlb_shootdown(asid, va);
+    }
+
     /// 激活当前页表
+    ///
+    /// 写 satp 之前的屏障保证之前对页表的写入先于新地址空间中的访存，
+    /// 之后的 `sfence.vma` 丢弃旧地址空间的 TLB 项
     fn activate(&self) {
         let satp = self.token();
         unsafe {
+            asm!("fence rw, rw");
             satp::write(satp);
             asm!("sfence.vma");
         }
     }
 
-    /// 获取页表 token
+    /// 获取页表 token，即 SATP 的值：Sv39 模式、ASID 与根页表物理页号
     fn token(&self) -> usize {
-        8usize << 60 | self.root_ppn.0
+        8usize << 60 | self.asid.as_ref().map_or(0, Asid::id) << ASID_SHIFT | self.root_ppn.0
+    }
+}
+
+/// 在本 hart 上使 `va` 的 TLB 项失效，`asid` 为 `None` 时针对所有 ASID
+///
+/// 也是跨核 TLB shootdown 的接收方在对方 hart 上执行的操作
+pub fn local_flush_tlb(asid: Option<usize>, va: usize) {
+    unsafe {
+        match asid {
+            Some(asid) => asm!("sfence.vma {}, {}", in(reg) va, in(reg) asid),
+            None => asm!("sfence.vma {}, zero", in(reg) va),
+        }
+    }
+}
+
+/// SATP 中 ASID 字段的位置与宽度
+const ASID_SHIFT: usize = 44;
+const ASID_MASK: usize = 0xffff;
+
+/// 探测 SATP 中可用的 ASID 位数：写入全 1 的 ASID 后读回，硬件不支持的位读回为 0
+///
+/// 必须在启用分页后调用，Bare 模式下 SATP 的其余字段必须为 0
+pub fn asid_bits() -> usize {
+    let old: usize;
+    let probed: usize;
+    unsafe {
+        asm!("csrr {}, satp", out(reg) old);
+        asm!("csrw satp, {}", in(reg) old | ASID_MASK << ASID_SHIFT);
+        asm!("csrr {}, satp", out(reg) probed);
+        asm!("csrw satp, {}", in(reg) old);
+        asm!("sfence.vma");
     }
+    (probed >> ASID_SHIFT & ASID_MASK).count_ones() as usize
 }
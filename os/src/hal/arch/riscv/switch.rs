@@ -7,6 +7,7 @@
 //! - 汇编文件 `switch.S` 提供底层实现，保存当前任务上下文到内存并加载下一个任务上下文。
 //! - Rust 通过 `extern "C"` 声明函数接口，使汇编函数可在 Rust 代码中调用。
 //! - 上下文切换保存的内容包括寄存器、栈指针、返回地址等，封装在 `TaskContext` 中。
+//! - 保存完当前上下文后执行 `fence rw, rw`，使被切出任务的全部写入先于下一任务的访存可见。
 //!
 //! # Assumptions
 //! - `TaskContext` 已正确初始化，包含完整的 CPU 寄存器状态。
@@ -8,6 +8,7 @@
 //! - `sie_before_masking` 记录第一次屏蔽前的 SIE（Supervisor Interrupt Enable）状态。
 //! - 屏蔽中断通过清除 `sstatus.sie` 实现，恢复中断在嵌套退出最外层时按原状态恢复。
 //! - 全局静态实例 `INTR_MASKING_INFO` 通过 `UPSafeCellRaw` 提供单核独占访问。
+//! - `memory_barrier` 以 `fence rw, rw` 提供全屏障。
 //!
 //! # Assumptions
 //! - 内核运行在单核（UP，Uniprocessor）模式下。
@@ -84,4 +85,17 @@ impl IntrMaskingInfo {
             }
         }
     }
+
+    /// 当前的嵌套屏蔽层数，不为零时有锁被持有，不能切换任务
+    pub fn nested_level(&self) -> usize {
+        self.nested_level
+    }
+}
+
+/// 全屏障：之前的访存对其他 hart 可见后才执行之后的访存
+///
+/// 任务切换、页表切换与 `membarrier` 使用；将来的 IPI 发送方在写入请求后、发送中断前调用
+#[inline(always)]
+pub fn memory_barrier() {
+    unsafe { core::arch::asm!("fence rw, rw") };
 }
@@ -6,11 +6,12 @@
 //! # Design
 //! - 使用 `time::read()` 获取当前时间戳（CPU 时钟 tick）
 //! - 使用 SBI `set_timer` 设置下一次定时器触发时间
-//! - 定时器频率由 `TICKS_PER_SEC` 控制，支持周期性触发
+//! - 周期性 tick 的频率默认为 `TICKS_PER_SEC`，可由启动参数 `hz=` 修改；下一次中断取 tick 与最早的内核定时器中较早的一个
+//!   （见 `crate::timer::program_next_event`），短睡眠不必等到下一个 tick
 //! - 提供获取系统时钟频率接口 `get_clock_freq()`
 //!
 //! # Assumptions
-//! - `CLOCK_FREQ` 为 CPU 时钟频率，单位 Hz
+//! - `CLOCK_FREQ` 为 CPU 时钟频率，单位 Hz；设备树提供 `timebase-frequency` 时由 `set_clock_freq` 覆盖
 //! - SBI `set_timer` 能正确触发定时器中断
 //! - 定时器中断处理函数能够及时响应触发
 //!
@@ -19,15 +20,19 @@
 //! - 时间读写基于 64 位寄存器，调用时需注意溢出
 //!
 //! # Invariants
-//! - `TICKS_PER_SEC` 恒定为 25，定时器周期固定
-//! - `set_next_trigger` 始终设置下一次触发在未来时间
+//! - 启动后 tick 周期固定；调度时间片与 tick 无关（见 `crate::timer::timeslice_us`）
+//! - `set_next_trigger` 始终把下一个 tick 设在未来时间
 //! - `get_time()` 返回单调递增时间戳
 
 use super::sbi::set_timer;
 use crate::hal::CLOCK_FREQ;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use riscv::register::time;
 
-/// 每秒的定时器 tick 数
+/// 定时器频率（Hz），默认为平台常量 `CLOCK_FREQ`
+static TIMEBASE_FREQ: AtomicUsize = AtomicUsize::new(CLOCK_FREQ);
+
+/// 默认的每秒 tick 数
 pub const TICKS_PER_SEC: usize = 25;
 
 /// 获取当前时间（tick 数）
@@ -38,13 +43,18 @@ pub fn get_time() -> usize {
     time::read()
 }
 
-/// 设置下一次定时器触发
+/// 设置下一个周期性 tick
 ///
 /// # Behavior
-/// - 通过 SBI `set_timer` 设置下一次触发时间
-/// - 触发时间 = 当前时间 + CLOCK_FREQ / TICKS_PER_SEC
+/// - tick 时间 = 当前时间 + 一个 tick 周期（`crate::timer::tick_period`）
+/// - 下一次定时器中断取 tick 与最早的内核定时器到期时间中较早的一个
 pub fn set_next_trigger() {
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
+    crate::timer::set_next_tick(get_time() + crate::timer::tick_period());
+}
+
+/// 通过 SBI `set_timer` 把下一次定时器中断设置在时刻 `deadline`（时钟周期数）
+pub fn set_timer_event(deadline: usize) {
+    set_timer(deadline);
 }
 
 /// 获取系统时钟频率
@@ -52,5 +62,10 @@ pub fn set_next_trigger() {
 /// # Returns
 /// - 系统时钟频率（Hz）
 pub fn get_clock_freq() -> usize {
-    CLOCK_FREQ
+    TIMEBASE_FREQ.load(Ordering::Relaxed)
+}
+
+/// 设置启动时从设备树读到的时钟频率
+pub fn set_clock_freq(freq: usize) {
+    TIMEBASE_FREQ.store(freq, Ordering::Relaxed);
 }
@@ -14,7 +14,10 @@
 //! - 通过 `TrapContext` 封装寄存器、程序状态寄存器（`sstatus`）、内核页表信息和内核栈信息。
 //!
 //! # Assumptions
-//! - `TrapContext` 中暂未包含浮点寄存器保存，如需支持需修改汇编保存/恢复逻辑。
+//! - 浮点寄存器惰性管理：用户任务初始时 `sstatus.FS` 为 Off，首次执行浮点指令触发非法指令异常，
+//!   由 `enable_fp` 启用；陷入时仅在 FS 为 Dirty 时由 `trap.S` 保存浮点寄存器，
+//!   返回用户态时 FS 不为 Off 才恢复。
+//! - 内核自身不使用浮点寄存器，否则会破坏尚未保存的用户浮点状态。
 //! - `app_init_context` 假设入口地址合法，用户栈空间已分配。
 //! - `sstatus` 中 SPP 位会被设置为用户态，确保 `sret` 返回用户态。
 //! - 本模块仅保存寄存器和 CPU 状态，不直接管理内存或页表。
@@ -27,6 +30,7 @@
 //! - `TrapContext.kernel_satp`：内核页表基地址，用于切换页表。
 //! - `TrapContext.kernel_sp`：内核栈顶地址，用于 trap 处理。
 //! - `TrapContext.trap_handler`：内核异常/中断处理函数入口地址。
+//! - `TrapContext.float_regs`：保存 f0-f31 与 fcsr，位于结构体末尾，不影响 `trap.S` 中其余字段的偏移。
 
 use riscv::register::sstatus::{read, Sstatus, SPP};
 
@@ -73,14 +77,20 @@ pub struct GeneralRegs {
     pub t6: usize,  // 31
 }
 
-// TODO: 因为实现浮点寄存器需要修改整个汇编代码，所以暂时注释掉
-//
-// #[repr(C)]
-// #[derive(Debug, Default, Clone, Copy)]
-// pub struct FloatRegs {
-//     pub f: [usize; 32],
-//     pub fcsr: usize,
-// }
+/// 浮点寄存器（Float Registers）
+///
+/// 由 `trap.S` 按 `TrapContext` 中的固定偏移保存与恢复。
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FloatRegs {
+    pub f: [usize; 32],
+    pub fcsr: usize,
+}
+
+/// `sstatus.FS` 字段的位置
+const SSTATUS_FS_SHIFT: usize = 13;
+/// `sstatus.FS` 为 Initial：浮点单元已启用，寄存器处于初始状态
+const FS_INITIAL: usize = 1;
 
 /// 异常/中断上下文（TrapContext）
 ///
@@ -97,8 +107,6 @@ pub struct TrapContext {
     /// 通用寄存器状态
     pub general_regs: GeneralRegs,
 
-    // 如果需要保存浮点寄存器，可以启用
-    // pub float_regs: FloatRegs,
     /// sstatus CSR，用于保存中断状态、特权级等
     pub sstatus: Sstatus,
 
@@ -113,14 +121,118 @@ pub struct TrapContext {
 
     /// 内核 trap 处理入口
     pub trap_handler: usize,
+
+    /// 浮点寄存器状态，仅在 `sstatus.FS` 不为 Off 时有效
+    pub float_regs: FloatRegs,
 }
 
+/// `GeneralRegs` 各字段的寄存器名，顺序与结构体布局一致
+const REG_NAMES: [&str; 32] = [
+    "pc", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5",
+    "t6",
+];
+
+/// ELF 文件头中的机器类型（EM_RISCV），用于生成 core 文件
+pub const ELF_MACHINE: u16 = 243;
+/// core 文件 `NT_PRSTATUS` 中通用寄存器组的项数，与 Linux 的 `ELF_NGREG` 相同
+pub const ELF_NGREG: usize = 32;
+
 impl TrapContext {
+    /// 按 Linux `user_regs_struct` 的顺序排列的通用寄存器：pc 之后依次为 x1 ~ x31
+    pub fn elf_gregset(&self) -> [usize; ELF_NGREG] {
+        let mut regs = [0; ELF_NGREG];
+        regs[0] = self.pc();
+        for (n, reg) in regs.iter_mut().enumerate().skip(1) {
+            *reg = self.reg(n);
+        }
+        regs
+    }
+
+    /// 打印全部寄存器，供 panic 时诊断使用
+    pub fn dump(&self) {
+        let regs = unsafe { &*(&self.general_regs as *const GeneralRegs as *const [usize; 32]) };
+        for (i, (name, value)) in REG_NAMES.iter().zip(regs.iter()).enumerate() {
+            print!("{:>4}={:#018x}", name, value);
+            print!("{}", if i % 4 == 3 { "\n" } else { " " });
+        }
+        let spp = match self.sstatus.spp() {
+            SPP::User => "U",
+            SPP::Supervisor => "S",
+        };
+        println!(
+            "sepc={:#018x} spp={} kernel_sp={:#018x}",
+            self.sepc, spp, self.kernel_sp
+        );
+    }
+
     /// 设置用户态栈指针
     pub fn set_sp(&mut self, sp: usize) {
         self.general_regs.sp = sp;
     }
 
+    /// 用户态栈指针
+    pub fn sp(&self) -> usize {
+        self.general_regs.sp
+    }
+
+    /// 设置线程指针（tp）
+    pub fn set_tp(&mut self, tp: usize) {
+        self.general_regs.tp = tp;
+    }
+
+    /// 陷入时的 pc
+    pub fn pc(&self) -> usize {
+        self.sepc
+    }
+
+    /// 帧指针（s0），用户程序保留帧指针时可据此回溯用户栈
+    pub fn fp(&self) -> usize {
+        self.reg(8)
+    }
+
+    /// 第 `n` 号通用寄存器（x0 恒为 0）
+    pub fn reg(&self, n: usize) -> usize {
+        let regs = unsafe { &*(&self.general_regs as *const GeneralRegs as *const [usize; 32]) };
+        if n == 0 {
+            0
+        } else {
+            regs[n]
+        }
+    }
+
+    /// 设置第 `n` 号通用寄存器（写 x0 无效）
+    pub fn set_reg(&mut self, n: usize, value: usize) {
+        let regs =
+            unsafe { &mut *(&mut self.general_regs as *mut GeneralRegs as *mut [usize; 32]) };
+        if n != 0 {
+            regs[n] = value;
+        }
+    }
+
+    /// `sstatus` 中的 FS 字段
+    fn fs(&self) -> usize {
+        (self.sstatus.bits() >> SSTATUS_FS_SHIFT) & 0b11
+    }
+
+    /// 设置 `sstatus` 中的 FS 字段
+    fn set_fs(&mut self, fs: usize) {
+        let bits = unsafe { &mut *(&mut self.sstatus as *mut Sstatus as *mut usize) };
+        *bits = (*bits & !(0b11 << SSTATUS_FS_SHIFT)) | (fs << SSTATUS_FS_SHIFT);
+    }
+
+    /// 处理首次使用浮点导致的非法指令异常：为任务启用浮点单元，浮点寄存器从全零开始
+    ///
+    /// 返回 `false` 表示任务已经启用过浮点单元，这次异常是真正的非法指令
+    pub fn enable_fp(&mut self) -> bool {
+        if self.fs() != 0 {
+            return false;
+        }
+        self.float_regs = FloatRegs::default();
+        self.set_fs(FS_INITIAL);
+        true
+    }
+
     /// 初始化用户任务上下文
     ///
     /// # 参数
@@ -131,7 +243,7 @@ impl TrapContext {
     /// - `trap_handler`：内核 trap 入口
     ///
     /// # 返回
-    /// 一个可用于用户任务的 `TrapContext`，已设置 sstatus 为用户态
+    /// 一个可用于用户任务的 `TrapContext`，已设置 sstatus 为用户态，浮点单元处于关闭状态
     pub fn app_init_context(
         entry: usize,
         sp: usize,
@@ -148,16 +260,17 @@ impl TrapContext {
         // 构造 TrapContext
         let mut cx = Self {
             general_regs: GeneralRegs::default(),
-            // float_regs: FloatRegs::default(),
             sstatus,
             sepc: entry,
             kernel_satp,
             kernel_sp,
             trap_handler,
+            float_regs: FloatRegs::default(),
         };
 
-        // 设置用户栈
+        // 设置用户栈，浮点单元等到首次使用时再启用
         cx.set_sp(sp);
+        cx.set_fs(0);
         cx
     }
 }
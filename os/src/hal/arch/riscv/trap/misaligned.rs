@@ -0,0 +1,204 @@
+//! 非对齐访存模拟（RISC-V）
+//!
+//! # Overview
+//! 与 LoongArch 的 `AddressNotAligned` 处理一致，当硬件（及 SBI）不支持非对齐访存、
+//! 把 load / store 地址不对齐异常交给 S 态时，由本模块在软件中完成这次访存：
+//! - 解码出错的指令，得到访存宽度、是否符号扩展以及源 / 目的寄存器
+//! - 按字节读写内存，再组合成寄存器值
+//! - 把 `sepc` 推进到下一条指令
+//!
+//! # Design
+//! - 访存地址直接取自 `stval`，无需重新计算基址与偏移
+//! - 用户态访存经过当前进程页表翻译，并检查页表项的读写权限，
+//!   越权或未映射时返回 `Fault`，由调用者发送信号
+//! - 内核态访存直接按字节访问虚拟地址
+//!
+//! # Assumptions
+//! - 只模拟整数 load / store（含压缩指令），浮点访存与原子指令返回 `Unsupported`
+//! - 陷阱帧不保存 `tp`，内核陷阱帧也不保存 `sp`；
+//!   涉及不在陷阱帧中的寄存器的访存返回 `Unsupported`
+
+use super::TrapContext;
+use crate::hal::{step, PageTableEntryImpl, PageTableImpl};
+use crate::mm::{try_read_bytes, try_write_bytes, PageTable, VirtAddr};
+
+/// 模拟失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MisalignedError {
+    /// 无法解码或不支持的指令
+    Unsupported,
+    /// 访存地址未映射或权限不足
+    Fault,
+}
+
+/// 一次被模拟的访存
+struct Access {
+    /// 是否为 store
+    store: bool,
+    /// 访存宽度（字节）
+    size: usize,
+    /// load 时是否符号扩展
+    signed: bool,
+    /// load 的目的寄存器或 store 的源寄存器
+    reg: usize,
+}
+
+/// 解码访存指令，`len` 为指令长度
+fn decode(insn: u32, len: usize) -> Option<Access> {
+    let insn = insn as usize;
+    if len == 2 {
+        let (op, funct3) = (insn & 0b11, (insn >> 13) & 0b111);
+        // c.lw / c.ld / c.sw / c.sd 使用 x8 ~ x15
+        let short = 8 + ((insn >> 2) & 0b111);
+        let (store, size, reg) = match (op, funct3) {
+            (0, 2) => (false, 4, short),
+            (0, 3) => (false, 8, short),
+            (0, 6) => (true, 4, short),
+            (0, 7) => (true, 8, short),
+            (2, 2) => (false, 4, (insn >> 7) & 0x1f),
+            (2, 3) => (false, 8, (insn >> 7) & 0x1f),
+            (2, 6) => (true, 4, (insn >> 2) & 0x1f),
+            (2, 7) => (true, 8, (insn >> 2) & 0x1f),
+            _ => return None,
+        };
+        return Some(Access {
+            store,
+            size,
+            signed: true,
+            reg,
+        });
+    }
+    let funct3 = (insn >> 12) & 0b111;
+    match insn & 0x7f {
+        // lb / lh / lw / ld / lbu / lhu / lwu
+        0x03 if funct3 != 7 => Some(Access {
+            store: false,
+            size: 1 << (funct3 & 0b11),
+            signed: funct3 < 4,
+            reg: (insn >> 7) & 0x1f,
+        }),
+        // sb / sh / sw / sd
+        0x23 if funct3 < 4 => Some(Access {
+            store: true,
+            size: 1 << funct3,
+            signed: false,
+            reg: (insn >> 20) & 0x1f,
+        }),
+        _ => None,
+    }
+}
+
+/// 解码 `cx` 处的指令并完成访存，成功后推进 `sepc`
+///
+/// - `unsaved` 为不在陷阱帧中的寄存器
+/// - `fetch(addr)` 读取 `addr` 处的 16 位指令片
+/// - `load` / `store` 从 `addr` 开始按字节读写内存
+fn emulate(
+    cx: &mut TrapContext,
+    addr: usize,
+    unsaved: &[usize],
+    fetch: impl Fn(usize) -> Option<u16>,
+    load: impl Fn(usize, &mut [u8]) -> Option<()>,
+    store: impl Fn(usize, &[u8]) -> Option<()>,
+) -> Result<(), MisalignedError> {
+    let pc = cx.sepc;
+    let low = fetch(pc).ok_or(MisalignedError::Fault)?;
+    let len = step::insn_len(low);
+    let insn = if len == 4 {
+        let high = fetch(pc + 2).ok_or(MisalignedError::Fault)?;
+        ((high as u32) << 16) | low as u32
+    } else {
+        low as u32
+    };
+    let access = decode(insn, len)
+        .filter(|access| !unsaved.contains(&access.reg))
+        .ok_or(MisalignedError::Unsupported)?;
+    if access.store {
+        let bytes = cx.reg(access.reg).to_le_bytes();
+        store(addr, &bytes[..access.size]).ok_or(MisalignedError::Fault)?;
+    } else {
+        let mut bytes = [0u8; 8];
+        load(addr, &mut bytes[..access.size]).ok_or(MisalignedError::Fault)?;
+        let shift = (8 - access.size) * 8;
+        let value = usize::from_le_bytes(bytes) << shift;
+        let value = if access.signed {
+            ((value as isize) >> shift) as usize
+        } else {
+            value >> shift
+        };
+        cx.set_reg(access.reg, value);
+    }
+    cx.sepc += len;
+    Ok(())
+}
+
+/// `[addr, addr + len)` 所在的用户页是否都已映射，并满足 `allowed` 要求的权限
+fn user_accessible(
+    page_table: &PageTableImpl,
+    addr: usize,
+    len: usize,
+    allowed: impl Fn(&PageTableEntryImpl) -> bool,
+) -> bool {
+    let start = VirtAddr::from(addr).floor();
+    let end = VirtAddr::from(addr + len).ceil();
+    (start.0..end.0).all(|vpn| {
+        page_table
+            .translate(vpn.into())
+            .is_some_and(|pte| pte.is_valid() && allowed(&pte))
+    })
+}
+
+/// 模拟用户态的非对齐访存，`token` 为当前进程页表
+pub fn emulate_user(
+    cx: &mut TrapContext,
+    token: usize,
+    addr: usize,
+) -> Result<(), MisalignedError> {
+    let page_table = PageTableImpl::from_token(token);
+    emulate(
+        cx,
+        addr,
+        &[4],
+        |pc| {
+            let mut half = [0u8; 2];
+            if !user_accessible(&page_table, pc, 2, |pte| pte.executable()) {
+                return None;
+            }
+            try_read_bytes(token, pc, &mut half).map(|_| u16::from_le_bytes(half))
+        },
+        |addr, buf| {
+            if !user_accessible(&page_table, addr, buf.len(), |pte| pte.readable()) {
+                return None;
+            }
+            try_read_bytes(token, addr, buf)
+        },
+        |addr, data| {
+            if !user_accessible(&page_table, addr, data.len(), |pte| pte.writable()) {
+                return None;
+            }
+            try_write_bytes(token, addr, data)
+        },
+    )
+}
+
+/// 模拟内核态的非对齐访存
+pub fn emulate_kernel(cx: &mut TrapContext, addr: usize) -> Result<(), MisalignedError> {
+    emulate(
+        cx,
+        addr,
+        &[2, 4],
+        |pc| Some(unsafe { (pc as *const u16).read() }),
+        |addr, buf| {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = unsafe { ((addr + i) as *const u8).read_volatile() };
+            }
+            Some(())
+        },
+        |addr, data| {
+            for (i, byte) in data.iter().enumerate() {
+                unsafe { ((addr + i) as *mut u8).write_volatile(*byte) };
+            }
+            Some(())
+        },
+    )
+}
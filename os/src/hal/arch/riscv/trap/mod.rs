@@ -4,7 +4,11 @@
 //! 它是内核与用户态、内核与硬件之间交互的核心通道，主要功能包括：
 //! - 用户态系统调用（Syscall）的分发
 //! - 用户态异常（如缺页、非法指令）的捕捉与处理
-//! - 时钟中断（Timer Interrupt）的调度
+//! - 时钟中断（Timer Interrupt）的调度：内核态的时钟中断只设置 `need_resched`，
+//!   任务在安全点让出处理器（见 `crate::task::cond_resched`）
+//! - 外部中断（External Interrupt）经 PLIC 分发给设备驱动
+//! - 软件中断即核间中断（IPI），交给 `crate::smp` 执行其他 hart 的请求
+//! - 非对齐 load / store 的软件模拟（见 `misaligned`）
 //! - 内核态陷阱（Kernel Trap）的保护性处理
 //!
 //! # Overview
@@ -21,25 +25,43 @@
 //! # Safety
 //! - 涉及大量 `unsafe` 操作，包括 CSR 寄存器读写（`stvec`, `sscratch`, `sstatus`）。
 //! - 依赖 `TRAMPOLINE` 虚拟地址进行代码跳转，必须保证该内存区域在所有页表中正确映射。
+//! - 内核位于高半部分，用户页表中没有它的映射：跳板代码必须先切换到内核页表，
+//!   再以 `TrapContext.trap_handler` / `sscratch` 中保存的绝对地址跳入内核，不能使用 PC 相对跳转。
 //! - 处理 Trap 期间必须严格管理中断嵌套（SIE 位）。
 
 pub mod context;
+mod misaligned;
 
 use crate::hal::TRAMPOLINE;
+use crate::mm::MapPermission;
 use crate::syscall::syscall;
 use crate::task::{
-    check_signals_of_current, current_add_signal, current_process, current_trap_cx,
-    current_trap_cx_user_va, current_user_token, exit_current_and_run_next,
-    suspend_current_and_run_next, SignalFlags,
+    check_group_exit_of_current, check_signals_of_current, cond_resched, current_add_signal,
+    current_handle_page_fault, current_process, current_trap_cx, current_trap_cx_user_va,
+    current_user_token, dump_core, exit_current_and_run_next, exit_group_and_run_next,
+    jobctl_stop_if_needed, preempt_count, ptrace_breakpoint, ptrace_stop_if_needed,
+    set_need_resched, SignalFlags, WaitStatus,
 };
 use core::arch::{asm, global_asm};
 use riscv::register::mtvec::TrapMode;
 use riscv::register::scause::{Exception, Interrupt, Trap};
 use riscv::register::{scause, sepc, sie, sscratch, sstatus, stval, stvec};
 
+use crate::hal::arch::riscv::plic;
 use crate::hal::arch::riscv::timer::set_next_trigger;
-use crate::timer::check_timer;
+use crate::timer::{check_timer, slice_expired, tick_due};
 pub use context::TrapContext;
+use misaligned::{emulate_kernel, emulate_user, MisalignedError};
+
+/// `scause` 中 load 地址不对齐异常的编号
+const LOAD_MISALIGNED: usize = 4;
+/// `scause` 中 store 地址不对齐异常的编号
+const STORE_MISALIGNED: usize = 6;
+
+/// 异常 `scause` 是否为 load / store 地址不对齐
+fn is_misaligned(scause: scause::Scause) -> bool {
+    matches!(scause.bits(), LOAD_MISALIGNED | STORE_MISALIGNED)
+}
 
 // 引入汇编代码，包含寄存器保存与恢复的具体实现。
 global_asm!(include_str!("trap.S"));
@@ -70,23 +92,42 @@ fn set_kernel_trap_entry() {
 
 /// 处理来自内核态的陷阱。
 ///
-/// 目前内核态仅预期处理外部中断和时钟中断。
-/// 如果发生页错误或非法指令，将触发 panic。
+/// 目前内核态仅预期处理外部中断、时钟中断和非对齐访存；启用调试桩时 `ebreak` 交给调试桩处理。
+/// 如果发生页错误、非法指令或无法模拟的非对齐访存，将触发 panic。
 #[no_mangle]
-pub fn trap_from_kernel(_trap_cx: &TrapContext) {
+pub fn trap_from_kernel(trap_cx: &mut TrapContext) {
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause() {
+        Trap::Exception(_) if is_misaligned(scause) => {
+            if let Err(err) = emulate_kernel(trap_cx, stval) {
+                panic!(
+                    "Misaligned access from kernel cannot be emulated: {:?}, sepc = {:#x}, stval = {:#x}!",
+                    err, trap_cx.sepc, stval
+                );
+            }
+        }
         Trap::Interrupt(Interrupt::SupervisorExternal) => {
-            // crate::board::irq_handler();
-            // 外部中断处理逻辑（待实现）
-            todo!()
+            handle_external_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            clear_ipi();
+            crate::smp::handle_ipi();
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
-            // 时钟中断：更新下次触发时间，但不立即触发调度
-            set_next_trigger();
+            // 时钟中断：到达 tick 时更新下一个 tick，但不立即触发调度，
+            // 时间片用完时请求在下一个安全点（`cond_resched` 或返回用户态之前）让出处理器
+            if tick_due() {
+                set_next_trigger();
+            }
             check_timer();
-            // do not schedule now
+            if slice_expired() {
+                set_need_resched();
+            }
+        }
+        #[cfg(feature = "gdbstub")]
+        Trap::Exception(Exception::Breakpoint) => {
+            crate::gdbstub::handle_breakpoint(trap_cx);
         }
         _ => {
             panic!(
@@ -106,6 +147,33 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+/// 开启 S 态外部中断
+pub fn enable_external_interrupt() {
+    unsafe {
+        sie::set_sext();
+    }
+}
+
+/// 开启 S 态软件中断（IPI）
+pub fn enable_ipi_interrupt() {
+    unsafe {
+        sie::set_ssoft();
+    }
+}
+
+/// 清除本 hart 待处理的 IPI（sip.SSIP）
+pub fn clear_ipi() {
+    unsafe { asm!("csrc sip, {}", in(reg) 1usize << 1) };
+}
+
+/// 处理一次外部中断：从 PLIC 领取中断号，交给驱动分发后通知完成
+fn handle_external_interrupt() {
+    if let Some(irq) = plic::claim() {
+        crate::drivers::irq_handler(irq);
+        plic::complete(irq);
+    }
+}
+
 /// 开启 S 态全局中断（设置 sstatus.sie）
 fn enable_supervisor_interrupt() {
     unsafe {
@@ -120,6 +188,22 @@ fn disable_supervisor_interrupt() {
     }
 }
 
+/// 空闲时等待下一个中断
+///
+/// 关中断执行 `wfi`：有中断待处理时即被唤醒，不会错过检查就绪队列之后到达的中断；
+/// 随后短暂开中断，由 `trap_from_kernel` 处理该中断（如唤醒到时的睡眠任务）
+pub fn wait_for_interrupt() {
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+        riscv::asm::wfi();
+    }
+    enable_supervisor_interrupt();
+    if !sie {
+        disable_supervisor_interrupt();
+    }
+}
+
 /// 设置用户态陷阱入口。
 ///
 /// 当 CPU 运行在用户态时，`stvec` 应指向映射在 `TRAMPOLINE` 地址处的汇编入口。
@@ -172,24 +256,59 @@ pub fn trap_handler() -> ! {
             cx = current_trap_cx();
             cx.general_regs.a0 = result as usize;
         }
+        // 缺页：先交给地址空间处理（如重新分配被丢弃的页），非法访问才视为段错误
+        Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault) => {
+            let access = match scause.cause() {
+                Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+                Trap::Exception(Exception::LoadPageFault) => MapPermission::R,
+                _ => MapPermission::X,
+            };
+            if !current_handle_page_fault(current_user_token(), stval, access) {
+                current_add_signal(SignalFlags::SIGSEGV);
+            }
+        }
         // 内存访问违例
         Trap::Exception(Exception::StoreFault)
-        | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::LoadFault)
-        | Trap::Exception(Exception::LoadPageFault)
-        | Trap::Exception(Exception::InstructionFault)
-        | Trap::Exception(Exception::InstructionPageFault) => {
+        | Trap::Exception(Exception::InstructionFault) => {
             current_add_signal(SignalFlags::SIGSEGV);
         }
-        // 非法指令
+        // 非对齐访存：软件模拟，地址无效时按段错误处理，无法模拟时发送 SIGBUS
+        Trap::Exception(_) if is_misaligned(scause) => {
+            let cx = current_trap_cx();
+            match emulate_user(cx, current_user_token(), stval) {
+                Ok(()) => {}
+                Err(MisalignedError::Fault) => current_add_signal(SignalFlags::SIGSEGV),
+                Err(MisalignedError::Unsupported) => current_add_signal(SignalFlags::SIGBUS),
+            }
+        }
+        // 非法指令：浮点单元关闭时可能是首次使用浮点，启用后重新执行该指令
         Trap::Exception(Exception::IllegalInstruction) => {
-            current_add_signal(SignalFlags::SIGILL);
+            if !current_trap_cx().enable_fp() {
+                current_add_signal(SignalFlags::SIGILL);
+            }
         }
-        // 时钟中断
+        // 断点：交给跟踪者，未被跟踪时发送 SIGTRAP
+        Trap::Exception(Exception::Breakpoint) => {
+            ptrace_breakpoint();
+        }
+        // 时钟中断：到达 tick 时开始下一个 tick 周期；是否切换任务在返回用户态之前按时间片决定
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
-            set_next_trigger();
+            if tick_due() {
+                set_next_trigger();
+            }
             check_timer();
-            suspend_current_and_run_next();
+        }
+        // 外部中断（设备）
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            handle_external_interrupt();
+        }
+        // 核间中断
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            clear_ipi();
+            crate::smp::handle_ipi();
         }
         _ => {
             panic!(
@@ -204,10 +323,37 @@ pub fn trap_handler() -> ! {
         let mut inner = current_process.inner_exclusive_access();
         inner.update_process_times_leave_trap();
     }
-    // 检查并处理信号，如进程因异常需要退出
-    if let Some((errno, msg)) = check_signals_of_current() {
+    // 执行中断处理程序推迟的工作，它们可能唤醒任务或产生信号
+    crate::workqueue::run_pending_work();
+    // 时间片已经用完（包括在系统调用中用完的）：让出处理器
+    debug_assert_eq!(preempt_count(), 0);
+    cond_resched();
+    // 被跟踪的进程先把信号交给跟踪者处理
+    ptrace_stop_if_needed();
+    // 停止信号使进程停止，直到收到 SIGCONT
+    jobctl_stop_if_needed();
+    // 线程组正在退出，本线程随之退出
+    if let Some(exit_code) = check_group_exit_of_current() {
+        exit_current_and_run_next(exit_code);
+    }
+    // 检查并处理信号，致命信号结束整个线程组
+    if let Some((signum, msg)) = check_signals_of_current() {
         println!("[kernel] {}", msg);
-        exit_current_and_run_next(errno);
+        #[cfg(feature = "fault_report")]
+        {
+            // 只有本次陷阱本身是异常时 stval 才是该故障的地址
+            let fault_addr = match scause.cause() {
+                Trap::Exception(Exception::UserEnvCall) | Trap::Interrupt(_) => None,
+                Trap::Exception(_) => Some(stval),
+            };
+            crate::task::report_fatal_signal(signum, fault_addr);
+        }
+        let status = if dump_core(signum) {
+            WaitStatus::CoreDumped(signum)
+        } else {
+            WaitStatus::Signaled(signum)
+        };
+        exit_group_and_run_next(status.encode());
     }
     trap_return();
 }
@@ -2,7 +2,7 @@
 //!
 //! # Overview
 //! - **内存布局**：定义了物理内存终点 `MEMORY_END`、页大小 `PAGE_SIZE` 以及内核/用户栈大小。
-//! - **地址空间**：定义了 `TRAMPOLINE`（跳板页）和 `TRAP_CONTEXT_BASE` 等关键虚拟地址。
+//! - **地址空间**：定义了 `TRAMPOLINE`（跳板页）、`TRAP_CONTEXT_BASE` 与内核直接映射区的起点 `KERNEL_OFFSET` 等关键虚拟地址。
 //! - **硬件交互**：导出串口输入输出 (`console`)、时钟管理和关机等原语。
 //! - **进程切换**：导出上下文切换函数 `__switch` 和中断上下文结构 `TrapContext`。
 //! # Design
@@ -20,14 +20,25 @@ pub use arch::__switch; // 核心函数：实现 CPU 寄存器上下文的切换
 pub use arch::kstack_alloc; // 内核栈分配函数
 pub use arch::KernelStack; // 内核栈结构体类型定义
 pub use arch::TrapContext; // 中断上下文结构体（保存通用寄存器等）
+pub use arch::{ELF_MACHINE, ELF_NGREG}; // core 文件的机器类型与通用寄存器组大小
 
 // --- 中断与陷阱处理 ---
 pub use arch::INTR_MASKING_INFO; // 中断屏蔽相关信息（用于处理中断嵌套或优先级）
+pub use arch::memory_barrier; // 全内存屏障（fence / dbar）
+pub use arch::{clear_ipi, send_ipi}; // 核间中断的发送与清除
 pub use arch::{bootstrap_init, machine_init}; // 系统的早期初始化和硬件初始化
+pub use arch::enable_irq; // 使能外部中断源
+pub use arch::wait_for_interrupt; // 空闲时等待下一个中断（wfi / idle）
+pub use arch::frame_pointer; // 读取当前帧指针，用于 panic 时回溯内核栈
+pub use arch::step; // 软件单步：断点指令与后继地址解码
+#[cfg(feature = "gdbstub")]
+pub use arch::gdb; // GDB 调试桩的体系结构相关操作
 pub use arch::{trap_handler, trap_return}; // 中断处理入口函数及返回函数
 
 // --- 内存管理相关 ---
 pub use arch::{PageTableEntryImpl, PageTableImpl}; // 页表项和页表的具体实现
+pub use arch::asid_bits; // 探测硬件支持的 ASID 位数
+pub use arch::local_flush_tlb; // 使本 hart 上某个虚拟地址的 TLB 项失效
 pub use arch::{
     BLOCK_SZ,          // 磁盘块大小
     KERNEL_HEAP_SIZE,  // 内核堆空间大小
@@ -35,10 +46,24 @@ pub use arch::{
     MEMORY_END,        // 物理内存结束地址
     PAGE_SIZE,         // 内存页大小（通常 4KB）
     PAGE_SIZE_BITS,    // 页面大小对应的位数（如 12 位）
+    PTES_PER_TABLE,    // 每个页表页中的页表项数
+    PT_LEVELS,         // 页表级数
+};
+
+// --- 地址宽度 ---
+pub use arch::{
+    PALEN,          // 物理地址位数
+    VA_BITS,        // 虚拟地址的有效位数
+    VA_MASK,        // 虚拟地址掩码
+    VA_SIGN_EXTEND, // 虚拟地址转为 usize 时是否做符号扩展
 };
 
 // --- 地址空间布局常量 ---
 pub use arch::{
+    DIRECT_MAP_SIZE,   // 内核直接映射区覆盖的物理地址范围大小
+    KERNEL_OFFSET,     // 内核直接映射区的起始虚拟地址（物理地址加此偏移即内核虚拟地址）
+    MMAP_BASE,         // 用户 mmap 区域的起始地址
+    MMAP_TOP,          // 用户 mmap 区域的结束地址
     UserStackBase,     // 用户栈基地址
     TRAMPOLINE,        // 跳板页地址（用于用户态/内核态转换代码的映射）
     TRAP_CONTEXT_BASE, // 中断上下文在虚拟地址空间中的基地址
@@ -46,8 +71,10 @@ pub use arch::{
 };
 
 // --- 控制台与系统操作 ---
-pub use arch::{console_flush, console_getchar, console_putchar, shutdown}; // 串口输入输出及关机
+pub use arch::{console_flush, console_getchar, console_putchar, console_write, shutdown}; // 串口输入输出及关机
 pub use arch::{get_clock_freq, get_time}; // 获取时钟频率和当前时间戳
+pub use arch::set_timer_event; // 设置下一次定时器中断的时刻
+pub use arch::TICKS_PER_SEC; // 默认的每秒 tick 数
 
 // --- 进程地址计算助手 ---
 pub use arch::{trap_cx_bottom_from_tid, ustack_bottom_from_tid}; // 根据进程 ID 计算其 Trap 上下文和用户栈的位置
@@ -60,19 +87,17 @@ pub use arch::{
     MEMORY_HIGH_BASE,     // 高位内存基地址
     MEMORY_HIGH_BASE_VPN, // 高位内存基地址对应的虚页号
     MEMORY_SIZE,          // 内存总量
-    PALEN,                // 物理地址长度
-    VA_MASK,              // 虚拟地址掩码
     VPN_SEG_MASK,         // 虚页号分段掩码
 };
 
 // --- 针对特定板卡：LoongArch QEMU ---
 #[cfg(feature = "board_laqemu")]
-pub use platform::{MEM_SIZE, MMIO}; // 内存大小和内存映射 I/O 地址
+pub use platform::{MEM_SIZE, MMIO, RTC_BASE, VIRTIO_MMIO_SLOTS}; // 内存大小、内存映射 I/O 地址、RTC 和 VirtIO 槽位
 
 // --- 针对特定板卡：RISC-V QEMU ---
 #[cfg(feature = "board_rvqemu")]
-pub use platform::{CLOCK_FREQ, MMIO}; // 时钟频率和内存映射 I/O 地址
+pub use platform::{CLOCK_FREQ, MMIO, RTC_BASE, VIRTIO_MMIO_SLOTS}; // 时钟频率、内存映射 I/O 地址、RTC 和 VirtIO 槽位
 
 // --- 针对特定板卡：龙芯 2K1000 开发板 ---
 #[cfg(feature = "board_2k1000")]
-pub use platform::{MEM_SIZE, MMIO};
+pub use platform::{AHCI_BASE, AHCI_IRQ, MEM_SIZE, MMIO, RTC_BASE, VIRTIO_MMIO_SLOTS}; // 另含 AHCI 控制器
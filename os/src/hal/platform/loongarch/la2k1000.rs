@@ -5,6 +5,10 @@ pub const MMIO: &[(usize, usize)] = &[(0x400E_0000, 0x1_0000)];
 pub const BLOCK_SZ: usize = 4096;
 // warning: 不能移除“ + HIGH_BASE_EIGHT”，会导致开发板上地址错误
 pub const UART_BASE: usize = 0x1FE2_0000 + HIGH_BASE_EIGHT;
+/// UART0 在 liointc 上的中断号
+pub const UART_IRQ: usize = 0;
+/// 传统 I/O 中断控制器（liointc）的寄存器基址，中断号 32 ~ 63 的一组紧随其后（`+ 0x40`）
+pub const LIOINTC_BASE: usize = 0x1FE0_1400 + HIGH_BASE_EIGHT;
 pub const ACPI_BASE: usize = 0x1FE2_7000 + HIGH_BASE_EIGHT;
 pub const MEM_START: usize = 0x0000_0000_9000_0000;
 
@@ -13,3 +17,14 @@ pub const MEM_SIZE: usize = 0x4000_0000;
 pub const DISK_IMAGE_BASE: usize = 0x2000_0000 + MEM_START;
 pub const KERNEL_STACK_SIZE: usize = PAGE_SIZE * 0x20;
 pub const KERNEL_HEAP_SIZE: usize = PAGE_SIZE * 0x20000; // 增加到512MB
+
+/// 片上 AHCI SATA 控制器的寄存器基址（ABAR），位于 `MMIO` 映射范围内
+pub const AHCI_BASE: usize = 0x400E_0000 + HIGH_BASE_EIGHT;
+/// AHCI 控制器的外部中断号；外部中断尚未路由，暂以轮询方式访问磁盘
+pub const AHCI_IRQ: Option<usize> = None;
+
+/// 片上 RTC 的寄存器基址，寄存器布局与 LS7A 相同
+pub const RTC_BASE: Option<usize> = Some(0x1FE0_7800 + HIGH_BASE_EIGHT);
+
+// 开发板上没有 VirtIO 设备
+pub const VIRTIO_MMIO_SLOTS: &[(usize, usize)] = &[];
@@ -17,3 +17,10 @@ pub const MEM_SIZE: usize = 0x3000_0000;
 pub const DISK_IMAGE_BASE: usize = 0x1800_0000 + MEM_START;
 pub const KERNEL_STACK_SIZE: usize = PAGE_SIZE * 0x20;
 pub const KERNEL_HEAP_SIZE: usize = PAGE_SIZE * 0x20000; // 增加到512MB
+
+/// LS7A 桥片中 RTC 的寄存器基址
+pub const RTC_BASE: Option<usize> = Some(0x100D_0100 + HIGH_BASE_EIGHT);
+
+// QEMU LoongArch virt 机器没有 virtio-mmio 传输，VirtIO 设备只能挂在 PCI 上；
+// 依赖的 virtio-drivers 版本只实现了 MMIO 传输，因此 board_laqemu 上不支持 virtio-net 等 VirtIO 设备
+pub const VIRTIO_MMIO_SLOTS: &[(usize, usize)] = &[];
@@ -17,7 +17,8 @@
 //! - 这些常量仅定义，不涉及运行时副作用。
 //!
 //! # Invariants
-//! - `CLOCK_FREQ` 恒定不变，用于计算定时器触发。
+//! - `CLOCK_FREQ` 与 `VIRTIO_MMIO_SLOTS` 只在引导程序没有传入设备树时使用，
+//!   否则以设备树中的 `timebase-frequency` 与 VirtIO 节点为准（见 `boot::info`）。
 //! - `MMIO` 列表中的每个 `(地址, 大小)` 不重叠，保证安全映射。
 
 /// CPU 时钟频率
@@ -36,8 +37,33 @@ pub const MMIO: &[(usize, usize)] = &[
     // 前者为地址，后者为大小
     // `UARTO` 串口设备 `mmio` 地址，用于打印日志
     (0x1000_0000, 0x1000),
+    // `goldfish-rtc` 实时时钟 `mmio` 地址，用于读取启动时的日期时间
+    (0x10_1000, 0x1000),
     // `VirtIO` 虚拟磁盘设备 `mmio`地址，用于读写文件
     (0x1000_1000, 0x1000),
+    // 其余 `VirtIO` 设备槽位（网卡等），由驱动在启动时探测
+    (0x1000_2000, 0x7000),
     // `PLIC` 中断控制设备 `mmio`地址，用于处理外部事件
     (0xC00_0000, 0x40_0000),
 ];
+
+/// NS16550A 串口的寄存器（物理）基址
+pub const UART_BASE: usize = 0x1000_0000;
+
+/// `goldfish-rtc` 实时时钟的寄存器（物理）基址，设备树中有 RTC 节点时以设备树为准
+pub const RTC_BASE: Option<usize> = Some(0x10_1000);
+
+/// 可供探测的 `VirtIO` MMIO 槽位
+///
+/// # Overview
+/// - 每个元组 `(base, irq)` 表示一个槽位的寄存器基址及其在 PLIC 上的中断号
+/// - 0 号槽位（`0x1000_1000`）固定留给块设备，不在此列出
+pub const VIRTIO_MMIO_SLOTS: &[(usize, usize)] = &[
+    (0x1000_2000, 2),
+    (0x1000_3000, 3),
+    (0x1000_4000, 4),
+    (0x1000_5000, 5),
+    (0x1000_6000, 6),
+    (0x1000_7000, 7),
+    (0x1000_8000, 8),
+];
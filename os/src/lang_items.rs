@@ -1,10 +1,42 @@
-use crate::hal::shutdown;
-use crate::task::current_kstack_top;
-use core::arch::asm;
+//! # panic 处理
+//!
+//! ## Overview
+//! 内核 panic 时依次输出：
+//! - panic 位置与消息
+//! - 当前任务的 PID、TID 与任务名
+//! - 基于帧指针的内核栈回溯（RISC-V 与 LoongArch 的栈帧布局相同）
+//! - 当前任务保存的 `TrapContext`（最近一次从用户态陷入时的寄存器）
+//!
+//! 随后关机。回溯只给出返回地址，可用
+//! `addr2line -e target/.../os -f -C <addr>...` 在宿主机上符号化。
+//!
+//! ## Assumptions
+//! - 内核以 `-Cforce-frame-pointers=yes` 编译，`fp - 8` 为返回地址，`fp - 16` 为上一帧的帧指针
+//!
+//! ## Safety
+//! - 栈回溯只跟随对齐、单调向栈底增长且位于内核栈范围内的帧指针，遇到异常值立即停止
+//! - panic 处理过程中不使用会因借用冲突而 panic 的接口；再次 panic 时直接关机
+//! - 控制台先进入紧急模式，之后的输出不等待控制台锁
+
+use crate::hal::{frame_pointer, shutdown, KERNEL_STACK_SIZE};
+use crate::task::try_current_task;
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 回溯的最大栈帧数
+const MAX_BACKTRACE_DEPTH: usize = 32;
+
+/// 是否已经处于 panic 处理中
+static PANICKING: AtomicBool = AtomicBool::new(false);
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    if PANICKING.swap(true, Ordering::Relaxed) {
+        println!("[kernel] nested panic, shutting down");
+        shutdown()
+    }
+    // panic 可能发生在持有控制台锁期间，此后的输出绕过缓冲与锁
+    crate::console::enter_emergency();
     println!("\n[kernel] PANIC!");
     if let Some(location) = info.location() {
         println!(
@@ -17,29 +49,80 @@ fn panic(info: &PanicInfo) -> ! {
     if let Some(msg) = info.message() {
         println!("[kernel] Message: {}", msg);
     }
+    dump_current_task();
     backtrace();
+    dump_trap_context();
     shutdown()
 }
 
+fn dump_current_task() {
+    let Some(task) = try_current_task() else {
+        return;
+    };
+    let Some(inner) = task.inner.try_exclusive_access() else {
+        return;
+    };
+    let pid = task.process.upgrade().map(|process| process.getpid());
+    let tid = inner.res.as_ref().map(|res| res.tid);
+    match (pid, tid) {
+        (Some(pid), Some(tid)) => {
+            println!(
+                "[kernel] current task: pid={} tid={} ({})",
+                pid, tid, inner.name
+            )
+        }
+        _ => println!("[kernel] current task: kernel thread ({})", inner.name),
+    }
+}
+
 fn backtrace() {
-    let mut fp: usize;
-    let stop = current_kstack_top();
-    unsafe {
-        asm!("mv {}, s0", out(reg) fp);
+    extern "C" {
+        fn stext();
+        fn etext();
     }
+    let text = stext as usize..etext as usize;
+    // 有当前任务时在其内核栈范围内回溯，否则（启动阶段）只限制回溯深度
+    let (low, high) = match try_current_task() {
+        Some(task) => {
+            let top = task.kstack.get_top();
+            (top - KERNEL_STACK_SIZE, top)
+        }
+        None => (0, usize::MAX),
+    };
+    let mut fp = frame_pointer();
     println!("\n----START BACKTRACE----");
-    for i in 0..10 {
-        if fp == stop {
+    for i in 0..MAX_BACKTRACE_DEPTH {
+        if fp % core::mem::size_of::<usize>() != 0 || fp < low + 16 || fp > high {
             break;
         }
-        unsafe {
-            println!("#{}:ra={:#x}", i, *((fp - 8) as *const usize));
-            fp = *((fp - 16) as *const usize);
+        let (ra, prev_fp) = unsafe { (*((fp - 8) as *const usize), *((fp - 16) as *const usize)) };
+        if !text.contains(&ra) {
+            break;
+        }
+        println!("#{:<2} ra={:#x} fp={:#x}", i, ra, fp);
+        if prev_fp <= fp {
+            break;
         }
+        fp = prev_fp;
     }
     println!("----END OF BACKTRACE----");
 }
 
+fn dump_trap_context() {
+    let task = match try_current_task() {
+        Some(task) => task,
+        None => return,
+    };
+    // 内核线程没有 trap 上下文
+    if task.is_kthread() {
+        return;
+    }
+    if let Some(inner) = task.inner.try_exclusive_access() {
+        println!("\n----TRAP CONTEXT----");
+        inner.get_trap_cx().dump();
+    }
+}
+
 #[macro_export]
 macro_rules! color_text {
     ($text:expr, $color:expr) => {{
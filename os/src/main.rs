@@ -31,25 +31,63 @@ fn clear_bss() {
     }
 }
 
+mod boot;
 mod drivers;
+#[cfg(feature = "fault_inject")]
+mod fault;
 mod fs;
+#[cfg(feature = "gdbstub")]
+mod gdbstub;
 mod mm;
+mod net;
+mod random;
+#[cfg(feature = "selftest")]
+mod selftest;
+mod smp;
+mod stats;
 mod sync;
 mod syscall;
+mod workqueue;
 
+/// 内核入口，`hartid` 与 `dtb` 为引导程序在 `a0`、`a1` 中传入的参数
 #[no_mangle]
-pub fn rust_main() -> ! {
+pub fn rust_main(hartid: usize, dtb: usize) -> ! {
     hal::bootstrap_init();
     clear_bss();
+    smp::init(if cfg!(feature = "riscv") { hartid } else { 0 });
+    // RISC-V 上 SBI 在 a1 中传入设备树的物理地址
+    boot::init(if cfg!(feature = "riscv") { dtb } else { 0 });
     console::init();
     println!("Welcome to RustOS!");
     mm::init();
     println!("Memory management initialized.");
+    println!("Command line: {}", boot::cmdline());
     hal::machine_init();
     println!("machine init completed.");
+    #[cfg(feature = "gdbstub")]
+    if boot::gdbstub_wait() {
+        gdbstub::init();
+    }
+    random::init();
+    drivers::serial::init();
+    drivers::rtc::init();
+    drivers::net::init();
     fs::list_apps();
     println!("File system initialized.");
+    // 页帧不足时先回收干净的文件缓存页，再回收 MADV_FREE 页
+    mm::register_shrinker(fs::shrink_page_caches);
+    mm::register_shrinker(task::shrink_lazy_free_pages);
+    // 仍不足时把匿名页换出到交换区
+    #[cfg(feature = "swap")]
+    {
+        mm::swap::init();
+        mm::register_shrinker(task::shrink_swap_pages);
+    }
+    #[cfg(feature = "selftest")]
+    selftest::run(&boot::selftests());
     task::add_initproc();
+    #[cfg(feature = "fault_inject")]
+    fault::init();
     println!("Initialization complete.");
     task::run_tasks();
     shutdown();
@@ -1,4 +1,4 @@
-//! 虚拟/物理地址与页号模块（SV39）
+//! 虚拟/物理地址与页号模块
 //!
 //! # Overview
 //! 本模块提供对虚拟地址(VA)、物理地址(PA)以及对应页号(VPN/PPN)的封装与操作。
@@ -13,30 +13,51 @@
 //!
 //! # Conversions
 //! - `usize` ↔ {`PhysAddr`, `VirtAddr`, `PhysPageNum`, `VirtPageNum`}
-//! - `VirtAddr` 转 `usize` 自动处理符号扩展。
+//! - 从 `usize` 转换时按各架构的地址位数截断：物理地址取低 `PALEN` 位，虚拟地址取低 `VA_BITS` 位。
+//! - `VirtAddr` 转 `usize` 时，`VA_SIGN_EXTEND` 为真的架构（RISC-V）按第 `VA_BITS - 1` 位做符号扩展，
+//!   其余架构（LoongArch）做零扩展。
 //! - `PhysAddr`/`VirtAddr` ↔ 对应页号需确保地址对齐。
 //!
 //! # Memory Access
-//! - `PhysAddr::get_ref` / `get_mut`：访问物理地址对应的静态引用。
-//! - `PhysPageNum::get_pte_array`：获取页表条目数组引用。
+//! - 内核通过直接映射区访问物理内存：物理地址 `pa` 对应虚拟地址 `pa + KERNEL_OFFSET`，
+//!   `phys_to_virt` / `virt_to_phys` 在两者之间换算。
+//! - `PhysAddr::kernel_alias` / `PhysPageNum::kernel_alias`：物理地址（页）在内核中的别名地址，
+//!   RISC-V 上为直接映射区中的地址，LoongArch 上为 DMW 窗口中的地址。
+//!   用户页帧只能经由别名访问，不能使用用户虚拟地址，因为内核运行时使用的是内核页表。
+//! - `PhysAddr::get_ref` / `get_mut`：经别名地址访问物理地址对应的静态引用。
+//! - `PhysPageNum::get_pte_array`：获取页表条目数组引用，长度为 `PTES_PER_TABLE`。
 //! - `PhysPageNum::get_bytes_array`：获取 4KB 页字节数组引用。
 //!
 //! # Paging
 //! - `VirtAddr::floor` / `ceil`：获取包含或下一页页号。
 //! - `VirtAddr::page_offset` / `aligned`：计算页内偏移与对齐状态。
-//! - `VirtPageNum::indexes`：提取三级页表索引。
+//! - `VirtPageNum::indexes`：提取各级页表索引。
+//! - 页表的几何（每级的索引位数 `DIR_WIDTH`、页表项数 `PTES_PER_TABLE`、级数 `PT_LEVELS`）
+//!   由各架构的 `config` 提供，本模块不假定具体的取值。
 //!
 //! # Range Iteration
 //! - `SimpleRange<T>`：泛型范围类型，支持 `StepByOne` 类型迭代。
 //! - `VPNRange`：`VirtPageNum` 的简单范围类型。
 
-use crate::hal::{PageTableEntryImpl, PAGE_SIZE, PAGE_SIZE_BITS};
+use crate::hal::{
+    PageTableEntryImpl, DIRECT_MAP_SIZE, KERNEL_OFFSET, PAGE_SIZE, PAGE_SIZE_BITS, PALEN,
+    PTES_PER_TABLE, PT_LEVELS, VA_BITS, VA_MASK, VA_SIGN_EXTEND,
+};
 use core::fmt::{self, Debug, Formatter};
 
-const PA_WIDTH_SV39: usize = 56;
-const VA_WIDTH_SV39: usize = 39;
-const PPN_WIDTH_SV39: usize = PA_WIDTH_SV39 - PAGE_SIZE_BITS;
-const VPN_WIDTH_SV39: usize = VA_WIDTH_SV39 - PAGE_SIZE_BITS;
+const PA_MASK: usize = (1 << PALEN) - 1;
+const PPN_MASK: usize = (1 << (PALEN - PAGE_SIZE_BITS)) - 1;
+const VPN_MASK: usize = (1 << (VA_BITS - PAGE_SIZE_BITS)) - 1;
+
+// 一个页表页恰好放下一级的全部页表项
+const _: () = assert!(PTES_PER_TABLE * core::mem::size_of::<PageTableEntryImpl>() == PAGE_SIZE);
+// 掩码的移位不溢出，且虚拟地址比页内偏移宽
+const _: () = assert!(PALEN < usize::BITS as usize && VA_BITS < usize::BITS as usize);
+const _: () = assert!(VA_BITS > PAGE_SIZE_BITS && PALEN > PAGE_SIZE_BITS);
+const _: () = assert!(VA_MASK == (1 << VA_BITS) - 1);
+// 各级页表索引恰好覆盖虚拟页号，`VirtPageNum::indexes` 不丢失高位
+const _: () =
+    assert!(PAGE_SIZE_BITS + PT_LEVELS * PTES_PER_TABLE.trailing_zeros() as usize >= VA_BITS);
 
 /// 物理地址封装
 #[repr(C)]
@@ -86,22 +107,22 @@ impl Debug for PhysPageNum {
 /// usize -> T: usize.into()
 impl From<usize> for PhysAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PA_WIDTH_SV39) - 1))
+        Self(v & PA_MASK)
     }
 }
 impl From<usize> for PhysPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PPN_WIDTH_SV39) - 1))
+        Self(v & PPN_MASK)
     }
 }
 impl From<usize> for VirtAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VA_WIDTH_SV39) - 1))
+        Self(v & VA_MASK)
     }
 }
 impl From<usize> for VirtPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VPN_WIDTH_SV39) - 1))
+        Self(v & VPN_MASK)
     }
 }
 impl From<PhysAddr> for usize {
@@ -116,8 +137,8 @@ impl From<PhysPageNum> for usize {
 }
 impl From<VirtAddr> for usize {
     fn from(v: VirtAddr) -> Self {
-        if v.0 >= (1 << (VA_WIDTH_SV39 - 1)) {
-            v.0 | (!((1 << VA_WIDTH_SV39) - 1))
+        if VA_SIGN_EXTEND && v.0 >= (1 << (VA_BITS - 1)) {
+            v.0 | !VA_MASK
         } else {
             v.0
         }
@@ -200,37 +221,57 @@ impl From<PhysPageNum> for PhysAddr {
 
 /// VirtPageNum 方法
 impl VirtPageNum {
-    /// 获取三级页表索引
-    pub fn indexes<const T: usize>(&self) -> [usize; 3] {
+    /// 获取各级页表索引，第 0 项为根页表中的索引
+    pub fn indexes(&self) -> [usize; PT_LEVELS] {
         let mut vpn = self.0;
-        let mut idx: [usize; 3] = [0usize; 3];
-        for i in (0..3).rev() {
-            idx[i] = vpn & 511;
-            vpn >>= 9;
+        let mut idx = [0usize; PT_LEVELS];
+        for i in (0..PT_LEVELS).rev() {
+            idx[i] = vpn & (PTES_PER_TABLE - 1);
+            vpn >>= PTES_PER_TABLE.trailing_zeros();
         }
         idx
     }
 }
 
+/// 物理地址在内核直接映射区中对应的虚拟地址
+pub fn phys_to_virt(pa: usize) -> usize {
+    pa + KERNEL_OFFSET
+}
+
+/// 内核直接映射区中的虚拟地址对应的物理地址
+///
+/// 不在直接映射区中的地址（如内核栈、跳板页）返回 `None`，需要查页表换算
+pub fn virt_to_phys(va: usize) -> Option<usize> {
+    va.checked_sub(KERNEL_OFFSET)
+        .filter(|&pa| pa < DIRECT_MAP_SIZE)
+}
+
 /// PhysAddr/PhysPageNum 内存访问方法
 impl PhysAddr {
+    /// 内核访问该物理地址所用的别名虚拟地址
+    pub fn kernel_alias(&self) -> usize {
+        phys_to_virt(self.0)
+    }
     /// 获取物理地址的不可变引用
     pub fn get_ref<T>(&self) -> &'static T {
-        unsafe { (self.0 as *const T).as_ref().unwrap() }
+        unsafe { (self.kernel_alias() as *const T).as_ref().unwrap() }
     }
     /// 获取物理地址的可变引用
     pub fn get_mut<T>(&self) -> &'static mut T {
-        unsafe { (self.0 as *mut T).as_mut().unwrap() }
+        unsafe { (self.kernel_alias() as *mut T).as_mut().unwrap() }
     }
 }
 impl PhysPageNum {
+    /// 内核访问该物理页所用的别名虚拟地址（页的起始地址）
+    pub fn kernel_alias(&self) -> usize {
+        PhysAddr::from(*self).kernel_alias()
+    }
     pub fn get_pte_array<T>(&self) -> &'static mut [PageTableEntryImpl] {
-        let pa: PhysAddr = (*self).into();
-        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut PageTableEntryImpl, 512) }
+        let va = self.kernel_alias();
+        unsafe { core::slice::from_raw_parts_mut(va as *mut PageTableEntryImpl, PTES_PER_TABLE) }
     }
     pub fn get_bytes_array(&self) -> &'static mut [u8] {
-        let pa: PhysAddr = (*self).into();
-        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, 4096) }
+        unsafe { core::slice::from_raw_parts_mut(self.kernel_alias() as *mut u8, PAGE_SIZE) }
     }
     pub fn get_mut<T>(&self) -> &'static mut T {
         let pa: PhysAddr = (*self).into();
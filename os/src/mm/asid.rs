@@ -0,0 +1,91 @@
+//! # 地址空间标识符（ASID）
+//!
+//! ## Overview
+//! 为每个用户页表分配一个 ASID，TLB 项按 ASID 区分所属的地址空间，
+//! 切换地址空间时不必清空整个 TLB：
+//! - `Asid` 以 RAII 方式持有一个 ASID，随页表一起释放
+//! - 硬件支持的 ASID 位数由 `hal::asid_bits` 在启用分页后探测
+//!
+//! ## Design
+//! - ASID 0 保留给内核页表，也用作“未标记”：ASID 耗尽或硬件不支持时，
+//!   页表不持有 `Asid`，切换到这样的地址空间时仍需清空 TLB
+//! - 分配方式与 `StackFrameAllocator` 相同：顺序分配，回收的 ASID 优先复用
+//!
+//! ## Invariants
+//! - 同一时刻每个非 0 的 ASID 至多属于一个页表
+//! - ASID 重新分配给新页表前，由页表实现清除该 ASID 的全部 TLB 项
+
+use crate::sync::UPIntrFreeCell;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 使用的 ASID 位数上限，与 SV39 的 ASID 字段宽度一致
+const MAX_ASID_BITS: usize = 16;
+
+/// 一个已分配的 ASID，drop 时归还
+pub struct Asid(usize);
+
+impl Asid {
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl Drop for Asid {
+    fn drop(&mut self) {
+        ASID_ALLOCATOR.exclusive_access().dealloc(self.0);
+    }
+}
+
+struct AsidAllocator {
+    /// 下一个从未分配过的 ASID
+    current: usize,
+    /// 可用 ASID 的上界（不包含），初始化前为 1，即不分配
+    end: usize,
+    /// 已回收、可再次分配的 ASID
+    recycled: Vec<usize>,
+}
+
+impl AsidAllocator {
+    fn alloc(&mut self) -> Option<usize> {
+        if let Some(asid) = self.recycled.pop() {
+            Some(asid)
+        } else if self.current < self.end {
+            self.current += 1;
+            Some(self.current - 1)
+        } else {
+            None
+        }
+    }
+
+    fn dealloc(&mut self, asid: usize) {
+        assert!(
+            asid < self.current && !self.recycled.contains(&asid),
+            "asid {} has not been allocated",
+            asid
+        );
+        self.recycled.push(asid);
+    }
+}
+
+lazy_static! {
+    static ref ASID_ALLOCATOR: UPIntrFreeCell<AsidAllocator> = unsafe {
+        UPIntrFreeCell::new(AsidAllocator {
+            current: 1,
+            end: 1,
+            recycled: Vec::new(),
+        })
+    };
+}
+
+/// 按硬件支持的 ASID 位数启用分配
+pub fn init(bits: usize) {
+    let bits = bits.min(MAX_ASID_BITS);
+    ASID_ALLOCATOR.exclusive_access().end = 1 << bits;
+    println!("[kernel] asid: {} bits", bits);
+}
+
+/// 分配一个 ASID，耗尽或硬件不支持时返回 `None`
+pub fn asid_alloc() -> Option<Asid> {
+    ASID_ALLOCATOR.exclusive_access().alloc().map(Asid)
+}
@@ -10,12 +10,18 @@
 //!
 //! # Allocation Strategy
 //! - 当前实现为基于栈（Stack）的页帧分配器
-//! - 支持顺序分配与回收页帧
+//! - 管理启动时给出的多段可用物理内存，支持顺序分配与回收页帧
 //! - 使用 recycled 列表复用已释放页帧
 //!
+//! # Reclaim
+//! - 其他子系统通过 `register_shrinker` 注册回收函数（如页缓存中的干净页、`MADV_FREE` 页）
+//! - 单页分配失败时按注册顺序调用回收函数，有页帧被释放后重试一次，仍失败才返回 `None`
+//! - 回收函数在分配器未被锁定时调用，它们释放页帧时可以正常回到分配器；
+//!   回收函数自身只能用 `try_exclusive_access` 访问其他全局状态，跳过正被借用的部分
+//!
 //! # Safety
 //! - 本模块包含全局可变状态
-//! - 所有访问必须通过 `UPIntrFreeCell` 串行化
+//! - 页帧分配器的所有访问必须通过 `SpinMutex` 串行化
 //! - 调用方必须保证在正确的初始化顺序下使用
 //!
 //! # Invariants
@@ -23,9 +29,8 @@
 //! - 被回收的页帧只能回收一次
 //! - `FrameTracker` 生命周期与页帧占用严格绑定
 
-use super::{PhysAddr, PhysPageNum};
-use crate::hal::MEMORY_END;
-use crate::sync::UPIntrFreeCell;
+use super::{PhysAddr, PhysPageNum, HUGE_PAGE_PAGES};
+use crate::sync::{SpinMutex, UPIntrFreeCell};
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
 use lazy_static::*;
@@ -33,63 +38,107 @@ use lazy_static::*;
 lazy_static! {
     /// 全局物理页帧分配器。
     ///
-    /// 使用 `UPIntrFreeCell` 包裹以保证在单核 / 关中断环境下的
-    /// 独占访问。
+    /// 使用 `SpinMutex` 包裹以保证独占访问，持锁期间屏蔽中断。
     ///
     /// INVARIANT:
     /// - 所有页帧分配与回收必须通过该分配器完成
     /// - 在任意时刻，分配器内部状态是自洽的
-    pub static ref FRAME_ALLOCATOR: UPIntrFreeCell<FrameAllocatorImpl> =
-        unsafe { UPIntrFreeCell::new(FrameAllocatorImpl::new()) };
+    pub static ref FRAME_ALLOCATOR: SpinMutex<FrameAllocatorImpl> =
+        SpinMutex::new(FrameAllocatorImpl::new());
+
+    /// 已注册的内存回收函数
+    static ref SHRINKERS: UPIntrFreeCell<Vec<Shrinker>> =
+        unsafe { UPIntrFreeCell::new(Vec::new()) };
+}
+
+/// 内存回收函数：释放一部分可回收的页帧，返回释放的页数
+pub type Shrinker = fn() -> usize;
+
+/// 注册内存回收函数，页帧分配失败时按注册顺序调用
+pub fn register_shrinker(shrinker: Shrinker) {
+    SHRINKERS.exclusive_access().push(shrinker);
+}
+
+/// 依次调用回收函数，直到有页帧被释放
+fn reclaim() -> bool {
+    let shrinkers = SHRINKERS.exclusive_access().clone();
+    shrinkers.iter().any(|shrinker| shrinker() > 0)
 }
 
 /// 初始化物理页帧分配器。
 ///
-/// 页帧管理范围：
-/// - 起始地址：内核镜像结束地址（`ekernel`）
-/// - 结束地址：系统物理内存上限（`MEMORY_END`）
+/// 页帧管理范围为 `boot::usable_memory` 给出的各个区域：
+/// 各物理内存区域扣除内核镜像、设备树、固件与磁盘镜像等保留区域后剩余的部分
 ///
 /// SAFETY:
-/// - `ekernel` 由链接脚本提供，地址有效
 /// - 初始化函数只会在系统启动阶段调用一次
 /// - 初始化期间不会发生并发页帧访问
 pub fn init_frame_allocator() {
-    extern "C" {
-        fn ekernel();
+    let mut allocator = FRAME_ALLOCATOR.lock();
+    for (start, end) in crate::boot::usable_memory() {
+        println!("[kernel] usable memory: [{:#x}, {:#x})", start, end);
+        allocator.add_range(PhysAddr::from(start).ceil(), PhysAddr::from(end).floor());
     }
-    FRAME_ALLOCATOR.exclusive_access().init(
-        PhysAddr::from(ekernel as *const () as usize).ceil(),
-        PhysAddr::from(MEMORY_END).floor(),
-    );
 }
 
 /// 分配一个物理页帧。
 ///
 /// 成功时返回一个 `FrameTracker`，
-/// 其生命周期与页帧占用绑定。
+/// 其生命周期与页帧占用绑定。没有空闲页帧时先尝试回收内存。
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+    #[cfg(feature = "fault_inject")]
+    if crate::fault::FAIL_PAGE_ALLOC.should_fail(crate::hal::PAGE_SIZE) {
+        return None;
+    }
+    let ppn = FRAME_ALLOCATOR.lock().alloc();
+    let ppn = match ppn {
+        Some(ppn) => ppn,
+        None if reclaim() => FRAME_ALLOCATOR.lock().alloc()?,
+        None => return None,
+    };
+    Some(FrameTracker::new(ppn))
 }
 
 /// 一次性分配多个连续页帧。
 ///
 /// 返回的每个页帧都由对应的 `FrameTracker` 管理。
 pub fn frame_alloc_more(num: usize) -> Option<Vec<FrameTracker>> {
+    #[cfg(feature = "fault_inject")]
+    if crate::fault::FAIL_PAGE_ALLOC.should_fail(num * crate::hal::PAGE_SIZE) {
+        return None;
+    }
     FRAME_ALLOCATOR
-        .exclusive_access()
+        .lock()
         .alloc_more(num)
         .map(|x| x.iter().map(|&t| FrameTracker::new(t)).collect())
 }
 
+/// 分配按 2MB 对齐的连续 `HUGE_PAGE_PAGES` 个页帧，用于大页映射
+///
+/// 只从尚未分配过的区域中取，找不到对齐的连续区域时返回 `None`，由调用者退回普通页
+pub fn frame_alloc_huge() -> Option<Vec<FrameTracker>> {
+    FRAME_ALLOCATOR
+        .lock()
+        .alloc_aligned(HUGE_PAGE_PAGES)
+        .map(|x| x.into_iter().map(FrameTracker::new).collect())
+}
+
+/// 当前空闲的页帧数
+pub fn frame_free_count() -> usize {
+    FRAME_ALLOCATOR.lock().free_count()
+}
+
+/// 页帧分配器管理的页帧总数
+pub fn frame_total_count() -> usize {
+    FRAME_ALLOCATOR.lock().total_count()
+}
+
 /// 回收一个物理页帧。
 ///
 /// 通常由 `FrameTracker::drop` 自动调用，
 /// 不建议手动使用。
 pub fn frame_dealloc(ppn: PhysPageNum) {
-    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+    FRAME_ALLOCATOR.lock().dealloc(ppn);
 }
 
 /// 页帧跟踪器（RAII 封装）。
@@ -141,38 +190,81 @@ trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
     fn alloc_more(&mut self, pages: usize) -> Option<Vec<PhysPageNum>>;
+    fn alloc_aligned(&mut self, pages: usize) -> Option<Vec<PhysPageNum>>;
     fn dealloc(&mut self, ppn: PhysPageNum);
 }
 
+/// 页帧分配器管理的一段连续页帧
+struct FrameRange {
+    /// 管理区间的起始页帧号
+    start: usize,
+    /// 当前尚未分配的起始页帧号
+    current: usize,
+    /// 可分配页帧的上界（不包含）
+    end: usize,
+}
+
 /// 基于栈的页帧分配器实现。
 ///
 /// 分配策略：
-/// - 顺序分配未使用页帧
+/// - 管理若干段互不重叠的页帧区间，按加入的顺序依次顺序分配未使用页帧
+/// - 连续多页的分配只在同一区间内进行
 /// - 回收的页帧放入 recycled 栈中复用
 pub struct StackFrameAllocator {
-    /// 当前尚未分配的起始页帧号
-    current: usize,
-    /// 可分配页帧的上界（不包含）
-    end: usize,
+    /// 按加入顺序排列的管理区间
+    ranges: Vec<FrameRange>,
     /// 已回收、可再次分配的页帧号
     recycled: Vec<usize>,
 }
 
 impl StackFrameAllocator {
-    /// 初始化页帧分配区间。
+    /// 加入一段页帧分配区间。
     ///
-    /// `[l, r)` 区间内的页帧将被纳入管理。
-    pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
-        self.current = l.0;
-        self.end = r.0;
+    /// `[l, r)` 区间内的页帧将被纳入管理，与已有区间重叠时 panic。
+    pub fn add_range(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        if l.0 >= r.0 {
+            return;
+        }
+        if let Some(range) = self
+            .ranges
+            .iter()
+            .find(|range| l.0 < range.end && range.start < r.0)
+        {
+            panic!(
+                "Frame range [{:#x}, {:#x}) overlaps [{:#x}, {:#x})",
+                l.0, r.0, range.start, range.end
+            );
+        }
+        self.ranges.push(FrameRange {
+            start: l.0,
+            current: l.0,
+            end: r.0,
+        });
+    }
+
+    /// 尚未分配与已回收的页帧总数
+    pub fn free_count(&self) -> usize {
+        let untouched: usize = self
+            .ranges
+            .iter()
+            .map(|range| range.end - range.current)
+            .sum();
+        untouched + self.recycled.len()
+    }
+
+    /// 管理区间内的页帧总数
+    pub fn total_count(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
     }
 }
 impl FrameAllocator for StackFrameAllocator {
     /// 创建一个新的栈式页帧分配器。
     fn new() -> Self {
         Self {
-            current: 0,
-            end: 0,
+            ranges: Vec::new(),
             recycled: Vec::new(),
         }
     }
@@ -180,25 +272,39 @@ impl FrameAllocator for StackFrameAllocator {
     /// 分配一个页帧。
     fn alloc(&mut self) -> Option<PhysPageNum> {
         if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else if self.current == self.end {
-            None
-        } else {
-            self.current += 1;
-            Some((self.current - 1).into())
+            return Some(ppn.into());
         }
+        let range = self
+            .ranges
+            .iter_mut()
+            .find(|range| range.current < range.end)?;
+        range.current += 1;
+        Some((range.current - 1).into())
     }
 
     /// 分配多个连续页帧。
     fn alloc_more(&mut self, pages: usize) -> Option<Vec<PhysPageNum>> {
-        if self.current + pages >= self.end {
-            None
-        } else {
-            self.current += pages;
-            let arr: Vec<usize> = (1..pages + 1).collect();
-            let v = arr.iter().map(|x| (self.current - x).into()).collect();
-            Some(v)
-        }
+        let range = self
+            .ranges
+            .iter_mut()
+            .find(|range| range.current + pages < range.end)?;
+        range.current += pages;
+        let current = range.current;
+        Some((1..pages + 1).map(|x| (current - x).into()).collect())
+    }
+
+    /// 分配按 `pages` 对齐的连续 `pages` 个页帧。
+    ///
+    /// 为对齐而跳过的页帧放入 recycled 栈，仍可被单页分配使用。
+    fn alloc_aligned(&mut self, pages: usize) -> Option<Vec<PhysPageNum>> {
+        let range = self
+            .ranges
+            .iter_mut()
+            .find(|range| range.current.div_ceil(pages) * pages + pages <= range.end)?;
+        let start = range.current.div_ceil(pages) * pages;
+        self.recycled.extend(range.current..start);
+        range.current = start + pages;
+        Some((start..start + pages).map(PhysPageNum).collect())
     }
 
     /// 回收一个页帧。
@@ -207,7 +313,11 @@ impl FrameAllocator for StackFrameAllocator {
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
         // 合法性检查
-        if ppn >= self.current || self.recycled.iter().any(|&v| v == ppn) {
+        let allocated = self
+            .ranges
+            .iter()
+            .any(|range| range.start <= ppn && ppn < range.current);
+        if !allocated || self.recycled.iter().any(|&v| v == ppn) {
             panic!("Frame ppn={:#x} has not been allocated!", ppn);
         }
         // 回收页帧
@@ -35,9 +35,31 @@ use core::ptr::addr_of_mut;
 /// INVARIANT:
 /// - 在系统生命周期内只会被初始化一次
 /// - 所有堆分配操作必须通过该分配器完成
-#[global_allocator]
+#[cfg_attr(not(feature = "fault_inject"), global_allocator)]
 static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
 
+/// 启用故障注入时的全局分配器：按 `failslab` 的设置让分配失败，其余交给 `HEAP_ALLOCATOR`
+#[cfg(feature = "fault_inject")]
+struct FaultyHeap;
+
+#[cfg(feature = "fault_inject")]
+unsafe impl core::alloc::GlobalAlloc for FaultyHeap {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        if crate::fault::FAILSLAB.should_fail(layout.size()) {
+            return core::ptr::null_mut();
+        }
+        core::alloc::GlobalAlloc::alloc(&HEAP_ALLOCATOR, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        core::alloc::GlobalAlloc::dealloc(&HEAP_ALLOCATOR, ptr, layout)
+    }
+}
+
+#[cfg(feature = "fault_inject")]
+#[global_allocator]
+static FAULTY_HEAP: FaultyHeap = FaultyHeap;
+
 /// 堆内存分配失败处理函数。
 ///
 /// 当内核发生堆分配失败（如内存耗尽或对齐要求无法满足）时，
@@ -88,3 +110,9 @@ pub fn init_heap() {
             .init(addr_of_mut!(HEAP_SPACE) as usize, KERNEL_HEAP_SIZE);
     }
 }
+
+/// 内核堆的总字节数与已分配的字节数（含对齐到 2 的幂造成的浪费）
+pub fn heap_usage() -> (usize, usize) {
+    let heap = HEAP_ALLOCATOR.lock();
+    (heap.stats_total_bytes(), heap.stats_alloc_actual())
+}
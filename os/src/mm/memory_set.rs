@@ -11,7 +11,7 @@
 //! - `MemorySet`：表示一组连续的虚拟地址区域及对应映射
 //! - `MapArea`：表示一段连续虚拟页范围和映射类型
 //! - `PageTable`：页表抽象，实际实现由 `PageTableImpl` 提供
-//! - `MapType`：映射类型（Identical / Framed / Linear）
+//! - `MapType`：映射类型（Direct / Framed / Linear / Shared）
 //! - `MapPermission`：映射权限（R/W/X/U）
 //!
 //! # Safety / Invariants
@@ -19,17 +19,68 @@
 //! - 所有映射、解除映射操作需保证单核独占访问（使用 UPIntrFreeCell）
 //! - ELF 加载区域假设合法且与用户栈、trap_context 不冲突
 //! - Framed 类型映射的页帧在 `MapArea` 内部追踪，确保不会泄漏
-
+//!
+//! # 内核地址空间
+//! - 内核位于虚拟地址空间的高半部分：内核镜像、剩余物理内存与 MMIO 外设都以 `Direct` 方式映射到
+//!   `KERNEL_OFFSET` 之上（虚拟地址 = 物理地址 + `KERNEL_OFFSET`），低半部分不含任何内核映射
+//! - 跳板页与内核栈不在直接映射区中，跳板页的物理地址由其直接映射区中的虚拟地址换算得到
+//!
+//! # mmap 区域
+//! - 未指定地址的 `mmap` 与共享内存挂接由 `MmapRegions` 在 `[MMAP_BASE, MMAP_TOP)` 中分配，
+//!   `munmap` / `shmdt` 后归还，堆只由 `brk` 在 `heap_start` 之上伸缩且不能越过 `MMAP_BASE`
+//! - 指定地址的映射落在 mmap 区域中时，同样从空闲段中扣除
+//! - 文件共享映射的区域记录所映射的文件与起始文件页号（`FileBacking`），
+//!   `msync` 据此把范围内的缓存页写回文件；区域被拆分时右侧的起始页号随之调整
+//!
+//! # madvise 与页回收
+//! - `MADV_DONTNEED` 直接释放 Framed 区域中的页帧，之后访问时经缺页处理重新分配全零页
+//! - `MADV_FREE` 的页被改为只读并记入 `lazy_free`：内存紧张时由 `reclaim_lazy_free` 释放；
+//!   在此之前写入会触发缺页，恢复写权限并撤销这次标记
+//! - Framed 区域（含 ELF 段与私有文件映射）都视为匿名页，被丢弃的页不会从文件重新读入
+//!
+//! # 大页
+//! - 物理内存的直接映射与较大的匿名 mmap 以 2MB 大页映射，减少 TLB 缺失；
+//!   区域中按 2MB 对齐的完整部分用大页，首尾不足 2MB 的部分仍用 4KB 页
+//! - Framed 区域的大页需要对齐的连续页帧，分配不到时退回普通页；`data_frames` 仍按 4KB 记录每一页
+//! - 对大页中的某一页单独操作（`madvise`、缺页重映射）时页表会先把大页拆开，换出不考虑大页区域
+//!
+//! # 零页
+//! - 堆与匿名 mmap 区域（`MapArea::with_zero_page`）中的页在写入前都映射到同一个只读的全零页帧
+//!   `ZERO_PAGE`，不占用私有页帧；第一次写入触发的缺页由缺页处理分配私有页帧（已清零），
+//!   以原权限映射替换零页（内核没有写时复制，零页是唯一被共享的只读页帧）
+//! - 零页不记入 `data_frames`，因此换出、`MADV_FREE` 与 fork 的复制都不会涉及它；
+//!   fork 后子进程中未写入的页没有映射，读访问触发缺页时重新映射零页
+//! - 区域中以大页映射的部分仍在映射时分配页帧
+//! - 内核经由物理页写入用户内存前必须先按写缺页处理，不能写入零页；地址空间正被借用、
+//!   缺页处理无法进行时，写入返回 EFAULT（见 `translate_user_page`）
+//!
+//! # 换出（`swap` feature）
+//! - 用户 Framed 区域中独占页帧的页可以被换出：内容写入交换槽，页表项改为记录槽号的无效项，
+//!   槽由 `MapArea::swapped` 持有；之后的访问触发缺页，由 `fault_in` 读回内容并重新映射
+//! - 换出页的选择用时钟算法近似 LRU：`swap_hand` 记录上次扫描停下的位置，
+//!   A 位为 1 的页清除 A 位后跳过（第二次机会），A 位为 0 的页被换出
+//! - 发起分配的进程正被借用，全局回收函数换不出它的页，因此 `push` 与缺页处理在分配前
+//!   先检查空闲页帧，不足时换出本地址空间的页
+//! - fork 时子进程与父进程共享换出页的槽，任一方换入后只释放自己的引用
+
+use crate::fs::inode::OSInode;
 use crate::fs::File;
-use crate::hal::{PageTableEntryImpl, PageTableImpl, MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE};
+use crate::hal::{PageTableEntryImpl, PageTableImpl, MMAP_BASE, PAGE_SIZE, TRAMPOLINE};
 use crate::mm::address::{align_up, VPNRange};
+#[cfg(feature = "swap")]
+use crate::mm::frame_free_count;
+use crate::mm::mmap_region::MmapRegions;
+#[cfg(feature = "swap")]
+use crate::mm::swap::{self, SwapSlot};
 use crate::mm::{
-    frame_alloc, FrameTracker, PageTable, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum,
+    frame_alloc, frame_alloc_huge, phys_to_virt, virt_to_phys, FrameTracker, PageSize, PageTable,
+    PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum, HUGE_PAGE_PAGES, HUGE_PAGE_SIZE,
 };
 use crate::sync::UPIntrFreeCell;
-use alloc::collections::BTreeMap;
+use crate::task::cond_resched;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
 use alloc::sync::Arc;
-use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use lazy_static::lazy_static;
@@ -49,6 +100,50 @@ extern "C" {
     fn strampoline();
 }
 
+/// `madvise` 建议：无特殊处理
+pub const MADV_NORMAL: usize = 0;
+/// `madvise` 建议：随机访问
+pub const MADV_RANDOM: usize = 1;
+/// `madvise` 建议：顺序访问
+pub const MADV_SEQUENTIAL: usize = 2;
+/// `madvise` 建议：即将访问
+pub const MADV_WILLNEED: usize = 3;
+/// `madvise` 建议：立即丢弃页内容，之后访问得到全零页
+pub const MADV_DONTNEED: usize = 4;
+/// `madvise` 建议：内存紧张时可以丢弃页内容
+pub const MADV_FREE: usize = 8;
+
+/// `mmap` 保护位：可读
+pub const PROT_READ: usize = 1;
+/// `mmap` 保护位：可写
+pub const PROT_WRITE: usize = 2;
+/// `mmap` 保护位：可执行
+pub const PROT_EXEC: usize = 4;
+
+/// `msync` 标志：发起写回后立即返回
+pub const MS_ASYNC: usize = 1;
+/// `msync` 标志：丢弃文件的其他缓存副本
+pub const MS_INVALIDATE: usize = 2;
+/// `msync` 标志：等待写回完成
+pub const MS_SYNC: usize = 4;
+
+/// 每次换出的页数
+#[cfg(feature = "swap")]
+pub const SWAP_CLUSTER: usize = 32;
+/// 为页表等额外分配预留的空闲页帧数
+#[cfg(feature = "swap")]
+const SWAP_RESERVE: usize = 8;
+
+lazy_static! {
+    /// 所有零填充区域共享的全零页帧，只以只读方式映射
+    static ref ZERO_PAGE: FrameTracker = frame_alloc().unwrap();
+}
+
+/// 共享零页的物理页号
+pub fn zero_page_ppn() -> PhysPageNum {
+    ZERO_PAGE.ppn
+}
+
 lazy_static! {
     /// 全局内核地址空间
     ///
@@ -78,6 +173,11 @@ pub struct MemorySet<T: PageTable> {
     pub brk: usize,
     /// 堆起始地址
     pub heap_start: usize,
+    /// mmap 区域的空闲段
+    mmap_regions: MmapRegions,
+    /// 换出扫描的时钟指针
+    #[cfg(feature = "swap")]
+    swap_hand: VirtPageNum,
 }
 
 impl<T: PageTable> MemorySet<T> {
@@ -88,6 +188,9 @@ impl<T: PageTable> MemorySet<T> {
             areas: Vec::new(),
             brk: 0,
             heap_start: 0,
+            mmap_regions: MmapRegions::new(),
+            #[cfg(feature = "swap")]
+            swap_hand: VirtPageNum(0),
         }
     }
 
@@ -125,6 +228,10 @@ impl<T: PageTable> MemorySet<T> {
 
     /// 将 MapArea 插入 MemorySet，并可附加数据写入页帧
     pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
+        #[cfg(feature = "swap")]
+        if map_area.map_type == MapType::Framed && !map_area.zero_fill {
+            self.reserve_frames(map_area.vpn_range.get_end().0 - map_area.vpn_range.get_start().0);
+        }
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
             map_area.copy_data(&self.page_table, data);
@@ -135,79 +242,111 @@ impl<T: PageTable> MemorySet<T> {
     fn map_trampoline(&mut self) {
         self.page_table.map(
             VirtAddr::from(TRAMPOLINE).into(),
-            PhysAddr::from(strampoline as *const () as usize).into(),
+            PhysAddr::from(virt_to_phys(strampoline as *const () as usize).unwrap()).into(),
             // PTEFlags::R | PTEFlags::X,
             MapPermission::R | MapPermission::X,
+            PageSize::Small,
         );
     }
-    /// 扩展堆区到 new_brk
-    pub fn expand_heap(&mut self, new_brk: usize) -> Result<(), ()> {
-        let old_brk = self.brk;
-
-        let old_page = align_up(old_brk, PAGE_SIZE);
+    /// 把堆顶移动到 `new_brk`，成功后更新 `brk`
+    ///
+    /// - 扩展时为新增的页建立零页映射，新增范围不能越过 mmap 区域的起始地址，也不能与已有的区域重叠
+    /// - 收缩时解除释放部分的映射并归还页帧；堆顶所在的页仍然保留
+    /// - 低于 `heap_start` 或无法扩展时返回 `-1`（ENOMEM），`brk` 与已有映射保持不变
+    pub fn set_brk(&mut self, new_brk: usize) -> Result<(), isize> {
+        if new_brk < self.heap_start {
+            return Err(-1); // ENOMEM
+        }
+        let old_page = align_up(self.brk, PAGE_SIZE);
         let new_page = align_up(new_brk, PAGE_SIZE);
-
+        if new_page > MMAP_BASE {
+            return Err(-1); // ENOMEM
+        }
         if new_page > old_page {
-            self.insert_framed_area(
+            let start_vpn = VirtAddr::from(old_page).floor();
+            let end_vpn = VirtAddr::from(new_page).floor();
+            let overlapped = self.areas.iter().any(|area| {
+                area.vpn_range.get_start() < end_vpn && start_vpn < area.vpn_range.get_end()
+            });
+            if overlapped {
+                return Err(-1); // ENOMEM
+            }
+            let area = MapArea::new(
                 old_page.into(),
                 new_page.into(),
+                MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
             );
+            self.push(area.with_zero_page(), None);
+        } else if new_page < old_page {
+            self.unmap_range(
+                VirtAddr::from(new_page).floor(),
+                VirtAddr::from(old_page).floor(),
+            );
         }
+        self.brk = new_brk;
         Ok(())
     }
 
+    /// 解除 `[start, start + len)` 中所有页的映射
+    ///
+    /// 范围可以跨越多个区域，也可以只覆盖区域的一部分：区域被裁剪或拆开，范围中没有映射的页被忽略
     pub fn munmap(&mut self, start: usize, len: usize) -> Result<(), isize> {
-        // use crate::errno::-1;
-
-        // 1. 参数检查
         if len == 0 {
-            return Err(-1);
+            return Err(-1); // EINVAL
         }
-
         let start_va = VirtAddr::from(start);
         if !start_va.aligned() {
-            return Err(-1);
+            return Err(-1); // EINVAL
         }
-
-        let end = start.checked_add(len).ok_or(-1isize)?;
-        let end_va = VirtAddr::from(end);
-
+        let end = start.checked_add(len).ok_or(-1isize)?; // EINVAL
         let start_vpn = start_va.floor();
-        let end_vpn = end_va.ceil();
-
-        if end_vpn <= start_vpn {
-            return Err(-1);
-        }
-
-        // 2. 查找完全匹配的 VMA
-        let mut target_idx: Option<usize> = None;
-
-        for (idx, area) in self.areas.iter().enumerate() {
-            if area.vpn_range.get_start() == start_vpn && area.vpn_range.get_end() == end_vpn {
-                target_idx = Some(idx);
-                break;
-            }
-        }
-
-        let idx = target_idx.ok_or(-1isize)?;
+        let end_vpn = VirtAddr::from(end).ceil();
+        self.unmap_range(start_vpn, end_vpn);
+        self.mmap_regions
+            .release(start, VirtAddr::from(end_vpn).into());
+        Ok(())
+    }
 
-        // 3. 真正 unmap 页表
-        {
+    /// 解除 `[start_vpn, end_vpn)` 中用户区域的映射，部分覆盖的区域被裁剪或从中间拆开
+    fn unmap_range(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let mut idx = 0;
+        while idx < self.areas.len() {
             let area = &mut self.areas[idx];
-            area.unmap(&mut self.page_table);
-            //     warn!("[munmap] unmap page table failed (maybe lazy alloc)");
-            // }
+            let area_start = area.vpn_range.get_start();
+            let area_end = area.vpn_range.get_end();
+            if !area.map_perm.contains(MapPermission::U)
+                || area_end <= start_vpn
+                || end_vpn <= area_start
+            {
+                idx += 1;
+                continue;
+            }
+            if start_vpn <= area_start && area_end <= end_vpn {
+                // 整个区域被覆盖
+                area.unmap(&mut self.page_table);
+                self.areas.remove(idx);
+                continue;
+            }
+            if area_start < start_vpn && end_vpn < area_end {
+                // 从中间挖掉一段，右侧剩余部分作为新区域
+                let (mut middle, right) = area.into_three(start_vpn, end_vpn).unwrap();
+                middle.unmap(&mut self.page_table);
+                self.areas.push(right);
+            } else if area_start < start_vpn {
+                area.shrink_to(&mut self.page_table, start_vpn.into())
+                    .unwrap();
+            } else {
+                area.rshrink_to(&mut self.page_table, end_vpn.into())
+                    .unwrap();
+            }
+            idx += 1;
         }
-
-        // 4. 删除 VMA（注意顺序）
-        self.areas.remove(idx);
-
-        Ok(())
     }
 
     /// 建立映射，错误码后续需要将-1改成特定的错误码
-    /// 目前支支持匿名映射
+    ///
+    /// `start` 为 0 时在 mmap 区域中分配地址，映射失败时归还
     pub fn mmap(
         &mut self,
         start: usize,
@@ -220,18 +359,80 @@ impl<T: PageTable> MemorySet<T> {
         if len == 0 {
             return Err(-1);
         }
-
-        // 如果 start 为 0为动态分配，动态分配时mmap从堆顶开始分配len字节（对齐），
-        let start_va = if start == 0 {
-            let va = self.find_free_area(len)?;
-            VirtAddr::from(va)
-        } else {
-            let va = VirtAddr::from(start);
-            if !va.aligned() {
-                return Err(-1);
+        let end = align_up(start.checked_add(len).ok_or(-1isize)?, PAGE_SIZE);
+        if MapFlags::from_bits_truncate(flags).contains(MapFlags::MAP_FIXED) {
+            if start == 0 || start % PAGE_SIZE != 0 {
+                return Err(-1); // EINVAL
             }
-            va
+            // 覆盖范围内已有的映射
+            self.unmap_range(VirtAddr::from(start).floor(), VirtAddr::from(end).ceil());
+            if let Err(err) = self.mmap_at(start, len, prot, flags, file_arc, off) {
+                self.mmap_regions.release(start, end);
+                return Err(err);
+            }
+            self.mmap_regions.reserve(start, end);
+            return Ok(start);
+        }
+        if start != 0 {
+            self.mmap_at(start, len, prot, flags, file_arc, off)?;
+            self.mmap_regions.reserve(start, end);
+            return Ok(start);
+        }
+        // 不小于大页的区域从 2MB 边界开始，以便使用大页
+        let align = if len >= HUGE_PAGE_SIZE {
+            HUGE_PAGE_SIZE
+        } else {
+            PAGE_SIZE
         };
+        let start = self.mmap_regions.alloc(len, align).ok_or(-1isize)?; // ENOMEM
+        let result = self.mmap_at(start, len, prot, flags, file_arc, off);
+        if result.is_err() {
+            self.mmap_regions
+                .release(start, start + align_up(len, PAGE_SIZE));
+        }
+        result
+    }
+
+    /// 把 `mmap` 的保护位转换为用户区域的权限
+    ///
+    /// - `PROT_NONE` 得到只有 `U` 的权限，区域不建立页表项，任何访问都以 SIGSEGV 结束
+    /// - 与 Linux 在 RISC-V 上相同，`PROT_WRITE` 隐含 `PROT_READ`（页表项不能只写不读）
+    /// - 含未知位时返回 EINVAL；启用 `strict_wx` 时同时可写与可执行返回 EACCES
+    fn prot_to_perm(prot: usize) -> Result<MapPermission, isize> {
+        if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+            return Err(-1); // EINVAL
+        }
+        #[cfg(feature = "strict_wx")]
+        if prot & PROT_WRITE != 0 && prot & PROT_EXEC != 0 {
+            return Err(-1); // EACCES
+        }
+        let mut perm = MapPermission::U;
+        if prot & (PROT_READ | PROT_WRITE) != 0 {
+            perm |= MapPermission::R;
+        }
+        if prot & PROT_WRITE != 0 {
+            perm |= MapPermission::W;
+        }
+        if prot & PROT_EXEC != 0 {
+            perm |= MapPermission::X;
+        }
+        Ok(perm)
+    }
+
+    /// 在 `start` 处建立映射
+    fn mmap_at(
+        &mut self,
+        start: usize,
+        len: usize,
+        prot: usize,
+        flags: usize,
+        file_arc: Option<Arc<dyn File + Send + Sync>>,
+        off: usize,
+    ) -> Result<usize, isize> {
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1);
+        }
 
         let end = usize::from(start_va).checked_add(len).ok_or(-1isize)?;
         let end_va = VirtAddr::from(end);
@@ -245,40 +446,81 @@ impl<T: PageTable> MemorySet<T> {
             }
         }
 
-        let perm = MapPermission::from_bits(prot as u8)
-            .unwrap_or(MapPermission::R | MapPermission::W | MapPermission::U);
-
-        MapArea::new(start_va, end_va, MapType::Framed, perm);
-        //建立映射，并将数据初始化为零
-        self.insert_framed_area(start_va, end_va, perm);
+        let perm = Self::prot_to_perm(prot)?;
+        if !perm.intersects(MapPermission::R | MapPermission::X) {
+            // PROT_NONE：只占用地址范围，不读取文件，也不分配页帧
+            self.push(MapArea::new(start_va, end_va, MapType::Framed, perm), None);
+            return Ok(start_va.into());
+        }
 
-        if file_arc.is_some() {
-            let file = file_arc.as_deref().ok_or(-1isize)?;
-            let file_stat = file.get_stat();
-            let file_len = file_stat.st_size as usize;
-            let copy_len = core::cmp::min(len, file_len);
+        // FAT32 上的普通文件经由页缓存映射
+        if let Some(inode) = file_arc
+            .as_ref()
+            .and_then(|file| file.as_any().downcast_ref::<OSInode>())
+        {
+            if off % PAGE_SIZE != 0 {
+                return Err(-1); // EINVAL
+            }
+            let cache = inode.page_cache().ok_or(-1isize)?; // ENODEV
+            let first = off / PAGE_SIZE;
+            let pages = first..first + (usize::from(end_vpn) - usize::from(start_vpn));
+            let frames = inode
+                .with_fat_file(|file| {
+                    pages
+                        .clone()
+                        .map(|idx| cache.get_page(idx, file))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .flatten()
+                .ok_or(-1isize)?; // ENOMEM
+            if MapFlags::from_bits_truncate(flags).contains(MapFlags::MAP_SHARED) {
+                // 共享映射直接使用缓存页，写入对其他映射与 read 立即可见
+                if perm.contains(MapPermission::W) {
+                    pages.for_each(|idx| cache.mark_dirty(idx));
+                }
+                let addr = self.attach_shared(start_va.into(), &frames, perm)?;
+                // 记录映射的文件，供 msync 写回
+                self.areas.last_mut().unwrap().backing = Some(FileBacking {
+                    file: file_arc.clone().unwrap(),
+                    first_page: first,
+                });
+                return Ok(addr);
+            }
+            // 私有映射复制一份缓存页的内容
+            self.insert_framed_area(start_va, end_va, perm);
+            let mut vpn = start_vpn;
+            for frame in frames.iter() {
+                let dst = self.page_table.translate(vpn).unwrap().ppn().get_bytes_array();
+                dst.copy_from_slice(frame.ppn.get_bytes_array());
+                vpn.step();
+            }
+            return Ok(start_va.into());
+        }
 
-            let mut buf = vec![0u8; copy_len];
-            file.read_at(0, &mut buf)
-                .expect("[MemorySet.mmap]pelease add true fd");
+        //建立映射，并将数据初始化为零；匿名映射尽量使用大页，其余部分写入前映射零页
+        let area = MapArea::new(start_va, end_va, MapType::Framed, perm);
+        let area = if file_arc.is_none() {
+            area.with_huge_pages().with_zero_page()
+        } else {
+            area
+        };
+        self.push(area, None);
 
-            let mut offset = off;
+        // 其他文件（设备等）在映射时按偏移复制一次内容
+        if let Some(file) = file_arc.as_deref() {
             let mut vpn = start_vpn;
-
-            while offset < copy_len {
+            let mut offset = 0;
+            while offset < len {
                 let page = self
                     .page_table
                     .translate(vpn)
                     .unwrap()
                     .ppn()
                     .get_bytes_array();
-
-                let end = core::cmp::min(offset + PAGE_SIZE, copy_len);
-                let src = &buf[offset..end];
-                let dst = &mut page[..src.len()];
-
-                dst.copy_from_slice(src);
-
+                let n = core::cmp::min(PAGE_SIZE, len - offset);
+                if file.read_at(off + offset, &mut page[..n]).is_err() {
+                    break;
+                }
                 offset += PAGE_SIZE;
                 vpn.step();
             }
@@ -287,6 +529,312 @@ impl<T: PageTable> MemorySet<T> {
         Ok(start_va.into())
     }
 
+    /// 将一组共享页帧映射到 `start` 开始的地址
+    ///
+    /// `start` 为 0 时在 mmap 区域中分配地址，返回实际映射的起始地址
+    pub fn attach_shared(
+        &mut self,
+        start: usize,
+        frames: &[Arc<FrameTracker>],
+        perm: MapPermission,
+    ) -> Result<usize, isize> {
+        let len = frames.len() * PAGE_SIZE;
+        if start != 0 {
+            self.attach_shared_at(start, frames, perm)?;
+            self.mmap_regions.reserve(start, start + len);
+            return Ok(start);
+        }
+        let start = self.mmap_regions.alloc(len, PAGE_SIZE).ok_or(-1isize)?; // ENOMEM
+        let result = self.attach_shared_at(start, frames, perm);
+        if result.is_err() {
+            self.mmap_regions.release(start, start + len);
+        }
+        result
+    }
+
+    /// 将一组共享页帧映射到 `start` 开始的地址
+    fn attach_shared_at(
+        &mut self,
+        start: usize,
+        frames: &[Arc<FrameTracker>],
+        perm: MapPermission,
+    ) -> Result<usize, isize> {
+        let len = frames.len() * PAGE_SIZE;
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1);
+        }
+        let end_va = VirtAddr::from(start.checked_add(len).ok_or(-1isize)?);
+        let start_vpn = start_va.floor();
+        let end_vpn = end_va.ceil();
+        for area in self.areas.iter() {
+            if area.check_overlapping(start_vpn, end_vpn).is_some() {
+                return Err(-1);
+            }
+        }
+
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Shared, perm);
+        let mut vpn = start_vpn;
+        for frame in frames {
+            map_area.data_frames.insert(vpn, frame.clone());
+            vpn.step();
+        }
+        self.push(map_area, None);
+        Ok(start)
+    }
+
+    /// 解除以 `start` 开始的共享区域映射
+    ///
+    /// 页帧仅在最后一个持有者释放后才会被回收
+    pub fn detach_shared(&mut self, start: usize) -> Result<(), isize> {
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1);
+        }
+        let start_vpn = start_va.floor();
+        let idx = self
+            .areas
+            .iter()
+            .position(|area| {
+                area.map_type == MapType::Shared && area.vpn_range.get_start() == start_vpn
+            })
+            .ok_or(-1isize)?;
+        self.areas[idx].unmap(&mut self.page_table);
+        let area = self.areas.remove(idx);
+        self.mmap_regions.release(
+            VirtAddr::from(area.vpn_range.get_start()).into(),
+            VirtAddr::from(area.vpn_range.get_end()).into(),
+        );
+        Ok(())
+    }
+
+    /// 包含 `vpn` 的用户可访问区域下标，陷入上下文等内核专用区域不会被找到
+    fn user_area_index(&self, vpn: VirtPageNum) -> Option<usize> {
+        self.areas.iter().position(|area| {
+            area.map_perm.contains(MapPermission::U)
+                && area.vpn_range.get_start() <= vpn
+                && vpn < area.vpn_range.get_end()
+        })
+    }
+
+    /// 用户可访问的区域，按起始地址排序
+    pub fn vmas(&self) -> Vec<Vma> {
+        let mut vmas: Vec<Vma> = self
+            .areas
+            .iter()
+            .filter(|area| area.map_perm.contains(MapPermission::U))
+            .map(|area| Vma {
+                start: VirtAddr::from(area.vpn_range.get_start()).into(),
+                end: VirtAddr::from(area.vpn_range.get_end()).into(),
+                perm: area.map_perm,
+                shared: area.map_type == MapType::Shared,
+                offset: area
+                    .backing
+                    .as_ref()
+                    .map_or(0, |backing| backing.first_page * PAGE_SIZE),
+                path: area.backing.as_ref().map(|backing| backing.file.get_path()),
+            })
+            .collect();
+        vmas.sort_by_key(|vma| vma.start);
+        vmas
+    }
+
+    /// `[start, start + len)` 中每一页是否驻留在内存中，每页一个字节，最低位为 1 表示驻留
+    ///
+    /// 以页表项是否有效为准：尚未访问过的页与已换出的页不驻留，以共享零页映射的页驻留；
+    /// 文件共享映射中尚未映射的页即使在页缓存中也报告为不驻留。
+    /// `start` 未按页对齐时返回 `-1`（EINVAL），范围内存在未映射的页时返回 `-1`（ENOMEM）
+    pub fn mincore(&self, start: usize, len: usize) -> Result<Vec<u8>, isize> {
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1); // EINVAL
+        }
+        let end = start.checked_add(len).ok_or(-1isize)?; // ENOMEM
+        let start_vpn = start_va.floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        (start_vpn.0..end_vpn.0)
+            .map(|vpn| {
+                let vpn = VirtPageNum::from(vpn);
+                self.user_area_index(vpn).ok_or(-1isize)?; // ENOMEM
+                Ok(self.translate(vpn).is_some_and(|pte| pte.is_valid()) as u8)
+            })
+            .collect()
+    }
+
+    /// 对 `[start, start + len)` 给出使用建议
+    ///
+    /// 范围内存在未映射的页时返回 `-1`（ENOMEM），其余错误返回 `-1`（EINVAL）
+    pub fn madvise(&mut self, start: usize, len: usize, advice: usize) -> Result<(), isize> {
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1); // EINVAL
+        }
+        let end = start.checked_add(len).ok_or(-1isize)?; // EINVAL
+        let start_vpn = start_va.floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        if (start_vpn.0..end_vpn.0).any(|vpn| self.user_area_index(vpn.into()).is_none()) {
+            return Err(-1); // ENOMEM
+        }
+        match advice {
+            MADV_NORMAL | MADV_RANDOM | MADV_SEQUENTIAL | MADV_WILLNEED => Ok(()),
+            MADV_DONTNEED | MADV_FREE => {
+                for area in self.areas.iter_mut() {
+                    if area.map_type != MapType::Framed || !area.map_perm.contains(MapPermission::U)
+                    {
+                        continue;
+                    }
+                    let (from, to) = match area.check_overlapping(start_vpn, end_vpn) {
+                        Some(range) => range,
+                        None => continue,
+                    };
+                    for vpn in from.0..to.0 {
+                        if advice == MADV_DONTNEED {
+                            area.unmap_one(&mut self.page_table, vpn.into());
+                        } else {
+                            area.lazy_free_one(&mut self.page_table, vpn.into());
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(-1), // EINVAL
+        }
+    }
+
+    /// 把 `[start, start + len)` 中的文件共享映射同步到文件
+    ///
+    /// - 可写共享映射中的页立即写回；没有后台写回，`MS_ASYNC` 与 `MS_SYNC` 一样同步完成
+    /// - `MS_INVALIDATE` 丢弃这些文件页中干净且未被映射的缓存副本，之后的 read 重新从磁盘读入
+    /// - 范围内存在未映射的页时返回 `-1`（ENOMEM），其余错误返回 `-1`（EINVAL）
+    pub fn msync(&self, start: usize, len: usize, flags: usize) -> Result<(), isize> {
+        if flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0
+            || (flags & MS_ASYNC != 0 && flags & MS_SYNC != 0)
+        {
+            return Err(-1); // EINVAL
+        }
+        let start_va = VirtAddr::from(start);
+        if !start_va.aligned() {
+            return Err(-1); // EINVAL
+        }
+        let end = start.checked_add(len).ok_or(-1isize)?; // EINVAL
+        let start_vpn = start_va.floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        if (start_vpn.0..end_vpn.0).any(|vpn| self.user_area_index(vpn.into()).is_none()) {
+            return Err(-1); // ENOMEM
+        }
+        for area in self.areas.iter() {
+            let Some(backing) = &area.backing else {
+                continue;
+            };
+            let Some(inode) = backing.file.as_any().downcast_ref::<OSInode>() else {
+                continue;
+            };
+            let Some((from, to)) = area.check_overlapping(start_vpn, end_vpn) else {
+                continue;
+            };
+            let first = backing.first_page + (from.0 - area.vpn_range.get_start().0);
+            let pages = first..first + to.0.saturating_sub(from.0);
+            if pages.is_empty() {
+                continue;
+            }
+            if area.map_perm.contains(MapPermission::W) {
+                inode.sync_pages(pages.clone());
+            }
+            if flags & MS_INVALIDATE != 0 {
+                inode.invalidate_pages(pages);
+            }
+        }
+        Ok(())
+    }
+
+    /// 处理用户地址 `va` 上的缺页，`access` 为本次访问需要的权限（R / W / X 之一）
+    ///
+    /// 返回 `false` 表示这是一次非法访问
+    pub fn handle_page_fault(&mut self, va: usize, access: MapPermission) -> bool {
+        let vpn = VirtAddr::from(va).floor();
+        let idx = match self.user_area_index(vpn) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        #[cfg(feature = "swap")]
+        if !self.areas[idx].data_frames.contains_key(&vpn) {
+            self.reserve_frames(1);
+        }
+        self.areas[idx].fault_in(&mut self.page_table, vpn, access)
+    }
+
+    /// 空闲页帧不足 `pages`（另加 `SWAP_RESERVE`）时换出本地址空间的页，直到足够或无页可换
+    #[cfg(feature = "swap")]
+    fn reserve_frames(&mut self, pages: usize) {
+        while frame_free_count() < pages + SWAP_RESERVE {
+            if self.swap_out(SWAP_CLUSTER) == 0 {
+                break;
+            }
+        }
+    }
+
+    /// 按时钟算法换出最多 `count` 个页，返回换出的页数
+    ///
+    /// 候选页为用户 Framed 区域中独占页帧、且没有 `MADV_FREE` 标记的页；
+    /// 至多扫描两圈，第一圈清除的 A 位在第二圈不会再阻止换出
+    #[cfg(feature = "swap")]
+    pub fn swap_out(&mut self, count: usize) -> usize {
+        let mut candidates: Vec<(VirtPageNum, usize)> = Vec::new();
+        for (idx, area) in self.areas.iter().enumerate() {
+            if area.map_type != MapType::Framed
+                || !area.map_perm.contains(MapPermission::U)
+                || area.huge
+            {
+                continue;
+            }
+            let range = area.vpn_range.get_start()..area.vpn_range.get_end();
+            for (&vpn, frame) in area.data_frames.range(range) {
+                if Arc::strong_count(frame) == 1 && !area.lazy_free.contains(&vpn) {
+                    candidates.push((vpn, idx));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return 0;
+        }
+        candidates.sort_unstable_by_key(|&(vpn, _)| vpn);
+        let start = candidates.partition_point(|&(vpn, _)| vpn < self.swap_hand);
+        let mut swapped = 0;
+        for i in 0..candidates.len() * 2 {
+            if swapped == count {
+                break;
+            }
+            let (vpn, idx) = candidates[(start + i) % candidates.len()];
+            let area = &mut self.areas[idx];
+            if !area.data_frames.contains_key(&vpn) {
+                continue;
+            }
+            self.swap_hand = VirtPageNum(vpn.0 + 1);
+            if self.page_table.find_pte(vpn).unwrap().take_accessed() {
+                self.page_table.flush_tlb(vpn);
+                continue;
+            }
+            if !area.swap_out_one(&mut self.page_table, vpn) {
+                // 交换区已满
+                break;
+            }
+            swapped += 1;
+        }
+        swapped
+    }
+
+    /// 释放所有 `MADV_FREE` 后没有再被写入的页，返回释放的页数
+    pub fn reclaim_lazy_free(&mut self) -> usize {
+        let mut freed = 0;
+        for area in self.areas.iter_mut() {
+            for vpn in core::mem::take(&mut area.lazy_free) {
+                area.unmap_one(&mut self.page_table, vpn);
+                freed += 1;
+            }
+        }
+        freed
+    }
+
     /// 构建内核空间 MemorySet，不包含内核栈
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
@@ -295,12 +843,17 @@ impl<T: PageTable> MemorySet<T> {
         memory_set.map_trampoline();
 
         // 映射内核段
+        // 调试桩需要在代码段写入断点指令
+        #[cfg(not(feature = "gdbstub"))]
+        let text_perm = MapPermission::R | MapPermission::X;
+        #[cfg(feature = "gdbstub")]
+        let text_perm = MapPermission::R | MapPermission::W | MapPermission::X;
         memory_set.push(
             MapArea::new(
                 (stext as usize).into(),
                 (etext as usize).into(),
-                MapType::Identical,
-                MapPermission::R | MapPermission::X,
+                MapType::Direct,
+                text_perm,
             ),
             None,
         );
@@ -309,7 +862,7 @@ impl<T: PageTable> MemorySet<T> {
             MapArea::new(
                 (srodata as usize).into(),
                 (erodata as usize).into(),
-                MapType::Identical,
+                MapType::Direct,
                 MapPermission::R,
             ),
             None,
@@ -319,7 +872,7 @@ impl<T: PageTable> MemorySet<T> {
             MapArea::new(
                 (sdata as usize).into(),
                 (edata as usize).into(),
-                MapType::Identical,
+                MapType::Direct,
                 MapPermission::R | MapPermission::W,
             ),
             None,
@@ -329,30 +882,33 @@ impl<T: PageTable> MemorySet<T> {
             MapArea::new(
                 (sbss_with_stack as usize).into(),
                 (ebss as usize).into(),
-                MapType::Identical,
+                MapType::Direct,
                 MapPermission::R | MapPermission::W,
             ),
             None,
         );
 
-        // 映射物理内存剩余空间
-        memory_set.push(
-            MapArea::new(
-                (ekernel as usize).into(),
-                MEMORY_END.into(),
-                MapType::Identical,
-                MapPermission::R | MapPermission::W,
-            ),
-            None,
-        );
+        // 映射页帧分配器管理的各段物理内存（直接映射，使用大页）
+        for (start, end) in crate::boot::usable_memory() {
+            memory_set.push(
+                MapArea::new(
+                    phys_to_virt(start).into(),
+                    phys_to_virt(end).into(),
+                    MapType::Direct,
+                    MapPermission::R | MapPermission::W,
+                )
+                .with_huge_pages(),
+                None,
+            );
+        }
 
         // 映射 MMIO 外设
-        for pair in MMIO {
+        for pair in crate::boot::mmio_regions() {
             memory_set.push(
                 MapArea::new(
-                    (*pair).0.into(),
-                    ((*pair).0 + (*pair).1).into(),
-                    MapType::Identical,
+                    phys_to_virt(pair.0).into(),
+                    phys_to_virt(pair.0 + pair.1).into(),
+                    MapType::Direct,
                     MapPermission::R | MapPermission::W,
                 ),
                 None,
@@ -412,26 +968,52 @@ impl<T: PageTable> MemorySet<T> {
     }
 
     /// 从已存在的用户空间 MemorySet 克隆新的 MemorySet
-    pub fn from_existed_user(user_space: &MemorySet<T>) -> MemorySet<T> {
+    ///
+    /// 只建立映射并分配页帧，页的内容由返回的 `PageCopies` 在调用者释放父进程的锁之后复制，
+    /// 复制期间可以被抢占；复制完成之前新的 MemorySet 不能被使用
+    pub fn from_existed_user(user_space: &MemorySet<T>) -> (MemorySet<T>, PageCopies) {
+        let mut copies = PageCopies { pages: Vec::new() };
         let mut memory_set = Self::new_bare();
+        memory_set.mmap_regions = user_space.mmap_regions.clone();
         // 映射跳板
         memory_set.map_trampoline();
 
         // 复制用户空间的每个映射区域
         for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
+            let mut new_area = MapArea::from_another(area);
+            if area.map_type == MapType::Shared {
+                // 共享区域与父进程使用同一组物理页帧，不复制内容
+                new_area.data_frames = area.data_frames.clone();
+                memory_set.push(new_area, None);
+                continue;
+            }
+            if area.map_type != MapType::Framed {
+                memory_set.push(new_area, None);
+                continue;
+            }
 
-            // 复制用户数据页内容
-            for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
-                let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
-                dst_ppn
-                    .get_bytes_array()
-                    .copy_from_slice(src_ppn.get_bytes_array());
+            // 换出的页与父进程共享交换槽
+            #[cfg(feature = "swap")]
+            for (&vpn, slot) in area
+                .swapped
+                .range(area.vpn_range.get_start()..area.vpn_range.get_end())
+            {
+                *memory_set.page_table.find_pte_create(vpn).unwrap() =
+                    PageTableEntryImpl::swapped(slot.id());
+                new_area.swapped.insert(vpn, slot.clone());
+            }
+            // 只复制已经分配的页，被丢弃的页在子进程中同样按需分配
+            for (&vpn, frame) in area.data_frames.iter() {
+                new_area.map_one(&mut memory_set.page_table, vpn);
+                copies.pages.push(PageCopy {
+                    _src: frame.clone(),
+                    src_ppn: user_space.translate(vpn).unwrap().ppn(),
+                    dst_ppn: memory_set.translate(vpn).unwrap().ppn(),
+                });
             }
+            memory_set.areas.push(new_area);
         }
-        memory_set
+        (memory_set, copies)
     }
 
     /// 激活页表
@@ -443,53 +1025,63 @@ impl<T: PageTable> MemorySet<T> {
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntryImpl> {
         self.page_table.translate(vpn)
     }
-    /// 从堆顶开始找到一块连续可用虚拟地址，并将堆顶向后移动（len/PAGE_SIZE）向下取整
-    /// len: 需要的字节数
-    pub fn find_free_area(&mut self, len: usize) -> Result<usize, isize> {
-        // 1. 对齐到页
-        let len = (len + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
-
-        // 2. 从 堆顶 开始搜索
-        let mut addr = self.brk;
+    /// 回收数据页（清空 areas）
+    pub fn recycle_data_pages(&mut self) {
+        //*self = Self::new_bare();
+        self.areas.clear();
+    }
+}
 
-        loop {
-            let start_vpn = VirtAddr::from(addr).floor();
-            let end_vpn = VirtAddr::from(addr + len).ceil();
-
-            // 检查是否与已有 VMA 冲突
-            let mut conflict = false;
-            for area in self.areas.iter() {
-                if area.check_overlapping(start_vpn, end_vpn).is_some() {
-                    conflict = true;
-                    break;
-                }
-            }
+/// fork 时一页待复制的内容
+struct PageCopy {
+    /// 持有源页帧，父进程在复制完成之前换出或解除映射这一页也不会释放它
+    _src: Arc<FrameTracker>,
+    src_ppn: PhysPageNum,
+    dst_ppn: PhysPageNum,
+}
 
-            if !conflict {
-                // 找到空闲区，更新 brk
-                self.brk = addr + len;
-                return Ok(addr);
-            }
+/// `MemorySet::from_existed_user` 推迟的页复制
+pub struct PageCopies {
+    pages: Vec<PageCopy>,
+}
 
-            // 冲突的话跳到上一个 VMA 结束后继续
-            let mut next_addr = addr + PAGE_SIZE;
-            for area in self.areas.iter() {
-                let area_start: usize = VirtAddr::from(area.vpn_range.get_start()).into();
-                let area_end: usize = VirtAddr::from(area.vpn_range.get_end()).into();
-                if area_start <= addr && addr < area_end {
-                    next_addr = area_end;
-                    break;
-                }
+impl PageCopies {
+    /// 每复制多少页检查一次是否需要让出处理器
+    const BATCH: usize = 64;
+
+    /// 复制所有页的内容，调用者不能持有任何锁，否则 `cond_resched` 不会让出处理器
+    pub fn run(self) {
+        for (i, page) in self.pages.iter().enumerate() {
+            page.dst_ppn
+                .get_bytes_array()
+                .copy_from_slice(page.src_ppn.get_bytes_array());
+            if i % Self::BATCH == Self::BATCH - 1 {
+                cond_resched();
             }
-            addr = next_addr;
         }
     }
+}
 
-    /// 回收数据页（清空 areas）
-    pub fn recycle_data_pages(&mut self) {
-        //*self = Self::new_bare();
-        self.areas.clear();
-    }
+/// 用户地址空间中一个区域的描述，见 `MemorySet::vmas`
+pub struct Vma {
+    /// 起止地址 `[start, end)`
+    pub start: usize,
+    pub end: usize,
+    pub perm: MapPermission,
+    /// 是否为共享映射（共享内存段或文件共享映射）
+    pub shared: bool,
+    /// 起始地址对应的文件偏移，不映射文件时为 0
+    pub offset: usize,
+    /// 文件共享映射所映射的文件
+    pub path: Option<String>,
+}
+
+/// 文件共享映射的来源
+#[derive(Clone)]
+struct FileBacking {
+    file: Arc<dyn File + Send + Sync>,
+    /// 区域起始页对应的文件页号
+    first_page: usize,
 }
 
 /// 表示连续虚拟页范围的映射区域
@@ -507,11 +1099,11 @@ pub struct MapArea {
     /// 数据页帧追踪表（仅 Framed 类型使用）
     ///
     /// 键：虚拟页号
-    /// 值：对应的物理页帧追踪器
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// 值：对应的物理页帧追踪器（Shared 类型下可能被多个地址空间共同持有）
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     /// 映射类型
     ///
-    /// `Identical`：虚拟页号与物理页号相同映射
+    /// `Direct`：内核直接映射，物理页号由虚拟页号减去 `KERNEL_OFFSET` 对应的页数得到
     /// `Framed`：为每个虚拟页分配独立物理页帧
     /// `Linear(offset)`：线性映射，物理页号 = 虚拟页号 + offset
     map_type: MapType,
@@ -519,6 +1111,17 @@ pub struct MapArea {
     ///
     /// `MapPermission` 位标志，表示读(R)/写(W)/执行(X)/用户权限(U)
     map_perm: MapPermission,
+    /// `MADV_FREE` 后尚未被写入的页，以只读方式映射，内存紧张时可直接释放
+    lazy_free: BTreeSet<VirtPageNum>,
+    /// 是否以 2MB 大页映射区域中对齐的部分（仅 Direct 与 Framed 类型）
+    huge: bool,
+    /// 是否在写入前以共享零页映射（仅 Framed 类型）
+    zero_fill: bool,
+    /// 文件共享映射所映射的文件（仅 Shared 类型）
+    backing: Option<FileBacking>,
+    /// 已换出的页及其交换槽（fork 后可能与其他地址空间共享）
+    #[cfg(feature = "swap")]
+    swapped: BTreeMap<VirtPageNum, Arc<SwapSlot>>,
 }
 
 impl MapArea {
@@ -536,6 +1139,12 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            lazy_free: BTreeSet::new(),
+            huge: false,
+            zero_fill: false,
+            backing: None,
+            #[cfg(feature = "swap")]
+            swapped: BTreeMap::new(),
         }
     }
 
@@ -546,9 +1155,27 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            lazy_free: BTreeSet::new(),
+            huge: false,
+            zero_fill: another.zero_fill,
+            backing: another.backing.clone(),
+            #[cfg(feature = "swap")]
+            swapped: BTreeMap::new(),
         }
     }
 
+    /// 以 2MB 大页映射区域中按 2MB 对齐的完整部分
+    pub fn with_huge_pages(mut self) -> Self {
+        self.huge = true;
+        self
+    }
+
+    /// 写入前以共享零页映射，第一次写入时才分配私有页帧
+    pub fn with_zero_page(mut self) -> Self {
+        self.zero_fill = true;
+        self
+    }
+
     ///求虚拟地址的交集
     pub fn check_overlapping(
         &self,
@@ -574,7 +1201,29 @@ impl MapArea {
         }
     }
 
-    ///将MaoAera分成三块
+    /// 在 `at` 处把区域一分为二：自身保留 `[start, at)`，返回 `[at, end)`
+    ///
+    /// 页帧、`MADV_FREE` 标记与换出槽按页号分给两侧，页表不变
+    fn split_off(&mut self, at: VirtPageNum) -> MapArea {
+        let mut right = MapArea::from_another(self);
+        right.vpn_range = VPNRange::new(at, self.vpn_range.get_end());
+        right.huge = self.huge;
+        right.data_frames = self.data_frames.split_off(&at);
+        right.lazy_free = self.lazy_free.split_off(&at);
+        if let Some(backing) = right.backing.as_mut() {
+            backing.first_page += at.0 - self.vpn_range.get_start().0;
+        }
+        #[cfg(feature = "swap")]
+        {
+            right.swapped = self.swapped.split_off(&at);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        right
+    }
+
+    /// 将 MapArea 分成三块：自身保留 `[area_start, start)`，返回 `[start, end)` 与 `[end, area_end)`
+    ///
+    /// 只允许严格位于区域中间的拆分，否则返回 `None`
     pub fn into_three(
         &mut self,
         start_vpn: VirtPageNum,
@@ -582,32 +1231,16 @@ impl MapArea {
     ) -> Option<(MapArea, MapArea)> {
         let area_start = self.vpn_range.get_start();
         let area_end = self.vpn_range.get_end();
-        let start_va = VirtAddr::from(start_vpn);
-        let end_va = VirtAddr::from(end_vpn);
-        let area_end_va = VirtAddr::from(area_end);
-        let _ = VirtAddr::from(area_start);
         // 必须是严格的中间拆分
         if !(area_start < start_vpn && start_vpn < end_vpn && end_vpn < area_end) {
             return None;
         }
-
-        // 1. 构造 middle: [start, end)
-        let mut middle = MapArea::new(start_va, end_va, self.map_type, self.map_perm);
-
-        // middle 继承 frame / lazy 状态
-        middle.data_frames = self.data_frames.clone();
-
-        // 2. 构造 right: [end, area_end)
-        let mut right = MapArea::new(end_va, area_end_va, self.map_type, self.map_perm);
-
-        right.data_frames = self.data_frames.clone();
-
-        // 3. 修改 self 为 left: [area_start, start)
-        self.vpn_range = VPNRange::new(area_start, start_vpn);
-
+        let right = self.split_off(end_vpn);
+        let middle = self.split_off(start_vpn);
         Some((middle, right))
     }
-    /// 把MapAera分成前一块
+
+    /// 把区域缩小为 `[start, new_end)`，解除其后各页的映射
     pub fn shrink_to<T: PageTable>(
         &mut self,
         page_table: &mut T,
@@ -620,18 +1253,11 @@ impl MapArea {
         if !(start_vpn < new_end_vpn && new_end_vpn < old_end_vpn) {
             return Err(());
         }
-
-        // unmap [new_end, old_end)
-        for vpn in new_end_vpn.0..old_end_vpn.0 {
-            let vpn = VirtPageNum(vpn);
-            let _ = page_table.unmap(vpn); // 已经 unmapped 也无所谓
-        }
-
-        // 更新区域
-        self.vpn_range = VPNRange::new(start_vpn, new_end_vpn);
+        self.split_off(new_end_vpn).unmap(page_table);
         Ok(())
     }
-    ///将MapAera分成后一块
+
+    /// 把区域缩小为 `[new_start, end)`，解除其前各页的映射
     pub fn rshrink_to<T: PageTable>(
         &mut self,
         page_table: &mut T,
@@ -644,15 +1270,9 @@ impl MapArea {
         if !(old_start_vpn < new_start_vpn && new_start_vpn < old_end_vpn) {
             return Err(());
         }
-
-        // unmap [old_start, new_start)
-        for vpn in old_start_vpn.0..new_start_vpn.0 {
-            let vpn = VirtPageNum(vpn);
-            let _ = page_table.unmap(vpn);
-        }
-
-        // 更新区域
-        self.vpn_range = VPNRange::new(new_start_vpn, old_end_vpn);
+        let right = self.split_off(new_start_vpn);
+        let mut left = core::mem::replace(self, right);
+        left.unmap(page_table);
         Ok(())
     }
 
@@ -662,13 +1282,17 @@ impl MapArea {
     pub fn map_one<T: PageTable>(&mut self, page_table: &mut T, vpn: VirtPageNum) {
         let ppn: PhysPageNum;
         match self.map_type {
-            MapType::Identical => {
-                ppn = PhysPageNum(vpn.0);
+            MapType::Direct => {
+                ppn = direct_ppn(vpn);
             }
             MapType::Framed => {
                 let frame = frame_alloc().unwrap();
                 ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                self.data_frames.insert(vpn, Arc::new(frame));
+            }
+            MapType::Shared => {
+                // 共享页帧在建立 MapArea 时已由调用者填入
+                ppn = self.data_frames.get(&vpn).unwrap().ppn;
             }
             MapType::Linear(pn_offset) => {
                 // check for sv39
@@ -677,27 +1301,186 @@ impl MapArea {
             }
         }
         let pte_flags = MapPermission::from_bits(self.map_perm.bits()).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
+        page_table.map(vpn, ppn, pte_flags, PageSize::Small);
     }
 
     /// 解除单页映射
+    ///
+    /// Framed 区域中的页可能已被 `MADV_DONTNEED` 丢弃，此时只清理记录；已换出的页同时释放交换槽
     pub fn unmap_one<T: PageTable>(&mut self, page_table: &mut T, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
+        if self.map_type == MapType::Framed || self.map_type == MapType::Shared {
             self.data_frames.remove(&vpn);
         }
+        self.lazy_free.remove(&vpn);
+        #[cfg(feature = "swap")]
+        if self.swapped.remove(&vpn).is_some() {
+            *page_table.find_pte(vpn).unwrap() = PageTableEntryImpl::empty();
+        }
+        if page_table.translate(vpn).is_some_and(|pte| pte.is_valid()) {
+            page_table.unmap(vpn);
+        }
+    }
+
+    /// 把 `vpn` 只读映射到共享零页
+    fn map_zero<T: PageTable>(&self, page_table: &mut T, vpn: VirtPageNum) {
+        page_table.map(
+            vpn,
+            ZERO_PAGE.ppn,
+            self.map_perm - MapPermission::W,
+            PageSize::Small,
+        );
+    }
+
+    /// `vpn` 是否已换出
+    #[cfg(feature = "swap")]
+    fn is_swapped(&self, vpn: VirtPageNum) -> bool {
+        self.swapped.contains_key(&vpn)
+    }
+
+    #[cfg(not(feature = "swap"))]
+    fn is_swapped(&self, _vpn: VirtPageNum) -> bool {
+        false
+    }
+
+    /// 以 `perm` 重新映射已分配页帧的页
+    fn remap_one<T: PageTable>(&self, page_table: &mut T, vpn: VirtPageNum, perm: MapPermission) {
+        let ppn = self.data_frames.get(&vpn).unwrap().ppn;
+        page_table.unmap(vpn);
+        page_table.map(vpn, ppn, perm, PageSize::Small);
+    }
+
+    /// 把一个已分配的可写页标记为 `MADV_FREE`：去掉写权限，之后的写入会触发缺页
+    fn lazy_free_one<T: PageTable>(&mut self, page_table: &mut T, vpn: VirtPageNum) {
+        if !self.map_perm.contains(MapPermission::W)
+            || !self.data_frames.contains_key(&vpn)
+            || !self.lazy_free.insert(vpn)
+        {
+            return;
+        }
+        self.remap_one(page_table, vpn, self.map_perm - MapPermission::W);
+    }
+
+    /// 把独占页帧的 `vpn` 换出：页帧内容写入交换槽，页表项改为记录槽号，交换区已满时返回 `false`
+    #[cfg(feature = "swap")]
+    fn swap_out_one<T: PageTable>(&mut self, page_table: &mut T, vpn: VirtPageNum) -> bool {
+        let slot = match swap::swap_out(self.data_frames.get(&vpn).unwrap().ppn) {
+            Some(slot) => slot,
+            None => return false,
+        };
         page_table.unmap(vpn);
+        *page_table.find_pte(vpn).unwrap() = PageTableEntryImpl::swapped(slot.id());
+        self.data_frames.remove(&vpn);
+        self.swapped.insert(vpn, Arc::new(slot));
+        true
+    }
+
+    /// 处理本区域内 `vpn` 上的缺页
+    ///
+    /// - 页已换出：读回交换槽中的内容重新映射
+    /// - 零填充区域中未写入的页：读访问映射共享零页，写访问分配私有页帧替换零页
+    /// - 页帧已被丢弃：分配全零页重新映射
+    /// - 写入 `MADV_FREE` 页：撤销标记并恢复写权限
+    fn fault_in<T: PageTable>(
+        &mut self,
+        page_table: &mut T,
+        vpn: VirtPageNum,
+        access: MapPermission,
+    ) -> bool {
+        if self.map_type != MapType::Framed || !self.map_perm.contains(access) {
+            return false;
+        }
+        if !self.data_frames.contains_key(&vpn) {
+            let mapped = page_table.translate(vpn).is_some_and(|pte| pte.is_valid());
+            if self.zero_fill && access != MapPermission::W && !self.is_swapped(vpn) {
+                // 已经映射了零页时不是本区域能处理的缺页
+                if mapped {
+                    return false;
+                }
+                self.map_zero(page_table, vpn);
+                return true;
+            }
+            let frame = match frame_alloc() {
+                Some(frame) => frame,
+                None => return false,
+            };
+            #[cfg(feature = "swap")]
+            if let Some(slot) = self.swapped.remove(&vpn) {
+                swap::swap_in(&slot, frame.ppn);
+            }
+            // 替换零页的映射
+            if mapped {
+                page_table.unmap(vpn);
+            }
+            page_table.map(vpn, frame.ppn, self.map_perm, PageSize::Small);
+            self.data_frames.insert(vpn, Arc::new(frame));
+            return true;
+        }
+        if access == MapPermission::W && self.lazy_free.remove(&vpn) {
+            self.remap_one(page_table, vpn, self.map_perm);
+            return true;
+        }
+        false
+    }
+
+    /// `vpn` 开始的 2MB 是否能以大页映射：按 2MB 对齐且完整落在区域内
+    fn huge_chunk_at(&self, vpn: VirtPageNum) -> bool {
+        self.huge
+            && vpn.0 % HUGE_PAGE_PAGES == 0
+            && vpn.0 + HUGE_PAGE_PAGES <= self.vpn_range.get_end().0
+    }
+
+    /// 以一个大页映射从 `vpn` 开始的 2MB，Framed 区域分配不到对齐的连续页帧时返回 `false`
+    fn map_huge<T: PageTable>(&mut self, page_table: &mut T, vpn: VirtPageNum) -> bool {
+        let ppn = match self.map_type {
+            MapType::Direct => direct_ppn(vpn),
+            MapType::Framed => {
+                let frames = match frame_alloc_huge() {
+                    Some(frames) => frames,
+                    None => return false,
+                };
+                let ppn = frames[0].ppn;
+                for (i, frame) in frames.into_iter().enumerate() {
+                    self.data_frames
+                        .insert(VirtPageNum(vpn.0 + i), Arc::new(frame));
+                }
+                ppn
+            }
+            _ => return false,
+        };
+        page_table.map(vpn, ppn, self.map_perm, PageSize::Huge);
+        true
     }
 
-    /// 映射整个 MapArea
+    /// 映射整个 MapArea；不可访问的区域（`PROT_NONE`）不建立页表项
     pub fn map<T: PageTable>(&mut self, page_table: &mut T) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+        if !self
+            .map_perm
+            .intersects(MapPermission::R | MapPermission::W | MapPermission::X)
+        {
+            return;
+        }
+        let end = self.vpn_range.get_end();
+        let mut vpn = self.vpn_range.get_start();
+        while vpn < end {
+            if self.huge_chunk_at(vpn) && self.map_huge(page_table, vpn) {
+                vpn = VirtPageNum(vpn.0 + HUGE_PAGE_PAGES);
+                continue;
+            }
+            if self.zero_fill && self.map_type == MapType::Framed {
+                self.map_zero(page_table, vpn);
+            } else {
+                self.map_one(page_table, vpn);
+            }
+            vpn.step();
         }
     }
 
-    /// 解除整个 MapArea 映射
+    /// 解除整个 MapArea 映射，大页整体解除，不经过拆分
     pub fn unmap<T: PageTable>(&mut self, page_table: &mut T) {
         for vpn in self.vpn_range {
+            if self.huge_chunk_at(vpn) {
+                page_table.unmap_huge(vpn);
+            }
             self.unmap_one(page_table, vpn);
         }
     }
@@ -727,21 +1510,31 @@ impl MapArea {
     }
 }
 
+/// 直接映射区中虚拟页对应的物理页
+fn direct_ppn(vpn: VirtPageNum) -> PhysPageNum {
+    let va: usize = VirtAddr::from(vpn).into();
+    PhysAddr::from(virt_to_phys(va).unwrap()).floor()
+}
+
 /// 页映射类型
 ///
-/// `Identical`：虚拟页号与物理页号相同映射
+/// `Direct`：内核直接映射，虚拟地址 = 物理地址 + `KERNEL_OFFSET`
 ///
 /// `Framed`：为每个虚拟页分配独立物理页帧
 ///
 /// `Linear(offset)`：线性映射，物理页号 = 虚拟页号 + offset
+///
+/// `Shared`：使用外部提供的页帧，可被多个地址空间同时映射
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MapType {
-    /// vpn == ppn
-    Identical,
+    /// va == pa + KERNEL_OFFSET
+    Direct,
     /// 每个页分配独立帧
     Framed,
     /// 映射关系为线性偏移， ppn = vpn + offset
     Linear(isize),
+    /// 页帧由外部（如共享内存段）提供，fork 时共享而不复制
+    Shared,
 }
 
 bitflags! {
@@ -0,0 +1,99 @@
+//! # mmap 区域分配
+//!
+//! ## Overview
+//! 每个用户地址空间在 `[MMAP_BASE, MMAP_TOP)` 中为没有指定地址的 `mmap` 与共享内存挂接分配虚拟地址，
+//! 与 `brk` 管理的堆互不干扰：
+//! - `alloc`：首次适配，从低地址开始找到第一个足够大的空闲段
+//! - `reserve`：把调用者指定的范围从空闲段中扣除（指定地址的 `mmap`）
+//! - `release`：`munmap` 后归还范围并与相邻空闲段合并，之后可以再次分配
+//!
+//! ## Design
+//! - 空闲段保存在按起始地址排序的 `BTreeMap<起始地址, 结束地址>` 中
+//! - 不小于大页的请求按 2MB 对齐，以便匿名映射使用大页，对齐留下的空隙仍是空闲段
+//!
+//! ## Invariants
+//! - 空闲段按页对齐、互不重叠且互不相邻，都落在 `[MMAP_BASE, MMAP_TOP)` 中
+//! - 范围之外的地址不受本模块管理，`reserve` / `release` 忽略超出的部分
+
+use super::address::align_up;
+use crate::hal::{MMAP_BASE, MMAP_TOP, PAGE_SIZE};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// 一个地址空间的 mmap 区域空闲段
+#[derive(Clone)]
+pub struct MmapRegions {
+    /// 空闲段：起始地址 -> 结束地址
+    free: BTreeMap<usize, usize>,
+}
+
+impl MmapRegions {
+    /// 整个 mmap 区域都空闲
+    pub fn new() -> Self {
+        let mut free = BTreeMap::new();
+        free.insert(MMAP_BASE, MMAP_TOP);
+        Self { free }
+    }
+
+    /// 分配 `len` 字节（按页向上取整），起始地址按 `align` 对齐，空间不足时返回 `None`
+    pub fn alloc(&mut self, len: usize, align: usize) -> Option<usize> {
+        let len = align_up(len, PAGE_SIZE);
+        let (start, end, addr) = self.free.iter().find_map(|(&start, &end)| {
+            let addr = align_up(start, align);
+            (addr.checked_add(len)? <= end).then_some((start, end, addr))
+        })?;
+        self.free.remove(&start);
+        if start < addr {
+            self.free.insert(start, addr);
+        }
+        if addr + len < end {
+            self.free.insert(addr + len, end);
+        }
+        Some(addr)
+    }
+
+    /// 把 `[start, end)` 标记为已使用
+    pub fn reserve(&mut self, start: usize, end: usize) {
+        let (start, end) = (start.max(MMAP_BASE), end.min(MMAP_TOP));
+        if start >= end {
+            return;
+        }
+        // 空闲段按地址排序且互不重叠，从 end 往前找到的第一个不相交的段之前都不会相交
+        let overlapping: Vec<(usize, usize)> = self
+            .free
+            .range(..end)
+            .rev()
+            .take_while(|&(_, &e)| e > start)
+            .map(|(&s, &e)| (s, e))
+            .collect();
+        for (s, e) in overlapping {
+            self.free.remove(&s);
+            if s < start {
+                self.free.insert(s, start);
+            }
+            if end < e {
+                self.free.insert(end, e);
+            }
+        }
+    }
+
+    /// 归还 `[start, end)`，与相邻的空闲段合并
+    pub fn release(&mut self, start: usize, end: usize) {
+        let (mut start, mut end) = (start.max(MMAP_BASE), end.min(MMAP_TOP));
+        if start >= end {
+            return;
+        }
+        // 先扣除其中已空闲的部分，保证插入后不与已有空闲段重叠
+        self.reserve(start, end);
+        if let Some((&s, &e)) = self.free.range(..start).next_back() {
+            if e == start {
+                self.free.remove(&s);
+                start = s;
+            }
+        }
+        if let Some(e) = self.free.remove(&end) {
+            end = e;
+        }
+        self.free.insert(start, end);
+    }
+}
@@ -1,21 +1,40 @@
 pub mod address;
+mod asid;
 mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
+mod mmap_region;
 mod pagetable;
+pub mod shm;
+#[cfg(feature = "swap")]
+pub mod swap;
 
 /// 初始化内存管理子系统
-/// 包括堆内存分配器、物理页帧分配器和内核虚拟地址空间的建立与激活
+/// 包括堆内存分配器、物理页帧分配器和内核虚拟地址空间的建立与激活，
+/// 启用分页后再探测 ASID 位数
 pub fn init() {
     heap_allocator::init_heap();
     frame_allocator::init_frame_allocator();
     KERNEL_SPACE.exclusive_access().activate();
+    asid::init(crate::hal::asid_bits());
 }
 
-pub use crate::mm::memory_set::{kernel_token, MapFlags, MapPermission, MemorySet, KERNEL_SPACE};
-pub use address::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
-pub use frame_allocator::{frame_alloc, frame_alloc_more, frame_dealloc, FrameTracker};
+#[cfg(feature = "swap")]
+pub use crate::mm::memory_set::SWAP_CLUSTER;
+pub use crate::mm::memory_set::{
+    kernel_token, zero_page_ppn, MapFlags, MapPermission, MemorySet, Vma, KERNEL_SPACE,
+};
+pub use address::{
+    phys_to_virt, virt_to_phys, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum,
+};
+pub use asid::{asid_alloc, Asid};
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_huge, frame_alloc_more, frame_dealloc, frame_free_count,
+    frame_total_count, register_shrinker, FrameTracker,
+};
+pub use heap_allocator::heap_usage;
 pub use pagetable::{
     copy_to_user, get_from_user, translated_byte_buffer, translated_ref, translated_refmut,
-    translated_str, PageTable, UserBuffer,
+    translated_str, try_read_bytes, try_write_bytes, PageSize, PageTable, UserBuffer,
+    HUGE_PAGE_PAGES, HUGE_PAGE_SIZE,
 };
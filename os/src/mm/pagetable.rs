@@ -11,26 +11,47 @@
 //! - **不连续性映射**：`UserBuffer` 结构体通过分段切片（`Vec<&mut [u8]>`）解决了用户虚拟空间连续但物理空间不连续的问题。
 //!
 //! # Assumptions
-//! 1. **恒等映射/直接映射**：假设内核已经将所有物理内存或目标物理页帧映射到了内核虚拟地址空间，
-//!    使得 `PhysAddr.get_mut()` 等方法能有效获取内核可直接操作的引用。
+//! 1. **别名地址**：内核运行时使用内核页表，用户页帧只能经由 `PhysPageNum::kernel_alias` 给出的别名地址
+//!    （RISC-V 的直接映射区、LoongArch 的 DMW 窗口）访问，`translated_*` 返回的引用与切片都指向别名地址。
 //! 2. **Token 有效性**：传入的 `token`（如 SATP 寄存器值）必须指向一个结构完整且有效的多级页表。
 //!
 //! # Safety
 //! - **生命周期安全**：返回的 `&'static mut T` 实际上是基于内核对物理页帧的临时访问。在实际使用中，
 //!   开发者必须确保在持有该引用期间，对应的物理页不会被释放或重新分配（虽然标注为 `'static` 以绕过借用检查）。
-//! - **手动验证**：模块函数通过 `Option` 处理翻译失败的情况，防止因用户传入非法地址导致内核触发异常（Panic）。
+//! - **手动验证**：模块函数通过 `Result` 处理翻译失败的情况，防止因用户传入非法地址导致内核触发异常（Panic）。
+//! - **权限检查**：`translated_*` 与 `copy_*_user` 只访问用户态可以访问（`U`）的页，写入时要求页可写，
+//!   读取时要求页可读，否则返回 EFAULT；用户传入内核地址或只读映射不能借系统调用被读写。
 //!
 //! # Invariants
 //! - **页对齐独立性**：`translated_byte_buffer` 必须保证无论用户地址是否页对齐，都能正确计算跨页边界，
 //!   并生成覆盖完整请求长度的切片序列。
 //! - **单向依赖**：该模块仅依赖底层的 `hal` 和 `mm` 模块，不应产生向上依赖，以维持内核分层结构。
 
-use crate::hal::{PageTableEntryImpl, PageTableImpl};
-use crate::mm::{MapPermission, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::hal::{PageTableEntryImpl, PageTableImpl, PAGE_SIZE, PTES_PER_TABLE};
+use crate::mm::{
+    zero_page_ppn, MapPermission, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum,
+};
 use alloc::string::String;
 use alloc::vec::Vec;
 
+/// 一个 2MB 大页包含的 4KB 页数，即一个页表页中的页表项数
+pub const HUGE_PAGE_PAGES: usize = PTES_PER_TABLE;
+/// 大页大小（2MB）
+pub const HUGE_PAGE_SIZE: usize = HUGE_PAGE_PAGES * PAGE_SIZE;
+
+/// 叶子页表项映射的页大小
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PageSize {
+    /// 4KB 普通页
+    Small,
+    /// 2MB 大页，由倒数第二级页表项直接映射，虚拟页号与物理页号都需按 `HUGE_PAGE_PAGES` 对齐
+    Huge,
+}
+
 /// 页表接口抽象：定义了硬件分页系统的核心操作，强制要求实现体系结构相关的转换逻辑。
+///
+/// 大页只是映射方式的优化：`translate` 对大页中的任意 `vpn` 返回该 4KB 页对应的页表项，
+/// 按 4KB 修改大页中的某一页（`unmap`、`find_pte_create`）时先把大页拆分为 512 个普通页
 pub trait PageTable {
     fn new() -> Self;
 
@@ -42,81 +63,134 @@ pub trait PageTable {
 
     fn find_pte(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntryImpl>;
 
-    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission);
+    fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: MapPermission, size: PageSize);
 
     fn unmap(&mut self, vpn: VirtPageNum);
 
+    /// 若 `vpn` 所在的 2MB 区域以大页映射，整体解除映射并返回 `true`
+    fn unmap_huge(&mut self, vpn: VirtPageNum) -> bool;
+
     fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntryImpl>;
 
     fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr>;
 
+    /// 使 `vpn` 在本页表地址空间中的 TLB 项失效，`map` / `unmap` 已自行调用，
+    /// 直接修改 `find_pte` 返回的页表项后需要手动调用
+    fn flush_tlb(&self, vpn: VirtPageNum);
+
     fn activate(&self);
 
     fn token(&self) -> usize;
 }
 
-/// 将用户缓冲区翻译为内核切片集合
+/// 翻译用户页 `vpn`，页不在内存中（如被 `MADV_DONTNEED` 丢弃）时先按缺页处理
+///
+/// 只接受用户态可以访问（`U`）的页，`write` 为真时还要求可写，否则要求可读；
+/// 未映射、属于内核（如跳板页）或权限不足时返回 EFAULT，与 Linux 的 `copy_*_user` 一致。
+/// 共享零页经缺页处理后才可写，地址空间正被借用时缺页处理不会进行，
+/// 对零页的写入同样返回 EFAULT，不会写穿共享的页帧。
+///
+/// `write` 为真时还会撤销该页的 `MADV_FREE` 标记：内核经由物理页写入不受页表权限约束，
+/// 必须让回收路径知道该页已被修改
+fn translate_user_page(
+    page_table: &PageTableImpl,
+    vpn: VirtPageNum,
+    write: bool,
+) -> Result<PhysPageNum, isize> {
+    let usable = |pte: &PageTableEntryImpl| {
+        let allowed = if write {
+            pte.writable()
+        } else {
+            pte.readable()
+        };
+        pte.is_valid() && pte.is_user() && allowed
+    };
+    if let Some(pte) = page_table.translate(vpn).filter(usable) {
+        return Ok(pte.ppn());
+    }
+    let access = if write {
+        MapPermission::W
+    } else {
+        MapPermission::R
+    };
+    crate::task::current_handle_page_fault(page_table.token(), VirtAddr::from(vpn).into(), access);
+    match page_table.translate(vpn).filter(usable) {
+        Some(pte) => Ok(pte.ppn()),
+        None => Err(-1), // EFAULT
+    }
+}
+
+/// 翻译用户地址 `va`，返回内核访问同一物理位置所用的别名地址，参见 `translate_user_page`
+fn translate_user_va(page_table: &PageTableImpl, va: usize, write: bool) -> Result<usize, isize> {
+    let va = VirtAddr::from(va);
+    let ppn = translate_user_page(page_table, va.floor(), write)?;
+    Ok(ppn.kernel_alias() + va.page_offset())
+}
+
+/// 将用户缓冲区翻译为内核切片集合，`write` 为真表示内核将写入该缓冲区
+///
+/// 缓冲区中任一页不可访问时返回 EFAULT，参见 `translate_user_page`
 ///
 /// ## Safety
 /// 必须确保 `token` 对应的进程在当前操作完成前不会被销毁。
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+pub fn translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+    write: bool,
+) -> Result<Vec<&'static mut [u8]>, isize> {
     let page_table: PageTableImpl = PageTable::from_token(token);
     let mut start = ptr as usize;
-    let end = start + len;
+    let Some(end) = start.checked_add(len) else {
+        return Err(-1); // EFAULT
+    };
     let mut v = Vec::new();
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.floor();
-        let ppn = page_table.translate(vpn).unwrap().ppn();
+        let ppn = translate_user_page(&page_table, vpn, write)?;
         vpn.step();
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end));
-        if end_va.page_offset() == 0 {
-            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
-        } else {
-            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
-        }
+        let len = usize::from(end_va) - usize::from(start_va);
+        let alias = ppn.kernel_alias() + start_va.page_offset();
+        v.push(unsafe { core::slice::from_raw_parts_mut(alias as *mut u8, len) });
         start = end_va.into();
     }
-    v
+    Ok(v)
 }
 
-/// 从用户空间读取以 `\0` 结尾的字符串并拷贝到内核空间的 String 中
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
+/// 从用户空间读取以 `\0` 结尾的字符串并拷贝到内核空间的 String 中，
+/// 在结尾之前遇到不可读的页时返回 EFAULT
+pub fn translated_str(token: usize, ptr: *const u8) -> Result<String, isize> {
     let page_table: PageTableImpl = PageTable::from_token(token);
     let mut string = String::new();
     let mut va = ptr as usize;
     loop {
-        let ch: u8 = *(page_table
-            .translate_va(VirtAddr::from(va))
-            .unwrap()
-            .get_mut());
+        let ch = unsafe { *(translate_user_va(&page_table, va, false)? as *const u8) };
         if ch == 0 {
             break;
         }
         string.push(ch as char);
         va += 1;
     }
-    string
+    Ok(string)
 }
 
-/// 将用户空间的指针翻译为地址空间中对相同物理位置的不可变引用
-pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+/// 将用户空间的指针翻译为地址空间中对相同物理位置的不可变引用，页不可读时返回 EFAULT
+///
+/// `T` 不能跨越页边界，跨页的值使用 `copy_from_user`
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> Result<&'static T, isize> {
     let page_table: PageTableImpl = PageTable::from_token(token);
-    page_table
-        .translate_va(VirtAddr::from(ptr as usize))
-        .unwrap()
-        .get_ref()
+    Ok(unsafe { &*(translate_user_va(&page_table, ptr as usize, false)? as *const T) })
 }
 
-/// 将用户空间的指针翻译为地址空间中对相同物理位置的可变引用
-pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+/// 将用户空间的指针翻译为地址空间中对相同物理位置的可变引用，页不可写时返回 EFAULT
+///
+/// `T` 不能跨越页边界，跨页的值使用 `copy_to_user`
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> Result<&'static mut T, isize> {
     let page_table: PageTableImpl = PageTable::from_token(token);
-    let va = ptr as usize;
-    page_table
-        .translate_va(VirtAddr::from(va))
-        .unwrap()
-        .get_mut()
+    Ok(unsafe { &mut *(translate_user_va(&page_table, ptr as usize, true)? as *mut T) })
 }
 
 /// 用户缓冲区容器
@@ -304,6 +378,7 @@ impl Iterator for UserBufferIterator {
 
 /// Copy `*src: T` to user space.
 /// `src` is a pointer in kernel space, `dst` is a pointer in user space.
+/// Returns EFAULT if `dst` is not writable by the user.
 pub fn copy_to_user<T: 'static + Copy>(
     token: usize,
     src: *const T,
@@ -313,10 +388,10 @@ pub fn copy_to_user<T: 'static + Copy>(
     // A nice predicate. Well done!
     // Re: Thanks!
     if VirtAddr::from(dst as usize).floor() == VirtAddr::from(dst as usize + size - 1).floor() {
-        unsafe { core::ptr::copy_nonoverlapping(src, translated_refmut(token, dst), 1) };
+        unsafe { core::ptr::copy_nonoverlapping(src, translated_refmut(token, dst)?, 1) };
     // use UserBuffer to write across user space pages
     } else {
-        UserBuffer::new(translated_byte_buffer(token, dst as *mut u8, size))
+        UserBuffer::new(translated_byte_buffer(token, dst as *mut u8, size, true)?)
             .write_buffer(None, unsafe {
                 core::slice::from_raw_parts(src as *const u8, size)
             });
@@ -331,20 +406,66 @@ pub fn copy_from_user<T: 'static + Copy>(
     let size = core::mem::size_of::<T>();
     // if all data of `*src` is in the same page, read directly
     if VirtAddr::from(src as usize).floor() == VirtAddr::from(src as usize + size - 1).floor() {
-        unsafe { core::ptr::copy_nonoverlapping(translated_ref(token, src), dst, 1) };
+        unsafe { core::ptr::copy_nonoverlapping(translated_ref(token, src)?, dst, 1) };
     // or we should use UserBuffer to read across user space pages
     } else {
-        UserBuffer::new(translated_byte_buffer(token, src as *const u8, size)).read(None, unsafe {
+        UserBuffer::new(translated_byte_buffer(
+            token,
+            src as *const u8,
+            size,
+            false,
+        )?)
+        .read(None, unsafe {
             core::slice::from_raw_parts_mut(dst as *mut u8, size)
         });
     }
     Ok(())
 }
-#[inline(always)]
-pub fn get_from_user<T: 'static + Copy>(token: usize, src: *const T) -> T {
-    unsafe {
-        let mut dst: T = core::mem::MaybeUninit::uninit().assume_init();
-        copy_from_user(token, src, &mut dst);
-        return dst;
+/// 把 `token` 地址空间中从 `va` 开始的 `buf.len()` 字节读入 `buf`
+///
+/// 与 `translated_*` 不同，不检查读权限，也不处理缺页，遇到未映射或用户态不可访问的页时返回 `None`，
+/// 用于访问另一个进程（如被跟踪进程）中不可信的地址
+pub fn try_read_bytes(token: usize, va: usize, buf: &mut [u8]) -> Option<()> {
+    let page_table: PageTableImpl = PageTable::from_token(token);
+    let mut done = 0;
+    while done < buf.len() {
+        let cur = VirtAddr::from(va.checked_add(done)?);
+        let pte = page_table
+            .translate(cur.floor())
+            .filter(|pte| pte.is_valid() && pte.is_user())?;
+        let bytes = pte.ppn().get_bytes_array();
+        let n = (PAGE_SIZE - cur.page_offset()).min(buf.len() - done);
+        buf[done..done + n].copy_from_slice(&bytes[cur.page_offset()..cur.page_offset() + n]);
+        done += n;
     }
+    Some(())
+}
+
+/// 把 `data` 写入 `token` 地址空间中从 `va` 开始的位置，
+/// 遇到未映射的页、用户态不可访问的页（如跳板页）或共享零页时返回 `None`
+///
+/// 写入经由物理页完成，不受用户页表读写权限位（如代码段只读）的限制；
+/// 中途失败时已写入的部分不会回滚
+pub fn try_write_bytes(token: usize, va: usize, data: &[u8]) -> Option<()> {
+    let page_table: PageTableImpl = PageTable::from_token(token);
+    let mut done = 0;
+    while done < data.len() {
+        let cur = VirtAddr::from(va.checked_add(done)?);
+        let pte = page_table
+            .translate(cur.floor())
+            .filter(|pte| pte.is_valid() && pte.is_user() && pte.ppn() != zero_page_ppn())?;
+        let bytes = pte.ppn().get_bytes_array();
+        let n = (PAGE_SIZE - cur.page_offset()).min(data.len() - done);
+        bytes[cur.page_offset()..cur.page_offset() + n].copy_from_slice(&data[done..done + n]);
+        done += n;
+    }
+    Some(())
+}
+
+/// 从用户空间读取一个 `T`，`src` 不可读时返回 EFAULT
+#[inline(always)]
+pub fn get_from_user<T: 'static + Copy>(token: usize, src: *const T) -> Result<T, isize> {
+    let mut dst = core::mem::MaybeUninit::<T>::uninit();
+    copy_from_user(token, src, dst.as_mut_ptr())?;
+    Ok(unsafe { dst.assume_init() })
 }
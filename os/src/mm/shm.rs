@@ -0,0 +1,166 @@
+//! System V 共享内存管理模块。
+//!
+//! 本模块维护全局的共享内存段表，为 `shmget` / `shmat` / `shmdt` / `shmctl`
+//! 系统调用提供底层支持。
+//!
+//! # Overview
+//! - 每个共享内存段由一组物理页帧组成，以 `shmid` 为索引
+//! - 段的页帧以 `Arc<FrameTracker>` 持有，映射到用户地址空间时
+//!   以 `MapType::Shared` 的 `MapArea` 共享同一组页帧
+//! - 段的挂接计数（`shm_nattch`）直接由页帧的引用计数得出
+//!
+//! # Behavior
+//! - `IPC_RMID` 只将段从表中移除，已挂接的映射仍可继续使用，
+//!   页帧在最后一次 `shmdt`（或进程退出）后自动回收
+//!
+//! # Invariants
+//! - 表中每个段的页帧数量在创建后不再改变
+//! - 同一 key（除 `IPC_PRIVATE`）最多对应一个段
+
+use crate::hal::PAGE_SIZE;
+use crate::mm::{frame_alloc, FrameTracker};
+use crate::sync::UPIntrFreeCell;
+use crate::timer::get_time_ms;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 私有段，总是创建新段
+pub const IPC_PRIVATE: usize = 0;
+/// 不存在时创建
+pub const IPC_CREAT: u32 = 0o1000;
+/// 与 IPC_CREAT 同用，段已存在时失败
+pub const IPC_EXCL: u32 = 0o2000;
+/// shmctl: 删除段
+pub const IPC_RMID: usize = 0;
+/// shmctl: 设置段属性
+pub const IPC_SET: usize = 1;
+/// shmctl: 获取段属性
+pub const IPC_STAT: usize = 2;
+/// shmat: 只读挂接
+pub const SHM_RDONLY: u32 = 0o10000;
+/// shmat: 将地址向下对齐到 SHMLBA
+pub const SHM_RND: u32 = 0o20000;
+
+/// 共享内存段的权限信息（对应 Linux `struct ipc64_perm`）
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct IpcPerm {
+    pub key: i32,
+    pub uid: u32,
+    pub gid: u32,
+    pub cuid: u32,
+    pub cgid: u32,
+    pub mode: u32,
+    pub seq: u16,
+    pub __pad2: u16,
+    pub __unused1: usize,
+    pub __unused2: usize,
+}
+
+/// 共享内存段描述（对应 Linux `struct shmid64_ds`）
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ShmIdDs {
+    pub shm_perm: IpcPerm,
+    pub shm_segsz: usize,
+    pub shm_atime: isize,
+    pub shm_dtime: isize,
+    pub shm_ctime: isize,
+    pub shm_cpid: i32,
+    pub shm_lpid: i32,
+    pub shm_nattch: usize,
+    pub __unused4: usize,
+    pub __unused5: usize,
+}
+
+/// 单个共享内存段
+pub struct ShmSegment {
+    /// 段属性
+    pub ds: ShmIdDs,
+    /// 段的物理页帧
+    pub frames: Vec<Arc<FrameTracker>>,
+}
+
+impl ShmSegment {
+    /// 当前挂接数：去掉段表自身持有的一份引用
+    pub fn nattch(&self) -> usize {
+        self.frames
+            .first()
+            .map(|frame| Arc::strong_count(frame) - 1)
+            .unwrap_or(0)
+    }
+}
+
+/// 共享内存段表
+pub struct ShmManager {
+    /// shmid -> 段
+    segments: BTreeMap<usize, ShmSegment>,
+    /// 下一个可用的 shmid
+    next_id: usize,
+}
+
+impl ShmManager {
+    pub fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// 按 key 查找段，返回 shmid
+    pub fn find_by_key(&self, key: usize) -> Option<usize> {
+        if key == IPC_PRIVATE {
+            return None;
+        }
+        self.segments
+            .iter()
+            .find(|(_, seg)| seg.ds.shm_perm.key == key as i32)
+            .map(|(id, _)| *id)
+    }
+
+    /// 创建新段，页帧全部清零
+    pub fn create(&mut self, key: usize, size: usize, mode: u32, pid: usize) -> Option<usize> {
+        let pages = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut frames = Vec::with_capacity(pages);
+        for _ in 0..pages {
+            frames.push(Arc::new(frame_alloc()?));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let ds = ShmIdDs {
+            shm_perm: IpcPerm {
+                key: key as i32,
+                mode: mode & 0o777,
+                seq: id as u16,
+                ..IpcPerm::default()
+            },
+            shm_segsz: size,
+            shm_cpid: pid as i32,
+            shm_ctime: (get_time_ms() / 1000) as isize,
+            ..ShmIdDs::default()
+        };
+        self.segments.insert(id, ShmSegment { ds, frames });
+        Some(id)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&ShmSegment> {
+        self.segments.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut ShmSegment> {
+        self.segments.get_mut(&id)
+    }
+
+    /// 从段表移除，已挂接的映射仍持有页帧
+    pub fn remove(&mut self, id: usize) -> Option<ShmSegment> {
+        self.segments.remove(&id)
+    }
+}
+
+lazy_static! {
+    /// 全局共享内存段表
+    pub static ref SHM_MANAGER: UPIntrFreeCell<ShmManager> =
+        unsafe { UPIntrFreeCell::new(ShmManager::new()) };
+}
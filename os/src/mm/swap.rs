@@ -0,0 +1,177 @@
+//! # 交换区（swap）
+//!
+//! ## Overview
+//! 把匿名页换出到交换设备（`drivers::SWAP_DEVICE`），使物理内存较小时仍能运行占用内存较多的程序：
+//! - 交换区按页划分为槽，`SwapSlot` 以 RAII 方式持有一个槽，被 drop 时槽回到空闲列表
+//! - `swap_out` 把一页写入新分配的槽，`swap_in` 把槽中的内容读回页帧
+//! - 选择换出哪些页、页表项如何记录换出状态见 `MemorySet::swap_out`
+//!
+//! ## Assumptions
+//! - 交换设备需用 `mkswap` 格式化：首页末尾为 `SWAPSPACE2` 签名，头部给出最后一页的页号与坏页表；
+//!   没有签名时不启用交换，避免覆盖设备上的其他数据
+//! - 首页（交换区头部）与坏页不会被分配
+//! - 交换区内容不跨重启保留，启动时所有槽都视为空闲
+//! - 换出与换入都同步读写块设备
+//!
+//! ## Invariants
+//! - 一个槽在分配后只写入一次，之后只读；共享同一个槽的多个 `SwapSlot` 持有者（`Arc`）看到相同内容
+
+use crate::drivers::{BlockDevice, SWAP_DEVICE};
+use crate::hal::{BLOCK_SZ, PAGE_SIZE};
+use crate::mm::PhysPageNum;
+use crate::sync::UPIntrFreeCell;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 每页占用的块数
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+/// 交换区头部的签名，位于首页末尾
+const SWAP_MAGIC: &[u8; 10] = b"SWAPSPACE2";
+/// 头部中 `last_page` 与 `nr_badpages` 字段的偏移
+const LAST_PAGE_OFFSET: usize = 1028;
+const NR_BADPAGES_OFFSET: usize = 1032;
+/// 坏页表的偏移
+const BADPAGES_OFFSET: usize = 1536;
+
+/// 交换区中的一个槽，drop 时归还
+pub struct SwapSlot(usize);
+
+impl SwapSlot {
+    /// 槽号，即槽在交换区中的页号
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        if let Some(area) = SWAP_AREA.exclusive_access().as_mut() {
+            area.dealloc(self.0);
+        }
+    }
+}
+
+/// 交换区的槽分配器，与 `StackFrameAllocator` 相同：顺序分配，回收的槽优先复用
+struct SwapArea {
+    device: Arc<dyn BlockDevice>,
+    /// 下一个从未分配过的槽
+    current: usize,
+    /// 可用槽的上界（不包含）
+    end: usize,
+    /// 已回收、可再次分配的槽
+    recycled: Vec<usize>,
+    /// 坏页，永不分配
+    bad: Vec<usize>,
+}
+
+impl SwapArea {
+    fn alloc(&mut self) -> Option<usize> {
+        if let Some(slot) = self.recycled.pop() {
+            return Some(slot);
+        }
+        while self.current < self.end {
+            self.current += 1;
+            if !self.bad.contains(&(self.current - 1)) {
+                return Some(self.current - 1);
+            }
+        }
+        None
+    }
+
+    /// 可用槽的总数与空闲数，不含头部与坏页
+    fn usage(&self) -> (usize, usize) {
+        let bad_in = |range: core::ops::Range<usize>| {
+            self.bad.iter().filter(|slot| range.contains(slot)).count()
+        };
+        let total = self.end - 1 - bad_in(1..self.end);
+        let free = self.end - self.current - bad_in(self.current..self.end) + self.recycled.len();
+        (total, free)
+    }
+
+    fn dealloc(&mut self, slot: usize) {
+        assert!(
+            slot < self.current && !self.recycled.contains(&slot),
+            "swap slot {} has not been allocated",
+            slot
+        );
+        self.recycled.push(slot);
+    }
+}
+
+lazy_static! {
+    /// 交换区，未找到可用的交换设备时为 `None`
+    static ref SWAP_AREA: UPIntrFreeCell<Option<SwapArea>> =
+        unsafe { UPIntrFreeCell::new(None) };
+}
+
+fn le_u32(buf: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+/// 读取交换设备的头部并启用交换区
+pub fn init() {
+    let device = match SWAP_DEVICE.as_ref() {
+        Some(device) => device.clone(),
+        None => {
+            println!("[kernel] no swap device found, swapping disabled");
+            return;
+        }
+    };
+    let mut header = vec![0u8; PAGE_SIZE];
+    for (i, chunk) in header.chunks_mut(BLOCK_SZ).enumerate() {
+        device.read_block(i, chunk);
+    }
+    if &header[PAGE_SIZE - SWAP_MAGIC.len()..] != SWAP_MAGIC {
+        println!("[kernel] swap device has no SWAPSPACE2 signature, swapping disabled");
+        return;
+    }
+    let last_page = le_u32(&header, LAST_PAGE_OFFSET);
+    let max_bad = (PAGE_SIZE - SWAP_MAGIC.len() - BADPAGES_OFFSET) / 4;
+    let bad = (0..le_u32(&header, NR_BADPAGES_OFFSET).min(max_bad))
+        .map(|i| le_u32(&header, BADPAGES_OFFSET + i * 4))
+        .collect();
+    println!("[kernel] swap: {} pages", last_page);
+    *SWAP_AREA.exclusive_access() = Some(SwapArea {
+        device,
+        current: 1,
+        end: last_page + 1,
+        recycled: Vec::new(),
+        bad,
+    });
+}
+
+/// 把页帧 `ppn` 的内容写入一个新分配的槽，交换区不可用或已满时返回 `None`
+pub fn swap_out(ppn: PhysPageNum) -> Option<SwapSlot> {
+    let (device, slot) = {
+        let mut swap = SWAP_AREA.exclusive_access();
+        let area = swap.as_mut()?;
+        (area.device.clone(), SwapSlot(area.alloc()?))
+    };
+    for (i, chunk) in ppn.get_bytes_array().chunks(BLOCK_SZ).enumerate() {
+        device.write_block(slot.0 * BLOCKS_PER_PAGE + i, chunk);
+    }
+    Some(slot)
+}
+
+/// 交换区的总页数与空闲页数，交换区不可用时均为 0
+pub fn swap_usage() -> (usize, usize) {
+    SWAP_AREA
+        .exclusive_access()
+        .as_ref()
+        .map_or((0, 0), SwapArea::usage)
+}
+
+/// 把槽 `slot` 中的内容读入页帧 `ppn`
+pub fn swap_in(slot: &SwapSlot, ppn: PhysPageNum) {
+    let device = SWAP_AREA
+        .exclusive_access()
+        .as_ref()
+        .unwrap()
+        .device
+        .clone();
+    for (i, chunk) in ppn.get_bytes_array().chunks_mut(BLOCK_SZ).enumerate() {
+        device.read_block(slot.0 * BLOCKS_PER_PAGE + i, chunk);
+    }
+}
@@ -0,0 +1,345 @@
+//! # AF_INET 套接字
+//!
+//! ## Overview
+//! `InetSocket` 是回环协议栈上的套接字 fd 对象，实现了 `File` trait：
+//! - UDP：每个端点维护一个数据报接收队列，`sendto` 直接投递到目标端口的队列
+//! - TCP：`connect` 为双方建立两条方向相反的管道，之后的读写语义与 `Pipe` 一致
+//!
+//! ## Invariants
+//! - `local` 为 `Some` 当且仅当端点已在对应协议的端口表中登记
+//! - 数据报队列中的每个元素都是一次完整的发送，不会被拆分或合并
+
+use super::{Loopback, SockAddrIn, SOCK_DGRAM, TCP_PORTS, UDP_PORTS};
+use crate::fs::{alloc_ino, make_pipe, File, Pipe, UserStat, BLK_SIZE, SOCKFS_DEV, SOCK_STREAM};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::task::{signal_pending_of_current, suspend_current_and_run_next};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// 单个 UDP 接收队列最多缓存的数据报数量
+const UDP_QUEUE_LIMIT: usize = 64;
+
+/// TCP 端点状态
+enum TcpState {
+    Closed,
+    Listening {
+        backlog: usize,
+        pending: VecDeque<Arc<InetSocket>>,
+    },
+    Connected {
+        rx: Arc<Pipe>,
+        tx: Arc<Pipe>,
+    },
+}
+
+/// 套接字的可变状态，端口表中登记的正是这一部分
+pub(crate) struct InetInner {
+    /// 本地端口（主机字节序）
+    local: Option<u16>,
+    /// 对端端口（UDP connect 之后或 TCP 已连接）
+    peer: Option<u16>,
+    /// UDP：收到的数据报及其源端口
+    datagrams: VecDeque<(u16, Vec<u8>)>,
+    /// TCP：连接状态
+    tcp: TcpState,
+}
+
+pub struct InetSocket {
+    /// 是否为数据报套接字
+    dgram: bool,
+    inner: Arc<UPIntrFreeCell<InetInner>>,
+    nonblocking: UPIntrFreeCell<bool>,
+    ino: u64,
+}
+
+impl InetSocket {
+    pub fn new(ty: usize) -> Self {
+        Self {
+            dgram: ty == SOCK_DGRAM,
+            inner: Arc::new(unsafe {
+                UPIntrFreeCell::new(InetInner {
+                    local: None,
+                    peer: None,
+                    datagrams: VecDeque::new(),
+                    tcp: TcpState::Closed,
+                })
+            }),
+            nonblocking: unsafe { UPIntrFreeCell::new(false) },
+            ino: alloc_ino(),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nb: bool) {
+        *self.nonblocking.exclusive_access() = nb;
+        if let TcpState::Connected { rx, tx } = &self.inner.exclusive_access().tcp {
+            rx.set_nonblocking(nb);
+            tx.set_nonblocking(nb);
+        }
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        *self.nonblocking.exclusive_access()
+    }
+
+    fn ports(&self) -> &'static UPIntrFreeCell<Loopback<UPIntrFreeCell<InetInner>>> {
+        if self.dgram {
+            &UDP_PORTS
+        } else {
+            &TCP_PORTS
+        }
+    }
+
+    /// 绑定到本地端口，`port` 为 0 时分配临时端口
+    pub fn bind(&self, addr: &SockAddrIn) -> Result<(), isize> {
+        if !addr.is_local() {
+            return Err(-1); // EADDRNOTAVAIL
+        }
+        if self.inner.exclusive_access().local.is_some() {
+            return Err(-1); // EINVAL
+        }
+        let port = self
+            .ports()
+            .exclusive_access()
+            .bind(addr.port(), &self.inner)
+            .ok_or(-1isize)?; // EADDRINUSE
+        self.inner.exclusive_access().local = Some(port);
+        Ok(())
+    }
+
+    /// 未绑定时自动绑定一个临时端口
+    fn ensure_bound(&self) -> Result<u16, isize> {
+        if let Some(port) = self.inner.exclusive_access().local {
+            return Ok(port);
+        }
+        self.bind(&SockAddrIn::loopback(0))?;
+        Ok(self.inner.exclusive_access().local.unwrap())
+    }
+
+    pub fn local_addr(&self) -> SockAddrIn {
+        SockAddrIn::loopback(self.inner.exclusive_access().local.unwrap_or(0))
+    }
+
+    pub fn peer_addr(&self) -> Option<SockAddrIn> {
+        self.inner.exclusive_access().peer.map(SockAddrIn::loopback)
+    }
+
+    pub fn listen(&self, backlog: usize) -> Result<(), isize> {
+        if self.dgram {
+            return Err(-1); // EOPNOTSUPP
+        }
+        self.ensure_bound()?;
+        let mut inner = self.inner.exclusive_access();
+        if matches!(inner.tcp, TcpState::Connected { .. }) {
+            return Err(-1); // EINVAL
+        }
+        if matches!(inner.tcp, TcpState::Closed) {
+            inner.tcp = TcpState::Listening {
+                backlog: backlog.max(1),
+                pending: VecDeque::new(),
+            };
+        }
+        Ok(())
+    }
+
+    /// UDP：设置默认对端；TCP：与监听者建立连接
+    pub fn connect(&self, addr: &SockAddrIn) -> Result<(), isize> {
+        if !addr.is_local() {
+            return Err(-1); // ENETUNREACH
+        }
+        let local = self.ensure_bound()?;
+        let port = addr.port();
+        if self.dgram {
+            self.inner.exclusive_access().peer = Some(port);
+            return Ok(());
+        }
+        if !matches!(self.inner.exclusive_access().tcp, TcpState::Closed) {
+            return Err(-1); // EISCONN
+        }
+        let listener = TCP_PORTS.exclusive_access().lookup(port).ok_or(-1isize)?; // ECONNREFUSED
+        let (c_rx, s_tx) = make_pipe();
+        let (s_rx, c_tx) = make_pipe();
+        let server = InetSocket::new(SOCK_STREAM);
+        {
+            let mut server_inner = server.inner.exclusive_access();
+            // 服务端连接沿用监听端口，不单独登记
+            server_inner.local = None;
+            server_inner.peer = Some(local);
+            server_inner.tcp = TcpState::Connected { rx: s_rx, tx: s_tx };
+        }
+        match &mut listener.exclusive_access().tcp {
+            TcpState::Listening { backlog, pending } => {
+                if pending.len() >= *backlog {
+                    return Err(-1); // ECONNREFUSED
+                }
+                pending.push_back(Arc::new(server));
+            }
+            _ => return Err(-1), // ECONNREFUSED
+        }
+        {
+            let mut inner = self.inner.exclusive_access();
+            inner.peer = Some(port);
+            inner.tcp = TcpState::Connected { rx: c_rx, tx: c_tx };
+        }
+        self.set_nonblocking(self.is_nonblocking());
+        Ok(())
+    }
+
+    /// 取出一个等待中的连接，没有连接时阻塞（非阻塞模式下直接失败）
+    pub fn accept(&self) -> Result<Arc<InetSocket>, isize> {
+        loop {
+            match &mut self.inner.exclusive_access().tcp {
+                TcpState::Listening { pending, .. } => {
+                    if let Some(conn) = pending.pop_front() {
+                        return Ok(conn);
+                    }
+                }
+                _ => return Err(-1), // EINVAL
+            }
+            if self.is_nonblocking() {
+                return Err(-1); // EAGAIN
+            }
+            if signal_pending_of_current() {
+                return Err(-1); // EINTR
+            }
+            suspend_current_and_run_next();
+        }
+    }
+
+    pub fn is_dgram(&self) -> bool {
+        self.dgram
+    }
+
+    /// UDP 发送一个数据报；`dest` 为空时发往 connect 设置的对端
+    pub fn send_to(&self, data: &[u8], dest: Option<SockAddrIn>) -> Result<usize, isize> {
+        let dest_port = match dest {
+            Some(addr) if !addr.is_local() => return Err(-1), // ENETUNREACH
+            Some(addr) => addr.port(),
+            None => self.inner.exclusive_access().peer.ok_or(-1isize)?, // EDESTADDRREQ
+        };
+        let src_port = self.ensure_bound()?;
+        // 回环上没有接收者时数据报被静默丢弃，与真实 UDP 一致
+        if let Some(target) = UDP_PORTS.exclusive_access().lookup(dest_port) {
+            let mut target = target.exclusive_access();
+            if target.datagrams.len() < UDP_QUEUE_LIMIT {
+                target.datagrams.push_back((src_port, data.to_vec()));
+            }
+        }
+        Ok(data.len())
+    }
+
+    /// UDP 接收一个数据报，返回（实际拷贝的长度，源地址）
+    ///
+    /// 缓冲区不足时多余部分被截断丢弃
+    pub fn recv_from(&self, buf: UserBuffer) -> Result<(usize, SockAddrIn), isize> {
+        if !self.dgram {
+            let peer = self.peer_addr().unwrap_or_default();
+            return Ok((self.read(buf), peer));
+        }
+        loop {
+            let datagram = self.inner.exclusive_access().datagrams.pop_front();
+            if let Some((src_port, data)) = datagram {
+                let mut buf = buf;
+                let n = buf.write_buffer(None, &data);
+                return Ok((n, SockAddrIn::loopback(src_port)));
+            }
+            if self.is_nonblocking() {
+                return Err(-1); // EAGAIN
+            }
+            if signal_pending_of_current() {
+                return Err(-1); // EINTR
+            }
+            suspend_current_and_run_next();
+        }
+    }
+}
+
+impl Drop for InetSocket {
+    fn drop(&mut self) {
+        let local = self.inner.exclusive_access().local;
+        if let Some(port) = local {
+            self.ports()
+                .exclusive_access()
+                .release(port, &Arc::downgrade(&self.inner));
+        }
+    }
+}
+
+impl File for InetSocket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        if self.dgram {
+            return self.recv_from(buf).map(|(n, _)| n).unwrap_or(0);
+        }
+        let rx = match &self.inner.exclusive_access().tcp {
+            TcpState::Connected { rx, .. } => rx.clone(),
+            _ => return 0,
+        };
+        rx.read(buf)
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        if self.dgram {
+            let mut data = alloc::vec![0u8; buf.len()];
+            buf.read(None, &mut data);
+            return self.send_to(&data, None).unwrap_or(0);
+        }
+        let tx = match &self.inner.exclusive_access().tcp {
+            TcpState::Connected { tx, .. } => tx.clone(),
+            _ => return 0,
+        };
+        tx.write(buf)
+    }
+
+    fn get_stat(&self) -> UserStat {
+        UserStat {
+            st_dev: SOCKFS_DEV,
+            st_ino: self.ino,
+            // socket type
+            st_mode: 0o140000,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: BLK_SIZE,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime_sec: 0,
+            st_atime_nsec: 0,
+            st_mtime_sec: 0,
+            st_mtime_nsec: 0,
+            st_ctime_sec: 0,
+            st_ctime_nsec: 0,
+            __unused: [0; 2],
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+
+    fn get_path(&self) -> String {
+        String::from("socket")
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,134 @@
+//! # 网络子系统（net）
+//!
+//! ## Overview
+//! 提供一个只支持本地回环（127.0.0.1）的最小 AF_INET 协议栈，
+//! 供 iperf 风格的测试以及 libc 网络相关测试在单机上运行：
+//! - `SOCK_DGRAM`（UDP）：按端口投递的数据报队列
+//! - `SOCK_STREAM`（TCP）：监听 / 连接 / 接受，已连接后为一对双向字节流
+//!
+//! 回环上不存在真实的链路层，报文不经过封装与校验，
+//! 数据直接在端点的接收队列之间搬运。
+//!
+//! ## Assumptions
+//! - 只接受 `127.0.0.0/8` 与 `0.0.0.0` 地址，其余目标视为不可达
+//! - 端口号在 UDP 与 TCP 之间互相独立
+//!
+//! ## Invariants
+//! - 端口表中只保存 `Weak` 引用，套接字关闭后端口自动释放
+//! - 同一协议下每个端口最多绑定一个套接字
+
+mod inet;
+
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use lazy_static::lazy_static;
+
+pub use inet::InetSocket;
+
+pub const AF_INET: usize = 2;
+pub const SOCK_DGRAM: usize = 2;
+
+/// 临时端口的分配范围
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// 用户态 `struct sockaddr_in`，端口与地址均为网络字节序
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SockAddrIn {
+    pub sin_family: u16,
+    pub sin_port: u16,
+    pub sin_addr: u32,
+    pub sin_zero: [u8; 8],
+}
+
+impl SockAddrIn {
+    /// 构造回环地址 127.0.0.1:`port`
+    pub fn loopback(port: u16) -> Self {
+        Self {
+            sin_family: AF_INET as u16,
+            sin_port: port.to_be(),
+            sin_addr: u32::from_be_bytes([127, 0, 0, 1]).to_be(),
+            sin_zero: [0; 8],
+        }
+    }
+
+    /// 主机字节序的端口号
+    pub fn port(&self) -> u16 {
+        u16::from_be(self.sin_port)
+    }
+
+    /// 是否为本机可达的地址（回环或任意地址）
+    pub fn is_local(&self) -> bool {
+        let addr = u32::from_be(self.sin_addr);
+        addr == 0 || addr >> 24 == 127
+    }
+}
+
+/// 回环接口上的端口表
+pub(crate) struct Loopback<T> {
+    ports: BTreeMap<u16, Weak<T>>,
+    next_ephemeral: u16,
+}
+
+impl<T> Loopback<T> {
+    pub fn new() -> Self {
+        Self {
+            ports: BTreeMap::new(),
+            next_ephemeral: EPHEMERAL_PORT_START,
+        }
+    }
+
+    /// 绑定端口；`port` 为 0 时分配一个临时端口
+    pub fn bind(&mut self, port: u16, endpoint: &Arc<T>) -> Option<u16> {
+        let port = if port == 0 {
+            self.alloc_ephemeral()?
+        } else {
+            port
+        };
+        if self.lookup(port).is_some() {
+            return None; // EADDRINUSE
+        }
+        self.ports.insert(port, Arc::downgrade(endpoint));
+        Some(port)
+    }
+
+    pub fn lookup(&self, port: u16) -> Option<Arc<T>> {
+        self.ports.get(&port).and_then(|e| e.upgrade())
+    }
+
+    /// 释放仍指向 `endpoint` 的端口
+    pub fn release(&mut self, port: u16, endpoint: &Weak<T>) {
+        if self
+            .ports
+            .get(&port)
+            .map_or(false, |e| Weak::ptr_eq(e, endpoint))
+        {
+            self.ports.remove(&port);
+        }
+    }
+
+    fn alloc_ephemeral(&mut self) -> Option<u16> {
+        for _ in EPHEMERAL_PORT_START..=u16::MAX {
+            let port = self.next_ephemeral;
+            self.next_ephemeral = if port == u16::MAX {
+                EPHEMERAL_PORT_START
+            } else {
+                port + 1
+            };
+            if self.lookup(port).is_none() {
+                return Some(port);
+            }
+        }
+        None
+    }
+}
+
+lazy_static! {
+    /// UDP 端口表
+    pub(crate) static ref UDP_PORTS: UPIntrFreeCell<Loopback<UPIntrFreeCell<inet::InetInner>>> =
+        unsafe { UPIntrFreeCell::new(Loopback::new()) };
+    /// TCP 端口表
+    pub(crate) static ref TCP_PORTS: UPIntrFreeCell<Loopback<UPIntrFreeCell<inet::InetInner>>> =
+        unsafe { UPIntrFreeCell::new(Loopback::new()) };
+}
@@ -0,0 +1,149 @@
+//! # 内核随机数发生器
+//!
+//! ## Overview
+//! 基于 ChaCha20 的密码学安全伪随机数发生器（CSPRNG），供以下场景使用：
+//! - `getrandom` 系统调用
+//! - `/dev/urandom` 与 `/dev/random`
+//! - `execve` 时写入用户栈的 `AT_RANDOM` 16 字节
+//!
+//! ## Design
+//! - 启动时在 `init` 中反复读取时钟计数器，以相邻读数之间的抖动作为初始熵
+//! - 每次取随机数前再混入一次当前时钟读数
+//! - 每次输出之后用一个新生成的密钥流块替换密钥（fast key erasure），
+//!   即使内部状态泄露也无法反推之前的输出
+//!
+//! ## Assumptions
+//! - 时钟计数器（`get_time`）单调递增，且在不同启动之间存在不可预测的抖动
+//!
+//! ## Invariants
+//! - `seeded` 为 `true` 之前不会向用户态交付随机数（非阻塞调用直接失败）
+
+use crate::hal::get_time;
+use crate::sync::UPIntrFreeCell;
+use lazy_static::lazy_static;
+
+/// 启动时采集的时钟抖动样本数
+const BOOT_JITTER_SAMPLES: usize = 256;
+
+/// "expand 32-byte k"
+const CHACHA_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+#[inline]
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// 计算一个 ChaCha20 密钥流块
+fn chacha20_block(key: &[u32; 8], counter: u64, nonce: u32) -> [u8; 64] {
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&CHACHA_CONSTANTS);
+    init[4..12].copy_from_slice(key);
+    init[12] = counter as u32;
+    init[13] = (counter >> 32) as u32;
+    init[14] = nonce;
+    init[15] = 0;
+    let mut s = init;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+    let mut out = [0u8; 64];
+    for (bytes, (word, orig)) in out.chunks_exact_mut(4).zip(s.iter().zip(init.iter())) {
+        bytes.copy_from_slice(&word.wrapping_add(*orig).to_le_bytes());
+    }
+    out
+}
+
+pub struct ChaChaRng {
+    key: [u32; 8],
+    counter: u64,
+    /// 已混入的熵事件数，作为 nonce 区分不同的混合轮次
+    mixes: u32,
+    seeded: bool,
+}
+
+impl ChaChaRng {
+    const fn new() -> Self {
+        Self {
+            key: [0; 8],
+            counter: 0,
+            mixes: 0,
+            seeded: false,
+        }
+    }
+
+    /// 将一个 64 位的熵样本混入密钥
+    pub fn add_entropy(&mut self, sample: u64) {
+        self.key[0] ^= sample as u32;
+        self.key[1] ^= (sample >> 32) as u32;
+        self.mixes = self.mixes.wrapping_add(1);
+        let block = chacha20_block(&self.key, self.counter, self.mixes);
+        self.rekey(&block);
+    }
+
+    fn rekey(&mut self, block: &[u8; 64]) {
+        for (word, bytes) in self.key.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+    }
+
+    /// 用随机字节填满 `buf`
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        self.add_entropy(get_time() as u64);
+        for chunk in buf.chunks_mut(64) {
+            self.counter = self.counter.wrapping_add(1);
+            let block = chacha20_block(&self.key, self.counter, 0);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        // fast key erasure：丢弃用于本次输出的密钥
+        self.counter = self.counter.wrapping_add(1);
+        let block = chacha20_block(&self.key, self.counter, 0);
+        self.rekey(&block);
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.seeded
+    }
+}
+
+lazy_static! {
+    pub static ref KERNEL_RNG: UPIntrFreeCell<ChaChaRng> =
+        unsafe { UPIntrFreeCell::new(ChaChaRng::new()) };
+}
+
+/// 以启动阶段的时钟抖动为种子初始化随机数发生器
+pub fn init() {
+    let mut rng = KERNEL_RNG.exclusive_access();
+    let mut last = get_time();
+    for i in 0..BOOT_JITTER_SAMPLES {
+        // 每轮做不等量的空转，让相邻两次读数的差值受流水线、缓存等影响而抖动
+        let mut acc = last;
+        for j in 0..(i & 0x1f) {
+            acc = acc.rotate_left(5) ^ j;
+            core::hint::spin_loop();
+        }
+        let now = get_time();
+        let jitter = (now - last) as u64;
+        rng.add_entropy((jitter << 32) | ((now ^ acc) as u64 & 0xffff_ffff));
+        last = now;
+    }
+    rng.seeded = true;
+}
+
+/// 用内核随机数填满 `buf`
+pub fn fill_random(buf: &mut [u8]) {
+    KERNEL_RNG.exclusive_access().fill(buf);
+}
@@ -0,0 +1,131 @@
+//! 块 I/O 请求队列自检
+
+use super::TestResult;
+use crate::drivers::block::block_dev::BlockDevice;
+use crate::drivers::block::request_queue::{Bio, RequestQueue, MAX_MERGE};
+use crate::hal::BLOCK_SZ;
+use crate::sync::SpinMutex;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 驱动收到的一次访问：（是否为写，起始块号，块数）
+type Access = (bool, usize, usize);
+
+/// 记录每次访问的内存块设备
+struct RecordingDevice {
+    blocks: SpinMutex<BTreeMap<usize, Vec<u8>>>,
+    log: SpinMutex<Vec<Access>>,
+}
+
+impl RecordingDevice {
+    fn new() -> Self {
+        Self {
+            blocks: SpinMutex::new(BTreeMap::new()),
+            log: SpinMutex::new(Vec::new()),
+        }
+    }
+
+    fn take_log(&self) -> Vec<Access> {
+        core::mem::take(&mut *self.log.lock())
+    }
+}
+
+impl BlockDevice for RecordingDevice {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.read_blocks(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.write_blocks(block_id, buf);
+    }
+
+    fn read_blocks(&self, block_id: usize, buf: &mut [u8]) {
+        self.log
+            .lock()
+            .push((false, block_id, buf.len() / BLOCK_SZ));
+        let blocks = self.blocks.lock();
+        for (i, chunk) in buf.chunks_mut(BLOCK_SZ).enumerate() {
+            match blocks.get(&(block_id + i)) {
+                Some(data) => chunk.copy_from_slice(data),
+                None => chunk.fill(0),
+            }
+        }
+    }
+
+    fn write_blocks(&self, block_id: usize, buf: &[u8]) {
+        self.log.lock().push((true, block_id, buf.len() / BLOCK_SZ));
+        let mut blocks = self.blocks.lock();
+        for (i, chunk) in buf.chunks(BLOCK_SZ).enumerate() {
+            blocks.insert(block_id + i, chunk.to_vec());
+        }
+    }
+}
+
+fn block_of(byte: u8) -> Vec<u8> {
+    vec![byte; BLOCK_SZ]
+}
+
+/// 请求按 C-LOOK 顺序派发，块号连续的同向请求合并为一次访问，且不超过 `MAX_MERGE` 块
+pub fn request_queue_test() -> TestResult {
+    let device = Arc::new(RecordingDevice::new());
+    let queue = RequestQueue::new(device.clone());
+
+    // 乱序提交，派发时按块号递增并合并相邻的块
+    for block_id in [10, 3, 11, 4, 12, 20] {
+        queue.submit(Bio::write(block_id, &block_of(block_id as u8)));
+    }
+    check!(device.take_log().is_empty());
+    queue.unplug();
+    check_eq!(
+        device.take_log(),
+        vec![(true, 3, 2), (true, 10, 3), (true, 20, 1)]
+    );
+
+    // 磁头停在 21：先派发之后的块，再回到最小的块号
+    for block_id in [5, 26, 25] {
+        queue.submit(Bio::write(block_id, &block_of(block_id as u8)));
+    }
+    queue.unplug();
+    check_eq!(device.take_log(), vec![(true, 25, 2), (true, 5, 1)]);
+
+    // 一次合并不超过 MAX_MERGE 块
+    let count = MAX_MERGE + 4;
+    for block_id in 100..100 + count {
+        queue.submit(Bio::write(block_id, &block_of(1)));
+    }
+    queue.unplug();
+    check_eq!(
+        device.take_log(),
+        vec![(true, 100, MAX_MERGE), (true, 100 + MAX_MERGE, 4)]
+    );
+
+    // 同一块上的写保持提交顺序，之后的读看到最后一次写
+    queue.submit(Bio::write(7, &block_of(1)));
+    queue.submit(Bio::write(7, &block_of(2)));
+    let mut buf = block_of(0);
+    queue.read_block(7, &mut buf);
+    check!(buf == block_of(2));
+    check_eq!(
+        device.take_log(),
+        vec![(true, 7, 1), (true, 7, 1), (false, 7, 1)]
+    );
+
+    // 异步读合并为一次访问，每个完成回调恰好调用一次并拿到对应块的数据
+    let results = Arc::new(SpinMutex::new(Vec::new()));
+    for block_id in [11, 10, 12] {
+        let results = results.clone();
+        queue.submit(Bio::read(
+            block_id,
+            Box::new(move |data| results.lock().push((block_id, data[0]))),
+        ));
+    }
+    queue.unplug();
+    check_eq!(device.take_log(), vec![(false, 10, 3)]);
+    let mut results = core::mem::take(&mut *results.lock());
+    results.sort();
+    check_eq!(results, vec![(10, 10), (11, 11), (12, 12)]);
+    Ok(())
+}
@@ -0,0 +1,270 @@
+//! 文件系统自检
+
+use super::TestResult;
+use crate::fs::epoll::{
+    Epoll, EpollEvent, EPOLLET, EPOLLONESHOT, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD,
+};
+use crate::fs::fat_name::{canonical_path, check_name, name_eq, FatCodePage};
+use crate::fs::inode::{FatType, OSInode, ROOT_DIR};
+use crate::fs::{
+    drop_page_cache, forget_ino, lookup_path, make_pipe, make_socket_pair, resolve_path, File,
+    PollEvents, Socket,
+};
+use crate::hal::PAGE_SIZE;
+use crate::mm::UserBuffer;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use fatfs::{OemCpConverter, SeekFrom};
+
+pub fn path_test() -> TestResult {
+    let cases = [
+        ("/a/b", "/x", "/a/b"),
+        ("c", "/a/b", "/a/b/c"),
+        ("../c", "/a/b", "/a/c"),
+        ("./c/./d/", "/", "/c/d"),
+        ("..", "/", "/"),
+        ("/a//b/../../..", "/a", "/"),
+        ("", "/a/b", "/a/b"),
+    ];
+    for (relative, base, expected) in cases {
+        check_eq!(resolve_path(relative, base).as_str(), expected);
+    }
+    check_eq!(lookup_path("/"), Some(true));
+    check_eq!(lookup_path("/__selftest_missing__"), None);
+    Ok(())
+}
+
+/// 管道按顺序传输数据（包括绕回缓冲区末尾），满时不可写，容量调整与写端关闭
+pub fn pipe_test() -> TestResult {
+    let (read_end, write_end) = make_pipe();
+    check!(read_end.readable() && !read_end.writable());
+    check!(write_end.writable() && !write_end.readable());
+    check!(!read_end.poll().contains(PollEvents::POLLIN));
+    check!(write_end.poll().contains(PollEvents::POLLOUT));
+    // 每次写入的长度与容量互质，多轮之后读写位置覆盖缓冲区的所有偏移
+    let capacity = write_end.capacity();
+    let chunk = PAGE_SIZE - 1;
+    let mut out = vec![0u8; chunk];
+    let mut seq = 0u8;
+    for _ in 0..2 * capacity / chunk {
+        let data: Vec<u8> = (0..chunk).map(|i| seq.wrapping_add(i as u8)).collect();
+        check_eq!(write_end.write_at(0, &data), Ok(chunk));
+        check!(read_end.poll().contains(PollEvents::POLLIN));
+        check_eq!(read_end.read_at(0, &mut out), Ok(chunk));
+        check!(out == data);
+        seq = seq.wrapping_add(7);
+    }
+    // 填满后不可写，写入只接受剩余空间
+    let data = vec![0x5a; capacity + 10];
+    check_eq!(write_end.write_at(0, &data), Ok(capacity));
+    check!(!write_end.poll().contains(PollEvents::POLLOUT));
+    check_eq!(write_end.write_at(0, &data), Ok(0));
+    // 放不下已缓冲数据的容量被拒绝，清空之后可以缩小，容量取 2 的幂个页
+    check_eq!(write_end.set_capacity(PAGE_SIZE), -1);
+    let mut drain = vec![0u8; capacity];
+    check_eq!(read_end.read_at(0, &mut drain), Ok(capacity));
+    check!(drain.iter().all(|&b| b == 0x5a));
+    check_eq!(
+        write_end.set_capacity(PAGE_SIZE + 1),
+        2 * PAGE_SIZE as isize
+    );
+    check_eq!(read_end.capacity(), 2 * PAGE_SIZE);
+    // 写端关闭后读端报告挂断，读到 EOF
+    check_eq!(write_end.write_at(0, b"tail"), Ok(4));
+    drop(write_end);
+    check!(read_end.poll().contains(PollEvents::POLLHUP));
+    let mut tail = [0u8; 8];
+    check_eq!(read_end.read_at(0, &mut tail), Ok(4));
+    check!(&tail[..4] == b"tail");
+    check_eq!(read_end.read_at(0, &mut tail), Ok(0));
+    Ok(())
+}
+
+/// 以内核缓冲区构造 `UserBuffer`
+fn kernel_buffer(data: &mut [u8]) -> UserBuffer {
+    // SAFETY: 调用者在读写返回之前一直持有 `data`
+    UserBuffer::new(vec![unsafe {
+        core::slice::from_raw_parts_mut(data.as_mut_ptr(), data.len())
+    }])
+}
+
+/// SCM_RIGHTS 传递的文件按批、按方向排队，读到消息的数据后才能取出，未连接的套接字不能传递文件
+pub fn scm_rights_test() -> TestResult {
+    let (a, b) = make_socket_pair();
+    let (read_end, write_end) = make_pipe();
+    let sent: Arc<dyn File + Send + Sync> = read_end;
+    check_eq!(a.write(kernel_buffer(&mut *b"ab")), 2);
+    check_eq!(a.send_files(vec![sent.clone()], 2), Ok(()));
+    let both: Vec<Arc<dyn File + Send + Sync>> = vec![write_end.clone(), sent.clone()];
+    check_eq!(a.write(kernel_buffer(&mut *b"cd")), 2);
+    check_eq!(a.send_files(both, 2), Ok(()));
+    // 发送方自己收不到，接收方读到数据之前也取不到
+    check!(a.take_files().is_none());
+    check!(b.take_files().is_none());
+    let mut data = [0u8; 3];
+    check_eq!(b.read(kernel_buffer(&mut data[..1])), 1);
+    let first = b.take_files().unwrap_or_default();
+    check_eq!(first.len(), 1);
+    check!(Arc::as_ptr(&first[0]) as *const u8 == Arc::as_ptr(&sent) as *const u8);
+    // 第二批随 "cd" 传递，读到它之前留在队列中
+    check!(b.take_files().is_none());
+    check_eq!(b.read(kernel_buffer(&mut data)), 3);
+    check!(&data == b"bcd");
+    check_eq!(b.take_files().map(|files| files.len()), Some(2));
+    check!(b.take_files().is_none());
+    // 收到的文件与发送方持有的是同一个打开的文件
+    check_eq!(write_end.write_at(0, b"fd"), Ok(2));
+    let mut buf = [0u8; 2];
+    check_eq!(first[0].read_at(0, &mut buf), Ok(2));
+    check!(&buf == b"fd");
+    check!(Socket::new().send_files(vec![sent], 0).is_err());
+    Ok(())
+}
+
+/// epoll 的水平触发、边沿触发与 EPOLLONESHOT，关闭的文件自动移出关注列表
+pub fn epoll_test() -> TestResult {
+    let epoll = Epoll::new();
+    let (read_end, write_end) = make_pipe();
+    let level: Arc<dyn File + Send + Sync> = read_end;
+    let edge: Arc<dyn File + Send + Sync> = write_end.clone();
+    let pollin = PollEvents::POLLIN.bits() as u32;
+    let pollout = PollEvents::POLLOUT.bits() as u32;
+    let add = |events, data| EpollEvent { events, data };
+    check_eq!(epoll.ctl(EPOLL_CTL_ADD, 3, &level, add(pollin, 3)), Ok(()));
+    check!(epoll.ctl(EPOLL_CTL_ADD, 3, &level, add(pollin, 3)).is_err());
+    check_eq!(
+        epoll.ctl(EPOLL_CTL_ADD, 4, &edge, add(pollout | EPOLLET, 4)),
+        Ok(())
+    );
+    // 管道为空：写端的边沿只报告一次，读端不就绪
+    let ready = epoll.collect(8);
+    check_eq!(ready.len(), 1);
+    check_eq!(ready[0].data, 4);
+    check!(epoll.collect(8).is_empty());
+    // 写入之后读端按水平触发每次报告
+    check_eq!(write_end.write_at(0, b"x"), Ok(1));
+    for _ in 0..2 {
+        let ready = epoll.collect(8);
+        check_eq!(ready.len(), 1);
+        check_eq!((ready[0].events, ready[0].data), (pollin, 3));
+    }
+    check!(epoll.poll().contains(PollEvents::POLLIN));
+    // EPOLLONESHOT 报告一次后停用，直到重新修改
+    check_eq!(
+        epoll.ctl(EPOLL_CTL_MOD, 3, &level, add(pollin | EPOLLONESHOT, 5)),
+        Ok(())
+    );
+    check_eq!(epoll.collect(8).len(), 1);
+    check!(epoll.collect(8).is_empty());
+    check_eq!(epoll.ctl(EPOLL_CTL_MOD, 3, &level, add(pollin, 6)), Ok(()));
+    check_eq!(epoll.collect(8).first().map(|event| event.data), Some(6));
+    // 删除与不存在的关注项，关闭的文件自动移除
+    check_eq!(epoll.ctl(EPOLL_CTL_DEL, 3, &level, add(0, 0)), Ok(()));
+    check!(epoll.ctl(EPOLL_CTL_DEL, 3, &level, add(0, 0)).is_err());
+    drop((edge, write_end));
+    check!(epoll.collect(8).is_empty());
+    check!(epoll.ctl(EPOLL_CTL_MOD, 4, &level, add(pollin, 0)).is_err());
+    Ok(())
+}
+
+/// 代码页往返、文件名检查，以及非 ASCII 长文件名的创建与不区分大小写的查找
+pub fn fat_name_test() -> TestResult {
+    for byte in 0..=u8::MAX {
+        let c = FatCodePage::Cp437.decode(byte);
+        check_eq!(FatCodePage::Cp437.encode(c), Some(byte));
+    }
+    check_eq!(FatCodePage::Cp437.decode(0x82), 'é');
+    check_eq!(FatCodePage::Ascii.encode('é'), None);
+    check_eq!(FatCodePage::Ascii.decode(0x82), '\u{fffd}');
+    check_eq!(FatCodePage::Cp437.encode('长'), None);
+
+    check_eq!(check_name("notes.txt"), Ok(()));
+    check_eq!(check_name("长文件名 with spaces.tar.gz"), Ok(()));
+    for bad in ["", "a:b", "what?", "tab\there", "back\\slash", "pipe|"] {
+        check!(check_name(bad).is_err());
+    }
+    check_eq!(check_name(&"x".repeat(255)), Ok(()));
+    check!(check_name(&"x".repeat(256)).is_err());
+
+    check!(name_eq("Readme.TXT", "README.txt"));
+    check!(name_eq("Ñandú", "ñANDÚ"));
+    check!(!name_eq("a.txt", "a.txt.bak"));
+
+    // 在根目录中创建非 ASCII 名字的文件：目录项保留原名，换了大小写的路径解析到同一项
+    let name = "__selftest_Ñandú_长文件名.txt";
+    let root = ROOT_DIR.exclusive_access().clone();
+    check!(root.create_file(name).is_ok());
+    let listed = root.iter().flatten().any(|entry| entry.file_name() == name);
+    let upper: String = name.chars().flat_map(char::to_uppercase).collect();
+    let canonical = canonical_path(&format!("/{}", upper));
+    let reopened = root.open_file(&upper).is_ok();
+    check!(root.remove(name).is_ok());
+    check!(listed);
+    check_eq!(canonical, format!("/{}", name));
+    check!(reopened);
+    Ok(())
+}
+
+/// 在文件末尾之后写入：中间的部分读出为零，长度与块数随之更新；lseek 可以移到文件末尾之后
+pub fn sparse_test() -> TestResult {
+    let name = "__selftest_sparse.bin";
+    let path = format!("/{}", name);
+    let root = ROOT_DIR.exclusive_access().clone();
+    let file = root
+        .create_file(name)
+        .map_err(|err| format!("create {}: {:?}", name, err))?;
+    let inode = OSInode::new(true, true, FatType::File(file), false, path.clone());
+    let result = sparse_checks(&inode);
+    drop(inode);
+    drop_page_cache(&path);
+    forget_ino(&path);
+    check!(root.remove(name).is_ok());
+    result
+}
+
+fn sparse_checks(inode: &OSInode) -> TestResult {
+    // pwrite 越过文件末尾
+    check_eq!(inode.write_at(0, b"head"), Ok(4));
+    let gap_end = 2 * PAGE_SIZE + 100;
+    check_eq!(inode.write_at(gap_end, b"tail"), Ok(4));
+    let size = gap_end + 4;
+    let stat = inode.get_stat();
+    check_eq!(stat.st_size, size as i64);
+    check_eq!(stat.st_blocks, size.div_ceil(512) as u64);
+    let mut buf = vec![0xffu8; size];
+    check_eq!(inode.read_at(0, &mut buf), Ok(size));
+    check!(&buf[..4] == b"head");
+    check!(buf[4..gap_end].iter().all(|&b| b == 0));
+    check!(&buf[gap_end..] == b"tail");
+
+    // lseek 移到文件末尾之后不改变文件；之后的 write 先把中间的部分填零
+    check_eq!(inode.lseek(SeekFrom::End(1000)), Ok(size + 1000));
+    check_eq!(inode.offset(), size + 1000);
+    check_eq!(inode.get_stat().st_size, size as i64);
+    check_eq!(inode.read_at(size, &mut buf[..16]), Ok(0));
+    check_eq!(inode.write(kernel_buffer(&mut *b"more")), 4);
+    let new_size = size + 1004;
+    check_eq!(inode.get_stat().st_size, new_size as i64);
+    check_eq!(inode.offset(), new_size);
+    let mut tail = vec![0xffu8; 1004];
+    check_eq!(inode.read_at(size, &mut tail), Ok(1004));
+    check!(tail[..1000].iter().all(|&b| b == 0));
+    check!(&tail[1000..] == b"more");
+
+    // 截短不移动偏移，偏移留在文件末尾之后；偏移不能为负
+    check_eq!(inode.truncate(4), Ok(()));
+    check_eq!(inode.offset(), new_size);
+    check_eq!(inode.lseek(SeekFrom::Current(-(new_size as i64))), Ok(0));
+    check_eq!(inode.lseek(SeekFrom::Current(-1)), Err(-1));
+
+    // 超过 FAT32 文件长度上限的偏移被拒绝，文件不变
+    check_eq!(inode.lseek(SeekFrom::Start(1 << 32)), Err(-1));
+    check_eq!(inode.offset(), 0);
+    check_eq!(inode.write_at(1 << 32, b"x"), Err(-1));
+    check_eq!(inode.truncate(1 << 32), Err(-1));
+    check_eq!(inode.get_stat().st_size, 4);
+    Ok(())
+}
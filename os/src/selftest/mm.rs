@@ -0,0 +1,108 @@
+//! 内存管理自检
+
+use super::TestResult;
+use crate::hal::PageTableImpl;
+use crate::mm::{
+    frame_alloc, frame_free_count, MapPermission, PageSize, PageTable, PhysAddr, VirtAddr,
+    VirtPageNum,
+};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+pub fn heap_test() -> TestResult {
+    let boxed = Box::new(5);
+    check_eq!(*boxed, 5);
+    drop(boxed);
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..500 {
+        v.push(i);
+    }
+    check!(v.iter().enumerate().all(|(i, &x)| i == x));
+    Ok(())
+}
+
+pub fn frame_test() -> TestResult {
+    let frames: Vec<_> = (0..16).map(|_| frame_alloc().unwrap()).collect();
+    for (i, frame) in frames.iter().enumerate() {
+        check!(frame.ppn.get_bytes_array().iter().all(|&b| b == 0));
+        check!(frames[..i].iter().all(|other| other.ppn != frame.ppn));
+        frame.ppn.get_bytes_array().fill(0xa5);
+    }
+    drop(frames);
+    let frame = frame_alloc().unwrap();
+    check!(frame.ppn.get_bytes_array().iter().all(|&b| b == 0));
+    Ok(())
+}
+
+/// 批量分配、交错释放后再分配：页帧不重复，全部释放后空闲数恢复
+pub fn frame_stress_test() -> TestResult {
+    const COUNT: usize = 1024;
+    // 先分配好记录用的向量，之后的堆扩容不会从页帧分配器取走页帧
+    let mut frames = Vec::with_capacity(COUNT);
+    let mut ppns = Vec::with_capacity(COUNT);
+    let free_before = frame_free_count();
+    if free_before < COUNT * 4 {
+        return Err("not enough free frames".into());
+    }
+    frames.extend((0..COUNT).map_while(|_| frame_alloc()));
+    check_eq!(frames.len(), COUNT);
+    ppns.extend(frames.iter().map(|frame| frame.ppn.0));
+    ppns.sort_unstable();
+    ppns.dedup();
+    check_eq!(ppns.len(), COUNT);
+    // 释放一半，使空闲页帧不连续
+    let mut i = 0;
+    frames.retain(|_| {
+        i += 1;
+        i % 2 == 0
+    });
+    ppns.clear();
+    ppns.extend(frames.iter().map(|frame| frame.ppn.0));
+    ppns.sort_unstable();
+    for _ in 0..COUNT / 2 {
+        let Some(frame) = frame_alloc() else {
+            return Err("out of frames after partial free".into());
+        };
+        check!(ppns.binary_search(&frame.ppn.0).is_err());
+        frames.push(frame);
+    }
+    ppns.clear();
+    ppns.extend(frames.iter().map(|frame| frame.ppn.0));
+    ppns.sort_unstable();
+    ppns.dedup();
+    check_eq!(ppns.len(), COUNT);
+    drop(frames);
+    check_eq!(frame_free_count(), free_before);
+    Ok(())
+}
+
+/// 在新页表中映射、翻译并解除映射一个用户页
+pub fn page_table_test() -> TestResult {
+    let frame = frame_alloc().unwrap();
+    let mut page_table = PageTableImpl::new();
+    let vpn = VirtPageNum::from(0x1234);
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    page_table.map(vpn, frame.ppn, perm, PageSize::Small);
+    let Some(pte) = page_table.translate(vpn) else {
+        return Err("mapped page not found".into());
+    };
+    check!(pte.is_valid());
+    check!(pte.readable() && pte.writable() && !pte.executable());
+    check_eq!(pte.ppn(), frame.ppn);
+    let va = VirtAddr::from(vpn).0 + 0x123;
+    let pa: usize = PhysAddr::from(frame.ppn).into();
+    check_eq!(
+        page_table.translate_va(va.into()).map(usize::from),
+        Some(pa + 0x123)
+    );
+    // 相邻的页没有被映射
+    let next = VirtPageNum::from(0x1235);
+    check!(page_table
+        .translate(next)
+        .map_or(true, |pte| !pte.is_valid()));
+    page_table.unmap(vpn);
+    check!(page_table
+        .translate(vpn)
+        .map_or(true, |pte| !pte.is_valid()));
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! # 启动自检
+//!
+//! ## Overview
+//! 启用 `selftest` feature 时，在创建初始进程之前运行的内核自检，逐项打印结果并汇总：
+//! - `mm`：内核堆、页帧分配器（含批量分配与交错释放）、页表映射与解除映射的往返
+//! - `fs`：路径解析、管道的读写顺序、容量、调整容量与写端关闭、套接字传递文件（SCM_RIGHTS）的排队、
+//!   epoll 的水平触发、边沿触发与 EPOLLONESHOT、
+//!   FAT32 文件名的代码页往返、合法性检查与不区分大小写的查找，
+//!   在文件末尾之后写入时填零的空洞与 lseek 越过文件末尾
+//! - `block`：请求队列按 C-LOOK 顺序派发、合并相邻的块，同一块上的请求保持提交顺序
+//! - `timer`：时间轮按到期时间的顺序触发，既不提前也不推迟
+//! - `task`：子进程状态编码的往返，停止信号与 `SIGCONT` 的相互抵消
+//!
+//! 命令行 `selftest=` 列出要运行的自检，缺省时运行全部；未知的名称只打印警告
+//!
+//! ## Design
+//! - 每项自检返回 `Result`，用 `check!` / `check_eq!` 代替 `assert!`：
+//!   失败时报告条件与所在位置并继续运行其余自检，而不是 panic
+//! - 自检失败不影响继续启动，由汇总行报告失败的数量
+//!
+//! ## Assumptions
+//! - 内存管理与文件系统已初始化，尚未有用户进程运行
+//! - 自检只使用自己创建的对象，结束时全部释放，不改变全局状态
+
+/// 条件不成立时使当前自检失败
+macro_rules! check {
+    ($cond:expr) => {
+        if !$cond {
+            return Err(alloc::format!(
+                "{}:{}: {}",
+                file!(),
+                line!(),
+                stringify!($cond)
+            ));
+        }
+    };
+}
+
+/// 两个值不相等时使当前自检失败
+macro_rules! check_eq {
+    ($left:expr, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    return Err(alloc::format!(
+                        "{}:{}: {} == {} ({:?} != {:?})",
+                        file!(),
+                        line!(),
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right
+                    ));
+                }
+            }
+        }
+    };
+}
+
+mod block;
+mod fs;
+mod mm;
+mod task;
+mod timer;
+
+use alloc::string::String;
+
+/// 自检的结果，失败时带有说明
+type TestResult = Result<(), String>;
+
+/// 所有可用的自检，按组列出
+const SELFTESTS: &[(&str, &[(&str, fn() -> TestResult)])] = &[
+    (
+        "mm",
+        &[
+            ("heap", mm::heap_test),
+            ("frame", mm::frame_test),
+            ("frame_stress", mm::frame_stress_test),
+            ("page_table", mm::page_table_test),
+        ],
+    ),
+    (
+        "fs",
+        &[
+            ("path", fs::path_test),
+            ("pipe", fs::pipe_test),
+            ("scm_rights", fs::scm_rights_test),
+            ("epoll", fs::epoll_test),
+            ("fat_name", fs::fat_name_test),
+            ("sparse", fs::sparse_test),
+        ],
+    ),
+    ("block", &[("request_queue", block::request_queue_test)]),
+    ("timer", &[("wheel", timer::wheel_test)]),
+    (
+        "task",
+        &[
+            ("wstatus", task::wstatus_test),
+            ("jobctl", task::jobctl_test),
+        ],
+    ),
+];
+
+/// 运行 `names` 中的自检组，为空时运行全部，并打印汇总
+pub fn run(names: &[String]) {
+    for name in names {
+        if !SELFTESTS.iter().any(|(group, _)| group == name) {
+            println!("[selftest] unknown selftest {}", name);
+        }
+    }
+    let (mut passed, mut failed) = (0, 0);
+    let selected = SELFTESTS
+        .iter()
+        .filter(|(group, _)| names.is_empty() || names.iter().any(|name| name == group));
+    for (group, tests) in selected {
+        for (name, test) in tests.iter() {
+            match test() {
+                Ok(()) => {
+                    passed += 1;
+                    println!("[selftest] {}::{} ... ok", group, name);
+                }
+                Err(msg) => {
+                    failed += 1;
+                    println!("[selftest] {}::{} ... FAILED: {}", group, name, msg);
+                }
+            }
+        }
+    }
+    println!("[selftest] {} passed, {} failed", passed, failed);
+}
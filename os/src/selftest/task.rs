@@ -0,0 +1,37 @@
+//! 进程管理自检
+
+use super::TestResult;
+use crate::task::{JobControl, SignalFlags, WaitStatus};
+
+pub fn wstatus_test() -> TestResult {
+    for status in [
+        WaitStatus::Exited(0),
+        WaitStatus::Exited(255),
+        WaitStatus::Signaled(9),
+        WaitStatus::CoreDumped(11),
+        WaitStatus::Stopped(19),
+        WaitStatus::Continued,
+    ] {
+        check_eq!(WaitStatus::decode(status.encode()), status);
+    }
+    check_eq!(WaitStatus::Exited(1).encode(), 0x100);
+    check_eq!(WaitStatus::Stopped(5).encode(), 0x57f);
+    check_eq!(WaitStatus::CoreDumped(11).encode(), 0x8b);
+    Ok(())
+}
+
+pub fn jobctl_test() -> TestResult {
+    let mut jobctl = JobControl::default();
+    // SIGCONT 丢弃尚未生效的停止信号，其余信号保留；进程没有停止过，不报告继续
+    let mut pending = SignalFlags::SIGTSTP | SignalFlags::SIGALRM;
+    jobctl.on_signal(&mut pending, SignalFlags::SIGCONT);
+    check_eq!(pending.bits(), SignalFlags::SIGALRM.bits());
+    check!(!jobctl.take_unreported_continue());
+    // 停止信号丢弃尚未处理的 SIGCONT，停止在返回用户态时才生效
+    let mut pending = SignalFlags::SIGCONT;
+    jobctl.on_signal(&mut pending, SignalFlags::SIGSTOP);
+    check!(pending.is_empty());
+    check!(!jobctl.is_stopped());
+    check_eq!(jobctl.take_unreported_stop(), None);
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! 定时器自检
+
+use super::TestResult;
+use crate::timer::wheel::{TimerWheel, WheelEntry};
+use alloc::vec::Vec;
+
+/// 时间轮中的条目在到期之后的第一次推进中触发，且按到期时间的顺序
+pub fn wheel_test() -> TestResult {
+    let start = 1000;
+    // 覆盖各层、层的边界与已经过去的时间
+    let expires = [
+        start + 5,
+        start,
+        start - 3,
+        start + 63,
+        start + 64,
+        start + 65,
+        start + 4095,
+        start + 4096,
+        start + 5000,
+        start + 300_000,
+        start + 64,
+    ];
+    let mut wheel = TimerWheel::new(start);
+    for (i, &expire_ms) in expires.iter().enumerate() {
+        wheel.add(expire_ms, i);
+    }
+    let mut fired: Vec<WheelEntry<usize>> = Vec::new();
+    let mut last_now = start - 1;
+    let mut now = start;
+    while fired.len() < expires.len() && now <= start + 400_000 {
+        let before = fired.len();
+        wheel.advance(now, &mut fired);
+        for entry in &fired[before..] {
+            check_eq!(entry.expire_ms, expires[entry.value]);
+            // 不提前，也不晚于到期之后的第一次推进
+            check!(entry.expire_ms <= now);
+            check!(entry.expire_ms > last_now || entry.expire_ms < start);
+        }
+        last_now = now;
+        // 步长不均匀，既有逐毫秒推进，也有跨越多个槽的推进
+        now += match now % 3 {
+            0 => 1,
+            1 => 17,
+            _ => 250,
+        };
+    }
+    check_eq!(fired.len(), expires.len());
+    check!(fired.windows(2).all(|w| w[0].expire_ms <= w[1].expire_ms));
+    Ok(())
+}
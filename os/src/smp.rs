@@ -0,0 +1,118 @@
+//! # 多核支持：核间中断与跨核函数调用
+//!
+//! ## Overview
+//! - `init`：记录引导 hart 并将其标记为在线
+//! - `call_on`：在其他在线 hart 上执行一个函数，通过 IPI 通知，等待全部执行完成
+//! - `handle_ipi`：IPI 中断处理，执行本 hart 队列中的请求
+//! - `tlb_shootdown`：页表修改后使其他 hart 上缓存的 TLB 项失效
+//!
+//! ## Design
+//! - 每个 hart 一个请求队列，同一个请求被放入所有目标 hart 的队列，带一个共享的未完成计数
+//! - 发送方在入队之后、发送 IPI 之前执行 `memory_barrier`，接收方执行完请求后以 Release 递减计数
+//! - 发送方等待时同时处理自己队列中的请求，两个 hart 互相发起请求时不会互相等待
+//! - IPI 的发送与清除由 HAL 提供：RISC-V 使用 SBI IPI 扩展，LoongArch 使用 IOCSR IPI 寄存器
+//!
+//! ## Assumptions
+//! - 目前只有引导 hart 上线，其他 hart 的启动尚未实现，在线位图中没有其他 hart 时所有操作只在本地进行
+//! - hart 编号小于 `MAX_HARTS`
+//! - 不跟踪地址空间在哪些 hart 上活跃，TLB shootdown 保守地发给所有其他在线 hart
+//! - 请求在中断上下文中执行，不能阻塞或调度
+
+use crate::hal::{local_flush_tlb, memory_barrier, send_ipi};
+use crate::sync::SpinMutex;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::lazy_static;
+
+/// 支持的最大 hart 数
+pub const MAX_HARTS: usize = 8;
+/// 包含所有 hart 的位图，新任务的默认 CPU 亲和性
+pub const ALL_HARTS: usize = (1 << MAX_HARTS) - 1;
+
+/// 在线 hart 的位图，位 i 对应 i 号 hart
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+/// 引导 hart 的编号
+static BOOT_HART: AtomicUsize = AtomicUsize::new(0);
+
+/// 一个跨核函数调用请求
+struct CallRequest {
+    func: Arc<dyn Fn() + Send + Sync>,
+    /// 尚未执行完该请求的 hart 数
+    pending: Arc<AtomicUsize>,
+}
+
+lazy_static! {
+    /// 每个 hart 的请求队列
+    static ref CALL_QUEUES: [SpinMutex<VecDeque<CallRequest>>; MAX_HARTS] =
+        core::array::from_fn(|_| SpinMutex::new(VecDeque::new()));
+}
+
+/// 记录引导 hart 并将其标记为在线
+pub fn init(boot_hart: usize) {
+    assert!(boot_hart < MAX_HARTS, "hart {} out of range", boot_hart);
+    BOOT_HART.store(boot_hart, Ordering::Relaxed);
+    ONLINE_HARTS.fetch_or(1 << boot_hart, Ordering::Release);
+}
+
+/// 当前 hart 的编号
+///
+/// 只有引导 hart 在运行，启动其他 hart 时需改为读取每个 hart 自己保存的编号
+pub fn this_hart() -> usize {
+    BOOT_HART.load(Ordering::Relaxed)
+}
+
+/// 在线 hart 的位图
+pub fn online_harts() -> usize {
+    ONLINE_HARTS.load(Ordering::Acquire)
+}
+
+/// 在 `harts` 中除自身以外的在线 hart 上执行 `func`，全部执行完成后返回
+pub fn call_on(harts: usize, func: Arc<dyn Fn() + Send + Sync>) {
+    let targets = harts & online_harts() & !(1 << this_hart());
+    if targets == 0 {
+        return;
+    }
+    let pending = Arc::new(AtomicUsize::new(targets.count_ones() as usize));
+    for hart in (0..MAX_HARTS).filter(|hart| targets & (1 << hart) != 0) {
+        CALL_QUEUES[hart].lock().push_back(CallRequest {
+            func: func.clone(),
+            pending: pending.clone(),
+        });
+    }
+    memory_barrier();
+    send_ipi(targets);
+    while pending.load(Ordering::Acquire) != 0 {
+        run_pending_calls();
+        core::hint::spin_loop();
+    }
+}
+
+/// IPI 中断处理：执行本 hart 队列中的全部请求
+pub fn handle_ipi() {
+    run_pending_calls();
+}
+
+fn run_pending_calls() {
+    loop {
+        let request = CALL_QUEUES[this_hart()].lock().pop_front();
+        match request {
+            Some(request) => {
+                (request.func)();
+                request.pending.fetch_sub(1, Ordering::Release);
+            }
+            None => break,
+        }
+    }
+}
+
+/// 使其他在线 hart 上 `va` 的 TLB 项失效，`asid` 为 `None` 时针对所有 ASID
+///
+/// 本 hart 的 TLB 由调用者自行刷新
+pub fn tlb_shootdown(asid: Option<usize>, va: usize) {
+    let others = online_harts() & !(1 << this_hart());
+    if others == 0 {
+        return;
+    }
+    call_on(others, Arc::new(move || local_flush_tlb(asid, va)));
+}
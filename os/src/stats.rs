@@ -0,0 +1,258 @@
+//! # 内核统计信息
+//!
+//! ## Overview
+//! 汇总内核的内存与进程统计，供 `sysinfo` 系统调用与 procfs 的 `meminfo` 共用：
+//! - 页帧分配器管理的页帧总数与空闲数
+//! - 内核堆的总大小与已分配的字节数
+//! - 交换区的总页数与空闲页数（启用 `swap` 特性时）
+//! - 进程数与开机时间
+//! - 1/5/15 分钟负载平均值，供 `sysinfo` 与 procfs 的 `loadavg` 使用
+//! - 单个进程与线程的状态（运行状态、父进程、进程组、内存与打开的文件数），
+//!   以及进程的线程列表，供 procfs 的 `<pid>/status` 与 `<pid>/task/` 使用
+//! - 进程地址空间中的区域列表，供 procfs 的 `<pid>/maps` 使用
+//!
+//! ## Assumptions
+//! - 各项分别加锁读取，得到的只是近似的快照，不保证彼此一致
+//! - 内核镜像与内核堆不在页帧分配器的管理范围内，不计入内存总量
+
+use crate::hal::PAGE_SIZE;
+use crate::mm::{
+    frame_free_count, frame_total_count, heap_usage, MapPermission, VirtAddr, VirtPageNum,
+};
+use crate::task::{
+    load_average, max_pid, pid2process, process_count, ready_count, TaskControlBlock, TaskStatus,
+    FSHIFT,
+};
+use crate::timer::get_time_ms;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// 某一时刻的内核统计信息
+pub struct KernelStats {
+    /// 页帧总数与空闲页帧数
+    pub total_frames: usize,
+    pub free_frames: usize,
+    /// 内核堆的总字节数与已分配的字节数
+    pub heap_total: usize,
+    pub heap_used: usize,
+    /// 交换区的总页数与空闲页数，未启用交换时为 0
+    pub total_swap_pages: usize,
+    pub free_swap_pages: usize,
+    /// 进程数
+    pub procs: usize,
+    /// 开机以来的毫秒数
+    pub uptime_ms: usize,
+    /// 1/5/15 分钟负载平均值，定点数，小数部分 `FSHIFT` 位
+    pub loads: [usize; 3],
+}
+
+impl KernelStats {
+    /// 内存总量（字节）
+    pub fn total_ram(&self) -> usize {
+        self.total_frames * PAGE_SIZE
+    }
+
+    /// 空闲内存（字节）
+    pub fn free_ram(&self) -> usize {
+        self.free_frames * PAGE_SIZE
+    }
+
+    /// 交换区总量（字节）
+    pub fn total_swap(&self) -> usize {
+        self.total_swap_pages * PAGE_SIZE
+    }
+
+    /// 交换区空闲量（字节）
+    pub fn free_swap(&self) -> usize {
+        self.free_swap_pages * PAGE_SIZE
+    }
+}
+
+/// 收集当前的统计信息
+pub fn collect() -> KernelStats {
+    let (heap_total, heap_used) = heap_usage();
+    #[cfg(feature = "swap")]
+    let (total_swap_pages, free_swap_pages) = crate::mm::swap::swap_usage();
+    #[cfg(not(feature = "swap"))]
+    let (total_swap_pages, free_swap_pages) = (0, 0);
+    KernelStats {
+        total_frames: frame_total_count(),
+        free_frames: frame_free_count(),
+        heap_total,
+        heap_used,
+        total_swap_pages,
+        free_swap_pages,
+        procs: process_count(),
+        uptime_ms: get_time_ms(),
+        loads: load_average(),
+    }
+}
+
+/// 按 Linux `/proc/meminfo` 的格式输出内存统计，单位为 kB
+///
+/// 没有对应统计的 `Buffers` / `Cached` 固定为 0，内核堆以 `KernelHeap*` 两项给出
+pub fn meminfo() -> String {
+    let stats = collect();
+    let mut out = String::new();
+    let fields = [
+        ("MemTotal", stats.total_ram()),
+        ("MemFree", stats.free_ram()),
+        ("MemAvailable", stats.free_ram()),
+        ("Buffers", 0),
+        ("Cached", 0),
+        ("SwapTotal", stats.total_swap()),
+        ("SwapFree", stats.free_swap()),
+        ("KernelHeapTotal", stats.heap_total),
+        ("KernelHeapUsed", stats.heap_used),
+    ];
+    for (name, bytes) in fields {
+        let label = alloc::format!("{}:", name);
+        let _ = writeln!(out, "{:<16}{:>8} kB", label, bytes / 1024);
+    }
+    out
+}
+
+/// 按 Linux `/proc/loadavg` 的格式输出负载平均值、可运行任务数 / 进程数与最大 PID
+pub fn loadavg() -> String {
+    let stats = collect();
+    let mut out = String::new();
+    for load in stats.loads {
+        let frac = ((load & ((1 << FSHIFT) - 1)) * 100) >> FSHIFT;
+        let _ = write!(out, "{}.{:02} ", load >> FSHIFT, frac);
+    }
+    // 读取者自身正在运行
+    let runnable = ready_count() + 1;
+    let _ = writeln!(out, "{}/{} {}", runnable, stats.procs, max_pid());
+    out
+}
+
+/// 按 Linux `/proc/<pid>/status` 的格式输出进程 `pid` 的状态，进程或线程不存在时返回 `None`
+///
+/// `tid` 为 `None` 时输出整个进程，任务名取主线程的名字；否则输出线程 `tid`（`/proc/<pid>/task/<tid>/status`），
+/// 任务名、运行状态与 `Pid` 取自该线程。打开的文件数记在 `FDSize` 中，与 Linux 报告文件描述符表的容量不同
+pub fn pid_status(pid: usize, tid: Option<usize>) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let task = inner.tasks.get(tid.unwrap_or(0)).cloned().flatten();
+    if tid.is_some() && task.is_none() {
+        return None;
+    }
+    let name = task
+        .as_ref()
+        .map(|task| task.inner_exclusive_access().name)
+        .unwrap_or_default();
+    let ppid = inner
+        .parent
+        .as_ref()
+        .and_then(Weak::upgrade)
+        .map_or(0, |parent| parent.getpid());
+    // 已挂起的线程之外都算作可运行
+    let runnable = |task: &Arc<TaskControlBlock>| {
+        task.inner_exclusive_access().task_status != TaskStatus::Blocked
+    };
+    let running = match (tid, &task) {
+        (Some(_), Some(task)) => runnable(task),
+        _ => inner.tasks.iter().flatten().any(runnable),
+    };
+    let state = if inner.is_zombie {
+        "Z (zombie)"
+    } else if inner.ptrace.is_stopped() {
+        "t (tracing stop)"
+    } else if inner.jobctl.is_stopped() {
+        "T (stopped)"
+    } else if running {
+        "R (running)"
+    } else {
+        "S (sleeping)"
+    };
+    let vmas = inner.memory_set.vmas();
+    let vm_size: usize = vmas.iter().map(|vma| vma.end - vma.start).sum();
+    let resident = vmas
+        .iter()
+        .flat_map(|vma| VirtAddr::from(vma.start).floor().0..VirtAddr::from(vma.end).ceil().0)
+        .filter(|&vpn| {
+            inner
+                .memory_set
+                .translate(VirtPageNum::from(vpn))
+                .is_some_and(|pte| pte.is_valid())
+        })
+        .count();
+    let open_fds = inner.fd_table.iter().flatten().count();
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:\t{}", name);
+    let _ = writeln!(out, "State:\t{}", state);
+    let _ = writeln!(out, "Tgid:\t{}", pid);
+    let _ = writeln!(out, "Pid:\t{}", tid.unwrap_or(pid));
+    let _ = writeln!(out, "PPid:\t{}", ppid);
+    let _ = writeln!(out, "Pgid:\t{}", inner.pgid);
+    let _ = writeln!(out, "FDSize:\t{}", open_fds);
+    let _ = writeln!(out, "VmSize:\t{:>8} kB", vm_size / 1024);
+    let _ = writeln!(out, "VmRSS:\t{:>8} kB", resident * PAGE_SIZE / 1024);
+    let _ = writeln!(out, "Threads:\t{}", inner.thread_count());
+    Some(out)
+}
+
+/// 按 Linux `/proc/<pid>/maps` 的格式列出进程 `pid` 的用户地址空间，进程不存在时返回 `None`
+///
+/// 每行为起止地址、权限、文件偏移、设备号、inode 号与名称；文件共享映射的名称为文件路径，
+/// brk 堆与主线程的用户栈分别标为 `[heap]` 与 `[stack]`。设备号与 inode 号固定为 0
+pub fn pid_maps(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    // brk 堆由若干个按页对齐的区域组成，最后一个区域结束于 brk 向上对齐处
+    let heap = inner.memory_set.heap_start..inner.memory_set.brk.next_multiple_of(PAGE_SIZE);
+    let stack_top = inner
+        .tasks
+        .first()
+        .cloned()
+        .flatten()
+        .and_then(|task| Some(task.inner_exclusive_access().res.as_ref()?.ustack_top()));
+    let mut out = String::new();
+    for vma in inner.memory_set.vmas() {
+        let flag = |perm, c| if vma.perm.contains(perm) { c } else { '-' };
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "{:08x}-{:08x} {}{}{}{} {:08x} 00:00 0",
+            vma.start,
+            vma.end,
+            flag(MapPermission::R, 'r'),
+            flag(MapPermission::W, 'w'),
+            flag(MapPermission::X, 'x'),
+            if vma.shared { 's' } else { 'p' },
+            vma.offset
+        );
+        let name = if let Some(path) = vma.path.as_deref() {
+            path
+        } else if heap.start <= vma.start && vma.end <= heap.end {
+            "[heap]"
+        } else if stack_top == Some(vma.end) {
+            "[stack]"
+        } else {
+            ""
+        };
+        if name.is_empty() {
+            let _ = writeln!(out, "{}", line);
+        } else {
+            // 与 Linux 相同，名称从第 74 列开始
+            let _ = writeln!(out, "{:<73}{}", line, name);
+        }
+    }
+    Some(out)
+}
+
+/// 进程 `pid` 现有线程的线程 ID，进程不存在时返回 `None`
+pub fn thread_ids(pid: usize) -> Option<Vec<usize>> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let tids = inner
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| task.is_some())
+        .map(|(tid, _)| tid)
+        .collect();
+    Some(tids)
+}
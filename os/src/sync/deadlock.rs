@@ -0,0 +1,190 @@
+//! # 死锁检测（deadlock_detect）
+//!
+//! ## Overview
+//! 调试用的死锁检测，由 `deadlock_detect` 特性启用，包含两部分：
+//! - `ResourceTracker`：进程的用户态互斥锁与信号量的资源分配表，
+//!   按银行家算法的安全性检查判断一次加锁 / P 操作之后能否让所有线程都运行完，
+//!   不能时系统调用返回 `EDEADLK`，而不是让线程永远阻塞
+//! - 内核 `SleepLock` 的等待图：记录每把锁的持有者与每个任务正在等待的锁，
+//!   任务即将阻塞前沿“锁 → 持有者 → 持有者等待的锁”检查是否成环，
+//!   成环时打印持有者链并 panic
+//!
+//! ## Assumptions
+//! - 银行家算法是保守的：信号量计数为 0、由其他线程 V 操作的生产者 / 消费者模式也会被判为不安全，
+//!   因此检查默认关闭，由 `enable_deadlock_detect` 系统调用按进程开启；关闭时仍然记录分配情况
+//! - 对信号量做 V 操作的线程不一定持有它，此时只增加可用数量
+//! - 内核中的 `SleepLock` 以锁与任务控制块的地址标识，检测到的死锁无法恢复，只能报告
+//!
+//! ## Invariants
+//! - `available[r]` 加上所有线程对 `r` 的 `allocation` 等于创建时的资源数（信号量的额外 V 操作除外）
+//! - 等待图中的每个任务都阻塞在它记录的锁上
+
+use super::SpinMutex;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::panic::Location;
+use lazy_static::lazy_static;
+
+/// 用户态同步资源
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Resource {
+    Mutex(usize),
+    Semaphore(usize),
+}
+
+/// 进程的同步资源分配表
+#[derive(Default)]
+pub struct ResourceTracker {
+    /// 是否在请求资源时做安全性检查
+    pub enabled: bool,
+    /// 每种资源的可用数量
+    available: BTreeMap<Resource, usize>,
+    /// 每个线程（按 tid）已持有的资源数量
+    allocation: BTreeMap<usize, BTreeMap<Resource, usize>>,
+    /// 每个线程正在等待的资源数量
+    need: BTreeMap<usize, BTreeMap<Resource, usize>>,
+}
+
+impl ResourceTracker {
+    /// 创建资源 `res`，共有 `count` 份
+    pub fn add(&mut self, res: Resource, count: usize) {
+        self.available.insert(res, count);
+    }
+
+    /// 线程 `tid` 请求一份 `res`：请求后系统不安全时撤销请求并返回 `false`
+    pub fn request(&mut self, tid: usize, res: Resource) -> bool {
+        *self.need.entry(tid).or_default().entry(res).or_default() += 1;
+        if self.enabled && !self.is_safe() {
+            self.need
+                .get_mut(&tid)
+                .unwrap()
+                .entry(res)
+                .and_modify(|n| *n -= 1);
+            return false;
+        }
+        true
+    }
+
+    /// 线程 `tid` 得到了一份 `res`
+    pub fn acquired(&mut self, tid: usize, res: Resource) {
+        if let Some(n) = self.need.entry(tid).or_default().get_mut(&res) {
+            *n = n.saturating_sub(1);
+        }
+        *self
+            .allocation
+            .entry(tid)
+            .or_default()
+            .entry(res)
+            .or_default() += 1;
+        let available = self.available.entry(res).or_default();
+        *available = available.saturating_sub(1);
+    }
+
+    /// 线程 `tid` 归还了一份 `res`
+    pub fn released(&mut self, tid: usize, res: Resource) {
+        if let Some(n) = self.allocation.entry(tid).or_default().get_mut(&res) {
+            *n = n.saturating_sub(1);
+        }
+        *self.available.entry(res).or_default() += 1;
+    }
+
+    /// 线程 `tid` 退出，丢弃它的记录
+    pub fn remove_thread(&mut self, tid: usize) {
+        self.allocation.remove(&tid);
+        self.need.remove(&tid);
+    }
+
+    /// 安全性检查：能否找到一个顺序，使每个线程的需求都能依次被满足
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut unfinished: Vec<usize> = self.need.keys().copied().collect();
+        loop {
+            let runnable = unfinished.iter().position(|tid| {
+                self.need[tid]
+                    .iter()
+                    .all(|(res, &n)| n <= work.get(res).copied().unwrap_or(0))
+            });
+            let Some(idx) = runnable else {
+                break;
+            };
+            let tid = unfinished.swap_remove(idx);
+            for (res, &n) in self.allocation.get(&tid).into_iter().flatten() {
+                *work.entry(*res).or_default() += n;
+            }
+        }
+        unfinished
+            .iter()
+            .all(|tid| self.need[tid].values().all(|&n| n == 0))
+    }
+}
+
+/// 等待图中的一条边：等待的锁及调用位置，以等待者为键
+struct Waiter {
+    lock: usize,
+    location: &'static Location<'static>,
+}
+
+/// 锁的持有者及其加锁位置
+struct Holder {
+    task: usize,
+    location: &'static Location<'static>,
+}
+
+lazy_static! {
+    /// 每把被持有的 `SleepLock` 的持有者
+    static ref HOLDERS: SpinMutex<BTreeMap<usize, Holder>> = SpinMutex::new(BTreeMap::new());
+    /// 每个阻塞在 `SleepLock` 上的任务等待的锁
+    static ref WAITERS: SpinMutex<BTreeMap<usize, Waiter>> = SpinMutex::new(BTreeMap::new());
+}
+
+/// 任务 `task` 在 `location` 处获得了锁 `lock`
+pub(super) fn lock_acquired(lock: usize, task: usize, location: &'static Location<'static>) {
+    HOLDERS.lock().insert(lock, Holder { task, location });
+}
+
+/// 锁 `lock` 被释放；若交给了等待者 `next`，记录新的持有者
+pub(super) fn lock_released(lock: usize, next: Option<usize>) {
+    let mut holders = HOLDERS.lock();
+    holders.remove(&lock);
+    let Some(task) = next else {
+        return;
+    };
+    if let Some(waiter) = WAITERS.lock().remove(&task) {
+        holders.insert(
+            lock,
+            Holder {
+                task,
+                location: waiter.location,
+            },
+        );
+    }
+}
+
+/// 任务 `task` 即将在 `location` 处阻塞等待锁 `lock`，等待会成环时打印持有者链并 panic
+pub(super) fn lock_wait(lock: usize, task: usize, location: &'static Location<'static>) {
+    let holders = HOLDERS.lock();
+    let mut waiters = WAITERS.lock();
+    let mut chain = Vec::new();
+    let mut current = lock;
+    while let Some(holder) = holders.get(&current) {
+        chain.push((current, holder));
+        if holder.task == task {
+            println!(
+                "[kernel] deadlock: task {:#x} waits for lock {:#x} at {}",
+                task, lock, location
+            );
+            for (lock, holder) in chain {
+                println!(
+                    "[kernel]   lock {:#x} held by task {:#x} since {}",
+                    lock, holder.task, holder.location
+                );
+            }
+            panic!("deadlock on kernel sleep locks");
+        }
+        match waiters.get(&holder.task) {
+            Some(waiter) => current = waiter.lock,
+            None => break,
+        }
+    }
+    waiters.insert(task, Waiter { lock, location });
+}
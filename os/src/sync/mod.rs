@@ -9,15 +9,20 @@
 //! - `mutex`：互斥锁抽象及其具体实现（自旋 / 阻塞）
 //! - `semaphore`：计数型信号量
 //! - `condvar`：条件变量
+//! - `spin`：屏蔽中断的自旋锁 `SpinMutex`，保护可能在中断处理程序中访问的短临界区
+//! - `sleep`：阻塞式数据锁 `SleepLock`，保护可能长时间持有（如包含 I/O）的数据
 //! - `up`：单处理器环境下的内部可变性与中断屏蔽封装
+//! - `deadlock`：调试用的死锁检测（`deadlock_detect` 特性）
 //!
 //! 该模块是内核并发控制的基础设施层，
 //! 负责在 **单处理器 + 中断并发模型** 下提供安全、可组合的同步机制。
+//! 为多处理器做准备，全局共享的数据结构逐步改用 `SpinMutex` / `SleepLock`，
+//! `UPIntrFreeCell` 只保留给真正属于单个处理器的状态。
 //!
 //! ## Assumptions
 //! - 系统运行在单处理器环境
 //! - 不存在真正的多核并行，仅可能被中断或调度切换打断
-//! - 所有同步原语都依赖 `UPIntrFreeCell` 提供的关中断互斥语义
+//! - 互斥锁、信号量、条件变量依赖 `UPIntrFreeCell` 提供的关中断互斥语义
 //!
 //! ## Safety
 //! - 所有 `unsafe impl Sync` 的正确性建立在“单处理器 + 中断屏蔽”假设之上
@@ -36,18 +41,32 @@
 //! - 模块本身不感知具体的任务调度策略
 
 mod condvar;
+#[cfg(feature = "deadlock_detect")]
+mod deadlock;
 mod mutex;
 mod semaphore;
+mod sleep;
+mod spin;
 mod up;
 
 /// 条件变量
 pub use condvar::Condvar;
 
+/// 用户态同步资源的死锁检测
+#[cfg(feature = "deadlock_detect")]
+pub use deadlock::{Resource, ResourceTracker};
+
 /// 互斥锁抽象与实现
 pub use mutex::{Mutex, MutexBlocking, MutexSpin};
 
 /// 计数型信号量
 pub use semaphore::Semaphore;
 
+/// 阻塞式数据锁
+pub use sleep::{SleepLock, SleepLockGuard};
+
+/// 屏蔽中断的自旋锁
+pub use spin::{SpinMutex, SpinMutexGuard};
+
 /// 单处理器内部可变性与中断屏蔽工具
 pub use up::{UPIntrFreeCell, UPIntrRefMut, UPSafeCellRaw};
@@ -0,0 +1,125 @@
+//! # 睡眠锁（SleepLock）
+//!
+//! ## Overview
+//! 保护共享数据的阻塞锁，适用于临界区较长（如包含块设备 I/O）的场景：
+//! - 锁空闲时 `lock` 立即返回守卫
+//! - 锁被占用时当前任务进入等待队列并阻塞，不忙等、不屏蔽中断
+//! - 守卫 drop 时若有等待任务，唤醒队首任务并把锁直接交给它，否则释放锁
+//!
+//! 与 `MutexBlocking` 的加解锁方式相同，但以守卫的形式保护数据，
+//! 锁的内部状态由 `SpinMutex` 保护
+//!
+//! ## Assumptions
+//! - 只在任务上下文中使用，不可在中断处理程序中获取
+//! - 锁被占用时必须存在当前任务；调度器启动前没有其他任务，锁不会被争用
+//! - 启用 `deadlock_detect` 特性时，阻塞前检查等待是否成环，见 `sync::deadlock`
+//!
+//! ## Invariants
+//! - `locked == false` ⇒ 等待队列为空
+//! - 等待队列中的任务一定处于阻塞状态，被唤醒时已经持有锁
+
+#[cfg(feature = "deadlock_detect")]
+use super::deadlock;
+use super::SpinMutex;
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// 阻塞式的数据锁
+pub struct SleepLock<T> {
+    /// 锁状态与等待队列
+    inner: SpinMutex<SleepLockInner>,
+    /// 被保护的数据
+    data: UnsafeCell<T>,
+}
+
+struct SleepLockInner {
+    locked: bool,
+    wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+unsafe impl<T> Sync for SleepLock<T> {}
+unsafe impl<T> Send for SleepLock<T> {}
+
+/// `SleepLock` 的守卫，drop 时释放锁或交给下一个等待者
+pub struct SleepLockGuard<'a, T> {
+    lock: &'a SleepLock<T>,
+}
+
+impl<T> SleepLock<T> {
+    /// 创建一个未上锁的睡眠锁
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: SpinMutex::new(SleepLockInner {
+                locked: false,
+                wait_queue: VecDeque::new(),
+            }),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// 获取锁，锁被占用时阻塞当前任务直到被唤醒
+    #[cfg_attr(feature = "deadlock_detect", track_caller)]
+    pub fn lock(&self) -> SleepLockGuard<'_, T> {
+        let mut inner = self.inner.lock();
+        if inner.locked {
+            let task = current_task().unwrap();
+            #[cfg(feature = "deadlock_detect")]
+            deadlock::lock_wait(self.id(), task_id(&task), core::panic::Location::caller());
+            inner.wait_queue.push_back(task);
+            drop(inner);
+            // 被唤醒时锁已经由解锁者转交给当前任务
+            block_current_and_run_next();
+        } else {
+            inner.locked = true;
+            #[cfg(feature = "deadlock_detect")]
+            deadlock::lock_acquired(
+                self.id(),
+                current_task().as_ref().map_or(0, task_id),
+                core::panic::Location::caller(),
+            );
+        }
+        SleepLockGuard { lock: self }
+    }
+
+    /// 死锁检测中标识这把锁
+    #[cfg(feature = "deadlock_detect")]
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+/// 死锁检测中标识任务
+#[cfg(feature = "deadlock_detect")]
+fn task_id(task: &Arc<TaskControlBlock>) -> usize {
+    Arc::as_ptr(task) as usize
+}
+
+impl<'a, T> Drop for SleepLockGuard<'a, T> {
+    fn drop(&mut self) {
+        let mut inner = self.lock.inner.lock();
+        assert!(inner.locked);
+        #[cfg(feature = "deadlock_detect")]
+        deadlock::lock_released(self.lock.id(), inner.wait_queue.front().map(task_id));
+        if let Some(waking_task) = inner.wait_queue.pop_front() {
+            wakeup_task(waking_task);
+        } else {
+            inner.locked = false;
+        }
+    }
+}
+
+impl<'a, T> Deref for SleepLockGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for SleepLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
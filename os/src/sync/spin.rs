@@ -0,0 +1,106 @@
+//! # 自旋锁（SpinMutex）
+//!
+//! ## Overview
+//! 保护共享数据的自旋锁，持有期间屏蔽中断：
+//! - `lock` 屏蔽中断后忙等获取锁，返回 RAII 守卫
+//! - 守卫 drop 时释放锁并恢复中断
+//!
+//! 与 `UPIntrFreeCell` 不同，互斥由原子变量而非 `RefCell` 的借用检查保证，
+//! 为多处理器做准备；临界区内不可阻塞或触发任务切换
+//!
+//! ## Assumptions
+//! - 临界区很短，且不会在临界区内再次获取同一把锁：
+//!   单处理器上的重入无法被其他处理器释放，会永远忙等
+//! - 中断处理程序中使用的锁，在任务上下文中持有时中断已被屏蔽，不会与中断处理程序互相等待
+//!
+//! ## Safety
+//! - `unsafe impl Sync` 的正确性由原子变量的获取 / 释放语义保证：
+//!   同一时刻至多一个守卫可以访问内部数据
+//!
+//! ## Invariants
+//! - `locked == true` 当且仅当存在一个未 drop 的 `SpinMutexGuard`
+//! - 守卫存活期间中断始终被屏蔽
+
+use crate::hal::INTR_MASKING_INFO;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 屏蔽中断的自旋锁
+pub struct SpinMutex<T> {
+    /// 锁状态
+    locked: AtomicBool,
+    /// 被保护的数据
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T> Sync for SpinMutex<T> {}
+unsafe impl<T> Send for SpinMutex<T> {}
+
+/// `SpinMutex` 的守卫，drop 时释放锁并恢复中断
+pub struct SpinMutexGuard<'a, T> {
+    mutex: &'a SpinMutex<T>,
+}
+
+impl<T> SpinMutex<T> {
+    /// 创建一个未上锁的自旋锁
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// 屏蔽中断并获取锁，锁被占用时忙等
+    pub fn lock(&self) -> SpinMutexGuard<'_, T> {
+        INTR_MASKING_INFO.get_mut().enter();
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                spin_loop();
+            }
+        }
+        SpinMutexGuard { mutex: self }
+    }
+
+    /// 尝试获取锁，锁被占用时返回 `None` 而不是忙等
+    ///
+    /// 用于 panic 处理等不能等待的路径
+    pub fn try_lock(&self) -> Option<SpinMutexGuard<'_, T>> {
+        INTR_MASKING_INFO.get_mut().enter();
+        match self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        {
+            Ok(_) => Some(SpinMutexGuard { mutex: self }),
+            Err(_) => {
+                INTR_MASKING_INFO.get_mut().exit();
+                None
+            }
+        }
+    }
+}
+
+impl<'a, T> Drop for SpinMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, Ordering::Release);
+        INTR_MASKING_INFO.get_mut().exit();
+    }
+}
+
+impl<'a, T> Deref for SpinMutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for SpinMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
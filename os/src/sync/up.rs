@@ -127,6 +127,20 @@ impl<T> UPIntrFreeCell<T> {
         UPIntrRefMut(Some(self.inner.borrow_mut()))
     }
 
+    /// 尝试获取独占访问权，已被借用时返回 `None` 而不是 panic
+    ///
+    /// 用于 panic 处理等不能再次 panic 的路径
+    pub fn try_exclusive_access(&self) -> Option<UPIntrRefMut<'_, T>> {
+        INTR_MASKING_INFO.get_mut().enter();
+        match self.inner.try_borrow_mut() {
+            Ok(inner) => Some(UPIntrRefMut(Some(inner))),
+            Err(_) => {
+                INTR_MASKING_INFO.get_mut().exit();
+                None
+            }
+        }
+    }
+
     /// 在独占访问会话中执行闭包
     ///
     /// ## Behavior
@@ -1,58 +1,166 @@
-use crate::fs::inode::{create_dir, OSInode, ROOT_DIR};
+use crate::fs::inode::{create_dir, current_root_inode, OSInode, ROOT_DIR};
 use crate::fs::{
-    make_pipe, open_dir, open_file, open_file_at, resolve_path, File, LinuxDirent64, OpenFlags, UserStat,
+    add_hard_link, block_cache_sync_all, drop_fifo, drop_file_meta, drop_page_cache,
+    file_meta_or_default, flock, follow, forget_ino, init_file_meta, ino_of, is_fifo, is_symlink,
+    lookup_path, make_fifo, make_pipe, make_symlink, mount, open_device, open_dir, open_fifo,
+    open_file, open_file_at, open_proc, posix_lock, posix_test, read_link, release_posix_locks,
+    rename_file_meta, rename_ino, rename_page_cache, rename_path, resolve_path, set_file_mode,
+    set_file_owner, splice, sync_page_caches, tee, umount, unlink, Epoll, EpollEvent, EventFd,
+    File, LinuxDirent64, LockKind, OpenFlags, Pipe, PollEvents, PosixLock, TimerFd, Unlink,
+    UserStat, EFD_CLOEXEC, EFD_NONBLOCK, EFD_SEMAPHORE, EPOLL_CLOEXEC, EPOLL_CTL_DEL, LOCK_TO_EOF,
+    POSIX_FADV_NOREUSE, R_OK, SPLICE_F_GIFT, SPLICE_F_MORE, SPLICE_F_MOVE, SPLICE_F_NONBLOCK,
+    TFD_CLOEXEC, TFD_NONBLOCK, TFD_TIMER_ABSTIME, W_OK, X_OK,
+};
+use crate::mm::{
+    copy_to_user, get_from_user, translated_byte_buffer, translated_str, try_read_bytes,
+    try_write_bytes, UserBuffer,
+};
+use crate::task::{
+    current_process, current_task, current_user_token, max_pid, pid2process,
+    set_signal_mask_of_current, signal_pending_of_current, suspend_current_and_run_next,
+    SignalFlags,
+};
+use crate::timer::{
+    get_time_us, ITimerSpec, TimeSpec, CLOCK_BOOTTIME, CLOCK_MONOTONIC, CLOCK_REALTIME,
+    NSEC_PER_SEC, NSEC_PER_USEC, USEC_PER_SEC,
 };
-use crate::mm::{copy_to_user, translated_byte_buffer,translated_refmut, translated_str, UserBuffer};
-use crate::task::{current_process, current_task, current_user_token};
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use bitflags::bitflags;
+use fatfs::SeekFrom;
 use log::info;
 
 pub const AT_FDCWD: usize = 100usize.wrapping_neg();
+pub const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+pub const AT_EACCESS: u32 = 0x200;
+pub const AT_SYMLINK_FOLLOW: u32 = 0x400;
+pub const AT_EMPTY_PATH: u32 = 0x1000;
 
-// 已实现
-// pub fn sys_getcwd(buf: *const u8, len: usize) -> *const u8 {
-//     let token = current_user_token();
-//     let process = current_process();
-//     let inner = process.inner_exclusive_access();
-//     let cwd = &inner.cwd;
-//     if cwd.len() + 1 > len {
-//         return core::ptr::null();
-//     }
-//     let mut buffer = UserBuffer::new(translated_byte_buffer(token, buf, len));
-//     buffer.write_string(cwd);
-//     buf
-// }
-
-pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
-    let token = current_user_token();
+/// fcntl 的命令
+const F_DUPFD: usize = 0;
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_GETLK: usize = 5;
+const F_SETLK: usize = 6;
+const F_SETLKW: usize = 7;
+const F_DUPFD_CLOEXEC: usize = 1030;
+const F_SETPIPE_SZ: usize = 1031;
+const F_GETPIPE_SZ: usize = 1032;
+/// 文件描述符标志：exec 时关闭
+const FD_CLOEXEC: usize = 1;
+
+/// `struct linux_dirent64` 中的文件类型
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+
+/// `struct flock` 中的锁类型
+const F_RDLCK: i16 = 0;
+const F_WRLCK: i16 = 1;
+const F_UNLCK: i16 = 2;
+
+/// `struct flock` 中 `l_start` 的基准
+const SEEK_SET: i16 = 0;
+const SEEK_CUR: i16 = 1;
+const SEEK_END: i16 = 2;
+
+/// flock 的操作
+const LOCK_SH: usize = 1;
+const LOCK_EX: usize = 2;
+const LOCK_NB: usize = 4;
+const LOCK_UN: usize = 8;
+
+/// 用户态的 `struct flock`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UserFlock {
+    pub l_type: i16,
+    pub l_whence: i16,
+    pub l_start: i64,
+    /// 为 0 表示一直到文件末尾之后，为负表示 `l_start` 之前的 `-l_len` 字节
+    pub l_len: i64,
+    pub l_pid: i32,
+}
+
+/// 按 `*at` 系列系统调用的约定把 `path` 解析为绝对路径
+///
+/// - 绝对路径忽略 `dirfd`；相对路径相对于 `dirfd` 指向的目录，`AT_FDCWD` 表示当前工作目录
+/// - `path` 为空且允许 `AT_EMPTY_PATH` 时，返回 `dirfd` 本身对应的路径
+pub(super) fn resolve_at(dirfd: usize, path: &str, empty_path: bool) -> Result<String, isize> {
     let process = current_process();
     let inner = process.inner_exclusive_access();
-    let cwd = &inner.cwd;
-    if cwd.len() + 1 > len {
-        // return core::ptr::null();
-        return -34;
+    if path.is_empty() {
+        if !empty_path {
+            return Err(-1); // ENOENT
+        }
+        if dirfd == AT_FDCWD {
+            return Ok(inner.cwd.clone());
+        }
+        return match inner.fd_table.get(dirfd) {
+            Some(Some(file)) => Ok(file.get_path()),
+            _ => Err(-1), // EBADF
+        };
+    }
+    let base = if path.starts_with('/') || dirfd == AT_FDCWD {
+        inner.cwd.clone()
+    } else {
+        match inner.fd_table.get(dirfd) {
+            Some(Some(file)) if file.is_dir() => file.get_path(),
+            Some(Some(_)) => return Err(-1), // ENOTDIR
+            _ => return Err(-1),             // EBADF
+        }
+    };
+    Ok(resolve_path(path, &base))
+}
+
+/// 打开 devfs 或 procfs 中的文件，路径不属于它们时返回 `None`
+fn open_special(full_path: &str) -> Option<Arc<dyn File + Send + Sync>> {
+    open_device(full_path).or_else(|| open_proc(full_path))
+}
+
+/// 查询路径是否存在（包括 devfs 与 procfs），存在时返回其是否为目录
+fn path_kind(full_path: &str) -> Option<bool> {
+    match open_special(full_path) {
+        Some(dev) => Some(dev.is_dir()),
+        None => lookup_path(full_path),
     }
-    let mut buffer = UserBuffer::new(translated_byte_buffer(token, buf, len));
-    buffer.write_string(cwd);
-    buf as isize
 }
 
-// cwd_inode更新逻辑，如果能打不开文件就崩溃，初始化为根目录
-pub fn sys_chdir(path: *const u8) -> isize {
+// 已实现
+/// 把工作目录的绝对路径（含结尾的 `\0`）写入 `buf`，成功时返回 `buf`
+///
+/// `len` 不足以容纳路径时返回 ERANGE，只写入路径所需的字节，这部分不可写时返回 EFAULT
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    // 写入用户缓冲区时可能缺页，不能持有 PCB
+    let cwd = current_process().inner_exclusive_access().cwd.clone();
+    let needed = cwd.len() + 1;
+    if len < needed {
+        return -1; // ERANGE
+    }
+    if buf.is_null() {
+        return -1; // EFAULT
+    }
+    let mut buffer = match translated_byte_buffer(token, buf, needed, true) {
+        Ok(buffers) => UserBuffer::new(buffers),
+        Err(_) => return -1, // EFAULT
+    };
+    buffer.write_string(&cwd);
+    buf as usize as isize
+}
 
-    //  计算新的 cwd（不打开目录）
-    let new_cwd: String = {
-        let process = current_process();
-        let inner = process.inner_exclusive_access();
-        resolve_path(path.as_str(), inner.cwd.as_str())
-    }; // inner 在这里自动 drop
+// cwd_inode 是工作目录的权威引用，相对路径从它的目录句柄出发查找；
+// cwd 只记录进入时的路径，供 getcwd 与 `*at` 系列拼接路径使用
+pub fn sys_chdir(path: *const u8) -> isize {
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
 
-    //  验证目录是否存在
-    let inode = match open_dir(new_cwd.as_str()) {
+    //  从当前工作目录出发打开目标目录
+    let inode = match open_dir(path.as_str()) {
         Ok(inode) => inode,
         Err(_) => return -1, // ENOENT / ENOTDIR
     };
@@ -60,15 +168,33 @@ pub fn sys_chdir(path: *const u8) -> isize {
     //  写回 PCB
     let process = current_process();
     let mut inner = process.inner_exclusive_access();
-    inner.cwd = new_cwd;
+    inner.cwd = inode.get_path();
     inner.cwd_inode = inode;
 
     0
 }
 
+/// 把工作目录切换到 `fd` 指向的目录
+pub fn sys_fchdir(fd: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    if !file.is_dir() {
+        return -1; // ENOTDIR
+    }
+    inner.cwd = file.get_path();
+    inner.cwd_inode = file;
+    0
+}
+
 pub fn sys_mkdirat(dirfd: isize, path: *const u8, mode: u32) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
 
     let process = current_process();
     let inner = process.inner_exclusive_access();
@@ -92,19 +218,32 @@ pub fn sys_mkdirat(dirfd: isize, path: *const u8, mode: u32) -> isize {
 
         fd.get_path()
     };
+    let (umask, cred) = (inner.umask, inner.cred);
     drop(inner);
     //  拼接最终路径
     let full_path = resolve_path(&path, &base_path);
 
     // 创建目录
     match create_dir(&full_path) {
-        Ok(_) => 0,
+        Ok(_) => {
+            init_file_meta(&full_path, mode, umask, cred.euid, cred.egid);
+            0
+        }
         Err(_) => {
             println!("[sys_mkdirat]Failed to create directory: {},Maybe existed", &full_path);
             -1
         },
     }
 }
+/// 设置文件创建掩码，返回原来的掩码
+pub fn sys_umask(mask: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old = inner.umask;
+    inner.umask = mask & 0o777;
+    old as isize
+}
+
 ///复制文件描述符
 pub fn sys_dup(fd: usize) -> isize {
     let process = current_process();
@@ -120,29 +259,20 @@ pub fn sys_dup(fd: usize) -> isize {
         None => return -1,
     };
 
-    // 找最小可用 fd
-    let new_fd = inner
-        .fd_table
-        .iter()
-        .position(|f| f.is_none())
-        .unwrap_or(inner.fd_table.len());
-
-    //  插入
-    if new_fd == inner.fd_table.len() {
-        inner.fd_table.push(Some(file));
-    } else {
-        inner.fd_table[new_fd] = Some(file);
-    }
+    // 找最小可用 fd，复制出的描述符不继承 FD_CLOEXEC
+    let Some(new_fd) = inner.alloc_fd() else {
+        return -1; // EMFILE
+    };
+    inner.fd_table[new_fd] = Some(file);
 
     new_fd as isize
 }
 
-///复制文件描述符，并指定新的文件描述符
-/// 后续需要添加flags相关的操作，目前测试文件只有dup2
+///复制文件描述符，并指定新的文件描述符，flags 只允许 O_CLOEXEC
 pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
-    //  flags 校验（最小实现）
-    if flags != 0 {
-        return -1;
+    //  flags 校验
+    if flags & !(OpenFlags::CLOEXEC.bits() as usize) != 0 {
+        return -1; // EINVAL
     }
 
     let process = current_process();
@@ -163,6 +293,11 @@ pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
         return -1;
     }
 
+    //  new_fd 不能超过 RLIMIT_NOFILE
+    if new_fd >= inner.fd_limit() {
+        return -1; // EBADF
+    }
+
     //  扩展 fd_table
     if new_fd >= inner.fd_table.len() {
         inner.fd_table.resize(new_fd + 1, None);
@@ -173,10 +308,186 @@ pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
 
     //  复制 fd
     inner.fd_table[new_fd] = Some(file);
+    inner.set_cloexec(new_fd, flags != 0);
 
     new_fd as isize
 }
 
+/// 文件描述符控制，支持复制描述符、读写 FD_CLOEXEC、POSIX 记录锁与管道容量
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    if matches!(cmd, F_GETLK | F_SETLK | F_SETLKW) {
+        // F_SETLKW 可能阻塞，不能持有 PCB
+        drop(inner);
+        return fcntl_lock(file, cmd, arg as *mut UserFlock);
+    }
+    match cmd {
+        F_DUPFD | F_DUPFD_CLOEXEC => {
+            // 不小于 arg 的最小可用描述符，不能超过 RLIMIT_NOFILE
+            let limit = inner.fd_limit();
+            if arg >= limit {
+                return -1; // EINVAL
+            }
+            let Some(new_fd) =
+                (arg..limit).find(|&fd| inner.fd_table.get(fd).map_or(true, |f| f.is_none()))
+            else {
+                return -1; // EMFILE
+            };
+            if new_fd >= inner.fd_table.len() {
+                inner.fd_table.resize(new_fd + 1, None);
+            }
+            inner.fd_table[new_fd] = Some(file);
+            inner.set_cloexec(new_fd, cmd == F_DUPFD_CLOEXEC);
+            new_fd as isize
+        }
+        F_GETFD => {
+            if inner.cloexec_fds.contains(&fd) {
+                FD_CLOEXEC as isize
+            } else {
+                0
+            }
+        }
+        F_SETFD => {
+            inner.set_cloexec(fd, arg & FD_CLOEXEC != 0);
+            0
+        }
+        // 只报告访问模式
+        F_GETFL => match (file.readable(), file.writable()) {
+            (true, true) => OpenFlags::RDWR.bits() as isize,
+            (false, true) => OpenFlags::WRONLY.bits() as isize,
+            _ => OpenFlags::RDONLY.bits() as isize,
+        },
+        F_GETPIPE_SZ | F_SETPIPE_SZ => {
+            let Some(pipe) = file.as_any().downcast_ref::<Pipe>() else {
+                return -1; // EBADF
+            };
+            if cmd == F_GETPIPE_SZ {
+                pipe.capacity() as isize
+            } else {
+                pipe.set_capacity(arg)
+            }
+        }
+        _ => -1, // EINVAL
+    }
+}
+
+/// fcntl 的 F_GETLK / F_SETLK / F_SETLKW，只支持 FAT32 上的普通文件
+fn fcntl_lock(file: Arc<dyn File + Send + Sync>, cmd: usize, arg: *mut UserFlock) -> isize {
+    let Some(inode) = file.as_any().downcast_ref::<OSInode>() else {
+        return -1; // EINVAL
+    };
+    let token = current_user_token();
+    let Ok(user_lock) = get_from_user(token, arg as *const UserFlock) else {
+        return -1; // EFAULT
+    };
+    let base = match user_lock.l_whence {
+        SEEK_SET => 0,
+        SEEK_CUR => inode.offset() as i64,
+        SEEK_END => inode.get_stat().st_size,
+        _ => return -1, // EINVAL
+    };
+    let start = base + user_lock.l_start;
+    let (start, end) = match user_lock.l_len {
+        0 => (start, LOCK_TO_EOF as i64),
+        len if len > 0 => (start, start.saturating_add(len)),
+        len => (start + len, start),
+    };
+    if start < 0 {
+        return -1; // EINVAL
+    }
+    let (start, end) = (start as u64, end as u64);
+    let kind = match user_lock.l_type {
+        F_RDLCK => Some(LockKind::Shared),
+        F_WRLCK => Some(LockKind::Exclusive),
+        F_UNLCK => None,
+        _ => return -1, // EINVAL
+    };
+    let pid = current_process().getpid();
+    let path = inode.get_path();
+    if cmd == F_GETLK {
+        let Some(kind) = kind else {
+            return -1; // EINVAL
+        };
+        let req = PosixLock {
+            pid,
+            kind,
+            start,
+            end,
+        };
+        let reply = match posix_test(&path, &req) {
+            Some(lock) => UserFlock {
+                l_type: match lock.kind {
+                    LockKind::Shared => F_RDLCK,
+                    LockKind::Exclusive => F_WRLCK,
+                },
+                l_whence: SEEK_SET,
+                l_start: lock.start as i64,
+                l_len: match lock.end {
+                    LOCK_TO_EOF => 0,
+                    end => (end - lock.start) as i64,
+                },
+                l_pid: lock.pid as i32,
+            },
+            None => UserFlock {
+                l_type: F_UNLCK,
+                ..user_lock
+            },
+        };
+        if copy_to_user(token, &reply, arg).is_err() {
+            return -1; // EFAULT
+        }
+        return 0;
+    }
+    // 读锁要求以读方式打开，写锁要求以写方式打开
+    match kind {
+        Some(LockKind::Shared) if !file.readable() => return -1, // EBADF
+        Some(LockKind::Exclusive) if !file.writable() => return -1, // EBADF
+        _ => {}
+    }
+    posix_lock(&path, pid, kind, start, end, cmd == F_SETLKW)
+}
+
+/// 整个文件的劝告锁，属于打开的文件，只支持 FAT32 上的普通文件
+pub fn sys_flock(fd: usize, operation: usize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    drop(inner);
+    let Some(inode) = file.as_any().downcast_ref::<OSInode>() else {
+        return -1; // EINVAL
+    };
+    let kind = match operation & !LOCK_NB {
+        LOCK_SH => Some(LockKind::Shared),
+        LOCK_EX => Some(LockKind::Exclusive),
+        LOCK_UN => None,
+        _ => return -1, // EINVAL
+    };
+    // 以打开的文件的地址区分持有者，与 OSInode 的 Drop 中释放时一致
+    let owner = inode as *const OSInode as usize;
+    flock(&inode.get_path(), owner, kind, operation & LOCK_NB != 0)
+}
+
+/// 设备控制，由文件自己解释 `cmd` 与 `arg`
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    drop(inner);
+    file.ioctl(cmd, arg)
+}
+
+/// 读取目录项，从上次读到的位置开始尽量填满 `buf`，目录已读完时返回 0
 pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
     let process = current_process();
     let inner = process.inner_exclusive_access();
@@ -193,46 +504,40 @@ pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
         return -1;
     }
     // 至少能放下一个 dirent
-    if len < core::mem::size_of::<LinuxDirent64>() {
+    let reclen = core::mem::size_of::<LinuxDirent64>();
+    if len < reclen {
         return -1;
     }
     drop(inner);
-    //  读取目录
-    let dir = file.as_any().downcast_ref::<OSInode>(); // Vec<String>
-    let dir_inode = match dir {
-        Some(dir) => dir,
-        None => return -1,
-    };
-    let entries = match dir_inode.list_dir() {
-        Ok(entries) => entries,
-        Err(_) => return -1,
-    };
-
-    if entries.is_empty() {
-        return 0;
-    }
-
-    let entry = &entries[0];
-    let name = entry.d_name.as_bytes();
-
-    let mut dirent = LinuxDirent64 {
-        d_ino: 1,
-        d_off: 0,
-        d_reclen: core::mem::size_of::<LinuxDirent64>() as u16,
-        d_type: 4,
-        d_name: [0; 256],
-    };
-
-    let copy_len = name.len().min(255);
-    dirent.d_name[..copy_len].copy_from_slice(&name[..copy_len]);
-
-    // 拷贝到用户态
+    let dir_path = file.get_path();
     let token = current_user_token();
-    if copy_to_user(token, &dirent, buf as *mut LinuxDirent64).is_err() {
-        log::error!("[sys_fstat] Failed to copy to {:?}", buf);
-        -1;
+    let mut written = 0;
+    // 每项都是定长的，先确认放得下再取下一项，不会丢掉已经取出的项
+    while written + reclen <= len {
+        let entry = match file.read_dir() {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) if written == 0 => return e,
+            Err(_) => break,
+        };
+        let name = entry.d_name.as_bytes();
+        let mut dirent = LinuxDirent64 {
+            d_ino: ino_of(&resolve_path(&entry.d_name, &dir_path)),
+            d_off: (written + reclen) as i64,
+            d_reclen: reclen as u16,
+            d_type: if entry.is_dir { DT_DIR } else { DT_REG },
+            d_name: [0; 256],
+        };
+        let copy_len = name.len().min(255);
+        dirent.d_name[..copy_len].copy_from_slice(&name[..copy_len]);
+        // 拷贝到用户态
+        let dst = unsafe { buf.add(written) } as *mut LinuxDirent64;
+        if copy_to_user(token, &dirent, dst).is_err() {
+            return -1; // EFAULT
+        }
+        written += reclen;
     }
-    core::mem::size_of::<LinuxDirent64>() as isize
+    written as isize
 }
 
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
@@ -249,7 +554,16 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
         }
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        let buffers = match translated_byte_buffer(token, buf, len, true) {
+            Ok(buffers) => buffers,
+            Err(e) => return e,
+        };
+        let n = file.read(UserBuffer::new(buffers));
+        // 阻塞的读被信号打断且没有读到数据
+        if n == 0 && len > 0 && signal_pending_of_current() {
+            return -1; // EINTR
+        }
+        n as isize
     } else {
         -1
     }
@@ -269,12 +583,355 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
         let file = file.clone();
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        let buffers = match translated_byte_buffer(token, buf, len, false) {
+            Ok(buffers) => buffers,
+            Err(e) => return e,
+        };
+        let n = file.write(UserBuffer::new(buffers));
+        if n == 0 && len > 0 && signal_pending_of_current() {
+            return -1; // EINTR
+        }
+        n as isize
     } else {
         -1
     }
 }
 
+/// 用户态 `struct iovec`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IoVec {
+    /// 缓冲区起始地址
+    pub iov_base: usize,
+    /// 缓冲区长度
+    pub iov_len: usize,
+}
+
+/// 单次 readv/writev 允许的最大 iovec 数量（Linux UIO_MAXIOV）
+pub(super) const IOV_MAX: usize = 1024;
+
+/// 分散读：依次填充 iovec 数组描述的各个缓冲区
+///
+/// 某一段读到的字节数少于该段长度时（如管道暂时没有更多数据）立即返回已读总数
+pub fn sys_readv(fd: usize, iov: *const IoVec, iovcnt: usize) -> isize {
+    if iovcnt > IOV_MAX {
+        return -1;
+    }
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1,
+    };
+    if !file.readable() {
+        return -1;
+    }
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    let mut total = 0usize;
+    for i in 0..iovcnt {
+        let Ok(vec) = get_from_user(token, unsafe { iov.add(i) }) else {
+            return -1; // EFAULT
+        };
+        if vec.iov_len == 0 {
+            continue;
+        }
+        // 已经读入数据时返回读入的总数，与 Linux 一致
+        let buffers =
+            match translated_byte_buffer(token, vec.iov_base as *const u8, vec.iov_len, true) {
+                Ok(buffers) => buffers,
+                Err(e) if total == 0 => return e,
+                Err(_) => break,
+            };
+        let read = file.read(UserBuffer::new(buffers));
+        total += read;
+        if read < vec.iov_len {
+            break;
+        }
+    }
+    total as isize
+}
+
+/// 聚集写：依次写出 iovec 数组描述的各个缓冲区
+///
+/// 与 write 一致允许部分写：某一段只写出一部分时（如非阻塞管道已满）立即返回已写总数
+pub fn sys_writev(fd: usize, iov: *const IoVec, iovcnt: usize) -> isize {
+    if iovcnt > IOV_MAX {
+        return -1;
+    }
+    let token = current_user_token();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1,
+    };
+    if !file.writable() {
+        return -1;
+    }
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    let mut total = 0usize;
+    for i in 0..iovcnt {
+        let Ok(vec) = get_from_user(token, unsafe { iov.add(i) }) else {
+            return -1; // EFAULT
+        };
+        if vec.iov_len == 0 {
+            continue;
+        }
+        let buffers =
+            match translated_byte_buffer(token, vec.iov_base as *const u8, vec.iov_len, false) {
+                Ok(buffers) => buffers,
+                Err(e) if total == 0 => return e,
+                Err(_) => break,
+            };
+        let written = file.write(UserBuffer::new(buffers));
+        total += written;
+        if written < vec.iov_len {
+            break;
+        }
+    }
+    total as isize
+}
+
+/// 用户态 `struct pollfd`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+/// pselect6 的第 6 个参数：信号集的地址与长度
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PselectSigmask {
+    pub ss: usize,
+    pub ss_len: usize,
+}
+
+/// 单次 ppoll / pselect6 允许的最大描述符数（与 Linux FD_SETSIZE 一致）
+const POLL_MAX_FDS: usize = 1024;
+
+/// 读入 ppoll / pselect6 的超时并换算为微秒，空指针表示无限等待
+fn read_poll_timeout(token: usize, timeout: *const TimeSpec) -> Result<Option<usize>, isize> {
+    if timeout.is_null() {
+        return Ok(None);
+    }
+    let ts = get_from_user(token, timeout)?;
+    if ts.tv_nsec >= NSEC_PER_SEC {
+        return Err(-1); // EINVAL
+    }
+    let us = ts.tv_sec.saturating_mul(USEC_PER_SEC);
+    Ok(Some(us.saturating_add(ts.tv_nsec.div_ceil(NSEC_PER_USEC))))
+}
+
+/// 读入 ppoll / pselect6 的信号集，空指针表示不替换信号屏蔽字
+fn read_sigmask(
+    token: usize,
+    sigmask: *const u64,
+    size: usize,
+) -> Result<Option<SignalFlags>, isize> {
+    if sigmask.is_null() {
+        return Ok(None);
+    }
+    if size != core::mem::size_of::<u64>() {
+        return Err(-1); // EINVAL
+    }
+    let set = get_from_user(token, sigmask)?;
+    Ok(Some(SignalFlags::from_bits_truncate(set as u32)))
+}
+
+/// ppoll / pselect6 的等待循环：`ready` 返回就绪的个数，直到非零、超时或被信号打断
+///
+/// `sigmask` 在第一次检查之前安装，返回之前恢复，因此在等待期间到达、被新屏蔽字放行的信号
+/// 一定会在某次检查中被发现；被临时屏蔽、而原屏蔽字放行的信号在返回用户态时处理
+fn poll_wait(
+    timeout_us: Option<usize>,
+    sigmask: Option<SignalFlags>,
+    mut ready: impl FnMut() -> usize,
+) -> isize {
+    let deadline = timeout_us.map(|us| get_time_us().saturating_add(us));
+    let old_mask = sigmask.map(set_signal_mask_of_current);
+    let ret = loop {
+        let n = ready();
+        if n > 0 {
+            break n as isize;
+        }
+        if deadline.is_some_and(|deadline| get_time_us() >= deadline) {
+            break 0;
+        }
+        if signal_pending_of_current() {
+            break -1; // EINTR
+        }
+        suspend_current_and_run_next();
+    };
+    if let Some(mask) = old_mask {
+        set_signal_mask_of_current(mask);
+    }
+    ret
+}
+
+/// 等待一组描述符上的事件，`sigmask` 非空时在等待期间临时替换信号屏蔽字
+pub fn sys_ppoll(
+    fds: *mut PollFd,
+    nfds: usize,
+    timeout: *const TimeSpec,
+    sigmask: *const u64,
+    sigsetsize: usize,
+) -> isize {
+    if nfds > POLL_MAX_FDS {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let timeout = match read_poll_timeout(token, timeout) {
+        Ok(timeout) => timeout,
+        Err(err) => return err,
+    };
+    let sigmask = match read_sigmask(token, sigmask, sigsetsize) {
+        Ok(sigmask) => sigmask,
+        Err(err) => return err,
+    };
+    let pollfds: Result<Vec<PollFd>, isize> = (0..nfds)
+        .map(|i| get_from_user(token, fds.wrapping_add(i)))
+        .collect();
+    let mut pollfds = match pollfds {
+        Ok(pollfds) => pollfds,
+        Err(err) => return err,
+    };
+    let ret = poll_wait(timeout, sigmask, || {
+        let process = current_process();
+        let inner = process.inner_exclusive_access();
+        let mut ready = 0;
+        for pollfd in pollfds.iter_mut() {
+            pollfd.revents = 0;
+            // 负的描述符被忽略
+            if pollfd.fd < 0 {
+                continue;
+            }
+            let revents = match inner.fd_table.get(pollfd.fd as usize) {
+                Some(Some(file)) => {
+                    let requested = PollEvents::from_bits_truncate(pollfd.events)
+                        | PollEvents::POLLERR
+                        | PollEvents::POLLHUP;
+                    file.poll() & requested
+                }
+                _ => PollEvents::POLLNVAL,
+            };
+            pollfd.revents = revents.bits();
+            ready += !revents.is_empty() as usize;
+        }
+        ready
+    });
+    for (i, pollfd) in pollfds.iter().enumerate() {
+        if copy_to_user(token, pollfd, fds.wrapping_add(i)).is_err() {
+            return -1; // EFAULT
+        }
+    }
+    ret
+}
+
+/// `fd_set` 按 64 位字存放，位 `fd` 对应描述符 `fd`
+fn fd_set_contains(set: &[u8], fd: usize) -> bool {
+    set[fd / 8] & (1 << (fd % 8)) != 0
+}
+
+/// 等待描述符可读、可写或有异常，`sigmask` 非空时在等待期间临时替换信号屏蔽字
+///
+/// 返回时三个集合只保留就绪的描述符，超时返回 0 并清空集合
+pub fn sys_pselect6(
+    nfds: usize,
+    readfds: *mut u8,
+    writefds: *mut u8,
+    exceptfds: *mut u8,
+    timeout: *const TimeSpec,
+    sigmask: *const PselectSigmask,
+) -> isize {
+    if nfds > POLL_MAX_FDS {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let timeout = match read_poll_timeout(token, timeout) {
+        Ok(timeout) => timeout,
+        Err(err) => return err,
+    };
+    let sigmask = if sigmask.is_null() {
+        Ok(None)
+    } else {
+        get_from_user(token, sigmask)
+            .and_then(|arg| read_sigmask(token, arg.ss as *const u64, arg.ss_len))
+    };
+    let sigmask = match sigmask {
+        Ok(sigmask) => sigmask,
+        Err(err) => return err,
+    };
+    let set_len = nfds.div_ceil(64) * 8;
+    let user_sets = [readfds, writefds, exceptfds];
+    let mut sets: [Option<Vec<u8>>; 3] = [None, None, None];
+    for (set, &user_set) in sets.iter_mut().zip(user_sets.iter()) {
+        if user_set.is_null() {
+            continue;
+        }
+        let mut bytes = vec![0u8; set_len];
+        if try_read_bytes(token, user_set as usize, &mut bytes).is_none() {
+            return -1; // EFAULT
+        }
+        *set = Some(bytes);
+    }
+    {
+        let process = current_process();
+        let inner = process.inner_exclusive_access();
+        for set in sets.iter().flatten() {
+            let closed = (0..nfds).any(|fd| {
+                fd_set_contains(set, fd) && !matches!(inner.fd_table.get(fd), Some(Some(_)))
+            });
+            if closed {
+                return -1; // EBADF
+            }
+        }
+    }
+    let wanted = [
+        PollEvents::POLLIN | PollEvents::POLLHUP | PollEvents::POLLERR,
+        PollEvents::POLLOUT | PollEvents::POLLERR,
+        PollEvents::POLLPRI,
+    ];
+    let mut results: [Option<Vec<u8>>; 3] = [None, None, None];
+    let ret = poll_wait(timeout, sigmask, || {
+        let process = current_process();
+        let inner = process.inner_exclusive_access();
+        let mut ready = 0;
+        for ((set, result), wanted) in sets.iter().zip(results.iter_mut()).zip(wanted) {
+            let Some(set) = set else {
+                continue;
+            };
+            let result = result.insert(vec![0u8; set_len]);
+            for fd in (0..nfds).filter(|&fd| fd_set_contains(set, fd)) {
+                let Some(Some(file)) = inner.fd_table.get(fd) else {
+                    continue;
+                };
+                if file.poll().intersects(wanted) {
+                    result[fd / 8] |= 1 << (fd % 8);
+                    ready += 1;
+                }
+            }
+        }
+        ready
+    });
+    if ret < 0 {
+        return ret;
+    }
+    for (result, &user_set) in results.iter().zip(user_sets.iter()) {
+        if let Some(result) = result {
+            if try_write_bytes(token, user_set as usize, result).is_none() {
+                return -1; // EFAULT
+            }
+        }
+    }
+    ret
+}
+
 pub fn sys_close(fd: usize) -> isize {
     let process = current_process();
     let mut inner = process.inner_exclusive_access();
@@ -284,7 +941,13 @@ pub fn sys_close(fd: usize) -> isize {
     if inner.fd_table[fd].is_none() {
         return -1;
     }
-    inner.fd_table[fd].take();
+    let file = inner.fd_table[fd].take().unwrap();
+    inner.set_cloexec(fd, false);
+    drop(inner);
+    // 关闭任意一个描述符都会释放进程在该文件上的 POSIX 锁
+    if let Some(inode) = file.as_any().downcast_ref::<OSInode>() {
+        release_posix_locks(&inode.get_path(), process.getpid());
+    }
     0
 }
 
@@ -292,14 +955,30 @@ pub fn sys_close(fd: usize) -> isize {
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
     let process = current_process();
     let token = current_user_token();
-    let path = translated_str(token, path);
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
     let flags = match OpenFlags::from_bits(flags) {
         Some(f) => f,
         None => return -1,
     };
+    if let Err(err) = flags.validate() {
+        return err;
+    }
+    let full_path = resolve_path(path.as_str(), process.inner_exclusive_access().cwd.as_str());
+    if let Some(dev) = open_special(full_path.as_str()) {
+        let mut inner = process.inner_exclusive_access();
+        let Some(fd) = inner.alloc_fd() else {
+            return -1; // EMFILE
+        };
+        inner.fd_table[fd] = Some(dev);
+        return fd as isize;
+    }
     if let Some(inode) = open_file(path.as_str(), flags) {
         let mut inner = process.inner_exclusive_access();
-        let fd = inner.alloc_fd();
+        let Some(fd) = inner.alloc_fd() else {
+            return -1; // EMFILE
+        };
         inner.fd_table[fd] = Some(inode);
         fd as isize
     } else {
@@ -312,52 +991,109 @@ pub fn sys_openat(dirfd: usize, path: *const u8, flags: u32, mode: u32) -> isize
     let token = task.get_user_token();
     let process = task.process.upgrade().unwrap();
     let mut inner = process.inner_exclusive_access();
-    let path = translated_str(token, path);
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
     let flags = match OpenFlags::from_bits(flags) {
         Some(flags) => flags,
         None => {
             return -1; //EINVAL;
         }
     };
+    if let Err(err) = flags.validate() {
+        return err;
+    }
+    let create_mode = mode;
     let mode = StatMode::from_bits(mode);
 
-    // let file_descriptor = inner.cwd ;
-    //let base_dir = inner.cwd.clone();
+    // 相对路径从工作目录或 dirfd 指向的目录的句柄出发查找
     let base_dir = if dirfd == AT_FDCWD {
-        inner.cwd.clone()
+        inner.cwd_inode.clone()
     } else {
         // 从 fd_table 查找 dirfd 对应的目录
         match inner.fd_table.get(dirfd) {
-            Some(Some(file)) if file.is_dir() => {
-                // 假设 File trait 有 get_path 方法
-                file.get_path()
-            }
+            Some(Some(file)) if file.is_dir() => file.clone(),
             _ => return -1, // EBADF
         }
     };
+    let base_path = if dirfd == AT_FDCWD {
+        inner.cwd.clone()
+    } else {
+        base_dir.get_path()
+    };
+    let full_path = resolve_path(&path, &base_path);
+    // `/dev` 与 `/proc` 下的路径由 devfs 与 procfs 处理，生成 procfs 的内容时可能访问当前进程的 PCB
+    drop(inner);
+    let special = open_special(&full_path);
+    let mut inner = process.inner_exclusive_access();
+    if let Some(dev) = special {
+        if flags.contains(OpenFlags::DIRECTORY) && !dev.is_dir() {
+            return -1; // ENOTDIR
+        }
+        let Some(fd) = inner.alloc_fd() else {
+            return -1; // EMFILE
+        };
+        inner.fd_table[fd] = Some(dev);
+        inner.set_cloexec(fd, flags.contains(OpenFlags::CLOEXEC));
+        return fd as isize;
+    }
+    if flags.contains(OpenFlags::NOFOLLOW) && is_symlink(&full_path) {
+        return -1; // ELOOP
+    }
+    if is_fifo(&full_path) {
+        if flags.contains(OpenFlags::DIRECTORY) {
+            return -1; // ENOTDIR
+        }
+        let cloexec = flags.contains(OpenFlags::CLOEXEC);
+        // 等待对端打开时不能持有 PCB
+        drop(inner);
+        let fifo = match open_fifo(&full_path, flags) {
+            Ok(fifo) => fifo,
+            Err(err) => return err,
+        };
+        let mut inner = process.inner_exclusive_access();
+        let Some(fd) = inner.alloc_fd() else {
+            return -1; // EMFILE
+        };
+        inner.fd_table[fd] = Some(fifo);
+        inner.set_cloexec(fd, cloexec);
+        return fd as isize;
+    }
+    // O_CREAT 新建的文件按 umask 记录权限
+    let created = flags.contains(OpenFlags::CREATE) && lookup_path(&full_path).is_none();
     // 调用 open_file_at 打开文件
     // 判断是否是 O_DIRECTORY
     if flags.contains(OpenFlags::DIRECTORY) {
         // 假设 OpenFlags 有 DIRECTORY 标志
         // 如果是 O_DIRECTORY，调用 open_dir_at 或类似逻辑
         // 但由于 open_file_at 已经能返回目录的 OSInode，可以直接调用
-        match open_file_at(&base_dir, &path, flags, mode.unwrap()) {
+        match open_file_at(&*base_dir, &path, flags, mode.unwrap()) {
             Some(inode) if inode.is_dir() => {
                 // 如果是目录，分配 fd 并返回
-                let fd = inner.alloc_fd();
+                let Some(fd) = inner.alloc_fd() else {
+                    return -1; // EMFILE
+                };
                 let file: Arc<dyn File + Send + Sync> = inode;
                 inner.fd_table[fd] = Some(file);
+                inner.set_cloexec(fd, flags.contains(OpenFlags::CLOEXEC));
                 fd as isize
             }
             _ => -1, // 不是目录或打开失败
         }
     } else {
         // 不是 O_DIRECTORY，按文件处理
-        match open_file_at(&base_dir, &path, flags, mode.unwrap()) {
+        match open_file_at(&*base_dir, &path, flags, mode.unwrap()) {
             Some(inode) => {
-                let fd = inner.alloc_fd();
+                if created {
+                    let cred = inner.cred;
+                    init_file_meta(&full_path, create_mode, inner.umask, cred.euid, cred.egid);
+                }
+                let Some(fd) = inner.alloc_fd() else {
+                    return -1; // EMFILE
+                };
                 let file: Arc<dyn File + Send + Sync> = inode;
                 inner.fd_table[fd] = Some(file);
+                inner.set_cloexec(fd, flags.contains(OpenFlags::CLOEXEC));
                 fd as isize
             }
             None => -1,
@@ -391,6 +1127,48 @@ pub fn sys_fstat(fd: usize, statbuf: *mut u8) -> isize {
     0
 }
 
+/// 查询 `path` 的文件状态（newfstatat）
+///
+/// `path` 为空且带 `AT_EMPTY_PATH` 时查询 `dirfd` 本身，与 fstat 相同；
+/// 带 `AT_SYMLINK_NOFOLLOW` 时查询符号链接本身而不是其目标
+pub fn sys_fstatat(dirfd: usize, path: *const u8, statbuf: *mut u8, flags: u32) -> isize {
+    if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    if path.is_empty() && flags & AT_EMPTY_PATH != 0 {
+        return sys_fstat(dirfd, statbuf);
+    }
+    let full_path = match resolve_at(dirfd, &path, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    let open_flags = if flags & AT_SYMLINK_NOFOLLOW != 0 {
+        OpenFlags::RDONLY | OpenFlags::NOFOLLOW
+    } else {
+        OpenFlags::RDONLY
+    };
+    let file: Arc<dyn File + Send + Sync> = match open_special(&full_path) {
+        Some(file) => file,
+        None => match open_file_at(
+            &*current_root_inode(),
+            &full_path,
+            open_flags,
+            StatMode::empty(),
+        ) {
+            Some(inode) => inode,
+            None => return -1, // ENOENT
+        },
+    };
+    if copy_to_user(token, &file.get_stat(), statbuf as *mut UserStat).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
 bitflags! {
     pub struct StatMode: u32 {
         ///bit mask for the file type bit field
@@ -463,22 +1241,302 @@ pub fn sys_pipe2(pipefd: usize, flags: u32) -> isize {
         pipe_read.set_nonblocking(true);
         pipe_write.set_nonblocking(true);
     }
-    let read_fd = inner.alloc_fd();
+    let cloexec = openflags.contains(OpenFlags::CLOEXEC);
+    let Some(read_fd) = inner.alloc_fd() else {
+        return -1; // EMFILE
+    };
     inner.fd_table[read_fd] = Some(pipe_read);
-    let write_fd = inner.alloc_fd();
+    inner.set_cloexec(read_fd, cloexec);
+    let Some(write_fd) = inner.alloc_fd() else {
+        inner.fd_table[read_fd] = None;
+        return -1; // EMFILE
+    };
     inner.fd_table[write_fd] = Some(pipe_write);
-    let pipe_ptr = pipefd as *mut i32;
-    *translated_refmut(token, pipe_ptr) = read_fd as i32;
-    *translated_refmut(token, unsafe { pipe_ptr.add(1) }) = write_fd as i32;
+    inner.set_cloexec(write_fd, cloexec);
+    // 写入用户缓冲区时可能缺页，不能持有 PCB
+    drop(inner);
+    let fds = [read_fd as i32, write_fd as i32];
+    if copy_to_user(token, &fds, pipefd as *mut [i32; 2]).is_err() {
+        sys_close(read_fd);
+        sys_close(write_fd);
+        return -1; // EFAULT
+    }
     0
 }
+/// 把文件安装到本进程的文件描述符表，返回新的文件描述符，描述符用尽时返回 EMFILE
+pub(super) fn install_file(file: Arc<dyn File + Send + Sync>, cloexec: bool) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let Some(fd) = inner.alloc_fd() else {
+        return -1; // EMFILE
+    };
+    inner.fd_table[fd] = Some(file);
+    inner.set_cloexec(fd, cloexec);
+    fd as isize
+}
+
+/// 创建计数器初值为 `initval` 的 eventfd，`flags` 可以含 `EFD_SEMAPHORE`、`EFD_NONBLOCK` 与 `EFD_CLOEXEC`
+pub fn sys_eventfd2(initval: u32, flags: u32) -> isize {
+    if flags & !(EFD_SEMAPHORE | EFD_NONBLOCK | EFD_CLOEXEC) != 0 {
+        return -1; // EINVAL
+    }
+    let eventfd = EventFd::new(
+        initval as u64,
+        flags & EFD_SEMAPHORE != 0,
+        flags & EFD_NONBLOCK != 0,
+    );
+    install_file(Arc::new(eventfd), flags & EFD_CLOEXEC != 0)
+}
+
+/// 创建以 `clockid` 为时钟的 timerfd，`flags` 可以含 `TFD_NONBLOCK` 与 `TFD_CLOEXEC`
+pub fn sys_timerfd_create(clockid: usize, flags: u32) -> isize {
+    if flags & !(TFD_NONBLOCK | TFD_CLOEXEC) != 0 {
+        return -1; // EINVAL
+    }
+    if !matches!(clockid, CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_BOOTTIME) {
+        return -1; // EINVAL
+    }
+    let timerfd = TimerFd::new(clockid, flags & TFD_NONBLOCK != 0);
+    install_file(Arc::new(timerfd), flags & TFD_CLOEXEC != 0)
+}
+
+/// 取出 fd 对应的 timerfd，不是 timerfd 时返回 EINVAL
+fn timerfd_of(fd: usize) -> Result<Arc<dyn File + Send + Sync>, isize> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(-1), // EBADF
+    };
+    if file.as_any().downcast_ref::<TimerFd>().is_none() {
+        return Err(-1); // EINVAL
+    }
+    Ok(file)
+}
+
+/// 设置 timerfd，`old_value` 不为空时写回原先的设置
+pub fn sys_timerfd_settime(
+    fd: usize,
+    flags: u32,
+    new_value: *const ITimerSpec,
+    old_value: *mut ITimerSpec,
+) -> isize {
+    let file = match timerfd_of(fd) {
+        Ok(file) => file,
+        Err(e) => return e,
+    };
+    if flags & !TFD_TIMER_ABSTIME != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(new) = get_from_user(token, new_value) else {
+        return -1; // EFAULT
+    };
+    if new.it_value.tv_nsec >= NSEC_PER_SEC || new.it_interval.tv_nsec >= NSEC_PER_SEC {
+        return -1; // EINVAL
+    }
+    let timerfd = file.as_any().downcast_ref::<TimerFd>().unwrap();
+    let old = timerfd.set_time(new, flags & TFD_TIMER_ABSTIME != 0);
+    if !old_value.is_null() && copy_to_user(token, &old, old_value).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
+/// 读取 timerfd 距离下次到期的时间与重复间隔
+pub fn sys_timerfd_gettime(fd: usize, curr_value: *mut ITimerSpec) -> isize {
+    let file = match timerfd_of(fd) {
+        Ok(file) => file,
+        Err(e) => return e,
+    };
+    let timerfd = file.as_any().downcast_ref::<TimerFd>().unwrap();
+    if copy_to_user(current_user_token(), &timerfd.get_time(), curr_value).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
+/// 单次 epoll_pwait 允许返回的最大事件数
+const EP_MAX_EVENTS: usize = i32::MAX as usize / core::mem::size_of::<EpollEvent>();
+
+/// 创建 epoll 实例，`flags` 只能含 `EPOLL_CLOEXEC`
+pub fn sys_epoll_create1(flags: u32) -> isize {
+    if flags & !EPOLL_CLOEXEC != 0 {
+        return -1; // EINVAL
+    }
+    install_file(Arc::new(Epoll::new()), flags & EPOLL_CLOEXEC != 0)
+}
+
+/// 取出 fd 对应的 epoll 实例，不是 epoll 实例时返回 EINVAL
+fn epoll_of(fd: usize) -> Result<Arc<dyn File + Send + Sync>, isize> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(-1), // EBADF
+    };
+    if file.as_any().downcast_ref::<Epoll>().is_none() {
+        return Err(-1); // EINVAL
+    }
+    Ok(file)
+}
+
+/// 在 epoll 实例 `epfd` 的关注列表中增加、修改或删除描述符 `fd`
+pub fn sys_epoll_ctl(epfd: usize, op: usize, fd: usize, event: *const EpollEvent) -> isize {
+    let file = match epoll_of(epfd) {
+        Ok(file) => file,
+        Err(e) => return e,
+    };
+    let target = {
+        let process = current_process();
+        let inner = process.inner_exclusive_access();
+        match inner.fd_table.get(fd) {
+            Some(Some(target)) => target.clone(),
+            _ => return -1, // EBADF
+        }
+    };
+    // EPOLL_CTL_DEL 忽略 event，可以为空
+    let event = if op == EPOLL_CTL_DEL {
+        EpollEvent { events: 0, data: 0 }
+    } else {
+        match get_from_user(current_user_token(), event) {
+            Ok(event) => event,
+            Err(_) => return -1, // EFAULT
+        }
+    };
+    let epoll = file.as_any().downcast_ref::<Epoll>().unwrap();
+    match epoll.ctl(op, fd, &target, event) {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+/// 等待 epoll 实例上的事件，最多返回 `maxevents` 个
+///
+/// `timeout` 以毫秒计，负数表示无限等待，0 表示立即返回；`sigmask` 非空时在等待期间临时替换信号屏蔽字
+pub fn sys_epoll_pwait(
+    epfd: usize,
+    events: *mut EpollEvent,
+    maxevents: i32,
+    timeout: i32,
+    sigmask: *const u64,
+    sigsetsize: usize,
+) -> isize {
+    if maxevents <= 0 || maxevents as usize > EP_MAX_EVENTS {
+        return -1; // EINVAL
+    }
+    let file = match epoll_of(epfd) {
+        Ok(file) => file,
+        Err(e) => return e,
+    };
+    let token = current_user_token();
+    let sigmask = match read_sigmask(token, sigmask, sigsetsize) {
+        Ok(sigmask) => sigmask,
+        Err(err) => return err,
+    };
+    let timeout_us = (timeout >= 0).then(|| timeout as usize * 1000);
+    let epoll = file.as_any().downcast_ref::<Epoll>().unwrap();
+    let mut ready = Vec::new();
+    let ret = poll_wait(timeout_us, sigmask, || {
+        ready = epoll.collect(maxevents as usize);
+        ready.len()
+    });
+    for (i, event) in ready.iter().enumerate() {
+        if copy_to_user(token, event, events.wrapping_add(i)).is_err() {
+            return -1; // EFAULT
+        }
+    }
+    ret
+}
+
+const SPLICE_FLAGS: u32 = SPLICE_F_MOVE | SPLICE_F_NONBLOCK | SPLICE_F_MORE | SPLICE_F_GIFT;
+
+/// 取出描述符 fd 对应的文件
+fn file_of(fd: usize) -> Result<Arc<dyn File + Send + Sync>, isize> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    match inner.fd_table.get(fd) {
+        Some(Some(file)) => Ok(file.clone()),
+        _ => Err(-1), // EBADF
+    }
+}
+
+/// 读入 splice 的偏移，空指针表示使用文件偏移
+fn read_splice_offset(token: usize, off: *mut i64) -> Result<Option<usize>, isize> {
+    if off.is_null() {
+        return Ok(None);
+    }
+    match get_from_user(token, off as *const i64)? {
+        off if off < 0 => Err(-1), // EINVAL
+        off => Ok(Some(off as usize)),
+    }
+}
+
+/// 在管道与文件之间、或两个管道之间移动至多 `len` 个字节，数据不经过用户空间
+///
+/// `off_in` / `off_out` 非空时从该偏移读写并更新它，文件偏移不变；管道一端必须为空
+pub fn sys_splice(
+    fd_in: usize,
+    off_in: *mut i64,
+    fd_out: usize,
+    off_out: *mut i64,
+    len: usize,
+    flags: u32,
+) -> isize {
+    if flags & !SPLICE_FLAGS != 0 {
+        return -1; // EINVAL
+    }
+    let (file_in, file_out) = match (file_of(fd_in), file_of(fd_out)) {
+        (Ok(file_in), Ok(file_out)) => (file_in, file_out),
+        _ => return -1, // EBADF
+    };
+    let token = current_user_token();
+    let (pos_in, pos_out) = match (
+        read_splice_offset(token, off_in),
+        read_splice_offset(token, off_out),
+    ) {
+        (Ok(pos_in), Ok(pos_out)) => (pos_in, pos_out),
+        (Err(err), _) | (_, Err(err)) => return err,
+    };
+    let nonblocking = flags & SPLICE_F_NONBLOCK != 0;
+    let moved = match splice(&file_in, pos_in, &file_out, pos_out, len, nonblocking) {
+        Ok(moved) => moved,
+        Err(err) => return err,
+    };
+    for (off, pos) in [(off_in, pos_in), (off_out, pos_out)] {
+        if let Some(pos) = pos {
+            if copy_to_user(token, &((pos + moved) as i64), off).is_err() {
+                return -1; // EFAULT
+            }
+        }
+    }
+    moved as isize
+}
+
+/// 把管道 `fd_in` 中至多 `len` 个字节复制到管道 `fd_out`，不从 `fd_in` 中取走
+pub fn sys_tee(fd_in: usize, fd_out: usize, len: usize, flags: u32) -> isize {
+    if flags & !SPLICE_FLAGS != 0 {
+        return -1; // EINVAL
+    }
+    let (file_in, file_out) = match (file_of(fd_in), file_of(fd_out)) {
+        (Ok(file_in), Ok(file_out)) => (file_in, file_out),
+        _ => return -1, // EBADF
+    };
+    match tee(&file_in, &file_out, len, flags & SPLICE_F_NONBLOCK != 0) {
+        Ok(copied) => copied as isize,
+        Err(err) => err,
+    }
+}
+
 pub fn sys_unlinkat(dirfd: usize, path: *const u8, flags: u32) -> isize {
     if path.is_null() {
         return -1;
     }
     let task = current_task().unwrap();
     let token = task.get_user_token();
-    let path = translated_str(token, path);
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
     let base_dir = if dirfd == AT_FDCWD {
         let process = current_process();
         let cwd = {
@@ -503,24 +1561,456 @@ pub fn sys_unlinkat(dirfd: usize, path: *const u8, flags: u32) -> isize {
     let full_path = resolve_path(path.as_str(), &base_dir);
     let path_in_fs = full_path.strip_prefix("/").unwrap_or(&full_path);
     let _remove_dir = (flags & 0x200) != 0;
+    // 链接只删除占位或改名，数据仍属于其余的链接
+    match unlink(&full_path) {
+        Unlink::Plain => {}
+        Unlink::Placeholder => {
+            if ROOT_DIR.exclusive_access().remove(path_in_fs).is_err() {
+                return -1; // EIO
+            }
+            drop_file_meta(&full_path);
+            forget_ino(&full_path);
+            return 0;
+        }
+        Unlink::Promote(new_primary) => return promote_link(&full_path, &new_primary),
+    }
     let root_dir = ROOT_DIR.exclusive_access();
     let res = root_dir.remove(path_in_fs);
     match res {
-        Ok(_) => 0,
+        Ok(_) => {
+            drop_page_cache(&full_path);
+            drop_file_meta(&full_path);
+            drop_fifo(&full_path);
+            forget_ino(&full_path);
+            0
+        }
         Err(_) => -1,
     }
 }
+
+/// 删除仍有其他硬链接的主路径 `old`：把文件改名为新的主路径 `new`（替换其占位），
+/// 页缓存、inode 号与元数据随之转移
+fn promote_link(old: &str, new: &str) -> isize {
+    if ROOT_DIR
+        .exclusive_access()
+        .remove(new.trim_start_matches('/'))
+        .is_err()
+    {
+        return -1; // EIO
+    }
+    if rename_path(old, new).is_err() {
+        return -1; // EIO
+    }
+    rename_page_cache(old, new);
+    rename_ino(old, new);
+    rename_file_meta(old, new);
+    0
+}
+
+/// 创建硬链接 `newpath`，指向 `oldpath` 所在的文件
+///
+/// FAT32 不能保存硬链接，链接只记录在内存中（见 `fs::link`）；不带 `AT_SYMLINK_FOLLOW` 时
+/// `oldpath` 若是符号链接，新链接是指向同一目标的符号链接
+pub fn sys_linkat(
+    olddirfd: usize,
+    oldpath: *const u8,
+    newdirfd: usize,
+    newpath: *const u8,
+    flags: u32,
+) -> isize {
+    if flags & !(AT_SYMLINK_FOLLOW | AT_EMPTY_PATH) != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(oldpath) = translated_str(token, oldpath) else {
+        return -1; // EFAULT
+    };
+    let Ok(newpath) = translated_str(token, newpath) else {
+        return -1; // EFAULT
+    };
+    let old = match resolve_at(olddirfd, &oldpath, flags & AT_EMPTY_PATH != 0) {
+        Ok(old) => old,
+        Err(err) => return err,
+    };
+    let new = match resolve_at(newdirfd, &newpath, false) {
+        Ok(new) => new,
+        Err(err) => return err,
+    };
+    let old = match follow(&old, flags & AT_SYMLINK_FOLLOW != 0) {
+        Ok(old) => old,
+        Err(err) => return err,
+    };
+    match path_kind(&old) {
+        None => return -1,       // ENOENT
+        Some(true) => return -1, // EPERM
+        Some(false) => {}
+    }
+    if open_special(&old).is_some() {
+        return -1; // EXDEV
+    }
+    if is_fifo(&old) {
+        return -1; // EPERM
+    }
+    if path_kind(&new).is_some() {
+        return -1; // EEXIST
+    }
+    if open_file(&new, OpenFlags::CREATE | OpenFlags::WRONLY).is_none() {
+        return -1; // ENOENT
+    }
+    match read_link(&old) {
+        Some(target) => make_symlink(&new, &target),
+        None => add_hard_link(&old, &new),
+    }
+    0
+}
+
+/// 创建指向 `target` 的符号链接 `linkpath`，目标按原样保存，不要求存在
+pub fn sys_symlinkat(target: *const u8, newdirfd: usize, linkpath: *const u8) -> isize {
+    let token = current_user_token();
+    let Ok(target) = translated_str(token, target) else {
+        return -1; // EFAULT
+    };
+    let Ok(linkpath) = translated_str(token, linkpath) else {
+        return -1; // EFAULT
+    };
+    if target.is_empty() {
+        return -1; // ENOENT
+    }
+    let full_path = match resolve_at(newdirfd, &linkpath, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    if path_kind(&full_path).is_some() {
+        return -1; // EEXIST
+    }
+    if open_file(&full_path, OpenFlags::CREATE | OpenFlags::WRONLY).is_none() {
+        return -1; // ENOENT
+    }
+    make_symlink(&full_path, &target);
+    0
+}
+
+/// 把符号链接 `path` 的目标写入 `buf`，超出 `bufsiz` 的部分被截断，不写入结尾的 NUL
+pub fn sys_readlinkat(dirfd: usize, path: *const u8, buf: *mut u8, bufsiz: usize) -> isize {
+    if bufsiz as isize <= 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, true) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    let Some(target) = read_link(&full_path) else {
+        return -1; // EINVAL（不是符号链接）或 ENOENT
+    };
+    let n = target.len().min(bufsiz);
+    if try_write_bytes(token, buf as usize, &target.as_bytes()[..n]).is_none() {
+        return -1; // EFAULT
+    }
+    n as isize
+}
+
+/// 创建文件系统节点，支持普通文件与 FIFO；FAT32 无法保存设备文件
+pub fn sys_mknodat(dirfd: usize, path: *const u8, mode: u32, _dev: usize) -> isize {
+    let Ok(path) = translated_str(current_user_token(), path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    let file_type = mode & StatMode::S_IFMT.bits();
+    let is_fifo = if file_type == StatMode::S_IFIFO.bits() {
+        true
+    } else if file_type == 0 || file_type == StatMode::S_IFREG.bits() {
+        false
+    } else if file_type == StatMode::S_IFCHR.bits() || file_type == StatMode::S_IFBLK.bits() {
+        return -1; // EPERM
+    } else {
+        return -1; // EINVAL
+    };
+    if lookup_path(&full_path).is_some() || open_special(&full_path).is_some() {
+        return -1; // EEXIST
+    }
+    if open_file(&full_path, OpenFlags::CREATE | OpenFlags::WRONLY).is_none() {
+        return -1; // ENOENT
+    }
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let (umask, cred) = (inner.umask, inner.cred);
+    drop(inner);
+    init_file_meta(&full_path, mode, umask, cred.euid, cred.egid);
+    if is_fifo {
+        make_fifo(&full_path);
+    }
+    0
+}
+
+/// 检查调用者能否以 `mode`（`F_OK` 或 `R_OK | W_OK | X_OK` 的组合）访问 `path`
+pub fn sys_faccessat(dirfd: usize, path: *const u8, mode: u32, flags: u32) -> isize {
+    if mode & !(R_OK | W_OK | X_OK) != 0
+        || flags & !(AT_EACCESS | AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0
+    {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, flags & AT_EMPTY_PATH != 0) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    // FAT32 没有符号链接，AT_SYMLINK_NOFOLLOW 不影响结果
+    let is_dir = match path_kind(&full_path) {
+        Some(is_dir) => is_dir,
+        None => return -1, // ENOENT
+    };
+    // 默认以实际身份检查，AT_EACCESS 时以有效身份检查
+    let cred = current_process().inner_exclusive_access().cred;
+    let (uid, gid) = if flags & AT_EACCESS != 0 {
+        (cred.euid, cred.egid)
+    } else {
+        (cred.uid, cred.gid)
+    };
+    if file_meta_or_default(&full_path).permits(uid, gid, mode, is_dir) {
+        0
+    } else {
+        -1 // EACCES
+    }
+}
+
+/// 把 `path` 指向的文件截断或扩展到 `length` 字节
+pub fn sys_truncate(path: *const u8, length: isize) -> isize {
+    if length < 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(AT_FDCWD, &path, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    match path_kind(&full_path) {
+        Some(false) => {}
+        Some(true) => return -1, // EISDIR
+        None => return -1,       // ENOENT
+    }
+    let cred = current_process().inner_exclusive_access().cred;
+    if !file_meta_or_default(&full_path).permits(cred.euid, cred.egid, W_OK, false) {
+        return -1; // EACCES
+    }
+    match open_file(&full_path, OpenFlags::WRONLY) {
+        Some(inode) => match inode.truncate(length as usize) {
+            Ok(()) => 0,
+            Err(err) => err,
+        },
+        None => -1, // EINVAL：不是普通文件（如设备）
+    }
+}
+
+/// 按 `whence` 移动文件 `fd` 的偏移，返回新的偏移；偏移可以移到文件末尾之后
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    let file = match current_process().inner_exclusive_access().fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    let pos = match whence as i16 {
+        SEEK_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+        SEEK_CUR => SeekFrom::Current(offset as i64),
+        SEEK_END => SeekFrom::End(offset as i64),
+        _ => return -1, // EINVAL
+    };
+    match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => match inode.lseek(pos) {
+            Ok(pos) => pos as isize,
+            Err(err) => err,
+        },
+        None => -1, // ESPIPE：管道、套接字与设备没有偏移
+    }
+}
+
+/// 把 `fd` 指向的文件截断或扩展到 `length` 字节，`fd` 必须以可写方式打开
+pub fn sys_ftruncate(fd: usize, length: isize) -> isize {
+    if length < 0 {
+        return -1; // EINVAL
+    }
+    let file = match current_process().inner_exclusive_access().fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    if !file.writable() {
+        return -1; // EINVAL
+    }
+    match file.as_any().downcast_ref::<OSInode>() {
+        Some(inode) => match inode.truncate(length as usize) {
+            Ok(()) => 0,
+            Err(err) => err,
+        },
+        None => -1, // EINVAL：管道、套接字与设备不能截断
+    }
+}
+
+/// 对文件 `fd` 中 `[offset, offset + len)` 的访问模式给出建议，`len` 为 0 表示直到文件末尾
+///
+/// 只有 FAT32 上的普通文件有页缓存，其他文件接受建议但不做处理
+pub fn sys_fadvise64(fd: usize, offset: isize, len: isize, advice: usize) -> isize {
+    if offset < 0 || len < 0 {
+        return -1; // EINVAL
+    }
+    let file = match current_process().inner_exclusive_access().fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    if file.as_any().downcast_ref::<Pipe>().is_some() {
+        return -1; // ESPIPE
+    }
+    let Some(inode) = file.as_any().downcast_ref::<OSInode>() else {
+        return if advice <= POSIX_FADV_NOREUSE { 0 } else { -1 }; // EINVAL
+    };
+    match inode.fadvise(offset as usize, len as usize, advice) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// 把所有文件的修改写到磁盘上：先同步各进程打开的文件（含目录项），再写回其余文件的页缓存与块缓存
+pub fn sys_sync() -> isize {
+    for process in (0..=max_pid()).filter_map(pid2process) {
+        let files: Vec<_> = process
+            .inner_exclusive_access()
+            .fd_table
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        for file in files {
+            let _ = file.sync(false);
+        }
+    }
+    sync_page_caches("/");
+    block_cache_sync_all();
+    0
+}
+
+/// 把文件 `fd` 的数据与元数据写到磁盘上
+pub fn sys_fsync(fd: usize) -> isize {
+    sync_fd(fd, false)
+}
+
+/// 把文件 `fd` 的数据写到磁盘上，只涉及元数据（修改时间）的更新可以不写
+pub fn sys_fdatasync(fd: usize) -> isize {
+    sync_fd(fd, true)
+}
+
+fn sync_fd(fd: usize, datasync: bool) -> isize {
+    let file = match current_process().inner_exclusive_access().fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1, // EBADF
+    };
+    match file.sync(datasync) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+/// 修改 `path` 的权限位
+pub fn sys_fchmodat(dirfd: usize, path: *const u8, mode: u32, flags: u32) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    if path_kind(&full_path).is_none() {
+        return -1; // ENOENT
+    }
+    // 只有属主或特权进程可以修改权限
+    let cred = current_process().inner_exclusive_access().cred;
+    if !cred.is_privileged() && file_meta_or_default(&full_path).uid != cred.euid {
+        return -1; // EPERM
+    }
+    set_file_mode(&full_path, mode);
+    0
+}
+
+/// 修改 `path` 的属主与属组，`owner` / `group` 为 -1 时保持不变
+pub fn sys_fchownat(dirfd: usize, path: *const u8, owner: u32, group: u32, flags: u32) -> isize {
+    if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, flags & AT_EMPTY_PATH != 0) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    if path_kind(&full_path).is_none() {
+        return -1; // ENOENT
+    }
+    let owner = (owner != u32::MAX).then_some(owner);
+    let group = (group != u32::MAX).then_some(group);
+    // 非特权进程不能改变属主，只能把自己文件的属组改为自己的有效组
+    let cred = current_process().inner_exclusive_access().cred;
+    let meta = file_meta_or_default(&full_path);
+    if !cred.is_privileged()
+        && (owner.is_some_and(|uid| uid != meta.uid)
+            || meta.uid != cred.euid
+            || group.is_some_and(|gid| gid != cred.egid))
+    {
+        return -1; // EPERM
+    }
+    set_file_owner(&full_path, owner, group);
+    0
+}
+/// 卸载挂载点 `target`
+///
+/// - `MNT_DETACH`：不检查挂载点是否繁忙，立即卸载
+/// - 挂载点之下仍有进程的当前目录或打开的文件时返回 EBUSY，不是挂载点时返回 EINVAL
+/// - 卸载前写回挂载点之下的页缓存与块缓存
 pub fn sys_umount2(target: *const u8, flags: u32) -> isize {
     if target.is_null() {
-        return -1;
+        return -1; // EFAULT
+    }
+    let Some(flags) = UmountFlags::from_bits(flags) else {
+        return -1; // EINVAL
+    };
+    if flags.contains(UmountFlags::MNT_EXPIRE)
+        && flags.intersects(UmountFlags::MNT_FORCE | UmountFlags::MNT_DETACH)
+    {
+        return -1; // EINVAL
+    }
+    if !current_process()
+        .inner_exclusive_access()
+        .cred
+        .is_privileged()
+    {
+        return -1; // EPERM
     }
     let token = current_user_token();
-    let target = translated_str(token, target);
-    let flags = UmountFlags::from_bits(flags);
-    if open_dir(target.as_str()).is_err() {
-        return -1;
+    let Ok(target) = translated_str(token, target) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(AT_FDCWD, &target, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    match umount(&full_path, flags.contains(UmountFlags::MNT_DETACH)) {
+        Ok(()) => 0,
+        Err(err) => err,
     }
-    0
 }
 bitflags! {
     pub struct UmountFlags: u32 {
@@ -540,20 +2030,43 @@ pub fn sys_mount(
     if source.is_null() || target.is_null() || filesystemtype.is_null() {
         return -1;
     }
+    if !current_process()
+        .inner_exclusive_access()
+        .cred
+        .is_privileged()
+    {
+        return -1; // EPERM
+    }
     let token = current_user_token();
-    let source = translated_str(token, source);
-    let target = translated_str(token, target);
-    let filesystemtype = translated_str(token, filesystemtype);
-    let mountflags = MountFlags::from_bits(mountflags).unwrap();
-    if open_dir(target.as_str()).is_err() {
-        return -1;
+    let Ok(source) = translated_str(token, source) else {
+        return -1; // EFAULT
+    };
+    let Ok(target) = translated_str(token, target) else {
+        return -1; // EFAULT
+    };
+    let Ok(filesystemtype) = translated_str(token, filesystemtype) else {
+        return -1; // EFAULT
+    };
+    let Some(mountflags) = MountFlags::from_bits(mountflags) else {
+        return -1; // EINVAL
+    };
+    let full_path = match resolve_at(AT_FDCWD, &target, false) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    if open_dir(full_path.as_str()).is_err() {
+        return -1; // ENOTDIR
     }
 
     let fs_type = filesystemtype.as_str();
-    if fs_type != "vfat" && fs_type != "fat32" && fs_type != "vfat" {
-        return -1;
+    if fs_type != "vfat" && fs_type != "fat32" {
+        return -1; // ENODEV
+    }
+    let remount = mountflags.contains(MountFlags::MS_REMOUNT);
+    match mount(&source, &full_path, fs_type, mountflags.bits(), remount) {
+        Ok(()) => 0,
+        Err(err) => err,
     }
-    0
 }
 bitflags! {
     pub struct MountFlags: usize {
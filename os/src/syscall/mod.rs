@@ -1,46 +1,156 @@
 const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_EVENTFD2: usize = 19;
+const SYSCALL_EPOLL_CREATE1: usize = 20;
+const SYSCALL_EPOLL_CTL: usize = 21;
+const SYSCALL_EPOLL_PWAIT: usize = 22;
 const SYSCALL_DUP: usize = 23;
 const SYSCALL_DUP3: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_FLOCK: usize = 32;
+const SYSCALL_MKNODAT: usize = 33;
 const SYSCALL_MKDIRAT: usize = 34;
 const SYSCALL_UNLINKAT: usize = 35;
-// const SYSCALL_LINKAT: usize =  37;
+const SYSCALL_SYMLINKAT: usize = 36;
+const SYSCALL_LINKAT: usize = 37;
 const SYSCALL_UMOUNT2: usize = 39;
 const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
+const SYSCALL_FACCESSAT: usize = 48;
 const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_FCHDIR: usize = 50;
+const SYSCALL_FCHMODAT: usize = 53;
+const SYSCALL_FCHOWNAT: usize = 54;
 const SYSCALL_OPENAT: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE2: usize = 59;
 const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_READV: usize = 65;
+const SYSCALL_WRITEV: usize = 66;
+const SYSCALL_PSELECT6: usize = 72;
+const SYSCALL_PPOLL: usize = 73;
+const SYSCALL_SPLICE: usize = 76;
+const SYSCALL_TEE: usize = 77;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
 const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
+const SYSCALL_FSYNC: usize = 82;
+const SYSCALL_FDATASYNC: usize = 83;
+const SYSCALL_TIMERFD_CREATE: usize = 85;
+const SYSCALL_TIMERFD_SETTIME: usize = 86;
+const SYSCALL_TIMERFD_GETTIME: usize = 87;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_EXIT_GROUP: usize = 94;
 const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
+const SYSCALL_CLOCK_SETTIME: usize = 112;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_PTRACE: usize = 117;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_TKILL: usize = 130;
+const SYSCALL_TGKILL: usize = 131;
+const SYSCALL_SIGALTSTACK: usize = 132;
+const SYSCALL_SETGID: usize = 144;
+const SYSCALL_SETUID: usize = 146;
+const SYSCALL_SETRESUID: usize = 147;
 const SYSCALL_TIMES: usize = 153;
 const SYSCALL_UNAME: usize = 160;
+const SYSCALL_UMASK: usize = 166;
+const SYSCALL_PRCTL: usize = 167;
 const SYSCALL_GET_TIME_OF_DAY: usize = 169;
+const SYSCALL_SETTIMEOFDAY: usize = 170;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_GETUID: usize = 174;
+const SYSCALL_GETEUID: usize = 175;
+const SYSCALL_GETGID: usize = 176;
+const SYSCALL_GETEGID: usize = 177;
+const SYSCALL_SYSINFO: usize = 179;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMCTL: usize = 195;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SHMDT: usize = 197;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_SOCKETPAIR: usize = 199;
+const SYSCALL_BIND: usize = 200;
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_ACCEPT: usize = 202;
+const SYSCALL_CONNECT: usize = 203;
+const SYSCALL_GETSOCKNAME: usize = 204;
+const SYSCALL_GETPEERNAME: usize = 205;
+const SYSCALL_SENDTO: usize = 206;
+const SYSCALL_RECVFROM: usize = 207;
+const SYSCALL_SETSOCKOPT: usize = 208;
+const SYSCALL_SENDMSG: usize = 211;
+const SYSCALL_RECVMSG: usize = 212;
 const SYSCALL_BRK: usize = 214;
 const SYSCALL_MUNMAP: usize = 215;
 // const SYSCALL_FORK: usize = 220;
 const SYSCALL_CLONE: usize = 220;
 const SYSCALL_EXECVE: usize = 221;
 const SYSCALL_MMAP: usize = 222;
+const SYSCALL_FADVISE64: usize = 223;
+const SYSCALL_MSYNC: usize = 227;
+const SYSCALL_MINCORE: usize = 232;
+const SYSCALL_MADVISE: usize = 233;
 const SYSCALL_WAIT4: usize = 260;
+const SYSCALL_PRLIMIT64: usize = 261;
+const SYSCALL_GETRANDOM: usize = 278;
+const SYSCALL_EXECVEAT: usize = 281;
+const SYSCALL_MEMBARRIER: usize = 283;
+const SYSCALL_FACCESSAT2: usize = 439;
+const SYSCALL_ENABLE_DEADLOCK_DETECT: usize = 469;
+// 内核私有：控制系统调用跟踪
+const SYSCALL_STRACE: usize = 1000;
+// 内核私有：线程同步原语
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
 
 mod fs;
+mod net;
 mod process;
+mod ptrace;
+mod shm;
 mod sync;
 mod thread;
+mod trace;
 
 use crate::task::Rusage;
 use crate::timer::Tms;
 pub use fs::*;
+pub use net::*;
 pub use process::*;
+pub use ptrace::*;
+pub use shm::*;
+pub use sync::*;
+pub use trace::{follow_fork, sys_strace, untrace};
 
 pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
+    let trace = trace::enter(syscall_id, &args);
+    let ret = dispatch(syscall_id, args);
+    if let Some(entry) = trace {
+        trace::exit(entry, ret);
+    }
+    ret
+}
+
+fn dispatch(syscall_id: usize, args: [usize; 6]) -> isize {
     match syscall_id {
         //SYSCALL_OPEN => sys_openat(args[0] as *const u8, args[1] as u32),
         SYSCALL_OPENAT => {
@@ -53,28 +163,140 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             )
         }
         SYSCALL_CLOSE => sys_close(args[0]),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_GETCWD => sys_getcwd(args[0] as *const u8, args[1]),
+        SYSCALL_READV => sys_readv(args[0], args[1] as *const IoVec, args[2]),
+        SYSCALL_WRITEV => sys_writev(args[0], args[1] as *const IoVec, args[2]),
+        SYSCALL_PSELECT6 => sys_pselect6(
+            args[0],
+            args[1] as *mut u8,
+            args[2] as *mut u8,
+            args[3] as *mut u8,
+            args[4] as *const crate::timer::TimeSpec,
+            args[5] as *const PselectSigmask,
+        ),
+        SYSCALL_PPOLL => sys_ppoll(
+            args[0] as *mut PollFd,
+            args[1],
+            args[2] as *const crate::timer::TimeSpec,
+            args[3] as *const u64,
+            args[4],
+        ),
+        SYSCALL_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
         SYSCALL_DUP => sys_dup(args[0]),
         SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2]),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_FLOCK => sys_flock(args[0], args[1]),
+        SYSCALL_MKNODAT => sys_mknodat(args[0], args[1] as *const u8, args[2] as u32, args[3]),
         SYSCALL_MKDIRAT => sys_mkdirat(args[0] as isize, args[1] as *const u8, args[2] as u32),
         SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
+        SYSCALL_FCHDIR => sys_fchdir(args[0]),
         SYSCALL_GETDENTS64 => {
             sys_getdents64(args[0], args[1] as *mut u8, args[2] as *const u64 as usize)
         }
         SYSCALL_GETPID => sys_getpid(),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_EXIT_GROUP => sys_exit_group(args[0] as i32),
+        SYSCALL_SCHED_SETAFFINITY => sys_sched_setaffinity(args[0], args[1], args[2] as *const u8),
+        SYSCALL_SCHED_GETAFFINITY => sys_sched_getaffinity(args[0], args[1], args[2] as *mut u8),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_GETPPID => sys_getppid(),
+        SYSCALL_GETUID => sys_getuid(),
+        SYSCALL_GETEUID => sys_geteuid(),
+        SYSCALL_GETGID => sys_getgid(),
+        SYSCALL_GETEGID => sys_getegid(),
+        SYSCALL_SYSINFO => sys_sysinfo(args[0] as *mut SysInfo),
+        SYSCALL_SETUID => sys_setuid(args[0] as u32),
+        SYSCALL_SETGID => sys_setgid(args[0] as u32),
+        SYSCALL_SETRESUID => sys_setresuid(args[0] as u32, args[1] as u32, args[2] as u32),
         SYSCALL_UNAME => sys_uname(args[0] as *mut u8),
         SYSCALL_TIMES => sys_times(args[0] as *mut Tms),
+        SYSCALL_UMASK => sys_umask(args[0] as u32),
+        SYSCALL_PRCTL => sys_prctl(args[0], args[1]),
         SYSCALL_BRK => sys_brk(args[0]),
         SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MSYNC => sys_msync(args[0], args[1], args[2]),
+        SYSCALL_MINCORE => sys_mincore(args[0], args[1], args[2] as *mut u8),
+        SYSCALL_MADVISE => sys_madvise(args[0], args[1], args[2]),
+        SYSCALL_FSTATAT => sys_fstatat(
+            args[0],
+            args[1] as *const u8,
+            args[2] as *mut u8,
+            args[3] as u32,
+        ),
         SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut u8),
+        SYSCALL_SYNC => sys_sync(),
+        SYSCALL_FSYNC => sys_fsync(args[0]),
+        SYSCALL_FDATASYNC => sys_fdatasync(args[0]),
         SYSCALL_PIPE2 => sys_pipe2(args[0], args[1] as u32),
+        SYSCALL_EVENTFD2 => sys_eventfd2(args[0] as u32, args[1] as u32),
+        SYSCALL_TIMERFD_CREATE => sys_timerfd_create(args[0], args[1] as u32),
+        SYSCALL_TIMERFD_SETTIME => sys_timerfd_settime(
+            args[0],
+            args[1] as u32,
+            args[2] as *const crate::timer::ITimerSpec,
+            args[3] as *mut crate::timer::ITimerSpec,
+        ),
+        SYSCALL_TIMERFD_GETTIME => {
+            sys_timerfd_gettime(args[0], args[1] as *mut crate::timer::ITimerSpec)
+        }
+        SYSCALL_EPOLL_CREATE1 => sys_epoll_create1(args[0] as u32),
+        SYSCALL_EPOLL_CTL => sys_epoll_ctl(
+            args[0],
+            args[1],
+            args[2],
+            args[3] as *const crate::fs::EpollEvent,
+        ),
+        SYSCALL_EPOLL_PWAIT => sys_epoll_pwait(
+            args[0],
+            args[1] as *mut crate::fs::EpollEvent,
+            args[2] as i32,
+            args[3] as i32,
+            args[4] as *const u64,
+            args[5],
+        ),
+        SYSCALL_SPLICE => sys_splice(
+            args[0],
+            args[1] as *mut i64,
+            args[2],
+            args[3] as *mut i64,
+            args[4],
+            args[5] as u32,
+        ),
+        SYSCALL_TEE => sys_tee(args[0], args[1], args[2], args[3] as u32),
         SYSCALL_UMOUNT2 => sys_umount2(args[0] as *const u8, args[1] as u32),
         SYSCALL_UNLINKAT => sys_unlinkat(args[0], args[1] as *const u8, args[2] as u32),
+        SYSCALL_SYMLINKAT => sys_symlinkat(args[0] as *const u8, args[1], args[2] as *const u8),
+        SYSCALL_LINKAT => sys_linkat(
+            args[0],
+            args[1] as *const u8,
+            args[2],
+            args[3] as *const u8,
+            args[4] as u32,
+        ),
+        SYSCALL_READLINKAT => {
+            sys_readlinkat(args[0], args[1] as *const u8, args[2] as *mut u8, args[3])
+        }
+        SYSCALL_TRUNCATE => sys_truncate(args[0] as *const u8, args[1] as isize),
+        SYSCALL_FTRUNCATE => sys_ftruncate(args[0], args[1] as isize),
+        SYSCALL_FADVISE64 => sys_fadvise64(args[0], args[1] as isize, args[2] as isize, args[3]),
+        // faccessat 没有 flags 参数，faccessat2 才有
+        SYSCALL_FACCESSAT => sys_faccessat(args[0], args[1] as *const u8, args[2] as u32, 0),
+        SYSCALL_FACCESSAT2 => {
+            sys_faccessat(args[0], args[1] as *const u8, args[2] as u32, args[3] as u32)
+        }
+        SYSCALL_FCHMODAT => {
+            sys_fchmodat(args[0], args[1] as *const u8, args[2] as u32, args[3] as u32)
+        }
+        SYSCALL_FCHOWNAT => sys_fchownat(
+            args[0],
+            args[1] as *const u8,
+            args[2] as u32,
+            args[3] as u32,
+            args[4] as u32,
+        ),
         SYSCALL_MOUNT => sys_mount(
             args[0] as *const u8,
             args[1] as *const u8,
@@ -92,11 +314,58 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             args[4] as isize,
             args[5],
         )},
+        SYSCALL_SHMGET => sys_shmget(args[0], args[1], args[2] as u32),
+        SYSCALL_SHMCTL => sys_shmctl(
+            args[0],
+            args[1],
+            args[2] as *mut crate::mm::shm::ShmIdDs,
+        ),
+        SYSCALL_SHMAT => sys_shmat(args[0], args[1], args[2] as u32),
+        SYSCALL_SHMDT => sys_shmdt(args[0]),
+        SYSCALL_SOCKET => sys_socket(args[0], args[1], args[2]),
+        SYSCALL_SOCKETPAIR => sys_socketpair(args[0], args[1], args[2], args[3] as *mut i32),
+        SYSCALL_BIND => sys_bind(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_LISTEN => sys_listen(args[0], args[1]),
+        SYSCALL_ACCEPT => sys_accept(args[0], args[1] as *mut u8, args[2] as *mut u32),
+        SYSCALL_CONNECT => sys_connect(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_GETSOCKNAME => sys_getsockname(args[0], args[1] as *mut u8, args[2] as *mut u32),
+        SYSCALL_GETPEERNAME => sys_getpeername(args[0], args[1] as *mut u8, args[2] as *mut u32),
+        SYSCALL_SENDTO => sys_sendto(
+            args[0],
+            args[1] as *const u8,
+            args[2],
+            args[3] as u32,
+            args[4] as *const u8,
+            args[5],
+        ),
+        SYSCALL_RECVFROM => sys_recvfrom(
+            args[0],
+            args[1] as *mut u8,
+            args[2],
+            args[3] as u32,
+            args[4] as *mut u8,
+            args[5] as *mut u32,
+        ),
+        SYSCALL_SETSOCKOPT => sys_setsockopt(args[0], args[1], args[2], args[3], args[4]),
+        SYSCALL_SENDMSG => sys_sendmsg(args[0], args[1] as *const MsgHdr, args[2] as u32),
+        SYSCALL_RECVMSG => sys_recvmsg(args[0], args[1] as *mut MsgHdr, args[2] as u32),
+        SYSCALL_GETRANDOM => sys_getrandom(args[0] as *mut u8, args[1], args[2] as u32),
+        SYSCALL_MEMBARRIER => sys_membarrier(args[0], args[1] as u32, args[2] as i32),
         SYSCALL_GET_TIME_OF_DAY => sys_gettimeofday(
             args[0] as *mut crate::timer::TimeVal,
             args[1] as *mut crate::timer::TimeZone,
         ),
+        SYSCALL_SETTIMEOFDAY => sys_settimeofday(
+            args[0] as *const crate::timer::TimeVal,
+            args[1] as *const crate::timer::TimeZone,
+        ),
+        SYSCALL_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut crate::timer::TimeSpec),
+        SYSCALL_CLOCK_SETTIME => {
+            sys_clock_settime(args[0], args[1] as *const crate::timer::TimeSpec)
+        }
         // SYSCALL_FORK => sys_fork(),
+        // RISC-V 的 clone 参数顺序为 flags, stack, ptid, tls, ctid（CLONE_BACKWARDS）
+        #[cfg(feature = "riscv")]
         SYSCALL_CLONE => sys_clone(
             args[0] as u32,
             args[1] as *const u8,
@@ -104,11 +373,27 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             args[3],
             args[4] as *mut u32,
         ),
+        // LoongArch 使用通用的顺序 flags, stack, ptid, ctid, tls
+        #[cfg(feature = "loongarch")]
+        SYSCALL_CLONE => sys_clone(
+            args[0] as u32,
+            args[1] as *const u8,
+            args[2] as *mut u32,
+            args[4],
+            args[3] as *mut u32,
+        ),
         SYSCALL_EXECVE => sys_execve(
             args[0] as *const u8,
             args[1] as *const *const u8,
             args[2] as *const *const u8,
         ),
+        SYSCALL_EXECVEAT => sys_execveat(
+            args[0],
+            args[1] as *const u8,
+            args[2] as *const *const u8,
+            args[3] as *const *const u8,
+            args[4] as u32,
+        ),
         //SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
         SYSCALL_WAIT4 => sys_wait4(
             args[0] as isize,
@@ -116,10 +401,39 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             args[2] as u32,
             args[3] as *mut Rusage,
         ),
+        SYSCALL_PRLIMIT64 => sys_prlimit64(args[0], args[1], args[2], args[3]),
         SYSCALL_NANOSLEEP => sys_nanosleep(
             args[0] as *const crate::timer::TimeSpec,
             args[1] as *mut crate::timer::TimeSpec,
         ),
+        SYSCALL_GETITIMER => sys_getitimer(args[0], args[1] as *mut crate::timer::ITimerVal),
+        SYSCALL_SETITIMER => sys_setitimer(
+            args[0],
+            args[1] as *const crate::timer::ITimerVal,
+            args[2] as *mut crate::timer::ITimerVal,
+        ),
+        SYSCALL_PTRACE => sys_ptrace(args[0], args[1], args[2], args[3]),
+        SYSCALL_KILL => sys_kill(args[0] as isize, args[1]),
+        SYSCALL_TKILL => sys_tkill(args[0] as isize, args[1]),
+        SYSCALL_TGKILL => sys_tgkill(args[0] as isize, args[1] as isize, args[2]),
+        SYSCALL_SIGALTSTACK => sys_sigaltstack(
+            args[0] as *const crate::task::SignalStack,
+            args[1] as *mut crate::task::SignalStack,
+        ),
+        SYSCALL_STRACE => sys_strace(args[0], args[1], args[2] as *mut u8, args[3]),
+        SYSCALL_MUTEX_CREATE => sys_mutex_create(args[0] != 0),
+        SYSCALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
+        SYSCALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
+        SYSCALL_SEMAPHORE_CREATE => sys_semaphore_create(args[0]),
+        SYSCALL_SEMAPHORE_UP => sys_semaphore_up(args[0]),
+        SYSCALL_SEMAPHORE_DOWN => sys_semaphore_down(args[0]),
+        SYSCALL_CONDVAR_CREATE => sys_condvar_create(),
+        SYSCALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
+        SYSCALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        #[cfg(feature = "deadlock_detect")]
+        SYSCALL_ENABLE_DEADLOCK_DETECT => sys_enable_deadlock_detect(args[0]),
+        #[cfg(not(feature = "deadlock_detect"))]
+        SYSCALL_ENABLE_DEADLOCK_DETECT => -1, // ENOSYS
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
     }
 }
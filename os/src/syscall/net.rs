@@ -0,0 +1,600 @@
+//! # 套接字相关系统调用
+//!
+//! ## Overview
+//! 支持两类套接字：
+//! - AF_UNIX 流套接字（`fs::Socket`）
+//! - 回环上的 AF_INET UDP / TCP 套接字（`net::InetSocket`）
+//!
+//! 提供 `socket / socketpair / bind / listen / connect / accept /
+//! sendto / recvfrom / sendmsg / recvmsg / getsockname / getpeername / setsockopt`，
+//! 已连接的套接字之后可直接使用 read / write / close。
+//!
+//! ## Behavior
+//! - 所有系统调用失败时返回 `-1`
+//! - AF_UNIX 中以 NUL 开头的抽象地址以 `@` 前缀登记，其余路径按 cwd 解析为绝对路径
+//! - `sendmsg` / `recvmsg` 的控制消息只支持 AF_UNIX 套接字上的 `SCM_RIGHTS`：
+//!   发送时按文件描述符取出发送进程的文件交给套接字，接收时把文件安装到接收进程的文件描述符表中，
+//!   控制消息缓冲区放不下的文件被丢弃并在 `msg_flags` 中设置 `MSG_CTRUNC`
+
+use super::fs::{install_file, sys_close, IoVec, IOV_MAX};
+use crate::fs::{
+    make_socket_pair, resolve_path, File, SockAddrUn, Socket, AF_UNIX, SOCK_CLOEXEC, SOCK_NONBLOCK,
+    SOCK_STREAM,
+};
+use crate::mm::{copy_to_user, get_from_user, translated_byte_buffer, UserBuffer};
+use crate::net::{InetSocket, SockAddrIn, AF_INET, SOCK_DGRAM};
+use crate::task::{current_process, current_user_token};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// 控制消息的协议层与类型：经由套接字传递文件描述符
+const SOL_SOCKET: i32 = 1;
+const SCM_RIGHTS: i32 = 1;
+/// 一条消息最多传递的文件数（Linux SCM_MAX_FD）
+const SCM_MAX_FD: usize = 253;
+/// recvmsg 的标志：收到的文件描述符设置 FD_CLOEXEC
+const MSG_CMSG_CLOEXEC: u32 = 0x4000_0000;
+/// `msg_flags`：控制消息缓冲区不足，有文件被丢弃
+const MSG_CTRUNC: i32 = 0x8;
+
+/// 用户态 `struct msghdr`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MsgHdr {
+    /// 对端地址：sendmsg 的目标、recvmsg 的来源
+    pub msg_name: usize,
+    pub msg_namelen: u32,
+    /// iovec 数组
+    pub msg_iov: usize,
+    pub msg_iovlen: usize,
+    /// 控制消息（依次排列的 `cmsghdr`）
+    pub msg_control: usize,
+    pub msg_controllen: usize,
+    pub msg_flags: i32,
+}
+
+/// 用户态 `struct cmsghdr`，数据紧随其后
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CmsgHdr {
+    /// 头部与数据的总长度
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+/// 控制消息按 `usize` 对齐（CMSG_ALIGN）
+fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// 取出 fd 对应的文件
+fn fd_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.fd_table.get(fd)?.as_ref().cloned()
+}
+
+/// 从用户态 `sockaddr_un` 中解析出登记用的路径
+fn read_unix_path(addr: *const u8, addrlen: usize) -> Option<String> {
+    if addr.is_null() || addrlen <= size_of::<u16>() || addrlen > size_of::<SockAddrUn>() {
+        return None;
+    }
+    let token = current_user_token();
+    let mut raw = vec![0u8; addrlen];
+    UserBuffer::new(translated_byte_buffer(token, addr, addrlen, false).ok()?).read(None, &mut raw);
+    if u16::from_ne_bytes([raw[0], raw[1]]) as usize != AF_UNIX {
+        return None;
+    }
+    let path = &raw[size_of::<u16>()..];
+    if path[0] == 0 {
+        // 抽象命名空间
+        let name = String::from_utf8_lossy(&path[1..]);
+        return Some(alloc::format!("@{}", name.trim_end_matches('\0')));
+    }
+    let end = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+    let path = core::str::from_utf8(&path[..end]).ok()?;
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    Some(resolve_path(path, inner.cwd.as_str()))
+}
+
+/// 读取用户态 `sockaddr_in`
+fn read_inet_addr(addr: *const u8, addrlen: usize) -> Option<SockAddrIn> {
+    if addr.is_null() || addrlen < size_of::<SockAddrIn>() {
+        return None;
+    }
+    let addr = get_from_user(current_user_token(), addr as *const SockAddrIn).ok()?;
+    if addr.sin_family as usize != AF_INET {
+        return None;
+    }
+    Some(addr)
+}
+
+/// 将 `sockaddr_in` 写回用户态（`addr` / `addrlen` 均可为空）
+fn write_inet_addr(addr: *mut u8, addrlen: *mut u32, value: &SockAddrIn) -> isize {
+    if addr.is_null() || addrlen.is_null() {
+        return 0;
+    }
+    let token = current_user_token();
+    let len = size_of::<SockAddrIn>() as u32;
+    if copy_to_user(token, value, addr as *mut SockAddrIn).is_err()
+        || copy_to_user(token, &len, addrlen).is_err()
+    {
+        return -1; // EFAULT
+    }
+    0
+}
+
+pub fn sys_socket(domain: usize, ty: usize, _protocol: usize) -> isize {
+    let nonblocking = ty & SOCK_NONBLOCK != 0;
+    let cloexec = ty & SOCK_CLOEXEC != 0;
+    let ty = ty & !(SOCK_NONBLOCK | SOCK_CLOEXEC);
+    match (domain, ty) {
+        (AF_UNIX, SOCK_STREAM) => {
+            let socket = Arc::new(Socket::new());
+            socket.set_nonblocking(nonblocking);
+            install_file(socket, cloexec)
+        }
+        (AF_INET, SOCK_STREAM) | (AF_INET, SOCK_DGRAM) => {
+            let socket = Arc::new(InetSocket::new(ty));
+            socket.set_nonblocking(nonblocking);
+            install_file(socket, cloexec)
+        }
+        _ => -1, // EAFNOSUPPORT / EPROTOTYPE
+    }
+}
+
+pub fn sys_socketpair(domain: usize, ty: usize, _protocol: usize, sv: *mut i32) -> isize {
+    if domain != AF_UNIX || ty & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != SOCK_STREAM {
+        return -1;
+    }
+    let token = current_user_token();
+    let (a, b) = make_socket_pair();
+    if ty & SOCK_NONBLOCK != 0 {
+        a.set_nonblocking(true);
+        b.set_nonblocking(true);
+    }
+    let cloexec = ty & SOCK_CLOEXEC != 0;
+    let fd0 = install_file(a, cloexec);
+    if fd0 < 0 {
+        return fd0;
+    }
+    let fd1 = install_file(b, cloexec);
+    if fd1 < 0 {
+        sys_close(fd0 as usize);
+        return fd1;
+    }
+    if copy_to_user(token, &[fd0 as i32, fd1 as i32], sv as *mut [i32; 2]).is_err() {
+        sys_close(fd0 as usize);
+        sys_close(fd1 as usize);
+        return -1; // EFAULT
+    }
+    0
+}
+
+pub fn sys_bind(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1, // EBADF
+    };
+    let res = if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        match read_unix_path(addr, addrlen) {
+            Some(path) => socket.bind(path),
+            None => Err(-1), // EINVAL
+        }
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        match read_inet_addr(addr, addrlen) {
+            Some(addr) => socket.bind(&addr),
+            None => Err(-1),
+        }
+    } else {
+        Err(-1) // ENOTSOCK
+    };
+    match res {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let res = if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        socket.listen(backlog)
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        socket.listen(backlog)
+    } else {
+        Err(-1)
+    };
+    match res {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+pub fn sys_connect(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let res = if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        match read_unix_path(addr, addrlen) {
+            Some(path) => socket.connect(path.as_str()),
+            None => Err(-1),
+        }
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        match read_inet_addr(addr, addrlen) {
+            Some(addr) => socket.connect(&addr),
+            None => Err(-1),
+        }
+    } else {
+        Err(-1)
+    };
+    match res {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+pub fn sys_accept(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let token = current_user_token();
+    if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        let conn = match socket.accept() {
+            Ok(conn) => conn,
+            Err(e) => return e,
+        };
+        if !addr.is_null() && !addrlen.is_null() {
+            // 对端通常是未绑定的套接字，只回填地址族
+            let family = AF_UNIX as u16;
+            let len = size_of::<u16>() as u32;
+            if copy_to_user(token, &family, addr as *mut u16).is_err()
+                || copy_to_user(token, &len, addrlen).is_err()
+            {
+                return -1; // EFAULT
+            }
+        }
+        install_file(conn, false)
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        let conn = match socket.accept() {
+            Ok(conn) => conn,
+            Err(e) => return e,
+        };
+        if write_inet_addr(addr, addrlen, &conn.peer_addr().unwrap_or_default()) < 0 {
+            return -1;
+        }
+        install_file(conn, false)
+    } else {
+        -1
+    }
+}
+
+pub fn sys_sendto(
+    fd: usize,
+    buf: *const u8,
+    len: usize,
+    _flags: u32,
+    dest_addr: *const u8,
+    addrlen: usize,
+) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let token = current_user_token();
+    let user_buf = match translated_byte_buffer(token, buf, len, false) {
+        Ok(buffers) => UserBuffer::new(buffers),
+        Err(e) => return e,
+    };
+    match file.as_any().downcast_ref::<InetSocket>() {
+        Some(socket) if socket.is_dgram() => {
+            let dest = if dest_addr.is_null() {
+                None
+            } else {
+                match read_inet_addr(dest_addr, addrlen) {
+                    Some(addr) => Some(addr),
+                    None => return -1, // EINVAL
+                }
+            };
+            let mut data = vec![0u8; len];
+            user_buf.read(None, &mut data);
+            match socket.send_to(&data, dest) {
+                Ok(n) => n as isize,
+                Err(e) => e,
+            }
+        }
+        // 流套接字忽略目标地址，等价于 write
+        Some(_) => file.write(user_buf) as isize,
+        None if file.as_any().downcast_ref::<Socket>().is_some() => file.write(user_buf) as isize,
+        None => -1, // ENOTSOCK
+    }
+}
+
+pub fn sys_recvfrom(
+    fd: usize,
+    buf: *mut u8,
+    len: usize,
+    _flags: u32,
+    src_addr: *mut u8,
+    addrlen: *mut u32,
+) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let token = current_user_token();
+    let user_buf = match translated_byte_buffer(token, buf, len, true) {
+        Ok(buffers) => UserBuffer::new(buffers),
+        Err(e) => return e,
+    };
+    if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        let (n, from) = match socket.recv_from(user_buf) {
+            Ok(res) => res,
+            Err(e) => return e,
+        };
+        if write_inet_addr(src_addr, addrlen, &from) < 0 {
+            return -1;
+        }
+        n as isize
+    } else if file.as_any().downcast_ref::<Socket>().is_some() {
+        file.read(user_buf) as isize
+    } else {
+        -1 // ENOTSOCK
+    }
+}
+
+/// 把 iovec 数组描述的各段缓冲区拼成一个 `UserBuffer`
+fn iovec_buffer(token: usize, iov: usize, iovlen: usize, write: bool) -> Result<UserBuffer, isize> {
+    if iovlen > IOV_MAX {
+        return Err(-1); // EMSGSIZE
+    }
+    let mut buffers = Vec::new();
+    for i in 0..iovlen {
+        let vec = get_from_user(token, (iov as *const IoVec).wrapping_add(i))?;
+        let base = vec.iov_base as *const u8;
+        buffers.extend(translated_byte_buffer(token, base, vec.iov_len, write)?);
+    }
+    Ok(UserBuffer::new(buffers))
+}
+
+/// 解析 sendmsg 的控制消息，取出 `SCM_RIGHTS` 中各文件描述符对应的文件
+///
+/// 控制消息格式错误或不是 `SCM_RIGHTS` 时返回 EINVAL，文件描述符无效时返回 EBADF
+fn read_rights(
+    token: usize,
+    control: usize,
+    controllen: usize,
+) -> Result<Vec<Arc<dyn File + Send + Sync>>, isize> {
+    let header = size_of::<CmsgHdr>();
+    let mut files = Vec::new();
+    let mut offset = 0;
+    while offset + header <= controllen {
+        let cmsg = get_from_user(token, (control + offset) as *const CmsgHdr)?;
+        if cmsg.cmsg_len < header || cmsg.cmsg_len > controllen - offset {
+            return Err(-1); // EINVAL
+        }
+        if cmsg.cmsg_level != SOL_SOCKET || cmsg.cmsg_type != SCM_RIGHTS {
+            return Err(-1); // EINVAL
+        }
+        let count = (cmsg.cmsg_len - header) / size_of::<i32>();
+        if files.len() + count > SCM_MAX_FD {
+            return Err(-1); // EINVAL
+        }
+        for i in 0..count {
+            let ptr = (control + offset + header + i * size_of::<i32>()) as *const i32;
+            let fd = get_from_user(token, ptr)?;
+            files.push(fd_file(fd as usize).ok_or(-1isize)?); // EBADF
+        }
+        offset += cmsg_align(cmsg.cmsg_len);
+    }
+    Ok(files)
+}
+
+/// 把收到的文件安装到本进程的文件描述符表，并在 `hdr.msg_control` 中写入 `SCM_RIGHTS` 控制消息
+///
+/// 更新 `hdr` 中的 `msg_controllen` 与 `msg_flags`，与 Linux 一样丢弃控制消息缓冲区放不下的文件，
+/// 文件描述符用尽时剩下的文件同样被丢弃
+fn install_rights(
+    token: usize,
+    hdr: &mut MsgHdr,
+    files: Vec<Arc<dyn File + Send + Sync>>,
+    cloexec: bool,
+) -> Result<(), isize> {
+    let header = size_of::<CmsgHdr>();
+    let room = hdr.msg_controllen.saturating_sub(header) / size_of::<i32>();
+    let total = files.len();
+    let fds: Vec<i32> = files
+        .into_iter()
+        .take(room)
+        .map(|file| install_file(file, cloexec))
+        .take_while(|&fd| fd >= 0)
+        .map(|fd| fd as i32)
+        .collect();
+    let count = fds.len();
+    if count < total {
+        hdr.msg_flags |= MSG_CTRUNC;
+    }
+    if count == 0 {
+        hdr.msg_controllen = 0;
+        return Ok(());
+    }
+    let cmsg = CmsgHdr {
+        cmsg_len: header + count * size_of::<i32>(),
+        cmsg_level: SOL_SOCKET,
+        cmsg_type: SCM_RIGHTS,
+    };
+    let control = hdr.msg_control;
+    let written = copy_to_user(token, &cmsg, control as *mut CmsgHdr).and_then(|()| {
+        fds.iter().enumerate().try_for_each(|(i, fd)| {
+            let ptr = (control + header + i * size_of::<i32>()) as *mut i32;
+            copy_to_user(token, fd, ptr)
+        })
+    });
+    if written.is_err() {
+        for fd in fds {
+            sys_close(fd as usize);
+        }
+        return Err(-1); // EFAULT
+    }
+    hdr.msg_controllen = cmsg_align(cmsg.cmsg_len).min(hdr.msg_controllen);
+    Ok(())
+}
+
+/// 按 `struct msghdr` 发送：数据取自 iovec 数组，AF_UNIX 套接字可以附带 `SCM_RIGHTS` 传递文件
+pub fn sys_sendmsg(fd: usize, msg: *const MsgHdr, _flags: u32) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1, // EBADF
+    };
+    let token = current_user_token();
+    let hdr = match get_from_user(token, msg) {
+        Ok(hdr) => hdr,
+        Err(e) => return e,
+    };
+    let user_buf = match iovec_buffer(token, hdr.msg_iov, hdr.msg_iovlen, false) {
+        Ok(user_buf) => user_buf,
+        Err(e) => return e,
+    };
+    if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        let files = match read_rights(token, hdr.msg_control, hdr.msg_controllen) {
+            Ok(files) => files,
+            Err(e) => return e,
+        };
+        // 数据写入成功后文件才进入队列，并记下这些数据在字节流中的位置
+        let n = file.write(user_buf);
+        if n > 0 && !files.is_empty() {
+            if let Err(e) = socket.send_files(files, n) {
+                return e;
+            }
+        }
+        n as isize
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        if hdr.msg_controllen != 0 {
+            return -1; // EINVAL
+        }
+        if !socket.is_dgram() {
+            return file.write(user_buf) as isize;
+        }
+        let dest = if hdr.msg_name == 0 {
+            None
+        } else {
+            match read_inet_addr(hdr.msg_name as *const u8, hdr.msg_namelen as usize) {
+                Some(addr) => Some(addr),
+                None => return -1, // EINVAL
+            }
+        };
+        let mut data = vec![0u8; user_buf.len()];
+        user_buf.read(None, &mut data);
+        match socket.send_to(&data, dest) {
+            Ok(n) => n as isize,
+            Err(e) => e,
+        }
+    } else {
+        -1 // ENOTSOCK
+    }
+}
+
+/// 按 `struct msghdr` 接收：数据读入 iovec 数组
+///
+/// AF_UNIX 套接字读到数据时一并取出对端随已读数据传来的一批文件，安装到本进程后以 `SCM_RIGHTS` 返回新的文件描述符；
+/// `flags` 中只有 `MSG_CMSG_CLOEXEC` 起作用
+pub fn sys_recvmsg(fd: usize, msg: *mut MsgHdr, flags: u32) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1, // EBADF
+    };
+    let token = current_user_token();
+    let mut hdr = match get_from_user(token, msg as *const MsgHdr) {
+        Ok(hdr) => hdr,
+        Err(e) => return e,
+    };
+    let user_buf = match iovec_buffer(token, hdr.msg_iov, hdr.msg_iovlen, true) {
+        Ok(user_buf) => user_buf,
+        Err(e) => return e,
+    };
+    hdr.msg_flags = 0;
+    let n = if let Some(socket) = file.as_any().downcast_ref::<Socket>() {
+        let n = file.read(user_buf);
+        let files = if n > 0 { socket.take_files() } else { None };
+        let cloexec = flags & MSG_CMSG_CLOEXEC != 0;
+        if let Err(e) = install_rights(token, &mut hdr, files.unwrap_or_default(), cloexec) {
+            return e;
+        }
+        hdr.msg_namelen = 0;
+        n
+    } else if let Some(socket) = file.as_any().downcast_ref::<InetSocket>() {
+        hdr.msg_controllen = 0;
+        if !socket.is_dgram() {
+            hdr.msg_namelen = 0;
+            file.read(user_buf)
+        } else {
+            let (n, from) = match socket.recv_from(user_buf) {
+                Ok(res) => res,
+                Err(e) => return e,
+            };
+            let len = size_of::<SockAddrIn>();
+            if hdr.msg_name != 0 && hdr.msg_namelen as usize >= len {
+                if copy_to_user(token, &from, hdr.msg_name as *mut SockAddrIn).is_err() {
+                    return -1; // EFAULT
+                }
+                hdr.msg_namelen = len as u32;
+            }
+            n
+        }
+    } else {
+        return -1; // ENOTSOCK
+    };
+    if copy_to_user(token, &hdr, msg).is_err() {
+        return -1; // EFAULT
+    }
+    n as isize
+}
+
+pub fn sys_getsockname(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    match file.as_any().downcast_ref::<InetSocket>() {
+        Some(socket) => write_inet_addr(addr, addrlen, &socket.local_addr()),
+        None => -1,
+    }
+}
+
+pub fn sys_getpeername(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    let file = match fd_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    match file
+        .as_any()
+        .downcast_ref::<InetSocket>()
+        .and_then(|s| s.peer_addr())
+    {
+        Some(peer) => write_inet_addr(addr, addrlen, &peer),
+        None => -1, // ENOTCONN
+    }
+}
+
+/// 回环上的选项（SO_REUSEADDR、TCP_NODELAY 等）均无实际意义，直接接受
+pub fn sys_setsockopt(
+    fd: usize,
+    _level: usize,
+    _optname: usize,
+    _optval: usize,
+    _optlen: usize,
+) -> isize {
+    match fd_file(fd) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
@@ -1,60 +1,231 @@
 #![allow(unused)]
 
-use crate::fs::{open_file, OpenFlags};
+use super::fs::{resolve_at, AT_EMPTY_PATH, AT_SYMLINK_NOFOLLOW};
+use crate::fs::{file_meta_or_default, open_file, File, OpenFlags};
 use crate::mm::{
-    copy_to_user, get_from_user, translated_byte_buffer, translated_ref, translated_refmut,
-    translated_str, UserBuffer,
+    copy_to_user, get_from_user, translated_byte_buffer, translated_str, try_read_bytes,
+    try_write_bytes, UserBuffer,
 };
+use crate::random::KERNEL_RNG;
+use crate::smp::{online_harts, this_hart, ALL_HARTS};
 use crate::task::{
-    block_current_and_run_next, current_process, current_task, current_user_token,
-    exit_current_and_run_next, find_task_by_pid, pid2process, suspend_current_and_run_next,
-    wake_blocked, Rusage, SignalFlags, TaskStatus,
+    all_processes, block_current_and_run_next, block_current_interruptible, current_process,
+    current_task, current_user_token, exit_current_and_run_next, exit_group_and_run_next,
+    pid2process, signal_pending_of_current, suspend_current_and_run_next, Credentials,
+    ProcessControlBlock, RLimit, Rusage, SignalFlags, SignalStack, TaskControlBlock, TaskName,
+    WaitStatus, MINSIGSTKSZ, SS_AUTODISARM, SS_DISABLE, SS_ONSTACK, TASK_COMM_LEN,
+};
+use crate::timer::{
+    clock_now, get_time_ms, set_realtime, ITimerVal, TimeSpec, TimeVal, TimeZone, Timer, Tms,
+    CLOCK_REALTIME, NSEC_PER_SEC, NSEC_PER_USEC, USEC_PER_SEC,
 };
-use crate::timer::{add_timer, get_time_ms, TimeSpec, TimeVal, TimeZone, Tms};
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::mem::size_of;
 use bitflags::bitflags;
 use core::ops::AddAssign;
+use core::sync::atomic::Ordering;
 
 pub fn sys_exit(exit_code: i32) -> ! {
-    exit_current_and_run_next((exit_code & 0xff) << 8);
+    exit_current_and_run_next(WaitStatus::Exited(exit_code).encode());
     panic!("Unreachable in sys_exit!");
 }
 
+/// 结束整个线程组，父进程的 wait4 得到 `exit_code`
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    exit_group_and_run_next(WaitStatus::Exited(exit_code).encode());
+    panic!("Unreachable in sys_exit_group!");
+}
+
 pub fn sys_yield() -> isize {
     suspend_current_and_run_next();
     0
 }
 
-pub fn sys_getpid() -> isize {
-    current_task().unwrap().process.upgrade().unwrap().getpid() as isize
+/// `sched_*affinity` 的目标线程：`pid` 为 0 时是当前线程，否则是进程 `pid` 的主线程
+///
+/// 修改其他用户的进程需要有效用户 ID 为 0 或与对方的实际 / 有效用户 ID 相同
+fn affinity_target(pid: usize, modify: bool) -> Result<Arc<TaskControlBlock>, isize> {
+    if pid == 0 {
+        return Ok(current_task().unwrap());
+    }
+    let cred = current_process().inner_exclusive_access().cred;
+    let process = pid2process(pid).ok_or(-1isize)?; // ESRCH
+    let inner = process.inner_exclusive_access();
+    if modify {
+        if cred.euid != 0 && cred.euid != inner.cred.uid && cred.euid != inner.cred.euid {
+            return Err(-1); // EPERM
+        }
+    }
+    inner.tasks.first().cloned().flatten().ok_or(-1) // ESRCH
 }
-/// brk 用于设置或获取当前进程的数据段（堆）的结束地址,成功返回新的堆顶地址，失败返回 -1
-/// 如果传入的 addr 为 0，则返回当前堆顶地址
-pub fn sys_brk(addr: usize) -> isize {
-    let task = current_task().unwrap();
-    let process = task.process.upgrade().unwrap();
-    let mut inner = process.inner_exclusive_access();
 
-    let memory_set = &mut inner.memory_set;
-    //相当于查看当前堆顶是多少
-    if addr == 0 {
-        return memory_set.brk as isize;
+/// 设置线程的 CPU 亲和性，掩码中超出 `MAX_HARTS` 的位被忽略
+///
+/// 掩码中没有在线的 hart 时返回 EINVAL；当前线程不再允许在所在 hart 上运行时立即让出，迁移到允许的 hart
+pub fn sys_sched_setaffinity(pid: usize, cpusetsize: usize, mask: *const u8) -> isize {
+    let mut bytes = [0u8; size_of::<usize>()];
+    let len = cpusetsize.min(bytes.len());
+    if try_read_bytes(current_user_token(), mask as usize, &mut bytes[..len]).is_none() {
+        return -1; // EFAULT
+    }
+    let affinity = usize::from_le_bytes(bytes) & ALL_HARTS;
+    if affinity & online_harts() == 0 {
+        return -1; // EINVAL
+    }
+    let task = match affinity_target(pid, true) {
+        Ok(task) => task,
+        Err(err) => return err,
+    };
+    task.cpu_affinity.store(affinity, Ordering::Relaxed);
+    let migrate = Arc::ptr_eq(&task, &current_task().unwrap()) && !task.runs_on(this_hart());
+    drop(task);
+    if migrate {
+        suspend_current_and_run_next();
+    }
+    0
+}
+
+/// 读取线程的 CPU 亲和性，返回写入的字节数
+///
+/// `cpusetsize` 须为 `usize` 大小的整数倍且能容纳全部 hart，与 Linux 一致
+pub fn sys_sched_getaffinity(pid: usize, cpusetsize: usize, mask: *mut u8) -> isize {
+    if cpusetsize < size_of::<usize>() || cpusetsize % size_of::<usize>() != 0 {
+        return -1; // EINVAL
+    }
+    let task = match affinity_target(pid, false) {
+        Ok(task) => task,
+        Err(err) => return err,
+    };
+    let affinity = task.cpu_affinity.load(Ordering::Relaxed) & online_harts();
+    if try_write_bytes(current_user_token(), mask as usize, &affinity.to_le_bytes()).is_none() {
+        return -1; // EFAULT
+    }
+    size_of::<usize>() as isize
+}
+
+/// `prctl` 的操作
+pub const PR_SET_NAME: usize = 15;
+pub const PR_GET_NAME: usize = 16;
+
+/// 进程控制，目前只支持设置 / 读取当前线程的名字
+///
+/// - `PR_SET_NAME`：`arg2` 指向的字符串超过 15 字节时被截断
+/// - `PR_GET_NAME`：向 `arg2` 写入 16 字节，以 0 结尾
+pub fn sys_prctl(option: usize, arg2: usize) -> isize {
+    let token = current_user_token();
+    match option {
+        PR_SET_NAME => {
+            let mut buf = [0u8; TASK_COMM_LEN];
+            // 逐字节读取，字符串之后的地址可能没有映射
+            for i in 0..TASK_COMM_LEN - 1 {
+                let Some(addr) = arg2.checked_add(i) else {
+                    return -1; // EFAULT
+                };
+                if try_read_bytes(token, addr, &mut buf[i..i + 1]).is_none() {
+                    return -1; // EFAULT
+                }
+                if buf[i] == 0 {
+                    break;
+                }
+            }
+            current_task().unwrap().inner_exclusive_access().name = TaskName::new(&buf);
+            0
+        }
+        PR_GET_NAME => {
+            let name = current_task().unwrap().inner_exclusive_access().name;
+            if try_write_bytes(token, arg2, name.as_bytes()).is_none() {
+                return -1; // EFAULT
+            }
+            0
+        }
+        _ => -1, // EINVAL
     }
+}
+
+/// `prlimit64` 支持的资源
+pub const RLIMIT_CORE: usize = 4;
+pub const RLIMIT_NOFILE: usize = 7;
 
-    if addr < memory_set.brk {
-        return memory_set.brk as isize;
+/// 读取并设置进程 `pid`（为 0 时是当前进程）的资源限制，目前只支持 `RLIMIT_CORE` 与 `RLIMIT_NOFILE`
+///
+/// - `new_limit` / `old_limit` 为 0 时不设置 / 不读取
+/// - 软限制超过硬限制返回 EINVAL，非特权进程提高硬限制返回 EPERM
+/// - 操作其他用户的进程需要有效用户 ID 为 0 或与对方的实际 / 有效用户 ID 相同
+pub fn sys_prlimit64(pid: usize, resource: usize, new_limit: usize, old_limit: usize) -> isize {
+    if resource != RLIMIT_CORE && resource != RLIMIT_NOFILE {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let new = if new_limit == 0 {
+        None
+    } else {
+        let mut bytes = [0u8; size_of::<RLimit>()];
+        if try_read_bytes(token, new_limit, &mut bytes).is_none() {
+            return -1; // EFAULT
+        }
+        let (cur, max) = bytes.split_at(size_of::<u64>());
+        Some(RLimit {
+            rlim_cur: u64::from_le_bytes(cur.try_into().unwrap()),
+            rlim_max: u64::from_le_bytes(max.try_into().unwrap()),
+        })
+    };
+    let cred = current_process().inner_exclusive_access().cred;
+    let process = if pid == 0 {
+        current_process()
+    } else {
+        match pid2process(pid) {
+            Some(process) => process,
+            None => return -1, // ESRCH
+        }
+    };
+    let mut inner = process.inner_exclusive_access();
+    if cred.euid != 0 && cred.euid != inner.cred.uid && cred.euid != inner.cred.euid {
+        return -1; // EPERM
     }
-    // 扩展堆
-    let old_brk = memory_set.brk;
-    if memory_set.expand_heap(addr).is_err() {
-        return -1;
+    let limit = if resource == RLIMIT_CORE {
+        &mut inner.core_limit
+    } else {
+        &mut inner.nofile_limit
+    };
+    let old = *limit;
+    if let Some(new) = new {
+        if new.rlim_cur > new.rlim_max {
+            return -1; // EINVAL
+        }
+        if new.rlim_max > old.rlim_max && !cred.is_privileged() {
+            return -1; // EPERM
+        }
+        *limit = new;
+    }
+    drop(inner);
+    if old_limit != 0 {
+        let mut bytes = [0u8; size_of::<RLimit>()];
+        bytes[..size_of::<u64>()].copy_from_slice(&old.rlim_cur.to_le_bytes());
+        bytes[size_of::<u64>()..].copy_from_slice(&old.rlim_max.to_le_bytes());
+        if try_write_bytes(token, old_limit, &bytes).is_none() {
+            return -1; // EFAULT
+        }
     }
+    0
+}
 
-    memory_set.brk = addr;
-    addr as isize
+pub fn sys_getpid() -> isize {
+    current_task().unwrap().process.upgrade().unwrap().getpid() as isize
+}
+/// 把堆顶设置为 `addr`，返回设置后的堆顶
+///
+/// 与 Linux 相同，`addr` 为 0 或设置失败时不报错，而是返回当前的堆顶，由调用者比较得知失败（ENOMEM）
+pub fn sys_brk(addr: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let memory_set = &mut inner.memory_set;
+    if addr != 0 {
+        // 失败时 brk 保持不变
+        let _ = memory_set.set_brk(addr);
+    }
+    memory_set.brk as isize
 }
 
 /// unmap用来释放一段虚拟地址空间.成果返回0，失败返回-1
@@ -67,6 +238,46 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     }
 }
 
+/// 对 `[start, start + len)` 给出内存使用建议，支持 `MADV_DONTNEED` 与 `MADV_FREE`
+pub fn sys_madvise(start: usize, len: usize, advice: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.memory_set.madvise(start, len, advice) {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+/// 在 `vec` 中为 `[start, start + len)` 的每一页写入一个字节，最低位为 1 表示该页驻留在内存中
+pub fn sys_mincore(start: usize, len: usize, vec: *mut u8) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let residency = match inner.memory_set.mincore(start, len) {
+        Ok(residency) => residency,
+        Err(e) => return e,
+    };
+    let token = inner.memory_set.token();
+    // 写入 vec 时可能触发缺页处理，需先释放进程锁
+    drop(inner);
+    match translated_byte_buffer(token, vec, residency.len(), true) {
+        Ok(buffers) => {
+            UserBuffer::new(buffers).write_buffer(None, &residency);
+            0
+        }
+        Err(e) => e, // EFAULT
+    }
+}
+
+/// 把 `[start, start + len)` 中的文件共享映射写回文件，支持 `MS_SYNC`、`MS_ASYNC` 与 `MS_INVALIDATE`
+pub fn sys_msync(start: usize, len: usize, flags: usize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    match inner.memory_set.msync(start, len, flags) {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
 pub fn sys_mmap(
     start: usize,
     len: usize,
@@ -125,8 +336,10 @@ pub fn sys_clone(
     let flags = CloneFlags::from_bits(flags & !0xff).unwrap();
     let child = parent.sys_clone(flags, stack, tls, exit_signal);
     let child_pid = child.pid.0;
+    super::follow_fork(parent.getpid(), child_pid);
     if copy_flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
-        *translated_refmut(parent_token, ptid) = child.pid.0 as u32
+        // 与 Linux 相同，子进程已经创建，写入失败时忽略
+        let _ = copy_to_user(parent_token, &(child.pid.0 as u32), ptid);
     }
     // if copy_flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
     //     *translated_refmut(parent_token, ctid) = child.pid.0 as u32
@@ -141,6 +354,17 @@ pub fn sys_clone(
     // for child process, fork returns 0
     trap_cx.general_regs.a0 = 0;
     // print!("child: {}", trap_cx.general_regs.a0) ;
+    if copy_flags.contains(CloneFlags::CLONE_SETTLS) {
+        trap_cx.set_tp(tls);
+    }
+    drop(child_inner);
+    if copy_flags.contains(CloneFlags::CLONE_VFORK) {
+        // 父线程阻塞，直到子进程 exec 或退出时将其唤醒
+        child.inner_exclusive_access().vfork_waiter = Some(parent_task.clone());
+        drop(parent_task);
+        drop(child);
+        block_current_and_run_next();
+    }
     child_pid as isize
 }
 // pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
@@ -168,49 +392,134 @@ pub fn sys_clone(
 //         -1
 //     }
 // }
-pub fn sys_execve(
-    path: *const u8,
-    mut argv: *const *const u8,
-    mut envp: *const *const u8,
-) -> isize {
-    let token = current_user_token();
-    let path = translated_str(token, path);
-    let mut argv_vec: Vec<String> = Vec::new();
-    let mut envp_vec: Vec<String> = Vec::new();
-    if !argv.is_null() {
-        loop {
-            let arg_str_ptr = *translated_ref(token, argv);
-            if arg_str_ptr.is_null() {
-                break;
-            }
-            argv_vec.push(translated_str(token, arg_str_ptr));
-            unsafe {
-                argv = argv.add(1);
-            }
-        }
+/// `#!` 行的最大长度，与 Linux 的 BINPRM_BUF_SIZE 一致
+const BINPRM_BUF_SIZE: usize = 256;
+/// 解释器的最大嵌套层数（解释器本身也可以是脚本），与 Linux 一致
+const MAX_INTERP_DEPTH: usize = 4;
+
+/// 读取用户态以空指针结尾的字符串指针数组（argv / envp）
+///
+/// 数组或其中的字符串不可读时返回 EFAULT
+fn read_user_strings(token: usize, mut ptrs: *const *const u8) -> Result<Vec<String>, isize> {
+    let mut strings = Vec::new();
+    if ptrs.is_null() {
+        return Ok(strings);
     }
-    if !envp.is_null() {
-        loop {
-            let envp_str_ptr = *translated_ref(token, envp);
-            if envp_str_ptr.is_null() {
-                break;
-            }
-            envp_vec.push(translated_str(token, envp_str_ptr));
-            unsafe {
-                envp = envp.add(1);
-            }
+    loop {
+        let str_ptr = get_from_user(token, ptrs)?;
+        if str_ptr.is_null() {
+            break;
+        }
+        strings.push(translated_str(token, str_ptr)?);
+        unsafe {
+            ptrs = ptrs.add(1);
         }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+    Ok(strings)
+}
+
+/// 解析脚本开头的 `#!` 行，返回解释器与可选的一个参数
+///
+/// 与 Linux 相同，解释器之后的内容去掉首尾空白后整体作为一个参数
+fn parse_shebang(data: &[u8]) -> Option<(String, Option<String>)> {
+    let line = data.strip_prefix(b"#!")?;
+    let line = &line[..line.len().min(BINPRM_BUF_SIZE - 2)];
+    let end = line.iter().position(|&c| c == b'\n').unwrap_or(line.len());
+    let line = core::str::from_utf8(&line[..end]).ok()?.trim();
+    let (interp, arg) = match line.find(|c: char| c == ' ' || c == '\t') {
+        Some(i) => (&line[..i], Some(line[i..].trim())),
+        None => (line, None),
+    };
+    if interp.is_empty() {
+        return None;
+    }
+    let arg = arg.filter(|arg| !arg.is_empty()).map(String::from);
+    Some((String::from(interp), arg))
+}
+
+/// 加载 `path` 处的程序替换当前进程的映像
+///
+/// 以 `#!` 开头的脚本改为加载其解释器，argv 变为“解释器 [参数] 脚本路径 原 argv[1..]”
+fn do_execve(mut path: String, mut argv: Vec<String>) -> isize {
+    // 与 Linux 相同，执行脚本时任务名取脚本而不是解释器的文件名
+    let name = TaskName::from_path(&path);
+    for _ in 0..=MAX_INTERP_DEPTH {
+        let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) else {
+            return -1; // ENOENT
+        };
         let all_data = app_inode.read_all();
+        if let Some((interp, arg)) = parse_shebang(&all_data) {
+            let mut interp_argv = Vec::with_capacity(argv.len() + 2);
+            interp_argv.push(interp.clone());
+            interp_argv.extend(arg);
+            interp_argv.push(path);
+            interp_argv.extend(argv.into_iter().skip(1));
+            argv = interp_argv;
+            path = interp;
+            continue;
+        }
+        if !all_data.starts_with(b"\x7fELF") {
+            return -1; // ENOEXEC
+        }
         let process = current_process();
-        let argv = argv_vec.len();
-        let envp = envp_vec.len();
-        process.exec(all_data.as_slice(), argv_vec);
-        0
-    } else {
-        -1
+        process.exec(all_data.as_slice(), argv);
+        // 新的地址空间已经生效，此后才更新身份，加载失败时调用者的身份保持不变；
+        // 脚本的 set-user-ID 位被忽略，只看最终加载的程序
+        let meta = file_meta_or_default(&app_inode.get_path());
+        process.inner_exclusive_access().cred.on_exec(
+            (meta.mode & S_ISUID != 0).then_some(meta.uid),
+            (meta.mode & S_ISGID != 0).then_some(meta.gid),
+        );
+        process.inner_exclusive_access().ptrace.on_exec();
+        current_task().unwrap().inner_exclusive_access().name = name;
+        return 0;
     }
+    -1 // ELOOP
+}
+
+pub fn sys_execve(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> isize {
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let argv_vec = match read_user_strings(token, argv) {
+        Ok(argv_vec) => argv_vec,
+        Err(err) => return err,
+    };
+    // 还不向新程序传递环境变量，但与 Linux 一致检查其可读
+    if let Err(err) = read_user_strings(token, envp) {
+        return err;
+    }
+    do_execve(path, argv_vec)
+}
+
+/// 相对于 `dirfd` 解析 `path` 后执行；`AT_EMPTY_PATH` 且 `path` 为空时执行 `dirfd` 本身
+pub fn sys_execveat(
+    dirfd: usize,
+    path: *const u8,
+    argv: *const *const u8,
+    envp: *const *const u8,
+    flags: u32,
+) -> isize {
+    if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+        return -1; // EINVAL
+    }
+    let token = current_user_token();
+    let Ok(path) = translated_str(token, path) else {
+        return -1; // EFAULT
+    };
+    let full_path = match resolve_at(dirfd, &path, flags & AT_EMPTY_PATH != 0) {
+        Ok(full_path) => full_path,
+        Err(err) => return err,
+    };
+    let argv_vec = match read_user_strings(token, argv) {
+        Ok(argv_vec) => argv_vec,
+        Err(err) => return err,
+    };
+    if let Err(err) = read_user_strings(token, envp) {
+        return err;
+    }
+    do_execve(full_path, argv_vec)
 }
 
 /// If there is not a child process whose pid is same as given, return -1.
@@ -251,6 +560,7 @@ pub fn sys_execve(
 bitflags! {
     struct WaitOption: u32 {
         const WNOHANG    = 1;
+        const WUNTRACED  = 2;
         const WSTOPPED   = 2;
         const WEXITED    = 4;
         const WCONTINUED = 8;
@@ -267,6 +577,38 @@ pub fn sys_wait4(pid: isize, status: *mut u32, option: u32, _ru: *mut Rusage) ->
     loop {
         let mut inner = process.inner_exclusive_access();
 
+        // 被跟踪的子进程进入停止时，即使没有 WUNTRACED 也要报告给跟踪者；
+        // 作业控制的停止与继续分别在带 WUNTRACED / WCONTINUED 时报告
+        let changed = inner.children.iter().find_map(|p| {
+            if pid != -1 && pid as usize != p.getpid() {
+                return None;
+            }
+            let mut child_inner = p.inner_exclusive_access();
+            if let Some(signum) = child_inner.ptrace.take_unreported_stop() {
+                return Some((p.getpid(), WaitStatus::Stopped(signum)));
+            }
+            if option.contains(WaitOption::WUNTRACED) {
+                if let Some(signum) = child_inner.jobctl.take_unreported_stop() {
+                    return Some((p.getpid(), WaitStatus::Stopped(signum)));
+                }
+            }
+            if option.contains(WaitOption::WCONTINUED)
+                && child_inner.jobctl.take_unreported_continue()
+            {
+                return Some((p.getpid(), WaitStatus::Continued));
+            }
+            None
+        });
+        if let Some((found_pid, changed)) = changed {
+            // 写入用户缓冲区时可能缺页，不能持有 PCB
+            drop(inner);
+            let wstatus = changed.encode() as u32;
+            if !status.is_null() && copy_to_user(token, &wstatus, status).is_err() {
+                return -1; // EFAULT
+            }
+            return found_pid as isize;
+        }
+
         if !inner
             .children
             .iter()
@@ -289,10 +631,17 @@ pub fn sys_wait4(pid: isize, status: *mut u32, option: u32, _ru: *mut Rusage) ->
                 let found_pid = child.getpid();
                 // ++++ temporarily hold child lock
                 let exit_code = child_inner.exit_code;
-                inner.rusage.ru_cutime =inner.rusage.ru_cutime + child_inner.rusage.ru_utime;
-                inner.rusage.ru_cstime =inner.rusage.ru_cstime + child_inner.rusage.ru_stime;
-                if !status.is_null() {
-                    *translated_refmut(token, status) = exit_code as u32;
+                // 子进程及其已回收的后代的 CPU 时间计入父进程
+                let child_usage = &child_inner.rusage;
+                inner.rusage.ru_cutime =
+                    inner.rusage.ru_cutime + child_usage.ru_utime + child_usage.ru_cutime;
+                inner.rusage.ru_cstime =
+                    inner.rusage.ru_cstime + child_usage.ru_stime + child_usage.ru_cstime;
+                drop(child_inner);
+                drop(inner);
+                let wstatus = exit_code as u32;
+                if !status.is_null() && copy_to_user(token, &wstatus, status).is_err() {
+                    return -1; // EFAULT
                 }
                 return found_pid as isize;
             }
@@ -300,6 +649,8 @@ pub fn sys_wait4(pid: isize, status: *mut u32, option: u32, _ru: *mut Rusage) ->
             drop(inner);
             if option.contains(WaitOption::WNOHANG) {
                 return 0;
+            } else if signal_pending_of_current() {
+                return -1; // EINTR
             } else {
                 suspend_current_and_run_next();
             }
@@ -307,86 +658,232 @@ pub fn sys_wait4(pid: isize, status: *mut u32, option: u32, _ru: *mut Rusage) ->
     }
 }
 
+/// 睡眠 `req` 指定的时间，`rem` 不为空时写入剩余时间
+///
+/// 只有会终止进程的信号（或线程组退出）才以 EINTR 打断睡眠，判断见 `signal_pending_of_current`：
+/// 内核还不支持用户信号处理函数，默认动作为忽略、停止或继续的信号不会让睡眠提前返回
 pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
     if req.is_null() {
         return -1; // EINVAL;
     }
     let task = current_task().unwrap();
     let token = task.get_user_token();
-    let req = get_from_user(token, req);
+    let Ok(req) = get_from_user(token, req) else {
+        return -1; // EFAULT
+    };
     let end = TimeSpec::now() + req;
-    // 精度会缺失一点
-    let expire_ms = end.to_ms();
-    add_timer(expire_ms, task.clone());
+    let timer = Timer::wake_at_us(end.to_ns().div_ceil(NSEC_PER_USEC), &task);
     drop(task);
 
-    block_current_and_run_next();
-    // let task = current_task().unwrap();
-    // let inner = task.inner_exclusive_access();
-    let process = current_process();
-    let process_inner = process.inner_exclusive_access();
-    let now = TimeSpec::now();
-    if process_inner.signals.is_empty() {
-        assert!(end <= now);
-        if !rem.is_null() {
-            copy_to_user(token, &TimeSpec::new(), rem).unwrap();
+    let mut interrupted = false;
+    // 被其他原因唤醒时继续等待
+    while !timer.fired() && !interrupted {
+        interrupted = block_current_interruptible(|| timer.fired());
+    }
+    if !interrupted {
+        if !rem.is_null() && copy_to_user(token, &TimeSpec::new(), rem).is_err() {
+            return -1; // EFAULT
         }
-        0 //SUCCESS
-    } else {
-        if !rem.is_null() {
-            copy_to_user(token, &(end - now), rem).unwrap();
+        return 0; //SUCCESS
+    }
+    // 被信号打断：撤销定时器，报告剩余时间
+    timer.cancel();
+    let now = TimeSpec::now();
+    if !rem.is_null() {
+        let remain = if now < end {
+            end - now
+        } else {
+            TimeSpec::new()
+        };
+        if copy_to_user(token, &remain, rem).is_err() {
+            return -1; // EFAULT
         }
-        -1 // EINTR
     }
-    // ---- release current PCB automatically
+    -1 // EINTR
 }
-// pub fn sys_kill(pid: usize, signal: u32) -> isize {
-//     if let Some(process) = pid2process(pid) {
-//         if let Some(flag) = SignalFlags::from_bits(signal) {
-//             process.inner_exclusive_access().signals |= flag;
-//             0
-//         } else {
-//             -1
-//         }
-//     } else {
-//         -1
-//     }
-// }
-pub fn sys_kill(pid: usize, sig: usize) -> isize {
+
+/// 只支持 ITIMER_REAL
+const ITIMER_REAL: usize = 0;
+
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    if which != ITIMER_REAL {
+        return -1; // EINVAL
+    }
+    let process = current_process();
+    let value = process.inner_exclusive_access().itimer_real();
+    if copy_to_user(current_user_token(), &value, curr_value).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    if which != ITIMER_REAL {
+        return -1; // EINVAL
+    }
+    if new_value.is_null() {
+        return -1; // EFAULT
+    }
+    let token = current_user_token();
+    let Ok(new) = get_from_user(token, new_value) else {
+        return -1; // EFAULT
+    };
+    if new.it_value.tv_usec >= USEC_PER_SEC || new.it_interval.tv_usec >= USEC_PER_SEC {
+        return -1; // EINVAL
+    }
+    let old = current_process().set_itimer_real(new);
+    if !old_value.is_null() && copy_to_user(token, &old, old_value).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+/// 发送者能否向目标发送信号：特权进程可以发给任何进程，
+/// 否则发送者的实际或有效用户 ID 须等于目标的实际用户 ID 或保存的 set-user-ID
+fn may_signal(sender: &Credentials, target: &Credentials) -> bool {
+    sender.is_privileged()
+        || [sender.uid, sender.euid].contains(&target.uid)
+        || [sender.uid, sender.euid].contains(&target.suid)
+}
+
+/// 向进程发送信号（sig 为 0 时只检查目标是否存在以及权限）：
+/// - `pid > 0`：发给进程 `pid`
+/// - `pid == 0`：发给调用者所在进程组中的每个进程
+/// - `pid == -1`：发给除 init（PID 1）与调用者自己之外、有权限发送的每个进程
+/// - `pid < -1`：发给进程组 `-pid` 中的每个进程
+///
+/// 没有匹配的进程时返回 ESRCH；发给一组进程时只要有一个发送成功就返回 0，全部无权限时返回 EPERM
+pub fn sys_kill(pid: isize, sig: usize) -> isize {
     let signal = match SignalFlags::from_signum(sig) {
         Ok(signal) => signal,
-        Err(_) => return -1, //EINVAL,
+        Err(_) => return -1, // EINVAL
     };
-    if pid > 0 {
-        // [Warning] in current implementation,
-        // signal will be sent to an arbitrary task with target `pid` (`tgid` more precisely).
-        // But manual also require that the target task should not mask this signal.
-        if let Some(task) = find_task_by_pid(pid) {
-            if !signal.is_empty() {
-                let mut task_inner = task.inner_exclusive_access();
-                let mut process = task.process.upgrade().unwrap();
-                let mut process_inner = process.inner_exclusive_access();
-                process_inner.add_signal(signal);
-                // wake up target process if it is sleeping
-                if task_inner.task_status == TaskStatus::Blocked {
-                    task_inner.task_status = TaskStatus::Ready;
-                    drop(task_inner);
-                    wake_blocked(task);
-                }
-            }
-            0 // SUCCESS
-        } else {
-            -1 // ESRCH
+    let current = current_process();
+    let (self_pid, self_pgid, cred) = {
+        let inner = current.inner_exclusive_access();
+        (current.getpid(), inner.pgid, inner.cred)
+    };
+    drop(current);
+    let targets: Vec<Arc<ProcessControlBlock>> = match pid {
+        1.. => pid2process(pid as usize).into_iter().collect(),
+        0 => all_processes()
+            .into_iter()
+            .filter(|process| process.inner_exclusive_access().pgid == self_pgid)
+            .collect(),
+        -1 => all_processes()
+            .into_iter()
+            .filter(|process| process.getpid() != 1 && process.getpid() != self_pid)
+            .collect(),
+        _ => all_processes()
+            .into_iter()
+            .filter(|process| process.inner_exclusive_access().pgid == pid.unsigned_abs())
+            .collect(),
+    };
+    if targets.is_empty() {
+        return -1; // ESRCH
+    }
+    let mut sent = false;
+    for process in targets {
+        let mut inner = process.inner_exclusive_access();
+        if !may_signal(&cred, &inner.cred) {
+            continue;
         }
-    } else if pid == 0 {
-        todo!()
-    } else if (pid as isize) == -1 {
-        todo!()
+        if !signal.is_empty() {
+            // 会终止进程的信号同时唤醒可被打断地阻塞的线程
+            inner.add_signal(signal);
+        }
+        sent = true;
+    }
+    if sent {
+        0
     } else {
-        // (pid as isize) < -1
-        todo!()
+        -1 // EPERM
+    }
+}
+
+/// 向进程 `process` 中的线程 `tid` 发送信号，sig 为 0 时只检查线程是否存在以及权限
+fn signal_thread(process: &ProcessControlBlock, tid: usize, sig: usize) -> isize {
+    let signal = match SignalFlags::from_signum(sig) {
+        Ok(signal) => signal,
+        Err(_) => return -1, // EINVAL
+    };
+    let cred = current_process().inner_exclusive_access().cred;
+    let mut inner = process.inner_exclusive_access();
+    if !matches!(inner.tasks.get(tid), Some(Some(_))) {
+        return -1; // ESRCH
+    }
+    if !may_signal(&cred, &inner.cred) {
+        return -1; // EPERM
     }
+    if !signal.is_empty() {
+        inner.add_thread_signal(tid, signal);
+    }
+    0
+}
+
+/// 向调用者所在线程组中的线程 `tid` 发送信号
+///
+/// 线程 ID 与 gettid 的返回值相同，只在线程组内唯一，因此只能指定调用者自己的线程组
+pub fn sys_tkill(tid: isize, sig: usize) -> isize {
+    if tid < 0 {
+        return -1; // EINVAL
+    }
+    sig_thread_of(current_process().getpid(), tid as usize, sig)
 }
+
+/// 向线程组 `tgid` 中的线程 `tid` 发送信号，线程已不在该线程组中时返回 ESRCH
+pub fn sys_tgkill(tgid: isize, tid: isize, sig: usize) -> isize {
+    if tgid <= 0 || tid < 0 {
+        return -1; // EINVAL
+    }
+    sig_thread_of(tgid as usize, tid as usize, sig)
+}
+
+fn sig_thread_of(tgid: usize, tid: usize, sig: usize) -> isize {
+    match pid2process(tgid) {
+        Some(process) => signal_thread(&process, tid, sig),
+        None => -1, // ESRCH
+    }
+}
+/// 设置（`ss` 非空）并查询（`old_ss` 非空）当前线程的信号备用栈
+///
+/// 正在备用栈上运行时不能修改它。`ss_flags` 只接受 0、`SS_ONSTACK`（旧程序的写法，等同 0）
+/// 或 `SS_DISABLE`，可以附加 `SS_AUTODISARM`
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let sp = task_inner.get_trap_cx().sp();
+    let old = task_inner.sigaltstack.report(sp);
+    if !ss.is_null() {
+        let Ok(new) = get_from_user(token, ss) else {
+            return -1; // EFAULT
+        };
+        if task_inner.sigaltstack.on_stack(sp) {
+            return -1; // EPERM
+        }
+        let mode = new.ss_flags & !SS_AUTODISARM;
+        task_inner.sigaltstack = match mode {
+            SS_DISABLE => SignalStack::disabled(),
+            0 | SS_ONSTACK if new.ss_size < MINSIGSTKSZ => return -1, // ENOMEM
+            0 | SS_ONSTACK => SignalStack {
+                ss_sp: new.ss_sp,
+                ss_flags: new.ss_flags & SS_AUTODISARM,
+                ss_size: new.ss_size,
+            },
+            _ => return -1, // EINVAL
+        };
+    }
+    drop(task_inner);
+    if !old_ss.is_null() && copy_to_user(token, &old, old_ss).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
 pub fn sys_getppid() -> isize {
     let task = current_task().unwrap();
     let process = task.process.upgrade().unwrap();
@@ -404,6 +901,83 @@ pub fn sys_getppid() -> isize {
     parent_arc.pid.0 as isize
 }
 
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+
+pub fn sys_getuid() -> isize {
+    current_process().inner_exclusive_access().cred.uid as isize
+}
+
+pub fn sys_geteuid() -> isize {
+    current_process().inner_exclusive_access().cred.euid as isize
+}
+
+pub fn sys_getgid() -> isize {
+    current_process().inner_exclusive_access().cred.gid as isize
+}
+
+pub fn sys_getegid() -> isize {
+    current_process().inner_exclusive_access().cred.egid as isize
+}
+
+/// 特权进程同时设置实际、有效与保存的用户 ID；
+/// 非特权进程只能把有效用户 ID 设为实际或保存的用户 ID
+pub fn sys_setuid(uid: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let cred = &mut inner.cred;
+    if cred.is_privileged() {
+        cred.uid = uid;
+        cred.suid = uid;
+    } else if uid != cred.uid && uid != cred.suid {
+        return -1; // EPERM
+    }
+    cred.euid = uid;
+    0
+}
+
+/// 与 `sys_setuid` 相同的规则作用于组 ID
+pub fn sys_setgid(gid: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let cred = &mut inner.cred;
+    if cred.is_privileged() {
+        cred.gid = gid;
+        cred.sgid = gid;
+    } else if gid != cred.gid && gid != cred.sgid {
+        return -1; // EPERM
+    }
+    cred.egid = gid;
+    0
+}
+
+/// 分别设置实际、有效与保存的用户 ID，值为 -1 的项保持不变；
+/// 非特权进程的每个新值都必须是当前三者之一
+pub fn sys_setresuid(ruid: u32, euid: u32, suid: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let cred = &mut inner.cred;
+    let current = [cred.uid, cred.euid, cred.suid];
+    let requested = [ruid, euid, suid];
+    if !cred.is_privileged()
+        && requested
+            .iter()
+            .any(|&id| id != u32::MAX && !current.contains(&id))
+    {
+        return -1; // EPERM
+    }
+    if ruid != u32::MAX {
+        cred.uid = ruid;
+    }
+    if euid != u32::MAX {
+        cred.euid = euid;
+    }
+    if suid != u32::MAX {
+        cred.suid = suid;
+    }
+    0
+}
+
 pub fn sys_times(tms_ptr: *mut Tms) -> isize {
     // let current_process = current_process();
     // let mut inner = current_process.inner_exclusive_access();
@@ -414,8 +988,7 @@ pub fn sys_times(tms_ptr: *mut Tms) -> isize {
     let task = current_task().unwrap();
     let user_token = task.get_user_token();
     let process = task.process.upgrade().unwrap();
-    let mut inner = process.inner_exclusive_access();
-    let tms = translated_refmut(user_token, tms_ptr);
+    let inner = process.inner_exclusive_access();
 
     let times = Tms {
         utime: inner.rusage.ru_utime.to_tick(),
@@ -423,18 +996,21 @@ pub fn sys_times(tms_ptr: *mut Tms) -> isize {
         cutime: inner.rusage.ru_cutime.to_tick(),
         cstime: inner.rusage.ru_cstime.to_tick(),
     };
-    copy_to_user(user_token, &times, tms_ptr);
+    // 写入用户缓冲区时可能缺页，不能持有 PCB
+    drop(inner);
+    if copy_to_user(user_token, &times, tms_ptr).is_err() {
+        return -1; // EFAULT
+    }
     crate::hal::get_time() as isize
 }
 
 // TODO：根据实际修改,新增loongarch64之后需要分隔开
 pub fn sys_uname(utsname_ptr: *mut u8) -> isize {
     let token = current_user_token();
-    let mut buffer = UserBuffer::new(translated_byte_buffer(
-        token,
-        utsname_ptr,
-        size_of::<UTSName>(),
-    ));
+    let mut buffer = match translated_byte_buffer(token, utsname_ptr, size_of::<UTSName>(), true) {
+        Ok(buffers) => UserBuffer::new(buffers),
+        Err(e) => return e,
+    };
     const FIELD_OFFSET: usize = 65;
     buffer.write_buffer(Some(FIELD_OFFSET * 0), b"cutecore\0");
     buffer.write_buffer(Some(FIELD_OFFSET * 1), b"xeinnious\0");
@@ -457,7 +1033,7 @@ pub fn sys_uname(utsname_ptr: *mut u8) -> isize {
 pub fn sys_gettimeofday(tv: *mut TimeVal, _tz: *mut TimeZone) -> isize {
     let token = current_user_token();
     if !tv.is_null() {
-        let time_val = &TimeVal::now();
+        let time_val = &TimeVal::realtime();
         if copy_to_user(token, time_val, tv).is_err() {
             log::error!("[sys_gettimeofday] Failed to copy to {:?}", tv);
             return -1; // EFAULT;
@@ -466,6 +1042,141 @@ pub fn sys_gettimeofday(tv: *mut TimeVal, _tz: *mut TimeZone) -> isize {
     0 // SUCCESS
 }
 
+/// 只有 root 可以调整墙上时间（对应 CAP_SYS_TIME）
+fn may_set_time() -> bool {
+    current_process().inner_exclusive_access().cred.euid == 0
+}
+
+/// 时区被忽略
+pub fn sys_settimeofday(tv: *const TimeVal, _tz: *const TimeZone) -> isize {
+    if tv.is_null() {
+        return 0;
+    }
+    let Ok(tv) = get_from_user(current_user_token(), tv) else {
+        return -1; // EFAULT
+    };
+    if tv.tv_usec >= USEC_PER_SEC {
+        return -1; // EINVAL
+    }
+    if !may_set_time() {
+        return -1; // EPERM
+    }
+    set_realtime(TimeSpec::from_ns(tv.to_us() * NSEC_PER_USEC));
+    0
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    let Some(now) = clock_now(clock_id) else {
+        return -1; // EINVAL
+    };
+    if copy_to_user(current_user_token(), &now, tp).is_err() {
+        return -1; // EFAULT
+    }
+    0
+}
+
+/// 只有 CLOCK_REALTIME 可以设置，单调时钟不受影响
+pub fn sys_clock_settime(clock_id: usize, tp: *const TimeSpec) -> isize {
+    if clock_id != CLOCK_REALTIME {
+        return -1; // EINVAL
+    }
+    if tp.is_null() {
+        return -1; // EFAULT
+    }
+    let Ok(tp) = get_from_user(current_user_token(), tp) else {
+        return -1; // EFAULT
+    };
+    if tp.tv_nsec >= NSEC_PER_SEC {
+        return -1; // EINVAL
+    }
+    if !may_set_time() {
+        return -1; // EPERM
+    }
+    set_realtime(tp);
+    0
+}
+
+/// getrandom 的 flags
+pub const GRND_NONBLOCK: u32 = 0x1;
+pub const GRND_RANDOM: u32 = 0x2;
+pub const GRND_INSECURE: u32 = 0x4;
+
+/// 单次 getrandom 最多返回的字节数，与 Linux 一致
+const GETRANDOM_MAX: usize = (1 << 25) - 1;
+
+/// 用内核 CSPRNG 填充用户缓冲区，返回写入的字节数
+///
+/// 发生器在启动阶段即已完成播种，因此正常情况下不会阻塞；
+/// 若尚未播种，带 `GRND_NONBLOCK` 的调用返回 EAGAIN，否则让出 CPU 直到播种完成
+pub fn sys_getrandom(buf: *mut u8, buflen: usize, flags: u32) -> isize {
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0 {
+        return -1; // EINVAL
+    }
+    if flags & GRND_RANDOM != 0 && flags & GRND_INSECURE != 0 {
+        return -1; // EINVAL
+    }
+    while flags & GRND_INSECURE == 0 && !KERNEL_RNG.exclusive_access().is_seeded() {
+        if flags & GRND_NONBLOCK != 0 {
+            return -1; // EAGAIN
+        }
+        suspend_current_and_run_next();
+    }
+    let len = buflen.min(GETRANDOM_MAX);
+    let token = current_user_token();
+    let mut buffers = match translated_byte_buffer(token, buf, len, true) {
+        Ok(buffers) => buffers,
+        Err(e) => return e,
+    };
+    let mut rng = KERNEL_RNG.exclusive_access();
+    for b in buffers.iter_mut() {
+        rng.fill(b);
+    }
+    len as isize
+}
+
+/// `sysinfo` 返回的系统统计信息，内存大小以 `mem_unit` 字节为单位
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SysInfo {
+    uptime: i64,
+    loads: [u64; 3],
+    totalram: u64,
+    freeram: u64,
+    sharedram: u64,
+    bufferram: u64,
+    totalswap: u64,
+    freeswap: u64,
+    procs: u16,
+    totalhigh: u64,
+    freehigh: u64,
+    mem_unit: u32,
+}
+
+/// `SysInfo::loads` 的定点小数位数
+const SI_LOAD_SHIFT: usize = 16;
+
+/// 填写系统统计信息：开机时间、负载平均值、内存与交换区大小、进程数
+pub fn sys_sysinfo(info: *mut SysInfo) -> isize {
+    let stats = crate::stats::collect();
+    let sysinfo = SysInfo {
+        uptime: (stats.uptime_ms / 1000) as i64,
+        loads: stats
+            .loads
+            .map(|load| (load << (SI_LOAD_SHIFT - crate::task::FSHIFT)) as u64),
+        totalram: stats.total_ram() as u64,
+        freeram: stats.free_ram() as u64,
+        totalswap: stats.total_swap() as u64,
+        freeswap: stats.free_swap() as u64,
+        procs: stats.procs.min(u16::MAX as usize) as u16,
+        mem_unit: 1,
+        ..Default::default()
+    };
+    match copy_to_user(current_user_token(), &sysinfo, info) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
 // new add:sys_uname()需要将NTSName结构体写到UseBuffer中
 #[allow(unused)]
 #[repr(C)]
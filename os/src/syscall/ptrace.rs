@@ -0,0 +1,99 @@
+//! # ptrace 系统调用
+//!
+//! ## Overview
+//! 实现 `ptrace` 的基本请求，被跟踪进程一侧的停止与恢复见 `task::ptrace`：
+//! - `PTRACE_TRACEME`：当前进程请求被父进程跟踪
+//! - `PTRACE_PEEKTEXT` / `PTRACE_PEEKDATA`：读取被跟踪进程的一个字，写入跟踪者的 `*data`
+//! - `PTRACE_POKETEXT` / `PTRACE_POKEDATA`：向被跟踪进程写入一个字
+//! - `PTRACE_CONT` / `PTRACE_SINGLESTEP`：恢复执行 / 单步，`data` 为恢复时递送的信号
+//! - `PTRACE_KILL` / `PTRACE_DETACH`：结束被跟踪进程 / 解除跟踪
+//!
+//! ## Behavior
+//! - 除 `PTRACE_TRACEME` 外，`pid` 必须是当前进程被跟踪的子进程；
+//!   除 `PTRACE_KILL` 外还要求它处于 ptrace 停止状态，否则返回 `-1`（ESRCH）
+//! - 访问被跟踪进程内存经过它的页表翻译，地址未映射时返回 `-1`（EIO）
+
+use crate::mm::{try_read_bytes, try_write_bytes};
+use crate::task::{current_process, current_user_token, SignalFlags};
+use core::mem::size_of;
+
+pub const PTRACE_TRACEME: usize = 0;
+pub const PTRACE_PEEKTEXT: usize = 1;
+pub const PTRACE_PEEKDATA: usize = 2;
+pub const PTRACE_POKETEXT: usize = 4;
+pub const PTRACE_POKEDATA: usize = 5;
+pub const PTRACE_CONT: usize = 7;
+pub const PTRACE_KILL: usize = 8;
+pub const PTRACE_SINGLESTEP: usize = 9;
+pub const PTRACE_DETACH: usize = 17;
+
+pub fn sys_ptrace(request: usize, pid: usize, addr: usize, data: usize) -> isize {
+    let process = current_process();
+    if request == PTRACE_TRACEME {
+        let mut inner = process.inner_exclusive_access();
+        if inner.ptrace.traced || inner.parent.is_none() {
+            return -1; // EPERM
+        }
+        inner.ptrace.traced = true;
+        return 0;
+    }
+    let child = process
+        .inner_exclusive_access()
+        .children
+        .iter()
+        .find(|p| p.getpid() == pid)
+        .cloned();
+    let child = match child {
+        Some(child) => child,
+        None => return -1, // ESRCH
+    };
+    let mut child_inner = child.inner_exclusive_access();
+    if !child_inner.ptrace.traced {
+        return -1; // ESRCH
+    }
+    if request != PTRACE_KILL && !child_inner.ptrace.is_stopped() {
+        return -1; // ESRCH
+    }
+    let token = child_inner.memory_set.token();
+    match request {
+        PTRACE_PEEKTEXT | PTRACE_PEEKDATA => {
+            let mut word = [0u8; size_of::<usize>()];
+            if try_read_bytes(token, addr, &mut word).is_none() {
+                return -1; // EIO
+            }
+            drop(child_inner);
+            match try_write_bytes(current_user_token(), data, &word) {
+                Some(()) => 0,
+                None => -1, // EFAULT
+            }
+        }
+        PTRACE_POKETEXT | PTRACE_POKEDATA => {
+            match try_write_bytes(token, addr, &data.to_ne_bytes()) {
+                Some(()) => 0,
+                None => -1, // EIO
+            }
+        }
+        PTRACE_CONT | PTRACE_SINGLESTEP | PTRACE_DETACH => {
+            let signal = match SignalFlags::from_signum(data) {
+                Ok(signal) => Some(signal),
+                Err(_) => return -1, // EIO
+            };
+            match request {
+                PTRACE_CONT => child_inner.ptrace.resume(signal),
+                PTRACE_SINGLESTEP => {
+                    let task = child_inner.tasks[0].clone().unwrap();
+                    let cx = task.inner_exclusive_access().get_trap_cx();
+                    child_inner.ptrace.single_step(token, cx, signal);
+                }
+                _ => child_inner.ptrace.detach(token, signal),
+            }
+            0
+        }
+        PTRACE_KILL => {
+            child_inner.add_signal(SignalFlags::SIGKILL);
+            child_inner.ptrace.resume(None);
+            0
+        }
+        _ => -1, // EIO
+    }
+}
@@ -0,0 +1,126 @@
+//! # System V 共享内存系统调用
+//!
+//! ## Overview
+//! 实现 `shmget` / `shmat` / `shmdt` / `shmctl`，
+//! 段表由 `mm::shm::SHM_MANAGER` 统一管理，
+//! 挂接时以共享 `MapArea` 映射进当前进程的地址空间。
+//!
+//! ## Behavior
+//! - 所有系统调用失败时返回 `-1`
+//! - fork 出的子进程继承父进程的全部挂接
+
+use crate::hal::PAGE_SIZE;
+use crate::mm::shm::{
+    ShmIdDs, IPC_CREAT, IPC_EXCL, IPC_PRIVATE, IPC_RMID, IPC_SET, IPC_STAT, SHM_MANAGER,
+    SHM_RDONLY, SHM_RND,
+};
+use crate::mm::{copy_to_user, get_from_user, MapPermission};
+use crate::task::{current_process, current_user_token};
+use crate::timer::get_time_ms;
+
+/// 获取（或创建）key 对应的共享内存段，返回 shmid
+pub fn sys_shmget(key: usize, size: usize, shmflg: u32) -> isize {
+    let pid = current_process().getpid();
+    let mut manager = SHM_MANAGER.exclusive_access();
+    if let Some(id) = manager.find_by_key(key) {
+        if shmflg & IPC_CREAT != 0 && shmflg & IPC_EXCL != 0 {
+            return -1; // EEXIST
+        }
+        if size > manager.get(id).unwrap().ds.shm_segsz {
+            return -1; // EINVAL
+        }
+        return id as isize;
+    }
+    if key != IPC_PRIVATE && shmflg & IPC_CREAT == 0 {
+        return -1; // ENOENT
+    }
+    if size == 0 {
+        return -1; // EINVAL
+    }
+    match manager.create(key, size, shmflg, pid) {
+        Some(id) => id as isize,
+        None => -1, // ENOMEM
+    }
+}
+
+/// 将共享内存段挂接到当前进程，返回挂接地址
+pub fn sys_shmat(shmid: usize, shmaddr: usize, shmflg: u32) -> isize {
+    let frames = match SHM_MANAGER.exclusive_access().get(shmid) {
+        Some(seg) => seg.frames.clone(),
+        None => return -1, // EINVAL
+    };
+    let addr = if shmflg & SHM_RND != 0 {
+        shmaddr & !(PAGE_SIZE - 1)
+    } else {
+        shmaddr
+    };
+    let mut perm = MapPermission::R | MapPermission::U;
+    if shmflg & SHM_RDONLY == 0 {
+        perm |= MapPermission::W;
+    }
+
+    let process = current_process();
+    let pid = process.getpid();
+    let mut inner = process.inner_exclusive_access();
+    let start = match inner.memory_set.attach_shared(addr, &frames, perm) {
+        Ok(start) => start,
+        Err(e) => return e,
+    };
+    drop(inner);
+
+    if let Some(seg) = SHM_MANAGER.exclusive_access().get_mut(shmid) {
+        seg.ds.shm_atime = (get_time_ms() / 1000) as isize;
+        seg.ds.shm_lpid = pid as i32;
+    }
+    start as isize
+}
+
+/// 解除 `shmaddr` 处的共享内存挂接
+pub fn sys_shmdt(shmaddr: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.memory_set.detach_shared(shmaddr) {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+/// 共享内存段控制操作
+pub fn sys_shmctl(shmid: usize, cmd: usize, buf: *mut ShmIdDs) -> isize {
+    let token = current_user_token();
+    let mut manager = SHM_MANAGER.exclusive_access();
+    match cmd {
+        IPC_RMID => match manager.remove(shmid) {
+            Some(_) => 0,
+            None => -1,
+        },
+        IPC_STAT => {
+            let seg = match manager.get(shmid) {
+                Some(seg) => seg,
+                None => return -1,
+            };
+            let mut ds = seg.ds;
+            ds.shm_nattch = seg.nattch();
+            drop(manager);
+            if copy_to_user(token, &ds, buf).is_err() {
+                return -1; // EFAULT
+            }
+            0
+        }
+        IPC_SET => {
+            let seg = match manager.get_mut(shmid) {
+                Some(seg) => seg,
+                None => return -1,
+            };
+            let Ok(ds) = get_from_user(token, buf as *const ShmIdDs) else {
+                return -1; // EFAULT
+            };
+            seg.ds.shm_perm.uid = ds.shm_perm.uid;
+            seg.ds.shm_perm.gid = ds.shm_perm.gid;
+            seg.ds.shm_perm.mode = ds.shm_perm.mode & 0o777;
+            seg.ds.shm_ctime = (get_time_ms() / 1000) as isize;
+            0
+        }
+        _ => -1, // EINVAL
+    }
+}
@@ -6,6 +6,7 @@
 //! - 互斥锁（Mutex）的创建、加锁与解锁
 //! - 信号量（Semaphore）的创建、P/V 操作
 //! - 条件变量（Condvar）的创建、等待与唤醒
+//! - 进程级内存屏障（membarrier）
 //!
 //! 这些系统调用以 **进程私有资源表** 的形式管理同步原语，
 //! 每个进程维护独立的 mutex / semaphore / condvar 列表。
@@ -33,12 +34,17 @@
 //! - 所有系统调用成功时返回 `0` 或合法资源 ID
 //! - 阻塞类系统调用会触发任务切换
 //! - 不负责对象的显式销毁（依赖进程退出时统一回收）
+//! - 启用 `deadlock_detect` 特性时，互斥锁与信号量的分配情况记录在进程的 `ResourceTracker` 中；
+//!   进程通过 `enable_deadlock_detect` 开启检查后，会导致死锁的加锁 / P 操作返回 `-35`（EDEADLK）
 
 #![allow(unused)]
 
+use crate::hal::memory_barrier;
+#[cfg(feature = "deadlock_detect")]
+use crate::sync::Resource;
 use crate::sync::{Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore};
-use crate::task::{block_current_and_run_next, current_process, current_task};
-use crate::timer::{add_timer, get_time_ms};
+use crate::task::{block_current_interruptible, current_process, current_task};
+use crate::timer::{get_time_ms, Timer};
 use alloc::sync::Arc;
 
 /// 使当前任务休眠指定的毫秒数
@@ -52,10 +58,15 @@ use alloc::sync::Arc;
 /// - 当前任务必须存在
 /// - 定时器系统必须正确维护唤醒逻辑
 pub fn sys_sleep(ms: usize) -> isize {
-    let expire_ms = get_time_ms() + ms;
     let task = current_task().unwrap();
-    add_timer(expire_ms, task);
-    block_current_and_run_next();
+    let timer = Timer::wake_at(get_time_ms() + ms, &task);
+    drop(task);
+    while !timer.fired() {
+        if block_current_interruptible(|| timer.fired()) {
+            timer.cancel();
+            return -1; // EINTR
+        }
+    }
     0
 }
 
@@ -79,7 +90,7 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
         Some(Arc::new(MutexBlocking::new()))
     };
     let mut process_inner = process.inner_exclusive_access();
-    if let Some(id) = process_inner
+    let id = if let Some(id) = process_inner
         .mutex_list
         .iter()
         .enumerate()
@@ -87,11 +98,14 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
         .map(|(id, _)| id)
     {
         process_inner.mutex_list[id] = mutex;
-        id as isize
+        id
     } else {
         process_inner.mutex_list.push(mutex);
-        process_inner.mutex_list.len() as isize - 1
-    }
+        process_inner.mutex_list.len() - 1
+    };
+    #[cfg(feature = "deadlock_detect")]
+    process_inner.deadlock.add(Resource::Mutex(id), 1);
+    id as isize
 }
 
 /// 对指定互斥锁加锁
@@ -103,11 +117,24 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
 /// - 在调用 `lock()` 前释放进程内部锁，避免死锁
 pub fn sys_mutex_lock(mutex_id: usize) -> isize {
     let process = current_process();
-    let process_inner = process.inner_exclusive_access();
+    let mut process_inner = process.inner_exclusive_access();
     let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    #[cfg(feature = "deadlock_detect")]
+    let tid = current_tid();
+    #[cfg(feature = "deadlock_detect")]
+    if !process_inner
+        .deadlock
+        .request(tid, Resource::Mutex(mutex_id))
+    {
+        return -35; // EDEADLK
+    }
     drop(process_inner);
-    drop(process);
     mutex.lock();
+    #[cfg(feature = "deadlock_detect")]
+    process
+        .inner_exclusive_access()
+        .deadlock
+        .acquired(tid, Resource::Mutex(mutex_id));
     0
 }
 
@@ -117,8 +144,12 @@ pub fn sys_mutex_lock(mutex_id: usize) -> isize {
 /// - 调用者应当是该互斥锁的持有者
 pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
     let process = current_process();
-    let process_inner = process.inner_exclusive_access();
+    let mut process_inner = process.inner_exclusive_access();
     let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    #[cfg(feature = "deadlock_detect")]
+    process_inner
+        .deadlock
+        .released(current_tid(), Resource::Mutex(mutex_id));
     drop(process_inner);
     drop(process);
     mutex.unlock();
@@ -150,6 +181,10 @@ pub fn sys_semaphore_create(res_count: usize) -> isize {
             .push(Some(Arc::new(Semaphore::new(res_count))));
         process_inner.semaphore_list.len() - 1
     };
+    #[cfg(feature = "deadlock_detect")]
+    process_inner
+        .deadlock
+        .add(Resource::Semaphore(id), res_count);
     id as isize
 }
 
@@ -160,8 +195,12 @@ pub fn sys_semaphore_create(res_count: usize) -> isize {
 /// - 若存在等待任务，可能唤醒其中一个
 pub fn sys_semaphore_up(sem_id: usize) -> isize {
     let process = current_process();
-    let process_inner = process.inner_exclusive_access();
+    let mut process_inner = process.inner_exclusive_access();
     let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
+    #[cfg(feature = "deadlock_detect")]
+    process_inner
+        .deadlock
+        .released(current_tid(), Resource::Semaphore(sem_id));
     drop(process_inner);
     sem.up();
     0
@@ -173,10 +212,24 @@ pub fn sys_semaphore_up(sem_id: usize) -> isize {
 /// - 若资源不足，当前任务将被阻塞
 pub fn sys_semaphore_down(sem_id: usize) -> isize {
     let process = current_process();
-    let process_inner = process.inner_exclusive_access();
+    let mut process_inner = process.inner_exclusive_access();
     let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
+    #[cfg(feature = "deadlock_detect")]
+    let tid = current_tid();
+    #[cfg(feature = "deadlock_detect")]
+    if !process_inner
+        .deadlock
+        .request(tid, Resource::Semaphore(sem_id))
+    {
+        return -35; // EDEADLK
+    }
     drop(process_inner);
     sem.down();
+    #[cfg(feature = "deadlock_detect")]
+    process
+        .inner_exclusive_access()
+        .deadlock
+        .acquired(tid, Resource::Semaphore(sem_id));
     0
 }
 
@@ -228,10 +281,81 @@ pub fn sys_condvar_signal(condvar_id: usize) -> isize {
 /// - 必须保证 mutex 与 condvar 属于同一进程
 pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
     let process = current_process();
-    let process_inner = process.inner_exclusive_access();
+    let mut process_inner = process.inner_exclusive_access();
     let condvar = Arc::clone(process_inner.condvar_list[condvar_id].as_ref().unwrap());
     let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    // 等待期间互斥锁被释放，醒来后重新持有
+    #[cfg(feature = "deadlock_detect")]
+    let tid = current_tid();
+    #[cfg(feature = "deadlock_detect")]
+    process_inner
+        .deadlock
+        .released(tid, Resource::Mutex(mutex_id));
     drop(process_inner);
     condvar.wait_with_mutex(mutex);
+    #[cfg(feature = "deadlock_detect")]
+    process
+        .inner_exclusive_access()
+        .deadlock
+        .acquired(tid, Resource::Mutex(mutex_id));
+    0
+}
+
+/// 开启或关闭当前进程的死锁检测
+///
+/// ## Parameters
+/// - `enabled`：`1` 开启，`0` 关闭，其他值返回 `-1`（EINVAL）
+#[cfg(feature = "deadlock_detect")]
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.deadlock.enabled = match enabled {
+        0 => false,
+        1 => true,
+        _ => return -1, // EINVAL
+    };
     0
 }
+
+/// 当前线程的 tid
+#[cfg(feature = "deadlock_detect")]
+fn current_tid() -> usize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .tid
+}
+
+/// membarrier 命令：查询支持的命令
+pub const MEMBARRIER_CMD_QUERY: usize = 0;
+/// 所有进程的所有线程都经过一次内存屏障
+pub const MEMBARRIER_CMD_GLOBAL: usize = 1 << 0;
+/// 同一进程的所有线程都经过一次内存屏障，须先注册
+pub const MEMBARRIER_CMD_PRIVATE_EXPEDITED: usize = 1 << 3;
+pub const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: usize = 1 << 4;
+
+/// 在其他线程上执行内存屏障，供用户态的非对称屏障（如 RCU、GC 安全点）使用
+///
+/// 内核还是单处理器的：其他线程都没有在运行，切换到它们时 `__switch` 已经执行过屏障，
+/// 因此只需在当前 hart 上执行一次屏障。多处理器下需改为向运行该进程线程的 hart 发送 IPI
+pub fn sys_membarrier(cmd: usize, flags: u32, _cpu_id: i32) -> isize {
+    if flags != 0 {
+        return -1; // EINVAL
+    }
+    match cmd {
+        MEMBARRIER_CMD_QUERY => {
+            (MEMBARRIER_CMD_GLOBAL
+                | MEMBARRIER_CMD_PRIVATE_EXPEDITED
+                | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED) as isize
+        }
+        MEMBARRIER_CMD_GLOBAL | MEMBARRIER_CMD_PRIVATE_EXPEDITED => {
+            memory_barrier();
+            0
+        }
+        MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED => 0,
+        _ => -1, // EINVAL
+    }
+}
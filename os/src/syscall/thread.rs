@@ -7,6 +7,7 @@ use crate::{
     task::{add_task, current_task, TaskControlBlock},
 };
 use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
 
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
     let task = current_task().unwrap();
@@ -17,6 +18,10 @@ pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
         UserStackBase,
         true,
     ));
+    // 新线程继承创建者的 CPU 亲和性与名字
+    let affinity = task.cpu_affinity.load(Ordering::Relaxed);
+    new_task.cpu_affinity.store(affinity, Ordering::Relaxed);
+    new_task.inner_exclusive_access().name = task.inner_exclusive_access().name;
     // add new task to scheduler
     add_task(Arc::clone(&new_task));
     let new_task_inner = new_task.inner_exclusive_access();
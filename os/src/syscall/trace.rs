@@ -0,0 +1,394 @@
+//! # 系统调用跟踪（strace）
+//!
+//! ## Overview
+//! 为选定的进程记录每一次系统调用的名字、解码后的参数与返回值，格式与 strace 相近：
+//! `[pid tid name] openat(AT_FDCWD, "/bin/ls", 0x0, 0o0) = 3`，`name` 为线程的任务名
+//!
+//! 通过内核私有的 `strace` 控制系统调用在运行时开关：
+//! - `STRACE_ON` / `STRACE_OFF`：开始 / 停止跟踪某个进程（`pid` 为 0 表示所有进程），
+//!   被跟踪进程此后创建的子进程同样被跟踪
+//! - `STRACE_READ`：取走环形缓冲区中已记录的内容
+//!
+//! ## Design
+//! - 记录写入固定大小的环形缓冲区而不是直接打印，跟踪频繁调用的进程时不会拖慢串口控制台；
+//!   缓冲区满时丢弃最旧的整行
+//! - 进入系统调用时格式化参数（此时用户指针仍然有效），返回时补上返回值后写入缓冲区；
+//!   不会返回的调用（`exit`）在进入时就以 `= ?` 结尾写入
+//! - `TRACING` 原子标志在没有任何跟踪目标时让 `enter` 直接返回，不影响系统调用的正常路径
+//!
+//! ## Assumptions
+//! - 路径参数按 C 字符串读取，经页表检查后最多读取 `MAX_STR_LEN` 字节，
+//!   无法访问的指针显示为十六进制地址
+//!
+//! ## Invariants
+//! - 缓冲区中的内容总是由完整的行组成，长度不超过 `TRACE_BUF_SIZE`
+
+use crate::mm::{translated_byte_buffer, try_read_bytes};
+use crate::sync::UPIntrFreeCell;
+use crate::task::{current_process, current_task, current_user_token};
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+
+use super::*;
+
+/// 环形缓冲区大小
+const TRACE_BUF_SIZE: usize = 64 * 1024;
+/// 字符串参数最多显示的字节数
+const MAX_STR_LEN: usize = 64;
+const AT_FDCWD: isize = -100;
+
+/// `strace` 控制系统调用的操作
+pub const STRACE_OFF: usize = 0;
+pub const STRACE_ON: usize = 1;
+pub const STRACE_READ: usize = 2;
+
+/// 参数的显示方式
+#[derive(Clone, Copy)]
+enum Arg {
+    /// 有符号十进制
+    Int,
+    /// 十六进制（指针、标志位）
+    Hex,
+    /// 八进制（权限位）
+    Oct,
+    /// 文件描述符，`AT_FDCWD` 显示为名字
+    Fd,
+    /// 用户态 C 字符串
+    Str,
+}
+
+use Arg::*;
+
+/// 系统调用名、参数的显示方式，以及返回值是否按地址显示
+fn describe(syscall_id: usize) -> Option<(&'static str, &'static [Arg], bool)> {
+    let desc: (&'static str, &'static [Arg]) = match syscall_id {
+        SYSCALL_GETCWD => ("getcwd", &[Hex, Int]),
+        SYSCALL_DUP => ("dup", &[Fd]),
+        SYSCALL_DUP3 => ("dup3", &[Fd, Fd, Hex]),
+        SYSCALL_FCNTL => ("fcntl", &[Fd, Int, Hex]),
+        SYSCALL_IOCTL => ("ioctl", &[Fd, Hex, Hex]),
+        SYSCALL_FLOCK => ("flock", &[Fd, Int]),
+        SYSCALL_MKNODAT => ("mknodat", &[Fd, Str, Oct, Hex]),
+        SYSCALL_MKDIRAT => ("mkdirat", &[Fd, Str, Oct]),
+        SYSCALL_UNLINKAT => ("unlinkat", &[Fd, Str, Hex]),
+        SYSCALL_SYMLINKAT => ("symlinkat", &[Str, Fd, Str]),
+        SYSCALL_LINKAT => ("linkat", &[Fd, Str, Fd, Str, Hex]),
+        SYSCALL_UMOUNT2 => ("umount2", &[Str, Hex]),
+        SYSCALL_MOUNT => ("mount", &[Str, Str, Str, Hex, Hex]),
+        SYSCALL_TRUNCATE => ("truncate", &[Str, Int]),
+        SYSCALL_FTRUNCATE => ("ftruncate", &[Fd, Int]),
+        SYSCALL_FADVISE64 => ("fadvise64", &[Fd, Int, Int, Int]),
+        SYSCALL_FACCESSAT => ("faccessat", &[Fd, Str, Oct]),
+        SYSCALL_CHDIR => ("chdir", &[Str]),
+        SYSCALL_FCHDIR => ("fchdir", &[Fd]),
+        SYSCALL_FCHMODAT => ("fchmodat", &[Fd, Str, Oct, Hex]),
+        SYSCALL_FCHOWNAT => ("fchownat", &[Fd, Str, Int, Int, Hex]),
+        SYSCALL_OPENAT => ("openat", &[Fd, Str, Hex, Oct]),
+        SYSCALL_CLOSE => ("close", &[Fd]),
+        SYSCALL_PIPE2 => ("pipe2", &[Hex, Hex]),
+        SYSCALL_EVENTFD2 => ("eventfd2", &[Int, Hex]),
+        SYSCALL_TIMERFD_CREATE => ("timerfd_create", &[Int, Hex]),
+        SYSCALL_TIMERFD_SETTIME => ("timerfd_settime", &[Fd, Hex, Hex, Hex]),
+        SYSCALL_TIMERFD_GETTIME => ("timerfd_gettime", &[Fd, Hex]),
+        SYSCALL_EPOLL_CREATE1 => ("epoll_create1", &[Hex]),
+        SYSCALL_EPOLL_CTL => ("epoll_ctl", &[Fd, Int, Fd, Hex]),
+        SYSCALL_EPOLL_PWAIT => ("epoll_pwait", &[Fd, Hex, Int, Int, Hex, Int]),
+        SYSCALL_SPLICE => ("splice", &[Fd, Hex, Fd, Hex, Int, Hex]),
+        SYSCALL_TEE => ("tee", &[Fd, Fd, Int, Hex]),
+        SYSCALL_GETDENTS64 => ("getdents64", &[Fd, Hex, Int]),
+        SYSCALL_LSEEK => ("lseek", &[Fd, Int, Int]),
+        SYSCALL_READ => ("read", &[Fd, Hex, Int]),
+        SYSCALL_WRITE => ("write", &[Fd, Hex, Int]),
+        SYSCALL_READV => ("readv", &[Fd, Hex, Int]),
+        SYSCALL_WRITEV => ("writev", &[Fd, Hex, Int]),
+        SYSCALL_PSELECT6 => ("pselect6", &[Int, Hex, Hex, Hex, Hex, Hex]),
+        SYSCALL_PPOLL => ("ppoll", &[Hex, Int, Hex, Hex, Int]),
+        SYSCALL_READLINKAT => ("readlinkat", &[Fd, Str, Hex, Int]),
+        SYSCALL_FSTATAT => ("newfstatat", &[Fd, Str, Hex, Hex]),
+        SYSCALL_FSTAT => ("fstat", &[Fd, Hex]),
+        SYSCALL_SYNC => ("sync", &[]),
+        SYSCALL_FSYNC => ("fsync", &[Fd]),
+        SYSCALL_FDATASYNC => ("fdatasync", &[Fd]),
+        SYSCALL_EXIT => ("exit", &[Int]),
+        SYSCALL_EXIT_GROUP => ("exit_group", &[Int]),
+        SYSCALL_NANOSLEEP => ("nanosleep", &[Hex, Hex]),
+        SYSCALL_PTRACE => ("ptrace", &[Int, Int, Hex, Hex]),
+        SYSCALL_YIELD => ("sched_yield", &[]),
+        SYSCALL_KILL => ("kill", &[Int, Int]),
+        SYSCALL_TKILL => ("tkill", &[Int, Int]),
+        SYSCALL_TGKILL => ("tgkill", &[Int, Int, Int]),
+        SYSCALL_SETGID => ("setgid", &[Int]),
+        SYSCALL_SETUID => ("setuid", &[Int]),
+        SYSCALL_SETRESUID => ("setresuid", &[Int, Int, Int]),
+        SYSCALL_TIMES => ("times", &[Hex]),
+        SYSCALL_UNAME => ("uname", &[Hex]),
+        SYSCALL_UMASK => ("umask", &[Oct]),
+        SYSCALL_PRCTL => ("prctl", &[Int, Hex]),
+        SYSCALL_GET_TIME_OF_DAY => ("gettimeofday", &[Hex, Hex]),
+        SYSCALL_SETTIMEOFDAY => ("settimeofday", &[Hex, Hex]),
+        SYSCALL_CLOCK_GETTIME => ("clock_gettime", &[Int, Hex]),
+        SYSCALL_CLOCK_SETTIME => ("clock_settime", &[Int, Hex]),
+        SYSCALL_GETPID => ("getpid", &[]),
+        SYSCALL_GETPPID => ("getppid", &[]),
+        SYSCALL_GETUID => ("getuid", &[]),
+        SYSCALL_GETEUID => ("geteuid", &[]),
+        SYSCALL_GETGID => ("getgid", &[]),
+        SYSCALL_GETEGID => ("getegid", &[]),
+        SYSCALL_SYSINFO => ("sysinfo", &[Hex]),
+        SYSCALL_SHMGET => ("shmget", &[Hex, Int, Oct]),
+        SYSCALL_SHMCTL => ("shmctl", &[Int, Int, Hex]),
+        SYSCALL_SHMAT => return Some(("shmat", &[Int, Hex, Hex], true)),
+        SYSCALL_SHMDT => ("shmdt", &[Hex]),
+        SYSCALL_SOCKET => ("socket", &[Int, Int, Int]),
+        SYSCALL_SOCKETPAIR => ("socketpair", &[Int, Int, Int, Hex]),
+        SYSCALL_BIND => ("bind", &[Fd, Hex, Int]),
+        SYSCALL_LISTEN => ("listen", &[Fd, Int]),
+        SYSCALL_ACCEPT => ("accept", &[Fd, Hex, Hex]),
+        SYSCALL_CONNECT => ("connect", &[Fd, Hex, Int]),
+        SYSCALL_GETSOCKNAME => ("getsockname", &[Fd, Hex, Hex]),
+        SYSCALL_GETPEERNAME => ("getpeername", &[Fd, Hex, Hex]),
+        SYSCALL_SENDTO => ("sendto", &[Fd, Hex, Int, Hex, Hex, Int]),
+        SYSCALL_RECVFROM => ("recvfrom", &[Fd, Hex, Int, Hex, Hex, Hex]),
+        SYSCALL_SETSOCKOPT => ("setsockopt", &[Fd, Int, Int, Hex, Int]),
+        SYSCALL_SENDMSG => ("sendmsg", &[Fd, Hex, Hex]),
+        SYSCALL_RECVMSG => ("recvmsg", &[Fd, Hex, Hex]),
+        SYSCALL_BRK => return Some(("brk", &[Hex], true)),
+        SYSCALL_MUNMAP => ("munmap", &[Hex, Int]),
+        SYSCALL_MSYNC => ("msync", &[Hex, Int, Hex]),
+        SYSCALL_MINCORE => ("mincore", &[Hex, Int, Hex]),
+        SYSCALL_MADVISE => ("madvise", &[Hex, Int, Int]),
+        SYSCALL_CLONE => ("clone", &[Hex, Hex, Hex, Hex, Hex]),
+        SYSCALL_EXECVE => ("execve", &[Str, Hex, Hex]),
+        SYSCALL_MMAP => return Some(("mmap", &[Hex, Int, Hex, Hex, Fd, Hex], true)),
+        SYSCALL_WAIT4 => ("wait4", &[Int, Hex, Hex, Hex]),
+        SYSCALL_PRLIMIT64 => ("prlimit64", &[Int, Int, Hex, Hex]),
+        SYSCALL_GETRANDOM => ("getrandom", &[Hex, Int, Hex]),
+        SYSCALL_EXECVEAT => ("execveat", &[Fd, Str, Hex, Hex, Hex]),
+        SYSCALL_FACCESSAT2 => ("faccessat2", &[Fd, Str, Oct, Hex]),
+        SYSCALL_ENABLE_DEADLOCK_DETECT => ("enable_deadlock_detect", &[Int]),
+        SYSCALL_STRACE => ("strace", &[Int, Int, Hex, Int]),
+        SYSCALL_MUTEX_CREATE => ("mutex_create", &[Int]),
+        SYSCALL_MUTEX_LOCK => ("mutex_lock", &[Int]),
+        SYSCALL_MUTEX_UNLOCK => ("mutex_unlock", &[Int]),
+        SYSCALL_SEMAPHORE_CREATE => ("semaphore_create", &[Int]),
+        SYSCALL_SEMAPHORE_UP => ("semaphore_up", &[Int]),
+        SYSCALL_SEMAPHORE_DOWN => ("semaphore_down", &[Int]),
+        SYSCALL_CONDVAR_CREATE => ("condvar_create", &[]),
+        SYSCALL_CONDVAR_SIGNAL => ("condvar_signal", &[Int]),
+        SYSCALL_CONDVAR_WAIT => ("condvar_wait", &[Int, Int]),
+        _ => return None,
+    };
+    Some((desc.0, desc.1, false))
+}
+
+struct TraceState {
+    /// 被跟踪的进程
+    pids: BTreeSet<usize>,
+    /// 跟踪所有进程
+    all: bool,
+    buf: VecDeque<u8>,
+}
+
+impl TraceState {
+    fn is_traced(&self, pid: usize) -> bool {
+        self.all || self.pids.contains(&pid)
+    }
+
+    fn update_flag(&self) {
+        TRACING.store(self.all || !self.pids.is_empty(), Ordering::Relaxed);
+    }
+
+    /// 写入一整行，空间不足时丢弃最旧的行
+    fn push_line(&mut self, line: &str) {
+        let line = &line.as_bytes()[..line.len().min(TRACE_BUF_SIZE)];
+        while self.buf.len() + line.len() > TRACE_BUF_SIZE {
+            while let Some(c) = self.buf.pop_front() {
+                if c == b'\n' {
+                    break;
+                }
+            }
+        }
+        self.buf.extend(line);
+    }
+}
+
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref TRACE: UPIntrFreeCell<TraceState> = unsafe {
+        UPIntrFreeCell::new(TraceState {
+            pids: BTreeSet::new(),
+            all: false,
+            buf: VecDeque::new(),
+        })
+    };
+}
+
+/// 读取用户态 C 字符串用于显示，遇到未映射的页时返回 `None`
+fn peek_user_str(token: usize, ptr: usize) -> Option<String> {
+    let mut s = String::from("\"");
+    for va in ptr..ptr.checked_add(MAX_STR_LEN)? {
+        let mut ch = [0u8];
+        try_read_bytes(token, va, &mut ch)?;
+        let ch = ch[0];
+        if ch == 0 {
+            s.push('"');
+            return Some(s);
+        }
+        match ch {
+            b'"' | b'\\' => {
+                s.push('\\');
+                s.push(ch as char);
+            }
+            0x20..=0x7e => s.push(ch as char),
+            _ => {
+                let _ = write!(s, "\\x{:02x}", ch);
+            }
+        }
+    }
+    s.push_str("\"...");
+    Some(s)
+}
+
+fn format_arg(out: &mut String, token: usize, kind: Arg, value: usize) {
+    let _ = match kind {
+        Int => write!(out, "{}", value as isize),
+        Hex => write!(out, "{:#x}", value),
+        Oct => write!(out, "{:#o}", value),
+        Fd if value as isize == AT_FDCWD => write!(out, "AT_FDCWD"),
+        Fd => write!(out, "{}", value as isize),
+        Str if value == 0 => write!(out, "NULL"),
+        Str => match peek_user_str(token, value) {
+            Some(s) => write!(out, "{}", s),
+            None => write!(out, "{:#x}", value),
+        },
+    };
+}
+
+/// 一次正在进行的被跟踪系统调用
+pub struct TraceEntry {
+    line: String,
+    ret_hex: bool,
+}
+
+/// 系统调用进入时调用；当前进程被跟踪时返回格式化好的调用行
+pub fn enter(syscall_id: usize, args: &[usize; 6]) -> Option<TraceEntry> {
+    if !TRACING.load(Ordering::Relaxed) {
+        return None;
+    }
+    let pid = current_process().getpid();
+    if !TRACE.exclusive_access().is_traced(pid) {
+        return None;
+    }
+    let task = current_task()?;
+    let task_inner = task.inner_exclusive_access();
+    let (tid, name) = (task_inner.res.as_ref()?.tid, task_inner.name);
+    drop(task_inner);
+    let token = current_user_token();
+    let mut line = format!("[{} {} {}] ", pid, tid, name);
+    let ret_hex = match describe(syscall_id) {
+        Some((name, kinds, ret_hex)) => {
+            line.push_str(name);
+            line.push('(');
+            for (i, (&kind, &value)) in kinds.iter().zip(args.iter()).enumerate() {
+                if i > 0 {
+                    line.push_str(", ");
+                }
+                format_arg(&mut line, token, kind, value);
+            }
+            line.push(')');
+            ret_hex
+        }
+        None => {
+            let _ = write!(line, "syscall_{}({:#x?})", syscall_id, args);
+            false
+        }
+    };
+    if syscall_id == SYSCALL_EXIT || syscall_id == SYSCALL_EXIT_GROUP {
+        line.push_str(" = ?\n");
+        TRACE.exclusive_access().push_line(&line);
+        return None;
+    }
+    Some(TraceEntry { line, ret_hex })
+}
+
+/// 系统调用返回时调用，补上返回值后写入缓冲区
+pub fn exit(entry: TraceEntry, ret: isize) {
+    let TraceEntry { mut line, ret_hex } = entry;
+    let _ = if ret_hex && ret != -1 {
+        writeln!(line, " = {:#x}", ret)
+    } else {
+        writeln!(line, " = {}", ret)
+    };
+    TRACE.exclusive_access().push_line(&line);
+}
+
+/// 被跟踪的进程创建子进程时，子进程同样被跟踪
+pub fn follow_fork(parent: usize, child: usize) {
+    let mut trace = TRACE.exclusive_access();
+    if trace.pids.contains(&parent) {
+        trace.pids.insert(child);
+    }
+}
+
+/// 被跟踪的进程退出时停止跟踪它，避免复用同一 pid 的新进程被误跟踪
+pub fn untrace(pid: usize) {
+    let mut trace = TRACE.exclusive_access();
+    if trace.pids.remove(&pid) {
+        trace.update_flag();
+    }
+}
+
+/// 控制系统调用跟踪
+///
+/// - `STRACE_ON` / `STRACE_OFF`：开始 / 停止跟踪 `pid`，`pid` 为 0 表示所有进程；
+///   跟踪其他进程需要特权
+/// - `STRACE_READ`：把缓冲区中最多 `len` 字节的记录复制到 `buf` 并从缓冲区移除，返回复制的字节数
+pub fn sys_strace(op: usize, pid: usize, buf: *mut u8, len: usize) -> isize {
+    let process = current_process();
+    let privileged = process.inner_exclusive_access().cred.is_privileged();
+    if !privileged && (op == STRACE_READ || pid != process.getpid()) {
+        return -1; // EPERM
+    }
+    let mut trace = TRACE.exclusive_access();
+    match op {
+        STRACE_ON => {
+            if pid == 0 {
+                trace.all = true;
+            } else {
+                trace.pids.insert(pid);
+            }
+        }
+        STRACE_OFF => {
+            if pid == 0 {
+                trace.all = false;
+                trace.pids.clear();
+            } else {
+                trace.pids.remove(&pid);
+            }
+        }
+        STRACE_READ => {
+            let len = len.min(trace.buf.len());
+            if len == 0 {
+                return 0;
+            }
+            let token = current_user_token();
+            let Ok(chunks) = translated_byte_buffer(token, buf, len, true) else {
+                return -1; // EFAULT
+            };
+            for chunk in chunks {
+                for byte in chunk.iter_mut() {
+                    *byte = trace.buf.pop_front().unwrap();
+                }
+            }
+            return len as isize;
+        }
+        _ => return -1, // EINVAL
+    }
+    trace.update_flag();
+    0
+}
@@ -27,7 +27,10 @@
 //!   - 构造一个“空上下文”，通常用于占位或初始化
 //! - `goto_trap_return`：
 //!   - 构造一个在首次调度时直接返回用户态的任务上下文
+//! - `goto_kthread_start`：
+//!   - 构造一个在首次调度时进入内核线程入口的任务上下文
 
+use super::kthread::kthread_start;
 use crate::hal::trap_return;
 
 /// 任务上下文
@@ -94,4 +97,16 @@ impl TaskContext {
             s: [0; 12],
         }
     }
+
+    /// 构造一个“首次运行即进入 `kthread_start`”的任务上下文，用于内核线程
+    ///
+    /// ## Safety
+    /// - `kstack_ptr` 必须指向合法且已分配的内核栈空间
+    pub fn goto_kthread_start(kstack_ptr: usize) -> Self {
+        Self {
+            ra: kthread_start as usize,
+            sp: kstack_ptr,
+            s: [0; 12],
+        }
+    }
 }
@@ -0,0 +1,267 @@
+//! # core 文件
+//!
+//! ## Overview
+//! 进程因默认动作为“终止并转储”的信号（SIGQUIT、SIGILL、SIGTRAP、SIGABRT、SIGBUS、SIGFPE、
+//! SIGSEGV、SIGXCPU、SIGXFSZ、SIGSYS）终止时，在其工作目录下写入 ELF 格式的 `core.<pid>`：
+//! - `PT_NOTE`：`NT_PRPSINFO`（任务名、PID 等），以及每个线程一个 `NT_PRSTATUS`（信号与通用寄存器），
+//!   触发信号的线程排在第一个，调试器将其作为当前线程
+//! - 每个用户可访问的区域一个 `PT_LOAD`，包含区域的全部内容
+//!
+//! ## Design
+//! - 文件大小受 `RLIMIT_CORE` 的软限制约束：为 0 时不生成；超过限制的部分被截断，与 Linux 一致
+//! - 区域中没有映射物理页的部分（尚未访问、已被丢弃或换出）以 0 填充，文件偏移与区域地址一一对应
+//! - 寄存器取自各线程的 trap 上下文，即它们最近一次陷入内核时的状态
+//!
+//! ## Assumptions
+//! - 写入 FAT32 上的文件，工作目录不在可写文件系统上时放弃生成
+//! - 只有 RISC-V 的陷阱处理程序投递信号，LoongArch 暂不调用；寄存器布局由 HAL 按体系结构提供
+
+use super::{current_process, current_task};
+use crate::fs::{init_file_meta, open_file_at, resolve_path, File, OpenFlags};
+use crate::hal::{ELF_MACHINE, ELF_NGREG, PAGE_SIZE};
+use crate::mm::{try_read_bytes, MapPermission, Vma};
+use crate::syscall::StatMode;
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 默认动作会生成 core 文件的信号
+const CORE_SIGNALS: [usize; 10] = [3, 4, 5, 6, 7, 8, 11, 24, 25, 31];
+
+const ELF_HEADER_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+/// `elf_prstatus` 中寄存器组之前的部分：`pr_info`、`pr_cursig`、信号集、各进程号与四个 `timeval`
+const PRSTATUS_PREFIX: usize = 112;
+/// `elf_prpsinfo` 的大小
+const PRPSINFO_SIZE: usize = 136;
+
+/// core 文件的写入位置与剩余的大小限制
+struct CoreWriter {
+    file: Arc<dyn File + Send + Sync>,
+    offset: usize,
+    limit: usize,
+}
+
+impl CoreWriter {
+    /// 写入 `data`，超过限制的部分被丢弃；写入失败或到达限制时返回 `false`
+    fn write(&mut self, data: &[u8]) -> bool {
+        let len = data.len().min(self.limit - self.offset);
+        let mut done = 0;
+        while done < len {
+            match self.file.write_at(self.offset + done, &data[done..len]) {
+                Ok(n) if n > 0 => done += n,
+                _ => return false,
+            }
+        }
+        self.offset += len;
+        len == data.len()
+    }
+
+    /// 以 0 填充到 `offset`
+    fn pad_to(&mut self, offset: usize) -> bool {
+        let zeros = vec![0u8; offset - self.offset];
+        self.write(&zeros)
+    }
+}
+
+/// 当前进程即将因信号 `signum` 终止，按需生成 core 文件，返回是否生成
+pub fn dump_core(signum: usize) -> bool {
+    if !CORE_SIGNALS.contains(&signum) {
+        return false;
+    }
+    let process = current_process();
+    let current = current_task().unwrap();
+    let inner = process.inner_exclusive_access();
+    let limit = inner.core_limit.rlim_cur.min(usize::MAX as u64) as usize;
+    if limit == 0 {
+        return false;
+    }
+    let pid = process.getpid();
+    let ppid = inner
+        .parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade())
+        .map_or(0, |parent| parent.getpid());
+    let token = inner.memory_set.token();
+    let vmas = inner.memory_set.vmas();
+    let (cwd, cwd_inode, cred, umask) = (
+        inner.cwd.clone(),
+        inner.cwd_inode.clone(),
+        inner.cred,
+        inner.umask,
+    );
+    // 触发信号的线程排在第一个
+    let mut threads: Vec<_> = inner.tasks.iter().flatten().cloned().collect();
+    drop(inner);
+    threads.sort_by_key(|task| !Arc::ptr_eq(task, &current));
+    let name = current.inner_exclusive_access().name;
+
+    let mut notes = Vec::new();
+    let mut psinfo = [0u8; PRPSINFO_SIZE];
+    psinfo[0] = b'R'; // pr_state
+    psinfo[1] = b'R'; // pr_sname
+    put_u32(&mut psinfo, 16, cred.uid);
+    put_u32(&mut psinfo, 20, cred.gid);
+    put_u32(&mut psinfo, 24, pid as u32);
+    put_u32(&mut psinfo, 28, ppid as u32);
+    put_u32(&mut psinfo, 32, pid as u32);
+    put_u32(&mut psinfo, 36, pid as u32);
+    psinfo[40..56].copy_from_slice(name.as_bytes());
+    push_note(&mut notes, NT_PRPSINFO, &psinfo);
+    for task in threads.iter() {
+        let task_inner = task.inner_exclusive_access();
+        let Some(res) = task_inner.res.as_ref() else {
+            continue;
+        };
+        let mut prstatus = vec![0u8; PRSTATUS_PREFIX + ELF_NGREG * 8 + 8];
+        // pr_info.si_signo 与 pr_cursig
+        put_u32(&mut prstatus, 0, signum as u32);
+        prstatus[12..14].copy_from_slice(&(signum as u16).to_le_bytes());
+        // pr_pid：线程没有全局唯一的线程号，以进程号加进程内的线程编号区分各线程
+        put_u32(&mut prstatus, 32, (pid + res.tid) as u32);
+        put_u32(&mut prstatus, 36, ppid as u32);
+        put_u32(&mut prstatus, 40, pid as u32);
+        put_u32(&mut prstatus, 44, pid as u32);
+        let regs = task_inner.get_trap_cx().elf_gregset();
+        for (i, reg) in regs.iter().enumerate() {
+            let at = PRSTATUS_PREFIX + i * 8;
+            prstatus[at..at + 8].copy_from_slice(&(*reg as u64).to_le_bytes());
+        }
+        push_note(&mut notes, NT_PRSTATUS, &prstatus);
+    }
+
+    let path = resolve_path(&format!("core.{}", pid), &cwd);
+    let flags = OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC;
+    let Some(file) = open_file_at(&*cwd_inode, &path, flags, StatMode::empty()) else {
+        return false;
+    };
+    init_file_meta(&path, 0o600, umask, cred.euid, cred.egid);
+
+    let phnum = vmas.len() + 1;
+    let notes_offset = ELF_HEADER_SIZE + phnum * PHDR_SIZE;
+    let mut data_offset = (notes_offset + notes.len()).next_multiple_of(PAGE_SIZE);
+    let mut headers = elf_header(phnum);
+    push_phdr(
+        &mut headers,
+        PT_NOTE,
+        0,
+        notes_offset,
+        0,
+        notes.len(),
+        notes.len(),
+        0,
+    );
+    for vma in vmas.iter() {
+        let size = vma.end - vma.start;
+        push_phdr(
+            &mut headers,
+            PT_LOAD,
+            segment_flags(vma),
+            data_offset,
+            vma.start,
+            size,
+            size,
+            PAGE_SIZE,
+        );
+        data_offset += size;
+    }
+    headers.extend_from_slice(&notes);
+
+    let mut writer = CoreWriter {
+        file,
+        offset: 0,
+        limit,
+    };
+    let mut page = vec![0u8; PAGE_SIZE];
+    if writer.write(&headers) && writer.pad_to(headers.len().next_multiple_of(PAGE_SIZE)) {
+        'dump: for vma in vmas.iter() {
+            for va in (vma.start..vma.end).step_by(PAGE_SIZE) {
+                if try_read_bytes(token, va, &mut page).is_none() {
+                    page.fill(0);
+                }
+                if !writer.write(&page) {
+                    break 'dump;
+                }
+            }
+        }
+    }
+    println!("[kernel] core dumped to {} ({} bytes)", path, writer.offset);
+    true
+}
+
+fn put_u32(buf: &mut [u8], at: usize, value: u32) {
+    buf[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// 追加一条名字为 `CORE` 的 note，名字与内容各自按 4 字节对齐
+fn push_note(notes: &mut Vec<u8>, note_type: u32, desc: &[u8]) {
+    const NAME: &[u8] = b"CORE\0";
+    notes.extend_from_slice(&(NAME.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&note_type.to_le_bytes());
+    notes.extend_from_slice(NAME);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+    notes.extend_from_slice(desc);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+}
+
+fn segment_flags(vma: &Vma) -> u32 {
+    let mut flags = 0;
+    if vma.perm.contains(MapPermission::R) {
+        flags |= PF_R;
+    }
+    if vma.perm.contains(MapPermission::W) {
+        flags |= PF_W;
+    }
+    if vma.perm.contains(MapPermission::X) {
+        flags |= PF_X;
+    }
+    flags
+}
+
+/// 小端 64 位 ELF 文件头，程序头表紧随其后
+fn elf_header(phnum: usize) -> Vec<u8> {
+    let mut header = Vec::with_capacity(ELF_HEADER_SIZE + phnum * PHDR_SIZE);
+    header.extend_from_slice(b"\x7fELF");
+    header.extend_from_slice(&[2, 1, 1, 0]); // ELFCLASS64、小端、EV_CURRENT、System V ABI
+    header.extend_from_slice(&[0; 8]);
+    header.extend_from_slice(&ET_CORE.to_le_bytes());
+    header.extend_from_slice(&ELF_MACHINE.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    header.extend_from_slice(&(ELF_HEADER_SIZE as u64).to_le_bytes()); // e_phoff
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    header.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    header.extend_from_slice(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&(phnum as u16).to_le_bytes());
+    header.extend_from_slice(&[0; 6]); // e_shentsize、e_shnum、e_shstrndx
+    header
+}
+
+#[allow(clippy::too_many_arguments)]
+fn push_phdr(
+    headers: &mut Vec<u8>,
+    p_type: u32,
+    p_flags: u32,
+    offset: usize,
+    vaddr: usize,
+    filesz: usize,
+    memsz: usize,
+    align: usize,
+) {
+    headers.extend_from_slice(&p_type.to_le_bytes());
+    headers.extend_from_slice(&p_flags.to_le_bytes());
+    for value in [offset, vaddr, 0, filesz, memsz, align] {
+        headers.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+}
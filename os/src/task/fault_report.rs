@@ -0,0 +1,100 @@
+//! # 用户态故障报告
+//!
+//! ## Overview
+//! 启用 `fault_report` feature 时，用户进程因硬件故障类信号（SIGILL、SIGBUS、SIGFPE、SIGSEGV）
+//! 终止前在控制台输出：
+//! - 进程、线程与任务名，终止它的信号与故障地址
+//! - 故障时的 `TrapContext` 寄存器
+//! - 进程的地址空间区域列表（起止地址、权限、共享与否、映射的文件）
+//! - 基于帧指针的用户栈回溯（尽力而为）
+//!
+//! ## Design
+//! - 在陷阱返回前发现致命信号时调用（`report_fatal_signal`），此时线程尚未退出，
+//!   trap 上下文与地址空间都还完整
+//! - 故障地址只在本次陷阱就是该故障时有效，由陷阱处理程序传入；信号来自 `kill` 等其他来源时为 `None`
+//! - 回溯与内核的 panic 回溯使用相同的栈帧布局：`fp - 8` 为返回地址，`fp - 16` 为上一帧的帧指针；
+//!   经页表检查后读取用户内存，遇到未映射、未对齐或不向栈底单调增长的帧指针立即停止
+//!
+//! ## Assumptions
+//! - 用户程序以 `-fno-omit-frame-pointer` 编译时回溯才有意义，否则通常在第一帧就停止
+//! - 只有 RISC-V 的陷阱处理程序投递信号，LoongArch 暂不调用
+
+use super::{current_process, current_task, current_trap_cx, current_user_token};
+use crate::mm::{try_read_bytes, MapPermission};
+
+/// 用户栈回溯的最大栈帧数
+const MAX_USER_BACKTRACE_DEPTH: usize = 16;
+
+/// 硬件故障类信号：SIGILL、SIGBUS、SIGFPE、SIGSEGV
+const FAULT_SIGNALS: [usize; 4] = [4, 7, 8, 11];
+
+/// 当前线程即将因信号 `signum` 终止，若为故障类信号则输出故障报告
+///
+/// `fault_addr` 为本次陷阱的故障地址（`stval` / `badv`）
+pub fn report_fatal_signal(signum: usize, fault_addr: Option<usize>) {
+    if !FAULT_SIGNALS.contains(&signum) {
+        return;
+    }
+    let task = current_task().unwrap();
+    let process = current_process();
+    let (tid, name) = {
+        let inner = task.inner_exclusive_access();
+        (inner.res.as_ref().map_or(0, |res| res.tid), inner.name)
+    };
+    drop(task);
+    println!("\n----FAULT REPORT----");
+    println!(
+        "pid={} tid={} ({}) killed by signal {}",
+        process.getpid(),
+        tid,
+        name,
+        signum
+    );
+    if let Some(addr) = fault_addr {
+        println!("fault address: {:#x}", addr);
+    }
+    let trap_cx = current_trap_cx();
+    trap_cx.dump();
+    println!("----MEMORY MAP----");
+    for vma in process.inner_exclusive_access().memory_set.vmas() {
+        let flag = |perm, c| if vma.perm.contains(perm) { c } else { '-' };
+        println!(
+            "{:#014x}-{:#014x} {}{}{}{} {}",
+            vma.start,
+            vma.end,
+            flag(MapPermission::R, 'r'),
+            flag(MapPermission::W, 'w'),
+            flag(MapPermission::X, 'x'),
+            if vma.shared { 's' } else { 'p' },
+            vma.path.as_deref().unwrap_or("")
+        );
+    }
+    user_backtrace(trap_cx.pc(), trap_cx.fp());
+    println!("----END OF FAULT REPORT----");
+}
+
+/// 从故障时的 `pc` 与帧指针 `fp` 回溯用户栈
+fn user_backtrace(pc: usize, mut fp: usize) {
+    let token = current_user_token();
+    println!("----USER BACKTRACE----");
+    println!("#0  pc={:#x}", pc);
+    for i in 1..MAX_USER_BACKTRACE_DEPTH {
+        if fp % core::mem::size_of::<usize>() != 0 || fp < 16 {
+            break;
+        }
+        let mut frame = [0u8; 16];
+        if try_read_bytes(token, fp - 16, &mut frame).is_none() {
+            break;
+        }
+        let prev_fp = usize::from_le_bytes(frame[..8].try_into().unwrap());
+        let ra = usize::from_le_bytes(frame[8..].try_into().unwrap());
+        if ra == 0 {
+            break;
+        }
+        println!("#{:<2} ra={:#x} fp={:#x}", i, ra, fp);
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
+    }
+}
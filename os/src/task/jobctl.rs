@@ -0,0 +1,116 @@
+//! # 作业控制（停止与继续）
+//!
+//! ## Overview
+//! 实现停止信号与 `SIGCONT` 的默认动作，供 shell 的作业控制使用：
+//! - `SIGSTOP`、`SIGTSTP`、`SIGTTIN`、`SIGTTOU` 使整个进程停止，直到收到 `SIGCONT` 或 `SIGKILL`
+//! - 父进程通过带 `WUNTRACED` 的 `wait4` 得知子进程停止，带 `WCONTINUED` 的 `wait4` 得知其继续运行
+//!
+//! ## Design
+//! - 发送信号时（`ProcessControlBlockInner::add_signal`）处理两类信号的相互抵消：
+//!   `SIGCONT` 丢弃尚未生效的停止信号，并让已停止的进程继续；停止信号丢弃尚未处理的 `SIGCONT`
+//! - 停止在线程返回用户态之前生效（`jobctl_stop_if_needed`）：第一个经过的线程取走停止信号，
+//!   把进程标记为停止，之后每个返回用户态的线程都停在这里。停止中的线程通过让出处理器轮询恢复条件，
+//!   与 ptrace 停止及 `wait4` 的等待方式一致
+//! - 每次停止与继续只向父进程报告一次，分别由 `take_unreported_stop` / `take_unreported_continue` 取走
+//!
+//! ## Assumptions
+//! - 还没有用户信号处理函数，停止信号总是执行默认动作；`SIGSTOP` 与 `SIGKILL` 一样不能被屏蔽
+//! - 不向父进程发送 `SIGCHLD`：`wait4` 轮询子进程的状态，不依赖它唤醒
+//! - 被跟踪的进程先以 ptrace 停止把信号交给跟踪者，跟踪者放行后才按默认动作停止
+//!
+//! ## Invariants
+//! - `continued` 为真时进程不处于停止状态
+//! - 待处理信号中不会同时有停止信号与 `SIGCONT`
+
+use super::signal::SignalFlags;
+use super::{current_process, current_task, suspend_current_and_run_next};
+
+/// 进程的作业控制状态
+#[derive(Default)]
+pub struct JobControl {
+    /// 处于停止时导致停止的信号
+    stop_signal: Option<usize>,
+    /// 本次停止是否已经报告给父进程
+    stop_reported: bool,
+    /// 从停止中继续、尚未报告给父进程
+    continued: bool,
+}
+
+impl JobControl {
+    /// 进程是否处于停止状态
+    pub fn is_stopped(&self) -> bool {
+        self.stop_signal.is_some()
+    }
+
+    /// 以信号 `signum` 停止
+    fn stop(&mut self, signum: usize) {
+        self.stop_signal = Some(signum);
+        self.stop_reported = false;
+        self.continued = false;
+    }
+
+    /// 收到 `SIGCONT`：处于停止时继续运行，并记录一次待报告的继续
+    fn resume(&mut self) {
+        if self.stop_signal.take().is_some() {
+            self.continued = true;
+        }
+    }
+
+    /// 取出尚未报告给父进程的停止信号（`WUNTRACED`）
+    pub fn take_unreported_stop(&mut self) -> Option<usize> {
+        if self.stop_reported {
+            return None;
+        }
+        let signum = self.stop_signal?;
+        self.stop_reported = true;
+        Some(signum)
+    }
+
+    /// 取出尚未报告给父进程的继续（`WCONTINUED`）
+    pub fn take_unreported_continue(&mut self) -> bool {
+        core::mem::take(&mut self.continued)
+    }
+
+    /// 发送信号 `signal` 之前调用，处理停止信号与 `SIGCONT` 的相互抵消
+    pub fn on_signal(&mut self, pending: &mut SignalFlags, signal: SignalFlags) {
+        if signal.contains(SignalFlags::SIGCONT) {
+            pending.remove(SignalFlags::STOP_SIGNALS);
+            self.resume();
+        } else if signal.intersects(SignalFlags::STOP_SIGNALS) {
+            pending.remove(SignalFlags::SIGCONT);
+        }
+    }
+}
+
+/// 返回用户态前调用：有没被屏蔽的停止信号时停止进程，进程停止期间本线程一直等待
+///
+/// 收到 `SIGCONT` 继续运行；收到 `SIGKILL` 或线程组正在退出时立即返回，由调用者随后处理
+pub fn jobctl_stop_if_needed() {
+    let mask = current_task().unwrap().inner_exclusive_access().signal_mask;
+    let process = current_process();
+    {
+        let mut inner = process.inner_exclusive_access();
+        if inner.signals.contains(SignalFlags::SIGKILL) {
+            return;
+        }
+        let pending = (inner.signals - mask) & SignalFlags::STOP_SIGNALS;
+        if !pending.is_empty() {
+            let signum = pending.bits().trailing_zeros() as usize + 1;
+            inner.signals.remove(pending);
+            inner.jobctl.stop(signum);
+        }
+        if !inner.jobctl.is_stopped() {
+            return;
+        }
+    }
+    loop {
+        suspend_current_and_run_next();
+        let inner = process.inner_exclusive_access();
+        if !inner.jobctl.is_stopped()
+            || inner.signals.contains(SignalFlags::SIGKILL)
+            || inner.group_exit_code.is_some()
+        {
+            return;
+        }
+    }
+}
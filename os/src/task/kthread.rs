@@ -0,0 +1,55 @@
+//! # 内核线程
+//!
+//! ## Overview
+//! 只在内核态运行、从不进入用户态的任务，供后台服务（写回缓存、工作队列、网络协议栈）使用：
+//! - `kthread_spawn(entry, arg)`：创建内核线程并加入就绪队列，首次被调度时执行 `entry(arg)`
+//! - `kthread_exit()`：结束当前内核线程；`entry` 返回时自动调用
+//!
+//! ## Design
+//! - 内核线程是不属于任何进程的 `TaskControlBlock`：`process` 为空的弱引用，没有用户资源与
+//!   trap 上下文，只有自己的内核栈；与用户任务由同一个调度器、同一组就绪队列调度
+//! - 首次调度时 `__switch` 返回到 `kthread_start`，它从当前 TCB 取出入口与参数后调用入口函数
+//! - 退出时仍运行在自己的内核栈上，不能在此释放 TCB；把最后一个引用交给工作队列，
+//!   切换到调度循环之后才被丢弃，内核栈随之回收
+//!
+//! ## Assumptions
+//! - 没有内核抢占：内核线程须通过 `suspend_current_and_run_next` 或阻塞主动让出处理器
+//! - 内核线程不接收信号，`signal_pending_of_current` 对其总为 `false`；
+//!   也不能调用访问当前进程的接口（`current_process`、`current_user_token` 等）
+
+use super::{add_task, current_task, schedule, take_current_task, TaskContext, TaskControlBlock};
+use alloc::sync::Arc;
+
+/// 内核线程的入口函数
+pub type KthreadFn = fn(usize);
+
+/// 创建一个执行 `entry(arg)` 的内核线程并加入就绪队列
+pub fn kthread_spawn(entry: KthreadFn, arg: usize) -> Arc<TaskControlBlock> {
+    let task = Arc::new(TaskControlBlock::new_kthread(entry, arg));
+    add_task(task.clone());
+    task
+}
+
+/// 内核线程首次被调度时的入口
+pub(super) fn kthread_start() -> ! {
+    let (entry, arg) = current_task()
+        .unwrap()
+        .kthread_entry
+        .expect("user task entered kthread_start");
+    entry(arg);
+    kthread_exit()
+}
+
+/// 结束当前内核线程并运行下一任务
+pub fn kthread_exit() -> ! {
+    let task = take_current_task().unwrap();
+    assert!(task.is_kthread(), "kthread_exit called by a user task");
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.exit_code = Some(0);
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    drop(task_inner);
+    // 调度循环中执行工作时已切换离开这个内核栈
+    crate::workqueue::schedule_work(move || drop(task));
+    schedule(task_cx_ptr);
+    unreachable!("exited kthread scheduled again");
+}
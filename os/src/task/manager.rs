@@ -6,12 +6,13 @@
 //! 是调度器和进程管理子系统的重要基础组成部分。
 //!
 //! 主要职责包括：
-//! - 维护就绪任务队列（ready queue）
+//! - 维护每个 hart 的就绪任务队列（run queue），空闲的 hart 从其他队列窃取任务
 //! - 提供任务的加入、唤醒与获取接口
 //! - 维护 PID 到 `ProcessControlBlock` 的全局映射
+//! - 在时钟中断中采样就绪队列长度，计算 1/5/15 分钟负载平均值
 //!
-//! 所有全局状态均通过 `UPIntrFreeCell` 进行保护，
-//! 以适配 **单处理器 + 中断并发模型**。
+//! 就绪队列各自由一把 `SpinMutex` 保护，调度时只锁本 hart 的队列；
+//! 其余全局状态通过 `UPIntrFreeCell` 进行保护，适配 **单处理器 + 中断并发模型**。
 //!
 //! ## Assumptions
 //! - 系统运行在单处理器环境
@@ -26,30 +27,43 @@
 //! ## Invariants
 //! - 就绪队列中的任务：
 //!   - 其 `task_status` 一定为 `Ready`
+//!   - 加入队列时所在 hart 在其 CPU 亲和性之内；亲和性之后被修改时，取出时再次检查
 //! - 同一个 PID 在 `PID2PCB` 中最多对应一个进程
 //! - 被移除的 PID 必然曾经存在于映射表中
 //!
 //! ## Behavior
 //! - 任务调度采用 FIFO 顺序（简单就绪队列）
+//! - 任务优先加入当前 hart 的队列，亲和性不允许时加入允许的在线 hart 中编号最小的一个
+//! - 本 hart 的队列中没有可运行的任务时，从最长的其他队列的队尾窃取一个允许在本 hart 上运行的任务
 //! - 模块本身不实现时间片或优先级策略
 //! - 调度策略可在此基础上扩展
+//! - 负载平均值的算法与 Linux 相同：每 5 秒以「就绪任务数 + 正在运行的任务数」
+//!   做一次指数衰减平均，`sched_yield` 让出的任务仍在就绪队列中，照常计入负载
 
-use crate::sync::UPIntrFreeCell;
+use crate::smp::{online_harts, this_hart, MAX_HARTS};
+use crate::sync::{SpinMutex, UPIntrFreeCell};
 use crate::task::process::ProcessControlBlock;
 use crate::task::task::TaskStatus;
-use crate::task::{current_task, TaskControlBlock};
+use crate::task::{current_task, try_current_task, TaskControlBlock};
+use crate::timer::get_time_ms;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
 use lazy_static::lazy_static;
 
 lazy_static! {
     /// 全局任务管理器
     ///
     /// ## Overview
-    /// 维护系统中所有处于就绪状态的任务队列
+    /// 维护负载平均值，就绪任务保存在 `RUN_QUEUES` 中
     pub static ref TASK_MANAGER: UPIntrFreeCell<TaskManager> =
         unsafe { UPIntrFreeCell::new(TaskManager::new()) };
 
+    /// 每个 hart 的就绪队列
+    static ref RUN_QUEUES: [SpinMutex<VecDeque<Arc<TaskControlBlock>>>; MAX_HARTS] =
+        core::array::from_fn(|_| SpinMutex::new(VecDeque::new()));
+
     /// PID → ProcessControlBlock 映射表
     ///
     /// ## Overview
@@ -58,12 +72,33 @@ lazy_static! {
         unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
 }
 
+/// 负载平均值的定点小数位数
+pub const FSHIFT: usize = 11;
+const FIXED_1: usize = 1 << FSHIFT;
+/// 采样间隔
+const LOAD_FREQ_MS: usize = 5000;
+/// 1/5/15 分钟的衰减系数，即 `FIXED_1 / exp(5s / 1min)` 等
+const EXP: [usize; 3] = [1884, 2014, 2037];
+
 /// 将一个任务加入就绪队列
 ///
 /// ## Behavior
 /// - 不检查任务状态，由调用者保证其合法性
 pub fn add_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.exclusive_access().add(task);
+    let hart = target_hart(task.cpu_affinity.load(Ordering::Relaxed));
+    RUN_QUEUES[hart].lock().push_back(task);
+}
+
+/// 亲和性为 `affinity` 的任务加入哪个 hart 的队列：允许时为当前 hart，
+/// 否则为允许的在线 hart 中编号最小的一个
+fn target_hart(affinity: usize) -> usize {
+    let hart = this_hart();
+    let allowed = affinity & online_harts();
+    if allowed & (1 << hart) != 0 || allowed == 0 {
+        hart
+    } else {
+        allowed.trailing_zeros() as usize
+    }
 }
 
 /// 唤醒一个任务并加入就绪队列
@@ -89,7 +124,52 @@ pub fn wakeup_task(task: Arc<TaskControlBlock>) {
 /// - `None`：
 ///   - 当前无可运行任务
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
+    let hart = this_hart();
+    let task = take_runnable(&mut RUN_QUEUES[hart].lock(), hart, false);
+    task.or_else(|| steal_task(hart))
+}
+
+/// 从 `queue` 中取出第一个（`from_back` 时为最后一个）允许在 `hart` 上运行的任务
+///
+/// 顺带丢弃用户资源已被回收的线程（所属线程组已退出）
+fn take_runnable(
+    queue: &mut VecDeque<Arc<TaskControlBlock>>,
+    hart: usize,
+    from_back: bool,
+) -> Option<Arc<TaskControlBlock>> {
+    queue.retain(|task| task.inner_exclusive_access().res.is_some());
+    let index = if from_back {
+        queue.iter().rposition(|task| task.runs_on(hart))?
+    } else {
+        queue.iter().position(|task| task.runs_on(hart))?
+    };
+    queue.remove(index)
+}
+
+/// 从最长的其他就绪队列的队尾窃取一个允许在 `hart` 上运行的任务
+fn steal_task(hart: usize) -> Option<Arc<TaskControlBlock>> {
+    let victim = (0..MAX_HARTS)
+        .filter(|&other| other != hart)
+        .max_by_key(|&other| RUN_QUEUES[other].lock().len())?;
+    take_runnable(&mut RUN_QUEUES[victim].lock(), hart, true)
+}
+
+/// 时钟中断时调用，每隔 `LOAD_FREQ_MS` 更新一次负载平均值
+pub fn sample_load() {
+    let active = ready_count() + try_current_task().is_some() as usize;
+    TASK_MANAGER
+        .exclusive_access()
+        .sample_load(get_time_ms(), active);
+}
+
+/// 1/5/15 分钟负载平均值，定点数，小数部分 `FSHIFT` 位
+pub fn load_average() -> [usize; 3] {
+    TASK_MANAGER.exclusive_access().loadavg
+}
+
+/// 就绪队列中的任务数
+pub fn ready_count() -> usize {
+    RUN_QUEUES.iter().map(|queue| queue.lock().len()).sum()
 }
 
 /// 根据 PID 获取对应的进程控制块
@@ -104,6 +184,69 @@ pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
     map.get(&pid).map(Arc::clone)
 }
 
+/// 当前存在的全部进程的快照，按 PID 升序
+pub fn all_processes() -> Vec<Arc<ProcessControlBlock>> {
+    PID2PCB.exclusive_access().values().cloned().collect()
+}
+
+/// 当前存在的进程数
+pub fn process_count() -> usize {
+    PID2PCB.exclusive_access().len()
+}
+
+/// 当前存在的最大 PID
+pub fn max_pid() -> usize {
+    PID2PCB
+        .exclusive_access()
+        .keys()
+        .next_back()
+        .copied()
+        .unwrap_or(0)
+}
+
+/// 内存回收函数：释放所有进程中 `MADV_FREE` 后未被写入的页
+///
+/// 正被借用的进程（如发起这次分配的进程）会被跳过
+pub fn shrink_lazy_free_pages() -> usize {
+    let processes: Vec<Arc<ProcessControlBlock>> = match PID2PCB.try_exclusive_access() {
+        Some(map) => map.values().cloned().collect(),
+        None => return 0,
+    };
+    processes
+        .iter()
+        .filter_map(|process| process.try_inner_exclusive_access())
+        .map(|mut inner| inner.memory_set.reclaim_lazy_free())
+        .sum()
+}
+
+/// 内存回收函数：按进程轮转，把未被借用的进程中最近未访问的匿名页换出到交换区
+#[cfg(feature = "swap")]
+pub fn shrink_swap_pages() -> usize {
+    use crate::mm::SWAP_CLUSTER;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    /// 上次换出从第几个进程开始，下次从其后一个进程开始，避免总是换出同一个进程的页
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let mut processes: Vec<Arc<ProcessControlBlock>> = match PID2PCB.try_exclusive_access() {
+        Some(map) => map.values().cloned().collect(),
+        None => return 0,
+    };
+    if processes.is_empty() {
+        return 0;
+    }
+    let len = processes.len();
+    processes.rotate_left(NEXT.fetch_add(1, Ordering::Relaxed) % len);
+    let mut swapped = 0;
+    for process in processes.iter() {
+        if swapped >= SWAP_CLUSTER {
+            break;
+        }
+        if let Some(mut inner) = process.try_inner_exclusive_access() {
+            swapped += inner.memory_set.swap_out(SWAP_CLUSTER - swapped);
+        }
+    }
+    swapped
+}
+
 /// 向 PID 映射表中插入一个进程
 ///
 /// ## Invariants
@@ -127,40 +270,40 @@ pub fn remove_from_pid2process(pid: usize) {
 /// 任务管理器
 ///
 /// ## Overview
-/// 维护一个简单的 FIFO 就绪队列，
-/// 为调度器提供最基础的任务管理能力
+/// 维护负载平均值的采样状态
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    /// 1/5/15 分钟负载平均值（定点数）
+    loadavg: [usize; 3],
+    /// 下一次采样的时间
+    next_sample_ms: usize,
 }
 
 impl TaskManager {
     /// 创建一个新的任务管理器
-    ///
-    /// ## Invariants
-    /// - 初始就绪队列为空
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            loadavg: [0; 3],
+            next_sample_ms: LOAD_FREQ_MS,
         }
     }
 
-    /// 将任务加入就绪队列
-    ///
-    /// ## Behavior
-    /// - 采用 FIFO 顺序
-    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+    /// 到达采样时间时，以 `active`（就绪与正在运行的任务数）更新负载平均值
+    pub fn sample_load(&mut self, now_ms: usize, active: usize) {
+        if now_ms < self.next_sample_ms {
+            return;
+        }
+        self.next_sample_ms = now_ms + LOAD_FREQ_MS;
+        let active = active * FIXED_1;
+        for (load, exp) in self.loadavg.iter_mut().zip(EXP) {
+            *load = (*load * exp + active * (FIXED_1 - exp)) / FIXED_1;
+        }
     }
+}
 
-    /// 从就绪队列中取出一个任务
-    ///
-    /// ## Behavior
-    /// - 返回队首任务
-    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
-    }
-    pub fn find_by_pid(&self, pid: usize) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.iter().find_map(|task| {
+/// 在所有就绪队列中查找属于进程 `pid` 的任务
+fn find_ready_by_pid(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    RUN_QUEUES.iter().find_map(|queue| {
+        queue.lock().iter().find_map(|task| {
             // 获取任务的进程引用
             let process = task.process.upgrade()?;
             // 检查进程的 PID 是否匹配
@@ -170,17 +313,21 @@ impl TaskManager {
                 None
             }
         })
-    }
+    })
 }
 pub fn find_task_by_pid(pid: usize) -> Option<Arc<TaskControlBlock>> {
     // 获取当前任务
     let task = current_task().unwrap();
     // 如果当前任务的pid与指定的pid相同，返回当前任务
-    if task.process.upgrade().unwrap().pid.0 == pid {
+    if task
+        .process
+        .upgrade()
+        .is_some_and(|process| process.pid.0 == pid)
+    {
         Some(task)
     } else {
-        // 否则从任务管理器中查找
-        TASK_MANAGER.exclusive_access().find_by_pid(pid)
+        // 否则从就绪队列中查找
+        find_ready_by_pid(pid)
     }
 }
 pub fn wake_blocked(task: Arc<TaskControlBlock>) {
@@ -30,45 +30,85 @@
 //!   - 返回任务上下文指针
 //! - `block_current_and_run_next()`：
 //!   - 阻塞当前任务并调度下一任务
+//! - `block_current_interruptible(ready)`：
+//!   - 同上，但会终止进程的信号到达时任务被提前唤醒，返回 `true`，系统调用据此返回 EINTR
+//!   - 还没有用户信号处理函数，因此不存在 `SA_RESTART` 式的重启，只有致命信号打断等待
 //! - `exit_current_and_run_next(exit_code)`：
 //!   - 记录退出码，释放用户资源
 //!   - 如果主线程退出，处理 PCB 回收、子进程重新挂载到 `initproc`
 //!   - 调度下一任务
+//! - `cond_resched()`：
+//!   - 内核态抢占的安全点，时钟中断请求了重新调度且没有持锁时让出处理器，见 `preempt` 模块
+//! - `kthread_spawn(entry, arg)` / `kthread_exit()`：
+//!   - 创建 / 结束只在内核态运行的内核线程，与用户任务由同一调度器调度
+//! - `exit_group_and_run_next(exit_code)`：
+//!   - 记录线程组的退出码并唤醒阻塞的其余线程，它们在返回用户态前退出
+//!   - 主线程退出时回收所有线程的用户资源，父进程的 wait4 只看到一次线程组的退出码
 //! - `INITPROC`：
-//!   - 通过 ELF 文件创建初始进程 PCB
+//!   - 通过 ELF 文件创建初始进程 PCB，文件由启动参数 `init=` 指定，默认 `/initproc`
+//!   - 自动运行列表（`autorun=`，默认 `/autorun.txt`）存在时，其中的命令作为初始进程的参数
 //!   - 保证系统启动后至少有一个进程存在
 //! - 信号处理：
-//!   - `check_signals_of_current()` 返回当前进程的错误信号
+//!   - `check_signals_of_current()` 返回当前线程的致命信号编号，tkill / tgkill 发给线程的信号优先
 //!   - `current_add_signal(signal)` 向当前进程添加信号
+//!   - `signal_pending_of_current()` 判断阻塞中的系统调用是否应返回 EINTR
+//!   - `set_signal_mask_of_current(mask)` 替换当前线程的信号屏蔽字，被屏蔽的信号不参与以上两项检查
+//!   - `dump_core(signum)` 在进程因信号终止前按 `RLIMIT_CORE` 生成 core 文件
 
 mod context;
+mod coredump;
+#[cfg(feature = "fault_report")]
+mod fault_report;
+mod jobctl;
+mod kthread;
 mod manager;
 mod pid;
+mod preempt;
 mod process;
 mod processor;
+mod ptrace;
 mod signal;
 mod task;
+mod tls;
+mod wstatus;
 
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 pub use context::TaskContext;
+pub use coredump::dump_core;
+#[cfg(feature = "fault_report")]
+pub use fault_report::report_fatal_signal;
+pub use jobctl::{jobctl_stop_if_needed, JobControl};
+pub use kthread::{kthread_exit, kthread_spawn, KthreadFn};
 use lazy_static::lazy_static;
+#[cfg(feature = "swap")]
+pub use manager::shrink_swap_pages;
 pub use manager::{
-    add_task, find_task_by_pid, pid2process, remove_from_pid2process, wake_blocked, wakeup_task,
+    add_task, all_processes, find_task_by_pid, load_average, max_pid, pid2process, process_count,
+    ready_count, remove_from_pid2process, sample_load, shrink_lazy_free_pages, wake_blocked,
+    wakeup_task, FSHIFT,
 };
-pub use process::Rusage;
+pub use preempt::{
+    clear_need_resched, cond_resched, need_resched, preempt_count, preemptible, set_need_resched,
+};
+pub use process::{Credentials, RLimit, Rusage, RLIM_INFINITY};
 pub use processor::{
     current_kstack_top, current_process, current_task, current_trap_cx, current_trap_cx_user_va,
-    current_user_token, run_tasks, schedule, take_current_task,
+    current_user_token, run_tasks, schedule, take_current_task, try_current_task,
 };
+pub use ptrace::{ptrace_breakpoint, ptrace_stop_if_needed, PtraceState};
 
 use crate::fs::{open_initproc, OpenFlags};
 use crate::hal::shutdown;
+use crate::mm::MapPermission;
 use crate::task::pid::IDLE_PID;
 pub use crate::task::process::{ProcessControlBlock, ProcessControlBlockInner};
 use crate::task::task::TaskUserRes;
-pub use signal::SignalFlags;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use signal::{SignalFlags, SignalStack, MINSIGSTKSZ, SS_AUTODISARM, SS_DISABLE, SS_ONSTACK};
+pub use task::{TaskControlBlock, TaskName, TaskStatus, TASK_COMM_LEN};
+pub use wstatus::WaitStatus;
 
 /// 挂起当前任务并运行下一个任务
 ///
@@ -110,6 +150,36 @@ pub fn block_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+/// 可被信号打断地阻塞当前任务并调度下一任务，返回时有待处理的信号则返回 `true`
+///
+/// 已有待处理的信号时不阻塞，直接返回 `true`。`ready` 表示等待的事件已经发生，
+/// 在屏蔽中断后检查：为真时不阻塞，只让出一次，因此检查之后才到期的定时器一定能唤醒任务。
+/// 被打断时调用者负责撤销自己登记的唤醒源（如定时器）
+pub fn block_current_interruptible(ready: impl Fn() -> bool) -> bool {
+    if signal_pending_of_current() {
+        return true;
+    }
+    let task = take_current_task().unwrap();
+    // 持有 TCB 期间中断被屏蔽
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    if ready() {
+        task_inner.task_status = TaskStatus::Ready;
+        drop(task_inner);
+        add_task(task);
+    } else {
+        task_inner.task_status = TaskStatus::Blocked;
+        task_inner.interruptible = true;
+        drop(task_inner);
+        drop(task);
+    }
+    schedule(task_cx_ptr);
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().interruptible = false;
+    drop(task);
+    signal_pending_of_current()
+}
+
 /// 退出当前任务并运行下一任务
 ///
 /// - 记录退出码，释放用户资源
@@ -120,6 +190,11 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     let mut task_inner = task.inner_exclusive_access();
     let process = task.process.upgrade().unwrap();
     let tid = task_inner.res.as_ref().unwrap().tid;
+    // 线程组退出时，所有线程都以线程组的退出码退出
+    let exit_code = process
+        .inner_exclusive_access()
+        .group_exit_code
+        .unwrap_or(exit_code);
     // record exit code
     task_inner.exit_code = Some(exit_code);
     task_inner.res = None;
@@ -127,16 +202,16 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     // it will be deallocated when sys_waittid is called
     drop(task_inner);
     drop(task);
+    #[cfg(feature = "deadlock_detect")]
+    process.inner_exclusive_access().deadlock.remove_thread(tid);
     // however, if this is the main thread of current process
     // the process should terminate at once
     if tid == 0 {
         let pid = process.getpid();
         if pid == IDLE_PID {
-            println!(
-                "[kernel] Idle process exit with exit_code {} ...",
-                exit_code
-            );
-            if exit_code != 0 {
+            let status = WaitStatus::decode(exit_code);
+            println!("[kernel] Idle process exit with {:?} ...", status);
+            if status != WaitStatus::Exited(0) {
                 //crate::sbi::shutdown(255); //255 == -1 for err hint
                 shutdown();
             } else {
@@ -145,17 +220,31 @@ pub fn exit_current_and_run_next(exit_code: i32) {
             }
         }
         remove_from_pid2process(pid);
+        crate::syscall::untrace(pid);
         let mut process_inner = process.inner_exclusive_access();
         // mark this process as a zombie process
         process_inner.is_zombie = true;
         // record exit code of main process
         process_inner.exit_code = exit_code;
+        // 计入最后一次进入内核后的系统态时间，父进程回收时累加到它的子进程时间
+        process_inner.update_process_times_exit();
+        process_inner.wake_vfork_waiter();
+        if let Some(timer) = process_inner.itimer_real.take() {
+            timer.cancel();
+        }
 
         {
             // move all child processes under init process
             let mut initproc_inner = INITPROC.inner_exclusive_access();
             for child in process_inner.children.iter() {
-                child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+                let mut child_inner = child.inner_exclusive_access();
+                child_inner.parent = Some(Arc::downgrade(&INITPROC));
+                // 跟踪者退出，解除跟踪，停止中的子进程继续运行
+                if child_inner.ptrace.traced {
+                    let token = child_inner.memory_set.token();
+                    child_inner.ptrace.detach(token, None);
+                }
+                drop(child_inner);
                 initproc_inner.children.push(child.clone());
             }
         }
@@ -183,6 +272,8 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         process_inner.memory_set.recycle_data_pages();
         // drop file descriptors
         process_inner.fd_table.clear();
+        crate::fs::release_process_locks(pid);
+        process_inner.cloexec_fds.clear();
         // Remove all tasks except for the main thread itself.
         // This is because we are still using the kstack under the TCB
         // of the main thread. This TCB, including its kstack, will be
@@ -197,30 +288,143 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     schedule(&mut _unused as *mut _);
 }
 
+/// 结束当前线程组并运行下一任务
+///
+/// - 第一次调用时记录线程组的退出码，之后的调用沿用它
+/// - 唤醒阻塞的其余线程，它们在返回用户态前以同一退出码退出
+/// - 当前线程随即退出；主线程退出时回收整个进程
+pub fn exit_group_and_run_next(exit_code: i32) {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let mut process_inner = process.inner_exclusive_access();
+    let exit_code = *process_inner.group_exit_code.get_or_insert(exit_code);
+    let siblings: Vec<_> = process_inner
+        .tasks
+        .iter()
+        .flatten()
+        .filter(|sibling| !Arc::ptr_eq(sibling, &task))
+        .cloned()
+        .collect();
+    drop(process_inner);
+    drop(process);
+    drop(task);
+    for sibling in siblings {
+        wake_blocked(sibling);
+    }
+    exit_current_and_run_next(exit_code);
+}
+
 lazy_static! {
     /// 系统初始化进程 PCB
     pub static ref INITPROC: Arc<ProcessControlBlock> = {
-        let inode = open_initproc(OpenFlags::RDONLY).unwrap();  // 已仅读模式打开 initproc 文件
+        let path = crate::boot::init_path();
+        let inode = open_initproc(&path, OpenFlags::RDONLY)
+            .unwrap_or_else(|| panic!("cannot open init {}", path));  // 已仅读模式打开初始进程文件
         let v = inode.read_all();   // 读取 initproc 文件的全部内容到内存中
-        ProcessControlBlock::new(v.as_slice())  // 创建 initproc 进程控制块
+        let process = ProcessControlBlock::new(v.as_slice());  // 创建 initproc 进程控制块
+        process.inner_exclusive_access().get_task(0).inner_exclusive_access().name =
+            TaskName::from_path(&path);
+        // 自动运行列表中的每条命令作为一个参数，由初始进程依次运行
+        let autorun = read_autorun();
+        if !autorun.is_empty() {
+            let mut args = vec![path];
+            args.extend(autorun);
+            process.exec(v.as_slice(), args);
+        }
+        process
     };
 }
 
+/// 读取自动运行列表：每行一条命令，忽略空行与 `#` 开头的注释，文件不存在时为空
+fn read_autorun() -> Vec<String> {
+    let path = crate::boot::autorun_path();
+    let Some(inode) = open_initproc(&path, OpenFlags::RDONLY) else {
+        return Vec::new();
+    };
+    let commands: Vec<String> = String::from_utf8_lossy(&inode.read_all())
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+    println!(
+        "[kernel] autorun: {} command(s) from {}",
+        commands.len(),
+        path
+    );
+    commands
+}
+
 /// 将 INITPROC 添加到系统中
 pub fn add_initproc() {
     let _initproc = INITPROC.clone(); // 提前克隆 INITPROC，确保其在后续使用中不会被释放
 }
 
-/// 检查当前进程的信号
-pub fn check_signals_of_current() -> Option<(i32, &'static str)> {
+/// 检查没有被当前线程屏蔽的信号，返回致命信号的编号与说明；发给当前线程的信号先于发给进程的信号
+pub fn check_signals_of_current() -> Option<(usize, &'static str)> {
+    let (mask, pending) = {
+        let task = current_task().unwrap();
+        let inner = task.inner_exclusive_access();
+        (inner.signal_mask, inner.pending)
+    };
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    process_inner.signals.check_error()
+    (pending - mask)
+        .check_error()
+        .or_else(|| (process_inner.signals - mask).check_error())
+}
+
+/// 当前进程是否有会打断阻塞系统调用的信号：没有被屏蔽的会终止进程的信号，或线程组正在退出
+///
+/// 还没有用户信号处理函数，不会终止进程的信号（如 `SIGALRM`、`SIGCHLD`）不打断系统调用；
+/// 内核线程不接收信号
+pub fn signal_pending_of_current() -> bool {
+    let task = current_task().unwrap();
+    let (mask, pending) = {
+        let inner = task.inner_exclusive_access();
+        (inner.signal_mask, inner.pending)
+    };
+    let Some(process) = task.process.upgrade() else {
+        return false;
+    };
+    drop(task);
+    let process_inner = process.inner_exclusive_access();
+    ((process_inner.signals | pending) - mask)
+        .check_error()
+        .is_some()
+        || process_inner.group_exit_code.is_some()
+}
+
+/// 把当前线程的信号屏蔽字替换为 `mask`，返回原来的屏蔽字；`SIGKILL` 与 `SIGSTOP` 不能被屏蔽
+pub fn set_signal_mask_of_current(mask: SignalFlags) -> SignalFlags {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    core::mem::replace(&mut inner.signal_mask, mask - SignalFlags::UNBLOCKABLE)
+}
+
+/// 当前线程组正在退出时返回其退出码
+pub fn check_group_exit_of_current() -> Option<i32> {
+    current_process().inner_exclusive_access().group_exit_code
 }
 
 /// 向当前进程添加信号
 pub fn current_add_signal(signal: SignalFlags) {
     let process = current_process();
     let mut process_inner = process.inner_exclusive_access();
-    process_inner.signals |= signal;
+    process_inner.add_signal(signal);
+}
+
+/// 在当前进程的地址空间中处理 `va` 上的缺页，`token` 须为当前进程的页表
+///
+/// 当前进程的 PCB 正被借用（如在持有它的系统调用中翻译用户地址）时不做处理，返回 `false`
+pub fn current_handle_page_fault(token: usize, va: usize, access: MapPermission) -> bool {
+    let process = match try_current_task().and_then(|task| task.process.upgrade()) {
+        Some(process) => process,
+        None => return false,
+    };
+    let mut inner = match process.try_inner_exclusive_access() {
+        Some(inner) => inner,
+        None => return false,
+    };
+    inner.memory_set.token() == token && inner.memory_set.handle_page_fault(va, access)
 }
@@ -0,0 +1,66 @@
+//! # 内核态抢占
+//!
+//! ## Overview
+//! 系统调用在内核中运行时，时钟中断不直接切换任务，只设置 `need_resched` 标志，
+//! 任务在安全点检查标志并让出处理器：
+//! - 从系统调用或中断返回用户态之前（`trap_handler` 的返回路径）
+//! - 长时间运行的内核循环中显式调用的 `cond_resched`：
+//!   `OSInode::read_all` 的分块读取、fork 时地址空间页的复制
+//!
+//! ## Design
+//! - 抢占计数即屏蔽中断的嵌套层数：持有 `UPIntrFreeCell` / `SpinMutex` 时中断被屏蔽，
+//!   计数不为零时 `cond_resched` 不让出处理器，因此在持锁的代码中调用 `cond_resched`
+//!   是安全的空操作，而不会在持锁时切换任务；需要禁止抢占的代码持有这类锁即可
+//! - 时间片用完（`slice_expired`）与 `need_resched` 任意一个成立即让出处理器，
+//!   中断被屏蔽期间到期的时间片不会丢失
+//! - 调度器切换到新任务之前清除 `need_resched`
+//!
+//! ## Assumptions
+//! - 单处理器：重新调度的标志是全局的，其他 hart 上线后需要改为每个 hart 一份
+//! - 中断处理程序不调用 `cond_resched`；陷入时硬件清除了中断使能，但屏蔽层数仍为零
+//!
+//! ## Invariants
+//! - 返回用户态时抢占计数为零
+//! - `cond_resched` 只在没有持有任何锁、且存在当前任务时切换任务
+
+use super::{suspend_current_and_run_next, try_current_task};
+use crate::hal::INTR_MASKING_INFO;
+use crate::timer::slice_expired;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 时钟中断请求在下一个安全点重新调度
+static NEED_RESCHED: AtomicBool = AtomicBool::new(false);
+
+/// 当前的抢占计数，即屏蔽中断的锁的嵌套层数
+pub fn preempt_count() -> usize {
+    INTR_MASKING_INFO.get_mut().nested_level()
+}
+
+/// 请求在下一个安全点重新调度，可以在中断处理程序中调用
+pub fn set_need_resched() {
+    NEED_RESCHED.store(true, Ordering::Relaxed);
+}
+
+/// 清除重新调度的请求，调度器切换到新任务之前调用
+pub fn clear_need_resched() {
+    NEED_RESCHED.store(false, Ordering::Relaxed);
+}
+
+/// 是否需要在下一个安全点重新调度
+pub fn need_resched() -> bool {
+    NEED_RESCHED.load(Ordering::Relaxed) || slice_expired()
+}
+
+/// 当前是否可以切换任务：抢占计数为零，即没有屏蔽中断的锁被持有
+pub fn preemptible() -> bool {
+    preempt_count() == 0
+}
+
+/// 安全点：需要重新调度且当前可以切换任务时让出处理器，返回是否让出了处理器
+pub fn cond_resched() -> bool {
+    if !need_resched() || !preemptible() || try_current_task().is_none() {
+        return false;
+    }
+    suspend_current_and_run_next();
+    true
+}
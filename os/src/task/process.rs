@@ -41,6 +41,7 @@
 //! - `fork`：
 //!   - 完全复制父进程内存空间（包括用户栈/ trap_cx）
 //!   - 复制文件描述符表
+//!   - 继承身份凭据（uid / gid）
 //!   - 分配新 PID 和内核栈
 //!   - 将子进程加入父进程 children 列表
 //! - `alloc_fd` / `alloc_tid` / `dealloc_tid`：
@@ -48,20 +49,35 @@
 //! - 任务访问：通过 `get_task(tid)` 获取特定线程
 
 use crate::fs::inode::OSInode;
-use crate::fs::{current_root_inode, File, Stdin, Stdout};
-use crate::hal::{trap_handler, PageTableImpl, TrapContext, UserStackBase};
+use crate::fs::{current_root_inode, File, Stdin, Stdout, DEFAULT_UMASK};
+use crate::hal::{trap_handler, PageTableImpl, TrapContext, UserStackBase, PAGE_SIZE};
 use crate::mm::{translated_refmut, MemorySet, KERNEL_SPACE};
+use crate::random::fill_random;
+#[cfg(feature = "deadlock_detect")]
+use crate::sync::ResourceTracker;
 use crate::sync::{Condvar, Mutex, Semaphore, UPIntrFreeCell, UPIntrRefMut};
 use crate::syscall::CloneFlags;
-use crate::task::manager::{add_task, insert_into_pid2process};
+use crate::task::jobctl::JobControl;
+use crate::task::manager::{add_task, insert_into_pid2process, wake_blocked};
 use crate::task::pid::{pid_alloc, PidHandle, RecycleAllocator};
-use crate::task::signal::SignalFlags;
+use crate::task::ptrace::PtraceState;
+use crate::task::signal::{SignalFlags, SignalStack};
 use crate::task::task::TaskControlBlock;
-use crate::timer::{ITimerVal, TimeVal};
+use crate::task::tls::TlsTemplate;
+use crate::timer::{get_time_ms, ITimerVal, TimeVal, Timer, USEC_PER_MSEC};
+use alloc::collections::BTreeSet;
 use alloc::string::{String, ToString};
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+/// execve 时压入用户栈的辅助向量类型
+const AT_NULL: usize = 0;
+const AT_PAGESZ: usize = 6;
+const AT_RANDOM: usize = 25;
+/// AT_RANDOM 指向的随机字节数
+const AT_RANDOM_BYTES: usize = 16;
 
 /// 进程控制块
 ///
@@ -85,16 +101,40 @@ pub struct ProcessControlBlockInner {
     //由于fat32每次打开都会开一个新inode，所以需要记录当前的inode是什么
     pub cwd_inode: Arc<dyn File + Send + Sync>,
     pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    /// 设置了 FD_CLOEXEC 的文件描述符，exec 时关闭
+    pub cloexec_fds: BTreeSet<usize>,
     pub signals: SignalFlags,
     pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
     pub task_res_allocator: RecycleAllocator,
     pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
     pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
     pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// 用户态互斥锁与信号量的分配表，用于死锁检测
+    #[cfg(feature = "deadlock_detect")]
+    pub deadlock: ResourceTracker,
     pub rusage: Rusage,
     pub clock: ProcClock,
-    pub timer: ITimerVal,
+    /// ITIMER_REAL 的定时器，到期时发送 SIGALRM
+    pub itimer_real: Option<Timer>,
+    /// ITIMER_REAL 的重复间隔，为零时只触发一次
+    pub itimer_interval: TimeVal,
     pub tgid: usize,
+    /// 进程组 ID：没有父进程的进程自成一组，fork 出的子进程继承父进程的进程组
+    pub pgid: usize,
+    pub cred: Credentials,
+    /// 文件创建掩码，新建文件与目录的权限为 `mode & !umask`
+    pub umask: u32,
+    pub ptrace: PtraceState,
+    /// 作业控制的停止与继续状态
+    pub jobctl: JobControl,
+    /// 以 CLONE_VFORK 创建本进程后阻塞的父线程，本进程 exec 或退出时唤醒
+    pub vfork_waiter: Option<Arc<TaskControlBlock>>,
+    /// 线程组正在退出时的退出码，由第一个发起 exit_group 的线程决定
+    pub group_exit_code: Option<i32>,
+    /// core 文件大小的限制（RLIMIT_CORE），fork 时继承，exec 时保留
+    pub core_limit: RLimit,
+    /// 文件描述符数目的限制（RLIMIT_NOFILE），新的描述符必须小于软限制
+    pub nofile_limit: RLimit,
 }
 
 impl ProcessControlBlock {
@@ -103,6 +143,11 @@ impl ProcessControlBlock {
         self.inner.exclusive_access()
     }
 
+    /// 尝试获取 PCB 内部独占访问，已被借用时返回 `None`
+    pub fn try_inner_exclusive_access(&self) -> Option<UPIntrRefMut<'_, ProcessControlBlockInner>> {
+        self.inner.try_exclusive_access()
+    }
+
     /// 创建新进程
     ///
     /// ## Parameters
@@ -113,6 +158,7 @@ impl ProcessControlBlock {
     pub fn new(elf_data: &[u8]) -> Arc<Self> {
         // memory_set with elf program headers/trampoline/trap context/user stack
         let (memory_set, entry_point) = MemorySet::from_elf(elf_data);
+        let token = memory_set.token();
         // allocate a pid
         let pid_handle = pid_alloc();
         let pid = pid_handle.0;
@@ -137,16 +183,29 @@ impl ProcessControlBlock {
                         // 2 -> stderr
                         Some(Arc::new(Stdout)),
                     ],
+                    cloexec_fds: BTreeSet::new(),
                     signals: SignalFlags::empty(),
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
+                    #[cfg(feature = "deadlock_detect")]
+                    deadlock: ResourceTracker::default(),
                     rusage: Rusage::new(),
                     clock: ProcClock::new(),
-                    timer: ITimerVal::new(),
+                    itimer_real: None,
+                    itimer_interval: TimeVal::new(),
                     tgid,
+                    pgid: pid,
+                    cred: Credentials::root(),
+                    umask: DEFAULT_UMASK,
+                    ptrace: PtraceState::default(),
+                    jobctl: JobControl::default(),
+                    vfork_waiter: None,
+                    group_exit_code: None,
+                    core_limit: RLimit::DEFAULT_CORE,
+                    nofile_limit: RLimit::DEFAULT_NOFILE,
                 })
             },
         });
@@ -161,9 +220,14 @@ impl ProcessControlBlock {
         // 初始化 trap context
         let task_inner = task.inner_exclusive_access();
         let trap_cx = task_inner.get_trap_cx();
-        let ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
+        let mut ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
         let kstack_top = task.kstack.get_top();
         drop(task_inner);
+        // 线程局部存储块放在用户栈的最高处
+        let tp = TlsTemplate::parse(elf_data).and_then(|tls| tls.install(token, ustack_top));
+        if let Some(tp) = tp {
+            ustack_top = tp & !0xf;
+        }
         *trap_cx = TrapContext::app_init_context(
             entry_point,
             ustack_top,
@@ -171,6 +235,7 @@ impl ProcessControlBlock {
             kstack_top,
             trap_handler as usize,
         );
+        trap_cx.set_tp(tp.unwrap_or(0));
 
         // 添加线程到 PCB
         let mut process_inner = process.inner_exclusive_access();
@@ -186,11 +251,13 @@ impl ProcessControlBlock {
     /// 执行新程序（仅支持单线程进程）
     pub fn exec(self: &Arc<Self>, elf_data: &[u8], args: Vec<String>) {
         assert_eq!(self.inner_exclusive_access().thread_count(), 1);
+        self.inner_exclusive_access().close_on_exec();
         // 通过 ELF 数据创建新的地址空间，获得新的用户栈基址和程序入口点
         let (memory_set, entry_point) = MemorySet::from_elf(elf_data);
         let new_token = memory_set.token();
-        // 更新进程地址空间
+        // 更新进程地址空间，此后 vfork 的父进程可以继续运行
         self.inner_exclusive_access().memory_set = memory_set;
+        self.inner_exclusive_access().wake_vfork_waiter();
 
         // 因为地址空间已经更改，需要重新为主线程分配用户资源
         let task = self.inner_exclusive_access().get_task(0);
@@ -201,31 +268,51 @@ impl ProcessControlBlock {
         // 分配用户资源（用户栈 + trap 上下文）
         task_inner.res.as_mut().unwrap().alloc_user_res();
         task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
-        // 把参数压入用户栈
+        // 旧的备用栈不在新地址空间中
+        task_inner.sigaltstack = SignalStack::disabled();
+        // 按 Linux 约定构造初始用户栈（自高地址向低地址）：
+        // TLS 块 | 参数字符串 | AT_RANDOM 的 16 字节 | auxv | envp | argv | argc <- sp
+        // 用户栈刚刚映射为用户可写，写入不会失败
         let mut user_sp = task_inner.res.as_mut().unwrap().ustack_top();
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    new_token,
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
+        let tp = TlsTemplate::parse(elf_data).and_then(|tls| tls.install(new_token, user_sp));
+        if let Some(tp) = tp {
+            user_sp = tp;
+        }
+        let mut arg_ptrs: Vec<usize> = Vec::with_capacity(args.len());
+        for arg in args.iter() {
+            user_sp -= arg.len() + 1;
+            arg_ptrs.push(user_sp);
             let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(new_token, p as *mut u8) = *c;
+            for c in arg.as_bytes() {
+                *translated_refmut(new_token, p as *mut u8).unwrap() = *c;
                 p += 1;
             }
-            *translated_refmut(new_token, p as *mut u8) = 0;
+            *translated_refmut(new_token, p as *mut u8).unwrap() = 0;
         }
-        // 让 user_sp 对齐到 8 字节（k210 平台要求）
-        user_sp -= user_sp % core::mem::size_of::<usize>();
+        // AT_RANDOM 指向的 16 字节随机数，供 libc 初始化栈保护与指针加密
+        user_sp -= AT_RANDOM_BYTES;
+        let random_ptr = user_sp;
+        let mut random = [0u8; AT_RANDOM_BYTES];
+        fill_random(&mut random);
+        for (i, b) in random.iter().enumerate() {
+            *translated_refmut(new_token, (random_ptr + i) as *mut u8).unwrap() = *b;
+        }
+        let auxv = [(AT_PAGESZ, PAGE_SIZE), (AT_RANDOM, random_ptr), (AT_NULL, 0)];
+        let mut stack: Vec<usize> = vec![args.len()];
+        stack.extend_from_slice(&arg_ptrs);
+        stack.push(0); // argv 结尾
+        stack.push(0); // envp 结尾
+        for (key, value) in auxv {
+            stack.push(key);
+            stack.push(value);
+        }
+        // sp 需按 16 字节对齐
+        user_sp = (user_sp - stack.len() * core::mem::size_of::<usize>()) & !0xf;
+        for (i, value) in stack.iter().enumerate() {
+            let addr = user_sp + i * core::mem::size_of::<usize>();
+            *translated_refmut(new_token, addr as *mut usize).unwrap() = *value;
+        }
+        let argv_base = user_sp + core::mem::size_of::<usize>();
         // 初始化 trap 上下文
         let mut trap_cx = TrapContext::app_init_context(
             entry_point,
@@ -236,6 +323,7 @@ impl ProcessControlBlock {
         );
         trap_cx.general_regs.a0 = args.len();
         trap_cx.general_regs.a1 = argv_base;
+        trap_cx.set_tp(tp.unwrap_or(0));
         *task_inner.get_trap_cx() = trap_cx;
     }
 
@@ -327,8 +415,8 @@ impl ProcessControlBlock {
         let mut parent = self.inner_exclusive_access();
         assert_eq!(parent.thread_count(), 1);
         // clone parent's memory_set completely including trampoline/ustacks/trap_cxs
-        let memory_set = MemorySet::from_existed_user(&parent.memory_set);
-        let mut memory_set = memory_set;
+        // 页的内容在释放父进程的锁之后复制，见下文
+        let (mut memory_set, page_copies) = MemorySet::from_existed_user(&parent.memory_set);
         memory_set.heap_start = parent.memory_set.heap_start;
         memory_set.brk = parent.memory_set.brk;
         // alloc a pid
@@ -356,22 +444,36 @@ impl ProcessControlBlock {
                     cwd_inode: parent.cwd_inode.clone(),
                     cwd: parent.cwd.clone(),
                     fd_table: new_fd_table,
+                    cloexec_fds: parent.cloexec_fds.clone(),
                     signals: SignalFlags::empty(),
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
+                    #[cfg(feature = "deadlock_detect")]
+                    deadlock: ResourceTracker::default(),
                     rusage: Rusage::new(),
                     clock: ProcClock::new(),
-                    timer: ITimerVal::new(),
+                    itimer_real: None,
+                    itimer_interval: TimeVal::new(),
                     tgid,
+                    pgid: parent.pgid,
+                    cred: parent.cred,
+                    umask: parent.umask,
+                    ptrace: PtraceState::default(),
+                    jobctl: JobControl::default(),
+                    vfork_waiter: None,
+                    group_exit_code: None,
+                    core_limit: parent.core_limit,
+                    nofile_limit: parent.nofile_limit,
                 })
             },
         });
         // add child
         parent.children.push(Arc::clone(&child));
         let parent_task = parent.get_task(0);
+        drop(parent);
 
         let (ustack_base, ustack_top) = {
             let task_inner = parent_task.inner_exclusive_access();
@@ -381,13 +483,7 @@ impl ProcessControlBlock {
         // create main thread of child process
         let task = Arc::new(TaskControlBlock::new(
             Arc::clone(&child),
-            parent
-                .get_task(0)
-                .inner_exclusive_access()
-                .res
-                .as_ref()
-                .unwrap()
-                .ustack_base(),
+            ustack_base,
             // here we do not allocate trap_cx or ustack again
             // but mention that we allocate a new kstack here
             false,
@@ -396,11 +492,22 @@ impl ProcessControlBlock {
         let mut child_inner = child.inner_exclusive_access();
         child_inner.tasks.push(Some(Arc::clone(&task)));
         drop(child_inner);
+        // 不持有任何锁时复制页的内容，复制大的地址空间时可以被抢占；
+        // 父进程只有这一个线程，复制期间不会修改自己的内存，子进程还没有加入调度器
+        page_copies.run();
         // modify kstack_top in trap_cx of this thread
-        let task_inner = task.inner_exclusive_access();
+        let mut task_inner = task.inner_exclusive_access();
         let trap_cx = task_inner.get_trap_cx();
         trap_cx.kernel_sp = task.kstack.get_top();
+        // 地址空间是复制的，备用栈仍然有效
+        let parent_inner = parent_task.inner_exclusive_access();
+        task_inner.sigaltstack = parent_inner.sigaltstack;
+        task_inner.signal_mask = parent_inner.signal_mask;
+        task_inner.name = parent_inner.name;
+        drop(parent_inner);
         drop(task_inner);
+        let affinity = parent_task.cpu_affinity.load(Ordering::Relaxed);
+        task.cpu_affinity.store(affinity, Ordering::Relaxed);
         insert_into_pid2process(child.getpid(), Arc::clone(&child));
         // add this thread to scheduler
         add_task(task);
@@ -419,13 +526,50 @@ impl ProcessControlBlockInner {
         self.memory_set.token()
     }
 
-    /// 分配新的文件描述符
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
+    /// 文件描述符号的上限（不含），即 RLIMIT_NOFILE 的软限制
+    pub fn fd_limit(&self) -> usize {
+        self.nofile_limit.rlim_cur.min(usize::MAX as u64) as usize
+    }
+
+    /// 分配新的文件描述符，没有小于 `fd_limit` 的空闲描述符时返回 `None`（EMFILE）
+    pub fn alloc_fd(&mut self) -> Option<usize> {
+        let limit = self.fd_limit();
+        let free = (0..self.fd_table.len().min(limit)).find(|fd| self.fd_table[*fd].is_none());
+        let fd = match free {
+            Some(fd) => fd,
+            None if self.fd_table.len() < limit => {
+                self.fd_table.push(None);
+                self.fd_table.len() - 1
+            }
+            None => return None,
+        };
+        // 新分配的描述符默认不设置 FD_CLOEXEC
+        self.cloexec_fds.remove(&fd);
+        Some(fd)
+    }
+
+    /// 设置或清除 `fd` 的 FD_CLOEXEC 标志
+    pub fn set_cloexec(&mut self, fd: usize, cloexec: bool) {
+        if cloexec {
+            self.cloexec_fds.insert(fd);
         } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
+            self.cloexec_fds.remove(&fd);
+        }
+    }
+
+    /// 关闭所有设置了 FD_CLOEXEC 的文件描述符
+    pub fn close_on_exec(&mut self) {
+        for fd in core::mem::take(&mut self.cloexec_fds) {
+            if let Some(file) = self.fd_table.get_mut(fd) {
+                *file = None;
+            }
+        }
+    }
+
+    /// 唤醒以 CLONE_VFORK 创建本进程后阻塞的父线程
+    pub fn wake_vfork_waiter(&mut self) {
+        if let Some(task) = self.vfork_waiter.take() {
+            wake_blocked(task);
         }
     }
 
@@ -448,8 +592,55 @@ impl ProcessControlBlockInner {
     pub fn get_task(&self, tid: usize) -> Arc<TaskControlBlock> {
         self.tasks[tid].as_ref().unwrap().clone()
     }
+    /// 添加信号，会终止进程的信号同时唤醒可被打断地阻塞、且没有屏蔽该信号的线程，使其尽快返回 EINTR
     pub fn add_signal(&mut self, signal: SignalFlags) {
+        self.jobctl.on_signal(&mut self.signals, signal);
         self.signals.insert(signal);
+        if signal.check_error().is_none() {
+            return;
+        }
+        for task in self.tasks.iter().flatten() {
+            let interrupt = {
+                let inner = task.inner_exclusive_access();
+                inner.interruptible && (signal - inner.signal_mask).check_error().is_some()
+            };
+            if interrupt {
+                wake_blocked(task.clone());
+            }
+        }
+    }
+
+    /// 向线程 `tid` 发送信号，线程不存在时返回 `false`
+    ///
+    /// `SIGKILL` 与停止、继续信号作用于整个进程，按发给进程处理；
+    /// 其他信号记入该线程的待处理集合，只打断该线程可被信号打断的阻塞
+    pub fn add_thread_signal(&mut self, tid: usize, signal: SignalFlags) -> bool {
+        let Some(Some(task)) = self.tasks.get(tid).cloned() else {
+            return false;
+        };
+        let process_wide = SignalFlags::SIGKILL | SignalFlags::SIGCONT | SignalFlags::STOP_SIGNALS;
+        if signal.intersects(process_wide) {
+            self.add_signal(signal);
+            return true;
+        }
+        let interrupt = {
+            let mut inner = task.inner_exclusive_access();
+            inner.pending.insert(signal);
+            inner.interruptible && (signal - inner.signal_mask).check_error().is_some()
+        };
+        if interrupt {
+            wake_blocked(task);
+        }
+        true
+    }
+
+    /// 唤醒可被信号打断地阻塞的线程，由它们自行撤销登记的唤醒源
+    pub fn interrupt_blocked_tasks(&self) {
+        for task in self.tasks.iter().flatten() {
+            if task.inner_exclusive_access().interruptible {
+                wake_blocked(task.clone());
+            }
+        }
     }
 
     /// 在进入陷阱时更新进程时间
@@ -466,7 +657,6 @@ impl ProcessControlBlockInner {
     /// 在离开陷阱时更新进程时间
     pub fn update_process_times_leave_trap(&mut self) {
         let now = TimeVal::now();
-        self.update_itimer_real_if_exists(now - self.clock.last_enter_u_mode);
         let diff = now - self.clock.last_enter_s_mode;
         // println!("DEBUG: diff={:?}, is_timer={}", diff, is_timer); // 调试日志
 
@@ -474,23 +664,62 @@ impl ProcessControlBlockInner {
 
         self.clock.last_enter_u_mode = now;
     }
-    /// 更新实时定时器
-    pub fn update_itimer_real_if_exists(&mut self, diff: TimeVal) {
-        // 如果当前定时器不为0
-        if !self.timer.it_value.is_zero() {
-            // 更新定时器
-            self.timer.it_value = self.timer.it_value - diff;
-            // 如果定时器为0
-            if self.timer.it_value.is_zero() {
-                // 添加信号
-                self.add_signal(SignalFlags::SIGALRM);
-                // 重置定时器
-                self.timer.it_value = self.timer.it_interval;
-            }
+    /// 进程退出时计入最后一次进入内核以来的系统态时间
+    pub fn update_process_times_exit(&mut self) {
+        let now = TimeVal::now();
+        self.rusage.ru_stime = self.rusage.ru_stime + (now - self.clock.last_enter_s_mode);
+        self.clock.last_enter_s_mode = now;
+    }
+    /// ITIMER_REAL 的当前设置，`it_value` 为距离下次到期的剩余时间
+    pub fn itimer_real(&self) -> ITimerVal {
+        let remaining_ms = match &self.itimer_real {
+            Some(timer) if !timer.fired() => timer.expire_ms().saturating_sub(get_time_ms()).max(1),
+            _ => 0,
+        };
+        ITimerVal {
+            it_interval: self.itimer_interval,
+            it_value: TimeVal::from_us(remaining_ms * USEC_PER_MSEC),
+        }
+    }
+}
+
+impl ProcessControlBlock {
+    /// 设置 ITIMER_REAL 并返回原先的设置，`it_value` 为零时停止定时器
+    pub fn set_itimer_real(self: &Arc<Self>, new: ITimerVal) -> ITimerVal {
+        let mut inner = self.inner_exclusive_access();
+        let old = inner.itimer_real();
+        if let Some(timer) = inner.itimer_real.take() {
+            timer.cancel();
+        }
+        inner.itimer_interval = new.it_interval;
+        if !new.it_value.is_zero() {
+            let expire_ms = get_time_ms() + timeval_to_ms(new.it_value);
+            inner.itimer_real = Some(arm_itimer_real(Arc::downgrade(self), expire_ms));
         }
+        old
     }
 }
 
+/// 向上取整到毫秒，非零的时间至少为 1 毫秒
+fn timeval_to_ms(time: TimeVal) -> usize {
+    time.to_us().div_ceil(USEC_PER_MSEC)
+}
+
+/// 在 `expire_ms` 向进程发送 SIGALRM，有重复间隔时到期后以上次的到期时间为基准再次设置
+fn arm_itimer_real(process: Weak<ProcessControlBlock>, expire_ms: usize) -> Timer {
+    Timer::call_at(expire_ms, move || {
+        let Some(process) = process.upgrade() else {
+            return;
+        };
+        let mut inner = process.inner_exclusive_access();
+        inner.add_signal(SignalFlags::SIGALRM);
+        let interval = inner.itimer_interval;
+        let next_ms = expire_ms + timeval_to_ms(interval);
+        inner.itimer_real =
+            (!interval.is_zero()).then(|| arm_itimer_real(Arc::downgrade(&process), next_ms));
+    })
+}
+
 #[allow(unused)]
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -512,6 +741,78 @@ impl Rusage {
         } 
     }
 }
+/// 进程身份凭据
+///
+/// `fork` 时原样继承，`execve` 时保留，执行 set-user-ID / set-group-ID 程序时切换有效身份
+#[derive(Clone, Copy)]
+pub struct Credentials {
+    /// 实际用户 / 组 ID
+    pub uid: u32,
+    pub gid: u32,
+    /// 有效用户 / 组 ID，用于权限检查
+    pub euid: u32,
+    pub egid: u32,
+    /// 保存的 set-user-ID / set-group-ID
+    pub suid: u32,
+    pub sgid: u32,
+}
+
+impl Credentials {
+    /// 初始进程以 root 身份运行
+    pub fn root() -> Self {
+        Self {
+            uid: 0,
+            gid: 0,
+            euid: 0,
+            egid: 0,
+            suid: 0,
+            sgid: 0,
+        }
+    }
+
+    pub fn is_privileged(&self) -> bool {
+        self.euid == 0
+    }
+
+    /// `execve` 时更新身份：`set_uid` / `set_gid` 为程序文件的 set-id 位对应的属主
+    pub fn on_exec(&mut self, set_uid: Option<u32>, set_gid: Option<u32>) {
+        if let Some(uid) = set_uid {
+            self.euid = uid;
+        }
+        if let Some(gid) = set_gid {
+            self.egid = gid;
+        }
+        self.suid = self.euid;
+        self.sgid = self.egid;
+    }
+}
+
+/// 没有限制
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// 资源限制，布局与用户态的 `struct rlimit` 一致
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RLimit {
+    /// 软限制，实际生效的值
+    pub rlim_cur: u64,
+    /// 硬限制，软限制的上限；只有特权进程可以提高
+    pub rlim_max: u64,
+}
+
+impl RLimit {
+    /// 与 Linux 相同，默认不生成 core 文件，但允许进程自行打开
+    pub const DEFAULT_CORE: Self = Self {
+        rlim_cur: 0,
+        rlim_max: RLIM_INFINITY,
+    };
+    /// 与 Linux 的默认值相同
+    pub const DEFAULT_NOFILE: Self = Self {
+        rlim_cur: 1024,
+        rlim_max: 4096,
+    };
+}
+
 #[repr(C)]
 /// 进程时钟
 /// 表示任务的时钟信息
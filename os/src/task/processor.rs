@@ -8,6 +8,9 @@
 //! - 系统中每个 CPU 核心对应一个全局 `Processor` 实例
 //! - `Processor` 记录当前正在运行的任务以及空闲任务的上下文
 //! - 调度器通过 `__switch` 在任务上下文与空闲上下文之间切换
+//! - 时间片按实际运行时间计算：切换到任务时从剩余的时间片设置结束时刻，切换出来时扣除运行的时间；
+//!   主动让出或阻塞时未用完的部分保留到下次运行，用完之后才分配新的完整时间片
+//! - 没有就绪任务时空闲循环以 `wfi` / `idle` 等待中断，由时钟或设备中断唤醒阻塞的任务
 //!
 //! # Concurrency Model
 //! - 本模块假定运行在单核环境（UP）或已禁用抢占的上下文中
@@ -26,11 +29,12 @@
 
 use crate::fs::inode::{OSInode, OpenFlags};
 use crate::fs::{open_dir, open_file};
-use crate::hal::{TrapContext, __switch};
+use crate::hal::{__switch, get_clock_freq, get_time, shutdown, wait_for_interrupt, TrapContext};
 use crate::sync::UPIntrFreeCell;
 use crate::task::manager::fetch_task;
 use crate::task::process::ProcessControlBlock;
-use crate::task::{TaskContext, TaskControlBlock, TaskStatus};
+use crate::task::{clear_need_resched, TaskContext, TaskControlBlock, TaskStatus, INITPROC};
+use crate::timer::{get_time_us, set_slice_end, timeslice_us, USEC_PER_SEC};
 use alloc::sync::Arc;
 use lazy_static::lazy_static;
 
@@ -119,6 +123,8 @@ lazy_static! {
 /// 当存在可运行任务时，CPU 会从空闲任务切换到该任务。
 pub fn run_tasks() {
     loop {
+        // 中断处理程序推迟的工作可能唤醒任务，在选择任务之前执行
+        crate::workqueue::run_pending_work();
         let mut processor = PROCESSOR.exclusive_access();
         if let Some(task) = fetch_task() {
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
@@ -126,10 +132,18 @@ pub fn run_tasks() {
             // SAFETY:
             // - 当前持有任务内部的独占访问权
             // - 返回的指针在任务状态切换前保持有效
-            let next_task_cx_ptr = task.inner.exclusive_session(|task_inner| {
+            let (next_task_cx_ptr, slice_us) = task.inner.exclusive_session(|task_inner| {
                 task_inner.task_status = TaskStatus::Running;
-                &task_inner.task_cx as *const TaskContext
+                if task_inner.slice_left_us == 0 {
+                    task_inner.slice_left_us = timeslice_us();
+                }
+                (
+                    &task_inner.task_cx as *const TaskContext,
+                    task_inner.slice_left_us,
+                )
             });
+            // 切换出来之后扣除运行的时间
+            let running = task.clone();
             processor.current = Some(task);
 
             // 在上下文切换前显式释放 Processor 的访问权
@@ -138,11 +152,24 @@ pub fn run_tasks() {
             // SAFETY:
             // - idle_task_cx_ptr 和 next_task_cx_ptr 均指向有效的 TaskContext
             // - 当前不会发生并发上下文切换
+            let start_us = get_time_us();
+            clear_need_resched();
+            set_slice_end(get_time() + slice_us * (get_clock_freq() / USEC_PER_SEC));
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            set_slice_end(usize::MAX);
+            let mut running_inner = running.inner_exclusive_access();
+            running_inner.slice_left_us = running_inner
+                .slice_left_us
+                .saturating_sub(get_time_us() - start_us);
         } else {
-            println!("No task to run, shutting down...");
+            drop(processor);
+            // 初始进程退出时已经关机，这里只防止之后空转
+            if INITPROC.inner_exclusive_access().is_zombie {
+                shutdown();
+            }
+            wait_for_interrupt();
         }
     }
 }
@@ -157,6 +184,11 @@ pub fn current_task() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.exclusive_access().current()
 }
 
+/// 与 `current_task` 相同，但处理器状态正被借用时返回 `None`（供 panic 处理使用）
+pub fn try_current_task() -> Option<Arc<TaskControlBlock>> {
+    PROCESSOR.try_exclusive_access()?.current()
+}
+
 /// 获得当前正在运行任务所属的进程 PCB 的引用
 pub fn current_process() -> Arc<ProcessControlBlock> {
     current_task().unwrap().process.upgrade().unwrap()
@@ -0,0 +1,203 @@
+//! # 进程跟踪（ptrace）
+//!
+//! ## Overview
+//! 记录进程的被跟踪状态，并实现被跟踪进程一侧的停止与恢复：
+//! - `PTRACE_TRACEME` 之后，进程在返回用户态前若有待处理的信号，先进入 ptrace 停止，
+//!   由跟踪者（父进程）通过 `wait4` 得知停止原因，再决定以何种信号恢复执行
+//! - 执行断点指令、`execve` 成功、单步完成时以 `SIGTRAP` 停止
+//! - 单步通过在下一条指令的所有可能地址写入断点指令实现（`hal::step`），
+//!   RISC-V 在 U 态没有可用的硬件单步；停止时撤销这些断点
+//!
+//! ## Assumptions
+//! - 只支持 `PTRACE_TRACEME` 模型，跟踪者总是父进程
+//! - 内核不会在信号“递送”后清除它：被跟踪者停止时取走该信号，跟踪者选择注入的信号重新置位后
+//!   记入 `passed`，本次返回用户态时不再上报；下一次返回用户态前，没有被屏蔽的注入信号
+//!   已经递送过，从待处理信号与 `passed` 中一并清除，之后再收到同一信号仍会上报
+//! - 停止中的进程通过让出处理器轮询恢复条件，与 `wait4` 的等待方式一致
+//!
+//! ## Invariants
+//! - `step_breakpoints` 非空当且仅当进程正在单步，且记录的地址上写着断点指令
+//! - `SIGKILL` 不会被拦截：待处理信号中有 `SIGKILL` 时不进入停止
+
+use super::process::ProcessControlBlockInner;
+use super::signal::SignalFlags;
+use super::{current_process, current_task, suspend_current_and_run_next};
+use crate::hal::{step, TrapContext};
+use crate::mm::{try_read_bytes, try_write_bytes};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const SIGTRAP: usize = 5;
+
+pub struct PtraceState {
+    /// 是否被父进程跟踪
+    pub traced: bool,
+    /// 处于 ptrace 停止时导致停止的信号
+    stop_signal: Option<usize>,
+    /// 本次停止是否已经通过 `wait4` 报告
+    reported: bool,
+    /// 返回用户态前需要以 `SIGTRAP` 停止
+    trap_pending: bool,
+    /// 恢复执行时注入的信号
+    inject: Option<SignalFlags>,
+    /// 已由跟踪者注入、尚未递送的信号，递送后清除
+    passed: SignalFlags,
+    /// 单步使用的临时断点：地址与被覆盖的原始指令
+    step_breakpoints: Vec<(usize, Vec<u8>)>,
+}
+
+impl Default for PtraceState {
+    fn default() -> Self {
+        Self {
+            traced: false,
+            stop_signal: None,
+            reported: false,
+            trap_pending: false,
+            inject: None,
+            passed: SignalFlags::empty(),
+            step_breakpoints: Vec::new(),
+        }
+    }
+}
+
+impl PtraceState {
+    /// 是否处于 ptrace 停止
+    pub fn is_stopped(&self) -> bool {
+        self.stop_signal.is_some()
+    }
+
+    /// 取出尚未报告给跟踪者的停止信号
+    pub fn take_unreported_stop(&mut self) -> Option<usize> {
+        if self.reported {
+            return None;
+        }
+        let signum = self.stop_signal?;
+        self.reported = true;
+        Some(signum)
+    }
+
+    /// 让停止中的进程恢复执行，`signal` 为恢复后递送给它的信号
+    pub fn resume(&mut self, signal: Option<SignalFlags>) {
+        self.inject = signal.filter(|signal| !signal.is_empty());
+        self.stop_signal = None;
+    }
+
+    /// 在 `cx` 所示指令的所有后继地址写入断点后恢复执行
+    pub fn single_step(&mut self, token: usize, cx: &TrapContext, signal: Option<SignalFlags>) {
+        let fetch = |addr: usize| {
+            let mut half = [0u8; 2];
+            try_read_bytes(token, addr, &mut half).map(|_| u16::from_le_bytes(half))
+        };
+        for target in step::next_pcs(cx.pc(), fetch, |reg| cx.reg(reg))
+            .into_iter()
+            .flatten()
+        {
+            if self
+                .step_breakpoints
+                .iter()
+                .any(|(addr, _)| *addr == target)
+            {
+                continue;
+            }
+            let insn = match fetch(target) {
+                Some(low) => step::breakpoint_insn(step::insn_len(low)),
+                None => continue,
+            };
+            let mut original = vec![0u8; insn.len()];
+            if try_read_bytes(token, target, &mut original).is_some()
+                && try_write_bytes(token, target, insn).is_some()
+            {
+                self.step_breakpoints.push((target, original));
+            }
+        }
+        self.resume(signal);
+    }
+
+    /// 撤销单步断点，恢复原始指令
+    fn remove_step_breakpoints(&mut self, token: usize) {
+        while let Some((addr, original)) = self.step_breakpoints.pop() {
+            let _ = try_write_bytes(token, addr, &original);
+        }
+    }
+
+    /// 解除跟踪，停止中的进程随之恢复执行
+    pub fn detach(&mut self, token: usize, signal: Option<SignalFlags>) {
+        self.remove_step_breakpoints(token);
+        self.traced = false;
+        self.trap_pending = false;
+        self.passed = SignalFlags::empty();
+        self.resume(signal);
+    }
+
+    /// `execve` 成功后调用：旧地址空间中的断点随之失效，被跟踪时以 `SIGTRAP` 停止
+    pub fn on_exec(&mut self) {
+        self.step_breakpoints.clear();
+        self.trap_pending = self.traced;
+    }
+}
+
+/// 用户态执行到断点指令时调用
+///
+/// 被跟踪时撤销单步断点，并在返回用户态前以 `SIGTRAP` 停止；否则向进程发送 `SIGTRAP`
+pub fn ptrace_breakpoint() {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.ptrace.traced {
+        let token = inner.memory_set.token();
+        inner.ptrace.remove_step_breakpoints(token);
+        inner.ptrace.trap_pending = true;
+    } else {
+        inner.signals |= SignalFlags::SIGTRAP;
+    }
+}
+
+/// 返回用户态前调用：被跟踪进程有需要上报的事件时进入停止，直到跟踪者让它恢复执行
+pub fn ptrace_stop_if_needed() {
+    let mask = current_task().unwrap().inner_exclusive_access().signal_mask;
+    let process = current_process();
+    {
+        let mut guard = process.inner_exclusive_access();
+        let inner: &mut ProcessControlBlockInner = &mut guard;
+        if !inner.ptrace.traced || inner.signals.contains(SignalFlags::SIGKILL) {
+            return;
+        }
+        // 上次注入的信号在返回用户态时已经递送，默认动作为忽略的仍留在待处理信号中，在此丢弃；
+        // 被屏蔽的继续等待
+        let delivered = inner.ptrace.passed - mask;
+        inner.signals.remove(delivered);
+        inner.ptrace.passed.remove(delivered);
+        let signum = if core::mem::take(&mut inner.ptrace.trap_pending) {
+            SIGTRAP
+        } else {
+            let pending = inner.signals - inner.ptrace.passed;
+            if pending.is_empty() {
+                return;
+            }
+            let signum = pending.bits().trailing_zeros() as usize + 1;
+            inner
+                .signals
+                .remove(SignalFlags::from_signum(signum).unwrap());
+            signum
+        };
+        inner.ptrace.stop_signal = Some(signum);
+        inner.ptrace.reported = false;
+    }
+    loop {
+        suspend_current_and_run_next();
+        let mut guard = process.inner_exclusive_access();
+        let inner: &mut ProcessControlBlockInner = &mut guard;
+        if inner.signals.contains(SignalFlags::SIGKILL) {
+            inner.ptrace.stop_signal = None;
+            return;
+        }
+        if !inner.ptrace.is_stopped() {
+            if let Some(signal) = inner.ptrace.inject.take() {
+                inner.signals |= signal;
+                if inner.ptrace.traced {
+                    inner.ptrace.passed |= signal;
+                }
+            }
+            return;
+        }
+    }
+}
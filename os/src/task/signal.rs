@@ -25,9 +25,22 @@
 //! - `check_error`：
 //!   - 按固定优先级检查信号集合
 //!   - 返回第一个匹配的错误码与描述字符串
+//! - `STOP_SIGNALS`：默认动作为停止进程的信号，由作业控制（`jobctl`）处理
+//! - `SignalStack`：
+//!   - 线程的信号备用栈（`sigaltstack`）
+//!   - `handler_sp` 为带 `SA_ONSTACK` 的处理函数选择栈顶；还没有用户信号处理函数，由将来的投递路径调用
 
 use bitflags::*;
 
+/// `stack_t.ss_flags`：线程正在备用栈上运行（只出现在查询结果中）
+pub const SS_ONSTACK: i32 = 1;
+/// `stack_t.ss_flags`：备用栈被禁用
+pub const SS_DISABLE: i32 = 2;
+/// `stack_t.ss_flags`：进入处理函数时自动禁用备用栈，处理函数返回后恢复
+pub const SS_AUTODISARM: i32 = 1 << 31;
+/// 备用栈的最小字节数，与 Linux 一致
+pub const MINSIGSTKSZ: usize = 2048;
+
 bitflags! {
     /// 信号标志集合
     ///
@@ -40,20 +53,39 @@ bitflags! {
     ///   - 中断信号（通常由用户或外部事件触发）
     /// - `SIGILL`：
     ///   - 非法指令异常
+    /// - `SIGTRAP`：
+    ///   - 断点（未被跟踪的进程执行断点指令）
     /// - `SIGABRT`：
     ///   - 程序异常终止
+    /// - `SIGBUS`：
+    ///   - 总线错误（如无法模拟的非对齐访存）
     /// - `SIGFPE`：
     ///   - 算术错误（如除零）
+    /// - `SIGKILL`：
+    ///   - 强制结束（不能被跟踪者拦截）
     /// - `SIGSEGV`：
     ///   - 段错误（非法内存访问）
+    /// - `SIGCONT`：
+    ///   - 让停止的进程继续运行
+    /// - `SIGSTOP` / `SIGTSTP` / `SIGTTIN` / `SIGTTOU`：
+    ///   - 停止进程（作业控制），其中 `SIGSTOP` 不能被屏蔽
+    #[derive(Clone, Copy)]
     pub struct SignalFlags: u32 {
         const SIGINT    = 1 << 1;
         const SIGILL    = 1 << 3;
+        const SIGTRAP   = 1 << 4;
         const SIGABRT   = 1 << 5;
+        const SIGBUS    = 1 << 6;
         const SIGFPE    = 1 << 7;
+        const SIGKILL   = 1 << 8;
         const SIGSEGV   = 1 << 10;
         const SIGALRM	= 1 << 13;
         const SIGCHLD	= 1 << 16;
+        const SIGCONT   = 1 << 17;
+        const SIGSTOP   = 1 << 18;
+        const SIGTSTP   = 1 << 19;
+        const SIGTTIN   = 1 << 20;
+        const SIGTTOU   = 1 << 21;
         const SIGVTALRM	= 1 << 25;
         const SIGPROF	= 1 << 26;
     }
@@ -64,42 +96,58 @@ impl SignalFlags {
     ///
     /// ## Overview
     /// 按预定义的优先级顺序检查信号标志，
-    /// 若发现致命信号，则返回对应的错误码与说明信息。
+    /// 若发现致命信号，则返回对应的信号编号与说明信息。
     ///
     /// ## Returns
-    /// - `Some((code, message))`：
-    ///   - `code`：进程退出码（负数，符合类 Unix 约定）
+    /// - `Some((signum, message))`：
+    ///   - `signum`：信号编号，由调用者编码为 `WaitStatus::Signaled`
     ///   - `message`：静态错误描述字符串
     /// - `None`：
     ///   - 当前不存在致命信号
     ///
     /// ## Invariants
     /// - 同一时间仅返回一个错误
-    /// - 返回的错误码与信号类型一一对应
+    /// - 返回的信号编号与信号类型一一对应
     ///
     /// ## Behavior
     /// - 检查顺序即信号处理优先级：
-    ///     1. SIGINT
-    ///     2. SIGILL
-    ///     3. SIGABRT
-    ///     4. SIGFPE
-    ///     5. SIGSEGV
-    pub fn check_error(&self) -> Option<(i32, &'static str)> {
-        if self.contains(Self::SIGINT) {
-            Some((-2, "Killed, SIGINT=2"))
+    ///     1. SIGKILL
+    ///     2. SIGINT
+    ///     3. SIGILL
+    ///     4. SIGTRAP
+    ///     5. SIGABRT
+    ///     6. SIGBUS
+    ///     7. SIGFPE
+    ///     8. SIGSEGV
+    pub fn check_error(&self) -> Option<(usize, &'static str)> {
+        if self.contains(Self::SIGKILL) {
+            Some((9, "Killed, SIGKILL=9"))
+        } else if self.contains(Self::SIGINT) {
+            Some((2, "Killed, SIGINT=2"))
         } else if self.contains(Self::SIGILL) {
-            Some((-4, "Illegal Instruction, SIGILL=4"))
+            Some((4, "Illegal Instruction, SIGILL=4"))
+        } else if self.contains(Self::SIGTRAP) {
+            Some((5, "Trace/breakpoint trap, SIGTRAP=5"))
         } else if self.contains(Self::SIGABRT) {
-            Some((-6, "Aborted, SIGABRT=6"))
+            Some((6, "Aborted, SIGABRT=6"))
+        } else if self.contains(Self::SIGBUS) {
+            Some((7, "Bus Error, SIGBUS=7"))
         } else if self.contains(Self::SIGFPE) {
-            Some((-8, "Erroneous Arithmetic Operation, SIGFPE=8"))
+            Some((8, "Erroneous Arithmetic Operation, SIGFPE=8"))
         } else if self.contains(Self::SIGSEGV) {
-            Some((-11, "Segmentation Fault, SIGSEGV=11"))
+            Some((11, "Segmentation Fault, SIGSEGV=11"))
         } else {
             None
         }
     }
     const EMPTY: SignalFlags = SignalFlags::empty();
+    /// 默认动作为停止进程的信号
+    pub const STOP_SIGNALS: SignalFlags = Self::SIGSTOP
+        .union(Self::SIGTSTP)
+        .union(Self::SIGTTIN)
+        .union(Self::SIGTTOU);
+    /// 不能被屏蔽的信号
+    pub const UNBLOCKABLE: SignalFlags = Self::SIGKILL.union(Self::SIGSTOP);
     pub fn from_signum(signum: usize) -> Result<SignalFlags, ()> {
         match signum {
             0 => Ok(SignalFlags::EMPTY),
@@ -108,3 +156,59 @@ impl SignalFlags {
         }
     }
 }
+
+/// 信号备用栈，布局与用户态 `stack_t` 一致
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalStack {
+    /// 栈的最低地址
+    pub ss_sp: usize,
+    /// `SS_DISABLE` 或 `SS_AUTODISARM`
+    pub ss_flags: i32,
+    /// 栈的字节数
+    pub ss_size: usize,
+}
+
+impl SignalStack {
+    /// 未设置备用栈
+    pub fn disabled() -> Self {
+        Self {
+            ss_sp: 0,
+            ss_flags: SS_DISABLE,
+            ss_size: 0,
+        }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.ss_flags & SS_DISABLE != 0
+    }
+
+    /// 用户栈指针 `sp` 是否落在备用栈上
+    pub fn on_stack(&self, sp: usize) -> bool {
+        !self.is_disabled() && sp > self.ss_sp && sp - self.ss_sp <= self.ss_size
+    }
+
+    /// `sigaltstack` 返回给用户的旧设置，正在备用栈上运行时带 `SS_ONSTACK`
+    pub fn report(&self, sp: usize) -> Self {
+        let mut old = *self;
+        if self.on_stack(sp) {
+            old.ss_flags |= SS_ONSTACK;
+        }
+        old
+    }
+
+    /// 为信号处理函数选择栈顶：处理函数带 `SA_ONSTACK`、设置了备用栈且尚未在其上运行时
+    /// 切换到备用栈顶，否则沿用当前的 `sp`
+    ///
+    /// 带 `SS_AUTODISARM` 时切换后禁用备用栈，投递路径需在处理函数返回时恢复
+    pub fn handler_sp(&mut self, sp: usize, onstack: bool) -> usize {
+        if !onstack || self.is_disabled() || self.on_stack(sp) {
+            return sp;
+        }
+        let top = self.ss_sp + self.ss_size;
+        if self.ss_flags & SS_AUTODISARM != 0 {
+            *self = Self::disabled();
+        }
+        top
+    }
+}
@@ -32,6 +32,10 @@
 //!   - 分配内核栈、trap 上下文页
 //!   - 创建用户栈与 trap 上下文（可选）
 //!   - 初始化 TCB 为 `Ready` 状态
+//! - `TaskControlBlock::new_kthread`：
+//!   - 只分配内核栈，不属于任何进程，没有用户资源与 trap 上下文
+//! - 任务名（`TaskName`）：
+//!   - exec 时设为程序文件名，新线程与 fork 的子进程继承创建者的名字，可由 `prctl(PR_SET_NAME)` 修改
 //! - `TaskUserRes`：
 //!   - 分配 / 回收用户栈和 trap 上下文
 //!   - 分配 / 回收 TID
@@ -42,10 +46,16 @@ use crate::hal::{
     TrapContext, UserStackBase, PAGE_SIZE, USER_STACK_SIZE,
 };
 use crate::mm::{MapPermission, MemorySet, PageTable, PhysPageNum, VirtAddr};
+use crate::smp::ALL_HARTS;
 use crate::sync::{UPIntrFreeCell, UPIntrRefMut};
 use crate::task::context::TaskContext;
+use crate::task::kthread::KthreadFn;
 use crate::task::process::ProcessControlBlock;
+use crate::task::signal::{SignalFlags, SignalStack};
+use alloc::string::String;
 use alloc::sync::{Arc, Weak};
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// 任务控制块
 ///
@@ -56,6 +66,10 @@ pub struct TaskControlBlock {
     pub process: Weak<ProcessControlBlock>,
     /// 内核栈
     pub kstack: KernelStack,
+    /// CPU 亲和性：位 i 为 1 表示可以在 i 号 hart 上运行，可被其他任务修改，因此不放在 `inner` 中
+    pub cpu_affinity: AtomicUsize,
+    /// 内核线程的入口函数与参数，用户任务为 `None`
+    pub kthread_entry: Option<(KthreadFn, usize)>,
     /// 内部可变状态，由 UPIntrFreeCell 保护
     pub inner: UPIntrFreeCell<TaskControlBlockInner>,
 }
@@ -66,6 +80,16 @@ impl TaskControlBlock {
         self.inner.exclusive_access()
     }
 
+    /// 亲和性是否允许在 `hart` 上运行
+    pub fn runs_on(&self, hart: usize) -> bool {
+        self.cpu_affinity.load(Ordering::Relaxed) & (1 << hart) != 0
+    }
+
+    /// 是否为内核线程
+    pub fn is_kthread(&self) -> bool {
+        self.kthread_entry.is_some()
+    }
+
     /// 获取任务所属进程的用户页表 token
     pub fn get_user_token(&self) -> usize {
         let process = self.process.upgrade().unwrap();
@@ -93,13 +117,50 @@ impl TaskControlBlock {
         Self {
             process: Arc::downgrade(&process),
             kstack,
+            cpu_affinity: AtomicUsize::new(ALL_HARTS),
+            kthread_entry: None,
             inner: unsafe {
                 UPIntrFreeCell::new(TaskControlBlockInner {
                     res: Some(res),
                     trap_cx_ppn,
+                    name: TaskName::default(),
                     task_cx: TaskContext::goto_trap_return(kstack_top),
                     task_status: TaskStatus::Ready,
                     exit_code: None,
+                    interruptible: false,
+                    slice_left_us: 0,
+                    sigaltstack: SignalStack::disabled(),
+                    signal_mask: SignalFlags::empty(),
+                    pending: SignalFlags::empty(),
+                })
+            },
+        }
+    }
+
+    /// 创建一个执行 `entry(arg)` 的内核线程
+    ///
+    /// 没有 trap 上下文，`trap_cx_ppn` 只是占位，不能调用 `get_trap_cx`
+    pub fn new_kthread(entry: KthreadFn, arg: usize) -> Self {
+        let kstack = kstack_alloc();
+        let kstack_top = kstack.get_top();
+        Self {
+            process: Weak::new(),
+            kstack,
+            cpu_affinity: AtomicUsize::new(ALL_HARTS),
+            kthread_entry: Some((entry, arg)),
+            inner: unsafe {
+                UPIntrFreeCell::new(TaskControlBlockInner {
+                    res: None,
+                    trap_cx_ppn: PhysPageNum(0),
+                    name: TaskName::new(b"kthread"),
+                    task_cx: TaskContext::goto_kthread_start(kstack_top),
+                    task_status: TaskStatus::Ready,
+                    exit_code: None,
+                    interruptible: false,
+                    slice_left_us: 0,
+                    sigaltstack: SignalStack::disabled(),
+                    signal_mask: SignalFlags::empty(),
+                    pending: SignalFlags::empty(),
                 })
             },
         }
@@ -112,12 +173,24 @@ pub struct TaskControlBlockInner {
     pub res: Option<TaskUserRes>,
     /// trap 上下文物理页号
     pub trap_cx_ppn: PhysPageNum,
+    /// 任务名，出现在 panic 输出、系统调用跟踪与 `/proc/<pid>/status` 中
+    pub name: TaskName,
     /// 任务上下文（内核栈上下文）
     pub task_cx: TaskContext,
     /// 任务状态
     pub task_status: TaskStatus,
     /// 退出码（None 表示未退出）
     pub exit_code: Option<i32>,
+    /// 是否阻塞在可被信号打断的等待中（`block_current_interruptible`）
+    pub interruptible: bool,
+    /// 当前时间片剩余的运行时间（微秒），为 0 时下次运行重新分配完整的时间片
+    pub slice_left_us: usize,
+    /// 信号备用栈，新线程与 exec 之后没有备用栈，fork 的子进程继承
+    pub sigaltstack: SignalStack,
+    /// 被屏蔽的信号：保持待处理，既不终止进程也不打断阻塞的系统调用，fork 的子进程继承
+    pub signal_mask: SignalFlags,
+    /// 发给本线程的待处理信号（tkill / tgkill），投递时先于发给整个进程的信号检查
+    pub pending: SignalFlags,
 }
 
 impl TaskControlBlockInner {
@@ -132,6 +205,45 @@ impl TaskControlBlockInner {
     }
 }
 
+/// 任务名的长度（含结尾的 0），与 Linux 的 `TASK_COMM_LEN` 相同
+pub const TASK_COMM_LEN: usize = 16;
+
+/// 任务名：最多 `TASK_COMM_LEN - 1` 字节，其后以 0 填充
+#[derive(Clone, Copy, Default)]
+pub struct TaskName([u8; TASK_COMM_LEN]);
+
+impl TaskName {
+    /// 取 `name` 中第一个 0 之前的部分，过长时截断
+    pub fn new(name: &[u8]) -> Self {
+        let len = name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(name.len())
+            .min(TASK_COMM_LEN - 1);
+        let mut buf = [0; TASK_COMM_LEN];
+        buf[..len].copy_from_slice(&name[..len]);
+        Self(buf)
+    }
+
+    /// 程序路径 `path` 的文件名部分，exec 时使用
+    pub fn from_path(path: &str) -> Self {
+        Self::new(path.rsplit('/').next().unwrap_or(path).as_bytes())
+    }
+
+    /// 以 0 结尾的原始字节，`PR_GET_NAME` 原样复制给用户
+    pub fn as_bytes(&self) -> &[u8; TASK_COMM_LEN] {
+        &self.0
+    }
+}
+
+impl fmt::Display for TaskName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(TASK_COMM_LEN);
+        // 截断可能切开多字节字符，不合法的部分显示为替换字符
+        f.write_str(&String::from_utf8_lossy(&self.0[..len]))
+    }
+}
+
 /// 用户任务资源
 ///
 /// ## Overview
@@ -0,0 +1,71 @@
+//! # 初始线程的线程局部存储
+//!
+//! ## Overview
+//! 按 ELF 的 `PT_TLS` 段为新程序的主线程建立静态 TLS 块，并给出线程指针的初值：
+//! - `TlsTemplate::parse`：取出 `PT_TLS` 段描述的模板（`.tdata` 的初始内容、`.tbss` 在内的总大小与对齐）
+//! - `TlsTemplate::install`：在用户栈顶之下放置按模板初始化的 TLS 块，返回块的起始地址
+//!
+//! ## Design
+//! - RISC-V 与 LoongArch 都使用 TLS variant I，且线程指针（`tp` / `$tp`）指向 TLS 块的起始，
+//!   局部执行（local-exec）模型下的变量地址为 `tp + 偏移`，两个体系结构的布局相同
+//! - TLS 块放在用户栈的最高处、参数字符串之上，与栈一同分配、随地址空间一同回收；
+//!   `.tdata` 之后直到 `mem_size` 的部分（`.tbss`）显式清零
+//! - 之后创建的线程由 libc 分配各自的 TLS，通过 clone 的 `CLONE_SETTLS` 设置线程指针
+//!
+//! ## Assumptions
+//! - 静态链接的 musl 在启动时仍会按 `PT_TLS` 建立自己的 TLS 并覆盖 `tp`，内核的初值只保证在此之前
+//!   以及不带 libc 的程序中线程指针有效
+//! - 超过用户栈四分之一的 TLS 块不放在栈上，此时不设置线程指针
+//!
+//! ## Invariants
+//! - TLS 块的起始地址按模板的对齐要求对齐
+
+use crate::hal::USER_STACK_SIZE;
+use crate::mm::try_write_bytes;
+use alloc::vec;
+use xmas_elf::program::Type;
+use xmas_elf::ElfFile;
+
+/// 放在用户栈上的 TLS 块的最大字节数
+const TLS_MAX_SIZE: usize = USER_STACK_SIZE / 4;
+
+/// `PT_TLS` 段描述的 TLS 模板
+pub struct TlsTemplate<'a> {
+    /// `.tdata` 的初始内容
+    init: &'a [u8],
+    /// 包括 `.tbss` 在内的总大小
+    mem_size: usize,
+    /// 对齐要求
+    align: usize,
+}
+
+impl<'a> TlsTemplate<'a> {
+    /// 取出 ELF 中的 `PT_TLS` 段，没有该段或段的内容超出文件时返回 `None`
+    pub fn parse(elf_data: &'a [u8]) -> Option<Self> {
+        let elf = ElfFile::new(elf_data).ok()?;
+        let ph = elf
+            .program_iter()
+            .find(|ph| matches!(ph.get_type(), Ok(Type::Tls)))?;
+        let offset = ph.offset() as usize;
+        let init = elf_data.get(offset..offset.checked_add(ph.file_size() as usize)?)?;
+        Some(Self {
+            init,
+            mem_size: (ph.mem_size() as usize).max(init.len()),
+            align: (ph.align() as usize).max(1),
+        })
+    }
+
+    /// 在地址空间 `token` 中 `sp` 之下放置初始化的 TLS 块，返回块的起始地址，即线程指针的初值
+    ///
+    /// 块过大、对齐要求不是 2 的幂或写入失败时返回 `None`，此时用户栈没有被使用
+    pub fn install(&self, token: usize, sp: usize) -> Option<usize> {
+        if self.mem_size > TLS_MAX_SIZE || !self.align.is_power_of_two() {
+            return None;
+        }
+        let start = sp.checked_sub(self.mem_size)? & !(self.align - 1);
+        let mut block = vec![0u8; self.mem_size];
+        block[..self.init.len()].copy_from_slice(self.init);
+        try_write_bytes(token, start, &block)?;
+        Some(start)
+    }
+}
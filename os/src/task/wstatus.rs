@@ -0,0 +1,65 @@
+//! # 子进程状态编码（wstatus）
+//!
+//! ## Overview
+//! `wait4` 写回用户态的 `wstatus` 与 Linux 的编码一致，由 `WaitStatus` 统一编码与解码：
+//! - 正常退出：`WIFEXITED`，退出码在 8..16 位
+//! - 被信号终止：`WIFSIGNALED`，信号编号在低 7 位；生成了 core 文件时第 7 位（`WCOREDUMP`）置位
+//! - 停止：`WIFSTOPPED`，低 8 位为 `0x7f`，信号编号在 8..16 位
+//! - 继续：`WIFCONTINUED`，整个状态为 `0xffff`
+//!
+//! ## Design
+//! - 进程与线程的 `exit_code` 保存的是编码后的状态，`wait4` 直接写回
+//! - 退出、信号致死、跟踪停止以及将来的作业控制都经由这里编码，不再各自拼位
+//!
+//! ## Invariants
+//! - `WaitStatus::decode(s.encode()) == s`（退出码只保留低 8 位，信号编号只保留低 7 位）
+
+/// 子进程的一次状态变化
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// 以退出码正常退出
+    Exited(i32),
+    /// 被信号终止
+    Signaled(usize),
+    /// 被信号终止并生成了 core 文件
+    CoreDumped(usize),
+    /// 因信号停止
+    Stopped(usize),
+    /// 收到 SIGCONT 后继续运行
+    Continued,
+}
+
+/// `WIFSTOPPED` 时低 8 位的取值
+const STOPPED: i32 = 0x7f;
+/// `WIFCONTINUED` 时的整个状态
+const CONTINUED: i32 = 0xffff;
+/// `WCOREDUMP` 位
+const CORE_DUMPED: i32 = 0x80;
+
+impl WaitStatus {
+    /// 编码为写回用户态的 `wstatus`
+    pub fn encode(self) -> i32 {
+        match self {
+            Self::Exited(code) => (code & 0xff) << 8,
+            Self::Signaled(signum) => signum as i32 & 0x7f,
+            Self::CoreDumped(signum) => (signum as i32 & 0x7f) | CORE_DUMPED,
+            Self::Stopped(signum) => ((signum as i32 & 0xff) << 8) | STOPPED,
+            Self::Continued => CONTINUED,
+        }
+    }
+
+    /// 从 `wstatus` 解码，对应 `WIFEXITED` / `WIFSIGNALED` / `WIFSTOPPED` / `WIFCONTINUED`
+    pub fn decode(status: i32) -> Self {
+        if status == CONTINUED {
+            Self::Continued
+        } else if status & 0xff == STOPPED {
+            Self::Stopped(((status >> 8) & 0xff) as usize)
+        } else if status & 0x7f == 0 {
+            Self::Exited((status >> 8) & 0xff)
+        } else if status & CORE_DUMPED != 0 {
+            Self::CoreDumped((status & 0x7f) as usize)
+        } else {
+            Self::Signaled((status & 0x7f) as usize)
+        }
+    }
+}
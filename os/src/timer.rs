@@ -1,12 +1,54 @@
-use crate::hal::{get_clock_freq, get_time};
-use crate::sync::UPIntrFreeCell;
-use crate::task::{wakeup_task, TaskControlBlock};
+//! # 时间与内核定时器
+//!
+//! ## Overview
+//! - 时钟读取：`get_time_ms` 等，以及 `TimeVal` / `TimeSpec` / `Tms` / `ITimerVal` 等用户态时间结构
+//! - 墙上时间：`TimeSpec::realtime` 等返回 Unix 时间，`set_realtime` 调整墙上时间，`clock_now` 按时钟编号读取
+//! - 内核定时器 `Timer`：到期时唤醒一个任务或执行一个回调，供 nanosleep、ITIMER_REAL 与等待超时使用
+//! - `check_timer`：时钟中断时推进时间轮，处理到期的定时器
+//! - 下一次时钟中断：`set_next_tick` 记录下一个周期性 tick，`program_next_event` 把硬件定时器设置为
+//!   下一个 tick、当前时间片的结束与最早的定时器到期时间中最早的一个，`tick_due` 区分 tick 与其他中断
+//! - tick 频率与调度时间片：分别由启动参数 `hz=` 与 `timeslice=` 设置（`tick_period`、`timeslice_us`）
+//!
+//! ## Design
+//! - 到期时间保存在分层时间轮（`wheel`）中，加入与到期都是 O(1)，不必在每次时钟中断时整理整个堆
+//! - 条目只持有任务的 `Weak` 引用，已退出的任务不会因为尚未到期的定时器而无法释放
+//! - 撤销只是把共享状态标记为已撤销，条目在到期或级联时被丢弃
+//! - 回调在释放时间轮的锁之后执行，可以在其中再次设置定时器
+//! - 到期时间精确到微秒：时间轮按毫秒（向下取整）组织，时间轮给出但尚未到达微秒到期时间的条目
+//!   暂存在 `near` 中，每次 `check_timer` 重新检查
+//! - 另有一个以到期时间排序的小根堆，只用于求最早的到期时间；加入比已设置的中断更早的定时器时
+//!   立即重新设置硬件定时器，使短睡眠与轮询超时不必等到下一个 tick
+//! - 撤销的定时器不从堆中删除，最多引起一次多余的时钟中断
+//! - 调度时间片与 tick 分离：调度器切换到任务时以 `set_slice_end` 设置时间片的结束时刻，
+//!   时钟中断按 `slice_expired` 决定是否抢占，时间片比 tick 短时也能按时结束；
+//!   tick 只负责负载采样等周期性工作
+//! - 墙上时间 = 启动时刻的 Unix 时间（`BOOT_EPOCH_NS`）+ 启动以来的单调时间；调整墙上时间只修改启动时刻，
+//!   单调时间、定时器与睡眠都不受影响
+//!
+//! ## Assumptions
+//! - 实际到期在到期时间之后的第一次时钟中断，误差取决于中断响应延迟，而不是 tick 的周期
+//! - 只有时间片用完才抢占当前任务，tick 与定时器中断本身不引起调度
+//! - tick 频率与时间片的长度在启动时确定，之后不能修改
+//! - 下一个 tick 与时间片的结束时刻全局只有一份，与工作队列一样假定只有一个处理器处理时钟中断
+//! - 全局只有一个时间轮，由 `SpinMutex` 保护；回调在中断上下文中执行，不能阻塞
+//! - 没有 RTC 设置启动时刻时，墙上时间从 1970-01-01 开始，直到用户态通过 settimeofday 设置
+
+pub(crate) mod wheel;
+
+use crate::hal::{get_clock_freq, get_time, set_timer_event, TICKS_PER_SEC};
+use crate::sync::SpinMutex;
+use crate::task::{sample_load, wake_blocked, TaskControlBlock};
+use alloc::boxed::Box;
 use alloc::collections::BinaryHeap;
-use alloc::sync::Arc;
-use core::cmp::Ordering;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::mem;
 use core::ops::{Add, AddAssign, Sub};
+use core::sync::atomic::{self, AtomicU8, AtomicUsize};
 use core::time::Duration;
 use lazy_static::lazy_static;
+use wheel::TimerWheel;
 
 pub const MSEC_PER_SEC: usize = 1000;
 
@@ -33,53 +75,289 @@ pub fn current_time_duration() -> Duration {
     Duration::from_micros(get_time_us() as u64)
 }
 
-pub struct TimerCondVar {
-    pub expire_ms: usize,
-    pub task: Arc<TaskControlBlock>,
+/// 时钟编号
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const CLOCK_MONOTONIC_RAW: usize = 4;
+pub const CLOCK_REALTIME_COARSE: usize = 5;
+pub const CLOCK_MONOTONIC_COARSE: usize = 6;
+pub const CLOCK_BOOTTIME: usize = 7;
+
+/// 启动时刻对应的 Unix 时间（纳秒）
+static BOOT_EPOCH_NS: AtomicUsize = AtomicUsize::new(0);
+
+/// 设置启动时刻对应的 Unix 时间，由 RTC 驱动在初始化时调用
+pub fn set_boot_epoch(epoch: TimeSpec) {
+    BOOT_EPOCH_NS.store(epoch.to_ns(), atomic::Ordering::Relaxed);
 }
 
-impl PartialEq for TimerCondVar {
-    fn eq(&self, other: &Self) -> bool {
-        self.expire_ms == other.expire_ms
-    }
+/// 把墙上时间设置为 `now`，早于启动时刻的时间按启动时刻处理
+pub fn set_realtime(now: TimeSpec) {
+    let epoch = now.to_ns().saturating_sub(TimeSpec::now().to_ns());
+    BOOT_EPOCH_NS.store(epoch, atomic::Ordering::Relaxed);
 }
-impl Eq for TimerCondVar {}
-impl PartialOrd for TimerCondVar {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let a = -(self.expire_ms as isize);
-        let b = -(other.expire_ms as isize);
-        Some(a.cmp(&b))
+
+/// 按时钟编号读取当前时间，不支持的时钟返回 `None`
+pub fn clock_now(clock_id: usize) -> Option<TimeSpec> {
+    match clock_id {
+        CLOCK_REALTIME | CLOCK_REALTIME_COARSE => Some(TimeSpec::realtime()),
+        // 没有挂起，启动以来的时间与单调时间相同
+        CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE | CLOCK_BOOTTIME => {
+            Some(TimeSpec::now())
+        }
+        _ => None,
     }
 }
 
-impl Ord for TimerCondVar {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+/// 定时器的状态
+const TIMER_PENDING: u8 = 0;
+const TIMER_FIRED: u8 = 1;
+const TIMER_CANCELLED: u8 = 2;
+
+/// 定时器到期时的动作
+enum TimerAction {
+    /// 唤醒阻塞中的任务
+    Wake(Weak<TaskControlBlock>),
+    /// 执行回调
+    Call(Box<dyn FnOnce() + Send>),
+}
+
+/// 时间轮中的一个定时器
+struct TimerEntry {
+    expire_us: usize,
+    state: Arc<AtomicU8>,
+    action: TimerAction,
+}
+
+/// 全部尚未到期的定时器
+struct TimerQueue {
+    /// 按毫秒组织的时间轮
+    wheel: TimerWheel<TimerEntry>,
+    /// 时间轮已经给出、但还没有到达微秒到期时间的条目
+    near: Vec<TimerEntry>,
+    /// 到期时间（微秒）的小根堆，含已撤销的定时器
+    deadlines: BinaryHeap<Reverse<usize>>,
+}
+
+lazy_static! {
+    static ref TIMERS: SpinMutex<TimerQueue> = SpinMutex::new(TimerQueue {
+        wheel: TimerWheel::new(get_time_ms()),
+        near: Vec::new(),
+        deadlines: BinaryHeap::new(),
+    });
+}
+
+/// 下一个周期性 tick 的时刻（时钟周期数）
+static NEXT_TICK: AtomicUsize = AtomicUsize::new(0);
+/// 当前时间片结束的时刻（时钟周期数），没有任务运行时为 `usize::MAX`
+static SLICE_END: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// `hz=` 允许的最大值
+const MAX_TICK_HZ: usize = 1000;
+
+/// 周期性 tick 与调度时间片的设置，第一次使用时从启动参数读取
+struct TickConfig {
+    /// 每秒的 tick 数
+    hz: usize,
+    /// 调度时间片（微秒）
+    slice_us: usize,
+}
+
+impl TickConfig {
+    fn from_cmdline() -> Self {
+        let hz = match crate::boot::tick_hz() {
+            Some(hz) if (1..=MAX_TICK_HZ).contains(&hz) => hz,
+            Some(hz) => {
+                log::warn!("invalid hz={}, using {}", hz, TICKS_PER_SEC);
+                TICKS_PER_SEC
+            }
+            None => TICKS_PER_SEC,
+        };
+        // 默认时间片为一个默认 tick 周期，与分离之前每个 tick 切换一次的行为相同
+        let slice_ms = crate::boot::timeslice_ms()
+            .filter(|&ms| ms > 0)
+            .unwrap_or(MSEC_PER_SEC / TICKS_PER_SEC);
+        Self {
+            hz,
+            slice_us: slice_ms * USEC_PER_MSEC,
+        }
     }
 }
 
 lazy_static! {
-    static ref TIMERS: UPIntrFreeCell<BinaryHeap<TimerCondVar>> =
-        unsafe { UPIntrFreeCell::new(BinaryHeap::<TimerCondVar>::new()) };
+    static ref TICK_CONFIG: TickConfig = TickConfig::from_cmdline();
 }
 
-pub fn add_timer(expire_ms: usize, task: Arc<TaskControlBlock>) {
-    let mut timers = TIMERS.exclusive_access();
-    timers.push(TimerCondVar { expire_ms, task });
+/// 一个 tick 周期的长度（时钟周期数）
+pub fn tick_period() -> usize {
+    get_clock_freq() / TICK_CONFIG.hz
+}
+
+/// 调度时间片的长度（微秒）
+pub fn timeslice_us() -> usize {
+    TICK_CONFIG.slice_us
+}
+
+/// 内核定时器的句柄
+///
+/// 丢弃句柄不会撤销定时器，需要撤销时调用 `cancel`
+pub struct Timer {
+    expire_us: usize,
+    state: Arc<AtomicU8>,
+}
+
+impl Timer {
+    fn add(expire_us: usize, action: TimerAction) -> Self {
+        let state = Arc::new(AtomicU8::new(TIMER_PENDING));
+        let entry = TimerEntry {
+            expire_us,
+            state: state.clone(),
+            action,
+        };
+        let earliest = {
+            let mut timers = TIMERS.lock();
+            let earliest = timers
+                .deadlines
+                .peek()
+                .map_or(true, |&Reverse(first)| expire_us < first);
+            timers.wheel.add(expire_us / USEC_PER_MSEC, entry);
+            timers.deadlines.push(Reverse(expire_us));
+            earliest
+        };
+        // 比已设置的时钟中断更早到期：重新设置硬件定时器
+        if earliest {
+            program_next_event();
+        }
+        Self { expire_us, state }
+    }
+
+    /// 在 `expire_ms` 唤醒 `task`，只唤醒仍处于阻塞状态的任务
+    pub fn wake_at(expire_ms: usize, task: &Arc<TaskControlBlock>) -> Self {
+        Self::wake_at_us(expire_ms * USEC_PER_MSEC, task)
+    }
+
+    /// 在 `expire_us`（微秒）唤醒 `task`，只唤醒仍处于阻塞状态的任务
+    pub fn wake_at_us(expire_us: usize, task: &Arc<TaskControlBlock>) -> Self {
+        Self::add(expire_us, TimerAction::Wake(Arc::downgrade(task)))
+    }
+
+    /// 在 `expire_ms` 执行 `f`
+    pub fn call_at(expire_ms: usize, f: impl FnOnce() + Send + 'static) -> Self {
+        Self::call_at_us(expire_ms * USEC_PER_MSEC, f)
+    }
+
+    /// 在 `expire_us`（微秒）执行 `f`
+    pub fn call_at_us(expire_us: usize, f: impl FnOnce() + Send + 'static) -> Self {
+        Self::add(expire_us, TimerAction::Call(Box::new(f)))
+    }
+
+    /// 到期时间（毫秒）
+    pub fn expire_ms(&self) -> usize {
+        self.expire_us / USEC_PER_MSEC
+    }
+
+    /// 是否已经到期
+    pub fn fired(&self) -> bool {
+        self.state.load(atomic::Ordering::Acquire) == TIMER_FIRED
+    }
+
+    /// 撤销定时器，尚未到期时返回 `true`
+    pub fn cancel(&self) -> bool {
+        self.state
+            .compare_exchange(
+                TIMER_PENDING,
+                TIMER_CANCELLED,
+                atomic::Ordering::AcqRel,
+                atomic::Ordering::Acquire,
+            )
+            .is_ok()
+    }
 }
 
+/// 时钟中断时调用：推进时间轮，执行到期且未被撤销的定时器，然后设置下一次时钟中断
 pub fn check_timer() {
-    let current_ms = get_time_ms();
-    TIMERS.exclusive_session(|timers| {
-        while let Some(timer) = timers.peek() {
-            if timer.expire_ms <= current_ms {
-                wakeup_task(Arc::clone(&timer.task));
-                timers.pop();
-            } else {
-                break;
+    sample_load();
+    let now_us = get_time_us();
+    let due = {
+        let mut timers = TIMERS.lock();
+        let mut expired = Vec::new();
+        timers.wheel.advance(now_us / USEC_PER_MSEC, &mut expired);
+        let TimerQueue {
+            near, deadlines, ..
+        } = &mut *timers;
+        near.extend(expired.into_iter().map(|entry| entry.value));
+        while deadlines
+            .peek()
+            .is_some_and(|&Reverse(first)| first <= now_us)
+        {
+            deadlines.pop();
+        }
+        let (due, later) = mem::take(near)
+            .into_iter()
+            .partition::<Vec<_>, _>(|timer| timer.expire_us <= now_us);
+        *near = later;
+        due
+    };
+    for timer in due {
+        let fired = timer.state.compare_exchange(
+            TIMER_PENDING,
+            TIMER_FIRED,
+            atomic::Ordering::AcqRel,
+            atomic::Ordering::Acquire,
+        );
+        if fired.is_err() {
+            continue;
+        }
+        match timer.action {
+            TimerAction::Wake(task) => {
+                if let Some(task) = task.upgrade() {
+                    wake_blocked(task);
+                }
             }
+            TimerAction::Call(f) => f(),
         }
-    });
+    }
+    program_next_event();
+}
+
+/// 记录下一个周期性 tick 的时刻 `tick`（时钟周期数），并据此设置下一次时钟中断
+pub fn set_next_tick(tick: usize) {
+    NEXT_TICK.store(tick, atomic::Ordering::Relaxed);
+    program_next_event();
+}
+
+/// 周期性 tick 是否已经到达；为 `false` 时本次时钟中断是为了时间片结束或到期的定时器
+pub fn tick_due() -> bool {
+    get_time() >= NEXT_TICK.load(atomic::Ordering::Relaxed)
+}
+
+/// 设置当前时间片结束的时刻 `end`（时钟周期数），`usize::MAX` 表示没有时间片
+pub fn set_slice_end(end: usize) {
+    SLICE_END.store(end, atomic::Ordering::Relaxed);
+    program_next_event();
+}
+
+/// 当前时间片是否已经用完
+pub fn slice_expired() -> bool {
+    get_time() >= SLICE_END.load(atomic::Ordering::Relaxed)
+}
+
+/// 把硬件定时器设置为下一个 tick、时间片结束与最早的定时器到期时间中最早的一个
+///
+/// 已经过去的时间片结束不再设置，否则在内核中用完时间片的任务会不断被时钟中断打断，
+/// 它在返回用户态之前让出处理器
+pub fn program_next_event() {
+    let slice_end = SLICE_END.load(atomic::Ordering::Relaxed);
+    let mut tick = NEXT_TICK.load(atomic::Ordering::Relaxed);
+    if slice_end > get_time() {
+        tick = tick.min(slice_end);
+    }
+    let deadline = TIMERS
+        .lock()
+        .deadlines
+        .peek()
+        .map(|&Reverse(first)| first.saturating_mul(get_clock_freq() / USEC_PER_SEC));
+    set_timer_event(deadline.map_or(tick, |deadline| deadline.min(tick)));
 }
 
 #[derive(Clone, Copy)]
@@ -120,6 +398,10 @@ impl TimeVal {
     pub fn now() -> Self {
         TimeVal::from_tick(get_time())
     }
+    /// 当前的墙上时间
+    pub fn realtime() -> Self {
+        TimeVal::from_us(TimeSpec::realtime().to_ns() / NSEC_PER_USEC)
+    }
     pub fn to_tick(&self) -> usize {
         self.tv_sec * get_clock_freq() + self.tv_usec * get_clock_freq() / USEC_PER_SEC
     }
@@ -241,6 +523,10 @@ impl TimeSpec {
     pub fn now() -> Self {
         TimeSpec::from_tick(get_time())
     }
+    /// 当前的墙上时间
+    pub fn realtime() -> Self {
+        TimeSpec::from_ns(BOOT_EPOCH_NS.load(atomic::Ordering::Relaxed) + TimeSpec::now().to_ns())
+    }
     /// 将 TimeSpec 转换为毫秒数（usize）
     pub fn to_ms(&self) -> usize {
         // 1 秒 = 1000 毫秒
@@ -273,3 +559,13 @@ impl ITimerVal {
         }
     }
 }
+
+/// 用户态 `struct itimerspec`（timerfd_settime / timerfd_gettime）
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct ITimerSpec {
+    /// 重复间隔，为零时只到期一次
+    pub it_interval: TimeSpec,
+    /// 距离下次到期的时间（或绝对到期时间），为零表示停止
+    pub it_value: TimeSpec,
+}
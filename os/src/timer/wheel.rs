@@ -0,0 +1,111 @@
+//! # 分层时间轮
+//!
+//! ## Overview
+//! 以毫秒为单位管理到期时间的分层时间轮，供 `Timer` 使用：
+//! - `add`：按距离到期的时间放入某一层的某个槽，O(1)
+//! - `advance`：推进到给定时间，取出其间到期的全部条目
+//!
+//! ## Design
+//! - 共 `LEVELS` 层，每层 `SLOTS` 个槽；第 k 层的一个槽覆盖 `SLOTS^k` 毫秒
+//! - 第 0 层的槽号是到期时间的低 6 位，第 k 层是第 6k 位起的 6 位
+//! - 第 0 层转完一圈时，把第 1 层当前槽中的条目重新放入（级联），它们会落到更低的层；
+//!   第 1 层也转完一圈时再级联第 2 层，以此类推
+//! - 超出最高层范围的条目放在最高层能表示的最远处，级联时重新计算位置
+//! - 时间轮为空时 `advance` 直接跳到目标时间，长时间没有时钟中断也不必逐毫秒推进
+//!
+//! ## Invariants
+//! - `now` 是下一个尚未处理的毫秒；所有条目的到期时间不早于 `now`
+//! - `len` 等于所有槽中条目的总数
+
+use alloc::vec::Vec;
+use core::mem;
+
+/// 每层的槽数的位数
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const SLOT_MASK: usize = SLOTS - 1;
+/// 层数，最高层覆盖约 4.6 小时
+const LEVELS: usize = 4;
+/// 可以直接表示的最远距离
+const MAX_DELTA: usize = (1 << (SLOT_BITS * LEVELS)) - 1;
+
+/// 时间轮中的一个条目
+pub struct WheelEntry<T> {
+    pub expire_ms: usize,
+    pub value: T,
+}
+
+/// 分层时间轮
+pub struct TimerWheel<T> {
+    /// 下一个尚未处理的毫秒
+    now: usize,
+    /// 各层的槽
+    levels: [[Vec<WheelEntry<T>>; SLOTS]; LEVELS],
+    /// 条目总数
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    /// 从时间 `now` 开始的空时间轮
+    pub fn new(now: usize) -> Self {
+        Self {
+            now,
+            levels: core::array::from_fn(|_| core::array::from_fn(|_| Vec::new())),
+            len: 0,
+        }
+    }
+
+    /// 加入一个在 `expire_ms` 到期的条目，已经过去的时间在下一次推进时到期
+    pub fn add(&mut self, expire_ms: usize, value: T) {
+        self.len += 1;
+        self.insert(WheelEntry { expire_ms, value });
+    }
+
+    fn insert(&mut self, entry: WheelEntry<T>) {
+        let due = entry.expire_ms.clamp(self.now, self.now + MAX_DELTA);
+        let delta = due - self.now;
+        let level = (0..LEVELS)
+            .find(|&level| delta < 1 << (SLOT_BITS * (level + 1)))
+            .unwrap_or(LEVELS - 1);
+        let slot = (due >> (SLOT_BITS * level)) & SLOT_MASK;
+        self.levels[level][slot].push(entry);
+    }
+
+    /// 推进到 `now_ms`（含），把其间到期的条目追加到 `expired`
+    pub fn advance(&mut self, now_ms: usize, expired: &mut Vec<WheelEntry<T>>) {
+        while self.now <= now_ms {
+            if self.len == 0 {
+                self.now = now_ms + 1;
+                break;
+            }
+            if self.now & SLOT_MASK == 0 {
+                self.cascade(1);
+            }
+            let slot = mem::take(&mut self.levels[0][self.now & SLOT_MASK]);
+            for entry in slot {
+                if entry.expire_ms <= self.now {
+                    self.len -= 1;
+                    expired.push(entry);
+                } else {
+                    // 被限制在最远处的条目还没有到期
+                    self.insert(entry);
+                }
+            }
+            self.now += 1;
+        }
+    }
+
+    /// 第 `level - 1` 层转完一圈时，把第 `level` 层当前槽中的条目重新放入
+    fn cascade(&mut self, level: usize) {
+        if level >= LEVELS {
+            return;
+        }
+        let slot = (self.now >> (SLOT_BITS * level)) & SLOT_MASK;
+        if slot == 0 {
+            self.cascade(level + 1);
+        }
+        for entry in mem::take(&mut self.levels[level][slot]) {
+            self.insert(entry);
+        }
+    }
+}
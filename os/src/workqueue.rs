@@ -0,0 +1,53 @@
+//! # 工作队列：中断处理的延迟执行
+//!
+//! ## Overview
+//! 中断处理程序只完成必须立即完成的部分（应答设备、记录状态），其余工作通过 `schedule_work`
+//! 放入工作队列，稍后在普通内核上下文中执行：
+//! - 块设备（AHCI）命令完成后唤醒等待的任务
+//! - 网卡收包：把设备收到的帧搬到协议栈的接收队列
+//!
+//! ## Design
+//! - 与 Linux 的软中断类似，没有专门的内核线程，队列在以下时机被清空（`run_pending_work`）：
+//!   - 从用户态陷阱返回之前
+//!   - 调度循环每次选择下一个任务之前，空闲等待被中断唤醒后随即执行
+//! - 每轮一次取走整个队列，在不持有队列锁的情况下按加入顺序执行；
+//!   执行期间新加入的工作最多再处理 `MAX_RESTART` 轮，剩余的留到下一次，
+//!   避免持续到来的中断使陷阱返回被无限推迟
+//!
+//! ## Assumptions
+//! - 工作与中断处理程序一样不能阻塞或调度，只是执行时不再处于中断上下文
+//! - 控制台输入经由 SBI 轮询读取，没有接收中断，因此不经过工作队列
+//! - 只有一个处理器；多核启动后需改为每个 hart 一个队列
+
+use crate::sync::SpinMutex;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use lazy_static::lazy_static;
+
+/// 一次 `run_pending_work` 最多处理的轮数
+const MAX_RESTART: usize = 10;
+
+type Work = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    /// 等待执行的工作
+    static ref PENDING: SpinMutex<VecDeque<Work>> = SpinMutex::new(VecDeque::new());
+}
+
+/// 把 `work` 加入工作队列，可以在中断处理程序中调用
+pub fn schedule_work(work: impl FnOnce() + Send + 'static) {
+    PENDING.lock().push_back(Box::new(work));
+}
+
+/// 执行队列中的工作
+pub fn run_pending_work() {
+    for _ in 0..MAX_RESTART {
+        let batch = core::mem::take(&mut *PENDING.lock());
+        if batch.is_empty() {
+            return;
+        }
+        for work in batch {
+            work();
+        }
+    }
+}
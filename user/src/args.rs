@@ -0,0 +1,89 @@
+//! 进程参数
+//!
+//! 按 SysV 约定解析入口处内核构造的初始栈，自 sp 向高地址依次为：
+//! argc、argv 指针（以空指针结尾）、envp 指针（以空指针结尾）、辅助向量（以 `AT_NULL` 结尾），
+//! 字符串位于更高的地址，在进程的整个生命周期内有效
+
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// 辅助向量的类型
+pub const AT_NULL: usize = 0;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_RANDOM: usize = 25;
+
+/// 入口处的栈指针，指向 argc
+static mut INIT_SP: usize = 0;
+
+pub(crate) fn set_init_sp(sp: usize) {
+    unsafe { INIT_SP = sp }
+}
+
+/// 初始栈中的参数、环境变量与辅助向量
+pub struct Args {
+    pub argv: Vec<&'static str>,
+    pub envp: Vec<&'static str>,
+    pub auxv: Vec<(usize, usize)>,
+}
+
+impl Args {
+    /// 环境变量 `name` 的值
+    pub fn env(&self, name: &str) -> Option<&'static str> {
+        self.envp
+            .iter()
+            .find_map(|&entry| entry.strip_prefix(name)?.strip_prefix('='))
+    }
+
+    /// 辅助向量中类型为 `key` 的值
+    pub fn aux(&self, key: usize) -> Option<usize> {
+        self.auxv
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn read_word(addr: usize) -> usize {
+    unsafe { (addr as *const usize).read_volatile() }
+}
+
+/// `addr` 处以 `\0` 结尾的字符串，不是合法的 UTF-8 时为空串
+fn c_str(addr: usize) -> &'static str {
+    let len = (0usize..)
+        .find(|i| unsafe { ((addr + i) as *const u8).read_volatile() == 0 })
+        .unwrap();
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+        .unwrap_or("")
+}
+
+/// 从 `addr` 开始读取以空指针结尾的字符串指针数组，返回其中的字符串与空指针之后的地址
+fn c_str_array(mut addr: usize) -> (Vec<&'static str>, usize) {
+    let mut strs = Vec::new();
+    loop {
+        let ptr = read_word(addr);
+        addr += size_of::<usize>();
+        if ptr == 0 {
+            return (strs, addr);
+        }
+        strs.push(c_str(ptr));
+    }
+}
+
+/// 解析入口处的初始栈
+pub fn getargs() -> Args {
+    let sp = unsafe { INIT_SP };
+    let argc = read_word(sp);
+    let (argv, envp_base) = c_str_array(sp + size_of::<usize>());
+    debug_assert_eq!(argc, argv.len());
+    let (envp, mut addr) = c_str_array(envp_base);
+    let mut auxv = Vec::new();
+    loop {
+        let key = read_word(addr);
+        if key == AT_NULL {
+            break;
+        }
+        auxv.push((key, read_word(addr + size_of::<usize>())));
+        addr += 2 * size_of::<usize>();
+    }
+    Args { argv, envp, auxv }
+}
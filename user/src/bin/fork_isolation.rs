@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+//! fork 之后父子进程的地址空间互相隔离：双方各自改写同一块堆内存，互相看不到对方的修改
+//!
+//! 子进程只改写奇数页，偶数页由父进程之后再改写；两个管道用来排定双方改写的先后
+
+extern crate alloc;
+extern crate user;
+
+use alloc::vec;
+use user::{close, exit, fork, pipe, println, read, waitpid, wexitstatus, wifexited, write};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 16;
+
+/// 第 `idx` 页在种子 `seed` 下第 `offset` 个字节的内容
+fn pattern(idx: usize, offset: usize, seed: u8) -> u8 {
+    seed.wrapping_mul(31)
+        .wrapping_add(idx as u8)
+        .wrapping_add(offset as u8)
+}
+
+/// 以种子 `seed` 改写满足 `which` 的页
+fn fill(buf: &mut [u8], seed: u8, which: impl Fn(usize) -> bool) {
+    for (idx, page) in buf.chunks_mut(PAGE_SIZE).enumerate() {
+        if which(idx) {
+            for (offset, b) in page.iter_mut().enumerate() {
+                *b = pattern(idx, offset, seed);
+            }
+        }
+    }
+}
+
+/// 每一页的内容都与 `seed_of` 给出的种子一致
+fn check(buf: &[u8], seed_of: impl Fn(usize) -> u8) -> bool {
+    buf.chunks(PAGE_SIZE).enumerate().all(|(idx, page)| {
+        page.iter()
+            .enumerate()
+            .all(|(offset, b)| *b == pattern(idx, offset, seed_of(idx)))
+    })
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buf = vec![0u8; PAGES * PAGE_SIZE];
+    fill(&mut buf, 1, |_| true);
+    let mut to_child = [0usize; 2];
+    let mut to_parent = [0usize; 2];
+    pipe(&mut to_child);
+    pipe(&mut to_parent);
+    let pid = fork();
+    if pid == 0 {
+        close(to_child[1]);
+        close(to_parent[0]);
+        let mut ok = check(&buf, |_| 1);
+        fill(&mut buf, 2, |idx| idx % 2 == 1);
+        write(to_parent[1], &[1]);
+        read(to_child[0], &mut [0]);
+        // 父进程已改写全部页：奇数页仍是自己写的内容，偶数页仍是 fork 时的内容
+        ok &= check(&buf, |idx| if idx % 2 == 1 { 2 } else { 1 });
+        exit(!ok as i32);
+    }
+    close(to_child[0]);
+    close(to_parent[1]);
+    read(to_parent[0], &mut [0]);
+    // 子进程的改写不可见
+    let mut ok = check(&buf, |_| 1);
+    fill(&mut buf, 3, |_| true);
+    write(to_child[1], &[1]);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    ok &= wifexited(status) && wexitstatus(status) == 0;
+    ok &= check(&buf, |_| 3);
+    println!("fork_isolation: {}", if ok { "ok" } else { "FAILED" });
+    !ok as i32
+}
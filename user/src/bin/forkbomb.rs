@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+//! 有上限的 fork 炸弹：进程树逐层分叉，直到深度达到上限，检查每个进程都被回收
+//!
+//! 深度未到 `DEPTH` 的进程分叉 `FANOUT` 个子进程，子进程以自己子树中的进程数退出，
+//! 根进程核对总数
+
+extern crate alloc;
+extern crate user;
+
+use alloc::vec::Vec;
+use user::{exit, fork, println, waitpid, wexitstatus, wifexited, yield_};
+
+const FANOUT: usize = 3;
+const DEPTH: usize = 4;
+
+/// 在第 `depth` 层分叉并回收子树，返回子树中的进程数（含自己），子进程异常时返回 0
+fn spawn(depth: usize) -> usize {
+    let mut pids = Vec::new();
+    if depth < DEPTH {
+        for _ in 0..FANOUT {
+            let pid = fork();
+            if pid == 0 {
+                // 让出处理器，使各层的分叉与退出交错进行
+                for _ in 0..depth {
+                    yield_();
+                }
+                exit(spawn(depth + 1) as i32);
+            }
+            if pid < 0 {
+                println!("forkbomb: fork failed at depth {}", depth);
+                return 0;
+            }
+            pids.push(pid);
+        }
+    }
+    let mut total = 1;
+    for pid in pids {
+        let mut status = 0;
+        if waitpid(pid as usize, &mut status) != pid || !wifexited(status) {
+            return 0;
+        }
+        total += wexitstatus(status) as usize;
+    }
+    total
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let expected: usize = (0..=DEPTH as u32).map(|i| FANOUT.pow(i)).sum();
+    let total = spawn(0);
+    println!("forkbomb: {} of {} processes reaped", total, expected);
+    (total != expected) as i32
+}
@@ -1,61 +1,78 @@
 #![no_std]
 #![no_main]
 
+extern crate alloc;
 extern crate user;
 
-use user::{exec, fork, println, wait, yield_};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use user::{exec, fork, println, wait, waitpid, yield_};
 
-#[no_mangle]
-fn main() -> i32 {
-    let tests = [
-        "brk\0",
-        "clone\0",
-        "dup\0",
-        "execve\0",
-        "fork\0",
-        "getcwd\0",
-        "getpid\0",
-        "gettimeofday\0",
-        "mmap\0",
-        // "mount\0",
-        "open\0",
-        // "pipe\0",
-        "times\0",  //好像有点问题？
-        "uname\0",
-        "wait\0",
-        "write\0",
-        "chdir\0",
-        "close\0",
-        "dup2\0",
-        "exit\0",
-        "fstat\0",
-        "getdents\0",
-        "getppid\0",
-        "mkdir_\0",
-        "munmap\0",
-        "openat\0",
-        "read\0",
-        "sleep\0",
-        // "umount\0",
-        // "unlink\0",
-        "waitpid\0",
-        "yield\0",
-    ];
+/// 内置的测试列表，没有自动运行列表时使用
+const TESTS: &[&str] = &[
+    "brk",
+    "clone",
+    "dup",
+    "execve",
+    "fork",
+    "getcwd",
+    "getpid",
+    "gettimeofday",
+    "mmap",
+    // "mount",
+    "open",
+    // "pipe",
+    "times", //好像有点问题？
+    "uname",
+    "wait",
+    "write",
+    "chdir",
+    "close",
+    "dup2",
+    "exit",
+    "fstat",
+    "getdents",
+    "getppid",
+    "mkdir_",
+    "munmap",
+    "openat",
+    "read",
+    "sleep",
+    // "umount",
+    // "unlink",
+    "waitpid",
+    "yield",
+];
 
-    for prog in tests {
-        let pid = fork();
-        if pid == 0 {
-            println!("Running {}", prog);
-            exec(prog, &[core::ptr::null()]);
-            panic!("exec failed");
-        } else {
-            let mut exit_code: i32 = 0;
-            wait(&mut exit_code);
-            if pid == -1 {
-                yield_();
-                continue;
-            }
-        }
+/// 运行一条命令并等待其结束，`command` 为以空白分隔的程序名与参数
+fn run(command: &str) {
+    let args: Vec<String> = command
+        .split_whitespace()
+        .map(|arg| format!("{}\0", arg))
+        .collect();
+    if args.is_empty() {
+        return;
+    }
+    let mut argv: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    argv.push(core::ptr::null());
+    let pid = fork();
+    if pid == 0 {
+        println!("Running {}", command);
+        exec(&args[0], &argv);
+        panic!("exec failed");
+    } else if pid > 0 {
+        let mut exit_code: i32 = 0;
+        waitpid(pid as usize, &mut exit_code);
+    }
+}
+
+/// 内核把自动运行列表（默认 `/autorun.txt`）中的命令作为参数传入，没有时运行内置的测试列表
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    let commands = if argc > 1 { &argv[1..] } else { TESTS };
+    for command in commands {
+        run(command);
     }
 
     if fork() == 0 {
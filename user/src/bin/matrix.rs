@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+//! 调度公平性：同时运行 `WORKERS` 个做矩阵乘法的计算密集型进程，比较各自用去的时间
+//!
+//! 每个子进程把（用时，校验和）写入管道；校验和必须一致，最慢与最快的用时之比不超过 `MAX_RATIO`
+
+extern crate alloc;
+extern crate user;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use user::{
+    clock_gettime, close, exit, fork, pipe, println, read, waitpid, wexitstatus, wifexited, write,
+    TimeSpec, CLOCK_MONOTONIC,
+};
+
+const WORKERS: usize = 4;
+const N: usize = 48;
+const ROUNDS: usize = 8;
+const MAX_RATIO: usize = 3;
+
+fn now_ms() -> usize {
+    let mut ts = TimeSpec::default();
+    clock_gettime(CLOCK_MONOTONIC, &mut ts);
+    ts.tv_sec * 1000 + ts.tv_nsec / 1_000_000
+}
+
+/// 反复计算 `c = a * b`，返回结果矩阵的校验和
+fn multiply() -> u64 {
+    let a: Vec<u64> = (0..N * N).map(|i| (i % 7) as u64).collect();
+    let b: Vec<u64> = (0..N * N).map(|i| (i % 11) as u64).collect();
+    let mut c = vec![0u64; N * N];
+    for round in 0..ROUNDS {
+        for i in 0..N {
+            for j in 0..N {
+                let mut sum = round as u64;
+                for k in 0..N {
+                    sum = sum.wrapping_add(a[i * N + k] * b[k * N + j]);
+                }
+                c[i * N + j] = sum;
+            }
+        }
+    }
+    c.iter()
+        .fold(0u64, |acc, x| acc.wrapping_mul(31).wrapping_add(*x))
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    pipe(&mut fds);
+    let mut pids = Vec::new();
+    for _ in 0..WORKERS {
+        let pid = fork();
+        if pid == 0 {
+            close(fds[0]);
+            let start = now_ms();
+            let checksum = multiply();
+            let elapsed = (now_ms() - start) as u64;
+            let mut record = [0u8; 16];
+            record[..8].copy_from_slice(&elapsed.to_le_bytes());
+            record[8..].copy_from_slice(&checksum.to_le_bytes());
+            write(fds[1], &record);
+            exit(0);
+        }
+        pids.push(pid);
+    }
+    close(fds[1]);
+    let mut times = Vec::new();
+    let mut checksums = Vec::new();
+    let mut record = [0u8; 16];
+    // 每条记录小于管道缓冲区，一次写入不会与其他记录交错
+    while read(fds[0], &mut record) == 16 {
+        times.push(u64::from_le_bytes(record[..8].try_into().unwrap()) as usize);
+        checksums.push(u64::from_le_bytes(record[8..].try_into().unwrap()));
+    }
+    close(fds[0]);
+    let mut ok = true;
+    for pid in pids {
+        let mut status = 0;
+        waitpid(pid as usize, &mut status);
+        ok &= wifexited(status) && wexitstatus(status) == 0;
+    }
+    ok &= times.len() == WORKERS && checksums.windows(2).all(|w| w[0] == w[1]);
+    if let (Some(min), Some(max)) = (times.iter().min(), times.iter().max()) {
+        println!("matrix: worker times {:?} ms", times);
+        // 用时太短时比值没有意义
+        ok &= *max <= (*min).max(10) * MAX_RATIO;
+    }
+    println!("matrix: {}", if ok { "ok" } else { "FAILED" });
+    !ok as i32
+}
@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+//! 管道吞吐量：子进程写入 `TOTAL` 字节，父进程读出并校验内容，报告每秒传输的 KiB 数
+
+extern crate alloc;
+extern crate user;
+
+use alloc::vec;
+use user::{
+    clock_gettime, close, exit, fork, pipe, println, read, waitpid, wexitstatus, wifexited, write,
+    TimeSpec, CLOCK_MONOTONIC,
+};
+
+const TOTAL: usize = 16 * 1024 * 1024;
+const CHUNK: usize = 4096;
+
+/// 数据流中第 `pos` 个字节，周期与块大小互质，错位时能被发现
+fn pattern(pos: usize) -> u8 {
+    (pos % 251) as u8
+}
+
+fn now_us() -> usize {
+    let mut ts = TimeSpec::default();
+    clock_gettime(CLOCK_MONOTONIC, &mut ts);
+    ts.tv_sec * 1_000_000 + ts.tv_nsec / 1_000
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) < 0 {
+        println!("pipe_bench: pipe failed");
+        return 1;
+    }
+    let mut buf = vec![0u8; CHUNK];
+    let pid = fork();
+    if pid == 0 {
+        close(fds[0]);
+        let mut pos = 0;
+        while pos < TOTAL {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = pattern(pos + i);
+            }
+            let mut sent = 0;
+            while sent < CHUNK {
+                let n = write(fds[1], &buf[sent..]);
+                if n <= 0 {
+                    exit(1);
+                }
+                sent += n as usize;
+            }
+            pos += CHUNK;
+        }
+        close(fds[1]);
+        exit(0);
+    }
+    close(fds[1]);
+    let start = now_us();
+    let mut pos = 0;
+    let mut ok = true;
+    loop {
+        let n = read(fds[0], &mut buf);
+        if n <= 0 {
+            break;
+        }
+        let n = n as usize;
+        ok &= buf[..n]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == pattern(pos + i));
+        pos += n;
+    }
+    let elapsed = (now_us() - start).max(1);
+    close(fds[0]);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    ok &= pos == TOTAL && wifexited(status) && wexitstatus(status) == 0;
+    println!(
+        "pipe_bench: {} bytes in {} us, {} KiB/s{}",
+        pos,
+        elapsed,
+        pos as u64 * 1_000_000 / 1024 / elapsed as u64,
+        if ok { "" } else { ", data mismatch" }
+    );
+    !ok as i32
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+//! 信号风暴：向忙碌的子进程连续发送大量信号，检查作业控制与终止信号的效果
+//!
+//! - 反复 `SIGSTOP` / `SIGCONT` 之后，计算中的子进程照常完成并以 0 退出
+//! - 连续的 `SIGCONT` 不影响运行，随后的 `SIGKILL` 使子进程被信号终止
+
+extern crate user;
+
+use user::{
+    exit, fork, kill, println, waitpid, wexitstatus, wifexited, wifsignaled, wtermsig, yield_,
+    SIGCONT, SIGKILL, SIGSTOP,
+};
+
+const STORM: usize = 200;
+
+/// 做一段计算后让出处理器，返回值防止计算被优化掉
+fn busy(rounds: usize) -> usize {
+    let mut acc = 0usize;
+    for i in 0..rounds {
+        for j in 0..1000 {
+            acc = acc.wrapping_mul(31).wrapping_add(i ^ j);
+        }
+        yield_();
+    }
+    acc
+}
+
+/// 停止与继续交替，子进程最终正常退出
+fn stop_cont() -> bool {
+    let pid = fork();
+    if pid == 0 {
+        let acc = busy(STORM);
+        exit((acc == usize::MAX) as i32);
+    }
+    for _ in 0..STORM {
+        if kill(pid, SIGSTOP) < 0 || kill(pid, SIGCONT) < 0 {
+            return false;
+        }
+        yield_();
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    wifexited(status) && wexitstatus(status) == 0
+}
+
+/// 大量 `SIGCONT` 之后以 `SIGKILL` 结束一个不会退出的子进程
+fn cont_then_kill() -> bool {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            busy(1);
+        }
+    }
+    for _ in 0..STORM {
+        kill(pid, SIGCONT);
+    }
+    kill(pid, SIGKILL);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    wifsignaled(status) && wtermsig(status) == SIGKILL
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut failed = 0;
+    for (name, test) in [
+        ("stop/cont", stop_cont as fn() -> bool),
+        ("cont/kill", cont_then_kill),
+    ] {
+        let ok = test();
+        println!("sigstorm: {} {}", name, if ok { "ok" } else { "FAILED" });
+        failed += !ok as i32;
+    }
+    failed
+}
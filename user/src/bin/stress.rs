@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+//! 依次运行调度与内存相关的压力测试，输出每项的结果，返回失败的项数
+//!
+//! 在 autorun.txt 中加入 `stress` 即可由 initproc 运行
+
+extern crate alloc;
+extern crate user;
+
+use alloc::format;
+use user::{exec, exit, fork, println, waitpid, wexitstatus, wifexited};
+
+const TESTS: &[&str] = &["forkbomb", "fork_isolation", "pipe_bench", "matrix", "sigstorm"];
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut failed = 0;
+    for test in TESTS {
+        let path = format!("{}\0", test);
+        let args = [path.as_ptr(), core::ptr::null()];
+        let pid = fork();
+        if pid == 0 {
+            exec(&path, &args);
+            println!("stress: cannot exec {}", test);
+            exit(127);
+        }
+        let mut status = 0;
+        waitpid(pid as usize, &mut status);
+        let ok = wifexited(status) && wexitstatus(status) == 0;
+        println!("[stress] {} {}", test, if ok { "PASS" } else { "FAIL" });
+        failed += !ok as i32;
+    }
+    println!("[stress] {} of {} failed", failed, TESTS.len());
+    failed
+}
@@ -8,14 +8,9 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
-use user::console::getchar;
+use user::console::getline;
 use user::{close, dup, exec, fork, open, pipe, println, print, waitpid, OpenFlags};
 
-const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
-
 /// 存储单个进程的参数和重定向信息
 struct Command {
     args_copy: Vec<String>, // 拥有所有权，防止悬垂指针
@@ -85,35 +80,17 @@ fn handle_file_redirection(input: &str, output: &str) {
 #[no_mangle]
 fn main() -> i32{
     println!("Rust Shell Initialized.");
-    let mut line = String::new();
 
     loop {
         print!(">> ");
-        line.clear();
-
-        loop {
-            let c = getchar();
-            match c {
-               CR | LF => {
-                   println!(" ");
-                   break;
-               }
-                BS | DL => {
-                    if !line.is_empty() {
-                        print!("{}", BS as char);
-                        print!(" ");
-                        print!("{}", BS as char);
-                        line.pop();
-                    }
-                }
-                _ => {
-                    print!("{}", c as char);
-                    line.push(c as char);
-                }
-            }
-        }
 
-        if line.is_empty() { continue; }
+        // 终端处于规范模式，回显与退格由内核的行规程处理
+        let Some(line) = getline() else {
+            println!("");
+            continue;
+        };
+
+        if line.trim().is_empty() { continue; }
 
         // 解析管道命令
         let cmd_parts: Vec<&str> = line.split('|').collect();
@@ -4,6 +4,8 @@ const STDIN: usize = 0;
 const STDOUT: usize = 1;
 
 use super::{read, write};
+use alloc::string::String;
+use alloc::vec::Vec;
 
 struct Stdout;
 
@@ -37,3 +39,24 @@ pub fn getchar() -> u8 {
     read(STDIN, &mut c);
     c[0]
 }
+
+/// 在规范模式下读入一行（不含行尾的 `\n`），由终端负责回显与行内编辑；读到 EOF 时返回 `None`
+pub fn getline() -> Option<String> {
+    let mut line = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let n = read(STDIN, &mut buf);
+        if n <= 0 {
+            if line.is_empty() {
+                return None;
+            }
+            break;
+        }
+        line.extend_from_slice(&buf[..n as usize]);
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            break;
+        }
+    }
+    Some(String::from_utf8_lossy(&line).into_owned())
+}
@@ -0,0 +1,48 @@
+//! 用户堆
+//!
+//! 在 brk 之上增长的伙伴系统分配器：分配失败时以 brk 扩展数据段，把新增的区域加入分配器后重试；
+//! 释放的内存留在分配器中，不归还给内核
+
+use crate::syscall::sys_brk;
+use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{null_mut, NonNull};
+
+const PAGE_SIZE: usize = 4096;
+/// 每次扩展堆的最小字节数
+const HEAP_GROW: usize = 64 * 1024;
+
+pub struct BrkHeap(LockedHeap);
+
+impl BrkHeap {
+    pub const fn empty() -> Self {
+        Self(LockedHeap::empty())
+    }
+}
+
+/// 为 `layout` 扩展的字节数：伙伴系统只分配按大小对齐的块，扩展两倍块大小保证能容纳一个对齐的块
+fn grow_size(layout: &Layout) -> usize {
+    let block = layout.size().max(layout.align()).next_power_of_two();
+    (2 * block).max(HEAP_GROW).next_multiple_of(PAGE_SIZE)
+}
+
+unsafe impl GlobalAlloc for BrkHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        loop {
+            if let Ok(ptr) = heap.alloc(layout) {
+                return ptr.as_ptr();
+            }
+            let start = sys_brk(0) as usize;
+            let end = start + grow_size(&layout);
+            if (sys_brk(end) as usize) < end {
+                return null_mut();
+            }
+            heap.add_to_heap(start, end);
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
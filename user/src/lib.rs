@@ -5,6 +5,8 @@
 
 #[macro_use]
 pub mod console;
+mod args;
+mod heap;
 mod lang_items;
 mod syscall;
 
@@ -12,45 +14,33 @@ extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
-use alloc::vec::Vec;
-use buddy_system_allocator::LockedHeap;
-use core::ptr::addr_of_mut;
+pub use args::{getargs, Args, AT_NULL, AT_PAGESZ, AT_RANDOM};
+use core::arch::global_asm;
+use heap::BrkHeap;
 use syscall::*;
 
-const USER_HEAP_SIZE: usize = 32768;
-
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
-
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: BrkHeap = BrkHeap::empty();
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout);
 }
 
+// 入口处 sp 指向内核构造的初始栈（argc、argv、envp、auxv），交给 `__user_start` 解析
+global_asm!(
+    ".section .text.entry, \"ax\"",
+    ".globl _start",
+    "_start:",
+    "    mv a0, sp",
+    "    call __user_start",
+);
+
 #[no_mangle]
-#[link_section = ".text.entry"]
-pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
-    unsafe {
-        HEAP.lock()
-            .init(addr_of_mut!(HEAP_SPACE) as usize, USER_HEAP_SIZE);
-    }
-    let mut v: Vec<&'static str> = Vec::new();
-    for i in 0..argc {
-        let str_start =
-            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
-        let len = (0usize..)
-            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
-            .unwrap();
-        v.push(
-            core::str::from_utf8(unsafe {
-                core::slice::from_raw_parts(str_start as *const u8, len)
-            })
-                .unwrap(),
-        );
-    }
-    exit(main(argc, v.as_slice()));
+extern "C" fn __user_start(sp: usize) -> ! {
+    args::set_init_sp(sp);
+    let args = getargs();
+    exit(main(args.argv.len(), args.argv.as_slice()));
 }
 
 #[linkage = "weak"]
@@ -70,22 +60,43 @@ bitflags! {
         // 创建
         const CREATE = 1 << 6;
         // 截断（若存在则以可写方式打开，但是长度清空为0）
-        const TRUNC = 1 << 10;
+        const TRUNC = 1 << 9;
+        // 追加
+        const APPEND = 1 << 10;
         const DIRECTORY = 1 << 17; // 目录（O_DIRECTORY = 0x0200000）
     }
 }
 
+/// 相对于当前目录解析路径
+pub const AT_FDCWD: isize = -100;
+/// unlinkat 删除目录
+pub const AT_REMOVEDIR: u32 = 0x200;
+
 pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
+/// 在 `dirfd` 之下打开 `path`（以 `\0` 结尾），创建文件时权限为 `mode`
+pub fn openat(dirfd: isize, path: &str, flags: OpenFlags, mode: u32) -> isize {
+    sys_openat(dirfd, path, flags.bits, mode)
+}
 pub fn open(path: &str, flags: OpenFlags) -> isize {
-    sys_open(path, flags.bits)
+    openat(AT_FDCWD, path, flags, 0o666)
 }
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
+/// 创建管道，`pipe_fd[0]` 为读端，`pipe_fd[1]` 为写端
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+    let mut fds = [0i32; 2];
+    let ret = sys_pipe2(&mut fds, 0);
+    if ret == 0 {
+        pipe_fd[0] = fds[0] as usize;
+        pipe_fd[1] = fds[1] as usize;
+    }
+    ret
+}
+pub fn pipe2(pipe_fd: &mut [i32; 2], flags: u32) -> isize {
+    sys_pipe2(pipe_fd, flags)
 }
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
@@ -96,21 +107,36 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
+/// 结束进程中的所有线程
+pub fn exit_group(exit_code: i32) -> ! {
+    sys_exit_group(exit_code);
+}
 pub fn yield_() -> isize {
     sys_yield()
 }
 pub fn getpid() -> isize {
     sys_getpid()
 }
+pub fn getppid() -> isize {
+    sys_getppid()
+}
 pub fn fork() -> isize {
-    sys_fork()
+    sys_clone(0, 0, core::ptr::null_mut(), 0, core::ptr::null_mut())
+}
+/// clone：`flags` 的低 8 位为子进程退出时发给父进程的信号，`stack` 为 0 时与父进程共用栈指针
+pub fn clone(flags: usize, stack: usize, ptid: *mut u32, tls: usize, ctid: *mut u32) -> isize {
+    sys_clone(flags, stack, ptid, tls, ctid)
+}
+/// `args` 与 `envs` 都以空指针结尾，其中的字符串以 `\0` 结尾
+pub fn execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_execve(path, args, envs)
 }
 pub fn exec(path: &str, args: &[*const u8]) -> isize {
-    sys_exec(path, args)
+    execve(path, args, &[core::ptr::null()])
 }
 pub fn wait(exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
+        match sys_wait4(-1, exit_code as *mut _, 0) {
             -2 => {
                 yield_();
             }
@@ -122,7 +148,7 @@ pub fn wait(exit_code: &mut i32) -> isize {
 
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
+        match sys_wait4(pid as isize, exit_code as *mut _, 0) {
             -2 => {
                 yield_();
             }
@@ -132,6 +158,21 @@ pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     }
 }
 
+/// wait 得到的状态与 Linux 相同：正常退出时低 7 位为 0，退出码在第 8 到 15 位；被信号终止时低 7 位为信号
+pub fn wifexited(status: i32) -> bool {
+    status & 0x7f == 0
+}
+pub fn wexitstatus(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+pub fn wifsignaled(status: i32) -> bool {
+    let sig = status & 0x7f;
+    sig != 0 && sig != 0x7f
+}
+pub fn wtermsig(status: i32) -> i32 {
+    status & 0x7f
+}
+
 pub fn getcwd(buf: &mut [u8]) -> isize {
     sys_getcwd(buf)
 }
@@ -148,8 +189,17 @@ pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
 
-pub fn mmap(start:usize,len:usize,prot:usize,flags:usize,fd:isize,off:usize) -> isize {
-    sys_mmap(start,len,prot,flags,fd as usize,off)
+pub const PROT_READ: usize = 1;
+pub const PROT_WRITE: usize = 2;
+pub const PROT_EXEC: usize = 4;
+pub const MAP_SHARED: usize = 0x01;
+pub const MAP_PRIVATE: usize = 0x02;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+/// 匿名映射时 `fd` 为 -1
+pub fn mmap(start: usize, len: usize, prot: usize, flags: usize, fd: isize, off: usize) -> isize {
+    sys_mmap(start, len, prot, flags, fd, off)
 }
 
 pub fn fstat(fd:usize,statbuff:*mut u8) -> isize {
@@ -157,7 +207,12 @@ pub fn fstat(fd:usize,statbuff:*mut u8) -> isize {
 }
 
 pub fn mkdir(dirfd:isize,path: *const u8,mode:u8) -> isize {
-    sys_mkdirat(dirfd,path,mode)
+    sys_mkdirat(dirfd, path, mode as u32)
+}
+
+/// 删除文件，`path` 以 `\0` 结尾
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
 }
 
 pub fn dup3(old:isize, new:isize, flags:usize) -> isize {
@@ -165,23 +220,118 @@ pub fn dup3(old:isize, new:isize, flags:usize) -> isize {
 }
 
 pub fn getdents(fd:usize, buf:*mut u8, len:usize) -> isize {
-    sys_getdents(fd,buf, len)
+    sys_getdents64(fd, buf, len)
+}
+
+/// 内核的 `struct termios`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; 19],
 }
 
-#[repr(C, align(16))]
-#[derive(Debug, Clone, Copy)]
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TCSETSF: usize = 0x5404;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_ioctl(fd, cmd, arg)
+}
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    sys_ioctl(fd, TCGETS, termios as *mut _ as usize)
+}
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    sys_ioctl(fd, TCSETS, termios as *const _ as usize)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    pub tv_sec: usize,
+    pub tv_nsec: usize,
+}
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// 睡眠 `req`，被信号打断时把剩余时间写入 `rem`
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    let rem = rem.map_or(core::ptr::null_mut(), |rem| rem as *mut TimeSpec as *mut u8);
+    sys_nanosleep(req as *const TimeSpec as *const u8, rem)
+}
+
+pub fn sleep_ms(ms: usize) -> isize {
+    let req = TimeSpec {
+        tv_sec: ms / 1000,
+        tv_nsec: ms % 1000 * 1_000_000,
+    };
+    nanosleep(&req, None)
+}
+
+pub fn clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, tp as *mut TimeSpec as *mut u8)
+}
+
+pub const STRACE_OFF: usize = 0;
+pub const STRACE_ON: usize = 1;
+pub const STRACE_READ: usize = 2;
+
+/// 开始 / 停止跟踪 `pid` 的系统调用（0 表示所有进程）
+pub fn strace(pid: usize, on: bool) -> isize {
+    sys_strace(if on { STRACE_ON } else { STRACE_OFF }, pid, &mut [])
+}
+
+/// 取走内核中已记录的系统调用跟踪内容
+pub fn strace_read(buf: &mut [u8]) -> isize {
+    sys_strace(STRACE_READ, 0, buf)
+}
+
+/// 与 riscv64 上的 `struct sigaction` 布局相同（没有 sa_restorer），`mask` 中第 `signum - 1` 位对应信号 `signum`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct SignalAction {
     pub handler: usize,
-    pub mask: SignalFlags,
+    pub flags: usize,
+    pub mask: u64,
 }
 
-impl Default for SignalAction {
-    fn default() -> Self {
-        Self {
-            handler: 0,
-            mask: SignalFlags::empty(),
-        }
-    }
+/// 默认动作与忽略信号
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+/// sigprocmask 的 `how`
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// 信号 `signum` 在信号集中对应的位
+pub fn sigmask(signum: i32) -> u64 {
+    1 << (signum - 1)
+}
+
+/// 设置信号 `signum` 的处理方式，`old_action` 不为空时写回原先的设置
+pub fn sigaction(
+    signum: i32,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> isize {
+    let action = action.map_or(core::ptr::null(), |action| action as *const SignalAction);
+    let old_action = old_action.map_or(core::ptr::null_mut(), |old| old as *mut SignalAction);
+    sys_sigaction(signum, action as *const u8, old_action as *mut u8)
+}
+
+pub fn sigprocmask(how: usize, set: Option<&u64>, old_set: Option<&mut u64>) -> isize {
+    let set = set.map_or(core::ptr::null(), |set| set as *const u64);
+    let old_set = old_set.map_or(core::ptr::null_mut(), |old| old as *mut u64);
+    sys_sigprocmask(how, set, old_set)
 }
 
 pub const SIGDEF: i32 = 0; // Default signal handling
@@ -254,6 +404,7 @@ bitflags! {
     }
 }
 
-pub fn kill(pid: usize, signum: i32) -> isize {
+/// `pid` 为 0 时发给本进程组，为 -1 时发给所有有权限的进程，小于 -1 时发给进程组 `-pid`
+pub fn kill(pid: isize, signum: i32) -> isize {
     sys_kill(pid, signum)
 }
\ No newline at end of file
@@ -1,27 +1,41 @@
+//! 系统调用的原始封装
+//!
+//! 调用号与参数顺序和内核（Linux riscv64 的通用调用号）一致，
+//! 路径参数需要调用者以 `\0` 结尾，返回值为负数时表示失败
+
 use core::arch::asm;
 
+const SYSCALL_GETCWD: usize = 17;
 const SYSCALL_DUP: usize = 23;
 const SYSCALL_DUP3: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_MKDIRAT: usize = 34;
-const SYSCALL_OPEN: usize = 56;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_OPENAT: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
-const SYSCALL_PIPE: usize = 59;
-const SYSCALL_GETDENTS: usize = 61;
+const SYSCALL_PIPE2: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_EXIT_GROUP: usize = 94;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETPPID: usize = 173;
 const SYSCALL_BRK: usize = 214;
 const SYSCALL_MUNMAP: usize = 215;
-const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
+const SYSCALL_CLONE: usize = 220;
+const SYSCALL_EXECVE: usize = 221;
 const SYSCALL_MMAP: usize = 222;
-const SYSCALL_WAITPID: usize = 260;
-const SYSCALL_GETCWD: usize = 17;
-const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_WAIT4: usize = 260;
+const SYSCALL_STRACE: usize = 1000;
 
 fn syscall(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
@@ -40,20 +54,29 @@ fn syscall(id: usize, args: [usize; 6]) -> isize {
     ret
 }
 
-// pub fn sys_dup(fd: usize) -> isize {
-//     syscall(SYSCALL_DUP, [fd, 0, 0, 0, 0, 0])
-// }
-
-pub fn sys_open(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0, 0, 0, 0])
+pub fn sys_openat(dirfd: isize, path: &str, flags: u32, mode: u32) -> isize {
+    syscall(
+        SYSCALL_OPENAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            flags as usize,
+            mode as usize,
+            0,
+            0,
+        ],
+    )
 }
 
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
-    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0, 0, 0, 0])
+pub fn sys_pipe2(pipe: &mut [i32; 2], flags: u32) -> isize {
+    syscall(
+        SYSCALL_PIPE2,
+        [pipe.as_mut_ptr() as usize, flags as usize, 0, 0, 0, 0],
+    )
 }
 
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
@@ -64,7 +87,10 @@ pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
 }
 
 pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
-    syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len(), 0, 0, 0])
+    syscall(
+        SYSCALL_WRITE,
+        [fd, buffer.as_ptr() as usize, buffer.len(), 0, 0, 0],
+    )
 }
 
 pub fn sys_exit(exit_code: i32) -> ! {
@@ -72,69 +98,161 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT_GROUP, [exit_code as usize, 0, 0, 0, 0, 0]);
+    panic!("sys_exit_group never returns!");
+}
+
+pub fn sys_nanosleep(req: *const u8, rem: *mut u8) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as usize, rem as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut u8) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, tp as usize, 0, 0, 0, 0])
+}
+
 pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_kill(pid: usize, signal: i32) -> isize {
-    syscall(SYSCALL_KILL, [pid, signal as usize, 0, 0, 0, 0])
+pub fn sys_kill(pid: isize, signal: i32) -> isize {
+    syscall(SYSCALL_KILL, [pid as usize, signal as usize, 0, 0, 0, 0])
+}
+
+/// rt_sigaction，信号集固定为 8 字节
+pub fn sys_sigaction(signum: i32, action: *const u8, old_action: *mut u8) -> isize {
+    syscall(
+        SYSCALL_SIGACTION,
+        [
+            signum as usize,
+            action as usize,
+            old_action as usize,
+            8,
+            0,
+            0,
+        ],
+    )
+}
+
+/// rt_sigprocmask，信号集固定为 8 字节
+pub fn sys_sigprocmask(how: usize, set: *const u64, old_set: *mut u64) -> isize {
+    syscall(
+        SYSCALL_SIGPROCMASK,
+        [how, set as usize, old_set as usize, 8, 0, 0],
+    )
 }
 
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_fork() -> isize {
-    syscall(SYSCALL_FORK, [0, 0, 0, 0, 0, 0])
+pub fn sys_getppid() -> isize {
+    syscall(SYSCALL_GETPPID, [0, 0, 0, 0, 0, 0])
+}
+
+/// 参数顺序与 riscv64 上的 clone 相同：flags, stack, ptid, tls, ctid
+pub fn sys_clone(flags: usize, stack: usize, ptid: *mut u32, tls: usize, ctid: *mut u32) -> isize {
+    syscall(
+        SYSCALL_CLONE,
+        [flags, stack, ptid as usize, tls, ctid as usize, 0],
+    )
 }
 
-pub fn sys_mmap(start:usize,len:usize,prot:usize,flags:usize,fd:usize,off:usize) -> isize {
-    syscall(SYSCALL_MMAP, [start, len, prot, flags , fd, off])
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: isize,
+    off: usize,
+) -> isize {
+    syscall(SYSCALL_MMAP, [start, len, prot, flags, fd as usize, off])
 }
 
-pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
+pub fn sys_execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
     syscall(
-        SYSCALL_EXEC,
-        [path.as_ptr() as usize, args.as_ptr() as usize, 0, 0, 0, 0],
+        SYSCALL_EXECVE,
+        [
+            path.as_ptr() as usize,
+            args.as_ptr() as usize,
+            envs.as_ptr() as usize,
+            0,
+            0,
+            0,
+        ],
     )
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0, 0, 0, 0])
+pub fn sys_wait4(pid: isize, status: *mut i32, options: usize) -> isize {
+    syscall(
+        SYSCALL_WAIT4,
+        [pid as usize, status as usize, options, 0, 0, 0],
+    )
 }
 
 pub fn sys_getcwd(buf: &mut [u8]) -> isize {
-    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0, 0])
+    syscall(
+        SYSCALL_GETCWD,
+        [buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0, 0],
+    )
 }
 
 pub fn sys_chdir(path: &str) -> isize {
     syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_brk(addr:usize) -> isize {
+pub fn sys_brk(addr: usize) -> isize {
     syscall(SYSCALL_BRK, [addr, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_munmap(start:usize, len:usize) -> isize {
+pub fn sys_munmap(start: usize, len: usize) -> isize {
     syscall(SYSCALL_MUNMAP, [start, len, 0, 0, 0, 0])
 }
 
 pub fn sys_fstat(fd: usize, statbuff: *mut u8) -> isize {
-    syscall(SYSCALL_FSTAT, [fd, statbuff as usize,  0, 0, 0, 0])
+    syscall(SYSCALL_FSTAT, [fd, statbuff as usize, 0, 0, 0, 0])
 }
 
-pub fn sys_mkdirat(dirfd: isize,path: *const u8,mode: u8) -> isize {
-    syscall(SYSCALL_MKDIRAT, [dirfd as usize, path as usize, mode as usize, 0, 0, 0])
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, mode: u32) -> isize {
+    syscall(
+        SYSCALL_MKDIRAT,
+        [dirfd as usize, path as usize, mode as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            flags as usize,
+            0,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, cmd, arg, 0, 0, 0])
 }
 
 pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_dup3(old:isize, new:isize, flags:usize) -> isize {
+pub fn sys_dup3(old: isize, new: isize, flags: usize) -> isize {
     syscall(SYSCALL_DUP3, [old as usize, new as usize, flags, 0, 0, 0])
 }
 
-pub fn sys_getdents(fd:usize, buf:*mut u8, len:usize) -> isize {
-    syscall(SYSCALL_GETDENTS,[fd, buf as usize, len, 0, 0, 0])
-}
\ No newline at end of file
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
+    syscall(SYSCALL_GETDENTS64, [fd, buf as usize, len, 0, 0, 0])
+}
+
+pub fn sys_strace(op: usize, pid: usize, buf: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_STRACE,
+        [op, pid, buf.as_mut_ptr() as usize, buf.len(), 0, 0],
+    )
+}